@@ -0,0 +1,95 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rquest::bench_support::{Bench, PROFILES};
+use rquest::test_server::BODY;
+use rquest::DecompressionBackend;
+
+/// A new connection and TLS handshake for each request.
+fn handshake(c: &mut Criterion) {
+    let bench = Bench::new();
+    let url = bench.server().tls_url("/status/200");
+    let mut group = c.benchmark_group("handshake");
+    for &profile in PROFILES {
+        let client = bench.unpooled_client(Some(profile));
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", profile)), |b| {
+            b.iter(|| bench.block_on(async { client.get(&url).send().await.unwrap() }))
+        });
+    }
+    group.finish();
+}
+
+/// A request on a connection kept in the pool.
+fn pooled_request(c: &mut Criterion) {
+    let bench = Bench::new();
+    let client = bench.client(None);
+    let mut group = c.benchmark_group("pooled_request");
+    for (name, url) in [
+        ("http", bench.server().url("/status/200")),
+        ("https", bench.server().tls_url("/status/200")),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| bench.block_on(async { client.get(&url).send().await.unwrap() }))
+        });
+    }
+    group.finish();
+}
+
+/// Receiving and decoding a compressed body, with each backend of gzip and
+/// deflate enabled.
+fn decompression(c: &mut Criterion) {
+    let bench = Bench::new();
+    let mut group = c.benchmark_group("decompression");
+    group.throughput(Throughput::Bytes(BODY.len() as u64));
+    for (backend, name) in BACKENDS {
+        let client = bench.decompressing_client(*backend);
+        for encoding in ["gzip", "brotli", "zstd", "deflate"] {
+            if *backend != DecompressionBackend::Streaming
+                && !["gzip", "deflate"].contains(&encoding)
+            {
+                continue;
+            }
+            let url = bench.server().url(&format!("/{}", encoding));
+            group.bench_function(BenchmarkId::new(encoding, name), |b| {
+                b.iter(|| {
+                    bench.block_on(async {
+                        let res = client.get(&url).send().await.unwrap();
+                        res.bytes().await.unwrap()
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// The decompression backends enabled, named after the feature providing
+/// them.
+const BACKENDS: &[(DecompressionBackend, &str)] = &[
+    #[cfg(not(feature = "zlib-ng"))]
+    (DecompressionBackend::Streaming, "miniz_oxide"),
+    #[cfg(feature = "zlib-ng")]
+    (DecompressionBackend::Streaming, "zlib-ng"),
+    #[cfg(feature = "libdeflate")]
+    (DecompressionBackend::Libdeflate, "libdeflate"),
+];
+
+/// Building the client and the requests of each profile.
+fn headers(c: &mut Criterion) {
+    let bench = Bench::new();
+    let url = bench.server().url("/headers");
+    let mut group = c.benchmark_group("headers");
+    for &profile in PROFILES {
+        let name = format!("{:?}", profile);
+        group.bench_function(BenchmarkId::new("client", &name), |b| {
+            b.iter(|| bench.client(Some(profile)))
+        });
+
+        let client = bench.client(Some(profile));
+        group.bench_function(BenchmarkId::new("request", &name), |b| {
+            b.iter(|| client.get(&url).header("x-bench", "1").build().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, handshake, pooled_request, decompression, headers);
+criterion_main!(benches);
@@ -0,0 +1,330 @@
+//! `Alt-Svc` (RFC 7838) support.
+//!
+//! Alternative services advertised by an origin are cached per origin and
+//! used for subsequent connections. The TLS handshake is still made for the
+//! origin host, so the alternative must present a certificate valid for it.
+//!
+//! Alternatives are reached through the resolver of the client, so that its
+//! checks of the addresses connected to apply to them as well. Alternatives
+//! given as IP addresses would skip it, and are ignored.
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use http::header::{HeaderMap, HeaderName};
+use http::Uri;
+use url::Url;
+
+const ALT_SVC: HeaderName = HeaderName::from_static("alt-svc");
+
+/// Default freshness of an entry when no `ma` parameter is given (24 hours).
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An alternative endpoint advertised by an origin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct AltService {
+    pub(crate) protocol: String,
+    pub(crate) host: Option<String>,
+    pub(crate) port: u16,
+    pub(crate) max_age: Duration,
+}
+
+impl AltService {
+    /// Whether this client can talk to the alternative at all.
+    ///
+    /// `h3` entries are cached but never used until HTTP/3 is supported.
+    /// Nor are alternatives given as IP addresses, which would not go through
+    /// the resolver.
+    pub(crate) fn is_supported(&self) -> bool {
+        let literal = self
+            .host
+            .as_deref()
+            .map_or(false, |host| host.parse::<IpAddr>().is_ok());
+        matches!(self.protocol.as_str(), "h2" | "http/1.1") && !literal
+    }
+
+    /// The URI to open a TCP connection to, falling back to the origin host.
+    pub(crate) fn uri(&self, origin: &Uri) -> Option<Uri> {
+        let host = match self.host {
+            Some(ref host) => host.as_str(),
+            None => origin.host()?,
+        };
+        if host.contains(':') && !host.starts_with('[') {
+            format!("https://[{}]:{}/", host, self.port).parse().ok()
+        } else {
+            format!("https://{}:{}/", host, self.port).parse().ok()
+        }
+    }
+}
+
+struct Entry {
+    services: Vec<AltService>,
+    expires: Instant,
+}
+
+/// Alternative services, keyed by origin `host:port`.
+pub(crate) struct AltSvcCache {
+    origins: Mutex<HashMap<String, Entry>>,
+    /// The hosts resolved to addresses set on the client, which are
+    /// connected to there rather than to their alternatives.
+    overridden: HashSet<String>,
+}
+
+impl AltSvcCache {
+    pub(crate) fn new() -> AltSvcCache {
+        AltSvcCache::with_overrides(HashSet::new())
+    }
+
+    /// A cache ignoring the alternatives of `overridden` hosts.
+    pub(crate) fn with_overrides(overridden: HashSet<String>) -> AltSvcCache {
+        AltSvcCache {
+            origins: Mutex::new(HashMap::new()),
+            overridden,
+        }
+    }
+
+    /// Update the cache from the `Alt-Svc` headers of a response for `url`.
+    ///
+    /// Several field lines are one list of alternatives, as if joined by
+    /// commas.
+    pub(crate) fn observe(&self, url: &Url, headers: &HeaderMap) {
+        if url.scheme() != "https" {
+            return;
+        }
+        let key = match (url.host_str(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => origin_key(host, port),
+            _ => return,
+        };
+
+        let mut seen = false;
+        let mut cleared = false;
+        let mut services = Vec::new();
+        for value in headers.get_all(ALT_SVC) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+            match parse(value) {
+                Some(parsed) => {
+                    seen = true;
+                    cleared |= parsed.is_empty();
+                    services.extend(parsed);
+                }
+                None => log::debug!("invalid alt-svc header for {}: {:?}", key, value),
+            }
+        }
+
+        if !seen {
+            return;
+        }
+        // `clear` can't be listed with alternatives, and wins if it is.
+        if cleared || services.is_empty() {
+            log::trace!("alt-svc cleared for {}", key);
+            self.origins.lock().unwrap().remove(&key);
+            return;
+        }
+        let max_age = services
+            .iter()
+            .map(|s| s.max_age)
+            .min()
+            .unwrap_or(DEFAULT_MAX_AGE);
+        log::trace!("alt-svc for {}: {:?}", key, services);
+        self.origins.lock().unwrap().insert(
+            key,
+            Entry {
+                services,
+                expires: Instant::now() + max_age,
+            },
+        );
+    }
+
+    /// The preferred usable alternative for `origin`, if any.
+    pub(crate) fn lookup(&self, origin: &Uri) -> Option<AltService> {
+        if origin.scheme_str() != Some("https") {
+            return None;
+        }
+        if self.overridden.contains(origin.host()?) {
+            return None;
+        }
+        let key = origin_key(origin.host()?, origin.port_u16().unwrap_or(443));
+        let mut origins = self.origins.lock().unwrap();
+        let entry = origins.get(&key)?;
+        if entry.expires <= Instant::now() {
+            origins.remove(&key);
+            return None;
+        }
+        entry.services.iter().find(|s| s.is_supported()).cloned()
+    }
+
+    /// Forget the alternatives for `origin`, e.g. after a failed connection.
+    pub(crate) fn evict(&self, origin: &Uri) {
+        if let Some(host) = origin.host() {
+            let key = origin_key(host, origin.port_u16().unwrap_or(443));
+            self.origins.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+fn origin_key(host: &str, port: u16) -> String {
+    format!("{}:{}", host.to_ascii_lowercase(), port)
+}
+
+/// Parse an `Alt-Svc` field value.
+///
+/// Returns an empty list for `clear`, and `None` if the value is malformed.
+fn parse(value: &str) -> Option<Vec<AltService>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("clear") {
+        return Some(Vec::new());
+    }
+
+    let mut services = Vec::new();
+    for alternative in split_unquoted(value, ',') {
+        let mut params = split_unquoted(alternative, ';').into_iter();
+        let (protocol, authority) = params.next()?.split_once('=')?;
+        let protocol = percent_encoding::percent_decode_str(protocol.trim())
+            .decode_utf8()
+            .ok()?
+            .into_owned();
+        let authority = unquote(authority.trim())?;
+        let (host, port) = authority.rsplit_once(':')?;
+        let port = port.parse().ok()?;
+        let host = match host {
+            "" => None,
+            host => Some(host.trim_matches(|c| c == '[' || c == ']').to_owned()),
+        };
+
+        let mut max_age = DEFAULT_MAX_AGE;
+        for param in params {
+            if let Some((name, value)) = param.split_once('=') {
+                if name.trim().eq_ignore_ascii_case("ma") {
+                    let secs = unquote(value.trim())?.parse().ok()?;
+                    max_age = Duration::from_secs(secs);
+                }
+            }
+        }
+
+        services.push(AltService {
+            protocol,
+            host,
+            port,
+            max_age,
+        });
+    }
+    Some(services)
+}
+
+fn split_unquoted(value: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                parts.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(value[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+fn unquote(value: &str) -> Option<&str> {
+    match value.strip_prefix('"') {
+        Some(rest) => rest.strip_suffix('"'),
+        None => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    #[test]
+    fn parse_alternatives() {
+        let services = parse(r#"h3=":443"; ma=86400, h2="alt.example.com:8443"; ma=60"#).unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].protocol, "h3");
+        assert_eq!(services[0].host, None);
+        assert_eq!(services[0].port, 443);
+        assert_eq!(services[1].host.as_deref(), Some("alt.example.com"));
+        assert_eq!(services[1].port, 8443);
+        assert_eq!(services[1].max_age, Duration::from_secs(60));
+
+        assert_eq!(parse("clear"), Some(Vec::new()));
+        assert_eq!(parse("h2"), None);
+    }
+
+    #[test]
+    fn cache_skips_unsupported_and_clears() {
+        let cache = AltSvcCache::new();
+        let url = Url::parse("https://example.com/").unwrap();
+        let origin: Uri = "https://example.com/".parse().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ALT_SVC, HeaderValue::from_static(r#"h3=":443""#));
+        cache.observe(&url, &headers);
+        assert_eq!(cache.lookup(&origin), None);
+
+        headers.insert(
+            ALT_SVC,
+            HeaderValue::from_static(r#"h3=":443", h2="alt.example.com:443""#),
+        );
+        cache.observe(&url, &headers);
+        let alt = cache.lookup(&origin).unwrap();
+        assert_eq!(
+            alt.uri(&origin).unwrap(),
+            "https://alt.example.com:443/".parse::<Uri>().unwrap()
+        );
+
+        headers.insert(ALT_SVC, HeaderValue::from_static("clear"));
+        cache.observe(&url, &headers);
+        assert_eq!(cache.lookup(&origin), None);
+    }
+
+    #[test]
+    fn cache_merges_field_lines() {
+        let cache = AltSvcCache::new();
+        let url = Url::parse("https://example.com/").unwrap();
+        let origin: Uri = "https://example.com/".parse().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.append(
+            ALT_SVC,
+            HeaderValue::from_static(r#"h2="alt.example.com:443""#),
+        );
+        headers.append(ALT_SVC, HeaderValue::from_static(r#"h3=":443""#));
+        cache.observe(&url, &headers);
+        let alt = cache.lookup(&origin).unwrap();
+        assert_eq!(alt.host.as_deref(), Some("alt.example.com"));
+    }
+
+    #[test]
+    fn cache_ignores_addresses_and_overridden_hosts() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let origin: Uri = "https://example.com/".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ALT_SVC,
+            HeaderValue::from_static(r#"h2="10.0.0.1:443", h2="[::1]:443""#),
+        );
+        let cache = AltSvcCache::new();
+        cache.observe(&url, &headers);
+        assert_eq!(cache.lookup(&origin), None);
+
+        headers.insert(
+            ALT_SVC,
+            HeaderValue::from_static(r#"h2="alt.example.com:443""#),
+        );
+        let cache = AltSvcCache::with_overrides(HashSet::from(["example.com".to_owned()]));
+        cache.observe(&url, &headers);
+        assert_eq!(cache.lookup(&origin), None);
+    }
+}
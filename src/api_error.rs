@@ -0,0 +1,83 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use bytes::Bytes;
+use http::StatusCode;
+
+/// The error of [`Response::json_or_error`](crate::Response::json_or_error).
+///
+/// An API answering with an error status usually describes the error in a
+/// payload of its own, deserialized into `E`.
+#[derive(Debug)]
+pub enum ApiError<E> {
+    /// The server answered with an error status and the payload `error`.
+    Status {
+        /// The status of the response.
+        status: StatusCode,
+        /// The deserialized error payload.
+        error: E,
+    },
+    /// The body of the response could not be deserialized.
+    Decode {
+        /// The status of the response.
+        status: StatusCode,
+        /// The raw body of the response, for debugging.
+        body: Bytes,
+        /// The decode error.
+        error: crate::Error,
+    },
+    /// The response body could not be read.
+    Request(crate::Error),
+}
+
+impl<E> ApiError<E> {
+    /// Get the status of the response, if one was received.
+    pub fn status(&self) -> Option<StatusCode> {
+        match *self {
+            ApiError::Status { status, .. } | ApiError::Decode { status, .. } => Some(status),
+            ApiError::Request(ref err) => err.status(),
+        }
+    }
+
+    /// Get the raw body of a response that could not be deserialized.
+    pub fn body(&self) -> Option<&Bytes> {
+        match *self {
+            ApiError::Decode { ref body, .. } => Some(body),
+            _ => None,
+        }
+    }
+}
+
+impl<E> From<crate::Error> for ApiError<E> {
+    fn from(err: crate::Error) -> ApiError<E> {
+        ApiError::Request(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for ApiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApiError::Status { status, ref error } => {
+                write!(f, "HTTP status {}: {:?}", status, error)
+            }
+            ApiError::Decode {
+                status, ref body, ..
+            } => write!(
+                f,
+                "error decoding response body ({}, {} bytes)",
+                status,
+                body.len()
+            ),
+            ApiError::Request(ref err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl<E: fmt::Debug> StdError for ApiError<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            ApiError::Status { .. } => None,
+            ApiError::Decode { ref error, .. } | ApiError::Request(ref error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,1224 @@
+//! Streaming extraction of archives.
+//!
+//! An [`Archive`] unpacks a tar, gzipped tar or zip body entry by entry while
+//! it downloads, without buffering the archive: the data of each entry is
+//! read in chunks, and the entries that are not read are skipped. Zip
+//! archives are read by their local headers, so the central directory at
+//! their end is never needed.
+//!
+//! Use [`Response::archive`](crate::Response::archive) to extract the
+//! archive of a response body.
+//!
+//! Paths come from the archive as they are. Use [`Entry::safe_path`] before
+//! writing entries to disk, so that `../` or absolute paths can't escape the
+//! destination.
+//!
+//! This requires the optional `archive` feature to be enabled.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+
+use bytes::Bytes;
+use flate2::{Decompress, FlushDecompress, Status};
+use futures_core::Stream;
+use futures_util::StreamExt;
+
+/// Metadata records, like long tar names, larger than this are an error.
+const MAX_META_LEN: u64 = 1024 * 1024;
+
+/// The most data inflated before it is handed out, so that a small chunk of
+/// a compressed archive isn't expanded all at once in memory.
+const MAX_INFLATE_LEN: usize = 64 * 1024;
+
+const BLOCK: usize = 512;
+
+const ZIP_LOCAL: u32 = 0x0403_4b50;
+const ZIP_DESCRIPTOR: u32 = 0x0807_4b50;
+const ZIP_CENTRAL: u32 = 0x0201_4b50;
+const ZIP_END: u32 = 0x0605_4b50;
+const ZIP64_END: u32 = 0x0606_4b50;
+
+/// The format of an archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// A tar archive.
+    Tar,
+    /// A tar archive compressed with gzip, such as a `.tar.gz` or `.tgz`.
+    TarGz,
+    /// A zip archive, with stored or deflated entries.
+    Zip,
+}
+
+/// The kind of an entry of an archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EntryKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Directory,
+    /// A symbolic or hard link.
+    Link,
+    /// Anything else, such as a device or a FIFO.
+    Other,
+}
+
+/// An archive, unpacked entry by entry from a stream of bytes.
+///
+/// # Example
+///
+/// ```
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use rquest::archive::{EntryKind, Format};
+///
+/// let mut archive = rquest::get("https://example.com/dataset.tar.gz")
+///     .await?
+///     .archive(Format::TarGz);
+///
+/// while let Some(mut entry) = archive.next_entry().await? {
+///     if entry.kind() != EntryKind::File {
+///         continue;
+///     }
+///     println!("{}", entry.path());
+///     while let Some(chunk) = entry.chunk().await? {
+///         // write `chunk` somewhere
+///         # let _ = chunk;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Archive {
+    body: Pin<Box<dyn Stream<Item = crate::Result<Bytes>> + Send>>,
+    unpacker: Unpacker,
+    events: VecDeque<Event>,
+    /// Whether the body has ended.
+    done: bool,
+    /// Whether the unpacker stopped inflating before the end of what it was
+    /// fed, and is to go on before more of the body is read.
+    held: bool,
+    /// Whether the data of the current entry is still to be read.
+    in_entry: bool,
+}
+
+/// An entry of an [`Archive`].
+///
+/// Its data must be read before the next entry, and is skipped if it isn't.
+pub struct Entry<'a> {
+    archive: &'a mut Archive,
+    header: Header,
+}
+
+#[derive(Debug)]
+struct Header {
+    path: String,
+    size: Option<u64>,
+    kind: EntryKind,
+}
+
+#[derive(Debug)]
+enum Event {
+    Entry(Header),
+    Data(Bytes),
+    /// The end of the data of the last entry.
+    End,
+}
+
+enum Unpacker {
+    Tar(Untar),
+    TarGz(Gunzip, Untar),
+    Zip(Unzip),
+}
+
+// ===== impl Archive =====
+
+impl Archive {
+    /// Unpack an archive of `format` from `body`.
+    pub fn new<S>(format: Format, body: S) -> Archive
+    where
+        S: Stream<Item = crate::Result<Bytes>> + Send + 'static,
+    {
+        let unpacker = match format {
+            Format::Tar => Unpacker::Tar(Untar::new()),
+            Format::TarGz => Unpacker::TarGz(Gunzip::new(), Untar::new()),
+            Format::Zip => Unpacker::Zip(Unzip::new()),
+        };
+        Archive {
+            body: Box::pin(body),
+            unpacker,
+            events: VecDeque::new(),
+            done: false,
+            held: false,
+            in_entry: false,
+        }
+    }
+
+    /// Get the next entry, skipping the rest of the data of the current one.
+    ///
+    /// Returns `None` at the end of the archive.
+    pub async fn next_entry(&mut self) -> crate::Result<Option<Entry<'_>>> {
+        loop {
+            match self.next_event().await? {
+                Some(Event::Entry(header)) => {
+                    self.in_entry = true;
+                    return Ok(Some(Entry {
+                        archive: self,
+                        header,
+                    }));
+                }
+                Some(Event::Data(_)) => {}
+                Some(Event::End) => self.in_entry = false,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    async fn next_event(&mut self) -> crate::Result<Option<Event>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            if self.held {
+                self.held = self
+                    .unpacker
+                    .feed(&[], &mut self.events)
+                    .map_err(crate::error::decode_io)?;
+                continue;
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.body.next().await {
+                Some(chunk) => {
+                    self.held = self
+                        .unpacker
+                        .feed(&chunk?, &mut self.events)
+                        .map_err(crate::error::decode_io)?
+                }
+                None => {
+                    self.done = true;
+                    self.unpacker.finish().map_err(crate::error::decode_io)?;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Archive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Archive").field("done", &self.done).finish()
+    }
+}
+
+// ===== impl Entry =====
+
+impl Entry<'_> {
+    /// Get the path of the entry, as it is in the archive.
+    pub fn path(&self) -> &str {
+        &self.header.path
+    }
+
+    /// Get the path of the entry relative to a destination directory.
+    ///
+    /// Returns `None` if the path is absolute or has `..` components, which
+    /// would write outside of the destination.
+    pub fn safe_path(&self) -> Option<PathBuf> {
+        let mut path = PathBuf::new();
+        for component in Path::new(&self.header.path).components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+            }
+        }
+        if path.as_os_str().is_empty() {
+            None
+        } else {
+            Some(path)
+        }
+    }
+
+    /// Get the uncompressed size of the entry, if the archive tells it
+    /// before its data.
+    pub fn size(&self) -> Option<u64> {
+        self.header.size
+    }
+
+    /// Get the kind of the entry.
+    pub fn kind(&self) -> EntryKind {
+        self.header.kind
+    }
+
+    /// Read the next chunk of the data of the entry.
+    ///
+    /// Returns `None` at the end of the data.
+    pub async fn chunk(&mut self) -> crate::Result<Option<Bytes>> {
+        if !self.archive.in_entry {
+            return Ok(None);
+        }
+        loop {
+            match self.archive.next_event().await? {
+                Some(Event::Data(chunk)) if chunk.is_empty() => {}
+                Some(Event::Data(chunk)) => return Ok(Some(chunk)),
+                Some(Event::End) => {
+                    self.archive.in_entry = false;
+                    return Ok(None);
+                }
+                Some(Event::Entry(_)) | None => {
+                    self.archive.in_entry = false;
+                    return Err(crate::error::decode("archive entry ended early"));
+                }
+            }
+        }
+    }
+
+    /// Read the rest of the data of the entry.
+    pub async fn bytes(mut self) -> crate::Result<Bytes> {
+        let mut buf = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(buf.into())
+    }
+}
+
+impl fmt::Debug for Entry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("path", &self.header.path)
+            .field("size", &self.header.size)
+            .field("kind", &self.header.kind)
+            .finish()
+    }
+}
+
+// ===== impl Unpacker =====
+
+impl Unpacker {
+    /// Unpack `chunk` into `events`, returning whether inflating stopped
+    /// at `MAX_INFLATE_LEN` with input left, to be unpacked by feeding an
+    /// empty chunk.
+    fn feed(&mut self, chunk: &[u8], events: &mut VecDeque<Event>) -> io::Result<bool> {
+        match self {
+            Unpacker::Tar(tar) => tar.feed(chunk, events).map(|()| false),
+            Unpacker::TarGz(gz, tar) => {
+                let mut out = Vec::new();
+                let held = gz.feed(chunk, &mut out)?;
+                tar.feed(&out, events)?;
+                Ok(held)
+            }
+            Unpacker::Zip(zip) => zip.feed(chunk, events),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Unpacker::Tar(tar) => tar.finish(),
+            Unpacker::TarGz(gz, tar) => {
+                gz.finish()?;
+                tar.finish()
+            }
+            Unpacker::Zip(zip) => zip.finish(),
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "archive is truncated")
+}
+
+/// Inflate what of `input` belongs to the raw deflate stream of `inflate`
+/// into `out`, until `out` holds `MAX_INFLATE_LEN` bytes, returning how much
+/// was consumed and whether the stream ended.
+fn inflate(inflate: &mut Decompress, input: &[u8], out: &mut Vec<u8>) -> io::Result<(usize, bool)> {
+    let mut consumed = 0;
+    while out.len() < MAX_INFLATE_LEN {
+        out.reserve_exact(MAX_INFLATE_LEN - out.len());
+        let (before_in, before_out) = (inflate.total_in(), inflate.total_out());
+        let status = inflate
+            .decompress_vec(&input[consumed..], out, FlushDecompress::None)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        consumed += (inflate.total_in() - before_in) as usize;
+        let produced = inflate.total_out() - before_out;
+
+        match status {
+            Status::StreamEnd => return Ok((consumed, true)),
+            // Either all of the input is inflated, with room left in the
+            // output, or the stream can't go on without more input.
+            _ if consumed == input.len() && out.len() < out.capacity() => {
+                return Ok((consumed, false))
+            }
+            Status::BufError if produced == 0 => return Ok((consumed, false)),
+            _ => {}
+        }
+    }
+    Ok((consumed, false))
+}
+
+// ===== gzip =====
+
+#[derive(Debug)]
+enum GzState {
+    Header,
+    Body,
+    Trailer(usize),
+    /// Bytes after the last member, like padding, that are ignored.
+    Trailing,
+}
+
+struct Gunzip {
+    buf: Vec<u8>,
+    state: GzState,
+    inflate: Decompress,
+    members: usize,
+    /// Whether inflating stopped at `MAX_INFLATE_LEN`, so that `inflate`
+    /// may hold output even with no input left.
+    held: bool,
+}
+
+impl Gunzip {
+    fn new() -> Gunzip {
+        Gunzip {
+            buf: Vec::new(),
+            state: GzState::Header,
+            inflate: Decompress::new(false),
+            members: 0,
+            held: false,
+        }
+    }
+
+    /// Inflate `chunk` into `out`, returning whether it stopped at
+    /// `MAX_INFLATE_LEN` with input left.
+    fn feed(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> io::Result<bool> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut pos = 0;
+        while pos < self.buf.len() || self.held {
+            let input = &self.buf[pos..];
+            match self.state {
+                GzState::Header => match gzip_header(input) {
+                    Ok(Some(len)) => {
+                        pos += len;
+                        self.inflate.reset(false);
+                        self.state = GzState::Body;
+                    }
+                    Ok(None) => break,
+                    Err(_) if self.members > 0 => self.state = GzState::Trailing,
+                    Err(err) => return Err(err),
+                },
+                GzState::Body => {
+                    let (consumed, ended) = inflate(&mut self.inflate, input, out)?;
+                    pos += consumed;
+                    self.held = !ended && out.len() >= MAX_INFLATE_LEN;
+                    if ended {
+                        self.state = GzState::Trailer(8);
+                    } else if self.held || consumed == 0 {
+                        break;
+                    }
+                }
+                GzState::Trailer(left) => {
+                    let n = left.min(input.len());
+                    pos += n;
+                    if n == left {
+                        self.members += 1;
+                        self.state = GzState::Header;
+                    } else {
+                        self.state = GzState::Trailer(left - n);
+                    }
+                }
+                GzState::Trailing => pos = self.buf.len(),
+            }
+        }
+        self.buf.drain(..pos);
+        Ok(self.held)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match self.state {
+            GzState::Header if self.members > 0 && self.buf.is_empty() => Ok(()),
+            GzState::Trailing => Ok(()),
+            _ => Err(truncated()),
+        }
+    }
+}
+
+/// Parse a gzip member header, returning its length if it is complete.
+fn gzip_header(buf: &[u8]) -> io::Result<Option<usize>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if buf.len() < 10 {
+        if buf.iter().zip([0x1f, 0x8b, 8]).all(|(a, b)| *a == b) {
+            return Ok(None);
+        }
+        return Err(invalid("invalid gzip header"));
+    }
+    if buf[..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid("invalid gzip header"));
+    }
+    let flags = buf[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        match buf.get(pos..pos + 2) {
+            Some(len) => pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize,
+            None => return Ok(None),
+        }
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            match buf
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+            {
+                Some(end) => pos += end + 1,
+                None => return Ok(None),
+            }
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    Ok(if buf.len() >= pos { Some(pos) } else { None })
+}
+
+// ===== tar =====
+
+#[derive(Debug)]
+enum TarState {
+    Header,
+    Data {
+        left: u64,
+        padding: u64,
+    },
+    Meta {
+        typeflag: u8,
+        left: u64,
+        padding: u64,
+    },
+    Padding(u64),
+    End,
+}
+
+struct Untar {
+    buf: Vec<u8>,
+    state: TarState,
+    meta: Vec<u8>,
+    /// The path of the next entry, from a GNU long name or a pax header.
+    long_path: Option<String>,
+    /// The size of the next entry, from a pax header.
+    pax_size: Option<u64>,
+}
+
+impl Untar {
+    fn new() -> Untar {
+        Untar {
+            buf: Vec::new(),
+            state: TarState::Header,
+            meta: Vec::new(),
+            long_path: None,
+            pax_size: None,
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8], events: &mut VecDeque<Event>) -> io::Result<()> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut pos = 0;
+        while pos < self.buf.len() {
+            let input = &self.buf[pos..];
+            match self.state {
+                TarState::Header => {
+                    if input.len() < BLOCK {
+                        break;
+                    }
+                    let mut block = [0; BLOCK];
+                    block.copy_from_slice(&input[..BLOCK]);
+                    pos += BLOCK;
+                    if block.iter().all(|b| *b == 0) {
+                        // Two zero blocks end the archive; either is enough.
+                        self.state = TarState::End;
+                        continue;
+                    }
+                    self.header(&block, events)?;
+                }
+                TarState::Data { left, padding } => {
+                    let n = left.min(input.len() as u64) as usize;
+                    events.push_back(Event::Data(Bytes::copy_from_slice(&input[..n])));
+                    pos += n;
+                    if n as u64 == left {
+                        events.push_back(Event::End);
+                        self.state = TarState::Padding(padding);
+                    } else {
+                        self.state = TarState::Data {
+                            left: left - n as u64,
+                            padding,
+                        };
+                    }
+                }
+                TarState::Meta {
+                    typeflag,
+                    left,
+                    padding,
+                } => {
+                    let n = left.min(input.len() as u64) as usize;
+                    self.meta.extend_from_slice(&input[..n]);
+                    pos += n;
+                    if n as u64 == left {
+                        self.meta(typeflag);
+                        self.state = TarState::Padding(padding);
+                    } else {
+                        self.state = TarState::Meta {
+                            typeflag,
+                            left: left - n as u64,
+                            padding,
+                        };
+                    }
+                }
+                TarState::Padding(left) => {
+                    let n = left.min(input.len() as u64);
+                    pos += n as usize;
+                    self.state = match left - n {
+                        0 => TarState::Header,
+                        left => TarState::Padding(left),
+                    };
+                }
+                TarState::End => pos = self.buf.len(),
+            }
+        }
+        self.buf.drain(..pos);
+
+        // Data that only waits for its padding is complete.
+        if let TarState::Padding(0) = self.state {
+            self.state = TarState::Header;
+        }
+        Ok(())
+    }
+
+    fn header(&mut self, block: &[u8], events: &mut VecDeque<Event>) -> io::Result<()> {
+        let checksum = octal(&block[148..156]).ok_or_else(|| invalid("invalid tar header"))?;
+        let sum = block
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    *b as u64
+                }
+            })
+            .sum::<u64>();
+        if sum != checksum {
+            return Err(invalid("invalid tar header checksum"));
+        }
+
+        let size = tar_size(&block[124..136]).ok_or_else(|| invalid("invalid tar entry size"))?;
+        let typeflag = block[156];
+        let padding = (BLOCK as u64 - size % BLOCK as u64) % BLOCK as u64;
+
+        // A GNU long name, or a pax header, of the next entry.
+        if matches!(typeflag, b'L' | b'x' | b'g') {
+            if size > MAX_META_LEN {
+                return Err(invalid("tar metadata is too large"));
+            }
+            self.meta.clear();
+            self.state = TarState::Meta {
+                typeflag,
+                left: size,
+                padding,
+            };
+            return Ok(());
+        }
+
+        let path = match self.long_path.take() {
+            Some(path) => path,
+            None => {
+                let name = cstr(&block[..100]);
+                // The prefix of POSIX ustar headers; GNU ones use it for
+                // other fields.
+                if &block[257..263] == b"ustar\0" && block[345] != 0 {
+                    format!("{}/{}", cstr(&block[345..500]), name)
+                } else {
+                    name
+                }
+            }
+        };
+        let size = self.pax_size.take().unwrap_or(size);
+        let kind = match typeflag {
+            b'0' | b'\0' | b'7' => EntryKind::File,
+            b'5' => EntryKind::Directory,
+            b'1' | b'2' => EntryKind::Link,
+            _ => EntryKind::Other,
+        };
+        // Links have no data, whatever their size field says.
+        let len = if kind == EntryKind::Link { 0 } else { size };
+        let padding = (BLOCK as u64 - len % BLOCK as u64) % BLOCK as u64;
+
+        events.push_back(Event::Entry(Header {
+            path,
+            size: Some(len),
+            kind,
+        }));
+        if len == 0 {
+            events.push_back(Event::End);
+            self.state = TarState::Header;
+        } else {
+            self.state = TarState::Data { left: len, padding };
+        }
+        Ok(())
+    }
+
+    fn meta(&mut self, typeflag: u8) {
+        match typeflag {
+            b'L' => self.long_path = Some(cstr(&self.meta)),
+            b'x' => {
+                for (key, value) in pax_records(&self.meta) {
+                    match key {
+                        "path" => self.long_path = Some(value.to_owned()),
+                        "size" => self.pax_size = value.parse().ok(),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.meta.clear();
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match self.state {
+            TarState::End => Ok(()),
+            // Some writers leave out the zero blocks at the end.
+            TarState::Header if self.buf.is_empty() => Ok(()),
+            _ => Err(truncated()),
+        }
+    }
+}
+
+/// Read a NUL-terminated field.
+fn cstr(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Read an octal field, padded with spaces or NULs.
+fn octal(field: &[u8]) -> Option<u64> {
+    let digits = std::str::from_utf8(field)
+        .ok()?
+        .trim_matches(|c| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+/// Read a size field, in octal or in the base-256 form of GNU tar.
+fn tar_size(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 == 0 {
+        return octal(field);
+    }
+    let bytes = &field[1..];
+    if bytes[..bytes.len() - 8].iter().any(|b| *b != 0) {
+        return None;
+    }
+    let mut size = [0; 8];
+    size.copy_from_slice(&bytes[bytes.len() - 8..]);
+    Some(u64::from_be_bytes(size))
+}
+
+/// Parse the `"<len> <key>=<value>\n"` records of a pax header.
+fn pax_records(mut data: &[u8]) -> Vec<(&str, &str)> {
+    let mut records = Vec::new();
+    while !data.is_empty() {
+        let space = match data.iter().position(|b| *b == b' ') {
+            Some(space) => space,
+            None => break,
+        };
+        let len = match std::str::from_utf8(&data[..space])
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+        {
+            Some(len) if len > space && len <= data.len() => len,
+            _ => break,
+        };
+        let record = &data[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        if let Ok(record) = std::str::from_utf8(record) {
+            if let Some((key, value)) = record.split_once('=') {
+                records.push((key, value));
+            }
+        }
+        data = &data[len..];
+    }
+    records
+}
+
+// ===== zip =====
+
+enum ZipState {
+    Header,
+    Stored {
+        left: u64,
+        descriptor: Option<bool>,
+    },
+    Deflated {
+        descriptor: Option<bool>,
+    },
+    /// A data descriptor after the data of an entry, with 64-bit sizes or not.
+    Descriptor(bool),
+    End,
+}
+
+struct Unzip {
+    buf: Vec<u8>,
+    state: ZipState,
+    inflate: Decompress,
+    /// Whether inflating stopped at `MAX_INFLATE_LEN` in the data of an
+    /// entry, so that `inflate` may hold output even with no input left.
+    held: bool,
+}
+
+impl Unzip {
+    fn new() -> Unzip {
+        Unzip {
+            buf: Vec::new(),
+            state: ZipState::Header,
+            inflate: Decompress::new(false),
+            held: false,
+        }
+    }
+
+    /// Unpack `chunk` into `events`, returning whether inflating stopped at
+    /// `MAX_INFLATE_LEN` with input left.
+    fn feed(&mut self, chunk: &[u8], events: &mut VecDeque<Event>) -> io::Result<bool> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut pos = 0;
+        let mut inflated = 0;
+        while pos < self.buf.len() || self.held {
+            let input = &self.buf[pos..];
+            match self.state {
+                ZipState::Header => {
+                    if input.len() < 4 {
+                        break;
+                    }
+                    match u32_le(input, 0) {
+                        ZIP_LOCAL => match local_header(input, events)? {
+                            Some((len, state)) => {
+                                if let ZipState::Deflated { .. } = state {
+                                    self.inflate.reset(false);
+                                }
+                                self.state = state;
+                                pos += len;
+                            }
+                            None => break,
+                        },
+                        // The central directory follows the last entry.
+                        ZIP_CENTRAL | ZIP_END | ZIP64_END => self.state = ZipState::End,
+                        _ => return Err(invalid("invalid zip local header")),
+                    }
+                }
+                ZipState::Stored { left, descriptor } => {
+                    let n = left.min(input.len() as u64) as usize;
+                    events.push_back(Event::Data(Bytes::copy_from_slice(&input[..n])));
+                    pos += n;
+                    if n as u64 == left {
+                        events.push_back(Event::End);
+                        self.state = after_data(descriptor);
+                    } else {
+                        self.state = ZipState::Stored {
+                            left: left - n as u64,
+                            descriptor,
+                        };
+                    }
+                }
+                ZipState::Deflated { descriptor } => {
+                    let mut out = Vec::new();
+                    let (consumed, ended) = inflate(&mut self.inflate, input, &mut out)?;
+                    pos += consumed;
+                    inflated += out.len();
+                    events.push_back(Event::Data(out.into()));
+                    self.held = !ended && inflated >= MAX_INFLATE_LEN;
+                    if ended {
+                        events.push_back(Event::End);
+                        self.state = after_data(descriptor);
+                    }
+                    if inflated >= MAX_INFLATE_LEN || (!ended && consumed == 0) {
+                        break;
+                    }
+                }
+                ZipState::Descriptor(zip64) => {
+                    let signed = input.len() >= 4 && u32_le(input, 0) == ZIP_DESCRIPTOR;
+                    let len = if signed { 4 } else { 0 } + if zip64 { 20 } else { 12 };
+                    if input.len() < len.max(4) {
+                        break;
+                    }
+                    pos += len;
+                    self.state = ZipState::Header;
+                }
+                ZipState::End => pos = self.buf.len(),
+            }
+        }
+        self.buf.drain(..pos);
+        Ok(self.held || inflated >= MAX_INFLATE_LEN)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match self.state {
+            ZipState::End => Ok(()),
+            _ => Err(truncated()),
+        }
+    }
+}
+
+/// Parse a local file header, returning its length and the state to read
+/// the data of its entry in, if it is complete.
+fn local_header(
+    input: &[u8],
+    events: &mut VecDeque<Event>,
+) -> io::Result<Option<(usize, ZipState)>> {
+    const ENCRYPTED: u16 = 0x0001;
+    const DESCRIPTOR: u16 = 0x0008;
+
+    if input.len() < 30 {
+        return Ok(None);
+    }
+    let flags = u16_le(input, 6);
+    let method = u16_le(input, 8);
+    let mut compressed = u32_le(input, 18) as u64;
+    let mut size = u32_le(input, 22) as u64;
+    let name_len = u16_le(input, 26) as usize;
+    let extra_len = u16_le(input, 28) as usize;
+    let len = 30 + name_len + extra_len;
+    if input.len() < len {
+        return Ok(None);
+    }
+    let path = String::from_utf8_lossy(&input[30..30 + name_len]).into_owned();
+
+    // The zip64 extra field has the sizes that don't fit the header.
+    let mut zip64 = false;
+    let mut extra = &input[30 + name_len..len];
+    while extra.len() >= 4 {
+        let (id, field_len) = (u16_le(extra, 0), u16_le(extra, 2) as usize);
+        let field = &extra[4..extra.len().min(4 + field_len)];
+        if id == 0x0001 {
+            zip64 = true;
+            let mut values = field.chunks_exact(8).map(|v| u64_le(v, 0));
+            if size == 0xFFFF_FFFF {
+                size = values.next().unwrap_or(size);
+            }
+            if compressed == 0xFFFF_FFFF {
+                compressed = values.next().unwrap_or(compressed);
+            }
+        }
+        extra = &extra[(4 + field_len).min(extra.len())..];
+    }
+
+    if flags & ENCRYPTED != 0 {
+        return Err(invalid("encrypted zip entries are not supported"));
+    }
+    let descriptor = if flags & DESCRIPTOR != 0 {
+        Some(zip64)
+    } else {
+        None
+    };
+    let kind = if path.ends_with('/') {
+        EntryKind::Directory
+    } else {
+        EntryKind::File
+    };
+
+    let state = match method {
+        0 if descriptor.is_some() && compressed == 0 && kind == EntryKind::File => {
+            return Err(invalid(
+                "stored zip entries of unknown size can't be streamed",
+            ));
+        }
+        0 => ZipState::Stored {
+            left: compressed,
+            descriptor,
+        },
+        8 => ZipState::Deflated { descriptor },
+        _ => return Err(invalid("unsupported zip compression method")),
+    };
+    events.push_back(Event::Entry(Header {
+        path,
+        size: if descriptor.is_some() {
+            None
+        } else {
+            Some(size)
+        },
+        kind,
+    }));
+    if let ZipState::Stored {
+        left: 0,
+        descriptor,
+    } = state
+    {
+        events.push_back(Event::End);
+        return Ok(Some((len, after_data(descriptor))));
+    }
+    Ok(Some((len, state)))
+}
+
+fn after_data(descriptor: Option<bool>) -> ZipState {
+    match descriptor {
+        Some(zip64) => ZipState::Descriptor(zip64),
+        None => ZipState::Header,
+    }
+}
+
+fn u16_le(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn u32_le(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn u64_le(buf: &[u8], at: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    fn body(data: Vec<u8>, size: usize) -> impl Stream<Item = crate::Result<Bytes>> {
+        let chunks = data
+            .chunks(size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+        futures_util::stream::iter(chunks)
+    }
+
+    fn tar_header(name: &str, size: usize, typeflag: u8) -> Vec<u8> {
+        let mut block = vec![0; BLOCK];
+        block[..name.len()].copy_from_slice(name.as_bytes());
+        block[100..107].copy_from_slice(b"0000644");
+        block[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        block[156] = typeflag;
+        block[257..263].copy_from_slice(b"ustar\0");
+        block[263..265].copy_from_slice(b"00");
+        block[148..156].copy_from_slice(b"        ");
+        let sum = block.iter().map(|b| *b as u64).sum::<u64>();
+        block[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        block
+    }
+
+    fn tar_entry(tar: &mut Vec<u8>, name: &str, data: &[u8], typeflag: u8) {
+        tar.extend(tar_header(name, data.len(), typeflag));
+        tar.extend_from_slice(data);
+        tar.resize(tar.len() + (BLOCK - data.len() % BLOCK) % BLOCK, 0);
+    }
+
+    fn tar() -> Vec<u8> {
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "data/", b"", b'5');
+        tar_entry(&mut tar, "data/a.csv", b"a,b\n1,2\n", b'0');
+        let long = format!("data/{}.txt", "x".repeat(120));
+        tar_entry(
+            &mut tar,
+            "././@LongLink",
+            format!("{}\0", long).as_bytes(),
+            b'L',
+        );
+        tar_entry(&mut tar, "data/xxx.txt", &vec![7; 1000], b'0');
+        tar.extend(vec![0; BLOCK * 2]);
+        tar
+    }
+
+    /// A zip with a stored entry, and a deflated one with a data descriptor.
+    fn zip() -> Vec<u8> {
+        zip_of(&b"deflated ".repeat(1000))
+    }
+
+    /// A zip with a stored entry, and `data` deflated with a data descriptor.
+    fn zip_of(data: &[u8]) -> Vec<u8> {
+        let mut zip = Vec::new();
+
+        let stored = b"hello";
+        zip.extend(ZIP_LOCAL.to_le_bytes());
+        zip.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        zip.extend((stored.len() as u32).to_le_bytes());
+        zip.extend((stored.len() as u32).to_le_bytes());
+        zip.extend(5u16.to_le_bytes());
+        zip.extend(0u16.to_le_bytes());
+        zip.extend(b"a.txt");
+        zip.extend(stored);
+
+        let mut compressed = Vec::with_capacity(data.len());
+        flate2::Compress::new(flate2::Compression::default(), false)
+            .compress_vec(data, &mut compressed, flate2::FlushCompress::Finish)
+            .unwrap();
+        zip.extend(ZIP_LOCAL.to_le_bytes());
+        zip.extend([20, 0, 8, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        zip.extend([0; 8]);
+        zip.extend(5u16.to_le_bytes());
+        zip.extend(0u16.to_le_bytes());
+        zip.extend(b"b.txt");
+        zip.extend(&compressed);
+        zip.extend(ZIP_DESCRIPTOR.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend((compressed.len() as u32).to_le_bytes());
+        zip.extend((data.len() as u32).to_le_bytes());
+
+        zip.extend(ZIP_CENTRAL.to_le_bytes());
+        zip.extend([0; 42]);
+        zip
+    }
+
+    /// Unpack `data` fed in chunks of `size`, as paths and contents.
+    fn unpack(format: Format, data: Vec<u8>, size: usize) -> Vec<(String, EntryKind, Vec<u8>)> {
+        let mut archive = Archive::new(format, body(data, size));
+
+        block_on(async {
+            let mut entries = Vec::new();
+            while let Some(entry) = archive.next_entry().await.unwrap() {
+                let (path, kind) = (entry.path().to_owned(), entry.kind());
+                entries.push((path, kind, entry.bytes().await.unwrap().to_vec()));
+            }
+            entries
+        })
+    }
+
+    #[test]
+    fn tar_entries() {
+        for size in [1, 7, 512, 100_000] {
+            let entries = unpack(Format::Tar, tar(), size);
+            assert_eq!(entries.len(), 3, "chunks of {}", size);
+            assert_eq!(entries[0].0, "data/");
+            assert_eq!(entries[0].1, EntryKind::Directory);
+            assert_eq!(entries[1].0, "data/a.csv");
+            assert_eq!(entries[1].2, b"a,b\n1,2\n");
+            assert_eq!(entries[2].0, format!("data/{}.txt", "x".repeat(120)));
+            assert_eq!(entries[2].2, vec![7; 1000]);
+        }
+    }
+
+    #[test]
+    fn tar_gz_entries() {
+        let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+        std::io::Write::write_all(&mut encoder, &tar()).unwrap();
+        let gz = encoder.finish().into_result().unwrap();
+
+        for size in [1, 64, 100_000] {
+            let entries = unpack(Format::TarGz, gz.clone(), size);
+            assert_eq!(entries.len(), 3);
+            assert_eq!(entries[1].2, b"a,b\n1,2\n");
+            assert_eq!(entries[2].2, vec![7; 1000]);
+        }
+    }
+
+    #[test]
+    fn zip_entries() {
+        for size in [1, 13, 100_000] {
+            let entries = unpack(Format::Zip, zip(), size);
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].0, "a.txt");
+            assert_eq!(entries[0].2, b"hello");
+            assert_eq!(entries[1].0, "b.txt");
+            assert_eq!(entries[1].2, b"deflated ".repeat(1000));
+        }
+    }
+
+    #[test]
+    fn inflated_in_bounded_chunks() {
+        let data = vec![0; 4 * 1024 * 1024];
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "zeros", &data, b'0');
+        tar.extend(vec![0; BLOCK * 2]);
+        let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+        std::io::Write::write_all(&mut encoder, &tar).unwrap();
+        let gz = encoder.finish().into_result().unwrap();
+
+        // The zip has a stored entry before the deflated one.
+        for (format, archive, skipped) in [(Format::TarGz, gz, 0), (Format::Zip, zip_of(&data), 1)]
+        {
+            // The whole archive arrives at once, and is inflated bit by bit.
+            let len = archive.len();
+            let mut archive = Archive::new(format, body(archive, len));
+            block_on(async {
+                for _ in 0..skipped {
+                    archive.next_entry().await.unwrap().unwrap();
+                }
+                let mut entry = archive.next_entry().await.unwrap().unwrap();
+                let mut len = 0;
+                while let Some(chunk) = entry.chunk().await.unwrap() {
+                    assert!(chunk.len() <= MAX_INFLATE_LEN);
+                    assert!(chunk.iter().all(|b| *b == 0));
+                    len += chunk.len();
+                }
+                assert_eq!(len, data.len());
+            });
+        }
+    }
+
+    #[test]
+    fn skipped_entries() {
+        let mut archive = Archive::new(Format::Zip, body(zip(), 100_000));
+
+        block_on(async {
+            let first = archive.next_entry().await.unwrap().unwrap();
+            assert_eq!(first.path(), "a.txt");
+            let second = archive.next_entry().await.unwrap().unwrap();
+            assert_eq!(second.path(), "b.txt");
+            assert_eq!(second.size(), None);
+            assert!(archive.next_entry().await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn truncated_archive() {
+        let mut tar = tar();
+        // In the middle of the data of the second entry.
+        tar.truncate(1028);
+        let mut archive = Archive::new(Format::Tar, body(tar, 100_000));
+
+        block_on(async {
+            let dir = archive.next_entry().await.unwrap().unwrap();
+            assert!(dir.bytes().await.unwrap().is_empty());
+            let file = archive.next_entry().await.unwrap().unwrap();
+            assert!(file.bytes().await.is_err());
+        });
+    }
+
+    #[test]
+    fn safe_paths() {
+        let safe_path = |path: &str| {
+            let mut archive = Archive::new(Format::Tar, body(Vec::new(), 1));
+            let entry = Entry {
+                archive: &mut archive,
+                header: Header {
+                    path: path.to_owned(),
+                    size: Some(0),
+                    kind: EntryKind::File,
+                },
+            };
+            entry.safe_path()
+        };
+
+        assert_eq!(safe_path("./data/a.csv"), Some(PathBuf::from("data/a.csv")));
+        assert_eq!(safe_path("../etc/passwd"), None);
+        assert_eq!(safe_path("/etc/passwd"), None);
+        assert_eq!(safe_path("data/../../x"), None);
+    }
+}
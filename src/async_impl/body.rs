@@ -1,7 +1,9 @@
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use bytes::Bytes;
 use futures_core::Stream;
@@ -13,6 +15,10 @@ use tokio::time::Sleep;
 #[cfg(feature = "stream")]
 use tokio_util::io::ReaderStream;
 
+use super::in_flight::{Permit, PermitBody};
+use super::progress::{CountingBody, Transfer};
+use super::scope::{ScopeGuard, ScopedBody};
+
 /// An asynchronous request body.
 pub struct Body {
     inner: Inner,
@@ -44,6 +50,12 @@ pin_project! {
 
 struct WrapHyper(hyper::Body);
 
+struct ReadTimeoutBody<B> {
+    inner: B,
+    timeout: Duration,
+    deadline: Pin<Box<Sleep>>,
+}
+
 impl Body {
     /// Returns a reference to the internal data of the `Body`.
     ///
@@ -117,6 +129,71 @@ impl Body {
         }
     }
 
+    /// Make a streaming body fail once the scope of `guard` is closed.
+    pub(crate) fn scoped(self, guard: ScopeGuard) -> Body {
+        match self.inner {
+            Inner::Streaming { body, timeout } => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(ScopedBody { inner: body, guard }),
+                    timeout,
+                },
+            },
+            inner @ Inner::Reusable(_) => Body { inner },
+        }
+    }
+
+    /// Count the bytes of a streaming body into `transfer`.
+    pub(crate) fn counted(self, transfer: Arc<Transfer>) -> Body {
+        match self.inner {
+            Inner::Streaming { body, timeout } => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(CountingBody {
+                        inner: body,
+                        transfer,
+                    }),
+                    timeout,
+                },
+            },
+            inner @ Inner::Reusable(_) => Body { inner },
+        }
+    }
+
+    /// Fail a streaming body once no data has been read for `timeout`.
+    pub(crate) fn with_read_timeout(self, timeout: Duration) -> Body {
+        match self.inner {
+            Inner::Streaming {
+                body,
+                timeout: total,
+            } => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(ReadTimeoutBody {
+                        inner: body,
+                        timeout,
+                        deadline: Box::pin(tokio::time::sleep(timeout)),
+                    }),
+                    timeout: total,
+                },
+            },
+            inner @ Inner::Reusable(_) => Body { inner },
+        }
+    }
+
+    /// Hold `permit` until a streaming body has been read to its end.
+    pub(crate) fn with_permit(self, permit: Permit) -> Body {
+        match self.inner {
+            Inner::Streaming { body, timeout } => Body {
+                inner: Inner::Streaming {
+                    body: Box::pin(PermitBody {
+                        inner: body,
+                        permit: Some(permit),
+                    }),
+                    timeout,
+                },
+            },
+            inner @ Inner::Reusable(_) => Body { inner },
+        }
+    }
+
     #[cfg(feature = "blocking")]
     pub(crate) fn wrap(body: hyper::Body) -> Body {
         Body {
@@ -357,6 +434,48 @@ impl HttpBody for WrapHyper {
     }
 }
 
+// ===== impl ReadTimeoutBody =====
+
+impl<B> HttpBody for ReadTimeoutBody<B>
+where
+    B: HttpBody<Data = Bytes, Error = Box<dyn std::error::Error + Send + Sync>> + Unpin,
+{
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        match Pin::new(&mut self.inner).poll_data(cx) {
+            Poll::Ready(item) => {
+                let next = tokio::time::Instant::now() + self.timeout;
+                self.deadline.as_mut().reset(next);
+                Poll::Ready(item)
+            }
+            Poll::Pending => match self.deadline.as_mut().poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Some(Err(crate::error::TimedOut.into()))),
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Body;
@@ -1,41 +1,79 @@
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::sync::Arc;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, convert::TryInto, net::SocketAddr};
 use std::{fmt, str};
 
 use bytes::Bytes;
 use http::header::{
-    Entry, HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH,
-    CONTENT_TYPE, LOCATION, PROXY_AUTHORIZATION, RANGE, REFERER, TRANSFER_ENCODING, USER_AGENT,
+    Entry, HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING,
+    CONTENT_LENGTH, CONTENT_TYPE, LOCATION, PROXY_AUTHORIZATION, RANGE, REFERER, TRANSFER_ENCODING,
+    USER_AGENT,
 };
 use http::uri::Scheme;
-use http::{HeaderName, Uri};
+use http::{HeaderName, Uri, Version};
 use hyper::client::{HttpConnector, ResponseFuture as HyperResponseFuture};
 use pin_project_lite::pin_project;
-use std::future::Future;
+use std::future::{self, Future};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::time::Sleep;
+use tower_layer::Layer;
 
-use super::decoder::Accepts;
-use super::request::{Request, RequestBuilder};
+#[cfg(feature = "libdeflate")]
+use super::decoder::Scratch;
+use super::decoder::{Accepts, ContentDecoder, ContentEncoding, DecompressionBackend};
+use super::in_flight::{Acquire, InFlightLimit, Permit};
+use super::request::{ConnectionMode, Pieces, Request, RequestBuilder, RequestTemplate, Timeouts};
 use super::response::Response;
+use super::scope::{Scope, ScopeGuard, ScopeState};
 use super::Body;
-use crate::connect::Connector;
+#[cfg(feature = "__boring")]
+use crate::alt_svc::AltSvcCache;
+use crate::connect::{BufferBudget, Connector};
 #[cfg(feature = "cookies")]
 use crate::cookie;
+use crate::dns;
+#[cfg(feature = "__boring")]
+use crate::dns::doh::DohResolver;
+use crate::dns::family::{FamilyCache, FamilyResolver};
+use crate::dns::private::{self, DenyPrivateIps};
+use crate::dns::{gai::GaiResolver, DnsResolverWithOverrides, DynResolver, IntoResolve, Resolve};
 #[cfg(feature = "hickory-dns")]
-use crate::dns::hickory::HickoryDnsResolver;
-use crate::dns::{gai::GaiResolver, DnsResolverWithOverrides, DynResolver, Resolve};
+use crate::dns::{hickory::HickoryDnsResolver, HickoryConfig};
+use crate::egress;
 use crate::error;
+#[cfg(feature = "__boring")]
+use crate::h2_fallback::{self, H2Fallback};
+use crate::http1::HeadWrites;
+use crate::http2::{Direction, Frame, FrameCallback, PrefaceShape};
 #[cfg(feature = "impersonate")]
-use crate::impersonate::{Impersonate, ImpersonateContext};
-use crate::into_url::{expect_uri, try_uri};
+use crate::impersonate::{
+    agent_profile, Http2Settings, Impersonate, ImpersonateContext, PseudoHeader, SessionPartition,
+};
+#[cfg(feature = "__boring")]
+use crate::impersonate::{EchConfigs, TlsSessionStore};
+use crate::into_url::{expect_uri, into_url_with_base, try_uri};
+use crate::middleware::{self, Interceptor, Next};
+use crate::proxy::{IntoProxyScheme, ProxyScheme};
+#[cfg(feature = "__boring")]
+use crate::proxy_protocol::ProxyHeader;
+use crate::rate_limit::RateLimit;
 use crate::redirect::{self, remove_sensitive_headers};
+use crate::retry;
+#[cfg(feature = "scripting")]
+use crate::script::ScriptHook;
+use crate::timing::Exchange;
 #[cfg(feature = "__tls")]
 use crate::tls::{self, TlsBackend};
-use crate::{IntoUrl, Method, Proxy, StatusCode, Url};
+#[cfg(feature = "__boring")]
+use crate::tls::{Certificate, Identity};
+#[cfg(feature = "__boring")]
+use crate::ProxyProtocol;
+use crate::{
+    History, HomographPolicy, IntoUrl, Method, Ping, Priority, Proxy, ProxyPool, StatusCode, Url,
+};
 use log::{debug, trace};
 
 /// An asynchronous `Client` to make Requests with.
@@ -50,10 +88,16 @@ use log::{debug, trace};
 /// You do **not** have to wrap the `Client` in an [`Rc`] or [`Arc`] to **reuse** it,
 /// because it already uses an [`Arc`] internally.
 ///
+/// A `Client` isn't tied to a tokio runtime, so it can be used from several
+/// ones, for instance by code calling `block_on` on runtimes of its own. Its
+/// connections run on the runtime that opened them, and are dropped from
+/// the pool when that runtime shuts down.
+///
 /// [`Rc`]: std::rc::Rc
 #[derive(Clone)]
 pub struct Client {
     inner: Arc<ClientRef>,
+    scope: Option<Arc<ScopeState>>,
 }
 
 /// A `ClientBuilder` can be used to create a `Client` with custom configuration.
@@ -62,6 +106,15 @@ pub struct ClientBuilder {
     config: Config,
 }
 
+/// Hyper clients kept for the proxies or addresses of requests, beyond
+/// which they are all dropped, so that rotating through many of them doesn't
+/// grow the client without bound.
+const MAX_ROUTE_CLIENTS: usize = 256;
+
+/// The most bytes hyper buffers while reading an HTTP/1 connection, unless
+/// set with `http1_max_buf_size`.
+const DEFAULT_MAX_BUF_SIZE: usize = 8192 + 4096 * 100;
+
 enum HttpVersionPref {
     Http1,
     Http2,
@@ -71,7 +124,9 @@ enum HttpVersionPref {
 struct Config {
     // NOTE: When adding a new field, update `fmt::Debug for ClientBuilder`
     accepts: Accepts,
-    headers: HeaderMap,
+    base_url: Option<Url>,
+    default_query: Vec<(String, String)>,
+    headers: Arc<HeaderMap>,
     headers_order: Option<Vec<HeaderName>>,
     #[cfg(feature = "__tls")]
     certs_verification: bool,
@@ -79,14 +134,26 @@ struct Config {
     tls_sni: bool,
     connect_timeout: Option<Duration>,
     connection_verbose: bool,
+    wire_capture: Option<PathBuf>,
+    http2_frame_callback: Option<FrameCallback>,
+    http2_preface: Option<Arc<PrefaceShape>>,
+    executor: Option<Exec>,
     pool_idle_timeout: Option<Duration>,
     pool_max_idle_per_host: usize,
+    max_in_flight: Option<usize>,
+    max_in_flight_per_priority: [Option<usize>; 2],
+    rate_limit: Option<RateLimit>,
+    retry_budget: Option<retry::Budget>,
+    retry_policy: Option<retry::Policy>,
+    layers: Vec<middleware::Wrap>,
     tcp_keepalive: Option<Duration>,
     proxies: Vec<Proxy>,
+    proxy_pool: Option<ProxyPool>,
     auto_sys_proxy: bool,
     redirect_policy: redirect::Policy,
     referer: bool,
     timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
     #[cfg(feature = "__tls")]
     tls_built_in_root_certs: bool,
     #[cfg(feature = "__tls")]
@@ -98,11 +165,17 @@ struct Config {
     #[cfg(feature = "__tls")]
     tls: Option<TlsBackend>,
     http_version_pref: HttpVersionPref,
+    h2c_prior_knowledge: bool,
     http09_responses: bool,
     http1_title_case_headers: bool,
     http1_allow_obsolete_multiline_headers_in_responses: bool,
     http1_ignore_invalid_headers_in_responses: bool,
     http1_allow_spaces_after_header_name_in_responses: bool,
+    http1_lenient_status_line: bool,
+    http1_preserve_response_headers: bool,
+    http1_head_writes: Option<HeadWrites>,
+    http1_max_buf_size: Option<usize>,
+    buffer_memory_limit: Option<usize>,
     http2_initial_stream_window_size: Option<u32>,
     http2_initial_connection_window_size: Option<u32>,
     http2_adaptive_window: bool,
@@ -114,16 +187,38 @@ struct Config {
     http2_keep_alive_interval: Option<Duration>,
     http2_keep_alive_timeout: Option<Duration>,
     http2_keep_alive_while_idle: bool,
+    #[cfg(feature = "__boring")]
+    http2_fallback_ttl: Option<Duration>,
+    #[cfg(feature = "__boring")]
+    alt_svc: bool,
     local_address_ipv6: Option<Ipv6Addr>,
     local_address_ipv4: Option<Ipv4Addr>,
     nodelay: bool,
+    #[cfg(feature = "__boring")]
+    proxy_protocol: Option<ProxyHeader>,
     #[cfg(feature = "cookies")]
     cookie_store: Option<Arc<dyn cookie::CookieStore>>,
+    #[cfg(feature = "cookies")]
+    cookie_jar: Option<Arc<cookie::Jar>>,
     hickory_dns: bool,
+    #[cfg(feature = "hickory-dns")]
+    hickory_dns_config: Option<HickoryConfig>,
     error: Option<crate::Error>,
     https_only: bool,
+    homograph_policy: HomographPolicy,
+    deny_private_ips: bool,
+    egress_policy: Option<Arc<egress::Policy>>,
     dns_overrides: HashMap<String, Vec<SocketAddr>>,
     dns_resolver: Option<Arc<dyn Resolve>>,
+    dns_timeout: Option<Duration>,
+    dns_fallback: dns::Fallback,
+    #[cfg(feature = "__boring")]
+    dns_over_https: Option<Url>,
+    #[cfg(feature = "__boring")]
+    dns_over_https_bootstrap: Vec<IpAddr>,
+    ip_family_ttl: Option<Duration>,
+    #[cfg(feature = "scripting")]
+    script_hook: Option<Arc<ScriptHook>>,
     #[cfg(feature = "impersonate")]
     impersonate: Impersonate,
     #[cfg(feature = "impersonate")]
@@ -132,6 +227,26 @@ struct Config {
     permute_extensions: bool,
     #[cfg(feature = "impersonate")]
     pre_shared_key: bool,
+    #[cfg(feature = "impersonate")]
+    post_quantum: Option<bool>,
+    #[cfg(feature = "impersonate")]
+    http2_pseudo_order: Option<[PseudoHeader; 4]>,
+    #[cfg(feature = "__boring")]
+    tls_session_partition: Option<String>,
+    #[cfg(feature = "__boring")]
+    tls_session_store: Option<Arc<dyn TlsSessionStore>>,
+    #[cfg(feature = "__boring")]
+    ech_config_lists: HashMap<String, Vec<u8>>,
+    #[cfg(feature = "__boring")]
+    ech_dns: bool,
+    #[cfg(feature = "__boring")]
+    identity: Option<Identity>,
+    #[cfg(feature = "__boring")]
+    root_certs: Vec<Certificate>,
+    #[cfg(feature = "__boring")]
+    cert_pins: Vec<(String, [u8; 32])>,
+    #[cfg(feature = "native-roots")]
+    tls_native_roots: bool,
 }
 
 impl Default for ClientBuilder {
@@ -152,7 +267,9 @@ impl ClientBuilder {
             config: Config {
                 error: None,
                 accepts: Accepts::default(),
-                headers,
+                base_url: None,
+                default_query: Vec::new(),
+                headers: Arc::new(headers),
                 headers_order: None,
                 #[cfg(feature = "__tls")]
                 certs_verification: true,
@@ -160,16 +277,28 @@ impl ClientBuilder {
                 tls_sni: true,
                 connect_timeout: None,
                 connection_verbose: false,
+                wire_capture: None,
+                http2_frame_callback: None,
+                http2_preface: None,
+                executor: None,
                 pool_idle_timeout: Some(Duration::from_secs(90)),
                 pool_max_idle_per_host: usize::MAX,
+                max_in_flight: None,
+                max_in_flight_per_priority: [None; 2],
+                rate_limit: None,
+                retry_budget: None,
+                retry_policy: None,
+                layers: Vec::new(),
                 // TODO: Re-enable default duration once hyper's HttpConnector is fixed
                 // to no longer error when an option fails.
                 tcp_keepalive: None, //Some(Duration::from_secs(60)),
                 proxies: Vec::new(),
+                proxy_pool: None,
                 auto_sys_proxy: true,
                 redirect_policy: redirect::Policy::default(),
                 referer: true,
                 timeout: None,
+                read_timeout: None,
                 #[cfg(feature = "__tls")]
                 tls_built_in_root_certs: true,
                 #[cfg(feature = "__tls")]
@@ -181,11 +310,17 @@ impl ClientBuilder {
                 #[cfg(feature = "__tls")]
                 tls: None,
                 http_version_pref: HttpVersionPref::All,
+                h2c_prior_knowledge: false,
                 http09_responses: false,
                 http1_title_case_headers: false,
                 http1_allow_obsolete_multiline_headers_in_responses: false,
                 http1_ignore_invalid_headers_in_responses: false,
                 http1_allow_spaces_after_header_name_in_responses: false,
+                http1_lenient_status_line: false,
+                http1_preserve_response_headers: false,
+                http1_head_writes: None,
+                http1_max_buf_size: None,
+                buffer_memory_limit: None,
                 http2_initial_stream_window_size: None,
                 http2_initial_connection_window_size: None,
                 http2_adaptive_window: false,
@@ -197,15 +332,37 @@ impl ClientBuilder {
                 http2_keep_alive_interval: None,
                 http2_keep_alive_timeout: None,
                 http2_keep_alive_while_idle: false,
+                #[cfg(feature = "__boring")]
+                http2_fallback_ttl: None,
+                #[cfg(feature = "__boring")]
+                alt_svc: false,
                 local_address_ipv6: None,
                 local_address_ipv4: None,
                 nodelay: true,
+                #[cfg(feature = "__boring")]
+                proxy_protocol: None,
                 hickory_dns: cfg!(feature = "hickory-dns"),
+                #[cfg(feature = "hickory-dns")]
+                hickory_dns_config: None,
                 #[cfg(feature = "cookies")]
                 cookie_store: None,
+                #[cfg(feature = "cookies")]
+                cookie_jar: None,
                 https_only: false,
+                homograph_policy: HomographPolicy::default(),
+                deny_private_ips: false,
+                egress_policy: None,
                 dns_overrides: HashMap::new(),
                 dns_resolver: None,
+                dns_timeout: None,
+                dns_fallback: dns::Fallback::Disabled,
+                #[cfg(feature = "__boring")]
+                dns_over_https: None,
+                #[cfg(feature = "__boring")]
+                dns_over_https_bootstrap: Vec::new(),
+                ip_family_ttl: Some(Duration::from_secs(600)),
+                #[cfg(feature = "scripting")]
+                script_hook: None,
                 #[cfg(feature = "impersonate")]
                 impersonate: Impersonate::default(),
                 #[cfg(feature = "impersonate")]
@@ -214,6 +371,26 @@ impl ClientBuilder {
                 permute_extensions: false,
                 #[cfg(feature = "impersonate")]
                 pre_shared_key: false,
+                #[cfg(feature = "impersonate")]
+                post_quantum: None,
+                #[cfg(feature = "impersonate")]
+                http2_pseudo_order: None,
+                #[cfg(feature = "__boring")]
+                tls_session_partition: None,
+                #[cfg(feature = "__boring")]
+                tls_session_store: None,
+                #[cfg(feature = "__boring")]
+                ech_config_lists: HashMap::new(),
+                #[cfg(feature = "__boring")]
+                ech_dns: false,
+                #[cfg(feature = "__boring")]
+                identity: None,
+                #[cfg(feature = "__boring")]
+                root_certs: Vec::new(),
+                #[cfg(feature = "__boring")]
+                cert_pins: Vec::new(),
+                #[cfg(feature = "native-roots")]
+                tls_native_roots: false,
             },
         }
     }
@@ -231,12 +408,47 @@ impl ClientBuilder {
             return Err(err);
         }
 
+        if !config.default_query.is_empty() && config.base_url.is_none() {
+            return Err(crate::error::builder(
+                "default_query requires a base_url to scope it to",
+            ));
+        }
+
+        // Hosts resolved to addresses of their own aren't connected to
+        // through their alternative services.
+        #[cfg(feature = "__boring")]
+        let overridden_hosts = config.dns_overrides.keys().cloned().collect();
+
         let mut proxies = config.proxies;
         if config.auto_sys_proxy {
             proxies.push(Proxy::system());
         }
         let proxies = Arc::new(proxies);
 
+        let family_cache = config
+            .ip_family_ttl
+            .map(|ttl| Arc::new(FamilyCache::new(ttl)));
+
+        #[cfg(feature = "__boring")]
+        let dns_over_https = config.dns_over_https.as_ref().map(DohResolver::new);
+        #[cfg(feature = "__boring")]
+        let mut dns_over_https_http = None;
+
+        #[cfg(feature = "__boring")]
+        let ech = match (config.ech_dns, &dns_over_https) {
+            (true, None) => {
+                return Err(crate::error::builder(
+                    "ECH lookups need a DNS-over-HTTPS resolver",
+                ))
+            }
+            (false, _) if config.ech_config_lists.is_empty() => None,
+            (dns, doh) => Some(Arc::new(EchConfigs::new(
+                config.ech_config_lists,
+                doh.clone().filter(|_| dns),
+            ))),
+        };
+
+        let routes_resolver: Arc<dyn Resolve>;
         let mut connector = {
             #[cfg(feature = "__tls")]
             fn user_agent(headers: &HeaderMap) -> Option<HeaderValue> {
@@ -246,12 +458,68 @@ impl ClientBuilder {
             let mut resolver: Arc<dyn Resolve> = match config.hickory_dns {
                 false => Arc::new(GaiResolver::new()),
                 #[cfg(feature = "hickory-dns")]
-                true => Arc::new(HickoryDnsResolver::default()),
+                true => match config.hickory_dns_config {
+                    Some(tuning) => Arc::new(HickoryDnsResolver::new(tuning)),
+                    None => Arc::new(HickoryDnsResolver::default()),
+                },
                 #[cfg(not(feature = "hickory-dns"))]
                 true => unreachable!("hickory-dns shouldn't be enabled unless the feature is"),
             };
+            resolver = Arc::new(dns::lookup::Lookup::new(
+                resolver,
+                dns::ResolvedBy::System,
+                config.dns_timeout,
+            ));
+            #[cfg(feature = "__boring")]
+            if let Some(ref doh) = dns_over_https {
+                // The server's own name is resolved by the system resolver,
+                // or by the bootstrap addresses.
+                let mut bootstrap = resolver.clone();
+                if !config.dns_over_https_bootstrap.is_empty() {
+                    let addrs = config
+                        .dns_over_https_bootstrap
+                        .iter()
+                        .map(|&ip| SocketAddr::new(ip, 0))
+                        .collect();
+                    bootstrap = Arc::new(DnsResolverWithOverrides::new(
+                        bootstrap,
+                        HashMap::from([(doh.host().to_owned(), addrs)]),
+                    ));
+                }
+                let mut http = HttpConnector::new_with_resolver(DynResolver::new(bootstrap));
+                http.set_connect_timeout(config.connect_timeout);
+                dns_over_https_http = Some(http);
+
+                resolver = Arc::new(dns::lookup::Lookup::new(
+                    Arc::new(doh.clone()),
+                    dns::ResolvedBy::DnsOverHttps,
+                    config.dns_timeout,
+                ));
+            }
             if let Some(dns_resolver) = config.dns_resolver {
-                resolver = dns_resolver;
+                let custom = Arc::new(dns::lookup::Lookup::new(
+                    dns_resolver,
+                    dns::ResolvedBy::Custom,
+                    config.dns_timeout,
+                ));
+                resolver = match config.dns_fallback {
+                    dns::Fallback::Disabled => custom,
+                    dns::Fallback::CustomThenSystem => {
+                        Arc::new(dns::lookup::FallbackResolver::new(custom, resolver))
+                    }
+                    dns::Fallback::SystemThenCustom => {
+                        Arc::new(dns::lookup::FallbackResolver::new(resolver, custom))
+                    }
+                };
+            }
+            if let Some(ref cache) = family_cache {
+                resolver = Arc::new(FamilyResolver::new(resolver, cache.clone()));
+            }
+            if config.deny_private_ips {
+                resolver = Arc::new(DenyPrivateIps::new(resolver));
+            }
+            if let Some(ref policy) = config.egress_policy {
+                resolver = Arc::new(egress::PolicyResolver::new(resolver, policy.clone()));
             }
             if !config.dns_overrides.is_empty() {
                 resolver = Arc::new(DnsResolverWithOverrides::new(
@@ -259,15 +527,43 @@ impl ClientBuilder {
                     config.dns_overrides,
                 ));
             }
+            routes_resolver = resolver.clone();
             let mut http = HttpConnector::new_with_resolver(DynResolver::new(resolver.clone()));
             http.set_connect_timeout(config.connect_timeout);
 
             #[cfg(feature = "__tls")]
             match config.tls.unwrap_or_default() {
                 #[cfg(feature = "__boring")]
-                TlsBackend::BoringTls(tls) => Connector::new_boring_tls(
+                TlsBackend::BoringTls(mut tls) => Connector::new_boring_tls(
                     http,
-                    tls,
+                    {
+                        if let Some(store) = config.tls_session_store {
+                            tls = tls.with_session_store(store);
+                        }
+                        if let Some(ech) = ech {
+                            tls = tls.with_ech(ech);
+                        }
+                        if let Some(identity) = config.identity {
+                            tls = tls.with_identity(identity);
+                        }
+                        if !config.root_certs.is_empty() {
+                            tls = tls.with_root_certs(
+                                config
+                                    .root_certs
+                                    .into_iter()
+                                    .map(Certificate::into_x509)
+                                    .collect(),
+                            );
+                        }
+                        if !config.cert_pins.is_empty() {
+                            tls = tls.with_pins(config.cert_pins);
+                        }
+                        #[cfg(feature = "native-roots")]
+                        if config.tls_native_roots {
+                            tls = tls.with_root_certs(tls::native_roots()?);
+                        }
+                        tls
+                    },
                     proxies.clone(),
                     user_agent(&config.headers),
                     config.local_address_ipv4,
@@ -280,12 +576,18 @@ impl ClientBuilder {
                         enable_ech_grease: config.enable_ech_grease,
                         permute_extensions: config.permute_extensions,
                         pre_shared_key: config.pre_shared_key,
+                        post_quantum: config.post_quantum,
                         h2: match config.http_version_pref {
                             HttpVersionPref::Http1 => false,
                             HttpVersionPref::Http2 | HttpVersionPref::All => true,
                         },
+                        session: SessionPartition {
+                            proxy: None,
+                            id: config.tls_session_partition,
+                        },
                     },
-                ),
+                )
+                .map_err(crate::error::builder)?,
 
                 #[cfg(not(feature = "__boring"))]
                 TlsBackend::UnknownPreconfigured => {
@@ -301,6 +603,49 @@ impl ClientBuilder {
 
         connector.set_timeout(config.connect_timeout);
         connector.set_verbose(config.connection_verbose);
+        let wire_capture = match config.wire_capture {
+            Some(ref path) => Some(Arc::new(
+                WireCapture::open(path).map_err(crate::error::builder)?,
+            )),
+            None => None,
+        };
+        connector.set_wire_capture(wire_capture);
+        connector.set_frame_callback(config.http2_frame_callback);
+        connector.set_h2_preface(config.http2_preface);
+        connector.set_family_cache(family_cache);
+        if let Some(limit) = config.buffer_memory_limit {
+            let share = config.http1_max_buf_size.unwrap_or(DEFAULT_MAX_BUF_SIZE);
+            let budget = BufferBudget::new(limit, share).ok_or_else(|| {
+                crate::error::builder(
+                    "buffer_memory_limit must fit the read buffer of a connection",
+                )
+            })?;
+            connector.set_buffer_budget(Some(budget));
+        }
+        connector.set_h2c(config.h2c_prior_knowledge);
+        connector.set_http2_only(matches!(config.http_version_pref, HttpVersionPref::Http2));
+        connector.set_head_writes(config.http1_head_writes);
+        connector.set_lenient_status_line(config.http1_lenient_status_line);
+        connector.set_raw_heads(
+            config.http1_preserve_response_headers
+                && !matches!(config.http_version_pref, HttpVersionPref::Http2),
+        );
+
+        #[cfg(feature = "__boring")]
+        let h2_fallback = config
+            .http2_fallback_ttl
+            .map(|ttl| Arc::new(H2Fallback::new(ttl)));
+        #[cfg(feature = "__boring")]
+        connector.set_h2_fallback(h2_fallback.clone());
+
+        #[cfg(feature = "__boring")]
+        let alt_svc = config
+            .alt_svc
+            .then(|| Arc::new(AltSvcCache::with_overrides(overridden_hosts)));
+        #[cfg(feature = "__boring")]
+        connector.set_alt_svc(alt_svc.clone());
+        #[cfg(feature = "__boring")]
+        connector.set_proxy_protocol(config.proxy_protocol);
 
         let mut builder = hyper::Client::builder();
         if matches!(config.http_version_pref, HttpVersionPref::Http2) {
@@ -343,9 +688,15 @@ impl ClientBuilder {
             builder.http2_keep_alive_while_idle(true);
         }
 
-        builder.http2_agent_profile(config.impersonate.profile().into());
+        builder.http2_agent_profile(agent_profile(
+            config.impersonate.profile(),
+            config.http2_pseudo_order,
+        ));
         builder.pool_idle_timeout(config.pool_idle_timeout);
         builder.pool_max_idle_per_host(config.pool_max_idle_per_host);
+        if let Some(executor) = config.executor {
+            builder.executor(executor);
+        }
         connector.set_keepalive(config.tcp_keepalive);
 
         if config.http09_responses {
@@ -368,21 +719,134 @@ impl ClientBuilder {
             builder.http1_allow_spaces_after_header_name_in_responses(true);
         }
 
+        if let Some(max) = config.http1_max_buf_size {
+            builder.http1_max_buf_size(max);
+        }
+
+        // Connections split vectored writes themselves, unless hyper
+        // flattens the head and body into one buffer.
+        if let Some(head_writes) = config.http1_head_writes {
+            builder.http1_writev(head_writes != HeadWrites::Coalesced);
+        }
+
         let proxies_maybe_http_auth = proxies.iter().any(|p| p.maybe_has_http_auth());
+        let proxies_custom = proxies.iter().any(Proxy::is_custom);
+
+        let no_slots = Some(0);
+        if config.max_in_flight == no_slots || config.max_in_flight_per_priority.contains(&no_slots)
+        {
+            return Err(crate::error::builder(
+                "max_in_flight must allow at least one request",
+            ));
+        }
+        let in_flight = if config.max_in_flight.is_some()
+            || config
+                .max_in_flight_per_priority
+                .iter()
+                .any(Option::is_some)
+        {
+            Some(Arc::new(InFlightLimit::new(
+                config.max_in_flight,
+                config.max_in_flight_per_priority,
+            )))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "__boring")]
+        if let (Some(doh), Some(http)) = (dns_over_https, dns_over_https_http) {
+            doh.set_client(builder.clone().build(connector.with_http(http)));
+        }
+
+        #[cfg(feature = "impersonate")]
+        let profile_routes = Arc::new(ProfileRoutes {
+            builder: builder.clone(),
+            connector: connector.clone(),
+            accepts: config.accepts.clone(),
+            head_writes: config.http1_head_writes,
+            routes: Mutex::new(HashMap::new()),
+        });
+
+        let proxy_routes = Arc::new(ProxyRoutes {
+            pool: config.proxy_pool,
+            builder: builder.clone(),
+            connector: connector.clone(),
+            clients: Mutex::new(HashMap::new()),
+        });
+
+        let dns_routes = Arc::new(DnsRoutes {
+            builder: builder.clone(),
+            connector: connector.clone(),
+            resolver: routes_resolver,
+            connect_timeout: config.connect_timeout,
+            tcp_keepalive: config.tcp_keepalive,
+            local_address_ipv4: config.local_address_ipv4,
+            local_address_ipv6: config.local_address_ipv6,
+            clients: Mutex::new(HashMap::new()),
+        });
+
+        let mut layers = config.layers;
+        if let Some(policy) = config.retry_policy {
+            let layer = retry::PolicyLayer::new(policy, config.retry_budget.clone());
+            layers.insert(0, middleware::wrap(layer));
+        }
 
         Ok(Client {
-            inner: Arc::new(ClientRef {
+            scope: None,
+            // The middleware is built once, over a client it doesn't keep
+            // alive.
+            inner: Arc::new_cyclic(|client| ClientRef {
                 accepts: config.accepts,
                 #[cfg(feature = "cookies")]
                 cookie_store: config.cookie_store,
+                #[cfg(feature = "cookies")]
+                cookie_jar: config.cookie_jar,
+                unpooled: builder
+                    .clone()
+                    .pool_max_idle_per_host(0)
+                    .build(connector.clone()),
                 hyper: builder.build(connector),
+                base_url: config.base_url,
+                default_query: config.default_query,
                 headers: config.headers,
                 headers_order: config.headers_order,
                 redirect_policy: Arc::new(config.redirect_policy),
                 referer: config.referer,
                 request_timeout: config.timeout,
+                read_timeout: config.read_timeout,
                 proxies_maybe_http_auth,
+                proxies_custom,
+                proxy_routes,
+                dns_routes,
+                #[cfg(feature = "impersonate")]
+                profile_routes,
+                in_flight,
+                rate_limit: config.rate_limit,
+                retry_budget: config.retry_budget,
+                middleware: (!layers.is_empty()).then(|| {
+                    let client = client.clone();
+                    middleware::stack(&layers, move |req| -> middleware::BoxFuture {
+                        match client.upgrade() {
+                            Some(inner) => {
+                                Box::pin(Client { inner, scope: None }.execute_direct(req))
+                            }
+                            None => {
+                                let err = error::request("the client was dropped");
+                                Box::pin(future::ready(Err(err.with_url(req.url().clone()))))
+                            }
+                        }
+                    })
+                }),
                 https_only: config.https_only,
+                homograph_policy: config.homograph_policy,
+                deny_private_ips: config.deny_private_ips,
+                egress_policy: config.egress_policy,
+                #[cfg(feature = "scripting")]
+                script_hook: config.script_hook,
+                #[cfg(feature = "__boring")]
+                h2_fallback,
+                #[cfg(feature = "__boring")]
+                alt_svc,
             }),
         })
     }
@@ -406,6 +870,32 @@ impl ClientBuilder {
         configure_impersonate(impersonate, self)
     }
 
+    /// Sends the headers of the specified impersonate version, keeping the
+    /// default TLS and HTTP/2 settings.
+    ///
+    /// Use this to look like a browser to the application while controlling
+    /// the TLS stack yourself, such as with
+    /// [`use_boring_tls`](ClientBuilder::use_boring_tls).
+    #[cfg(feature = "__impersonate")]
+    pub fn impersonate_headers(self, impersonate: Impersonate) -> ClientBuilder {
+        use crate::impersonate::configure_impersonate_headers;
+
+        configure_impersonate_headers(impersonate, self)
+    }
+
+    /// Uses the TLS and HTTP/2 settings of the specified impersonate version,
+    /// keeping the default headers.
+    ///
+    /// Use this to match the fingerprint of a browser while setting every
+    /// header yourself.
+    #[cfg(feature = "__impersonate")]
+    pub fn impersonate_tls(mut self, impersonate: Impersonate) -> ClientBuilder {
+        use crate::impersonate::configure_impersonate_tls;
+
+        self.config.impersonate = impersonate;
+        configure_impersonate_tls(impersonate, self)
+    }
+
     /// Enable Encrypted Client Hello (Secure SNI)
     #[cfg(feature = "__impersonate")]
     pub fn enable_ech_grease(mut self) -> ClientBuilder {
@@ -427,8 +917,194 @@ impl ClientBuilder {
         self
     }
 
+    /// Offer, or not, the hybrid post-quantum key share of Chromium.
+    ///
+    /// Chrome and Edge 124 and later offer `X25519Kyber768Draft00` first in
+    /// `supported_groups` and `key_share`, so their profiles do too, while
+    /// older ones don't. This overrides the profile, for servers that
+    /// choke on the larger ClientHello, or to follow a browser whose
+    /// rollout differs. Other profiles are left as they are.
+    ///
+    /// The group is left out anyway if the linked BoringSSL lacks it, see
+    /// [`capabilities`](crate::impersonate::capabilities).
+    #[cfg(feature = "__impersonate")]
+    pub fn post_quantum_key_share(mut self, enabled: bool) -> ClientBuilder {
+        self.config.post_quantum = Some(enabled);
+        self
+    }
+
+    /// Keep the TLS sessions of this client in their own partition.
+    ///
+    /// Session tickets are only resumed with the server and through the
+    /// proxy they were issued for, so that resuming one doesn't link the
+    /// identities a crawler takes. Clients sharing a `BoringTlsConnector`
+    /// passed to [`use_boring_tls`](ClientBuilder::use_boring_tls) share
+    /// its tickets otherwise; giving each identity its own partition id
+    /// keeps them apart.
+    #[cfg(feature = "__boring")]
+    pub fn tls_session_partition<S: Into<String>>(mut self, id: S) -> ClientBuilder {
+        self.config.tls_session_partition = Some(id.into());
+        self
+    }
+
+    /// Keep the TLS sessions of this client in `store`.
+    ///
+    /// Profiles offering a `pre_shared_key` resume the sessions of the
+    /// store, so a store that is saved and loaded again, or shared by
+    /// several processes, lets them resume sessions across restarts. By
+    /// default, sessions are kept in memory for the life of the client.
+    ///
+    /// Profiles set on requests with
+    /// [`RequestBuilder::impersonate`](crate::RequestBuilder::impersonate)
+    /// keep their sessions in memory regardless.
+    ///
+    /// See [`TlsSessionStore`] for an example.
+    #[cfg(feature = "__boring")]
+    pub fn tls_session_store<S: TlsSessionStore>(mut self, store: S) -> ClientBuilder {
+        self.config.tls_session_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Encrypt the ClientHello sent to `host` with Encrypted Client Hello,
+    /// using the `ECHConfigList` `list`.
+    ///
+    /// The server name and the other sensitive extensions are then only
+    /// sent in the encrypted inner ClientHello, and the outer one carries
+    /// the public name of the configuration. A server rejecting the
+    /// configuration sends new ones, which the connection is made again
+    /// with, or none if it disabled ECH, in which case the connection is
+    /// made again without it. A list that can't be parsed is skipped.
+    ///
+    /// Lists set here take precedence over those of
+    /// [`ech_dns`](ClientBuilder::ech_dns).
+    #[cfg(feature = "__boring")]
+    pub fn ech_config_list<L: Into<Vec<u8>>>(mut self, host: &str, list: L) -> ClientBuilder {
+        self.config
+            .ech_config_lists
+            .insert(host.to_ascii_lowercase(), list.into());
+        self
+    }
+
+    /// Look up the ECH configurations of servers in their DNS HTTPS
+    /// records, and offer them as Chrome does on origins publishing one.
+    ///
+    /// Records are looked up through the resolver set with
+    /// [`dns_over_https`](ClientBuilder::dns_over_https), before each new
+    /// connection to a host not looked up yet, and cached for their TTL.
+    /// Connections to hosts without a configuration are made as usual.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .dns_over_https("https://1.1.1.1/dns-query")
+    ///     .ech_dns(true)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if no DNS-over-HTTPS resolver is set.
+    #[cfg(feature = "__boring")]
+    pub fn ech_dns(mut self, enabled: bool) -> ClientBuilder {
+        self.config.ech_dns = enabled;
+        self
+    }
+
+    /// Present `identity` to servers asking for a client certificate.
+    ///
+    /// The certificate is added to the impersonated TLS settings, so the
+    /// ClientHello is left as the profile makes it, including for the
+    /// profiles set on requests with `RequestBuilder::impersonate`. Load it
+    /// with [`Identity::from_pkcs12_der`] or [`Identity::from_pem`], or see
+    /// [`PrivateKeySigner`](crate::tls::PrivateKeySigner) for keys that
+    /// can't be exported, such as those of hardware tokens.
+    #[cfg(feature = "__boring")]
+    pub fn identity(mut self, identity: Identity) -> ClientBuilder {
+        self.config.identity = Some(identity);
+        self
+    }
+
     // Higher-level options
 
+    /// Set a base URL for the relative URLs of requests.
+    ///
+    /// A request to a relative URL such as `/v1/items` is sent to that path
+    /// appended to the path of the base URL, whether or not either has a
+    /// `/` where they meet, and with the query of the relative URL. Absolute
+    /// URLs are used as they are.
+    ///
+    /// Without a base URL, requests to relative URLs fail with a builder
+    /// error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # async fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .base_url("https://api.example.com/v1")
+    ///     .build()?;
+    ///
+    /// // GET https://api.example.com/v1/items?page=2
+    /// let res = client.get("/items?page=2").send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn base_url<U: IntoUrl>(mut self, url: U) -> ClientBuilder {
+        match url.into_url() {
+            Ok(url) => self.config.base_url = Some(url),
+            Err(err) => self.config.error = Some(err),
+        }
+        self
+    }
+
+    /// Append query pairs to the URL of every request to the origin of the
+    /// [`base_url`](ClientBuilder::base_url), such as an API key or a
+    /// locale.
+    ///
+    /// Requests to other origins, such as absolute URLs to third parties,
+    /// are sent without them, so that a key isn't leaked to another host.
+    ///
+    /// This takes anything `RequestBuilder::query` does, and adds to the
+    /// pairs set before. The pairs are appended when the request is sent,
+    /// before the middleware of the client, except those whose key the URL
+    /// of the request already has, which lets a request override them, and
+    /// those removed with [`RequestBuilder::without_default_query`]. The
+    /// URLs of redirects are followed as they are.
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if `query` can't be serialized into a
+    /// query string, or if no `base_url` is set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # async fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .base_url("https://api.example.com/v1")
+    ///     .default_query(&[("api_key", "secret"), ("locale", "en")])
+    ///     .build()?;
+    ///
+    /// // GET https://api.example.com/v1/items?locale=fr&api_key=secret
+    /// let res = client.get("/items?locale=fr").send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn default_query<T: serde::Serialize + ?Sized>(mut self, query: &T) -> ClientBuilder {
+        match serde_urlencoded::to_string(query) {
+            Ok(query) => self
+                .config
+                .default_query
+                .extend(url::form_urlencoded::parse(query.as_bytes()).into_owned()),
+            Err(err) => self.config.error = Some(crate::error::builder(err)),
+        }
+        self
+    }
+
     /// Sets the `User-Agent` header to be used by this client.
     ///
     /// # Example
@@ -456,7 +1132,7 @@ impl ClientBuilder {
     {
         match value.try_into() {
             Ok(value) => {
-                self.config.headers.insert(USER_AGENT, value);
+                Arc::make_mut(&mut self.config.headers).insert(USER_AGENT, value);
             }
             Err(e) => {
                 self.config.error = Some(crate::error::builder(e.into()));
@@ -509,20 +1185,24 @@ impl ClientBuilder {
     /// # }
     /// ```
     pub fn default_headers(mut self, headers: HeaderMap) -> ClientBuilder {
+        let default_headers = Arc::make_mut(&mut self.config.headers);
         for (key, value) in headers.iter() {
-            self.config.headers.insert(key, value.clone());
+            default_headers.insert(key, value.clone());
         }
         self
     }
 
     #[cfg(feature = "__browser_common")]
-    pub(crate) fn replace_default_headers(mut self, headers: HeaderMap) -> ClientBuilder {
+    pub(crate) fn replace_default_headers(mut self, headers: Arc<HeaderMap>) -> ClientBuilder {
         self.config.headers = headers;
         self
     }
 
     /// Change the order in which headers will be sent
     ///
+    /// Impersonation sets the order of the browser, which this replaces, and
+    /// [`RequestBuilder::header_order`] overrides it for one request.
+    ///
     /// Warning
     ///
     /// The host header needs to be manually inserted if you want to modify its order.
@@ -549,6 +1229,7 @@ impl ClientBuilder {
             self.cookie_provider(Arc::new(cookie::Jar::default()))
         } else {
             self.config.cookie_store = None;
+            self.config.cookie_jar = None;
             self
         }
     }
@@ -569,6 +1250,8 @@ impl ClientBuilder {
         mut self,
         cookie_store: Arc<C>,
     ) -> ClientBuilder {
+        let any: Arc<dyn std::any::Any + Send + Sync> = cookie_store.clone();
+        self.config.cookie_jar = any.downcast::<cookie::Jar>().ok();
         self.config.cookie_store = Some(cookie_store as _);
         self
     }
@@ -733,6 +1416,74 @@ impl ClientBuilder {
         }
     }
 
+    /// Set how gzip and deflate response bodies are decompressed.
+    ///
+    /// Defaults to [`DecompressionBackend::Streaming`]. Brotli and zstd
+    /// bodies, and those of decoders set with
+    /// [`content_decoder`](ClientBuilder::content_decoder), are always
+    /// streamed.
+    ///
+    /// # Optional
+    ///
+    /// [`DecompressionBackend::Libdeflate`] requires the optional
+    /// `libdeflate` feature to be enabled, or building the client fails.
+    pub fn decompression_backend(mut self, backend: DecompressionBackend) -> ClientBuilder {
+        #[cfg(feature = "libdeflate")]
+        {
+            self.config.accepts.backend = backend;
+        }
+
+        #[cfg(not(feature = "libdeflate"))]
+        if backend == DecompressionBackend::Libdeflate {
+            self.config.error = Some(crate::error::builder(
+                "the libdeflate backend requires the `libdeflate` feature",
+            ));
+        }
+        self
+    }
+
+    /// Decode response bodies with the content coding `coding` using
+    /// `decoder`, and advertise it in `Accept-Encoding`.
+    ///
+    /// The coding is added to the `Accept-Encoding` the client sends by
+    /// default, or sets from its default headers, but not to one set on a
+    /// request. Registering a built-in coding such as `gzip` replaces its
+    /// decoder, and registering a coding twice keeps the last decoder.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn run() -> Result<(), rquest::Error> {
+    /// fn decode_custom(encoded: rquest::DecoderStream) -> rquest::DecoderStream {
+    ///     // Unwrap the proprietary framing here.
+    ///     encoded
+    /// }
+    ///
+    /// let client = rquest::Client::builder()
+    ///     .content_decoder("x-custom", decode_custom)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_decoder<D>(mut self, coding: &str, decoder: D) -> ClientBuilder
+    where
+        D: ContentDecoder,
+    {
+        let is_token = !coding.is_empty()
+            && coding
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+        if !is_token {
+            self.config.error = Some(crate::error::builder(format!(
+                "invalid content coding {:?}",
+                coding
+            )));
+            return self;
+        }
+        self.config.accepts.register(coding, Arc::new(decoder));
+        self
+    }
+
     // Redirect options
 
     /// Set a `RedirectPolicy` for this client.
@@ -764,6 +1515,24 @@ impl ClientBuilder {
         self
     }
 
+    /// Route requests through a `ProxyPool`.
+    ///
+    /// Requests rotate across the healthy proxies of the pool, and requests
+    /// with a [`proxy_tag`](crate::RequestBuilder::proxy_tag) only use the
+    /// proxies carrying that tag. Each proxy gets its own connection pool,
+    /// so a kept-alive connection is never reused through another proxy.
+    ///
+    /// # Note
+    ///
+    /// Adding a proxy pool will disable the automatic usage of the
+    /// "system" proxy.
+    pub fn proxy_pool(mut self, pool: ProxyPool) -> ClientBuilder {
+        self.config.proxies.push(pool.proxy());
+        self.config.proxy_pool = Some(pool);
+        self.config.auto_sys_proxy = false;
+        self
+    }
+
     /// Clear all `Proxies`, so `Client` will use no proxy anymore.
     ///
     /// # Note
@@ -773,6 +1542,7 @@ impl ClientBuilder {
     /// This also disables the automatic usage of the "system" proxy.
     pub fn no_proxy(mut self) -> ClientBuilder {
         self.config.proxies.clear();
+        self.config.proxy_pool = None;
         self.config.auto_sys_proxy = false;
         self
     }
@@ -790,6 +1560,19 @@ impl ClientBuilder {
         self
     }
 
+    /// Enables a read timeout.
+    ///
+    /// The timeout is applied to waiting for the response headers once the
+    /// request is sent, and then to each read of the response body. It
+    /// starts over for each retry and redirect, and after each chunk of the
+    /// body, so it bounds stalls rather than slow transfers.
+    ///
+    /// Default is no timeout.
+    pub fn read_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.read_timeout = Some(timeout);
+        self
+    }
+
     /// Set a timeout for only the connect phase of a `Client`.
     ///
     /// Default is `None`.
@@ -814,6 +1597,38 @@ impl ClientBuilder {
         self
     }
 
+    /// Capture the decrypted traffic of connections to a file.
+    ///
+    /// What each connection writes and reads after TLS is appended to the
+    /// file at `path`, to look at the fingerprint of the client without a
+    /// proxy or keylog and pcap tools. Each line is a record:
+    ///
+    /// ```text
+    /// <unix time in ms> <connection id> <w|r> h1 <escaped bytes>
+    /// <unix time in ms> <connection id> <w|r> h2 <frame type> flags=<flags> stream=<id> len=<length> [<payload>]
+    /// ```
+    ///
+    /// Connection ids count up in hex from `00000000`, in the order
+    /// connections open. Records are written to the file by a thread of
+    /// their own, and all of them by the time the client is dropped.
+    ///
+    /// HTTP/2 connections are split into frames. `SETTINGS` are written as
+    /// `id:value` pairs separated by `;`, as in Akamai fingerprints, and
+    /// `WINDOW_UPDATE` as its increment. `DATA` frames are only recorded as
+    /// boundaries, and other payloads are written in hex, such as the HPACK
+    /// blocks of `HEADERS`.
+    ///
+    /// The file holds request and response bodies and credentials, so this
+    /// is meant for debugging only.
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if the file can't be opened.
+    pub fn wire_capture<P: AsRef<Path>>(mut self, path: P) -> ClientBuilder {
+        self.config.wire_capture = Some(path.as_ref().to_owned());
+        self
+    }
+
     // HTTP options
 
     /// Set an optional timeout for idle sockets being kept-alive.
@@ -835,16 +1650,149 @@ impl ClientBuilder {
         self
     }
 
-    /// Send headers as title case instead of lowercase.
-    pub fn http1_title_case_headers(mut self) -> ClientBuilder {
-        self.config.http1_title_case_headers = true;
+    /// Drive connections with `executor` instead of the ambient runtime.
+    ///
+    /// The client spawns a task for each connection, which reads and writes
+    /// it while requests are made. `executor` is passed those tasks and must
+    /// run them to completion, such as on a runtime dedicated to IO.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let io = tokio::runtime::Handle::current();
+    /// let client = rquest::Client::builder()
+    ///     .executor(move |task| {
+    ///         io.spawn(task);
+    ///     })
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn executor<E>(mut self, executor: E) -> ClientBuilder
+    where
+        E: Fn(Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync + 'static,
+    {
+        self.config.executor = Some(Exec(Arc::new(executor)));
         self
     }
 
-    /// Set whether HTTP/1 connections will accept obsolete line folding for
-    /// header values.
+    /// Drive connections on the runtime of `handle` instead of the ambient
+    /// runtime.
     ///
-    /// Newline codepoints (`\r` and `\n`) will be transformed to spaces when
+    /// See [`ClientBuilder::executor`].
+    pub fn runtime_handle(self, handle: tokio::runtime::Handle) -> ClientBuilder {
+        self.executor(move |task| {
+            handle.spawn(task);
+        })
+    }
+
+    /// Limit how many requests this client runs at once.
+    ///
+    /// Further requests wait in a queue until a running one has finished,
+    /// that is until its response body is read to the end or dropped. Queued [`Priority::Foreground`] requests
+    /// start before queued [`Priority::Background`] ones, see
+    /// [`RequestBuilder::priority`]. Time spent waiting counts towards the
+    /// request [`timeout`](ClientBuilder::timeout).
+    ///
+    /// Default is no limit. A limit of 0 fails building the client.
+    pub fn max_in_flight(mut self, max: usize) -> ClientBuilder {
+        self.config.max_in_flight = Some(max);
+        self
+    }
+
+    /// Limit how many requests of the given priority this client runs at once.
+    ///
+    /// This applies on top of [`max_in_flight`](ClientBuilder::max_in_flight),
+    /// for example to keep some slots free for foreground requests by
+    /// capping background ones.
+    ///
+    /// Default is no limit. A limit of 0 fails building the client.
+    pub fn max_in_flight_for(mut self, priority: Priority, max: usize) -> ClientBuilder {
+        self.config.max_in_flight_per_priority[priority as usize] = Some(max);
+        self
+    }
+
+    /// Limit the rate of requests this client sends to each host.
+    ///
+    /// Requests over the limit wait before they are sent, which counts
+    /// towards the request [`timeout`](ClientBuilder::timeout). Redirects
+    /// and retries aren't delayed. See the [`rate_limit`](crate::rate_limit)
+    /// module.
+    ///
+    /// Default is no limit.
+    pub fn rate_limit(mut self, limit: RateLimit) -> ClientBuilder {
+        self.config.rate_limit = Some(limit);
+        self
+    }
+
+    /// Cap the retries of this client at a share of its requests.
+    ///
+    /// A retry over the budget isn't made, and the request fails with the
+    /// error that would have been retried. See the [`retry`](crate::retry)
+    /// module.
+    ///
+    /// Default is no budget, each request being retried up to twice.
+    pub fn retry_budget(mut self, budget: retry::Budget) -> ClientBuilder {
+        self.config.retry_budget = Some(budget);
+        self
+    }
+
+    /// Send requests again as `policy` says, when the server is overloaded
+    /// or the connection fails.
+    ///
+    /// The policy wraps the [layers](ClientBuilder::with_layer) of the
+    /// client, which see each attempt, and the retries it makes count
+    /// against the [`retry_budget`](ClientBuilder::retry_budget). The
+    /// timeouts of a request apply to each attempt. See the
+    /// [`retry`](crate::retry) module.
+    ///
+    /// Default is no policy.
+    pub fn retry_policy(mut self, policy: retry::Policy) -> ClientBuilder {
+        self.config.retry_policy = Some(policy);
+        self
+    }
+
+    /// Wrap the sending of each request of this client with `layer`.
+    ///
+    /// The `tower` layer wraps a [`Next`] service, sending the request
+    /// through the layers set after it, then with the client. Layers set
+    /// first see requests first. See the [`middleware`](crate::middleware)
+    /// module.
+    ///
+    /// An error of the service of the layer fails the request, as a request
+    /// error unless it is an [`Error`](crate::Error) already.
+    pub fn with_layer<L>(mut self, layer: L) -> ClientBuilder
+    where
+        L: Layer<Next> + Send + Sync + 'static,
+        L::Service: tower_service::Service<Request, Response = Response> + Clone + Send + 'static,
+        <L::Service as tower_service::Service<Request>>::Error: Into<error::BoxError>,
+        <L::Service as tower_service::Service<Request>>::Future: Send + 'static,
+    {
+        self.config.layers.push(middleware::wrap(layer));
+        self
+    }
+
+    /// Edit each request of this client before it is sent, and its
+    /// response before it is returned, with `interceptor`.
+    ///
+    /// Interceptors are layers as well, in the order they are set along
+    /// with [`with_layer`](ClientBuilder::with_layer).
+    pub fn with_interceptor<I: Interceptor>(mut self, interceptor: I) -> ClientBuilder {
+        self.config.layers.push(middleware::intercept(interceptor));
+        self
+    }
+
+    /// Send headers as title case instead of lowercase.
+    pub fn http1_title_case_headers(mut self) -> ClientBuilder {
+        self.config.http1_title_case_headers = true;
+        self
+    }
+
+    /// Set whether HTTP/1 connections will accept obsolete line folding for
+    /// header values.
+    ///
+    /// Newline codepoints (`\r` and `\n`) will be transformed to spaces when
     /// parsing.
     pub fn http1_allow_obsolete_multiline_headers_in_responses(
         mut self,
@@ -875,6 +1823,124 @@ impl ClientBuilder {
         self
     }
 
+    /// Set whether the heads of HTTP/1 responses are kept as received.
+    ///
+    /// This lets [`Response::headers_ordered`] list the headers of a
+    /// response in their original order and casing, with repeated ones such
+    /// as `Set-Cookie` interleaved as they were sent, at the cost of a copy
+    /// of each head.
+    ///
+    /// Default is `false`.
+    pub fn http1_preserve_response_headers(mut self, value: bool) -> ClientBuilder {
+        self.config.http1_preserve_response_headers = value;
+        self
+    }
+
+    /// Set whether the head of HTTP/1 requests is written along with their
+    /// body.
+    ///
+    /// Impersonation profiles set this to how their browser writes
+    /// requests, which a later call overrides. It has no effect on HTTP/2
+    /// connections.
+    ///
+    /// ```rust
+    /// use rquest::http1::HeadWrites;
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .http1_head_writes(HeadWrites::Separate)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Default is to write them at once when the body is ready with the
+    /// head.
+    pub fn http1_head_writes(mut self, head_writes: HeadWrites) -> ClientBuilder {
+        self.config.http1_head_writes = Some(head_writes);
+        self
+    }
+
+    /// Set the most bytes each HTTP/1 connection buffers while reading.
+    ///
+    /// The read buffer of a connection grows up to this size when the
+    /// response is read slower than it arrives, so this bounds the memory
+    /// of a client to about `max` for each open connection. A response head
+    /// larger than the buffer fails to parse. Combine it with
+    /// [`max_in_flight`](ClientBuilder::max_in_flight) and
+    /// [`pool_max_idle_per_host`](ClientBuilder::pool_max_idle_per_host) to
+    /// cap the memory of processes running many requests at once.
+    ///
+    /// Default is about 400kB. `max` must be at least 8192.
+    ///
+    /// To cap the buffers of all connections together, see
+    /// [`buffer_memory_limit`](ClientBuilder::buffer_memory_limit).
+    pub fn http1_max_buf_size(mut self, max: usize) -> ClientBuilder {
+        if max < 8192 {
+            self.config.error = Some(crate::error::builder(
+                "http1_max_buf_size must be at least 8192",
+            ));
+        } else {
+            self.config.http1_max_buf_size = Some(max);
+        }
+        self
+    }
+
+    /// Set the most bytes the read buffers of all connections of the client
+    /// may take together.
+    ///
+    /// Each busy connection holds a share of `limit` as large as its read
+    /// buffer may grow, set with
+    /// [`http1_max_buf_size`](ClientBuilder::http1_max_buf_size). A
+    /// connection gives its share back after a second without traffic, such
+    /// as while idle in the pool, and takes it again before writing. New
+    /// connections and writes wait until a share is free, which counts
+    /// against the [`connect_timeout`](ClientBuilder::connect_timeout) when
+    /// connecting.
+    ///
+    /// With the [`Libdeflate`](DecompressionBackend::Libdeflate) backend,
+    /// bodies decompressing to more than `limit` are refused, and the
+    /// buffers compressed bodies are read into are kept for the next bodies
+    /// up to a quarter of `limit`.
+    ///
+    /// By default there is no limit. Building the client fails if `limit`
+    /// is smaller than the read buffer of one connection.
+    pub fn buffer_memory_limit(mut self, limit: usize) -> ClientBuilder {
+        self.config.buffer_memory_limit = Some(limit);
+        #[cfg(feature = "libdeflate")]
+        {
+            self.config.accepts.scratch = Arc::new(Scratch::new(limit));
+        }
+        self
+    }
+
+    /// Set whether HTTP/1 responses are parsed as leniently as browsers do.
+    ///
+    /// This accepts:
+    ///
+    /// - obsolete line folding, as
+    ///   [`http1_allow_obsolete_multiline_headers_in_responses`][Self::http1_allow_obsolete_multiline_headers_in_responses]
+    ///   does,
+    /// - invalid header lines, which are skipped, as
+    ///   [`http1_ignore_invalid_headers_in_responses`][Self::http1_ignore_invalid_headers_in_responses]
+    ///   does,
+    /// - spaces before the colon of a header, as
+    ///   [`http1_allow_spaces_after_header_name_in_responses`][Self::http1_allow_spaces_after_header_name_in_responses]
+    ///   does,
+    /// - runs of spaces and tabs between the parts of the status line, as in
+    ///   `HTTP/1.1  200\tOK`.
+    ///
+    /// Bare `LF` line endings in the status line and headers, and chunk
+    /// extensions, are always accepted. Other malformed responses still fail
+    /// to parse.
+    ///
+    /// Default is `false`.
+    pub fn http1_lenient(mut self, value: bool) -> ClientBuilder {
+        self.config.http1_lenient_status_line = value;
+        self.http1_allow_obsolete_multiline_headers_in_responses(value)
+            .http1_ignore_invalid_headers_in_responses(value)
+            .http1_allow_spaces_after_header_name_in_responses(value)
+    }
+
     /// Only use HTTP/1.
     pub fn http1_only(mut self) -> ClientBuilder {
         self.config.http_version_pref = HttpVersionPref::Http1;
@@ -893,6 +1959,29 @@ impl ClientBuilder {
         self
     }
 
+    /// Use HTTP/2 over cleartext (h2c) with prior knowledge for `http://`
+    /// URLs, while `https://` URLs keep negotiating the version with ALPN.
+    ///
+    /// Connections to `http://` URLs start with the HTTP/2 preface instead
+    /// of HTTP/1.1, with the HTTP/2 settings of the client, including those
+    /// of its impersonation profile. This suits internal services and test
+    /// servers speaking h2c. Connections through proxies are left alone.
+    ///
+    /// ```rust
+    /// # async fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .h2c_prior_knowledge()
+    ///     .build()?;
+    /// let res = client.get("http://internal.service:8080/health").send().await?;
+    /// assert_eq!(res.version(), rquest::Version::HTTP_2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn h2c_prior_knowledge(mut self) -> ClientBuilder {
+        self.config.h2c_prior_knowledge = true;
+        self
+    }
+
     /// Sets the `SETTINGS_INITIAL_WINDOW_SIZE` option for HTTP2 stream-level flow control.
     ///
     /// Default is currently 65,535 but may change internally to optimize for common uses.
@@ -962,6 +2051,57 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets all the HTTP2 settings at once, such as those parsed from the
+    /// fingerprint of a client with [`Http2Settings::from_akamai`].
+    ///
+    /// Settings left unset in `settings` keep the values set before, such as
+    /// those of an impersonation. The `SETTINGS` of connections are sent in the order of the settings,
+    /// followed by their `PRIORITY` frames, so that the HTTP/2 fingerprint
+    /// of connections matches the one the settings were parsed from.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// use rquest::impersonate::Http2Settings;
+    ///
+    /// let settings =
+    ///     Http2Settings::from_akamai("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p")?;
+    /// let client = rquest::Client::builder().http2_settings(settings).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "impersonate")]
+    pub fn http2_settings(mut self, settings: Http2Settings) -> ClientBuilder {
+        let config = &mut self.config;
+        if let Some(size) = settings.initial_stream_window_size() {
+            config.http2_initial_stream_window_size = Some(size);
+        }
+        if let Some(size) = settings.initial_connection_window_size() {
+            config.http2_initial_connection_window_size = Some(size);
+        }
+        if let Some(max) = settings.max_concurrent_streams() {
+            config.http2_max_concurrent_streams = Some(max);
+        }
+        if let Some(size) = settings.max_header_list_size() {
+            config.http2_max_header_list_size = Some(size);
+        }
+        if let Some(size) = settings.header_table_size() {
+            config.http2_header_table_size = Some(size);
+        }
+        if let Some(enabled) = settings.enable_push() {
+            config.http2_enable_push = Some(enabled);
+        }
+        if let Some(size) = settings.max_frame_size() {
+            config.http2_max_frame_size = Some(size);
+        }
+        if let Some(order) = settings.headers_pseudo_order() {
+            config.http2_pseudo_order = Some(order);
+        }
+        config.http2_preface = Some(settings.preface());
+        self
+    }
+
     /// Sets an interval for HTTP2 Ping frames should be sent to keep a connection alive.
     ///
     /// Pass `None` to disable HTTP2 keep-alive.
@@ -995,6 +2135,68 @@ impl ClientBuilder {
         self
     }
 
+    /// Call `callback` with the `SETTINGS`, `PRIORITY`, `WINDOW_UPDATE`,
+    /// `PING` and `GOAWAY` frames of HTTP/2 connections, as they are sent
+    /// and received.
+    ///
+    /// Use this to check the HTTP/2 fingerprint of the client, or to watch
+    /// how servers manage their connections. The callback runs while the
+    /// connection is read or written, so it should return quickly.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rquest::http2::{Direction, Frame};
+    ///
+    /// let client = rquest::Client::builder()
+    ///     .http2_frame_callback(|direction, frame| {
+    ///         if let (Direction::Received, Frame::GoAway { error_code, .. }) = (direction, frame) {
+    ///             eprintln!("server is closing the connection: {}", error_code);
+    ///         }
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn http2_frame_callback<F>(mut self, callback: F) -> ClientBuilder
+    where
+        F: Fn(Direction, &Frame) + Send + Sync + 'static,
+    {
+        self.config.http2_frame_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Remember hosts that reject HTTP/2 and only offer them HTTP/1.1 for `ttl`.
+    ///
+    /// A host is downgraded when it doesn't select h2 during ALPN, or when an
+    /// h2 request fails with `PROTOCOL_ERROR`, `HTTP_1_1_REQUIRED` or
+    /// `INADEQUATE_SECURITY`. In the latter case the failed request is also
+    /// retried.
+    ///
+    /// Default is `None` (disabled).
+    #[cfg(feature = "__boring")]
+    pub fn http2_fallback_ttl(mut self, ttl: impl Into<Option<Duration>>) -> ClientBuilder {
+        self.config.http2_fallback_ttl = ttl.into();
+        self
+    }
+
+    /// Enable or disable honoring `Alt-Svc` response headers.
+    ///
+    /// When enabled, alternative services advertised by an HTTPS origin are
+    /// cached for their `ma` lifetime and new connections to that origin are
+    /// made to the alternative endpoint instead. The TLS handshake still
+    /// targets the origin host. Alternatives for unsupported protocols such
+    /// as `h3` are cached but not used, and so are alternatives given as IP
+    /// addresses, which would skip the resolver and its checks. Hosts set
+    /// with [`resolve`](ClientBuilder::resolve) and requests with
+    /// [`resolve_to`](crate::RequestBuilder::resolve_to) keep connecting
+    /// to their addresses.
+    ///
+    /// Default is `false`.
+    #[cfg(feature = "__boring")]
+    pub fn alt_svc(mut self, enable: bool) -> ClientBuilder {
+        self.config.alt_svc = enable;
+        self
+    }
+
     // TCP options
 
     /// Set whether sockets have `TCP_NODELAY` enabled.
@@ -1040,6 +2242,36 @@ impl ClientBuilder {
         self
     }
 
+    /// Send a HAProxy PROXY protocol header at the start of every connection.
+    ///
+    /// The header is written right after the TCP connection is established,
+    /// before the TLS handshake. It announces `source` as the client address,
+    /// or the local address of the socket if `None`, and the connected
+    /// address as the destination. Connections made through a `Proxy` don't
+    /// send it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rquest::ProxyProtocol;
+    /// use std::net::SocketAddr;
+    /// let source = SocketAddr::from(([203, 0, 113, 7], 0));
+    /// let client = rquest::Client::builder()
+    ///     .proxy_protocol(ProxyProtocol::V2, source)
+    ///     .build().unwrap();
+    /// ```
+    #[cfg(feature = "__boring")]
+    pub fn proxy_protocol<S>(mut self, version: ProxyProtocol, source: S) -> ClientBuilder
+    where
+        S: Into<Option<SocketAddr>>,
+    {
+        self.config.proxy_protocol = Some(ProxyHeader {
+            version,
+            source: source.into(),
+        });
+        self
+    }
+
     /// Set that all sockets have `SO_KEEPALIVE` set with the supplied duration.
     ///
     /// If `None`, the option will not be set.
@@ -1094,6 +2326,83 @@ impl ClientBuilder {
         self
     }
 
+    /// Trust the root certificates of the platform's certificate store, as
+    /// well as those the profile trusts.
+    ///
+    /// The store is the system keychain on macOS, the `ROOT` store of
+    /// Windows, and the CA bundle of the distribution elsewhere, so that
+    /// the CAs an enterprise deploys to its machines are trusted. It is
+    /// loaded when the client is built.
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if the store can't be read.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `native-roots` feature to be enabled.
+    #[cfg(feature = "native-roots")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "native-roots")))]
+    pub fn tls_native_roots(mut self) -> ClientBuilder {
+        self.config.tls_native_roots = true;
+        self
+    }
+
+    /// Trust `cert` as a root certificate, as well as those the profile
+    /// trusts.
+    ///
+    /// This suits a private CA, or the CA of a proxy intercepting TLS for
+    /// debugging, while certificates are still verified.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pem = std::fs::read("mitmproxy-ca-cert.pem")?;
+    /// let client = rquest::Client::builder()
+    ///     .add_root_certificate(rquest::tls::Certificate::from_pem(&pem)?)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "__boring")]
+    pub fn add_root_certificate(mut self, cert: Certificate) -> ClientBuilder {
+        self.config.root_certs.push(cert);
+        self
+    }
+
+    /// Accept connections to `host` only from a server whose certificate
+    /// chain has a public key with the SHA-256 digest `sha256_of_spki`.
+    ///
+    /// The digest is that of the DER `SubjectPublicKeyInfo`, as
+    /// [`Certificate::public_key_sha256`] computes. Pinning `host` several
+    /// times accepts any of the keys, so that a backup key can be pinned
+    /// before the server rotates to it. The chain is still verified, and
+    /// the pins aren't checked if
+    /// [`danger_accept_invalid_certs`](ClientBuilder::danger_accept_invalid_certs)
+    /// is set. `host` is compared to the host of the URL as it is, without
+    /// matching subdomains.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pem = std::fs::read("api.example.com.pem")?;
+    /// let pin = rquest::tls::Certificate::from_pem(&pem)?.public_key_sha256()?;
+    /// let client = rquest::Client::builder()
+    ///     .pin_server_certificate("api.example.com", pin)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "__boring")]
+    pub fn pin_server_certificate(mut self, host: &str, sha256_of_spki: [u8; 32]) -> ClientBuilder {
+        self.config
+            .cert_pins
+            .push((host.to_owned(), sha256_of_spki));
+        self
+    }
+
     /// Controls the use of certificate validation.
     ///
     /// Defaults to `false`.
@@ -1206,6 +2515,8 @@ impl ClientBuilder {
 
     /// Add TLS information as `TlsInfo` extension to responses.
     ///
+    /// Read it with [`Response::tls_info`].
+    ///
     /// # Optional
     ///
     /// This requires the optional `default-tls`, `native-tls`, or `rustls-tls(-...)`
@@ -1224,6 +2535,66 @@ impl ClientBuilder {
         self
     }
 
+    /// Set how the Client treats hosts that may be IDN homograph spoofs.
+    ///
+    /// An internationalized host that mixes scripts, like `aрple.com` with a
+    /// Cyrillic `р`, or that only uses letters looking like Latin ones, like
+    /// `аррӏе.com`, is checked before connecting, including on redirects.
+    /// Use [`HomographPolicy::Reject`] when fetching user-supplied URLs.
+    ///
+    /// Defaults to [`HomographPolicy::Allow`].
+    pub fn homograph_policy(mut self, policy: HomographPolicy) -> ClientBuilder {
+        self.config.homograph_policy = policy;
+        self
+    }
+
+    /// Reject connections to private, loopback and link-local addresses.
+    ///
+    /// When enabled, requests fail if their host is, or resolves to, an
+    /// address in a private (RFC 1918), shared, loopback, link-local (such
+    /// as the `169.254.169.254` cloud metadata endpoint), multicast or
+    /// otherwise reserved range. Hosts are resolved again for every new
+    /// connection, including on redirects, so DNS rebinding is caught as
+    /// well. This protects servers fetching untrusted URLs against SSRF.
+    ///
+    /// Proxies are resolved the same way, so a proxy on a private address
+    /// can't be used in this mode. Addresses set with
+    /// [`resolve`](ClientBuilder::resolve) are trusted.
+    ///
+    /// Defaults to false.
+    pub fn deny_private_ips(mut self, enabled: bool) -> ClientBuilder {
+        self.config.deny_private_ips = enabled;
+        self
+    }
+
+    /// Set an [`egress::Policy`] restricting the destinations of requests.
+    ///
+    /// The policy is checked with the URL of every request and redirect,
+    /// and with the addresses its host resolves to for every new
+    /// connection. Proxies are resolved the same way, so they must be
+    /// allowed by the policy as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rquest::egress::{Policy, Rule};
+    ///
+    /// # fn run() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .egress_policy(Policy::allow([
+    ///         Rule::new().scheme("https").host("*.example.com"),
+    ///     ]))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Default is no policy.
+    pub fn egress_policy(mut self, policy: egress::Policy) -> ClientBuilder {
+        self.config.egress_policy = Some(Arc::new(policy));
+        self
+    }
+
     /// Enables the [hickory-dns](hickory-dns) async resolver instead of a default threadpool using `getaddrinfo`.
     ///
     /// If the `hickory-dns` feature is turned on, the default option is enabled.
@@ -1238,6 +2609,23 @@ impl ClientBuilder {
         self
     }
 
+    /// Enables the hickory-dns async resolver, tuned with `config`.
+    ///
+    /// This allows adding search domains, setting `ndots`, rotating across
+    /// nameservers and overriding the system nameservers for this client
+    /// only.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `hickory-dns` feature to be enabled
+    #[cfg(feature = "hickory-dns")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "hickory-dns")))]
+    pub fn hickory_dns_config(mut self, config: HickoryConfig) -> ClientBuilder {
+        self.config.hickory_dns = true;
+        self.config.hickory_dns_config = Some(config);
+        self
+    }
+
     /// Disables the hickory-dns async resolver.
     ///
     /// This method exists even if the optional `hickory-dns` feature is not enabled.
@@ -1284,17 +2672,186 @@ impl ClientBuilder {
 
     /// Override the DNS resolver implementation.
     ///
-    /// Pass an `Arc` wrapping a trait object implementing `Resolve`.
-    /// Overrides for specific names passed to `resolve` and `resolve_to_addrs` will
-    /// still be applied on top of this resolver.
-    pub fn dns_resolver<R: Resolve + 'static>(mut self, resolver: Arc<R>) -> ClientBuilder {
-        self.config.dns_resolver = Some(resolver as _);
+    /// Pass an `Arc` wrapping a type implementing `Resolve`, or an
+    /// `Arc<dyn Resolve>`. Overrides for specific names passed to `resolve`
+    /// and `resolve_to_addrs` will still be applied on top of this resolver.
+    ///
+    /// # Example
+    ///
+    /// A resolver pinning a domain to its own addresses, rotating through
+    /// them, and leaving the rest to the system resolver with `dns_fallback`:
+    ///
+    /// ```rust
+    /// use std::net::SocketAddr;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// use rquest::dns::{Addrs, Fallback, Name, Resolve, Resolving};
+    ///
+    /// struct RoundRobin {
+    ///     addrs: Vec<SocketAddr>,
+    ///     next: AtomicUsize,
+    /// }
+    ///
+    /// impl Resolve for RoundRobin {
+    ///     fn resolve(&self, name: Name) -> Resolving {
+    ///         let addrs: Result<Addrs, _> = if name.as_str() == "api.example.com" {
+    ///             let start = self.next.fetch_add(1, Ordering::Relaxed);
+    ///             let mut addrs = self.addrs.clone();
+    ///             addrs.rotate_left(start % addrs.len());
+    ///             Ok(Box::new(addrs.into_iter()))
+    ///         } else {
+    ///             Err("not pinned".into())
+    ///         };
+    ///         Box::pin(std::future::ready(addrs))
+    ///     }
+    /// }
+    ///
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let resolver: Arc<dyn Resolve> = Arc::new(RoundRobin {
+    ///     addrs: vec!["10.0.0.1:0".parse().unwrap(), "10.0.0.2:0".parse().unwrap()],
+    ///     next: AtomicUsize::new(0),
+    /// });
+    /// let client = rquest::Client::builder()
+    ///     .dns_resolver(resolver)
+    ///     .dns_fallback(Fallback::CustomThenSystem)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dns_resolver<R: IntoResolve>(mut self, resolver: R) -> ClientBuilder {
+        self.config.dns_resolver = Some(resolver.into_resolve());
+        self
+    }
+
+    /// Set a timeout for each DNS lookup.
+    ///
+    /// A lookup taking longer fails with a timeout error, or falls back to
+    /// the other resolver as set with `dns_fallback`.
+    ///
+    /// Default is no timeout.
+    pub fn dns_timeout(mut self, timeout: Duration) -> ClientBuilder {
+        self.config.dns_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how the resolver set with `dns_resolver` combines with the
+    /// system one.
+    ///
+    /// By default, the custom resolver replaces the system one. Which of
+    /// them answered is available from
+    /// [`Response::resolved_by`](crate::Response::resolved_by).
+    pub fn dns_fallback(mut self, fallback: dns::Fallback) -> ClientBuilder {
+        self.config.dns_fallback = fallback;
+        self
+    }
+
+    /// Resolve names with DNS-over-HTTPS, querying the server at `url`.
+    ///
+    /// This replaces the system resolver, so that lookups don't leak to the
+    /// resolver of the network. Queries are sent with the TLS and HTTP/2
+    /// fingerprints of the client and through its proxies, in the wire
+    /// format of RFC 8484 and padded like browsers pad them. Answers are
+    /// cached for their TTL, an hour at most, and names under `localhost`
+    /// resolve to the loopback addresses without a query.
+    ///
+    /// The host of `url` is itself resolved by the system resolver, unless
+    /// its addresses are set with `dns_over_https_bootstrap`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .dns_over_https("https://1.1.1.1/dns-query")
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Building the client fails if `url` isn't an https URL.
+    #[cfg(feature = "__boring")]
+    pub fn dns_over_https<U: IntoUrl>(mut self, url: U) -> ClientBuilder {
+        match url.into_url() {
+            Ok(url) if url.scheme() == "https" => self.config.dns_over_https = Some(url),
+            Ok(url) => {
+                self.config.error = Some(crate::error::builder(format!(
+                    "DNS-over-HTTPS needs an https URL, got {}",
+                    url
+                )))
+            }
+            Err(err) => self.config.error = Some(err),
+        }
+        self
+    }
+
+    /// Set the addresses of the DNS-over-HTTPS server, instead of looking
+    /// its host up with the system resolver.
+    ///
+    /// ```rust
+    /// # fn doc() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::builder()
+    ///     .dns_over_https("https://dns.google/dns-query")
+    ///     .dns_over_https_bootstrap(&["8.8.8.8".parse().unwrap(), "8.8.4.4".parse().unwrap()])
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "__boring")]
+    pub fn dns_over_https_bootstrap(mut self, addrs: &[IpAddr]) -> ClientBuilder {
+        self.config.dns_over_https_bootstrap = addrs.to_vec();
+        self
+    }
+
+    /// Remember hosts whose IPv6 or IPv4 addresses keep failing, and try
+    /// their addresses of the other family first for `ttl`.
+    ///
+    /// A host resolving to both families is connected to with the family
+    /// of its first address, then the other one. After two connections in
+    /// a row had to fall back, the other family goes first until the TTL
+    /// elapses, so that networks with broken IPv6 don't wait for it on
+    /// every connection.
+    ///
+    /// Default is 10 minutes. Pass `None` to disable.
+    pub fn ip_family_cache_ttl(mut self, ttl: impl Into<Option<Duration>>) -> ClientBuilder {
+        self.config.ip_family_ttl = ttl.into();
+        self
+    }
+
+    /// Run a script to compute the headers of each request.
+    ///
+    /// The script sees the headers the request would be sent with and
+    /// returns the ones to set or remove, such as signatures generated by
+    /// a site's JavaScript. It runs once per request, not for redirects.
+    /// See the [`script`](crate::script) module.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `scripting` feature to be enabled.
+    #[cfg(feature = "scripting")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "scripting")))]
+    pub fn script_hook(mut self, hook: ScriptHook) -> ClientBuilder {
+        self.config.script_hook = Some(Arc::new(hook));
         self
     }
 }
 
 type HyperClient = hyper::Client<Connector, super::body::ImplStream>;
 
+type BoxTask = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The executor of [`ClientBuilder::executor`].
+#[derive(Clone)]
+struct Exec(Arc<dyn Fn(BoxTask) + Send + Sync>);
+
+impl hyper::rt::Executor<BoxTask> for Exec {
+    fn execute(&self, task: BoxTask) {
+        (self.0)(task)
+    }
+}
+
 impl Default for Client {
     fn default() -> Self {
         Self::new()
@@ -1376,6 +2933,161 @@ impl Client {
         self.request(Method::HEAD, url)
     }
 
+    /// Convenience method to make a WebDAV `PROPFIND` request to a URL.
+    ///
+    /// The properties to find are usually sent as an XML body, with a
+    /// `Depth` header.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn propfind<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("PROPFIND", url)
+    }
+
+    /// Convenience method to make a WebDAV `MKCOL` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn mkcol<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("MKCOL", url)
+    }
+
+    /// Convenience method to make a WebDAV `REPORT` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn report<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("REPORT", url)
+    }
+
+    /// Convenience method to make a `PURGE` request to a URL, which caches
+    /// such as Varnish or Fastly take to evict it.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    pub fn purge<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("PURGE", url)
+    }
+
+    /// Start building a `Request` with the method named `method` and the
+    /// `Url`.
+    ///
+    /// This allows methods that [`Method`] has no constant for, such as
+    /// `SUBSCRIBE` or `LINK`. Names are case-sensitive and sent as they are
+    /// given, except for `DELETE`, `GET`, `HEAD`, `OPTIONS`, `POST` and
+    /// `PUT`, which are uppercased as browsers do.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run(client: &rquest::Client) {
+    /// let req = client.method("SUBSCRIBE", "http://localhost/events");
+    /// # drop(req);
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if `method` isn't a valid method name, or
+    /// whenever the supplied `Url` cannot be parsed.
+    pub fn method<U: IntoUrl>(&self, method: &str, url: U) -> RequestBuilder {
+        match crate::util::parse_method(method) {
+            Ok(method) => self.request(method, url),
+            Err(err) => RequestBuilder::new(self.clone(), Err(err)),
+        }
+    }
+
+    /// Start a websocket handshake with a `ws://` or `wss://` URL.
+    ///
+    /// The handshake is sent like any other request of this client, so a
+    /// `wss://` connection has its TLS fingerprint. As websockets are opened
+    /// over HTTP/1.1, build the client with
+    /// [`impersonate_websocket`](ClientBuilder::impersonate_websocket).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// use rquest::impersonate::Impersonate;
+    ///
+    /// let client = rquest::Client::builder()
+    ///     .impersonate_websocket(Impersonate::Chrome127)
+    ///     .build()?;
+    /// let websocket = client
+    ///     .websocket("wss://echo.websocket.org")
+    ///     .send()
+    ///     .await?
+    ///     .into_websocket()
+    ///     .await?;
+    /// # drop(websocket);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the supplied `Url` cannot be parsed.
+    #[cfg(feature = "websocket")]
+    pub fn websocket<U: IntoUrl>(&self, url: U) -> crate::WebSocketRequestBuilder {
+        self.get(url).upgrade()
+    }
+
+    /// Check that the server of `origin` answers, timing each phase of a
+    /// minimal request.
+    ///
+    /// An `OPTIONS` request is sent for `origin`, on a pooled connection if
+    /// there is one, or else on a new connection which is then pooled. It
+    /// goes through the proxies of the client, like any other request, so
+    /// that targets and proxies are checked the same way as the requests
+    /// relying on them are sent.
+    ///
+    /// Any response counts, whatever its status, and redirects are followed
+    /// as the client follows them.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let client = rquest::Client::new();
+    /// let ping = client.ping("https://example.com").await?;
+    /// if let Some(connection) = ping.connection() {
+    ///     println!("dns {:?}, tls {:?}", connection.dns(), connection.tls());
+    /// }
+    /// println!("{} in {:?}", ping.status(), ping.total());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if the request fails, as
+    /// [`RequestBuilder::send`] would.
+    pub async fn ping<U: IntoUrl>(&self, origin: U) -> crate::Result<Ping> {
+        let started = Instant::now();
+        let res = self.request(Method::OPTIONS, origin).send().await?;
+        let head = started.elapsed();
+
+        let connection = res
+            .connect_timings()
+            .filter(|timings| timings.started_after(started));
+        let status = res.status();
+        let version = res.version();
+        // Read the body, so that the connection goes back to the pool.
+        res.bytes().await?;
+
+        Ok(Ping {
+            status,
+            version,
+            first_byte: head.saturating_sub(connection.map_or(Duration::ZERO, |c| c.total())),
+            total: started.elapsed(),
+            connection,
+        })
+    }
+
     /// Start building a `Request` with the `Method` and `Url`.
     ///
     /// Returns a `RequestBuilder`, which will allow setting headers and
@@ -1385,10 +3097,26 @@ impl Client {
     ///
     /// This method fails whenever the supplied `Url` cannot be parsed.
     pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
-        let req = url.into_url().map(move |url| Request::new(method, url));
+        let req = into_url_with_base(url, self.inner.base_url.as_ref())
+            .map(move |url| Request::new(method, url));
         RequestBuilder::new(self.clone(), req)
     }
 
+    /// Start building a `RequestTemplate` for requests relative to `base`.
+    ///
+    /// A relative `base` is itself relative to the client's base URL.
+    ///
+    /// See [`RequestTemplate`] for an example.
+    ///
+    /// # Errors
+    ///
+    /// The requests of the template fail whenever the supplied `Url` cannot
+    /// be parsed.
+    pub fn request_template<U: IntoUrl>(&self, base: U) -> RequestTemplate {
+        let base = into_url_with_base(base, self.inner.base_url.as_ref());
+        RequestTemplate::new(self.clone(), base)
+    }
+
     /// Executes a `Request`.
     ///
     /// A `Request` can be built manually with `Request::new()` or obtained
@@ -1408,20 +3136,239 @@ impl Client {
         self.execute_request(request)
     }
 
-    pub(super) fn execute_request(&self, req: Request) -> Pending {
-        let (method, url, mut headers, body, timeout, version) = req.pieces();
+    /// Open a [`Scope`] whose requests are aborted once it is dropped.
+    ///
+    /// The scope shares this client's configuration and connection pool.
+    pub fn scope(&self) -> Scope {
+        Scope::new(self)
+    }
+
+    pub(super) fn scope_state(&self) -> Option<&Arc<ScopeState>> {
+        self.scope.as_ref()
+    }
+
+    pub(super) fn with_scope(&self, scope: Arc<ScopeState>) -> Client {
+        Client {
+            inner: self.inner.clone(),
+            scope: Some(scope),
+        }
+    }
+
+    /// The timeouts of requests that don't override them.
+    pub(super) fn timeouts(&self) -> Timeouts {
+        Timeouts::new(self.inner.request_timeout, self.inner.read_timeout)
+    }
+
+    pub(super) fn execute_request(&self, mut req: Request) -> Pending {
+        if let Some(ref base_url) = self.inner.base_url {
+            if !self.inner.default_query.is_empty() && req.url().origin() == base_url.origin() {
+                req.append_default_query(&self.inner.default_query);
+            }
+        }
+        let middleware = match self.inner.middleware {
+            Some(ref middleware) => middleware,
+            None => return self.execute_direct(req),
+        };
+        // The middleware sends past it outside of any scope, so the request
+        // is in the scope of this client as it goes through the middleware.
+        let url = req.url().clone();
+        let scope = match self.scope {
+            Some(ref state) => match state.enter_request() {
+                Some(guard) => Some(guard),
+                None => return Pending::new_err(error::request(error::Aborted).with_url(url)),
+            },
+            None => None,
+        };
+        let client = self.inner.clone();
+        let mut sending = middleware.send(req);
+        let pending = future::poll_fn(move |cx| {
+            // The middleware only has a weak reference to the client.
+            let _client = &client;
+            if let Some(ref scope) = scope {
+                if scope.poll_aborted(cx) {
+                    return Poll::Ready(Err(error::request(error::Aborted).with_url(url.clone())));
+                }
+            }
+            sending.as_mut().poll(cx)
+        });
+        Pending {
+            inner: PendingInner::Middleware(Box::pin(pending)),
+        }
+    }
+
+    /// Send `req`, past the middleware of the client.
+    pub(crate) fn execute_direct(&self, req: Request) -> Pending {
+        #[cfg(feature = "cookies")]
+        let cookie_store = req
+            .cookie_store()
+            .unwrap_or_else(|| self.inner.cookie_store.clone());
+        let Pieces {
+            method,
+            url,
+            mut headers,
+            body,
+            timeout,
+            read_timeout,
+            version,
+            proxy,
+            proxy_tag,
+            proxy_session,
+            priority,
+            accept_encoding,
+            header_order,
+            impersonate,
+            connection,
+            resolve_to,
+        } = req.pieces();
         if url.scheme() != "http" && url.scheme() != "https" {
             return Pending::new_err(error::url_bad_scheme(url));
         }
 
-        // check if we're in https_only mode and check the scheme of the current URL
-        if self.inner.https_only && url.scheme() != "https" {
-            return Pending::new_err(error::url_bad_scheme(url));
+        let scope = match self.scope {
+            Some(ref state) => match state.enter_request() {
+                Some(guard) => Some(guard),
+                None => return Pending::new_err(error::request(error::Aborted).with_url(url)),
+            },
+            None => None,
+        };
+
+        let pooled = connection == ConnectionMode::Reuse;
+        let proxied = proxy.is_some() || proxy_tag.is_some() || proxy_session.is_some();
+        if proxied && resolve_to.is_some() {
+            let err = error::builder("resolve_to can't be combined with a proxy");
+            return Pending::new_err(err.with_url(url));
+        }
+        if let Some(addr) = resolve_to {
+            if let Err(err) = self.check_resolve_to(&url, addr) {
+                return Pending::new_err(err.with_url(url));
+            }
+        }
+        let hyper = if let Some(ref proxy) = proxy {
+            if proxy_tag.is_some() || proxy_session.is_some() {
+                let err = error::builder("proxy can't be combined with a proxy tag or session");
+                return Pending::new_err(err.with_url(url));
+            }
+            self.inner
+                .proxy_routes
+                .client_through(proxy.intercept(&expect_uri(&url)), pooled)
+        } else if proxy_tag.is_some() || proxy_session.is_some() {
+            match self.route_for(proxy_tag.as_deref(), proxy_session.as_deref(), pooled) {
+                Ok(hyper) => hyper,
+                Err(err) => return Pending::new_err(err.with_url(url)),
+            }
+        } else if let (Some(addr), Some(host)) = (resolve_to, url.host_str()) {
+            self.inner.dns_routes.client_for(host, addr, pooled)
+        } else if self.inner.proxies_custom {
+            // A function may pick another proxy each time it is asked, so
+            // it is asked once, and the request is sent through, and
+            // authenticated with, the proxy it picked.
+            let uri = expect_uri(&url);
+            let scheme = self
+                .inner
+                .hyper
+                .get_proxies()
+                .iter()
+                .find_map(|proxy| proxy.intercept(&uri));
+            self.inner.proxy_routes.client_through(scheme, pooled)
+        } else if pooled {
+            self.inner.hyper.clone()
+        } else {
+            self.inner.unpooled.clone()
+        };
+
+        #[cfg(feature = "impersonate")]
+        let (hyper, profile) = match impersonate {
+            Some(_) if proxied => {
+                let err = error::builder("impersonate can't be combined with a proxy");
+                return Pending::new_err(err.with_url(url));
+            }
+            Some(_) if resolve_to.is_some() => {
+                let err = error::builder("impersonate can't be combined with resolve_to");
+                return Pending::new_err(err.with_url(url));
+            }
+            Some(impersonate) => match self.inner.profile_routes.route(impersonate) {
+                Ok(route) if pooled => (route.hyper.clone(), Some(route)),
+                Ok(route) => (route.unpooled.clone(), Some(route)),
+                Err(err) => return Pending::new_err(err.with_url(url)),
+            },
+            None => (hyper, None),
+        };
+        #[cfg(not(feature = "impersonate"))]
+        let _ = (impersonate, proxied);
+
+        if connection == ConnectionMode::Close && version != Version::HTTP_2 {
+            headers
+                .entry(CONNECTION)
+                .or_insert_with(|| HeaderValue::from_static("close"));
+        }
+
+        // check if we're in https_only mode and check the scheme of the current URL
+        if self.inner.https_only && url.scheme() != "https" {
+            return Pending::new_err(error::url_bad_scheme(url));
+        }
+
+        if let Err(err) = self.inner.homograph_policy.check(&url) {
+            return Pending::new_err(error::request(err).with_url(url));
+        }
+
+        if self.inner.deny_private_ips {
+            if let Err(err) = private::check_url(&url) {
+                return Pending::new_err(error::request(err).with_url(url));
+            }
+        }
+
+        if let Some(ref policy) = self.inner.egress_policy {
+            if let Err(err) = policy.check_url(&url) {
+                return Pending::new_err(error::request(err).with_url(url));
+            }
         }
 
+        // A request-level `accept_encoding` goes before the default headers,
+        // to override their `Accept-Encoding`.
+        let accepts = match accept_encoding {
+            Some(encodings) => {
+                let encodings = encodings
+                    .into_iter()
+                    .filter(ContentEncoding::is_supported)
+                    .collect::<Vec<_>>();
+                if !headers.contains_key(ACCEPT_ENCODING) && !headers.contains_key(RANGE) {
+                    let value = if encodings.is_empty() {
+                        "identity".to_owned()
+                    } else {
+                        encodings
+                            .iter()
+                            .map(ContentEncoding::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    headers.insert(
+                        ACCEPT_ENCODING,
+                        HeaderValue::from_str(&value).expect("valid encoding tokens"),
+                    );
+                }
+                #[allow(unused_mut)]
+                let mut accepts = Accepts::from_encodings(&encodings);
+                #[cfg(feature = "libdeflate")]
+                {
+                    accepts.backend = self.inner.accepts.backend;
+                    accepts.scratch = self.inner.accepts.scratch.clone();
+                }
+                accepts
+            }
+            None => self.inner.accepts.clone(),
+        };
+        let explicit_accept_encoding = headers.contains_key(ACCEPT_ENCODING);
+
+        #[cfg(feature = "impersonate")]
+        let default_headers = profile
+            .as_ref()
+            .map_or(&*self.inner.headers, |route| &*route.headers);
+        #[cfg(not(feature = "impersonate"))]
+        let default_headers = &*self.inner.headers;
+
         // insert default headers in the request headers
         // without overwriting already appended headers.
-        for (key, value) in &self.inner.headers {
+        for (key, value) in default_headers {
             if let Entry::Vacant(entry) = headers.entry(key) {
                 entry.insert(value.clone());
             }
@@ -1430,40 +3377,50 @@ impl Client {
         // Add cookies from the cookie store.
         #[cfg(feature = "cookies")]
         {
-            if let Some(cookie_store) = self.inner.cookie_store.as_ref() {
+            if let Some(cookie_store) = cookie_store.as_ref() {
                 if headers.get(crate::header::COOKIE).is_none() {
                     add_cookie_header(&mut headers, &**cookie_store, &url);
                 }
             }
         }
 
-        let accept_encoding = self.inner.accepts.as_str();
-
-        if let Some(accept_encoding) = accept_encoding {
-            if !headers.contains_key(ACCEPT_ENCODING) && !headers.contains_key(RANGE) {
-                headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(accept_encoding));
+        if !headers.contains_key(RANGE) {
+            match headers.get(ACCEPT_ENCODING) {
+                // Advertise the registered codings along with the ones of
+                // the default headers, such as those of a profile.
+                Some(value) if !explicit_accept_encoding => {
+                    if let Some(value) = accepts.append_custom(value) {
+                        headers.insert(ACCEPT_ENCODING, value);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    if let Some(value) = accepts.header_value() {
+                        headers.insert(ACCEPT_ENCODING, value);
+                    }
+                }
             }
         }
 
-        // Insert headers in order if enabled
-        if let Some(ref headers_order) = self.inner.headers_order {
-            let mut sorted_headers = HeaderMap::with_capacity(headers.keys_len());
-
-            // First insert headers in order
-            for key in headers_order {
-                if let Some(value) = headers.get(key) {
-                    sorted_headers.insert(key, value.clone());
-                }
+        #[cfg(feature = "scripting")]
+        if let Some(ref hook) = self.inner.script_hook {
+            let bytes = body.as_ref().and_then(Body::as_bytes);
+            if let Err(err) = hook.run(&method, &url, &mut headers, bytes) {
+                return Pending::new_err(err.with_url(url));
             }
+        }
 
-            // Then insert any remaining headers
-            for (name, value) in headers.iter() {
-                if !sorted_headers.contains_key(name) {
-                    sorted_headers.insert(name, value.clone());
-                }
-            }
+        #[cfg(feature = "impersonate")]
+        let default_order = match profile {
+            Some(ref route) => Some(&route.order[..]),
+            None => self.inner.headers_order.as_deref(),
+        };
+        #[cfg(not(feature = "impersonate"))]
+        let default_order = self.inner.headers_order.as_deref();
 
-            headers = sorted_headers;
+        // Insert headers in order if enabled
+        if let Some(order) = header_order.as_deref().or(default_order) {
+            headers = sort_headers(headers, order);
         }
 
         let uri = expect_uri(&url);
@@ -1476,7 +3433,9 @@ impl Client {
             None => (None, Body::empty()),
         };
 
-        self.proxy_auth(&uri, &mut headers);
+        let maybe_http_auth = self.inner.proxies_maybe_http_auth
+            || proxy.as_ref().map_or(false, Proxy::maybe_has_http_auth);
+        self.proxy_auth(&hyper, &uri, &mut headers, maybe_http_auth);
 
         let builder = hyper::Request::builder()
             .method(method.clone())
@@ -1488,13 +3447,34 @@ impl Client {
                 .body(body.into_stream())
                 .expect("valid request parts");
             *req.headers_mut() = headers.clone();
-            ResponseFuture::Default(self.inner.hyper.request(req))
+            ResponseFuture::Default(hyper.request(req))
         };
 
         let timeout = timeout
             .or(self.inner.request_timeout)
             .map(tokio::time::sleep)
             .map(Box::pin);
+        let read_timeout = read_timeout.or(self.inner.read_timeout);
+
+        let throttle = self
+            .inner
+            .rate_limit
+            .as_ref()
+            .zip(url.host_str())
+            .map(|(limit, host)| limit.reserve(host))
+            .filter(|wait| !wait.is_zero())
+            .map(tokio::time::sleep)
+            .map(Box::pin);
+
+        if let Some(ref budget) = self.inner.retry_budget {
+            budget.deposit();
+        }
+
+        let queued = self
+            .inner
+            .in_flight
+            .as_ref()
+            .map(|limit| limit.acquire(priority));
 
         Pending {
             inner: PendingInner::Request(PendingRequest {
@@ -1503,16 +3483,80 @@ impl Client {
                 headers,
                 body: reusable,
                 urls: Vec::new(),
+                history: History::default(),
                 retry_count: 0,
                 client: self.inner.clone(),
+                #[cfg(feature = "cookies")]
+                cookie_store,
+                hyper,
+                accepts,
                 in_flight,
                 timeout,
+                read_timeout,
+                read_deadline: None,
+                scope,
+                sent: None,
+                throttle,
+                queued,
+                permit: None,
             }),
         }
     }
 
-    fn proxy_auth(&self, dst: &Uri, headers: &mut HeaderMap) {
-        if !self.inner.proxies_maybe_http_auth {
+    /// The hyper client for the proxy of the pool bound to `session`, or
+    /// else the next one tagged with `tag`.
+    fn route_for(
+        &self,
+        tag: Option<&str>,
+        session: Option<&str>,
+        pooled: bool,
+    ) -> crate::Result<HyperClient> {
+        let routes = &self.inner.proxy_routes;
+        let pool = routes
+            .pool
+            .as_ref()
+            .ok_or_else(|| error::builder("proxy_tag and proxy_session require a ProxyPool"))?;
+        let proxy = match (session, tag) {
+            (Some(session), tag) => pool.select_session(session, tag),
+            (None, Some(tag)) => pool.select_tagged(tag),
+            (None, None) => pool.select(),
+        };
+        let proxy = proxy.ok_or_else(|| match tag {
+            Some(tag) => error::builder(format!("no proxy tagged {:?}", tag)),
+            None => error::builder("the proxy pool is empty"),
+        })?;
+        routes.client_for(proxy, pooled)
+    }
+
+    /// Check the address set with `RequestBuilder::resolve_to`, which is
+    /// connected to without going through the resolvers of the client, and
+    /// so without their checks.
+    fn check_resolve_to(&self, url: &Url, addr: IpAddr) -> crate::Result<()> {
+        // A proxy of the client would resolve the host itself.
+        let uri = expect_uri(url);
+        let proxies = self.inner.hyper.get_proxies();
+        if proxies.iter().any(|proxy| proxy.intercept(&uri).is_some()) {
+            return Err(error::builder("resolve_to can't be combined with a proxy"));
+        }
+
+        if self.inner.deny_private_ips {
+            private::check_addr(addr).map_err(error::request)?;
+        }
+        if let Some(ref policy) = self.inner.egress_policy {
+            let host = url.host_str().unwrap_or_default();
+            policy.check_addr(host, addr).map_err(error::request)?;
+        }
+        Ok(())
+    }
+
+    fn proxy_auth(
+        &self,
+        hyper: &HyperClient,
+        dst: &Uri,
+        headers: &mut HeaderMap,
+        maybe_http_auth: bool,
+    ) {
+        if !maybe_http_auth {
             return;
         }
 
@@ -1527,7 +3571,7 @@ impl Client {
             return;
         }
 
-        for proxy in self.inner.hyper.get_proxies().iter() {
+        for proxy in hyper.get_proxies().iter() {
             if proxy.is_match(dst) {
                 if let Some(header) = proxy.http_basic_auth(dst) {
                     headers.insert(PROXY_AUTHORIZATION, header);
@@ -1544,6 +3588,32 @@ impl Client {
         self.inner.headers.get(USER_AGENT)
     }
 
+    /// Get the cookie jar of the client, if its cookie store is a
+    /// [`Jar`](cookie::Jar).
+    ///
+    /// This is the case with `cookie_store(true)`, or a `Jar` passed to
+    /// `cookie_provider`, so that its cookies can be saved and loaded.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = rquest::Client::builder().cookie_store(true).build()?;
+    ///
+    /// // ... make requests ...
+    ///
+    /// if let Some(jar) = client.cookie_jar() {
+    ///     jar.save_netscape(std::fs::File::create("cookies.txt")?)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "cookies")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookies")))]
+    pub fn cookie_jar(&self) -> Option<&Arc<cookie::Jar>> {
+        self.inner.cookie_jar.as_ref()
+    }
+
     /// Returns a `String` of the header-value of all `Cookie` in a `Url`.
     ///
     /// # Errors
@@ -1684,10 +3754,27 @@ impl Config {
             f.field("proxies", &self.proxies);
         }
 
+        if let Some(ref pool) = self.proxy_pool {
+            f.field("proxy_pool", pool);
+        }
+
         if !self.redirect_policy.is_default() {
             f.field("redirect_policy", &self.redirect_policy);
         }
 
+        if let Some(ref base_url) = self.base_url {
+            f.field("base_url", base_url);
+        }
+
+        if !self.default_query.is_empty() {
+            f.field("default_query", &self.default_query);
+        }
+
+        #[cfg(feature = "__boring")]
+        if let Some(ref url) = self.dns_over_https {
+            f.field("dns_over_https", url);
+        }
+
         if self.referer {
             f.field("referer", &true);
         }
@@ -1710,6 +3797,22 @@ impl Config {
             f.field("http1_allow_spaces_after_header_name_in_responses", &true);
         }
 
+        if self.http1_preserve_response_headers {
+            f.field("http1_preserve_response_headers", &true);
+        }
+
+        if let Some(ref head_writes) = self.http1_head_writes {
+            f.field("http1_head_writes", head_writes);
+        }
+
+        if let Some(ref v) = self.http1_max_buf_size {
+            f.field("http1_max_buf_size", v);
+        }
+
+        if let Some(ref v) = self.buffer_memory_limit {
+            f.field("buffer_memory_limit", v);
+        }
+
         if matches!(self.http_version_pref, HttpVersionPref::Http1) {
             f.field("http1_only", &true);
         }
@@ -1718,6 +3821,20 @@ impl Config {
             f.field("http2_prior_knowledge", &true);
         }
 
+        if self.h2c_prior_knowledge {
+            f.field("h2c_prior_knowledge", &true);
+        }
+
+        #[cfg(feature = "__boring")]
+        if let Some(ref d) = self.http2_fallback_ttl {
+            f.field("http2_fallback_ttl", d);
+        }
+
+        #[cfg(feature = "__boring")]
+        if self.alt_svc {
+            f.field("alt_svc", &true);
+        }
+
         if let Some(ref d) = self.connect_timeout {
             f.field("connect_timeout", d);
         }
@@ -1726,6 +3843,49 @@ impl Config {
             f.field("timeout", d);
         }
 
+        if let Some(ref d) = self.read_timeout {
+            f.field("read_timeout", d);
+        }
+
+        if let Some(ref v) = self.max_in_flight {
+            f.field("max_in_flight", v);
+        }
+
+        if self.max_in_flight_per_priority.iter().any(Option::is_some) {
+            f.field(
+                "max_in_flight_per_priority",
+                &self.max_in_flight_per_priority,
+            );
+        }
+
+        if let Some(ref limit) = self.rate_limit {
+            f.field("rate_limit", limit);
+        }
+
+        if let Some(ref budget) = self.retry_budget {
+            f.field("retry_budget", budget);
+        }
+
+        if let Some(ref policy) = self.retry_policy {
+            f.field("retry_policy", policy);
+        }
+
+        if !self.layers.is_empty() {
+            f.field("layers", &self.layers.len());
+        }
+
+        if self.homograph_policy != HomographPolicy::Allow {
+            f.field("homograph_policy", &self.homograph_policy);
+        }
+
+        if self.deny_private_ips {
+            f.field("deny_private_ips", &true);
+        }
+
+        if let Some(ref policy) = self.egress_policy {
+            f.field("egress_policy", policy);
+        }
+
         if let Some(ref v) = self.local_address_ipv4 {
             f.field("local_address_4", v);
         }
@@ -1738,6 +3898,11 @@ impl Config {
             f.field("tcp_nodelay", &true);
         }
 
+        #[cfg(feature = "__boring")]
+        if let Some(ref v) = self.proxy_protocol {
+            f.field("proxy_protocol", v);
+        }
+
         #[cfg(feature = "__tls")]
         {
             if !self.certs_verification {
@@ -1760,6 +3925,23 @@ impl Config {
         if !self.dns_overrides.is_empty() {
             f.field("dns_overrides", &self.dns_overrides);
         }
+
+        if let Some(ref d) = self.dns_timeout {
+            f.field("dns_timeout", d);
+        }
+
+        if self.dns_fallback != dns::Fallback::Disabled {
+            f.field("dns_fallback", &self.dns_fallback);
+        }
+
+        if self.ip_family_ttl != Some(Duration::from_secs(600)) {
+            f.field("ip_family_ttl", &self.ip_family_ttl);
+        }
+
+        #[cfg(feature = "scripting")]
+        if let Some(ref hook) = self.script_hook {
+            f.field("script_hook", hook);
+        }
     }
 }
 
@@ -1768,14 +3950,251 @@ struct ClientRef {
     accepts: Accepts,
     #[cfg(feature = "cookies")]
     cookie_store: Option<Arc<dyn cookie::CookieStore>>,
-    headers: HeaderMap,
+    #[cfg(feature = "cookies")]
+    cookie_jar: Option<Arc<cookie::Jar>>,
+    base_url: Option<Url>,
+    default_query: Vec<(String, String)>,
+    headers: Arc<HeaderMap>,
     headers_order: Option<Vec<HeaderName>>,
     hyper: HyperClient,
+    /// A client without a pool, for requests that don't reuse connections.
+    unpooled: HyperClient,
     redirect_policy: Arc<redirect::Policy>,
     referer: bool,
     request_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
     proxies_maybe_http_auth: bool,
+    /// Whether a proxy of the client is picked by a function, such as the
+    /// one of a `ProxyPool`.
+    proxies_custom: bool,
+    proxy_routes: Arc<ProxyRoutes>,
+    dns_routes: Arc<DnsRoutes>,
+    #[cfg(feature = "impersonate")]
+    profile_routes: Arc<ProfileRoutes>,
+    in_flight: Option<Arc<InFlightLimit>>,
+    rate_limit: Option<RateLimit>,
+    retry_budget: Option<retry::Budget>,
+    middleware: Option<Next>,
     https_only: bool,
+    homograph_policy: HomographPolicy,
+    deny_private_ips: bool,
+    egress_policy: Option<Arc<egress::Policy>>,
+    #[cfg(feature = "scripting")]
+    script_hook: Option<Arc<ScriptHook>>,
+    #[cfg(feature = "__boring")]
+    h2_fallback: Option<Arc<H2Fallback>>,
+    #[cfg(feature = "__boring")]
+    alt_svc: Option<Arc<AltSvcCache>>,
+}
+
+/// Hyper clients dedicated to a single proxy, picked from a `ProxyPool`
+/// or set on a request, so that pooled connections are never shared
+/// between proxies or between the credentials used with them.
+struct ProxyRoutes {
+    pool: Option<ProxyPool>,
+    builder: hyper::client::Builder,
+    connector: Connector,
+    clients: Mutex<HashMap<String, HyperClient>>,
+}
+
+impl ProxyRoutes {
+    fn client_for(&self, proxy: Url, pooled: bool) -> crate::Result<HyperClient> {
+        let scheme = proxy.into_proxy_scheme()?;
+        Ok(self.client_through(Some(scheme), pooled))
+    }
+
+    /// The client connecting through `scheme`, or directly if `None`.
+    fn client_through(&self, scheme: Option<ProxyScheme>, pooled: bool) -> HyperClient {
+        let key = scheme
+            .as_ref()
+            .map(ProxyScheme::identity)
+            .unwrap_or_default();
+        let proxies = scheme.map(Proxy::pinned).into_iter().collect();
+        if !pooled {
+            let connector = self.connector.with_proxies(proxies);
+            return self
+                .builder
+                .clone()
+                .pool_max_idle_per_host(0)
+                .build(connector);
+        }
+
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&key) {
+            return client.clone();
+        }
+
+        let client = self.builder.build(self.connector.with_proxies(proxies));
+        if clients.len() >= MAX_ROUTE_CLIENTS {
+            clients.clear();
+        }
+        clients.insert(key, client.clone());
+        client
+    }
+}
+
+/// Hyper clients dedicated to the addresses set on requests with
+/// `RequestBuilder::resolve_to`, so that a pooled connection to one address
+/// is never reused for another.
+struct DnsRoutes {
+    builder: hyper::client::Builder,
+    connector: Connector,
+    /// The resolver of the client, for the other hosts of redirects.
+    resolver: Arc<dyn Resolve>,
+    connect_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    local_address_ipv4: Option<Ipv4Addr>,
+    local_address_ipv6: Option<Ipv6Addr>,
+    clients: Mutex<HashMap<(String, IpAddr), HyperClient>>,
+}
+
+impl DnsRoutes {
+    /// The client connecting to `addr` for `host`.
+    fn client_for(&self, host: &str, addr: IpAddr, pooled: bool) -> HyperClient {
+        let key = (host.to_owned(), addr);
+        if pooled {
+            if let Some(client) = self.clients.lock().unwrap().get(&key) {
+                return client.clone();
+            }
+        }
+
+        let mut overrides = HashMap::new();
+        overrides.insert(host.to_owned(), vec![SocketAddr::new(addr, 0)]);
+        let resolver = DnsResolverWithOverrides::new(self.resolver.clone(), overrides);
+        let mut http = HttpConnector::new_with_resolver(DynResolver::new(Arc::new(resolver)));
+        http.set_connect_timeout(self.connect_timeout);
+        let mut connector = self.connector.with_http(http);
+        connector.set_keepalive(self.tcp_keepalive);
+        #[cfg(feature = "__boring")]
+        connector.set_alt_svc(None);
+        match (self.local_address_ipv4, self.local_address_ipv6) {
+            (Some(v4), Some(v6)) => connector.set_local_addresses(v4, v6),
+            (Some(v4), None) => connector.set_local_address(Some(IpAddr::from(v4))),
+            (None, Some(v6)) => connector.set_local_address(Some(IpAddr::from(v6))),
+            _ => {}
+        }
+
+        if !pooled {
+            return self
+                .builder
+                .clone()
+                .pool_max_idle_per_host(0)
+                .build(connector);
+        }
+        let client = self.builder.build(connector);
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= MAX_ROUTE_CLIENTS {
+            clients.clear();
+        }
+        clients.insert(key, client.clone());
+        client
+    }
+}
+
+/// Hyper clients dedicated to the profiles set on requests with
+/// `RequestBuilder::impersonate`, so that pooled connections are never
+/// shared between fingerprints.
+#[cfg(feature = "impersonate")]
+struct ProfileRoutes {
+    builder: hyper::client::Builder,
+    connector: Connector,
+    /// The codings the client decodes, which profiles only advertise.
+    accepts: Accepts,
+    /// How the client writes HTTP/1 requests, if set, rather than as the
+    /// browsers of the profiles do.
+    head_writes: Option<HeadWrites>,
+    routes: Mutex<HashMap<Impersonate, ProfileRoute>>,
+}
+
+#[cfg(feature = "impersonate")]
+#[derive(Clone)]
+struct ProfileRoute {
+    hyper: HyperClient,
+    unpooled: HyperClient,
+    headers: Arc<HeaderMap>,
+    order: Arc<[HeaderName]>,
+}
+
+#[cfg(feature = "impersonate")]
+impl ProfileRoutes {
+    fn route(&self, impersonate: Impersonate) -> crate::Result<ProfileRoute> {
+        let mut routes = self.routes.lock().unwrap();
+        if let Some(route) = routes.get(&impersonate) {
+            return Ok(route.clone());
+        }
+
+        let settings = crate::impersonate::get_settings(impersonate);
+        let mut connector = self
+            .connector
+            .with_impersonation(
+                settings.tls_connector,
+                impersonate,
+                settings.headers.get(USER_AGENT).cloned(),
+            )
+            .map_err(crate::error::builder)?;
+
+        let mut builder = self.builder.clone();
+        let mut http2 = settings.http2;
+        http2.or_headers_priority(crate::impersonate::headers_priority(impersonate.profile()));
+        builder.http2_agent_profile(agent_profile(
+            impersonate.profile(),
+            http2.headers_pseudo_order(),
+        ));
+        if let Some(size) = http2.initial_stream_window_size() {
+            builder.http2_initial_stream_window_size(size);
+        }
+        if let Some(size) = http2.initial_connection_window_size() {
+            builder.http2_initial_connection_window_size(size);
+        }
+        if let Some(size) = http2.max_frame_size() {
+            builder.http2_max_frame_size(size);
+        }
+        if let Some(max) = http2.max_concurrent_streams() {
+            builder.http2_max_concurrent_streams(max);
+        }
+        if let Some(max) = http2.max_header_list_size() {
+            builder.http2_max_header_list_size(max);
+        }
+        if let Some(enabled) = http2.enable_push() {
+            builder.http2_enable_push(enabled);
+        }
+        if let Some(size) = http2.header_table_size() {
+            builder.http2_header_table_size(size);
+        }
+        connector.set_h2_preface(Some(http2.preface()));
+
+        let head_writes = self
+            .head_writes
+            .unwrap_or_else(|| crate::impersonate::head_writes(impersonate.profile()));
+        builder.http1_writev(head_writes != HeadWrites::Coalesced);
+        connector.set_head_writes(Some(head_writes));
+
+        let order = crate::impersonate::header_order(&settings.headers).into();
+        let mut headers = settings.headers;
+        if let Some(value) = headers.get(ACCEPT_ENCODING) {
+            match self.accepts.restrict(value) {
+                Some(ref restricted) if restricted == value => {}
+                Some(restricted) => {
+                    Arc::make_mut(&mut headers).insert(ACCEPT_ENCODING, restricted);
+                }
+                None => {
+                    Arc::make_mut(&mut headers).remove(ACCEPT_ENCODING);
+                }
+            }
+        }
+
+        let route = ProfileRoute {
+            unpooled: builder
+                .clone()
+                .pool_max_idle_per_host(0)
+                .build(connector.clone()),
+            hyper: builder.build(connector),
+            order,
+            headers,
+        };
+        routes.insert(impersonate, route.clone());
+        Ok(route)
+    }
 }
 
 impl ClientRef {
@@ -1801,6 +4220,14 @@ impl ClientRef {
             f.field("redirect_policy", &self.redirect_policy);
         }
 
+        if let Some(ref base_url) = self.base_url {
+            f.field("base_url", base_url);
+        }
+
+        if !self.default_query.is_empty() {
+            f.field("default_query", &self.default_query);
+        }
+
         if self.referer {
             f.field("referer", &true);
         }
@@ -1810,6 +4237,10 @@ impl ClientRef {
         if let Some(ref d) = self.request_timeout {
             f.field("timeout", d);
         }
+
+        if let Some(ref d) = self.read_timeout {
+            f.field("read_timeout", d);
+        }
     }
 }
 
@@ -1822,6 +4253,7 @@ pin_project! {
 
 enum PendingInner {
     Request(PendingRequest),
+    Middleware(Pin<Box<dyn Future<Output = Result<Response, crate::Error>> + Send>>),
     Error(Option<crate::Error>),
 }
 
@@ -1833,20 +4265,37 @@ pin_project! {
         body: Option<Option<Bytes>>,
 
         urls: Vec<Url>,
+        history: History,
 
         retry_count: usize,
 
         client: Arc<ClientRef>,
+        #[cfg(feature = "cookies")]
+        cookie_store: Option<Arc<dyn cookie::CookieStore>>,
+        hyper: HyperClient,
+        accepts: Accepts,
 
         #[pin]
         in_flight: ResponseFuture,
         #[pin]
         timeout: Option<Pin<Box<Sleep>>>,
+        read_timeout: Option<Duration>,
+        read_deadline: Option<Pin<Box<Sleep>>>,
+
+        scope: Option<ScopeGuard>,
+        // When the request being waited for was sent.
+        sent: Option<Instant>,
+        throttle: Option<Pin<Box<Sleep>>>,
+        queued: Option<Acquire>,
+        permit: Option<Permit>,
     }
 }
 
 enum ResponseFuture {
     Default(HyperResponseFuture),
+    // A response whose cookies are being persisted.
+    #[cfg(feature = "cookies")]
+    Persisting(cookie::Persisting, Option<hyper::Response<hyper::Body>>),
 }
 
 impl PendingRequest {
@@ -1858,6 +4307,16 @@ impl PendingRequest {
         self.project().timeout
     }
 
+    /// Start the read timeout over, for a request sent again.
+    fn restart_read_timeout(self: Pin<&mut Self>) {
+        let this = self.project();
+        if let (Some(timeout), Some(deadline)) = (*this.read_timeout, this.read_deadline) {
+            deadline
+                .as_mut()
+                .reset(tokio::time::Instant::now() + timeout);
+        }
+    }
+
     fn urls(self: Pin<&mut Self>) -> &mut Vec<Url> {
         self.project().urls
     }
@@ -1866,8 +4325,31 @@ impl PendingRequest {
         self.project().headers
     }
 
+    #[cfg(feature = "__boring")]
+    fn fallback_to_http1(self: Pin<&mut Self>, err: &(dyn std::error::Error + 'static)) -> bool {
+        let cache = match self.client.h2_fallback {
+            Some(ref cache) => cache,
+            None => return false,
+        };
+        if !h2_fallback::is_h2_rejection(err) {
+            return false;
+        }
+        match h2_fallback::url_key(&self.url) {
+            Some(key) if !cache.is_downgraded(&key) => {
+                cache.downgrade(key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(not(feature = "__boring"))]
+    fn fallback_to_http1(self: Pin<&mut Self>, _err: &(dyn std::error::Error + 'static)) -> bool {
+        false
+    }
+
     fn retry_error(mut self: Pin<&mut Self>, err: &(dyn std::error::Error + 'static)) -> bool {
-        if !is_retryable_error(err) {
+        if !is_retryable_error(err) && !self.as_mut().fallback_to_http1(err) {
             return false;
         }
 
@@ -1886,7 +4368,14 @@ impl PendingRequest {
             trace!("retry count too high");
             return false;
         }
+        if let Some(ref budget) = self.client.retry_budget {
+            if !budget.withdraw() {
+                debug!("retry budget exhausted");
+                return false;
+            }
+        }
         self.retry_count += 1;
+        self.sent = None;
 
         let uri = expect_uri(&self.url);
 
@@ -1898,9 +4387,10 @@ impl PendingRequest {
                     .body(body.into_stream())
                     .expect("valid request parts");
                 *req.headers_mut() = self.headers.clone();
-                ResponseFuture::Default(self.client.hyper.request(req))
+                ResponseFuture::Default(self.hyper.request(req))
             }
         };
+        self.as_mut().restart_read_timeout();
 
         true
     }
@@ -1937,6 +4427,7 @@ impl Future for Pending {
         let inner = self.inner();
         match inner.get_mut() {
             PendingInner::Request(ref mut req) => Pin::new(req).poll(cx),
+            PendingInner::Middleware(ref mut fut) => fut.as_mut().poll(cx),
             PendingInner::Error(ref mut err) => Poll::Ready(Err(err
                 .take()
                 .expect("Pending error polled more than once"))),
@@ -1948,6 +4439,14 @@ impl Future for PendingRequest {
     type Output = Result<Response, crate::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some(ref scope) = self.scope {
+            if scope.poll_aborted(cx) {
+                return Poll::Ready(Err(
+                    crate::error::request(crate::error::Aborted).with_url(self.url.clone())
+                ));
+            }
+        }
+
         if let Some(delay) = self.as_mut().timeout().as_mut().as_pin_mut() {
             if let Poll::Ready(()) = delay.poll(cx) {
                 return Poll::Ready(Err(
@@ -1956,7 +4455,35 @@ impl Future for PendingRequest {
             }
         }
 
+        if let Some(ref mut throttle) = self.throttle {
+            futures_core::ready!(throttle.as_mut().poll(cx));
+            self.throttle = None;
+        }
+
+        if let Some(ref mut queued) = self.queued {
+            let permit = futures_core::ready!(queued.poll_acquire(cx));
+            self.permit = Some(permit);
+            self.queued = None;
+        }
+
+        // The read timeout starts once the request is sent.
+        if let Some(timeout) = self.read_timeout {
+            let deadline = self
+                .read_deadline
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+            if let Poll::Ready(()) = deadline.as_mut().poll(cx) {
+                return Poll::Ready(Err(
+                    crate::error::request(crate::error::TimedOut).with_url(self.url.clone())
+                ));
+            }
+        }
+
         loop {
+            #[cfg(feature = "cookies")]
+            let mut persisted = false;
+            if self.sent.is_none() {
+                self.sent = Some(Instant::now());
+            }
             let res = match self.as_mut().in_flight().get_mut() {
                 ResponseFuture::Default(r) => match Pin::new(r).poll(cx) {
                     Poll::Ready(Err(e)) => {
@@ -1967,21 +4494,53 @@ impl Future for PendingRequest {
                             crate::error::request(e).with_url(self.url.clone())
                         ));
                     }
-                    Poll::Ready(Ok(res)) => res,
+                    Poll::Ready(Ok(mut res)) => {
+                        if let Some(sent) = self.sent.take() {
+                            res.extensions_mut().insert(Exchange {
+                                sent,
+                                head: Instant::now(),
+                            });
+                        }
+                        res
+                    }
+                    Poll::Pending => return Poll::Pending,
+                },
+                #[cfg(feature = "cookies")]
+                ResponseFuture::Persisting(persisting, res) => match persisting.as_mut().poll(cx) {
+                    Poll::Ready(Err(e)) => {
+                        return Poll::Ready(Err(
+                            crate::error::request(e).with_url(self.url.clone())
+                        ));
+                    }
+                    Poll::Ready(Ok(())) => {
+                        persisted = true;
+                        res.take().expect("polled after completion")
+                    }
                     Poll::Pending => return Poll::Pending,
                 },
             };
 
             #[cfg(feature = "cookies")]
-            {
-                if let Some(ref cookie_store) = self.client.cookie_store {
-                    let mut cookies =
-                        cookie::extract_response_cookie_headers(&res.headers()).peekable();
-                    if cookies.peek().is_some() {
-                        cookie_store.set_cookies(&mut cookies, &self.url);
+            if !persisted {
+                if let Some(ref cookie_store) = self.cookie_store {
+                    let cookies = cookie::extract_response_cookie_headers(&res.headers())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if !cookies.is_empty() {
+                        cookie_store.set_cookies(&mut cookies.iter(), &self.url);
+                        if let Some(persisting) = cookie_store.persist(&cookies, &self.url) {
+                            *self.as_mut().in_flight().get_mut() =
+                                ResponseFuture::Persisting(persisting, Some(res));
+                            continue;
+                        }
                     }
                 }
             }
+            #[cfg(feature = "__boring")]
+            if let Some(ref alt_svc) = self.client.alt_svc {
+                alt_svc.observe(&self.url, res.headers());
+            }
+
             let should_redirect = match res.status() {
                 StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
                     self.body = None;
@@ -2052,6 +4611,9 @@ impl Future for PendingRequest {
                         redirect::ActionKind::Follow => {
                             debug!("redirecting '{}' to '{}'", self.url, loc);
 
+                            let url = self.url.clone();
+                            self.history.push(res.status(), url, res.headers().clone());
+
                             if loc.scheme() != "http" && loc.scheme() != "https" {
                                 return Poll::Ready(Err(error::url_bad_scheme(loc)));
                             }
@@ -2063,11 +4625,29 @@ impl Future for PendingRequest {
                                 )));
                             }
 
+                            if let Err(err) = self.client.homograph_policy.check(&loc) {
+                                return Poll::Ready(Err(error::redirect(err, loc)));
+                            }
+
+                            if self.client.deny_private_ips {
+                                if let Err(err) = private::check_url(&loc) {
+                                    return Poll::Ready(Err(error::redirect(err, loc)));
+                                }
+                            }
+
+                            if let Some(ref policy) = self.client.egress_policy {
+                                if let Err(err) = policy.check_url(&loc) {
+                                    return Poll::Ready(Err(error::redirect(err, loc)));
+                                }
+                            }
+
                             self.url = loc;
                             let mut headers =
                                 std::mem::replace(self.as_mut().headers(), HeaderMap::new());
 
-                            remove_sensitive_headers(&mut headers, &self.url, &self.urls);
+                            if self.client.redirect_policy.strips_sensitive_headers() {
+                                remove_sensitive_headers(&mut headers, &self.url, &self.urls);
+                            }
                             let uri = expect_uri(&self.url);
                             let body = match self.body {
                                 Some(Some(ref body)) => Body::reusable(body.clone()),
@@ -2077,7 +4657,7 @@ impl Future for PendingRequest {
                             // Add cookies from the cookie store.
                             #[cfg(feature = "cookies")]
                             {
-                                if let Some(ref cookie_store) = self.client.cookie_store {
+                                if let Some(ref cookie_store) = self.cookie_store {
                                     add_cookie_header(&mut headers, &**cookie_store, &self.url);
                                 }
                             }
@@ -2092,9 +4672,10 @@ impl Future for PendingRequest {
                                             .expect("valid request parts");
                                         *req.headers_mut() = headers.clone();
                                         std::mem::swap(self.as_mut().headers(), &mut headers);
-                                        ResponseFuture::Default(self.client.hyper.request(req))
+                                        ResponseFuture::Default(self.hyper.request(req))
                                     }
                                 };
+                            self.as_mut().restart_read_timeout();
 
                             continue;
                         }
@@ -2108,12 +4689,18 @@ impl Future for PendingRequest {
                 }
             }
 
+            let mut history = std::mem::take(&mut self.history);
+            history.set_retries(self.retry_count);
             let res = Response::new(
                 res,
                 self.url.clone(),
-                self.client.accepts,
+                &self.accepts,
                 self.timeout.take(),
-            );
+                self.read_timeout,
+                self.scope.take(),
+                self.permit.take(),
+            )
+            .with_history(history);
             return Poll::Ready(Ok(res));
         }
     }
@@ -2127,6 +4714,7 @@ impl fmt::Debug for Pending {
                 .field("method", &req.method)
                 .field("url", &req.url)
                 .finish(),
+            PendingInner::Middleware(_) => f.debug_struct("Pending").finish(),
             PendingInner::Error(ref err) => f.debug_struct("Pending").field("error", err).finish(),
         }
     }
@@ -2151,8 +4739,54 @@ fn add_cookie_header(headers: &mut HeaderMap, cookie_store: &dyn cookie::CookieS
     }
 }
 
+/// `headers` with the names in `order` first, and the others after them.
+fn sort_headers(headers: HeaderMap, order: &[HeaderName]) -> HeaderMap {
+    let mut sorted = HeaderMap::with_capacity(headers.keys_len());
+    for name in order {
+        if sorted.contains_key(name) {
+            continue;
+        }
+        for value in headers.get_all(name) {
+            sorted.append(name, value.clone());
+        }
+    }
+    for (name, value) in headers.iter() {
+        if !order.contains(name) {
+            sorted.append(name, value.clone());
+        }
+    }
+    sorted
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_headers_keeps_values() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.append("x-a", HeaderValue::from_static("1"));
+        headers.append("x-a", HeaderValue::from_static("2"));
+        headers.insert(CONNECTION, HeaderValue::from_static("close"));
+
+        let order = [CONNECTION, HeaderName::from_static("x-a"), CONNECTION];
+        let sorted = sort_headers(headers, &order);
+        let pairs = sorted
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            [
+                ("connection", "close"),
+                ("x-a", "1"),
+                ("x-a", "2"),
+                ("accept-encoding", "gzip"),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn execute_request_rejects_invald_urls() {
         let url_str = "hxxps://www.rust-lang.org/";
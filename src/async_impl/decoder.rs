@@ -1,6 +1,10 @@
 use std::fmt;
 use std::future::Future;
+use std::io;
 use std::pin::Pin;
+use std::sync::Arc;
+#[cfg(feature = "libdeflate")]
+use std::sync::Mutex;
 use std::task::{Context, Poll};
 
 #[cfg(feature = "gzip")]
@@ -18,7 +22,7 @@ use async_compression::tokio::bufread::DeflateDecoder;
 use bytes::Bytes;
 use futures_core::Stream;
 use futures_util::stream::Peekable;
-use http::HeaderMap;
+use http::{HeaderMap, HeaderValue};
 use hyper::body::HttpBody;
 
 #[cfg(any(
@@ -39,7 +43,122 @@ use tokio_util::io::StreamReader;
 use super::super::Body;
 use crate::error;
 
-#[derive(Clone, Copy, Debug)]
+/// A content coding a response body can be compressed with.
+///
+/// See [`RequestBuilder::accept_encoding`](crate::RequestBuilder::accept_encoding).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    /// `gzip`, decoded with the `gzip` feature.
+    Gzip,
+    /// `br`, decoded with the `brotli` feature.
+    Brotli,
+    /// `zstd`, decoded with the `zstd` feature.
+    Zstd,
+    /// `deflate`, decoded with the `deflate` feature.
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// Get the token of this coding, as in `Accept-Encoding`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    /// Whether the feature decoding this coding is enabled.
+    pub(super) fn is_supported(&self) -> bool {
+        match self {
+            ContentEncoding::Gzip => cfg!(feature = "gzip"),
+            ContentEncoding::Brotli => cfg!(feature = "brotli"),
+            ContentEncoding::Zstd => cfg!(feature = "zstd"),
+            ContentEncoding::Deflate => cfg!(feature = "deflate"),
+        }
+    }
+}
+
+/// A decoder of a content coding that isn't built in, such as a
+/// proprietary one, registered with
+/// [`ClientBuilder::content_decoder`](crate::ClientBuilder::content_decoder).
+///
+/// It is implemented for closures taking and returning a [`DecoderStream`].
+///
+/// # Example
+///
+/// ```
+/// use futures_util::StreamExt;
+///
+/// // A coding flipping every bit, for the sake of the example.
+/// let client = rquest::Client::builder()
+///     .content_decoder("x-not", |encoded: rquest::DecoderStream| -> rquest::DecoderStream {
+///         Box::pin(encoded.map(|chunk| chunk.map(|bytes| bytes.iter().map(|b| !b).collect())))
+///     })
+///     .build();
+/// ```
+pub trait ContentDecoder: Send + Sync + 'static {
+    /// Wrap a body encoded with this coding in a stream of its decoded
+    /// bytes.
+    fn decode(&self, encoded: DecoderStream) -> DecoderStream;
+}
+
+impl<F> ContentDecoder for F
+where
+    F: Fn(DecoderStream) -> DecoderStream + Send + Sync + 'static,
+{
+    fn decode(&self, encoded: DecoderStream) -> DecoderStream {
+        self(encoded)
+    }
+}
+
+/// A stream of body bytes, passed to and returned by a [`ContentDecoder`].
+pub type DecoderStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + Sync>>;
+
+/// How gzip and deflate bodies are decompressed.
+///
+/// See [`ClientBuilder::decompression_backend`](crate::ClientBuilder::decompression_backend).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecompressionBackend {
+    /// Decompress the body as it is received, with zlib-ng if the `zlib-ng`
+    /// feature is enabled, or else with miniz_oxide. The default.
+    #[default]
+    Streaming,
+    /// Decompress the whole body at once, when it is received, with
+    /// libdeflate.
+    ///
+    /// This trades holding the compressed and the decompressed body in
+    /// memory together for faster decompression, which suits crawlers
+    /// reading whole pages. The `decompression` benchmark compares the
+    /// backends:
+    ///
+    /// ```text
+    /// cargo bench --bench client --features bench-support,libdeflate,zlib-ng -- decompression
+    /// ```
+    ///
+    /// Bodies of several concatenated gzip members, or decompressing to
+    /// more than 1 GiB, or to more than
+    /// [`buffer_memory_limit`](crate::ClientBuilder::buffer_memory_limit)
+    /// if set, are refused. This requires the `libdeflate` feature.
+    Libdeflate,
+}
+
+/// A decoder registered for a coding.
+#[derive(Clone)]
+pub(super) struct CustomDecoder {
+    pub(super) coding: String,
+    pub(super) decoder: Arc<dyn ContentDecoder>,
+}
+
+impl fmt::Debug for CustomDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CustomDecoder").field(&self.coding).finish()
+    }
+}
+
+#[derive(Clone)]
 pub(super) struct Accepts {
     #[cfg(feature = "gzip")]
     pub(super) gzip: bool,
@@ -49,6 +168,69 @@ pub(super) struct Accepts {
     pub(super) zstd: bool,
     #[cfg(feature = "deflate")]
     pub(super) deflate: bool,
+    #[cfg(feature = "libdeflate")]
+    pub(super) backend: DecompressionBackend,
+    #[cfg(feature = "libdeflate")]
+    pub(super) scratch: Arc<Scratch>,
+    pub(super) custom: Arc<[CustomDecoder]>,
+}
+
+impl fmt::Debug for Accepts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut f = f.debug_struct("Accepts");
+        #[cfg(feature = "gzip")]
+        f.field("gzip", &self.gzip);
+        #[cfg(feature = "brotli")]
+        f.field("brotli", &self.brotli);
+        #[cfg(feature = "zstd")]
+        f.field("zstd", &self.zstd);
+        #[cfg(feature = "deflate")]
+        f.field("deflate", &self.deflate);
+        #[cfg(feature = "libdeflate")]
+        f.field("backend", &self.backend);
+        f.field("custom", &self.custom).finish()
+    }
+}
+
+/// The compressed bodies read for libdeflate, with the buffers they were
+/// read into kept for the next bodies.
+#[cfg(feature = "libdeflate")]
+pub(super) struct Scratch {
+    // The largest body decompressed at once, compressed or not.
+    limit: usize,
+    // The most bytes of buffers kept.
+    keep: usize,
+    free: Mutex<Vec<Vec<u8>>>,
+}
+
+/// The most bytes of buffers kept for compressed bodies.
+#[cfg(feature = "libdeflate")]
+const MAX_KEPT_SCRATCH: usize = 4 << 20;
+
+#[cfg(feature = "libdeflate")]
+impl Scratch {
+    /// Scratch for bodies of up to `limit` bytes, keeping up to a quarter
+    /// of it in buffers.
+    pub(super) fn new(limit: usize) -> Scratch {
+        Scratch {
+            limit: limit.min(MAX_WHOLE_SIZE),
+            keep: (limit / 4).min(MAX_KEPT_SCRATCH),
+            free: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn take(&self) -> Vec<u8> {
+        self.free.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    fn give(&self, mut buf: Vec<u8>) {
+        buf.clear();
+        let mut free = self.free.lock().unwrap();
+        let kept: usize = free.iter().map(Vec::capacity).sum();
+        if kept + buf.capacity() <= self.keep {
+            free.push(buf);
+        }
+    }
 }
 
 /// A response decompressor over a non-blocking stream of chunks.
@@ -83,21 +265,44 @@ enum Inner {
     #[cfg(feature = "deflate")]
     Deflate(Pin<Box<FramedRead<DeflateDecoder<PeekableIoStreamReader>, BytesCodec>>>),
 
+    /// A decoder registered with `ClientBuilder::content_decoder`.
+    Custom(DecoderStream),
+
     /// A decoder that doesn't have a value yet.
-    #[cfg(any(
-        feature = "brotli",
-        feature = "zstd",
-        feature = "gzip",
-        feature = "deflate"
-    ))]
     Pending(Pin<Box<Pending>>),
+
+    /// A decoder failing once, for a body it refuses to decode.
+    Error(Option<error::Error>),
 }
 
 /// A future attempt to poll the response body for EOF so we know whether to use gzip or not.
 struct Pending(PeekableIoStream, DecoderType);
 
-struct IoStream(super::body::ImplStream);
+struct IoStream(Source);
 
+/// The stream under a decoder: the response body, or the decoder of the
+/// next encoding when several are stacked.
+enum Source {
+    Body(super::body::ImplStream),
+    Decoded(Box<Decoder>),
+}
+
+/// The most encodings stacked on a response body that are decoded.
+///
+/// Misconfigured origins do send bodies encoded twice, but a deeper nesting
+/// is refused rather than decoded layer after layer.
+const MAX_ENCODINGS: usize = 3;
+
+/// The largest body decompressed with libdeflate, which needs room for the
+/// whole of it at once.
+#[cfg(feature = "libdeflate")]
+const MAX_WHOLE_SIZE: usize = 1 << 30;
+
+/// The most a deflate stream expands, see the zlib technical details.
+#[cfg(feature = "libdeflate")]
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+#[derive(Clone)]
 enum DecoderType {
     #[cfg(feature = "gzip")]
     Gzip,
@@ -107,6 +312,19 @@ enum DecoderType {
     Zstd,
     #[cfg(feature = "deflate")]
     Deflate,
+    /// A body decompressed at once with libdeflate.
+    #[cfg(feature = "libdeflate")]
+    Whole(Whole, Arc<Scratch>),
+    Custom(Arc<dyn ContentDecoder>),
+}
+
+/// The format of a body decompressed with libdeflate.
+#[cfg(feature = "libdeflate")]
+#[derive(Clone, Copy)]
+enum Whole {
+    Gzip,
+    #[cfg(feature = "deflate")]
+    Deflate,
 }
 
 impl fmt::Debug for Decoder {
@@ -132,99 +350,79 @@ impl Decoder {
         }
     }
 
-    /// A gzip decoder.
+    /// A decoder of `kind`, over `source`.
     ///
-    /// This decoder will buffer and decompress chunks that are gzipped.
-    #[cfg(feature = "gzip")]
-    fn gzip(body: Body) -> Decoder {
+    /// This decoder will buffer and decompress chunks once the source is
+    /// known not to be empty.
+    fn pending(source: Source, kind: DecoderType) -> Decoder {
         use futures_util::StreamExt;
 
         Decoder {
-            inner: Inner::Pending(Box::pin(Pending(
-                IoStream(body.into_stream()).peekable(),
-                DecoderType::Gzip,
-            ))),
-        }
-    }
-
-    /// A brotli decoder.
-    ///
-    /// This decoder will buffer and decompress chunks that are brotlied.
-    #[cfg(feature = "brotli")]
-    fn brotli(body: Body) -> Decoder {
-        use futures_util::StreamExt;
-
-        Decoder {
-            inner: Inner::Pending(Box::pin(Pending(
-                IoStream(body.into_stream()).peekable(),
-                DecoderType::Brotli,
-            ))),
+            inner: Inner::Pending(Box::pin(Pending(IoStream(source).peekable(), kind))),
         }
     }
 
-    /// A zstd decoder.
-    ///
-    /// This decoder will buffer and decompress chunks that are zstd compressed.
-    #[cfg(feature = "zstd")]
-    fn zstd(body: Body) -> Decoder {
-        use futures_util::StreamExt;
-
-        Decoder {
-            inner: Inner::Pending(Box::pin(Pending(
-                IoStream(body.into_stream()).peekable(),
-                DecoderType::Zstd,
-            ))),
+    /// The source of a decoder stacked over this one.
+    fn into_source(self) -> Source {
+        match self.inner {
+            Inner::PlainText(body) => Source::Body(body),
+            _ => Source::Decoded(Box::new(self)),
         }
     }
 
-    /// A deflate decoder.
+    /// Parse the encodings of a response, in the order to decode them.
     ///
-    /// This decoder will buffer and decompress chunks that are deflated.
-    #[cfg(feature = "deflate")]
-    fn deflate(body: Body) -> Decoder {
-        use futures_util::StreamExt;
-
-        Decoder {
-            inner: Inner::Pending(Box::pin(Pending(
-                IoStream(body.into_stream()).peekable(),
-                DecoderType::Deflate,
-            ))),
-        }
-    }
-
-    #[cfg(any(
-        feature = "brotli",
-        feature = "zstd",
-        feature = "gzip",
-        feature = "deflate"
-    ))]
-    fn detect_encoding(headers: &mut HeaderMap, encoding_str: &str) -> bool {
+    /// These are the `Content-Encoding`s then the `Transfer-Encoding`s
+    /// other than `chunked`, undone from the last one applied. Nothing is
+    /// decoded unless all of them are accepted.
+    fn detect_encodings(
+        headers: &mut HeaderMap,
+        accepts: &Accepts,
+    ) -> Result<Vec<DecoderType>, error::Error> {
         use http::header::{CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING};
         use log::warn;
 
-        let mut is_content_encoded = {
-            headers
-                .get_all(CONTENT_ENCODING)
-                .iter()
-                .any(|enc| enc == encoding_str)
-                || headers
-                    .get_all(TRANSFER_ENCODING)
-                    .iter()
-                    .any(|enc| enc == encoding_str)
-        };
-        if is_content_encoded {
-            if let Some(content_length) = headers.get(CONTENT_LENGTH) {
-                if content_length == "0" {
-                    warn!("{} response with content-length of 0", encoding_str);
-                    is_content_encoded = false;
+        let mut encodings = Vec::new();
+        let values = headers
+            .get_all(CONTENT_ENCODING)
+            .iter()
+            .chain(headers.get_all(TRANSFER_ENCODING).iter());
+        for value in values {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => return Ok(Vec::new()),
+            };
+            for coding in value.split(',').map(str::trim) {
+                if coding.is_empty()
+                    || coding.eq_ignore_ascii_case("identity")
+                    || coding.eq_ignore_ascii_case("chunked")
+                {
+                    continue;
+                }
+                match DecoderType::from_coding(coding, accepts) {
+                    Some(kind) => encodings.push(kind),
+                    None => return Ok(Vec::new()),
                 }
             }
         }
-        if is_content_encoded {
-            headers.remove(CONTENT_ENCODING);
-            headers.remove(CONTENT_LENGTH);
+        if encodings.is_empty() {
+            return Ok(encodings);
+        }
+
+        if let Some(content_length) = headers.get(CONTENT_LENGTH) {
+            if content_length == "0" {
+                warn!("{:?} response with content-length of 0", encodings);
+                return Ok(Vec::new());
+            }
         }
-        is_content_encoded
+        if encodings.len() > MAX_ENCODINGS {
+            return Err(error::decode(TooManyEncodings(encodings.len())));
+        }
+
+        headers.remove(CONTENT_ENCODING);
+        headers.remove(CONTENT_LENGTH);
+        encodings.reverse();
+        Ok(encodings)
     }
 
     /// Constructs a Decoder from a hyper request.
@@ -232,37 +430,21 @@ impl Decoder {
     /// A decoder is just a wrapper around the hyper request that knows
     /// how to decode the content body of the request.
     ///
-    /// Uses the correct variant by inspecting the Content-Encoding header.
-    pub(super) fn detect(_headers: &mut HeaderMap, body: Body, _accepts: Accepts) -> Decoder {
-        #[cfg(feature = "gzip")]
-        {
-            if _accepts.gzip && Decoder::detect_encoding(_headers, "gzip") {
-                return Decoder::gzip(body);
-            }
-        }
-
-        #[cfg(feature = "brotli")]
-        {
-            if _accepts.brotli && Decoder::detect_encoding(_headers, "br") {
-                return Decoder::brotli(body);
-            }
-        }
-
-        #[cfg(feature = "zstd")]
-        {
-            if _accepts.zstd && Decoder::detect_encoding(_headers, "zstd") {
-                return Decoder::zstd(body);
-            }
-        }
-
-        #[cfg(feature = "deflate")]
-        {
-            if _accepts.deflate && Decoder::detect_encoding(_headers, "deflate") {
-                return Decoder::deflate(body);
+    /// Uses the correct variants by inspecting the Content-Encoding and
+    /// Transfer-Encoding headers, stacking a decoder for each encoding.
+    pub(super) fn detect(headers: &mut HeaderMap, body: Body, accepts: &Accepts) -> Decoder {
+        match Decoder::detect_encodings(headers, accepts) {
+            Ok(encodings) => {
+                let mut decoder = Decoder::plain_text(body);
+                for kind in encodings {
+                    decoder = Decoder::pending(decoder.into_source(), kind);
+                }
+                decoder
             }
+            Err(err) => Decoder {
+                inner: Inner::Error(Some(err)),
+            },
         }
-
-        Decoder::plain_text(body)
     }
 }
 
@@ -272,12 +454,6 @@ impl Stream for Decoder {
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         // Do a read or poll for a pending decoder value.
         match self.inner {
-            #[cfg(any(
-                feature = "brotli",
-                feature = "zstd",
-                feature = "gzip",
-                feature = "deflate"
-            ))]
             Inner::Pending(ref mut future) => match Pin::new(future).poll(cx) {
                 Poll::Ready(Ok(inner)) => {
                     self.inner = inner;
@@ -286,7 +462,15 @@ impl Stream for Decoder {
                 Poll::Ready(Err(e)) => Poll::Ready(Some(Err(crate::error::decode_io(e)))),
                 Poll::Pending => Poll::Pending,
             },
+            Inner::Error(ref mut err) => Poll::Ready(err.take().map(Err)),
             Inner::PlainText(ref mut body) => Pin::new(body).poll_next(cx),
+            Inner::Custom(ref mut decoder) => {
+                match futures_core::ready!(decoder.as_mut().poll_next(cx)) {
+                    Some(Ok(bytes)) => Poll::Ready(Some(Ok(bytes))),
+                    Some(Err(err)) => Poll::Ready(Some(Err(crate::error::decode_io(err)))),
+                    None => Poll::Ready(None),
+                }
+            }
             #[cfg(feature = "gzip")]
             Inner::Gzip(ref mut decoder) => {
                 match futures_core::ready!(Pin::new(decoder).poll_next(cx)) {
@@ -345,12 +529,6 @@ impl HttpBody for Decoder {
         match self.inner {
             Inner::PlainText(ref body) => HttpBody::size_hint(body),
             // the rest are "unknown", so default
-            #[cfg(any(
-                feature = "brotli",
-                feature = "zstd",
-                feature = "gzip",
-                feature = "deflate"
-            ))]
             _ => http_body::SizeHint::default(),
         }
     }
@@ -377,30 +555,37 @@ impl Future for Pending {
             None => return Poll::Ready(Ok(Inner::PlainText(Body::empty().into_stream()))),
         };
 
-        let _body = std::mem::replace(
+        let body = std::mem::replace(
             &mut self.0,
-            IoStream(Body::empty().into_stream()).peekable(),
+            IoStream(Source::Body(Body::empty().into_stream())).peekable(),
         );
 
         match self.1 {
+            DecoderType::Custom(ref decoder) => {
+                Poll::Ready(Ok(Inner::Custom(decoder.decode(Box::pin(body)))))
+            }
+            #[cfg(feature = "libdeflate")]
+            DecoderType::Whole(format, ref scratch) => Poll::Ready(Ok(Inner::Custom(
+                decode_whole(body, format, scratch.clone()),
+            ))),
             #[cfg(feature = "brotli")]
             DecoderType::Brotli => Poll::Ready(Ok(Inner::Brotli(Box::pin(FramedRead::new(
-                BrotliDecoder::new(StreamReader::new(_body)),
+                BrotliDecoder::new(StreamReader::new(body)),
                 BytesCodec::new(),
             ))))),
             #[cfg(feature = "zstd")]
             DecoderType::Zstd => Poll::Ready(Ok(Inner::Zstd(Box::pin(FramedRead::new(
-                ZstdDecoder::new(StreamReader::new(_body)),
+                ZstdDecoder::new(StreamReader::new(body)),
                 BytesCodec::new(),
             ))))),
             #[cfg(feature = "gzip")]
             DecoderType::Gzip => Poll::Ready(Ok(Inner::Gzip(Box::pin(FramedRead::new(
-                GzipDecoder::new(StreamReader::new(_body)),
+                GzipDecoder::new(StreamReader::new(body)),
                 BytesCodec::new(),
             ))))),
             #[cfg(feature = "deflate")]
             DecoderType::Deflate => Poll::Ready(Ok(Inner::Deflate(Box::pin(FramedRead::new(
-                DeflateDecoder::new(StreamReader::new(_body)),
+                DeflateDecoder::new(StreamReader::new(body)),
                 BytesCodec::new(),
             ))))),
         }
@@ -411,7 +596,13 @@ impl Stream for IoStream {
     type Item = Result<Bytes, std::io::Error>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        match futures_core::ready!(Pin::new(&mut self.0).poll_next(cx)) {
+        let item = match self.0 {
+            Source::Body(ref mut body) => futures_core::ready!(Pin::new(body).poll_next(cx)),
+            Source::Decoded(ref mut decoder) => {
+                futures_core::ready!(Pin::new(&mut **decoder).poll_next(cx))
+            }
+        };
+        match item {
             Some(Ok(chunk)) => Poll::Ready(Some(Ok(chunk))),
             Some(Err(err)) => Poll::Ready(Some(Err(err.into_io()))),
             None => Poll::Ready(None),
@@ -419,6 +610,159 @@ impl Stream for IoStream {
     }
 }
 
+impl DecoderType {
+    /// The decoder of `coding`, if it is accepted.
+    ///
+    /// Registered decoders are looked up first, so they may replace the
+    /// built-in ones.
+    fn from_coding(coding: &str, accepts: &Accepts) -> Option<DecoderType> {
+        if let Some(custom) = accepts
+            .custom
+            .iter()
+            .find(|custom| custom.coding.eq_ignore_ascii_case(coding))
+        {
+            return Some(DecoderType::Custom(custom.decoder.clone()));
+        }
+
+        #[cfg(feature = "gzip")]
+        {
+            if accepts.gzip
+                && (coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip"))
+            {
+                #[cfg(feature = "libdeflate")]
+                if accepts.backend == DecompressionBackend::Libdeflate {
+                    return Some(DecoderType::Whole(Whole::Gzip, accepts.scratch.clone()));
+                }
+                return Some(DecoderType::Gzip);
+            }
+        }
+
+        #[cfg(feature = "brotli")]
+        {
+            if accepts.brotli && coding.eq_ignore_ascii_case("br") {
+                return Some(DecoderType::Brotli);
+            }
+        }
+
+        #[cfg(feature = "zstd")]
+        {
+            if accepts.zstd && coding.eq_ignore_ascii_case("zstd") {
+                return Some(DecoderType::Zstd);
+            }
+        }
+
+        #[cfg(feature = "deflate")]
+        {
+            if accepts.deflate && coding.eq_ignore_ascii_case("deflate") {
+                #[cfg(feature = "libdeflate")]
+                if accepts.backend == DecompressionBackend::Libdeflate {
+                    return Some(DecoderType::Whole(Whole::Deflate, accepts.scratch.clone()));
+                }
+                return Some(DecoderType::Deflate);
+            }
+        }
+
+        None
+    }
+}
+
+/// Read the whole of `encoded` into `scratch`, then decompress it with
+/// libdeflate on the blocking pool, so that a large body doesn't stall the
+/// runtime.
+#[cfg(feature = "libdeflate")]
+fn decode_whole(encoded: PeekableIoStream, format: Whole, scratch: Arc<Scratch>) -> DecoderStream {
+    use futures_util::TryStreamExt;
+
+    Box::pin(futures_util::stream::once(async move {
+        let limit = scratch.limit;
+        let encoded = encoded
+            .try_fold(scratch.take(), |mut buf, chunk| async move {
+                if buf.len() + chunk.len() > limit {
+                    return Err(too_large());
+                }
+                buf.extend_from_slice(&chunk);
+                Ok(buf)
+            })
+            .await?;
+        let inflating = move || {
+            let decoded = inflate(&encoded, format, limit);
+            scratch.give(encoded);
+            decoded
+        };
+        match tokio::task::spawn_blocking(inflating).await {
+            Ok(decoded) => decoded.map(Bytes::from),
+            Err(err) => match err.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+            },
+        }
+    }))
+}
+
+#[cfg(feature = "libdeflate")]
+fn inflate(encoded: &[u8], format: Whole, max: usize) -> io::Result<Vec<u8>> {
+    use libdeflater::{DecompressionError, Decompressor};
+
+    // libdeflate needs room for the whole output, which can't be more than
+    // the largest expansion of the input. A gzip trailer ends with the size
+    // of the body modulo 2^32, but as the server sets it, it is only a hint,
+    // and the room is grown until the body fits.
+    let limit = encoded
+        .len()
+        .saturating_mul(MAX_DEFLATE_RATIO)
+        .clamp(4096.min(max), max);
+    let mut size = match format {
+        Whole::Gzip if encoded.len() >= 18 => {
+            let trailer = &encoded[encoded.len() - 4..];
+            u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) as usize
+        }
+        _ => encoded.len().saturating_mul(4),
+    }
+    .clamp(4096.min(limit), limit);
+
+    let mut decompressor = Decompressor::new();
+    loop {
+        let mut out = vec![0; size];
+        let decompressed = match format {
+            Whole::Gzip => decompressor.gzip_decompress(encoded, &mut out),
+            #[cfg(feature = "deflate")]
+            Whole::Deflate => decompressor.deflate_decompress(encoded, &mut out),
+        };
+        match decompressed {
+            Ok(len) => {
+                out.truncate(len);
+                return Ok(out);
+            }
+            Err(DecompressionError::InsufficientSpace) if size < limit => {
+                size = size.saturating_mul(2).min(limit);
+            }
+            Err(DecompressionError::InsufficientSpace) => return Err(too_large()),
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+}
+
+#[cfg(feature = "libdeflate")]
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "decompressed body is too large")
+}
+
+/// The error of a body with more than `MAX_ENCODINGS` encodings.
+#[derive(Debug)]
+struct TooManyEncodings(usize);
+
+impl fmt::Display for TooManyEncodings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "response body has {} stacked encodings, more than the {} decoded",
+            self.0, MAX_ENCODINGS
+        )
+    }
+}
+
+impl std::error::Error for TooManyEncodings {}
+
 // ===== impl Accepts =====
 
 impl Accepts {
@@ -432,6 +776,124 @@ impl Accepts {
             zstd: false,
             #[cfg(feature = "deflate")]
             deflate: false,
+            #[cfg(feature = "libdeflate")]
+            backend: DecompressionBackend::Streaming,
+            #[cfg(feature = "libdeflate")]
+            scratch: Arc::new(Scratch::new(MAX_WHOLE_SIZE)),
+            custom: Arc::new([]),
+        }
+    }
+
+    /// Accept only `encodings`.
+    pub(super) fn from_encodings(_encodings: &[ContentEncoding]) -> Self {
+        Accepts {
+            #[cfg(feature = "gzip")]
+            gzip: _encodings.contains(&ContentEncoding::Gzip),
+            #[cfg(feature = "brotli")]
+            brotli: _encodings.contains(&ContentEncoding::Brotli),
+            #[cfg(feature = "zstd")]
+            zstd: _encodings.contains(&ContentEncoding::Zstd),
+            #[cfg(feature = "deflate")]
+            deflate: _encodings.contains(&ContentEncoding::Deflate),
+            #[cfg(feature = "libdeflate")]
+            backend: DecompressionBackend::Streaming,
+            #[cfg(feature = "libdeflate")]
+            scratch: Arc::new(Scratch::new(MAX_WHOLE_SIZE)),
+            custom: Arc::new([]),
+        }
+    }
+
+    /// Register `decoder` for `coding`, replacing a decoder registered
+    /// for it before.
+    pub(super) fn register(&mut self, coding: &str, decoder: Arc<dyn ContentDecoder>) {
+        let mut custom = self
+            .custom
+            .iter()
+            .filter(|custom| !custom.coding.eq_ignore_ascii_case(coding))
+            .cloned()
+            .collect::<Vec<_>>();
+        custom.push(CustomDecoder {
+            coding: coding.to_ascii_lowercase(),
+            decoder,
+        });
+        self.custom = custom.into();
+    }
+
+    /// The value of `Accept-Encoding` for these codings.
+    pub(super) fn header_value(&self) -> Option<HeaderValue> {
+        let builtin = self.as_str();
+        let mut value = builtin.unwrap_or_default().to_owned();
+        for custom in self.custom.iter() {
+            let listed = value
+                .split(',')
+                .any(|coding| coding.trim().eq_ignore_ascii_case(&custom.coding));
+            if !listed {
+                if !value.is_empty() {
+                    value.push_str(", ");
+                }
+                value.push_str(&custom.coding);
+            }
+        }
+        match builtin {
+            Some(builtin) if builtin.len() == value.len() => {
+                Some(HeaderValue::from_static(builtin))
+            }
+            _ if value.is_empty() => None,
+            _ => HeaderValue::from_str(&value).ok(),
+        }
+    }
+
+    /// The registered codings missing from `value`, to add to an
+    /// `Accept-Encoding` set by default headers.
+    pub(super) fn append_custom(&self, value: &HeaderValue) -> Option<HeaderValue> {
+        let value = value.to_str().ok()?;
+        let missing = self
+            .custom
+            .iter()
+            .filter(|custom| {
+                !value
+                    .split(',')
+                    .any(|coding| coding.trim().eq_ignore_ascii_case(&custom.coding))
+            })
+            .map(|custom| custom.coding.as_str())
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&format!("{}, {}", value, missing.join(", "))).ok()
+    }
+
+    /// `value` of an `Accept-Encoding` without the codings these don't
+    /// decode, or `None` if none are left.
+    pub(super) fn restrict(&self, value: &HeaderValue) -> Option<HeaderValue> {
+        let value = value.to_str().ok()?;
+        let kept = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| {
+                let coding = item.split(';').next().unwrap_or_default().trim();
+                self.decodes(coding)
+            })
+            .collect::<Vec<_>>();
+        if kept.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&kept.join(", ")).ok()
+    }
+
+    /// Whether a body with `coding` is decoded, or needs no decoding.
+    fn decodes(&self, coding: &str) -> bool {
+        let is = |name: &str| coding.eq_ignore_ascii_case(name);
+        if is("gzip") || is("x-gzip") {
+            self.is_gzip()
+        } else if is("br") {
+            self.is_brotli()
+        } else if is("zstd") {
+            self.is_zstd()
+        } else if is("deflate") {
+            self.is_deflate()
+        } else {
+            is("identity") || self.custom.iter().any(|custom| is(&custom.coding))
         }
     }
 
@@ -521,6 +983,11 @@ impl Default for Accepts {
             zstd: true,
             #[cfg(feature = "deflate")]
             deflate: true,
+            #[cfg(feature = "libdeflate")]
+            backend: DecompressionBackend::Streaming,
+            #[cfg(feature = "libdeflate")]
+            scratch: Arc::new(Scratch::new(MAX_WHOLE_SIZE)),
+            custom: Arc::new([]),
         }
     }
 }
@@ -0,0 +1,256 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use bytes::Bytes;
+use http_body::Body as HttpBody;
+
+use crate::error::BoxError;
+
+/// The priority of a request in the client's in-flight queue.
+///
+/// When the client limits its in-flight requests with
+/// [`ClientBuilder::max_in_flight`](crate::ClientBuilder::max_in_flight),
+/// queued `Foreground` requests always start before queued `Background`
+/// ones, so prefetching never delays interactive calls sharing the client.
+/// Each priority can additionally be capped with
+/// [`ClientBuilder::max_in_flight_for`](crate::ClientBuilder::max_in_flight_for).
+///
+/// Set with [`RequestBuilder::priority`](crate::RequestBuilder::priority).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Interactive requests, the default.
+    #[default]
+    Foreground,
+    /// Requests that may wait, such as prefetching.
+    Background,
+}
+
+const FOREGROUND: usize = Priority::Foreground as usize;
+const BACKGROUND: usize = Priority::Background as usize;
+
+/// Limits the requests a client runs at once, queueing the others by
+/// priority.
+#[derive(Debug)]
+pub(crate) struct InFlightLimit {
+    max: Option<usize>,
+    caps: [Option<usize>; 2],
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: [usize; 2],
+    // Queued requests of each priority, oldest first.
+    waiting: [VecDeque<(usize, Waker)>; 2],
+    next_id: usize,
+}
+
+impl InFlightLimit {
+    pub(crate) fn new(max: Option<usize>, caps: [Option<usize>; 2]) -> InFlightLimit {
+        InFlightLimit {
+            max,
+            caps,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub(crate) fn acquire(self: &Arc<Self>, priority: Priority) -> Acquire {
+        Acquire {
+            limit: self.clone(),
+            priority: priority as usize,
+            id: None,
+        }
+    }
+
+    /// Whether the request `id` (or a new one, if `None`) may start now.
+    fn can_start(&self, state: &State, priority: usize, id: Option<usize>) -> bool {
+        let first = match state.waiting[priority].front() {
+            Some(&(head, _)) => Some(head) == id,
+            None => true,
+        };
+        let total = state.in_flight.iter().sum::<usize>();
+        let below_max = self.max.map_or(true, |max| total < max);
+        let below_cap = self.caps[priority].map_or(true, |cap| state.in_flight[priority] < cap);
+        // Background requests give way to foreground ones that are only
+        // waiting for a free slot, not for their own cap.
+        let yields = priority == BACKGROUND
+            && !state.waiting[FOREGROUND].is_empty()
+            && self.caps[FOREGROUND].map_or(true, |cap| state.in_flight[FOREGROUND] < cap);
+        first && below_max && below_cap && !yields
+    }
+}
+
+impl State {
+    fn wake_first(&self) {
+        for queue in &self.waiting {
+            if let Some((_, waker)) = queue.front() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+
+    fn dequeue(&mut self, priority: usize, id: usize) {
+        self.waiting[priority].retain(|&(other, _)| other != id);
+    }
+}
+
+/// A request waiting for a slot of an `InFlightLimit`.
+pub(crate) struct Acquire {
+    limit: Arc<InFlightLimit>,
+    priority: usize,
+    id: Option<usize>,
+}
+
+impl Acquire {
+    pub(crate) fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<Permit> {
+        let mut state = self.limit.state.lock().unwrap();
+        if self.limit.can_start(&state, self.priority, self.id) {
+            if let Some(id) = self.id.take() {
+                state.dequeue(self.priority, id);
+            }
+            state.in_flight[self.priority] += 1;
+            // The next requests in line may fit as well.
+            state.wake_first();
+            return Poll::Ready(Permit {
+                limit: self.limit.clone(),
+                priority: self.priority,
+            });
+        }
+
+        match self.id {
+            Some(id) => {
+                if let Some(entry) = state.waiting[self.priority]
+                    .iter_mut()
+                    .find(|(other, _)| *other == id)
+                {
+                    entry.1 = cx.waker().clone();
+                }
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.waiting[self.priority].push_back((id, cx.waker().clone()));
+                self.id = Some(id);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut state = self.limit.state.lock().unwrap();
+            state.dequeue(self.priority, id);
+            state.wake_first();
+        }
+    }
+}
+
+/// A slot of an `InFlightLimit`, released when dropped.
+pub(crate) struct Permit {
+    limit: Arc<InFlightLimit>,
+    priority: usize,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.limit.state.lock().unwrap();
+        state.in_flight[self.priority] -= 1;
+        state.wake_first();
+    }
+}
+
+/// A response body that releases its request's `Permit` once it ends.
+pub(crate) struct PermitBody<B> {
+    pub(crate) inner: B,
+    pub(crate) permit: Option<Permit>,
+}
+
+impl<B> HttpBody for PermitBody<B>
+where
+    B: HttpBody<Data = Bytes, Error = BoxError> + Unpin,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let item = futures_core::ready!(Pin::new(&mut self.inner).poll_data(cx));
+        if !matches!(item, Some(Ok(_))) {
+            self.permit = None;
+        }
+        Poll::Ready(item)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(acquire: &mut Acquire) -> Option<Permit> {
+        let waker = futures_util::task::noop_waker();
+        match acquire.poll_acquire(&mut Context::from_waker(&waker)) {
+            Poll::Ready(permit) => Some(permit),
+            Poll::Pending => None,
+        }
+    }
+
+    #[test]
+    fn foreground_goes_first() {
+        let limit = Arc::new(InFlightLimit::new(Some(1), [None, None]));
+        let running = poll(&mut limit.acquire(Priority::Foreground)).unwrap();
+
+        let mut background = limit.acquire(Priority::Background);
+        let mut foreground = limit.acquire(Priority::Foreground);
+        assert!(poll(&mut background).is_none());
+        assert!(poll(&mut foreground).is_none());
+
+        drop(running);
+        assert!(poll(&mut background).is_none());
+        let running = poll(&mut foreground).unwrap();
+
+        drop(running);
+        assert!(poll(&mut background).is_some());
+    }
+
+    #[test]
+    fn per_priority_caps() {
+        let limit = Arc::new(InFlightLimit::new(Some(3), [None, Some(1)]));
+        let _background = poll(&mut limit.acquire(Priority::Background)).unwrap();
+        assert!(poll(&mut limit.acquire(Priority::Background)).is_none());
+
+        let _first = poll(&mut limit.acquire(Priority::Foreground)).unwrap();
+        let _second = poll(&mut limit.acquire(Priority::Foreground)).unwrap();
+        assert!(poll(&mut limit.acquire(Priority::Foreground)).is_none());
+    }
+
+    #[test]
+    fn capped_foreground_does_not_block_background() {
+        let limit = Arc::new(InFlightLimit::new(Some(4), [Some(1), None]));
+        let _running = poll(&mut limit.acquire(Priority::Foreground)).unwrap();
+
+        let mut foreground = limit.acquire(Priority::Foreground);
+        assert!(poll(&mut foreground).is_none());
+        assert!(poll(&mut limit.acquire(Priority::Background)).is_some());
+    }
+}
@@ -1,7 +1,11 @@
 pub use self::body::Body;
 pub use self::client::{Client, ClientBuilder};
-pub use self::request::{Request, RequestBuilder};
-pub use self::response::Response;
+pub use self::decoder::{ContentDecoder, ContentEncoding, DecoderStream, DecompressionBackend};
+pub use self::in_flight::Priority;
+pub use self::progress::{ContentLengthHint, Progress};
+pub use self::request::{ConnectionMode, Request, RequestBuilder, RequestTemplate, Timeouts};
+pub use self::response::{Response, ResponseHead};
+pub use self::scope::Scope;
 pub use self::upgrade::Upgraded;
 
 #[cfg(feature = "blocking")]
@@ -10,10 +14,13 @@ pub(crate) use self::decoder::Decoder;
 pub mod body;
 pub mod client;
 pub mod decoder;
+mod in_flight;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+mod progress;
 pub(crate) mod request;
 mod response;
+mod scope;
 mod upgrade;
 #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
 pub mod websocket;
@@ -0,0 +1,270 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+use http::HeaderMap;
+use http_body::Body as HttpBody;
+
+use crate::error::BoxError;
+
+/// The length of a response body, as announced by the server.
+///
+/// When a compressed response is decoded transparently, its `Content-Length`
+/// header counts the compressed bytes sent by the server, not the bytes
+/// read from the body, which is only known once the body ends.
+///
+/// Returned by [`Response::content_length_hint`](crate::Response::content_length_hint).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentLengthHint {
+    transfer: Option<u64>,
+    decompressed: bool,
+}
+
+impl ContentLengthHint {
+    /// Get the number of bytes the server sends for the body.
+    pub fn transfer(&self) -> Option<u64> {
+        self.transfer
+    }
+
+    /// Get the number of bytes read from the body, known unless it is
+    /// decompressed.
+    pub fn decoded(&self) -> Option<u64> {
+        if self.decompressed {
+            None
+        } else {
+            self.transfer
+        }
+    }
+
+    /// Whether the body is decompressed as it is read.
+    pub fn is_decompressed(&self) -> bool {
+        self.decompressed
+    }
+}
+
+/// The download progress of a response body.
+///
+/// Counts the bytes received from the server rather than the ones read
+/// from a decompressed body, so that they compare with the
+/// [`transfer`](ContentLengthHint::transfer) length.
+///
+/// Returned by [`Response::progress`](crate::Response::progress), and
+/// updated as the body is read, even after the `Response` was consumed.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let res = rquest::get("https://hyper.rs/large.bin").await?;
+/// let progress = res.progress();
+/// let mut body = res.bytes_stream();
+///
+/// while let Some(chunk) = body.next().await {
+///     let _chunk = chunk?;
+///     if let (Some(fraction), Some(eta)) = (progress.fraction(), progress.eta()) {
+///         println!("{:.0}%, {:?} left", fraction * 100.0, eta);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Progress {
+    transfer: Arc<Transfer>,
+}
+
+#[derive(Debug)]
+pub(super) struct Transfer {
+    received: AtomicU64,
+    started: Instant,
+    length: Option<u64>,
+    encoded: bool,
+    decompressed: AtomicBool,
+}
+
+impl Transfer {
+    /// Start counting the body of a response with `headers`, as received.
+    pub(super) fn new(headers: &HeaderMap) -> Arc<Transfer> {
+        let length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok())
+            .and_then(|len| len.parse().ok());
+        Arc::new(Transfer {
+            received: AtomicU64::new(0),
+            started: Instant::now(),
+            length,
+            encoded: headers.contains_key(CONTENT_ENCODING),
+            decompressed: AtomicBool::new(false),
+        })
+    }
+
+    /// Record whether the body is decompressed, from the `headers` left by
+    /// the `Decoder`, which removes the encoding it decodes.
+    pub(super) fn set_decoded_headers(&self, headers: &HeaderMap) {
+        let decompressed = self.encoded && !headers.contains_key(CONTENT_ENCODING);
+        self.decompressed.store(decompressed, Ordering::Relaxed);
+    }
+
+    pub(super) fn hint(&self) -> ContentLengthHint {
+        ContentLengthHint {
+            transfer: self.length,
+            decompressed: self.decompressed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Progress {
+    pub(super) fn new(transfer: Arc<Transfer>) -> Progress {
+        Progress { transfer }
+    }
+
+    /// Get the number of bytes received so far.
+    pub fn received(&self) -> u64 {
+        self.transfer.received.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of bytes to receive in total, if known.
+    pub fn total(&self) -> Option<u64> {
+        self.transfer.length
+    }
+
+    /// Get the fraction of the body received so far, between 0 and 1.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total()? {
+            0 => Some(1.0),
+            total => Some((self.received() as f64 / total as f64).min(1.0)),
+        }
+    }
+
+    /// Get the average rate of the download, in bytes per second.
+    pub fn rate(&self) -> f64 {
+        let elapsed = self.transfer.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.received() as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimate the time left until the body is received, at the average
+    /// rate so far.
+    pub fn eta(&self) -> Option<Duration> {
+        let left = self.total()?.saturating_sub(self.received());
+        if left == 0 {
+            return Some(Duration::ZERO);
+        }
+        let rate = self.rate();
+        if rate > 0.0 {
+            // A `Content-Length` set by the server may leave more than a
+            // `Duration` holds.
+            Duration::try_from_secs_f64(left as f64 / rate).ok()
+        } else {
+            None
+        }
+    }
+}
+
+/// A response body counting the bytes received into a `Transfer`.
+pub(crate) struct CountingBody<B> {
+    pub(crate) inner: B,
+    pub(crate) transfer: Arc<Transfer>,
+}
+
+impl<B> HttpBody for CountingBody<B>
+where
+    B: HttpBody<Data = Bytes, Error = BoxError> + Unpin,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let item = futures_core::ready!(Pin::new(&mut self.inner).poll_data(cx));
+        if let Some(Ok(ref chunk)) = item {
+            self.transfer
+                .received
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        Poll::Ready(item)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(headers: &[(&'static str, &'static str)], decode: bool) -> Arc<Transfer> {
+        let mut map = HeaderMap::new();
+        for &(name, value) in headers {
+            map.insert(name, value.parse().unwrap());
+        }
+        let transfer = Transfer::new(&map);
+        if decode {
+            map.remove(CONTENT_ENCODING);
+            map.remove(CONTENT_LENGTH);
+        }
+        transfer.set_decoded_headers(&map);
+        transfer
+    }
+
+    #[test]
+    fn length_hint() {
+        let plain = transfer(&[("content-length", "100")], false).hint();
+        assert_eq!(plain.transfer(), Some(100));
+        assert_eq!(plain.decoded(), Some(100));
+
+        let headers = [("content-length", "40"), ("content-encoding", "gzip")];
+        let gzip = transfer(&headers, true).hint();
+        assert_eq!(gzip.transfer(), Some(40));
+        assert_eq!(gzip.decoded(), None);
+        assert!(gzip.is_decompressed());
+
+        // Not decoded, as it was not accepted.
+        let raw = transfer(&headers, false).hint();
+        assert_eq!(raw.decoded(), Some(40));
+    }
+
+    #[test]
+    fn fraction_and_eta() {
+        let transfer = transfer(&[("content-length", "100")], false);
+        let progress = Progress::new(transfer.clone());
+        assert_eq!(progress.fraction(), Some(0.0));
+
+        transfer.received.store(100, Ordering::Relaxed);
+        assert_eq!(progress.fraction(), Some(1.0));
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn eta_beyond_duration() {
+        let mut transfer = transfer(&[("content-length", "18446744073709551615")], false);
+        let started = Instant::now().checked_sub(Duration::from_secs(60)).unwrap();
+        Arc::get_mut(&mut transfer).unwrap().started = started;
+        transfer.received.store(1, Ordering::Relaxed);
+
+        assert_eq!(Progress::new(transfer).eta(), None);
+    }
+}
@@ -1,11 +1,14 @@
 use std::fmt;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use encoding_rs::{Encoding, UTF_8};
 use futures_util::stream::StreamExt;
 use hyper::client::connect::HttpInfo;
+use hyper::header::HeaderValue;
 use hyper::{HeaderMap, StatusCode, Version};
 use mime::Mime;
 #[cfg(feature = "json")]
@@ -17,9 +20,28 @@ use url::Url;
 
 use super::body::Body;
 use super::decoder::{Accepts, Decoder};
+use super::in_flight::Permit;
+use super::progress::{ContentLengthHint, Progress, Transfer};
+use super::scope::ScopeGuard;
 #[cfg(feature = "cookies")]
 use crate::cookie;
-use crate::response::ResponseUrl;
+use crate::http2::ReceivedPreface;
+use crate::raw_head::{self, HeadRecorder, RawHead};
+use crate::response::{History, ResponseUrl, EMPTY_HISTORY};
+use crate::timing::Exchange;
+use crate::MediaType;
+
+/// The status and headers of a `Response` whose body is left unread.
+///
+/// Returned by [`RequestBuilder::send_head_first`], so that the caller
+/// decides what happens to the body once it has seen the head: read it as
+/// a `Response`, [`discard`](ResponseHead::discard) it while keeping the
+/// connection for other requests, or [`abort`](ResponseHead::abort) it.
+///
+/// [`RequestBuilder::send_head_first`]: crate::RequestBuilder::send_head_first
+pub struct ResponseHead {
+    res: Response,
+}
 
 /// A Response to a submitted `Request`.
 pub struct Response {
@@ -27,23 +49,54 @@ pub struct Response {
     // Boxed to save space (11 words to 1 word), and it's not accessed
     // frequently internally.
     url: Box<Url>,
+    history: Option<Box<History>>,
+    transfer: Arc<Transfer>,
 }
 
 impl Response {
     pub(super) fn new(
         res: hyper::Response<hyper::Body>,
         url: Url,
-        accepts: Accepts,
+        accepts: &Accepts,
         timeout: Option<Pin<Box<Sleep>>>,
+        read_timeout: Option<Duration>,
+        scope: Option<ScopeGuard>,
+        permit: Option<Permit>,
     ) -> Response {
         let (mut parts, body) = res.into_parts();
-        let decoder = Decoder::detect(&mut parts.headers, Body::response(body, timeout), accepts);
+        if let Some(recorder) = parts.extensions.remove::<HeadRecorder>() {
+            if let Some(head) = recorder.latest() {
+                parts.extensions.insert(RawHead(head));
+            }
+        }
+        let transfer = Transfer::new(&parts.headers);
+        let mut body = Body::response(body, timeout).counted(transfer.clone());
+        if let Some(timeout) = read_timeout {
+            body = body.with_read_timeout(timeout);
+        }
+        if let Some(guard) = scope {
+            body = body.scoped(guard);
+        }
+        if let Some(permit) = permit {
+            body = body.with_permit(permit);
+        }
+        let decoder = Decoder::detect(&mut parts.headers, body, accepts);
+        transfer.set_decoded_headers(&parts.headers);
         let res = hyper::Response::from_parts(parts, decoder);
 
         Response {
             res,
             url: Box::new(url),
+            history: None,
+            transfer,
+        }
+    }
+
+    pub(super) fn with_history(mut self, history: History) -> Response {
+        if !history.is_empty() {
+            self.history = Some(Box::new(history));
         }
+        self
     }
 
     /// Get the `StatusCode` of this `Response`.
@@ -70,6 +123,35 @@ impl Response {
         self.res.headers_mut()
     }
 
+    /// Get the headers of this `Response` as received, including every
+    /// repeated one.
+    ///
+    /// With [`ClientBuilder::http1_preserve_response_headers`], the headers
+    /// of an HTTP/1 response are listed in the order the server sent them,
+    /// with their original names, before any were removed by decompression.
+    /// Otherwise, they are those of [`headers`](Response::headers), where
+    /// the values of a repeated header follow each other, in order.
+    ///
+    /// [`ClientBuilder::http1_preserve_response_headers`]: crate::ClientBuilder::http1_preserve_response_headers
+    pub fn headers_ordered(&self) -> Vec<(String, HeaderValue)> {
+        match self.res.extensions().get::<RawHead>() {
+            Some(head) => raw_head::fields(&head.0),
+            None => self
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.as_str().to_owned(), value.clone()))
+                .collect(),
+        }
+    }
+
+    /// Get the kind of content the server sent, according to its `Content-Type`.
+    ///
+    /// Returns `None` if the header is missing or not one of the known
+    /// [`MediaType`]s.
+    pub fn negotiated_type(&self) -> Option<MediaType> {
+        MediaType::from_headers(self.headers())
+    }
+
     /// Get the content-length of this response, if known.
     ///
     /// Reasons it may not be known:
@@ -83,6 +165,24 @@ impl Response {
         HttpBody::size_hint(self.res.body()).exact()
     }
 
+    /// Get the length of this response as announced by the server, telling
+    /// apart the bytes sent from the bytes read when the body is
+    /// decompressed.
+    ///
+    /// Unlike [`content_length`](Response::content_length), the length of
+    /// a decompressed body is still known, as its
+    /// [`transfer`](ContentLengthHint::transfer) length.
+    pub fn content_length_hint(&self) -> ContentLengthHint {
+        self.transfer.hint()
+    }
+
+    /// Get a handle to follow the download of the body of this response.
+    ///
+    /// See [`Progress`] for an example.
+    pub fn progress(&self) -> Progress {
+        Progress::new(self.transfer.clone())
+    }
+
     /// Retrieve the cookies contained in the response.
     ///
     /// Note that invalid 'Set-Cookie' headers will be ignored.
@@ -102,6 +202,23 @@ impl Response {
         &self.url
     }
 
+    /// Get the redirects followed and retries taken to get this `Response`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let res = rquest::get("http://httpbin.org/redirect/2").await?;
+    /// for entry in res.history().entries() {
+    ///     println!("{} {}", entry.status(), entry.url());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn history(&self) -> &History {
+        self.history.as_deref().unwrap_or(&EMPTY_HISTORY)
+    }
+
     /// Get the remote address used to get this `Response`.
     pub fn remote_addr(&self) -> Option<SocketAddr> {
         self.res
@@ -110,6 +227,84 @@ impl Response {
             .map(|info| info.remote_addr())
     }
 
+    /// Get which resolver produced the address of the connection used to
+    /// get this `Response`.
+    ///
+    /// This is `None` when the host was an IP address. Through a proxy, it
+    /// is the resolver of the proxy address.
+    pub fn resolved_by(&self) -> Option<crate::dns::ResolvedBy> {
+        self.res
+            .extensions()
+            .get::<crate::dns::ResolvedBy>()
+            .copied()
+    }
+
+    /// Get how long establishing the connection used to get this
+    /// `Response` took, phase by phase.
+    ///
+    /// This is the same for all the responses of a pooled connection, so
+    /// it tells how long the first request waited for it.
+    pub fn connect_timings(&self) -> Option<crate::ConnectTimings> {
+        self.res
+            .extensions()
+            .get::<crate::ConnectTimings>()
+            .copied()
+    }
+
+    /// Get what was negotiated with the server of this `Response`, and how
+    /// long each phase of getting it took.
+    ///
+    /// The report gathers the timings of the connection, the time to the
+    /// first byte of the response, the TLS parameters if
+    /// [`ClientBuilder::tls_info`](crate::ClientBuilder::tls_info) is set,
+    /// and the settings an HTTP/2 server sent, to tune an impersonation
+    /// profile or find out why an origin resets connections.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let res = rquest::get("https://example.com").await?;
+    /// let report = res.handshake_report();
+    /// if let Some(connection) = report.connection() {
+    ///     println!("dns {:?}, tls {:?}", connection.dns(), connection.tls());
+    /// }
+    /// if let Some(http2) = report.http2() {
+    ///     println!("server settings {:?}", http2.settings());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn handshake_report(&self) -> crate::HandshakeReport {
+        let extensions = self.res.extensions();
+        let mut report = crate::HandshakeReport::new(
+            self.version(),
+            self.remote_addr(),
+            self.connect_timings(),
+            extensions.get::<Exchange>().copied(),
+        );
+        #[cfg(feature = "__tls")]
+        {
+            report.tls = self.tls_info().cloned();
+        }
+        report.http2 = extensions
+            .get::<ReceivedPreface>()
+            .and_then(ReceivedPreface::get);
+        report
+    }
+
+    /// Get the TLS details of the connection used to get this `Response`.
+    ///
+    /// This is the negotiated version, cipher suite and ALPN protocol, the
+    /// certificates of the peer and whether the session was resumed, which
+    /// shows what handshake an impersonation profile actually made. It is
+    /// `None` for plain HTTP, and unless the client was built with
+    /// [`ClientBuilder::tls_info`](crate::ClientBuilder::tls_info).
+    #[cfg(feature = "__tls")]
+    pub fn tls_info(&self) -> Option<&crate::tls::TlsInfo> {
+        self.res.extensions().get::<crate::tls::TlsInfo>()
+    }
+
     /// Returns a reference to the associated extensions.
     pub fn extensions(&self) -> &http::Extensions {
         self.res.extensions()
@@ -240,6 +435,198 @@ impl Response {
         serde_json::from_slice(&full).map_err(crate::error::decode)
     }
 
+    /// Deserialize a large response body as JSON on the blocking thread pool.
+    ///
+    /// Like [`json`](Response::json), but the body is parsed with
+    /// [`tokio::task::spawn_blocking`] once it is read, so that a payload of
+    /// many megabytes doesn't stall the other tasks of the runtime while it
+    /// is parsed. For small bodies, the hand-off costs more than it saves.
+    ///
+    /// With the `json-simd` feature, the body is parsed with `simd-json`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use serde::Deserialize;
+    /// #
+    /// #[derive(Deserialize)]
+    /// struct Release {
+    ///     name: String,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let releases = rquest::get("https://example.com/releases.json")
+    ///     .await?
+    ///     .json_blocking_offloaded::<Vec<Release>>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the response body is not in JSON format
+    /// or it cannot be properly deserialized to target type `T`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if it is not called from a Tokio runtime, and resumes a
+    /// panic of the deserializer of `T`.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub async fn json_blocking_offloaded<T>(self) -> crate::Result<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let full = self.bytes().await?;
+
+        match tokio::task::spawn_blocking(move || decode_json(full)).await {
+            Ok(result) => result,
+            Err(err) => match err.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(err) => Err(crate::error::decode(err)),
+            },
+        }
+    }
+
+    /// Parse the entries of an RSS, Atom or JSON feed.
+    ///
+    /// The body is decoded with the `charset` of the `Content-Type`, or else
+    /// the encoding of its byte order mark or XML declaration, and relative
+    /// links are resolved against the final URL of the response. See
+    /// [`feed`](crate::feed) for the formats that are recognized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # async fn run() -> Result<(), rquest::Error> {
+    /// let entries = rquest::get("https://blog.rust-lang.org/feed.xml")
+    ///     .await?
+    ///     .feed()
+    ///     .await?;
+    ///
+    /// for entry in entries {
+    ///     println!("{:?}: {:?}", entry.title, entry.link);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `feed` feature to be enabled.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the response body is not a well-formed
+    /// feed.
+    #[cfg(feature = "feed")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "feed")))]
+    pub async fn feed(self) -> crate::Result<Vec<crate::feed::Entry>> {
+        let charset = self
+            .headers()
+            .get(crate::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Mime>().ok())
+            .and_then(|mime| mime.get_param("charset").map(|charset| charset.to_string()));
+        let url = self.url().clone();
+        let full = self.bytes().await?;
+
+        crate::feed::parse(&crate::feed::decode(&full, charset.as_deref()), &url)
+    }
+
+    /// Try to deserialize a response body that is almost JSON.
+    ///
+    /// Like [`json`](Response::json), but first strips a byte order mark,
+    /// comments and trailing commas, and replaces `NaN`, `Infinity` and
+    /// `undefined` with `null`, as found in responses of many endpoints
+    /// serving JavaScript rather than JSON. Fields that may hold non-finite
+    /// numbers must therefore be `Option`s.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever the cleaned up response body is not in
+    /// JSON format or it cannot be properly deserialized to target type `T`.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub async fn json_lenient<T: DeserializeOwned>(self) -> crate::Result<T> {
+        let full = self.bytes().await?;
+
+        serde_json::from_slice(&crate::lenient_json::sanitize(&full)).map_err(crate::error::decode)
+    }
+
+    /// Deserialize the response body as JSON into `T`, or into the error
+    /// payload `E` if the server returned an error.
+    ///
+    /// A response with a status between 400 and 599 is deserialized into
+    /// `E` and returned as [`ApiError::Status`](crate::ApiError::Status),
+    /// any other response into `T`. If the body can not be deserialized,
+    /// the raw body is kept in [`ApiError::Decode`](crate::ApiError::Decode).
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rquest::ApiError;
+    /// # use serde::Deserialize;
+    /// #
+    /// #[derive(Deserialize)]
+    /// struct Ip {
+    ///     origin: String,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Problem {
+    ///     message: String,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), ApiError<Problem>> {
+    /// let ip = rquest::get("http://httpbin.org/ip")
+    ///     .await?
+    ///     .json_or_error::<Ip, Problem>()
+    ///     .await?;
+    ///
+    /// println!("ip: {}", ip.origin);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub async fn json_or_error<T, E>(self) -> Result<T, crate::ApiError<E>>
+    where
+        T: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let status = self.status();
+        let url = self.url().clone();
+        let body = self.bytes().await?;
+
+        let decode = |err| crate::ApiError::Decode {
+            status,
+            body: body.clone(),
+            error: crate::error::decode(err).with_url(url.clone()),
+        };
+        if status.is_client_error() || status.is_server_error() {
+            match serde_json::from_slice(&body) {
+                Ok(error) => Err(crate::ApiError::Status { status, error }),
+                Err(err) => Err(decode(err)),
+            }
+        } else {
+            serde_json::from_slice(&body).map_err(decode)
+        }
+    }
+
     /// Get the full response body as `Bytes`.
     ///
     /// # Example
@@ -311,6 +698,85 @@ impl Response {
         self.res.into_body()
     }
 
+    /// Extract the links of an HTML response body while it downloads.
+    ///
+    /// Relative links are resolved against the final URL of the response.
+    /// See [`links`](crate::links) for the elements that are recognized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut links = rquest::get("http://httpbin.org/links/10/0")
+    ///     .await?
+    ///     .links();
+    ///
+    /// while let Some(url) = links.next().await {
+    ///     println!("found {}", url?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `links` feature to be enabled.
+    #[cfg(feature = "links")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "links")))]
+    pub fn links(self) -> impl futures_core::Stream<Item = crate::Result<Url>> {
+        let extractor = crate::links::LinkExtractor::new(self.url().clone());
+        let state = (
+            Box::pin(self.bytes_stream()),
+            extractor,
+            std::collections::VecDeque::new(),
+        );
+
+        futures_util::stream::unfold(state, |(mut body, mut extractor, mut queue)| async move {
+            loop {
+                if let Some(url) = queue.pop_front() {
+                    return Some((Ok(url), (body, extractor, queue)));
+                }
+                match body.next().await? {
+                    Ok(chunk) => queue.extend(extractor.feed(&chunk)),
+                    Err(err) => return Some((Err(err), (body, extractor, queue))),
+                }
+            }
+        })
+    }
+
+    /// Extract the entries of an archive response body while it downloads.
+    ///
+    /// The body is unpacked as it is read, without buffering the archive. See
+    /// [`archive`](crate::archive) for the formats that are supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rquest::archive::Format;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut archive = rquest::get("https://example.com/dataset.zip")
+    ///     .await?
+    ///     .archive(Format::Zip);
+    ///
+    /// while let Some(entry) = archive.next_entry().await? {
+    ///     println!("{} ({:?} bytes)", entry.path(), entry.size());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `archive` feature to be enabled.
+    #[cfg(feature = "archive")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+    pub fn archive(self, format: crate::archive::Format) -> crate::archive::Archive {
+        crate::archive::Archive::new(format, self.bytes_stream())
+    }
+
     // util methods
 
     /// Turn a response into an error if the server returned an error.
@@ -386,6 +852,88 @@ impl Response {
     }
 }
 
+impl ResponseHead {
+    pub(crate) fn new(res: Response) -> ResponseHead {
+        ResponseHead { res }
+    }
+
+    /// Get the `StatusCode` of the response.
+    #[inline]
+    pub fn status(&self) -> StatusCode {
+        self.res.status()
+    }
+
+    /// Get the HTTP `Version` of the response.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.res.version()
+    }
+
+    /// Get the `Headers` of the response.
+    #[inline]
+    pub fn headers(&self) -> &HeaderMap {
+        self.res.headers()
+    }
+
+    /// Get the final `Url` of the response.
+    #[inline]
+    pub fn url(&self) -> &Url {
+        self.res.url()
+    }
+
+    /// Get the content-length of the response, if it is known.
+    ///
+    /// See [`Response::content_length`].
+    pub fn content_length(&self) -> Option<u64> {
+        self.res.content_length()
+    }
+
+    /// Read the body, as a `Response`.
+    pub fn into_response(self) -> Response {
+        self.res
+    }
+
+    /// Discard the body, reading up to `max` bytes of it.
+    ///
+    /// A connection can only serve other requests once the body sent on it
+    /// was read to its end, so a body of at most `max` bytes is drained and
+    /// its connection kept, while a larger one is aborted without reading
+    /// it when its length is known upfront. Returns whether the body was
+    /// drained.
+    pub async fn discard(self, max: u64) -> crate::Result<bool> {
+        let mut res = self.res;
+        if res.content_length().map_or(false, |len| len > max) {
+            return Ok(false);
+        }
+        let mut read = 0;
+        while let Some(chunk) = res.chunk().await? {
+            read += chunk.len() as u64;
+            if read > max {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Abort the body without reading it.
+    ///
+    /// This closes an HTTP/1 connection, and cancels the stream of an
+    /// HTTP/2 one.
+    pub fn abort(self) {
+        drop(self.res);
+    }
+}
+
+impl fmt::Debug for ResponseHead {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResponseHead")
+            .field("url", self.url())
+            .field("status", &self.status())
+            .field("headers", self.headers())
+            .finish()
+    }
+}
+
 impl fmt::Debug for Response {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Response")
@@ -399,8 +947,10 @@ impl fmt::Debug for Response {
 impl<T: Into<Body>> From<http::Response<T>> for Response {
     fn from(r: http::Response<T>) -> Response {
         let (mut parts, body) = r.into_parts();
-        let body = body.into();
-        let decoder = Decoder::detect(&mut parts.headers, body, Accepts::none());
+        let transfer = Transfer::new(&parts.headers);
+        let body = body.into().counted(transfer.clone());
+        let decoder = Decoder::detect(&mut parts.headers, body, &Accepts::none());
+        transfer.set_decoded_headers(&parts.headers);
         let url = parts
             .extensions
             .remove::<ResponseUrl>()
@@ -410,6 +960,8 @@ impl<T: Into<Body>> From<http::Response<T>> for Response {
         Response {
             res,
             url: Box::new(url),
+            history: None,
+            transfer,
         }
     }
 }
@@ -421,6 +973,21 @@ impl From<Response> for Body {
     }
 }
 
+/// Deserialize a JSON body, off the runtime.
+#[cfg(feature = "json")]
+fn decode_json<T: DeserializeOwned>(full: Bytes) -> crate::Result<T> {
+    #[cfg(feature = "json-simd")]
+    {
+        // simd-json parses in place.
+        let mut full = full.to_vec();
+        simd_json::serde::from_slice(&mut full).map_err(crate::error::decode)
+    }
+    #[cfg(not(feature = "json-simd"))]
+    {
+        serde_json::from_slice(&full).map_err(crate::error::decode)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Response;
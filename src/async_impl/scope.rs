@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::task::AtomicWaker;
+use http_body::Body as HttpBody;
+
+use super::client::Client;
+use crate::error::{Aborted, BoxError};
+
+/// A view of a [`Client`] whose requests are aborted when it is dropped.
+///
+/// Requests sent through a `Scope` (or a clone of [`Scope::client`]) are
+/// tied to it: once the `Scope` is dropped, every one of them that is still
+/// waiting for a response or streaming its body fails with an error, even
+/// if its future was handed to `tokio::spawn`. Requests started after that
+/// fail immediately. This keeps a server handler that is cancelled from
+/// leaking background requests.
+///
+/// A `Scope` created from a scoped client is nested: dropping the outer
+/// scope aborts the requests of the inner one too.
+///
+/// Created with [`Client::scope`].
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn run() -> Result<(), rquest::Error> {
+/// let client = rquest::Client::new();
+/// let scope = client.scope();
+///
+/// let prefetch = tokio::spawn(scope.get("https://example.com/large").send());
+/// let res = scope.get("https://example.com").send().await?;
+///
+/// // `prefetch` fails instead of running on in the background.
+/// drop(scope);
+/// assert!(prefetch.await.unwrap().is_err());
+/// # drop(res);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scope {
+    client: Client,
+    // Keeps this scope registered with its parent, if any.
+    _parent: Option<ScopeGuard>,
+}
+
+impl Scope {
+    pub(super) fn new(client: &Client) -> Scope {
+        let state = Arc::new(ScopeState::default());
+        let parent = match client.scope_state() {
+            Some(parent) => {
+                let guard = parent.enter(Entry::Scope(Arc::downgrade(&state)));
+                if guard.is_none() {
+                    state.close();
+                }
+                guard
+            }
+            None => None,
+        };
+        Scope {
+            client: client.with_scope(state),
+            _parent: parent,
+        }
+    }
+
+    /// The client bound to this scope.
+    ///
+    /// Clones of it stay bound to the scope after they are moved elsewhere.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Deref for Scope {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        if let Some(state) = self.client.scope_state() {
+            state.close();
+        }
+    }
+}
+
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("client", &self.client)
+            .finish()
+    }
+}
+
+enum Entry {
+    Request(Arc<AtomicWaker>),
+    Scope(Weak<ScopeState>),
+}
+
+/// The requests and nested scopes registered with a `Scope`.
+#[derive(Default)]
+pub(crate) struct ScopeState {
+    next_id: AtomicUsize,
+    entries: Mutex<HashMap<usize, Entry>>,
+    closed: AtomicBool,
+}
+
+impl ScopeState {
+    /// Register a request with the scope, or `None` if it is already closed.
+    pub(crate) fn enter_request(self: &Arc<Self>) -> Option<ScopeGuard> {
+        self.enter(Entry::Request(Arc::new(AtomicWaker::new())))
+    }
+
+    fn enter(self: &Arc<Self>, entry: Entry) -> Option<ScopeGuard> {
+        let waker = match entry {
+            Entry::Request(ref waker) => Some(waker.clone()),
+            Entry::Scope(_) => None,
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        if self.closed.load(Ordering::Acquire) {
+            return None;
+        }
+        entries.insert(id, entry);
+        Some(ScopeGuard {
+            state: self.clone(),
+            id,
+            waker,
+        })
+    }
+
+    fn close(&self) {
+        let entries = {
+            let mut entries = self.entries.lock().unwrap();
+            self.closed.store(true, Ordering::Release);
+            std::mem::take(&mut *entries)
+        };
+        for (_, entry) in entries {
+            match entry {
+                Entry::Request(waker) => waker.wake(),
+                Entry::Scope(scope) => {
+                    if let Some(scope) = scope.upgrade() {
+                        scope.close();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A registration with a `Scope`, removed again when dropped.
+pub(crate) struct ScopeGuard {
+    state: Arc<ScopeState>,
+    id: usize,
+    waker: Option<Arc<AtomicWaker>>,
+}
+
+impl ScopeGuard {
+    /// Whether the scope was closed, waking `cx` once it is.
+    pub(crate) fn poll_aborted(&self, cx: &mut Context<'_>) -> bool {
+        if let Some(ref waker) = self.waker {
+            waker.register(cx.waker());
+        }
+        self.state.closed.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        self.state.entries.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// A response body that fails once its request's scope is closed.
+pub(crate) struct ScopedBody<B> {
+    pub(crate) inner: B,
+    pub(crate) guard: ScopeGuard,
+}
+
+impl<B> HttpBody for ScopedBody<B>
+where
+    B: HttpBody<Data = Bytes, Error = BoxError> + Unpin,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        if self.guard.poll_aborted(cx) {
+            return Poll::Ready(Some(Err(Aborted.into())));
+        }
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_scopes_close_with_parent() {
+        let parent = Arc::new(ScopeState::default());
+        let child = Arc::new(ScopeState::default());
+        let _child_guard = parent.enter(Entry::Scope(Arc::downgrade(&child)));
+        let request = child.enter_request().unwrap();
+
+        parent.close();
+
+        let waker = futures_util::task::noop_waker();
+        assert!(request.poll_aborted(&mut Context::from_waker(&waker)));
+        assert!(child.enter_request().is_none());
+    }
+
+    #[test]
+    fn finished_requests_are_unregistered() {
+        let scope = Arc::new(ScopeState::default());
+        let request = scope.enter_request().unwrap();
+        assert_eq!(scope.entries.lock().unwrap().len(), 1);
+        drop(request);
+        assert!(scope.entries.lock().unwrap().is_empty());
+    }
+}
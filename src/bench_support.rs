@@ -0,0 +1,128 @@
+//! Fixtures for benchmarking the client.
+//!
+//! The benchmarks in `benches/` run against a local [`TestServer`], so their
+//! results don't depend on the network. [`Bench`] bundles that server with a
+//! runtime and builds the clients being measured, which lets contributors
+//! benchmark a change of their own the same way. Run the suite with
+//! `cargo bench --features bench-support`, adding the `zlib-ng` and
+//! `libdeflate` features to measure the throughput of those decompression
+//! backends as well.
+//!
+//! This requires the optional `bench-support` feature to be enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rquest::bench_support::Bench;
+//!
+//! let bench = Bench::new();
+//! let client = bench.client(None);
+//! let url = bench.server().url("/status/200");
+//!
+//! let start = std::time::Instant::now();
+//! for _ in 0..1000 {
+//!     bench.block_on(async { client.get(&url).send().await.unwrap() });
+//! }
+//! println!("{:?} per request", start.elapsed() / 1000);
+//! ```
+
+use std::fmt;
+use std::future::Future;
+
+use tokio::runtime::Runtime;
+
+use crate::impersonate::Impersonate;
+use crate::test_server::TestServer;
+use crate::{Client, DecompressionBackend};
+
+/// A profile of each browser family, for benchmarks run per profile.
+pub const PROFILES: &[Impersonate] = &[
+    Impersonate::Chrome127,
+    Impersonate::Edge127,
+    Impersonate::Safari17_5,
+    Impersonate::SafariIos17_4_1,
+    Impersonate::OkHttp5,
+];
+
+/// A local server and the runtime requests to it are driven on.
+pub struct Bench {
+    server: TestServer,
+    runtime: Runtime,
+}
+
+impl Bench {
+    /// Start the server and the runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server or the runtime can't be started.
+    pub fn new() -> Bench {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("bench runtime");
+        Bench {
+            server: TestServer::start(),
+            runtime,
+        }
+    }
+
+    /// The server to send requests to.
+    pub fn server(&self) -> &TestServer {
+        &self.server
+    }
+
+    /// Run `future` to completion on the runtime.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// A client keeping its connections, to measure requests alone.
+    ///
+    /// The client trusts the self-signed certificate of the server.
+    pub fn client(&self, impersonate: Option<Impersonate>) -> Client {
+        self.build(impersonate, usize::MAX, DecompressionBackend::Streaming)
+    }
+
+    /// A client keeping its connections and decompressing gzip and deflate
+    /// bodies with `backend`, to compare the backends.
+    pub fn decompressing_client(&self, backend: DecompressionBackend) -> Client {
+        self.build(None, usize::MAX, backend)
+    }
+
+    /// A client opening a connection for each request, to measure the
+    /// connection and TLS handshake along with it.
+    pub fn unpooled_client(&self, impersonate: Option<Impersonate>) -> Client {
+        self.build(impersonate, 0, DecompressionBackend::Streaming)
+    }
+
+    fn build(
+        &self,
+        impersonate: Option<Impersonate>,
+        max_idle: usize,
+        backend: DecompressionBackend,
+    ) -> Client {
+        let mut builder = Client::builder()
+            .danger_accept_invalid_certs(true)
+            .pool_max_idle_per_host(max_idle)
+            .decompression_backend(backend);
+        if let Some(impersonate) = impersonate {
+            builder = builder.impersonate(impersonate);
+        }
+        builder.build().expect("bench client")
+    }
+}
+
+impl Default for Bench {
+    fn default() -> Bench {
+        Bench::new()
+    }
+}
+
+impl fmt::Debug for Bench {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bench")
+            .field("server", &self.server)
+            .finish()
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -14,11 +15,14 @@ use tokio::sync::{mpsc, oneshot};
 use super::request::{Request, RequestBuilder};
 use super::response::Response;
 use super::wait;
+use crate::http2::{Direction, Frame};
 #[cfg(feature = "__impersonate")]
 use crate::impersonate::Impersonate;
 #[cfg(feature = "__tls")]
 use crate::tls;
-use crate::{async_impl, header, redirect, IntoUrl, Method, Proxy};
+use crate::{
+    async_impl, header, redirect, ContentDecoder, DecompressionBackend, IntoUrl, Method, Proxy,
+};
 
 /// A `Client` to make Requests with.
 ///
@@ -90,6 +94,20 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.impersonate(ver))
     }
 
+    /// Sends the headers of the specified impersonate version, keeping the
+    /// default TLS and HTTP/2 settings.
+    #[cfg(feature = "__impersonate")]
+    pub fn impersonate_headers(self, ver: Impersonate) -> ClientBuilder {
+        self.with_inner(move |inner| inner.impersonate_headers(ver))
+    }
+
+    /// Uses the TLS and HTTP/2 settings of the specified impersonate version,
+    /// keeping the default headers.
+    #[cfg(feature = "__impersonate")]
+    pub fn impersonate_tls(self, ver: Impersonate) -> ClientBuilder {
+        self.with_inner(move |inner| inner.impersonate_tls(ver))
+    }
+
     /// Enable Encrypted Client Hello (Secure SNI)
     #[cfg(feature = "__impersonate")]
     pub fn enable_ech_grease(self) -> ClientBuilder {
@@ -108,6 +126,14 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.pre_shared_key())
     }
 
+    /// Offer, or not, the hybrid post-quantum key share of Chromium.
+    ///
+    /// See [`crate::ClientBuilder::post_quantum_key_share`].
+    #[cfg(feature = "__impersonate")]
+    pub fn post_quantum_key_share(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(move |inner| inner.post_quantum_key_share(enabled))
+    }
+
     /// Returns a `Client` that uses this `ClientBuilder` configuration.
     ///
     /// # Errors
@@ -201,6 +227,13 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.default_headers(headers))
     }
 
+    /// Append query pairs to the URL of every request.
+    ///
+    /// See [`crate::ClientBuilder::default_query`].
+    pub fn default_query<T: serde::Serialize + ?Sized>(self, query: &T) -> ClientBuilder {
+        self.with_inner(move |inner| inner.default_query(query))
+    }
+
     /// Change the order in which headers will be sent
     ///
     /// Warning
@@ -370,6 +403,24 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.no_deflate())
     }
 
+    /// Set how gzip and deflate response bodies are decompressed.
+    ///
+    /// See [`crate::ClientBuilder::decompression_backend`].
+    pub fn decompression_backend(self, backend: DecompressionBackend) -> ClientBuilder {
+        self.with_inner(move |inner| inner.decompression_backend(backend))
+    }
+
+    /// Decode response bodies with the content coding `coding` using
+    /// `decoder`, and advertise it in `Accept-Encoding`.
+    ///
+    /// See [`crate::ClientBuilder::content_decoder`].
+    pub fn content_decoder<D>(self, coding: &str, decoder: D) -> ClientBuilder
+    where
+        D: ContentDecoder,
+    {
+        self.with_inner(move |inner| inner.content_decoder(coding, decoder))
+    }
+
     // Redirect options
 
     /// Set a `redirect::Policy` for this client.
@@ -423,6 +474,14 @@ impl ClientBuilder {
         self
     }
 
+    /// Enables a read timeout, applied to waiting for the response headers
+    /// and then to each read of the response body.
+    ///
+    /// Default is no timeout.
+    pub fn read_timeout(self, timeout: Duration) -> ClientBuilder {
+        self.with_inner(move |inner| inner.read_timeout(timeout))
+    }
+
     /// Set a timeout for only the connect phase of a `Client`.
     ///
     /// Default is `None`.
@@ -448,6 +507,13 @@ impl ClientBuilder {
         self.with_inner(move |inner| inner.connection_verbose(verbose))
     }
 
+    /// Capture the decrypted traffic of connections to a file.
+    ///
+    /// See [`crate::ClientBuilder::wire_capture`] for the format of the file.
+    pub fn wire_capture<P: AsRef<Path>>(self, path: P) -> ClientBuilder {
+        self.with_inner(move |inner| inner.wire_capture(path))
+    }
+
     // HTTP options
 
     /// Set an optional timeout for idle sockets being kept-alive.
@@ -500,6 +566,12 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.http1_only())
     }
 
+    /// Set whether the head of HTTP/1 requests is written along with their
+    /// body.
+    pub fn http1_head_writes(self, head_writes: crate::http1::HeadWrites) -> ClientBuilder {
+        self.with_inner(|inner| inner.http1_head_writes(head_writes))
+    }
+
     /// Allow HTTP/0.9 responses
     pub fn http09_responses(self) -> ClientBuilder {
         self.with_inner(|inner| inner.http09_responses())
@@ -510,6 +582,12 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.http2_prior_knowledge())
     }
 
+    /// Use HTTP/2 over cleartext (h2c) with prior knowledge for `http://`
+    /// URLs, while `https://` URLs keep negotiating the version with ALPN.
+    pub fn h2c_prior_knowledge(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.h2c_prior_knowledge())
+    }
+
     /// Sets the `SETTINGS_INITIAL_WINDOW_SIZE` option for HTTP2 stream-level flow control.
     ///
     /// Default is currently 65,535 but may change internally to optimize for common uses.
@@ -568,6 +646,17 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.http2_header_table_size(sz))
     }
 
+    /// Call `callback` with the `SETTINGS`, `WINDOW_UPDATE`, `PING` and
+    /// `GOAWAY` frames of HTTP/2 connections, as they are sent and received.
+    ///
+    /// See [`crate::ClientBuilder::http2_frame_callback`].
+    pub fn http2_frame_callback<F>(self, callback: F) -> ClientBuilder
+    where
+        F: Fn(Direction, &Frame) + Send + Sync + 'static,
+    {
+        self.with_inner(move |inner| inner.http2_frame_callback(callback))
+    }
+
     // TCP options
 
     /// Set whether sockets have `TCP_NODELAY` enabled.
@@ -754,6 +843,100 @@ impl ClientBuilder {
         self.with_inner(|inner| inner.resolve_to_addrs(domain, addrs))
     }
 
+    /// Override the DNS resolver implementation.
+    ///
+    /// See [`ClientBuilder::dns_resolver`](crate::ClientBuilder::dns_resolver).
+    pub fn dns_resolver<R: crate::dns::IntoResolve>(self, resolver: R) -> ClientBuilder {
+        self.with_inner(|inner| inner.dns_resolver(resolver))
+    }
+
+    /// Resolve names with DNS-over-HTTPS, querying the server at `url`.
+    ///
+    /// See [`ClientBuilder::dns_over_https`](crate::ClientBuilder::dns_over_https).
+    #[cfg(feature = "__boring")]
+    pub fn dns_over_https<U: IntoUrl>(self, url: U) -> ClientBuilder {
+        self.with_inner(move |inner| inner.dns_over_https(url))
+    }
+
+    /// Set the addresses of the DNS-over-HTTPS server, instead of looking
+    /// its host up with the system resolver.
+    #[cfg(feature = "__boring")]
+    pub fn dns_over_https_bootstrap(self, addrs: &[IpAddr]) -> ClientBuilder {
+        self.with_inner(|inner| inner.dns_over_https_bootstrap(addrs))
+    }
+
+    /// Encrypt the ClientHello sent to `host` with the `ECHConfigList`
+    /// `list`.
+    ///
+    /// See [`ClientBuilder::ech_config_list`](crate::ClientBuilder::ech_config_list).
+    #[cfg(feature = "__boring")]
+    pub fn ech_config_list<L: Into<Vec<u8>>>(self, host: &str, list: L) -> ClientBuilder {
+        self.with_inner(move |inner| inner.ech_config_list(host, list))
+    }
+
+    /// Look up the ECH configurations of servers in their DNS HTTPS
+    /// records.
+    ///
+    /// See [`ClientBuilder::ech_dns`](crate::ClientBuilder::ech_dns).
+    #[cfg(feature = "__boring")]
+    pub fn ech_dns(self, enabled: bool) -> ClientBuilder {
+        self.with_inner(|inner| inner.ech_dns(enabled))
+    }
+
+    /// Trust the root certificates of the platform's certificate store.
+    ///
+    /// See [`crate::ClientBuilder::tls_native_roots`].
+    #[cfg(feature = "native-roots")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "native-roots")))]
+    pub fn tls_native_roots(self) -> ClientBuilder {
+        self.with_inner(|inner| inner.tls_native_roots())
+    }
+
+    /// Trust `cert` as a root certificate, as well as those the profile
+    /// trusts.
+    ///
+    /// See [`crate::ClientBuilder::add_root_certificate`].
+    #[cfg(feature = "__boring")]
+    pub fn add_root_certificate(self, cert: crate::tls::Certificate) -> ClientBuilder {
+        self.with_inner(move |inner| inner.add_root_certificate(cert))
+    }
+
+    /// Accept connections to `host` only from a server presenting the
+    /// public key with the SHA-256 digest `sha256_of_spki`.
+    ///
+    /// See [`crate::ClientBuilder::pin_server_certificate`].
+    #[cfg(feature = "__boring")]
+    pub fn pin_server_certificate(self, host: &str, sha256_of_spki: [u8; 32]) -> ClientBuilder {
+        self.with_inner(move |inner| inner.pin_server_certificate(host, sha256_of_spki))
+    }
+
+    /// Present `identity` to servers asking for a client certificate.
+    ///
+    /// See [`crate::ClientBuilder::identity`].
+    #[cfg(feature = "__boring")]
+    pub fn identity(self, identity: crate::tls::Identity) -> ClientBuilder {
+        self.with_inner(move |inner| inner.identity(identity))
+    }
+
+    /// Send requests again as `policy` says, when the server is overloaded
+    /// or the connection fails.
+    ///
+    /// See [`crate::ClientBuilder::retry_policy`].
+    pub fn retry_policy(self, policy: crate::retry::Policy) -> ClientBuilder {
+        self.with_inner(move |inner| inner.retry_policy(policy))
+    }
+
+    /// Edit each request of this client before it is sent, and its
+    /// response before it is returned, with `interceptor`.
+    ///
+    /// See [`crate::ClientBuilder::with_interceptor`].
+    pub fn with_interceptor<I: crate::middleware::Interceptor>(
+        self,
+        interceptor: I,
+    ) -> ClientBuilder {
+        self.with_inner(move |inner| inner.with_interceptor(interceptor))
+    }
+
     // private
 
     fn with_inner<F>(mut self, func: F) -> ClientBuilder
@@ -858,6 +1041,74 @@ impl Client {
         self.request(Method::HEAD, url)
     }
 
+    /// Convenience method to make a WebDAV `PROPFIND` request to a URL.
+    ///
+    /// The properties to find are usually sent as an XML body, with a
+    /// `Depth` header.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn propfind<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("PROPFIND", url)
+    }
+
+    /// Convenience method to make a WebDAV `MKCOL` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn mkcol<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("MKCOL", url)
+    }
+
+    /// Convenience method to make a WebDAV `REPORT` request to a URL.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn report<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("REPORT", url)
+    }
+
+    /// Convenience method to make a `PURGE` request to a URL, which caches
+    /// such as Varnish or Fastly take to evict it.
+    ///
+    /// # Errors
+    ///
+    /// This method fails whenever supplied `Url` cannot be parsed.
+    pub fn purge<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.method("PURGE", url)
+    }
+
+    /// Start building a `Request` with the method named `method` and the
+    /// `Url`.
+    ///
+    /// This allows methods that [`Method`] has no constant for, such as
+    /// `SUBSCRIBE` or `LINK`. Names are case-sensitive and sent as they are
+    /// given, except for `DELETE`, `GET`, `HEAD`, `OPTIONS`, `POST` and
+    /// `PUT`, which are uppercased as browsers do.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run(client: &rquest::blocking::Client) {
+    /// let req = client.method("SUBSCRIBE", "http://localhost/events");
+    /// # drop(req);
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method fails if `method` isn't a valid method name, or
+    /// whenever the supplied `Url` cannot be parsed.
+    pub fn method<U: IntoUrl>(&self, method: &str, url: U) -> RequestBuilder {
+        match crate::util::parse_method(method) {
+            Ok(method) => self.request(method, url),
+            Err(err) => RequestBuilder::new(self.clone(), Err(err)),
+        }
+    }
+
     /// Start building a `Request` with the `Method` and `Url`.
     ///
     /// Returns a `RequestBuilder`, which will allow setting headers and
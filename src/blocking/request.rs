@@ -12,8 +12,8 @@ use super::body::{self, Body};
 #[cfg(feature = "multipart")]
 use super::multipart;
 use super::Client;
-use crate::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
-use crate::{async_impl, Method, Url};
+use crate::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use crate::{async_impl, MediaType, Method, Url};
 
 /// A request which can be executed with `Client::execute()`.
 pub struct Request {
@@ -113,6 +113,18 @@ impl Request {
         self.inner.timeout_mut()
     }
 
+    /// Get the read timeout.
+    #[inline]
+    pub fn read_timeout(&self) -> Option<&Duration> {
+        self.inner.read_timeout()
+    }
+
+    /// Get a mutable reference to the read timeout.
+    #[inline]
+    pub fn read_timeout_mut(&mut self) -> &mut Option<Duration> {
+        self.inner.read_timeout_mut()
+    }
+
     /// Attempts to clone the `Request`.
     ///
     /// None is returned if a body is which can not be cloned. This can be because the body is a
@@ -249,6 +261,31 @@ impl RequestBuilder {
         self
     }
 
+    /// Set the `Accept` header from a list of media types, most preferred first.
+    ///
+    /// ```rust
+    /// use rquest::MediaType;
+    ///
+    /// # fn run() -> Result<(), Box<std::error::Error>> {
+    /// let client = rquest::blocking::Client::new();
+    /// let res = client.get("http://httpbin.org/json")
+    ///     .accepts([MediaType::Json, MediaType::Any])
+    ///     .send()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn accepts<I>(mut self, types: I) -> RequestBuilder
+    where
+        I: IntoIterator<Item = MediaType>,
+    {
+        if let Ok(ref mut req) = self.request {
+            let types = types.into_iter().collect::<Vec<_>>();
+            req.headers_mut()
+                .insert(ACCEPT, MediaType::accept_header(&types));
+        }
+        self
+    }
+
     /// Enable HTTP basic authentication.
     ///
     /// ```rust
@@ -350,6 +387,18 @@ impl RequestBuilder {
         self
     }
 
+    /// Enables a read timeout.
+    ///
+    /// The timeout is applied to waiting for the response headers, and then
+    /// to each read of the response body. It affects only this request and
+    /// overrides the timeout configured using `ClientBuilder::read_timeout()`.
+    pub fn read_timeout(mut self, timeout: Duration) -> RequestBuilder {
+        if let Ok(ref mut req) = self.request {
+            *req.read_timeout_mut() = Some(timeout);
+        }
+        self
+    }
+
     /// Modify the query string of the URL.
     ///
     /// Modifies the URL of this request, adding the parameters provided.
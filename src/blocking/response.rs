@@ -15,7 +15,7 @@ use super::client::KeepCoreThreadAlive;
 use super::wait;
 #[cfg(feature = "cookies")]
 use crate::cookie;
-use crate::{async_impl, StatusCode, Url, Version};
+use crate::{async_impl, History, MediaType, StatusCode, Url, Version};
 
 /// A Response to a submitted `Request`.
 pub struct Response {
@@ -163,6 +163,16 @@ impl Response {
         self.inner.url()
     }
 
+    /// Get the kind of content the server sent, according to its `Content-Type`.
+    pub fn negotiated_type(&self) -> Option<MediaType> {
+        self.inner.negotiated_type()
+    }
+
+    /// Get the redirects followed and retries taken to get this `Response`.
+    pub fn history(&self) -> &History {
+        self.inner.history()
+    }
+
     /// Get the remote address used to get this `Response`.
     ///
     /// # Example
@@ -178,6 +188,14 @@ impl Response {
         self.inner.remote_addr()
     }
 
+    /// Get the TLS details of the connection used to get this `Response`.
+    ///
+    /// See [`async_impl::Response::tls_info`](crate::Response::tls_info).
+    #[cfg(feature = "__tls")]
+    pub fn tls_info(&self) -> Option<&crate::tls::TlsInfo> {
+        self.inner.tls_info()
+    }
+
     /// Returns a reference to the associated extensions.
     pub fn extensions(&self) -> &http::Extensions {
         self.inner.extensions()
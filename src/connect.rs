@@ -1,3 +1,13 @@
+#[cfg(feature = "__boring")]
+use boring::error::ErrorStack;
+#[cfg(feature = "__boring")]
+use boring::{
+    pkey::{PKey, Private},
+    ssl::Ssl,
+    x509::X509,
+};
+#[cfg(feature = "__boring")]
+use foreign_types::ForeignType;
 #[cfg(feature = "__tls")]
 use http::header::HeaderValue;
 use http::uri::{Authority, Scheme};
@@ -5,25 +15,47 @@ use http::Uri;
 use hyper::client::connect::{Connected, Connection};
 use hyper::service::Service;
 #[cfg(feature = "__boring")]
+use hyper_boring::{HttpsConnector, HttpsLayer};
+#[cfg(feature = "__boring")]
 use impersonate::BoringTlsConnector;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Sleep;
+#[cfg(feature = "__boring")]
+use tower_layer::Layer;
 
 use pin_project_lite::pin_project;
 use std::future::Future;
 use std::io::{self, IoSlice};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
 use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
 use std::time::Duration;
 
 #[cfg(feature = "__boring")]
 use self::boring_tls_conn::BoringTlsConn;
-use crate::dns::DynResolver;
+#[cfg(feature = "__boring")]
+use crate::alt_svc::{AltService, AltSvcCache};
+use crate::dns::family::FamilyCache;
+use crate::dns::{lookup, DynResolver, ResolvedBy};
 use crate::error::BoxError;
+#[cfg(feature = "__boring")]
+use crate::h2_fallback::{self, H2Fallback};
+use crate::http1::HeadWrites;
+use crate::http2::{
+    FrameCallback, FrameInspector, PrefaceRecorder, PrefaceShape, PrefaceShaper, ShiftedReads,
+};
 #[cfg(feature = "impersonate")]
 use crate::impersonate::{self, ImpersonateContext};
+#[cfg(feature = "__boring")]
+use crate::proxy::ProxyTls;
 use crate::proxy::{Proxy, ProxyScheme};
+#[cfg(feature = "__boring")]
+use crate::proxy_protocol::ProxyHeader;
+use crate::raw_head::{HeadRecorder, StatusLine};
+use crate::timing::{self, ConnectTimings};
+use crate::wire_capture::{ConnCapture, WireCapture};
 
 pub(crate) type HttpConnector = hyper::client::HttpConnector<DynResolver>;
 
@@ -41,6 +73,25 @@ pub(crate) struct Connector {
     user_agent: Option<HeaderValue>,
     #[cfg(feature = "impersonate")]
     context: ImpersonateContext,
+    /// The TLS layer of `context`, built with the client.
+    #[cfg(feature = "__boring")]
+    layer: Arc<(ImpersonateContext, HttpsLayer)>,
+    #[cfg(feature = "__boring")]
+    h2_fallback: Option<Arc<H2Fallback>>,
+    #[cfg(feature = "__boring")]
+    alt_svc: Option<Arc<AltSvcCache>>,
+    #[cfg(feature = "__boring")]
+    proxy_protocol: Option<ProxyHeader>,
+    family_cache: Option<Arc<FamilyCache>>,
+    raw_heads: bool,
+    wire_capture: Option<Arc<WireCapture>>,
+    frame_callback: Option<FrameCallback>,
+    h2_preface: Option<Arc<PrefaceShape>>,
+    h2c: bool,
+    http2_only: bool,
+    merge_limit: Option<usize>,
+    lenient_status_line: bool,
+    buffer_budget: Option<BufferBudget>,
 }
 
 #[derive(Clone)]
@@ -66,7 +117,7 @@ impl Connector {
         nodelay: bool,
         tls_info: bool,
         impersonate_context: ImpersonateContext,
-    ) -> Connector {
+    ) -> Result<Connector, ErrorStack> {
         match (local_addr_v4, local_addr_v6) {
             (Some(v4), Some(v6)) => http.set_local_addresses(v4, v6),
             (Some(v4), None) => http.set_local_address(Some(IpAddr::from(v4))),
@@ -74,8 +125,9 @@ impl Connector {
             _ => {}
         }
         http.enforce_http(false);
+        let layer = tls.layer(&impersonate_context)?;
 
-        Connector {
+        Ok(Connector {
             inner: Inner::BoringTls { http, tls },
             proxies,
             verbose: verbose::OFF,
@@ -83,10 +135,163 @@ impl Connector {
             nodelay,
             user_agent,
             tls_info,
+            layer: Arc::new((impersonate_context.clone(), layer)),
             context: impersonate_context,
+            h2_fallback: None,
+            alt_svc: None,
+            proxy_protocol: None,
+            family_cache: None,
+            raw_heads: false,
+            wire_capture: None,
+            frame_callback: None,
+            h2_preface: None,
+            h2c: false,
+            http2_only: false,
+            merge_limit: None,
+            lenient_status_line: false,
+            buffer_budget: None,
+        })
+    }
+
+    /// The TLS connector for `context`, over `http`.
+    ///
+    /// Only connections with another context than the client's, such as
+    /// those falling back to HTTP/1, look up the layer cache of `tls`.
+    #[cfg(feature = "__boring")]
+    fn https(
+        &self,
+        tls: &BoringTlsConnector,
+        context: &ImpersonateContext,
+        http: HttpConnector,
+    ) -> Result<HttpsConnector<HttpConnector>, ErrorStack> {
+        if *context == self.layer.0 {
+            Ok(self.layer.1.layer(http))
+        } else {
+            tls.create_connector(context, http)
         }
     }
 
+    #[cfg(feature = "__boring")]
+    pub(crate) fn set_proxy_protocol(&mut self, proxy_protocol: Option<ProxyHeader>) {
+        self.proxy_protocol = proxy_protocol;
+    }
+
+    #[cfg(feature = "__boring")]
+    pub(crate) fn set_alt_svc(&mut self, alt_svc: Option<Arc<AltSvcCache>>) {
+        self.alt_svc = alt_svc;
+    }
+
+    pub(crate) fn set_family_cache(&mut self, family_cache: Option<Arc<FamilyCache>>) {
+        self.family_cache = family_cache;
+    }
+
+    pub(crate) fn set_raw_heads(&mut self, enabled: bool) {
+        self.raw_heads = enabled;
+    }
+
+    /// Speak HTTP/2 without negotiating it on direct cleartext connections.
+    pub(crate) fn set_h2c(&mut self, enabled: bool) {
+        self.h2c = enabled;
+    }
+
+    /// Speak HTTP/2 on every connection, whatever was negotiated.
+    pub(crate) fn set_http2_only(&mut self, enabled: bool) {
+        self.http2_only = enabled;
+    }
+
+    /// Split the writes of HTTP/1 connections as `head_writes` does.
+    pub(crate) fn set_head_writes(&mut self, head_writes: Option<HeadWrites>) {
+        self.merge_limit = head_writes.and_then(HeadWrites::merge_limit);
+    }
+
+    /// Collapse the whitespace in the status lines of HTTP/1 responses.
+    pub(crate) fn set_lenient_status_line(&mut self, enabled: bool) {
+        self.lenient_status_line = enabled;
+    }
+
+    /// Hold a share of `budget` for each connection while it is open.
+    pub(crate) fn set_buffer_budget(&mut self, budget: Option<BufferBudget>) {
+        self.buffer_budget = budget;
+    }
+
+    pub(crate) fn set_wire_capture(&mut self, wire_capture: Option<Arc<WireCapture>>) {
+        self.wire_capture = wire_capture;
+    }
+
+    pub(crate) fn set_frame_callback(&mut self, frame_callback: Option<FrameCallback>) {
+        self.frame_callback = frame_callback;
+    }
+
+    /// Shape the first frames of HTTP/2 connections as `shape` says.
+    pub(crate) fn set_h2_preface(&mut self, shape: Option<Arc<PrefaceShape>>) {
+        self.h2_preface = shape.filter(|shape| !shape.is_empty());
+    }
+
+    /// Record the address family a connection to `dst` ended up using.
+    fn observe_peer(&self, dst: &Uri, peer: io::Result<SocketAddr>) {
+        if let (Some(cache), Some(host), Ok(peer)) = (&self.family_cache, dst.host(), peer) {
+            cache.observe(host, peer);
+        }
+    }
+
+    #[cfg(feature = "__boring")]
+    pub(crate) fn set_h2_fallback(&mut self, h2_fallback: Option<Arc<H2Fallback>>) {
+        self.h2_fallback = h2_fallback;
+    }
+
+    /// Only offer HTTP/1.1 to hosts that previously rejected h2.
+    #[cfg(feature = "__boring")]
+    fn apply_h2_fallback(&mut self, dst: &Uri) {
+        if !self.context.h2 {
+            return;
+        }
+        if let (Some(cache), Some(key)) = (&self.h2_fallback, h2_fallback::uri_key(dst)) {
+            if cache.is_downgraded(&key) {
+                log::trace!("skipping h2 negotiation for {}", key);
+                self.context.h2 = false;
+            }
+        }
+    }
+
+    /// Remember hosts that did not select h2 even though it was offered.
+    #[cfg(feature = "__boring")]
+    fn observe_alpn(&self, dst: &Uri, ssl: &boring::ssl::SslRef) {
+        if !self.context.h2 || ssl.selected_alpn_protocol() == Some(b"h2") {
+            return;
+        }
+        if let (Some(cache), Some(key)) = (&self.h2_fallback, h2_fallback::uri_key(dst)) {
+            cache.downgrade(key);
+        }
+    }
+
+    /// Connect to `dst` with `connect`, once the ECH configuration of the
+    /// server is looked up, and again if the server rejected the one
+    /// offered.
+    fn with_ech<F, Fut>(&self, dst: Uri, connect: F) -> Connecting
+    where
+        F: Fn(Uri) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Conn, BoxError>> + Send + 'static,
+    {
+        #[cfg(feature = "__boring")]
+        {
+            let Inner::BoringTls { tls, .. } = &self.inner;
+            if let (Some(ech), Some(&Scheme::HTTPS)) = (tls.ech(), dst.scheme()) {
+                let ech = ech.clone();
+                return Box::pin(async move {
+                    ech.prepare(&dst).await;
+                    match connect(dst.clone()).await {
+                        Err(err) if ech.take_rejection(&dst) => {
+                            log::debug!("connecting again to {:?} after ECH: {}", dst, err);
+                            connect(dst).await
+                        }
+                        result => result,
+                    }
+                });
+            }
+        }
+        Box::pin(connect(dst))
+    }
+
     pub(crate) fn set_keepalive(&mut self, dur: Option<Duration>) {
         match &mut self.inner {
             #[cfg(not(feature = "__tls"))]
@@ -108,6 +313,56 @@ impl Connector {
         self.proxies.clone()
     }
 
+    /// A copy of this connector that uses `proxies` instead.
+    pub(crate) fn with_proxies(&self, proxies: Vec<Proxy>) -> Connector {
+        let mut connector = self.clone();
+        connector.proxies = Arc::new(proxies);
+        connector
+    }
+
+    /// A connector handshaking with `tls`, as the profile `impersonate`.
+    #[cfg(feature = "impersonate")]
+    pub(crate) fn with_impersonation(
+        &self,
+        tls: BoringTlsConnector,
+        impersonate: impersonate::Impersonate,
+        user_agent: Option<HeaderValue>,
+    ) -> Result<Connector, ErrorStack> {
+        let Inner::BoringTls { http, tls: own } = &self.inner;
+        let tls = own.carry_certs_to(tls);
+        let mut context = self.context.clone();
+        context.impersonate = impersonate;
+        let layer = tls.layer(&context)?;
+
+        let mut connector = self.clone();
+        connector.inner = Inner::BoringTls {
+            http: http.clone(),
+            tls,
+        };
+        connector.layer = Arc::new((context.clone(), layer));
+        connector.context = context;
+        connector.user_agent = user_agent;
+        Ok(connector)
+    }
+
+    /// A copy of the connector, opening TCP connections with `http`.
+    pub(crate) fn with_http(&self, mut http: HttpConnector) -> Connector {
+        let mut connector = self.clone();
+        connector.inner = match &self.inner {
+            #[cfg(not(feature = "__tls"))]
+            Inner::Http(_) => Inner::Http(http),
+            #[cfg(feature = "__boring")]
+            Inner::BoringTls { tls, .. } => {
+                http.enforce_http(false);
+                Inner::BoringTls {
+                    http,
+                    tls: tls.clone(),
+                }
+            }
+        };
+        connector
+    }
+
     pub(crate) fn set_proxies(&mut self, proxies: Vec<Proxy>) {
         Arc::make_mut(&mut self.proxies).clone_from_slice(&proxies);
     }
@@ -137,9 +392,7 @@ impl Connector {
             ProxyScheme::Socks5 {
                 remote_dns: true, ..
             } => socks::DnsResolve::Proxy,
-            ProxyScheme::Http { .. } | ProxyScheme::Https { .. } => {
-                unreachable!("connect_socks is only called for socks proxies");
-            }
+            _ => unreachable!("connect_socks is only called for socks proxies"),
         };
 
         match &self.inner {
@@ -148,40 +401,73 @@ impl Connector {
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     let host = dst.host().ok_or("no host in url")?;
                     let conn = socks::connect(proxy, dst.clone(), dns).await?;
-                    let ssl = tls
-                        .create_ssl(&self.context, http.clone(), &dst, host)
-                        .await?;
+                    let ssl = self
+                        .https(tls, &self.context, http.clone())?
+                        .setup_ssl(&dst, host)?;
                     let io = tokio_boring::SslStreamBuilder::new(ssl, conn)
                         .connect()
                         .await?;
-                    return Ok(Conn {
-                        inner: self.verbose.wrap(BoringTlsConn { inner: io }),
-                        is_proxy: false,
-                        tls_info: self.tls_info,
-                    });
+                    self.observe_alpn(&dst, io.ssl());
+                    return Ok(Conn::new(
+                        self.verbose.wrap(BoringTlsConn { inner: io }),
+                        false,
+                        self.tls_info,
+                    ));
                 }
             }
             #[cfg(not(feature = "__tls"))]
             Inner::Http(_) => (),
         }
 
-        socks::connect(proxy, dst, dns).await.map(|tcp| Conn {
-            inner: self.verbose.wrap(tcp),
-            is_proxy: false,
-            tls_info: false,
-        })
+        socks::connect(proxy, dst, dns)
+            .await
+            .map(|tcp| Conn::new(self.verbose.wrap(tcp), false, false))
+    }
+
+    #[cfg(feature = "shadowsocks")]
+    async fn connect_shadowsocks(
+        &self,
+        dst: Uri,
+        server: crate::shadowsocks::ServerConfig,
+    ) -> Result<Conn, BoxError> {
+        let Inner::BoringTls { http, tls } = &self.inner;
+        let tcp = http
+            .clone()
+            .call(server.uri()?)
+            .await
+            .map_err(|e| format!("shadowsocks connect error: {}", e))?;
+        let conn = crate::shadowsocks::connect(tcp, &server, &dst)?;
+
+        if dst.scheme() == Some(&Scheme::HTTPS) {
+            let host = dst.host().ok_or("no host in url")?;
+            let ssl = self
+                .https(tls, &self.context, http.clone())?
+                .setup_ssl(&dst, host)?;
+            let io = tokio_boring::SslStreamBuilder::new(ssl, conn)
+                .connect()
+                .await?;
+            self.observe_alpn(&dst, io.ssl());
+            return Ok(Conn::new(
+                self.verbose.wrap(BoringTlsConn { inner: io }),
+                false,
+                self.tls_info,
+            ));
+        }
+
+        Ok(Conn::new(self.verbose.wrap(conn), false, false))
     }
 
     async fn connect_with_maybe_proxy(self, dst: Uri, is_proxy: bool) -> Result<Conn, BoxError> {
-        match self.inner {
+        match &self.inner {
             #[cfg(not(feature = "__tls"))]
-            Inner::Http(mut http) => {
-                let io = http.call(dst).await?;
-                Ok(Conn {
-                    inner: self.verbose.wrap(io),
-                    is_proxy,
-                    tls_info: false,
-                })
+            Inner::Http(http) => {
+                let io = http.clone().call(dst.clone()).await?;
+                self.observe_peer(&dst, io.peer_addr());
+                Ok(Conn::new(self.verbose.wrap(io), is_proxy, false))
+            }
+            #[cfg(feature = "__boring")]
+            Inner::BoringTls { .. } if !is_proxy && self.proxy_protocol.is_some() => {
+                self.connect_with_proxy_protocol(dst).await
             }
             #[cfg(feature = "__boring")]
             Inner::BoringTls { http, tls } => {
@@ -194,30 +480,123 @@ impl Connector {
                     http.set_nodelay(true);
                 }
 
-                let mut http = tls.create_connector(&self.context, http).await?;
-                let io = http.call(dst).await?;
+                let mut http = self.https(tls, &self.context, http)?;
+                let io = http.call(dst.clone()).await?;
+                let peer = match io {
+                    hyper_boring::MaybeHttpsStream::Https(ref stream) => {
+                        stream.get_ref().peer_addr()
+                    }
+                    hyper_boring::MaybeHttpsStream::Http(ref tcp) => tcp.peer_addr(),
+                };
+                self.observe_peer(&dst, peer);
 
                 if let hyper_boring::MaybeHttpsStream::Https(stream) = io {
+                    if !is_proxy {
+                        self.observe_alpn(&dst, stream.ssl());
+                    }
                     if !self.nodelay {
                         let stream_ref = stream.get_ref();
                         stream_ref.set_nodelay(false)?;
                     }
-                    Ok(Conn {
-                        inner: self.verbose.wrap(BoringTlsConn { inner: stream }),
+                    Ok(Conn::new(
+                        self.verbose.wrap(BoringTlsConn { inner: stream }),
                         is_proxy,
-                        tls_info: self.tls_info,
-                    })
+                        self.tls_info,
+                    ))
                 } else {
-                    Ok(Conn {
-                        inner: self.verbose.wrap(io),
-                        is_proxy,
-                        tls_info: self.tls_info,
-                    })
+                    Ok(Conn::new(self.verbose.wrap(io), is_proxy, self.tls_info))
                 }
             }
         }
     }
 
+    /// Connect directly to `dst`, sending the PROXY protocol header before
+    /// anything else.
+    #[cfg(feature = "__boring")]
+    async fn connect_with_proxy_protocol(&self, dst: Uri) -> Result<Conn, BoxError> {
+        let Inner::BoringTls { http, tls } = &self.inner;
+        let mut tcp = http.clone().call(dst.clone()).await?;
+        self.observe_peer(&dst, tcp.peer_addr());
+        self.send_proxy_protocol(&mut tcp).await?;
+
+        if dst.scheme() != Some(&Scheme::HTTPS) {
+            return Ok(Conn::new(self.verbose.wrap(tcp), false, false));
+        }
+
+        let host = dst.host().ok_or("no host in url")?;
+        let ssl = self
+            .https(tls, &self.context, http.clone())?
+            .setup_ssl(&dst, host)?;
+        // Disable Nagle's algorithm for the TLS handshake.
+        tcp.set_nodelay(true)?;
+        let io = tokio_boring::SslStreamBuilder::new(ssl, tcp)
+            .connect()
+            .await?;
+        if !self.nodelay {
+            io.get_ref().set_nodelay(false)?;
+        }
+        self.observe_alpn(&dst, io.ssl());
+        Ok(Conn::new(
+            self.verbose.wrap(BoringTlsConn { inner: io }),
+            false,
+            self.tls_info,
+        ))
+    }
+
+    #[cfg(feature = "__boring")]
+    async fn send_proxy_protocol(&self, tcp: &mut tokio::net::TcpStream) -> io::Result<()> {
+        if let Some(ref header) = self.proxy_protocol {
+            let buf = header.encode(tcp.local_addr()?, tcp.peer_addr()?);
+            tokio::io::AsyncWriteExt::write_all(tcp, &buf).await?;
+        }
+        Ok(())
+    }
+
+    /// Connect to an alternative service advertised by `dst`, falling back to
+    /// `dst` itself if the alternative can't be reached.
+    #[cfg(feature = "__boring")]
+    async fn connect_via_alt_svc(self, dst: Uri, alt: AltService) -> Result<Conn, BoxError> {
+        let alt_dst = alt.uri(&dst).ok_or("invalid alt-svc authority")?;
+        log::debug!("alt-svc: connecting to {:?} for {:?}", alt_dst, dst);
+
+        let mut context = self.context.clone();
+        if alt.protocol == "http/1.1" {
+            context.h2 = false;
+        }
+
+        let Inner::BoringTls { http, tls } = &self.inner;
+        let connect = async {
+            let host = dst.host().ok_or("no host in url")?;
+            let connector = self.https(tls, &context, http.clone())?;
+            let mut tcp = http.clone().call(alt_dst).await?;
+            self.send_proxy_protocol(&mut tcp).await?;
+            let ssl = connector.setup_ssl(&dst, host)?;
+            let io = tokio_boring::SslStreamBuilder::new(ssl, tcp)
+                .connect()
+                .await?;
+            Ok::<_, BoxError>(io)
+        };
+
+        let result = connect.await;
+        match result {
+            Ok(io) => {
+                self.observe_alpn(&dst, io.ssl());
+                Ok(Conn::new(
+                    self.verbose.wrap(BoringTlsConn { inner: io }),
+                    false,
+                    self.tls_info,
+                ))
+            }
+            Err(err) => {
+                log::debug!("alt-svc connection for {:?} failed: {}", dst, err);
+                if let Some(ref cache) = self.alt_svc {
+                    cache.evict(&dst);
+                }
+                self.connect_with_maybe_proxy(dst, false).await
+            }
+        }
+    }
+
     async fn connect_via_proxy(
         self,
         dst: Uri,
@@ -225,11 +604,23 @@ impl Connector {
     ) -> Result<Conn, BoxError> {
         log::debug!("proxy({:?}) intercepts '{:?}'", proxy_scheme, dst);
 
+        #[cfg(feature = "__boring")]
+        let mut proxy_tls = None;
         let (proxy_dst, _auth) = match proxy_scheme {
             ProxyScheme::Http { host, auth } => (into_uri(Scheme::HTTP, host), auth),
+            #[cfg(feature = "__boring")]
+            ProxyScheme::Https { host, auth, tls } => {
+                proxy_tls = Some(tls);
+                (into_uri(Scheme::HTTPS, host), auth)
+            }
+            #[cfg(not(feature = "__boring"))]
             ProxyScheme::Https { host, auth } => (into_uri(Scheme::HTTPS, host), auth),
             #[cfg(feature = "socks")]
             ProxyScheme::Socks5 { .. } => return self.connect_socks(dst, proxy_scheme).await,
+            #[cfg(feature = "shadowsocks")]
+            ProxyScheme::Shadowsocks { server } => {
+                return self.connect_shadowsocks(dst, server).await
+            }
         };
 
         #[cfg(feature = "__tls")]
@@ -238,11 +629,16 @@ impl Connector {
         match &self.inner {
             #[cfg(feature = "__boring")]
             Inner::BoringTls { http, tls } => {
+                if let Some(proxy_tls) = proxy_tls {
+                    return self
+                        .connect_via_tls_proxy(dst, proxy_dst, proxy_tls, auth)
+                        .await;
+                }
                 if dst.scheme() == Some(&Scheme::HTTPS) {
                     let host = dst.host().ok_or("no host in url")?;
                     let port = dst.port().map(|p| p.as_u16()).unwrap_or(443);
 
-                    let mut http = tls.create_connector(&self.context, http.clone()).await?;
+                    let mut http = self.https(tls, &self.context, http.clone())?;
                     let conn = http.call(proxy_dst).await?;
                     log::trace!("tunneling HTTPS over proxy");
                     let tunneled = tunnel(conn, host, port, self.user_agent.as_ref(), auth).await?;
@@ -251,12 +647,13 @@ impl Connector {
                     let io = tokio_boring::SslStreamBuilder::new(ssl, tunneled)
                         .connect()
                         .await?;
+                    self.observe_alpn(&dst, io.ssl());
 
-                    return Ok(Conn {
-                        inner: self.verbose.wrap(BoringTlsConn { inner: io }),
-                        is_proxy: false,
-                        tls_info: self.tls_info,
-                    });
+                    return Ok(Conn::new(
+                        self.verbose.wrap(BoringTlsConn { inner: io }),
+                        false,
+                        self.tls_info,
+                    ));
                 }
             }
             #[cfg(not(feature = "__tls"))]
@@ -265,6 +662,85 @@ impl Connector {
 
         self.connect_with_maybe_proxy(proxy_dst, true).await
     }
+
+    /// Connect through an `https://` proxy, over TLS to the proxy itself.
+    ///
+    /// Only HTTP/1.1 is offered to the proxy, since CONNECT tunnels and
+    /// absolute-form requests are sent to it as HTTP/1.1.
+    #[cfg(feature = "__boring")]
+    async fn connect_via_tls_proxy(
+        self,
+        dst: Uri,
+        proxy_dst: Uri,
+        proxy_tls: ProxyTls,
+        auth: Option<HeaderValue>,
+    ) -> Result<Conn, BoxError> {
+        let Inner::BoringTls { http, tls } = &self.inner;
+        let context = ImpersonateContext {
+            h2: false,
+            ..self.context.clone()
+        };
+        let https = match proxy_tls.connector {
+            Some(ref connector) => connector.create_connector(&context, http.clone())?,
+            None => self.https(tls, &context, http.clone())?,
+        };
+
+        let tcp = http.clone().call(proxy_dst.clone()).await?;
+        self.observe_peer(&proxy_dst, tcp.peer_addr());
+        // Disable Nagle's algorithm for the TLS handshake.
+        tcp.set_nodelay(true)?;
+
+        let proxy_host = proxy_dst.host().ok_or("no host in proxy url")?;
+        let name = proxy_tls.server_name.as_deref().unwrap_or(proxy_host);
+        let mut ssl = https.setup_ssl(&proxy_dst, name)?;
+        if let Some((ref cert, ref key)) = proxy_tls.identity {
+            set_identity(&mut ssl, cert, key)?;
+        }
+        let io = tokio_boring::SslStreamBuilder::new(ssl, tcp)
+            .connect()
+            .await?;
+        if !self.nodelay {
+            io.get_ref().set_nodelay(false)?;
+        }
+        let proxied = hyper_boring::MaybeHttpsStream::Https(io);
+
+        if dst.scheme() != Some(&Scheme::HTTPS) {
+            return Ok(Conn::new(self.verbose.wrap(proxied), true, false));
+        }
+
+        let host = dst.host().ok_or("no host in url")?;
+        let port = dst.port().map(|p| p.as_u16()).unwrap_or(443);
+        log::trace!("tunneling HTTPS over TLS to proxy");
+        let tunneled = tunnel(proxied, host, port, self.user_agent.as_ref(), auth).await?;
+
+        let ssl = self
+            .https(tls, &self.context, http.clone())?
+            .setup_ssl(&dst, host)?;
+        let io = tokio_boring::SslStreamBuilder::new(ssl, tunneled)
+            .connect()
+            .await?;
+        self.observe_alpn(&dst, io.ssl());
+        Ok(Conn::new(
+            self.verbose.wrap(BoringTlsConn { inner: io }),
+            false,
+            self.tls_info,
+        ))
+    }
+}
+
+/// Present `cert` and `key` as the client identity of one connection.
+#[cfg(feature = "__boring")]
+fn set_identity(ssl: &mut Ssl, cert: &X509, key: &PKey<Private>) -> Result<(), ErrorStack> {
+    // SAFETY: both calls take their own reference on the certificate and
+    // the key, which outlive the call.
+    unsafe {
+        if boring_sys::SSL_use_certificate(ssl.as_ptr(), cert.as_ptr()) != 1
+            || boring_sys::SSL_use_PrivateKey(ssl.as_ptr(), key.as_ptr()) != 1
+        {
+            return Err(ErrorStack::get());
+        }
+    }
+    Ok(())
 }
 
 fn into_uri(scheme: Scheme, host: Authority) -> Uri {
@@ -304,22 +780,217 @@ impl Service<Uri> for Connector {
     fn call(&mut self, dst: Uri) -> Self::Future {
         log::debug!("starting new connection: {:?}", dst);
         let timeout = self.timeout;
+        let taps = Taps {
+            raw_heads: self.raw_heads,
+            wire_capture: self.wire_capture.clone(),
+            frame_callback: self.frame_callback.clone(),
+            h2_preface: self.h2_preface.clone(),
+            h2c: self.h2c && dst.scheme() == Some(&Scheme::HTTP),
+            http2_only: self.http2_only,
+            merge_limit: self.merge_limit,
+            lenient_status_line: self.lenient_status_line,
+            buffer_budget: self.buffer_budget.clone(),
+        };
+        let mut connector = self.clone();
+        #[cfg(feature = "__boring")]
+        connector.apply_h2_fallback(&dst);
+
         for prox in self.proxies.iter() {
             if let Some(proxy_scheme) = prox.intercept(&dst) {
-                return Box::pin(with_timeout(
-                    self.clone().connect_via_proxy(dst, proxy_scheme),
-                    timeout,
-                ));
+                // Resume TLS sessions only through the proxy they were made
+                // with.
+                #[cfg(feature = "__boring")]
+                {
+                    connector.context.session.proxy = Some(proxy_scheme.identity());
+                }
+                let connecting = connector.with_ech(dst, {
+                    let connector = connector.clone();
+                    move |dst| {
+                        connector
+                            .clone()
+                            .connect_via_proxy(dst, proxy_scheme.clone())
+                    }
+                });
+                return finish(Box::pin(connecting), timeout, Taps { h2c: false, ..taps });
             }
         }
 
-        Box::pin(with_timeout(
-            self.clone().connect_with_maybe_proxy(dst, false),
-            timeout,
-        ))
+        #[cfg(feature = "__boring")]
+        if let Some(alt) = self.alt_svc.as_ref().and_then(|cache| cache.lookup(&dst)) {
+            let connecting = connector.with_ech(dst, {
+                let connector = connector.clone();
+                move |dst| connector.clone().connect_via_alt_svc(dst, alt.clone())
+            });
+            return finish(Box::pin(connecting), timeout, taps);
+        }
+
+        let connecting = connector.with_ech(dst, {
+            let connector = connector.clone();
+            move |dst| connector.clone().connect_with_maybe_proxy(dst, false)
+        });
+        finish(Box::pin(connecting), timeout, taps)
+    }
+}
+
+/// What is recorded of the traffic of connections.
+struct Taps {
+    /// The heads of responses on HTTP/1 connections.
+    raw_heads: bool,
+    wire_capture: Option<Arc<WireCapture>>,
+    frame_callback: Option<FrameCallback>,
+    /// How the first frames of HTTP/2 connections are shaped.
+    h2_preface: Option<Arc<PrefaceShape>>,
+    /// Whether the connection speaks HTTP/2 with prior knowledge.
+    h2c: bool,
+    /// Whether the client speaks HTTP/2 on every connection.
+    http2_only: bool,
+    /// The most bytes HTTP/1 connections write at once.
+    merge_limit: Option<usize>,
+    /// Whether the status lines of HTTP/1 responses are rewritten.
+    lenient_status_line: bool,
+    buffer_budget: Option<BufferBudget>,
+}
+
+/// The memory the read buffers of all connections of a client may take
+/// together, in KiB, of which each busy connection holds its share.
+#[derive(Clone)]
+pub(crate) struct BufferBudget {
+    kib: Arc<Semaphore>,
+    share: u32,
+}
+
+impl BufferBudget {
+    /// A budget of `limit` bytes for connections buffering up to `share`
+    /// bytes each, or `None` if not even one connection fits.
+    pub(crate) fn new(limit: usize, share: usize) -> Option<BufferBudget> {
+        let kib = (limit / 1024).min(Semaphore::MAX_PERMITS);
+        let share = u32::try_from((share + 1023) / 1024).ok()?;
+        if share as usize > kib {
+            return None;
+        }
+        Some(BufferBudget {
+            kib: Arc::new(Semaphore::new(kib)),
+            share,
+        })
+    }
+
+    /// Wait for the share of a connection.
+    fn reserve(&self) -> Reserving {
+        Box::pin(self.kib.clone().acquire_many_owned(self.share))
     }
 }
 
+type Reserving = Pin<Box<dyn Future<Output = Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+/// How long a connection goes without traffic before it gives its share of
+/// the buffer budget back.
+const SHARE_IDLE: Duration = Duration::from_secs(1);
+
+/// The share of the buffer budget of a connection, given back while the
+/// connection is idle, such as in the pool, and taken again before it
+/// writes.
+struct Share {
+    budget: BufferBudget,
+    permit: Option<OwnedSemaphorePermit>,
+    // Taking the share again, once a write waits for it.
+    reserving: Option<Reserving>,
+    // When the connection counts as idle.
+    idle: Pin<Box<Sleep>>,
+}
+
+impl Share {
+    fn new(budget: BufferBudget, permit: OwnedSemaphorePermit) -> Share {
+        Share {
+            budget,
+            permit: Some(permit),
+            reserving: None,
+            idle: Box::pin(tokio::time::sleep(SHARE_IDLE)),
+        }
+    }
+
+    /// Take the share back, if given back, before writing.
+    fn poll_take(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.permit.is_none() {
+            let budget = &self.budget;
+            let reserving = self.reserving.get_or_insert_with(|| budget.reserve());
+            let permit = ready!(reserving.as_mut().poll(cx))
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.reserving = None;
+            self.permit = Some(permit);
+        }
+        self.active();
+        Poll::Ready(Ok(()))
+    }
+
+    /// Put off giving the share back after traffic.
+    fn active(&mut self) {
+        self.idle
+            .as_mut()
+            .reset(tokio::time::Instant::now() + SHARE_IDLE);
+    }
+
+    /// Give the share back once nothing was read or written for a while.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) {
+        if self.permit.is_some() && self.idle.as_mut().poll(cx).is_ready() {
+            self.permit = None;
+        }
+    }
+}
+
+/// Record in the connection which resolver answered the lookups of
+/// `connecting` and how long its phases took, and set up the `taps` of its
+/// traffic.
+fn finish(connecting: Connecting, timeout: Option<Duration>, taps: Taps) -> Connecting {
+    Box::pin(async move {
+        let budget = taps.buffer_budget.clone();
+        let connected = with_timeout(
+            async move {
+                // Connections open once their read buffer fits in the
+                // budget, which counts against the connect timeout.
+                let share = match budget {
+                    Some(budget) => {
+                        let permit = budget.reserve().await?;
+                        Some(Share::new(budget, permit))
+                    }
+                    None => None,
+                };
+                let ((conn, resolved_by), timings) = timing::scope(lookup::scope(connecting)).await;
+                conn.map(|conn| (conn, share, resolved_by, timings))
+            },
+            timeout,
+        )
+        .await;
+        connected.map(|(mut conn, share, resolved_by, timings)| {
+            conn.share = share;
+            conn.resolved_by = resolved_by;
+            conn.timings = Some(timings);
+            conn.h2c = taps.h2c;
+            let h2 = taps.h2c || taps.http2_only || conn.inner.connected().is_negotiated_h2();
+            if !h2 {
+                if taps.raw_heads {
+                    conn.head = Some(HeadRecorder::default());
+                }
+                conn.merge_limit = taps.merge_limit;
+                if taps.lenient_status_line {
+                    conn.status_line = Some(StatusLine::default());
+                }
+            } else {
+                conn.shifted = taps
+                    .h2_preface
+                    .as_ref()
+                    .map(|shape| shape.stream_offset())
+                    .filter(|&offset| offset > 0)
+                    .map(ShiftedReads::new);
+                conn.shaper = taps.h2_preface.map(PrefaceShaper::new);
+                conn.received = Some(PrefaceRecorder::default());
+            }
+            conn.capture = taps.wire_capture.as_ref().map(WireCapture::connection);
+            conn.frames = taps.frame_callback.map(FrameInspector::new);
+            conn
+        })
+    })
+}
+
 #[cfg(feature = "__tls")]
 trait TlsInfoFactory {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo>;
@@ -328,12 +999,23 @@ trait TlsInfoFactory {
 #[cfg(feature = "__boring")]
 impl TlsInfoFactory for BoringTlsConn<tokio::net::TcpStream> {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
-        let peer_certificate = self
-            .inner
-            .ssl()
-            .peer_certificate()
-            .and_then(|c| c.to_der().ok());
-        Some(crate::tls::TlsInfo { peer_certificate })
+        Some(crate::tls::TlsInfo::from_ssl(self.inner.ssl()))
+    }
+}
+
+#[cfg(feature = "shadowsocks")]
+impl TlsInfoFactory
+    for BoringTlsConn<crate::shadowsocks::ShadowsocksStream<tokio::net::TcpStream>>
+{
+    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+        Some(crate::tls::TlsInfo::from_ssl(self.inner.ssl()))
+    }
+}
+
+#[cfg(feature = "shadowsocks")]
+impl TlsInfoFactory for crate::shadowsocks::ShadowsocksStream<tokio::net::TcpStream> {
+    fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
+        None
     }
 }
 
@@ -342,8 +1024,7 @@ impl TlsInfoFactory for hyper_boring::MaybeHttpsStream<tokio::net::TcpStream> {
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
         match self {
             hyper_boring::MaybeHttpsStream::Https(tls) => {
-                let peer_certificate = tls.ssl().peer_certificate().and_then(|c| c.to_der().ok());
-                Some(crate::tls::TlsInfo { peer_certificate })
+                Some(crate::tls::TlsInfo::from_ssl(tls.ssl()))
             }
             hyper_boring::MaybeHttpsStream::Http(_) => None,
         }
@@ -355,8 +1036,7 @@ impl TlsInfoFactory for BoringTlsConn<hyper_boring::MaybeHttpsStream<tokio::net:
     fn tls_info(&self) -> Option<crate::tls::TlsInfo> {
         match self.inner.get_ref() {
             hyper_boring::MaybeHttpsStream::Https(ref tls) => {
-                let peer_certificate = tls.ssl().peer_certificate().and_then(|c| c.to_der().ok());
-                Some(crate::tls::TlsInfo { peer_certificate })
+                Some(crate::tls::TlsInfo::from_ssl(tls.ssl()))
             }
             hyper_boring::MaybeHttpsStream::Http(_) => None,
         }
@@ -400,12 +1080,71 @@ pin_project! {
         is_proxy: bool,
         // Only needed for __tls, but #[cfg()] on fields breaks pin_project!
         tls_info: bool,
+        resolved_by: Option<ResolvedBy>,
+        timings: Option<ConnectTimings>,
+        head: Option<HeadRecorder>,
+        capture: Option<ConnCapture>,
+        frames: Option<FrameInspector>,
+        // Keeps the first frames the server of an HTTP/2 connection sends.
+        received: Option<PrefaceRecorder>,
+        // Shapes the first frames of an HTTP/2 connection, until done.
+        shaper: Option<PrefaceShaper>,
+        // The shaped bytes left to write.
+        shaped: Vec<u8>,
+        // Lowers the stream ids the shaper raised in what the server sends.
+        shifted: Option<ShiftedReads>,
+        h2c: bool,
+        // The most bytes of a request head and body written at once.
+        merge_limit: Option<usize>,
+        // Rewrites the status lines of HTTP/1 responses.
+        status_line: Option<StatusLine>,
+        // The share of the buffer budget held while the connection is busy.
+        share: Option<Share>,
+    }
+}
+
+impl Conn {
+    /// A connection over `inner`, whose taps are set up by `finish`.
+    fn new(inner: BoxConn, is_proxy: bool, tls_info: bool) -> Conn {
+        Conn {
+            inner,
+            is_proxy,
+            tls_info,
+            resolved_by: None,
+            timings: None,
+            head: None,
+            capture: None,
+            frames: None,
+            received: None,
+            shaper: None,
+            shaped: Vec::new(),
+            shifted: None,
+            h2c: false,
+            merge_limit: None,
+            status_line: None,
+            share: None,
+        }
     }
 }
 
 impl Connection for Conn {
     fn connected(&self) -> Connected {
-        let connected = self.inner.connected().proxy(self.is_proxy);
+        let mut connected = self.inner.connected().proxy(self.is_proxy);
+        if self.h2c {
+            connected = connected.negotiated_h2();
+        }
+        if let Some(resolved_by) = self.resolved_by {
+            connected = connected.extra(resolved_by);
+        }
+        if let Some(timings) = self.timings {
+            connected = connected.extra(timings);
+        }
+        if let Some(ref head) = self.head {
+            connected = connected.extra(head.clone());
+        }
+        if let Some(ref received) = self.received {
+            connected = connected.extra(received.received());
+        }
         #[cfg(feature = "__tls")]
         if self.tls_info {
             if let Some(tls_info) = self.inner.tls_info() {
@@ -427,8 +1166,54 @@ impl AsyncRead for Conn {
         cx: &mut Context,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        let this = self.project();
-        AsyncRead::poll_read(this.inner, cx, buf)
+        let mut this = self.project();
+        loop {
+            let filled = buf.filled().len();
+            if let Some(shifted) = this.shifted.as_mut() {
+                if shifted.put_ready(buf) {
+                    return Poll::Ready(Ok(()));
+                }
+            }
+            let res = AsyncRead::poll_read(this.inner.as_mut(), cx, buf);
+            if let Some(share) = this.share.as_mut() {
+                match res {
+                    Poll::Pending => share.poll_idle(cx),
+                    Poll::Ready(_) => share.active(),
+                }
+            }
+            ready!(res)?;
+            let read = &buf.filled()[filled..];
+            if let Some(head) = this.head.as_ref() {
+                head.on_read(read);
+            }
+            if let Some(capture) = this.capture.as_mut() {
+                capture.on_read(read);
+            }
+            if let Some(frames) = this.frames.as_mut() {
+                frames.on_read(read);
+            }
+            if let Some(received) = this.received.as_mut() {
+                received.on_read(read);
+            }
+            if let Some(shifted) = this.shifted.as_mut() {
+                let read = buf.filled().len() - filled;
+                shifted.on_read(buf, filled);
+                // Reading nothing would end the stream.
+                if buf.filled().len() == filled && read > 0 {
+                    continue;
+                }
+            }
+            if let Some(status_line) = this.status_line.as_mut() {
+                let read = buf.filled().len() - filled;
+                let kept = status_line.on_read(&mut buf.filled_mut()[filled..]);
+                buf.set_filled(filled + kept);
+                // Reading nothing would end the stream.
+                if kept == 0 && read > 0 {
+                    continue;
+                }
+            }
+            return Poll::Ready(Ok(()));
+        }
     }
 }
 
@@ -438,8 +1223,47 @@ impl AsyncWrite for Conn {
         cx: &mut Context,
         buf: &[u8],
     ) -> Poll<Result<usize, io::Error>> {
-        let this = self.project();
-        AsyncWrite::poll_write(this.inner, cx, buf)
+        let mut this = self.project();
+        if let Some(share) = this.share.as_mut() {
+            ready!(share.poll_take(cx))?;
+        }
+        if this.shaper.is_some() || !this.shaped.is_empty() {
+            ready!(poll_shaped(
+                this.inner.as_mut(),
+                cx,
+                this.shaped,
+                this.capture,
+                this.frames
+            ))?;
+            if let Some(shaper) = this.shaper.as_mut() {
+                if shaper.shape(buf, this.shaped) {
+                    *this.shaper = None;
+                }
+                // What is left is written by the next write or flush.
+                if let Poll::Ready(Err(e)) =
+                    poll_shaped(this.inner, cx, this.shaped, this.capture, this.frames)
+                {
+                    return Poll::Ready(Err(e));
+                }
+                return Poll::Ready(Ok(buf.len()));
+            }
+        }
+        let res = AsyncWrite::poll_write(this.inner, cx, buf);
+        if let Poll::Ready(Ok(n @ 1..)) = res {
+            if let Some(head) = this.head.as_ref() {
+                head.on_write();
+            }
+            if let Some(status_line) = this.status_line.as_mut() {
+                status_line.on_write();
+            }
+            if let Some(capture) = this.capture.as_mut() {
+                capture.on_write(&buf[..n]);
+            }
+            if let Some(frames) = this.frames.as_mut() {
+                frames.on_write(&buf[..n]);
+            }
+        }
+        res
     }
 
     fn poll_write_vectored(
@@ -447,8 +1271,54 @@ impl AsyncWrite for Conn {
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<Result<usize, io::Error>> {
+        if self.shaper.is_some() || !self.shaped.is_empty() {
+            let buf = bufs
+                .iter()
+                .find(|buf| !buf.is_empty())
+                .map_or(&[][..], |buf| buf);
+            return self.poll_write(cx, buf);
+        }
         let this = self.project();
-        AsyncWrite::poll_write_vectored(this.inner, cx, bufs)
+        if let Some(share) = this.share.as_mut() {
+            ready!(share.poll_take(cx))?;
+        }
+        let res = match *this.merge_limit {
+            // Leave the body to the next write.
+            Some(limit) if bufs.iter().map(|buf| buf.len()).sum::<usize>() > limit => {
+                AsyncWrite::poll_write_vectored(this.inner, cx, &bufs[..1])
+            }
+            // Write the head and body at once, even without vectored writes.
+            Some(_) if bufs.len() > 1 && !this.inner.is_write_vectored() => {
+                let merged = bufs
+                    .iter()
+                    .flat_map(|buf| buf.iter().copied())
+                    .collect::<Vec<u8>>();
+                AsyncWrite::poll_write(this.inner, cx, &merged)
+            }
+            _ => AsyncWrite::poll_write_vectored(this.inner, cx, bufs),
+        };
+        if let Poll::Ready(Ok(n @ 1..)) = res {
+            if let Some(head) = this.head.as_ref() {
+                head.on_write();
+            }
+            if let Some(status_line) = this.status_line.as_mut() {
+                status_line.on_write();
+            }
+            if this.capture.is_some() || this.frames.is_some() {
+                let mut written = Vec::with_capacity(n);
+                for buf in bufs {
+                    let take = (n - written.len()).min(buf.len());
+                    written.extend_from_slice(&buf[..take]);
+                }
+                if let Some(capture) = this.capture.as_mut() {
+                    capture.on_write(&written);
+                }
+                if let Some(frames) = this.frames.as_mut() {
+                    frames.on_write(&written);
+                }
+            }
+        }
+        res
     }
 
     fn is_write_vectored(&self) -> bool {
@@ -456,16 +1326,55 @@ impl AsyncWrite for Conn {
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
-        let this = self.project();
+        let mut this = self.project();
+        ready!(poll_shaped(
+            this.inner.as_mut(),
+            cx,
+            this.shaped,
+            this.capture,
+            this.frames
+        ))?;
         AsyncWrite::poll_flush(this.inner, cx)
     }
 
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
-        let this = self.project();
+        let mut this = self.project();
+        ready!(poll_shaped(
+            this.inner.as_mut(),
+            cx,
+            this.shaped,
+            this.capture,
+            this.frames
+        ))?;
         AsyncWrite::poll_shutdown(this.inner, cx)
     }
 }
 
+/// Write the bytes shaped by a `PrefaceShaper`, passing them to the taps of
+/// the connection.
+fn poll_shaped(
+    mut inner: Pin<&mut BoxConn>,
+    cx: &mut Context,
+    shaped: &mut Vec<u8>,
+    capture: &mut Option<ConnCapture>,
+    frames: &mut Option<FrameInspector>,
+) -> Poll<Result<(), io::Error>> {
+    while !shaped.is_empty() {
+        let n = ready!(AsyncWrite::poll_write(inner.as_mut(), cx, shaped))?;
+        if n == 0 {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        if let Some(capture) = capture.as_mut() {
+            capture.on_write(&shaped[..n]);
+        }
+        if let Some(frames) = frames.as_mut() {
+            frames.on_write(&shaped[..n]);
+        }
+        shaped.drain(..n);
+    }
+    Poll::Ready(Ok(()))
+}
+
 pub(crate) type Connecting = Pin<Box<dyn Future<Output = Result<Conn, BoxError>> + Send>>;
 
 #[cfg(feature = "__tls")]
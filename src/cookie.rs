@@ -2,9 +2,13 @@
 
 use std::convert::TryInto;
 use std::fmt;
-use std::sync::RwLock;
+use std::future::Future;
+use std::io::{self, BufRead, Write};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
 
+use crate::error::BoxError;
 use crate::header::{HeaderValue, SET_COOKIE};
 use bytes::Bytes;
 
@@ -14,8 +18,25 @@ pub trait CookieStore: Send + Sync {
     fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url);
     /// Get any Cookie values in the store for `url`
     fn cookies(&self, url: &url::Url) -> Option<HeaderValue>;
+    /// Persist the Set-Cookie header values just stored for `url`
+    ///
+    /// This is called after `set_cookies` for every response setting
+    /// cookies, and notifies stores shared by several clients or processes
+    /// of the change, for instance to write it to a database or publish it
+    /// to other workers. The client waits for the returned future before
+    /// returning the response or following its redirect, so the change is
+    /// visible elsewhere once the request completes. An error fails the
+    /// request.
+    ///
+    /// The default does nothing.
+    fn persist(&self, _cookie_headers: &[HeaderValue], _url: &url::Url) -> Option<Persisting> {
+        None
+    }
 }
 
+/// The future returned by [`CookieStore::persist`].
+pub type Persisting = Pin<Box<dyn Future<Output = Result<(), BoxError>> + Send>>;
+
 /// A single HTTP cookie.
 pub struct Cookie<'a>(cookie_crate::Cookie<'a>);
 
@@ -25,11 +46,66 @@ pub struct Cookie<'a>(cookie_crate::Cookie<'a>);
 /// This type is exposed to allow creating one and filling it with some
 /// existing cookies more easily, before creating a `Client`.
 ///
-/// For more advanced scenarios, such as needing to serialize the store or
-/// manipulate it between requests, you may refer to the
-/// [rquest_cookie_store crate](https://crates.io/crates/rquest_cookie_store).
-#[derive(Debug, Default)]
-pub struct Jar(RwLock<cookie_store::CookieStore>);
+/// A jar can be saved to and loaded from a Netscape cookie file, as written
+/// by curl and browser extensions, or JSON, so that a long-running session
+/// keeps its cookies across restarts. The jar of a client is returned by
+/// [`Client::cookie_jar`](crate::Client::cookie_jar).
+///
+/// To keep cookies in a database instead, implement [`CookieStore`].
+///
+/// The changes servers make to the cookies of a jar can be followed with
+/// [`subscribe`](Jar::subscribe), to save them as they happen.
+#[derive(Default)]
+pub struct Jar {
+    store: RwLock<cookie_store::CookieStore>,
+    subscribers: RwLock<Vec<Subscriber>>,
+}
+
+/// A change of the cookies of a [`Jar`], passed to its subscribers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CookieEvent {
+    /// A cookie was added.
+    Added {
+        /// The name of the cookie.
+        name: String,
+        /// The value of the cookie.
+        value: String,
+        /// The domain the cookie is sent to.
+        domain: String,
+        /// The path the cookie is sent to.
+        path: String,
+    },
+    /// A cookie was set again, with a new value or attributes.
+    Updated {
+        /// The name of the cookie.
+        name: String,
+        /// The new value of the cookie.
+        value: String,
+        /// The domain the cookie is sent to.
+        domain: String,
+        /// The path the cookie is sent to.
+        path: String,
+    },
+    /// A cookie was removed by setting it expired, as servers delete
+    /// cookies, such as that of a session on logout.
+    Expired {
+        /// The name of the cookie.
+        name: String,
+        /// The domain the cookie was sent to.
+        domain: String,
+        /// The path the cookie was sent to.
+        path: String,
+    },
+    /// The cookies of a host were removed with
+    /// [`clear_host`](Jar::clear_host).
+    HostCleared {
+        /// The host.
+        host: String,
+    },
+}
+
+type Subscriber = Arc<dyn Fn(&CookieEvent) + Send + Sync>;
 
 // ===== impl Cookie =====
 
@@ -160,8 +236,390 @@ impl Jar {
             .ok()
             .map(|c| c.into_owned())
             .into_iter();
-        self.0.write().unwrap().store_response_cookies(cookies, url);
+        self.store_response(cookies, url);
+    }
+
+    /// Call `subscriber` with each change made to the cookies of this jar,
+    /// by the responses of the clients using it, by
+    /// [`add_cookie_str`](Jar::add_cookie_str) or by
+    /// [`clear_host`](Jar::clear_host).
+    ///
+    /// Cookies loaded from a file are not passed, nor cookies expiring on
+    /// their own. The subscriber is called after the change, on the task
+    /// of the request, so it should be quick.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use rquest::cookie::{CookieEvent, Jar};
+    ///
+    /// let jar = Arc::new(Jar::default());
+    /// jar.subscribe(|event| {
+    ///     if let CookieEvent::Expired { name, .. } = event {
+    ///         if name == "session" {
+    ///             eprintln!("logged out");
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn subscribe<F>(&self, subscriber: F)
+    where
+        F: Fn(&CookieEvent) + Send + Sync + 'static,
+    {
+        self.subscribers.write().unwrap().push(Arc::new(subscriber));
+    }
+
+    /// Remove the cookies set for `host`, including those it shares with
+    /// its subdomains.
+    pub fn clear_host(&self, host: &str) {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        {
+            let mut store = self.store.write().unwrap();
+            let cookies = store
+                .iter_any()
+                .filter_map(|cookie| {
+                    let domain = match cookie.domain {
+                        cookie_store::CookieDomain::HostOnly(ref domain)
+                        | cookie_store::CookieDomain::Suffix(ref domain) => domain,
+                        _ => return None,
+                    };
+                    (*domain == host).then(|| {
+                        (
+                            domain.clone(),
+                            String::from(&cookie.path),
+                            cookie.name().to_owned(),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+            for (domain, path, name) in cookies {
+                store.remove(&domain, &path, &name);
+            }
+        }
+        self.emit(vec![CookieEvent::HostCleared { host }]);
+    }
+
+    /// Write the unexpired cookies of this jar in the Netscape cookie file
+    /// format, as read by curl with `--cookie`.
+    ///
+    /// Session cookies are written with an expiry of `0`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use std::sync::Arc;
+    /// use rquest::cookie::Jar;
+    ///
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let jar = Arc::new(Jar::default());
+    /// if let Ok(file) = File::open("cookies.txt") {
+    ///     jar.load_netscape(std::io::BufReader::new(file))?;
+    /// }
+    ///
+    /// let client = rquest::Client::builder()
+    ///     .cookie_provider(jar.clone())
+    ///     .build()?;
+    ///
+    /// // ... make requests ...
+    ///
+    /// jar.save_netscape(File::create("cookies.txt")?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_netscape<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(b"# Netscape HTTP Cookie File\n")?;
+        for cookie in self.stored() {
+            writeln!(
+                writer,
+                "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                if cookie.http_only { "#HttpOnly_" } else { "" },
+                if cookie.host_only {
+                    cookie.domain.clone()
+                } else {
+                    format!(".{}", cookie.domain)
+                },
+                if cookie.host_only { "FALSE" } else { "TRUE" },
+                cookie.path,
+                if cookie.secure { "TRUE" } else { "FALSE" },
+                cookie.expires.unwrap_or(0),
+                cookie.name,
+                cookie.value,
+            )?;
+        }
+        writer.flush()
+    }
+
+    /// Add the cookies of a Netscape cookie file, as written by
+    /// [`save_netscape`](Jar::save_netscape) or curl with `--cookie-jar`.
+    ///
+    /// Malformed lines and expired cookies are skipped.
+    pub fn load_netscape<R: BufRead>(&self, reader: R) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            let (http_only, line) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (true, line),
+                None if line.starts_with('#') => continue,
+                None => (false, &line[..]),
+            };
+            let fields = line.trim_end_matches('\r').split('\t').collect::<Vec<_>>();
+            let (domain, subdomains, path, secure, expires, name, value) = match fields[..] {
+                [domain, subdomains, path, secure, expires, name, value] => {
+                    (domain, subdomains, path, secure, expires, name, value)
+                }
+                // A cookie with an empty value, trimmed by some writers.
+                [domain, subdomains, path, secure, expires, name] => {
+                    (domain, subdomains, path, secure, expires, name, "")
+                }
+                _ => continue,
+            };
+            let expires = match expires.parse::<i64>() {
+                Ok(0) => None,
+                Ok(expires) => Some(expires),
+                Err(_) => continue,
+            };
+            self.insert(StoredCookie {
+                name: name.to_owned(),
+                value: value.to_owned(),
+                host_only: !subdomains.eq_ignore_ascii_case("TRUE"),
+                domain: domain.trim_start_matches('.').to_owned(),
+                path: path.to_owned(),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                http_only,
+                same_site: None,
+                expires,
+            });
+        }
+        Ok(())
     }
+
+    /// Write the unexpired cookies of this jar as a JSON array.
+    ///
+    /// Each cookie is an object with the fields `name`, `value`, `domain`,
+    /// `host_only`, `path`, `secure`, `http_only`, `same_site` and
+    /// `expires`, in seconds since the Unix epoch, or `null` for a session
+    /// cookie.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn save_json<W: Write>(&self, writer: W) -> io::Result<()> {
+        let cookies = self
+            .stored()
+            .into_iter()
+            .map(|cookie| {
+                serde_json::json!({
+                    "name": cookie.name,
+                    "value": cookie.value,
+                    "domain": cookie.domain,
+                    "host_only": cookie.host_only,
+                    "path": cookie.path,
+                    "secure": cookie.secure,
+                    "http_only": cookie.http_only,
+                    "same_site": cookie.same_site.map(|same_site| same_site.to_string()),
+                    "expires": cookie.expires,
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_writer(writer, &cookies).map_err(io::Error::from)
+    }
+
+    /// Add the cookies of a JSON array written by
+    /// [`save_json`](Jar::save_json).
+    ///
+    /// Cookies missing a `name`, `value` or `domain`, and expired ones, are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `reader` fails, or doesn't hold a JSON array.
+    ///
+    /// # Optional
+    ///
+    /// This requires the optional `json` feature to be enabled.
+    #[cfg(feature = "json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "json")))]
+    pub fn load_json<R: io::Read>(&self, reader: R) -> io::Result<()> {
+        use serde_json::Value;
+
+        let cookies: Vec<Value> = serde_json::from_reader(reader).map_err(io::Error::from)?;
+        for cookie in cookies {
+            let string = |name: &str| cookie.get(name).and_then(Value::as_str).map(str::to_owned);
+            let flag = |name: &str| cookie.get(name).and_then(Value::as_bool);
+            let (name, value, domain) = match (string("name"), string("value"), string("domain")) {
+                (Some(name), Some(value), Some(domain)) => (name, value, domain),
+                _ => continue,
+            };
+            self.insert(StoredCookie {
+                name,
+                value,
+                host_only: flag("host_only").unwrap_or(true),
+                domain: domain.trim_start_matches('.').to_owned(),
+                path: string("path").unwrap_or_else(|| "/".to_owned()),
+                secure: flag("secure").unwrap_or(false),
+                http_only: flag("http_only").unwrap_or(false),
+                same_site: match string("same_site").as_deref() {
+                    Some("Strict") => Some(cookie_crate::SameSite::Strict),
+                    Some("Lax") => Some(cookie_crate::SameSite::Lax),
+                    Some("None") => Some(cookie_crate::SameSite::None),
+                    _ => None,
+                },
+                expires: cookie.get("expires").and_then(Value::as_i64),
+            });
+        }
+        Ok(())
+    }
+
+    /// The unexpired cookies of this jar.
+    fn stored(&self) -> Vec<StoredCookie> {
+        let store = self.store.read().unwrap();
+        store
+            .iter_unexpired()
+            .filter_map(|cookie| {
+                let (domain, host_only) = match cookie.domain {
+                    cookie_store::CookieDomain::HostOnly(ref domain) => (domain.clone(), true),
+                    cookie_store::CookieDomain::Suffix(ref domain) => (domain.clone(), false),
+                    _ => return None,
+                };
+                let expires = match cookie.expires {
+                    cookie_store::CookieExpiration::AtUtc(ref at) => Some(at.unix_timestamp()),
+                    cookie_store::CookieExpiration::SessionEnd => None,
+                };
+                Some(StoredCookie {
+                    name: cookie.name().to_owned(),
+                    value: cookie.value().to_owned(),
+                    host_only,
+                    domain,
+                    path: String::from(&cookie.path),
+                    secure: cookie.secure().unwrap_or(false),
+                    http_only: cookie.http_only().unwrap_or(false),
+                    same_site: cookie.same_site(),
+                    expires,
+                })
+            })
+            .collect()
+    }
+
+    /// Add a cookie read from a file, as if set by its domain.
+    fn insert(&self, cookie: StoredCookie) {
+        let mut raw = cookie_crate::Cookie::build((cookie.name, cookie.value))
+            .path(cookie.path.clone())
+            .secure(cookie.secure)
+            .http_only(cookie.http_only);
+        if !cookie.host_only {
+            raw = raw.domain(cookie.domain.clone());
+        }
+        if let Some(same_site) = cookie.same_site {
+            raw = raw.same_site(same_site);
+        }
+        if let Some(expires) = cookie.expires {
+            match cookie_crate::time::OffsetDateTime::from_unix_timestamp(expires) {
+                Ok(expires) => raw = raw.expires(expires),
+                Err(_) => return,
+            }
+        }
+
+        let url = match url::Url::parse(&format!("https://{}{}", cookie.domain, cookie.path)) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        // Expired cookies are refused by the store.
+        let _ = self.store.write().unwrap().insert_raw(&raw.build(), &url);
+    }
+
+    /// Store the cookies set by a response from `url`, telling the
+    /// subscribers.
+    fn store_response<I>(&self, cookies: I, url: &url::Url)
+    where
+        I: Iterator<Item = cookie_crate::Cookie<'static>>,
+    {
+        let notify = !self.subscribers.read().unwrap().is_empty();
+        let mut events = Vec::new();
+        {
+            let mut store = self.store.write().unwrap();
+            for cookie in cookies {
+                match store.insert_raw(&cookie, url) {
+                    Ok(action) if notify => events.push(event(action, &cookie, url)),
+                    _ => {}
+                }
+            }
+        }
+        self.emit(events);
+    }
+
+    /// Pass `events` to the subscribers, once the store is unlocked so
+    /// that they can read it.
+    fn emit(&self, events: Vec<CookieEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        let subscribers = self.subscribers.read().unwrap().clone();
+        for event in &events {
+            for subscriber in &subscribers {
+                subscriber(event);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Jar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Jar").field(&self.store).finish()
+    }
+}
+
+/// The event of `cookie`, set by a response from `url`, once stored.
+fn event(
+    action: cookie_store::StoreAction,
+    cookie: &cookie_crate::Cookie,
+    url: &url::Url,
+) -> CookieEvent {
+    let name = cookie.name().to_owned();
+    let value = cookie.value().to_owned();
+    let domain = match cookie.domain() {
+        Some(domain) => domain.trim_start_matches('.').to_ascii_lowercase(),
+        None => url.host_str().unwrap_or_default().to_owned(),
+    };
+    let path = match cookie.path() {
+        Some(path) if path.starts_with('/') => path.to_owned(),
+        // The default path, the directory of the path of the URL.
+        _ => match url.path().rfind('/') {
+            Some(0) | None => "/".to_owned(),
+            Some(end) => url.path()[..end].to_owned(),
+        },
+    };
+    match action {
+        cookie_store::StoreAction::Inserted => CookieEvent::Added {
+            name,
+            value,
+            domain,
+            path,
+        },
+        cookie_store::StoreAction::UpdatedExisting => CookieEvent::Updated {
+            name,
+            value,
+            domain,
+            path,
+        },
+        cookie_store::StoreAction::ExpiredExisting => CookieEvent::Expired { name, domain, path },
+    }
+}
+
+/// A cookie of a `Jar`, as saved to and loaded from a file.
+struct StoredCookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<cookie_crate::SameSite>,
+    /// Seconds since the Unix epoch, or `None` for a session cookie.
+    expires: Option<i64>,
 }
 
 impl CookieStore for Jar {
@@ -169,12 +627,12 @@ impl CookieStore for Jar {
         let iter =
             cookie_headers.filter_map(|val| Cookie::parse(val).map(|c| c.0.into_owned()).ok());
 
-        self.0.write().unwrap().store_response_cookies(iter, url);
+        self.store_response(iter, url);
     }
 
     fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
         let s = self
-            .0
+            .store
             .read()
             .unwrap()
             .get_request_values(url)
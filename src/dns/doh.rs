@@ -0,0 +1,408 @@
+//! DNS-over-HTTPS, as in [RFC 8484](https://www.rfc-editor.org/rfc/rfc8484).
+//!
+//! Queries are sent with a copy of the client's connector, so that the
+//! resolver shows the same TLS and HTTP/2 fingerprints as the requests
+//! whose names it resolves.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use http::header::{ACCEPT, CONTENT_TYPE};
+use http::Uri;
+
+use super::{Addrs, Name, Resolve, Resolving};
+use crate::connect::Connector;
+use crate::error::BoxError;
+use crate::Url;
+
+/// The client queries are sent with.
+pub(crate) type DohClient = hyper::Client<Connector, hyper::Body>;
+
+const DNS_MESSAGE: &str = "application/dns-message";
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const TYPE_OPT: u16 = 41;
+const TYPE_HTTPS: u16 = 65;
+const OPTION_PADDING: u16 = 12;
+
+/// Queries are padded to a multiple of this length, as recommended by
+/// RFC 8467 and done by browsers.
+const PADDING_BLOCK: usize = 128;
+
+/// The largest DNS message.
+const MAX_MESSAGE: usize = 65535;
+
+/// Answers are cached for their TTL, up to this long.
+const MAX_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Expired answers are purged once this many names are cached.
+const MAX_CACHED: usize = 1024;
+
+/// A resolver sending its queries to a DNS-over-HTTPS server.
+#[derive(Clone)]
+pub(crate) struct DohResolver {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    uri: Uri,
+    host: String,
+    client: OnceLock<DohClient>,
+    cache: Mutex<HashMap<String, Cached>>,
+}
+
+struct Cached {
+    addrs: Vec<IpAddr>,
+    expires: Instant,
+}
+
+struct Answer {
+    addrs: Vec<IpAddr>,
+    ttl: u32,
+}
+
+/// The ECH configurations published by a server.
+pub(crate) struct EchAnswer {
+    /// The `ECHConfigList` of the HTTPS record, if it has one.
+    pub(crate) list: Option<Vec<u8>>,
+    pub(crate) ttl: Duration,
+}
+
+impl DohResolver {
+    /// A resolver querying `url`, which must have been checked to be an
+    /// https URL.
+    pub(crate) fn new(url: &Url) -> DohResolver {
+        DohResolver {
+            shared: Arc::new(Shared {
+                uri: crate::into_url::expect_uri(url),
+                host: url.host_str().unwrap_or_default().to_owned(),
+                client: OnceLock::new(),
+                cache: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// The host of the server, resolved with the bootstrap resolver.
+    pub(crate) fn host(&self) -> &str {
+        &self.shared.host
+    }
+
+    /// Set the client queries are sent with, once the client being built
+    /// has its connector.
+    pub(crate) fn set_client(&self, client: DohClient) {
+        let _ = self.shared.client.set(client);
+    }
+
+    /// Look up the ECH configurations in the HTTPS record of the service at
+    /// `host` and `port`, see RFC 9460.
+    pub(crate) async fn lookup_ech(&self, host: &str, port: u16) -> Result<EchAnswer, BoxError> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let name = match port {
+            443 => host,
+            port => format!("_{}._https.{}", port, host),
+        };
+        let msg = self.shared.query(&name, TYPE_HTTPS).await?;
+        decode_ech(&msg)
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let shared = self.shared.clone();
+        Box::pin(async move {
+            let addrs = shared.lookup(name.as_str()).await?;
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+impl Shared {
+    async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, BoxError> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        // Like browsers, never ask the server about localhost.
+        if host == "localhost" || host.ends_with(".localhost") {
+            return Ok(vec![Ipv6Addr::LOCALHOST.into(), Ipv4Addr::LOCALHOST.into()]);
+        }
+        if let Some(addrs) = self.cached(&host) {
+            return Ok(addrs);
+        }
+
+        let (v6, v4) = futures_util::future::join(
+            self.query_addrs(&host, TYPE_AAAA),
+            self.query_addrs(&host, TYPE_A),
+        )
+        .await;
+
+        let mut addrs = Vec::new();
+        let mut ttl = u32::MAX;
+        let mut error = None;
+        for answer in [v6, v4] {
+            match answer {
+                Ok(answer) => {
+                    addrs.extend(answer.addrs);
+                    ttl = ttl.min(answer.ttl);
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+        if addrs.is_empty() {
+            return Err(error.unwrap_or_else(|| format!("no addresses found for {}", host).into()));
+        }
+
+        let ttl = Duration::from_secs(ttl.into()).min(MAX_TTL);
+        self.cache(host, addrs.clone(), ttl);
+        Ok(addrs)
+    }
+
+    fn cached(&self, host: &str) -> Option<Vec<IpAddr>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(host)
+            .filter(|cached| cached.expires > Instant::now())
+            .map(|cached| cached.addrs.clone())
+    }
+
+    fn cache(&self, host: String, addrs: Vec<IpAddr>, ttl: Duration) {
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED {
+            cache.retain(|_, cached| cached.expires > now);
+        }
+        if cache.len() < MAX_CACHED {
+            let expires = now + ttl;
+            cache.insert(host, Cached { addrs, expires });
+        }
+    }
+
+    async fn query_addrs(&self, host: &str, qtype: u16) -> Result<Answer, BoxError> {
+        decode_answer(&self.query(host, qtype).await?)
+    }
+
+    async fn query(&self, host: &str, qtype: u16) -> Result<bytes::Bytes, BoxError> {
+        let client = self
+            .client
+            .get()
+            .ok_or("DNS-over-HTTPS client is not built yet")?;
+        let req = http::Request::post(self.uri.clone())
+            .header(ACCEPT, DNS_MESSAGE)
+            .header(CONTENT_TYPE, DNS_MESSAGE)
+            .body(hyper::Body::from(encode_query(host, qtype)?))?;
+
+        let res = client.request(req).await?;
+        if !res.status().is_success() {
+            return Err(format!("DNS-over-HTTPS server responded with {}", res.status()).into());
+        }
+        let body = http_body::Limited::new(res.into_body(), MAX_MESSAGE);
+        Ok(hyper::body::to_bytes(body).await?)
+    }
+}
+
+/// Encode a recursive query for the `qtype` records of `host`.
+///
+/// The id is 0, as RFC 8484 recommends for caching, and an EDNS(0) record
+/// pads the query to a multiple of `PADDING_BLOCK`.
+fn encode_query(host: &str, qtype: u16) -> Result<Vec<u8>, BoxError> {
+    if host.len() > 253 {
+        return Err(format!("invalid DNS name {:?}", host).into());
+    }
+
+    let mut msg = Vec::with_capacity(PADDING_BLOCK);
+    // id, flags with recursion desired, one question, one additional record
+    msg.extend_from_slice(&[0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 1]);
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("invalid DNS name {:?}", host).into());
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&qtype.to_be_bytes());
+    msg.extend_from_slice(&1u16.to_be_bytes());
+
+    // OPT record: root name, UDP payload size, extended rcode and flags
+    msg.push(0);
+    msg.extend_from_slice(&TYPE_OPT.to_be_bytes());
+    msg.extend_from_slice(&4096u16.to_be_bytes());
+    msg.extend_from_slice(&[0, 0, 0, 0]);
+    // its data length, then the padding option
+    let padding = (PADDING_BLOCK - (msg.len() + 6) % PADDING_BLOCK) % PADDING_BLOCK;
+    msg.extend_from_slice(&(4 + padding as u16).to_be_bytes());
+    msg.extend_from_slice(&OPTION_PADDING.to_be_bytes());
+    msg.extend_from_slice(&(padding as u16).to_be_bytes());
+    msg.resize(msg.len() + padding, 0);
+    Ok(msg)
+}
+
+/// Decode the A and AAAA records of a response, and their lowest TTL.
+fn decode_answer(msg: &[u8]) -> Result<Answer, BoxError> {
+    let mut answer = Answer {
+        addrs: Vec::new(),
+        ttl: u32::MAX,
+    };
+    for record in records(msg)? {
+        // CNAME records are followed by the records of their target.
+        let addr = match record.rtype {
+            TYPE_A => <[u8; 4]>::try_from(record.data).map(IpAddr::from),
+            TYPE_AAAA => <[u8; 16]>::try_from(record.data).map(IpAddr::from),
+            _ => continue,
+        };
+        answer.addrs.push(addr.map_err(|_| malformed())?);
+        answer.ttl = answer.ttl.min(record.ttl);
+    }
+    Ok(answer)
+}
+
+/// Decode the `ech` parameter of the HTTPS records of a response.
+///
+/// Records in alias mode are skipped, and an answer without records is
+/// given a TTL of a minute.
+fn decode_ech(msg: &[u8]) -> Result<EchAnswer, BoxError> {
+    const PARAM_ECH: u16 = 5;
+
+    let mut answer = EchAnswer {
+        list: None,
+        ttl: Duration::from_secs(60),
+    };
+    let mut best = u16::MAX;
+    for record in records(msg)? {
+        if record.rtype != TYPE_HTTPS {
+            continue;
+        }
+        answer.ttl = Duration::from_secs(record.ttl.into()).min(MAX_TTL);
+
+        let data = record.data;
+        let priority = data.get(..2).ok_or_else(malformed)?;
+        let priority = u16::from_be_bytes([priority[0], priority[1]]);
+        if priority == 0 || priority >= best {
+            continue;
+        }
+        // The target name is never compressed.
+        let mut pos = skip_name(data, 2).ok_or_else(malformed)?;
+        while pos < data.len() {
+            let param = data.get(pos..pos + 4).ok_or_else(malformed)?;
+            let key = u16::from_be_bytes([param[0], param[1]]);
+            let len = usize::from(u16::from_be_bytes([param[2], param[3]]));
+            let value = data.get(pos + 4..pos + 4 + len).ok_or_else(malformed)?;
+            pos += 4 + len;
+            if key == PARAM_ECH {
+                answer.list = Some(value.to_vec());
+                best = priority;
+            }
+        }
+    }
+    Ok(answer)
+}
+
+struct Record<'a> {
+    rtype: u16,
+    ttl: u32,
+    data: &'a [u8],
+}
+
+/// The records of the answer section of a response.
+fn records(msg: &[u8]) -> Result<Vec<Record<'_>>, BoxError> {
+    let header = msg.get(..12).ok_or_else(malformed)?;
+    match header[3] & 0x0f {
+        0 => {}
+        3 => return Err("no such host".into()),
+        rcode => return Err(format!("DNS-over-HTTPS query failed with rcode {}", rcode).into()),
+    }
+    let questions = u16::from_be_bytes([header[4], header[5]]);
+    let answers = u16::from_be_bytes([header[6], header[7]]);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        // name, type and class
+        pos = skip_name(msg, pos).ok_or_else(malformed)? + 4;
+    }
+
+    let mut records = Vec::with_capacity(answers.into());
+    for _ in 0..answers {
+        pos = skip_name(msg, pos).ok_or_else(malformed)?;
+        let fixed = msg.get(pos..pos + 10).ok_or_else(malformed)?;
+        let len = usize::from(u16::from_be_bytes([fixed[8], fixed[9]]));
+        records.push(Record {
+            rtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+            ttl: u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]),
+            data: msg.get(pos + 10..pos + 10 + len).ok_or_else(malformed)?,
+        });
+        pos += 10 + len;
+    }
+    Ok(records)
+}
+
+fn malformed() -> BoxError {
+    "malformed DNS-over-HTTPS response".into()
+}
+
+/// The position after the name at `pos`, which may be compressed.
+fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        match *msg.get(pos)? {
+            0 => return Some(pos + 1),
+            len if len & 0xc0 == 0xc0 => {
+                msg.get(pos + 1)?;
+                return Some(pos + 2);
+            }
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_is_padded() {
+        let query = encode_query("example.com", TYPE_AAAA).unwrap();
+        assert_eq!(query.len() % PADDING_BLOCK, 0);
+        assert_eq!(&query[..2], [0, 0]);
+        assert_eq!(&query[12..25], b"\x07example\x03com\x00");
+
+        assert!(encode_query("a..b", TYPE_A).is_err());
+    }
+
+    #[test]
+    fn answer_with_cname() {
+        let mut msg = vec![0, 0, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        msg.extend_from_slice(b"\x03www\x07example\x03com\x00\x00\x01\x00\x01");
+        // CNAME to example.com, compressed
+        msg.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 16]);
+        msg.extend_from_slice(&[0xc0, 16, 0, 1, 0, 1, 0, 0, 0, 30, 0, 4, 93, 184, 216, 34]);
+
+        let answer = decode_answer(&msg).unwrap();
+        assert_eq!(answer.addrs, [IpAddr::from([93, 184, 216, 34])]);
+        assert_eq!(answer.ttl, 30);
+
+        msg[3] = 0x83;
+        assert_eq!(
+            decode_answer(&msg).err().unwrap().to_string(),
+            "no such host"
+        );
+        assert!(decode_answer(&msg[..20]).is_err());
+    }
+
+    #[test]
+    fn ech_of_https_record() {
+        let mut msg = vec![0, 0, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        msg.extend_from_slice(b"\x07example\x03com\x00\x00\x41\x00\x01");
+        // priority 1, target ".", alpn=h2, ech=0xfe0d...
+        let rdata = b"\x00\x01\x00\x00\x01\x00\x03\x02h2\x00\x05\x00\x04\x00\x02\xfe\x0d";
+        msg.extend_from_slice(&[0xc0, 12, 0, 65, 0, 1, 0, 0, 1, 44, 0, rdata.len() as u8]);
+        msg.extend_from_slice(rdata);
+
+        let answer = decode_ech(&msg).unwrap();
+        assert_eq!(answer.list.as_deref(), Some(&b"\x00\x02\xfe\x0d"[..]));
+        assert_eq!(answer.ttl, Duration::from_secs(300));
+
+        let last = msg.len() - 1;
+        assert!(decode_ech(&msg[..last]).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Per-host memory of a broken IP family.
+//!
+//! When a host resolves to both IPv6 and IPv4 addresses, a connection tries
+//! the family of the first address, then falls back to the other one. If
+//! connections to a host keep falling back, its addresses of the working
+//! family are tried first for a configurable TTL, sparing later connections
+//! the wait for the broken one to fail.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{Addrs, Name, Resolve, Resolving};
+
+/// Consecutive fallbacks after which the other family is preferred.
+const FALLBACKS: u32 = 2;
+
+/// Hosts remembered, beyond which those without a preference are forgotten,
+/// then those whose preference expires first.
+const MAX_HOSTS: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    fn of(addr: &SocketAddr) -> Family {
+        match addr {
+            SocketAddr::V4(_) => Family::V4,
+            SocketAddr::V6(_) => Family::V6,
+        }
+    }
+}
+
+#[derive(Default)]
+struct HostState {
+    /// The family tried first after the latest lookup.
+    first: Option<Family>,
+    /// Consecutive connections that fell back from `first`.
+    fallbacks: u32,
+    preferred: Option<(Family, Instant)>,
+}
+
+/// A cache of the IP family to try first, keyed by host.
+pub(crate) struct FamilyCache {
+    ttl: Duration,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl FamilyCache {
+    pub(crate) fn new(ttl: Duration) -> FamilyCache {
+        FamilyCache {
+            ttl,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Put the addresses of the family preferred for `host` first.
+    fn order(&self, host: &str, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        let first = match addrs.first() {
+            Some(addr) => Family::of(addr),
+            None => return addrs,
+        };
+        if addrs.iter().all(|addr| Family::of(addr) == first) {
+            return addrs;
+        }
+
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap();
+        if hosts.len() >= MAX_HOSTS && !hosts.contains_key(host) {
+            hosts.retain(|_, state| matches!(state.preferred, Some((_, expires)) if expires > now));
+            if hosts.len() >= MAX_HOSTS {
+                let first_expiring = hosts
+                    .iter()
+                    .min_by_key(|(_, state)| state.preferred.map(|(_, expires)| expires))
+                    .map(|(host, _)| host.clone());
+                if let Some(host) = first_expiring {
+                    hosts.remove(&host);
+                }
+            }
+        }
+        let state = hosts.entry(host.to_owned()).or_default();
+        match state.preferred {
+            Some((family, expires)) if expires > now => {
+                // Stable, so the resolver order is kept within a family.
+                addrs.sort_by_key(|addr| Family::of(addr) != family);
+            }
+            Some(_) => state.preferred = None,
+            None => {}
+        }
+        state.first = addrs.first().map(Family::of);
+        addrs
+    }
+
+    /// Record that a connection to `host` was made to `peer`.
+    pub(crate) fn observe(&self, host: &str, peer: SocketAddr) {
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = match hosts.get_mut(host) {
+            Some(state) => state,
+            None => return,
+        };
+        let family = Family::of(&peer);
+        match state.first {
+            Some(first) if first != family => state.fallbacks += 1,
+            _ => {
+                state.fallbacks = 0;
+                return;
+            }
+        }
+        if state.fallbacks >= FALLBACKS {
+            log::debug!(
+                "preferring {:?} addresses of {} for {:?}",
+                family,
+                host,
+                self.ttl
+            );
+            state.preferred = Some((family, Instant::now() + self.ttl));
+            state.first = Some(family);
+            state.fallbacks = 0;
+        }
+    }
+}
+
+/// A resolver ordering addresses according to a `FamilyCache`.
+pub(crate) struct FamilyResolver {
+    resolver: Arc<dyn Resolve>,
+    cache: Arc<FamilyCache>,
+}
+
+impl FamilyResolver {
+    pub(crate) fn new(resolver: Arc<dyn Resolve>, cache: Arc<FamilyCache>) -> Self {
+        FamilyResolver { resolver, cache }
+    }
+}
+
+impl Resolve for FamilyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolving = self.resolver.resolve(name.clone());
+        let cache = self.cache.clone();
+        Box::pin(async move {
+            let addrs = resolving.await?.collect::<Vec<_>>();
+            let addrs: Addrs = Box::new(cache.order(name.as_str(), addrs).into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_working_family() {
+        let cache = FamilyCache::new(Duration::from_secs(60));
+        let v6: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let v4: SocketAddr = "192.0.2.1:443".parse().unwrap();
+
+        // A single fallback is not enough.
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v6, v4]);
+        cache.observe("example.com", v4);
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v6, v4]);
+        cache.observe("example.com", v6);
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v6, v4]);
+
+        cache.observe("example.com", v4);
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v6, v4]);
+        cache.observe("example.com", v4);
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v4, v6]);
+        assert_eq!(cache.order("other.com", vec![v6, v4]), [v6, v4]);
+
+        // And back, when the preferred family breaks in turn.
+        cache.observe("example.com", v6);
+        cache.order("example.com", vec![v6, v4]);
+        cache.observe("example.com", v6);
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v6, v4]);
+    }
+
+    #[test]
+    fn bounded() {
+        let cache = FamilyCache::new(Duration::from_secs(60));
+        let v6: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let v4: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        // Hosts that all keep a preference.
+        for i in 0..MAX_HOSTS + 10 {
+            let host = format!("{}.example.com", i);
+            for _ in 0..FALLBACKS {
+                cache.order(&host, vec![v6, v4]);
+                cache.observe(&host, v4);
+            }
+        }
+        assert!(cache.hosts.lock().unwrap().len() <= MAX_HOSTS);
+    }
+
+    #[test]
+    fn expires() {
+        let cache = FamilyCache::new(Duration::ZERO);
+        let v6: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let v4: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        for _ in 0..FALLBACKS {
+            cache.order("example.com", vec![v6, v4]);
+            cache.observe("example.com", v4);
+        }
+        assert_eq!(cache.order("example.com", vec![v6, v4]), [v6, v4]);
+    }
+}
@@ -1,10 +1,15 @@
 //! DNS resolution via the [hickory-resolver](https://github.com/hickory-dns/hickory-dns) crate
 
 use super::{Addrs, Name, Resolve, Resolving};
+use hickory_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
 use hickory_resolver::{lookup_ip::LookupIpIntoIter, system_conf, TokioAsyncResolver};
 use std::io;
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::OnceCell;
 
 /// Wrapper around an `AsyncResolver`, which implements the `Resolve` trait.
@@ -14,17 +19,143 @@ pub(crate) struct HickoryDnsResolver {
     /// Tokio Runtime in initialization, so we must delay the actual
     /// construction of the resolver.
     state: Arc<OnceCell<TokioAsyncResolver>>,
+    config: Arc<HickoryConfig>,
+}
+
+/// Tuning of the hickory-dns resolver of a `Client`.
+///
+/// Starts from the system configuration, read from `/etc/resolv.conf` on
+/// Unix, with each option overriding or extending it.
+///
+/// Set with [`ClientBuilder::hickory_dns_config`](crate::ClientBuilder::hickory_dns_config).
+///
+/// # Example
+///
+/// ```
+/// use rquest::dns::HickoryConfig;
+///
+/// let config = HickoryConfig::new()
+///     .nameservers(["1.1.1.1:53".parse().unwrap(), "8.8.8.8:53".parse().unwrap()])
+///     .rotate(true)
+///     .search_domain("svc.cluster.local")
+///     .ndots(2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HickoryConfig {
+    search: Vec<String>,
+    nameservers: Vec<SocketAddr>,
+    ndots: Option<usize>,
+    rotate: Option<bool>,
+    timeout: Option<Duration>,
+    attempts: Option<usize>,
 }
 
 struct SocketAddrs {
     iter: LookupIpIntoIter,
 }
 
+impl HickoryDnsResolver {
+    pub(crate) fn new(config: HickoryConfig) -> Self {
+        HickoryDnsResolver {
+            state: Default::default(),
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl HickoryConfig {
+    /// Create a configuration with the system defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a domain to search for names with fewer dots than
+    /// [`ndots`](HickoryConfig::ndots), after the system ones.
+    pub fn search_domain(mut self, domain: &str) -> Self {
+        self.search.push(domain.to_owned());
+        self
+    }
+
+    /// Set the number of dots a name needs to be looked up as is before
+    /// the search domains.
+    pub fn ndots(mut self, ndots: usize) -> Self {
+        self.ndots = Some(ndots);
+        self
+    }
+
+    /// Spread queries across the nameservers in turn, instead of always
+    /// querying them in order.
+    pub fn rotate(mut self, enabled: bool) -> Self {
+        self.rotate = Some(enabled);
+        self
+    }
+
+    /// Query these nameservers, over UDP and TCP, instead of the system
+    /// ones.
+    pub fn nameservers<I: IntoIterator<Item = SocketAddr>>(mut self, nameservers: I) -> Self {
+        self.nameservers = nameservers.into_iter().collect();
+        self
+    }
+
+    /// Set the time to wait for the answer to a query.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the number of times a query is attempted.
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.attempts = Some(attempts);
+        self
+    }
+
+    fn apply(&self, config: &mut ResolverConfig, opts: &mut ResolverOpts) -> io::Result<()> {
+        if !self.nameservers.is_empty() {
+            let mut group = NameServerConfigGroup::with_capacity(self.nameservers.len() * 2);
+            for &addr in &self.nameservers {
+                group.push(NameServerConfig::new(addr, Protocol::Udp));
+                group.push(NameServerConfig::new(addr, Protocol::Tcp));
+            }
+            *config = ResolverConfig::from_parts(
+                config.domain().cloned(),
+                config.search().to_vec(),
+                group,
+            );
+        }
+        for domain in &self.search {
+            let name = hickory_resolver::Name::from_str(domain).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid DNS search domain {:?}: {}", domain, e),
+                )
+            })?;
+            config.add_search(name);
+        }
+        if let Some(ndots) = self.ndots {
+            opts.ndots = ndots;
+        }
+        if let Some(rotate) = self.rotate {
+            opts.rotate = rotate;
+        }
+        if let Some(timeout) = self.timeout {
+            opts.timeout = timeout;
+        }
+        if let Some(attempts) = self.attempts {
+            opts.attempts = attempts;
+        }
+        Ok(())
+    }
+}
+
 impl Resolve for HickoryDnsResolver {
     fn resolve(&self, name: Name) -> Resolving {
         let resolver = self.clone();
         Box::pin(async move {
-            let resolver = resolver.state.get_or_try_init(new_resolver).await?;
+            let config = &resolver.config;
+            let resolver = resolver
+                .state
+                .get_or_try_init(|| new_resolver(config))
+                .await?;
 
             let lookup = resolver.lookup_ip(name.as_str()).await?;
             let addrs: Addrs = Box::new(SocketAddrs {
@@ -44,13 +175,52 @@ impl Iterator for SocketAddrs {
 }
 
 /// Create a new resolver with the default configuration,
-/// which reads from `/etc/resolve.conf`.
-async fn new_resolver() -> io::Result<TokioAsyncResolver> {
-    let (config, opts) = system_conf::read_system_conf().map_err(|e| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!("error reading DNS system conf: {}", e),
-        )
-    })?;
+/// which reads from `/etc/resolve.conf`, tuned by `tuning`.
+async fn new_resolver(tuning: &HickoryConfig) -> io::Result<TokioAsyncResolver> {
+    let (mut config, mut opts) = match system_conf::read_system_conf() {
+        Ok(conf) => conf,
+        // The system nameservers are not needed when they are overridden.
+        Err(_) if !tuning.nameservers.is_empty() => (
+            ResolverConfig::from_parts(None, Vec::new(), Vec::new()),
+            ResolverOpts::default(),
+        ),
+        Err(e) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("error reading DNS system conf: {}", e),
+            ))
+        }
+    };
+    tuning.apply(&mut config, &mut opts)?;
     Ok(TokioAsyncResolver::tokio(config, opts))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuning() {
+        let tuning = HickoryConfig::new()
+            .nameservers(["10.0.0.53:53".parse().unwrap()])
+            .search_domain("svc.cluster.local")
+            .ndots(5)
+            .rotate(true);
+        let mut config = ResolverConfig::default();
+        let mut opts = ResolverOpts::default();
+        tuning.apply(&mut config, &mut opts).unwrap();
+
+        let addrs = config
+            .name_servers()
+            .iter()
+            .map(|ns| ns.socket_addr)
+            .collect::<Vec<_>>();
+        assert_eq!(addrs, ["10.0.0.53:53".parse().unwrap(); 2]);
+        assert_eq!(config.search().len(), 1);
+        assert_eq!(opts.ndots, 5);
+        assert!(opts.rotate);
+
+        let invalid = HickoryConfig::new().search_domain("a..b");
+        assert!(invalid.apply(&mut config, &mut opts).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Timeouts, fallback and provenance of lookups.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{Name, Resolve, Resolving};
+use crate::error::{BoxError, TimedOut};
+
+/// The resolver that produced the addresses of a connection.
+///
+/// Available from [`Response::resolved_by`](crate::Response::resolved_by).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResolvedBy {
+    /// The system resolver, `getaddrinfo` or hickory-dns.
+    System,
+    /// The resolver set with
+    /// [`ClientBuilder::dns_resolver`](crate::ClientBuilder::dns_resolver).
+    Custom,
+    /// The DNS-over-HTTPS server set with
+    /// [`ClientBuilder::dns_over_https`](crate::ClientBuilder::dns_over_https).
+    DnsOverHttps,
+    /// An address set with [`ClientBuilder::resolve`](crate::ClientBuilder::resolve).
+    Override,
+}
+
+/// How a custom resolver combines with the system one.
+///
+/// See [`ClientBuilder::dns_fallback`](crate::ClientBuilder::dns_fallback).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Only use the custom resolver, the default.
+    #[default]
+    Disabled,
+    /// Use the custom resolver, then the system one if it fails.
+    CustomThenSystem,
+    /// Use the system resolver, then the custom one if it fails.
+    SystemThenCustom,
+}
+
+tokio::task_local! {
+    static RESOLVED_BY: Cell<Option<ResolvedBy>>;
+}
+
+/// Run `connect`, returning which resolver answered the lookups it made.
+pub(crate) async fn scope<F: Future>(connect: F) -> (F::Output, Option<ResolvedBy>) {
+    RESOLVED_BY
+        .scope(Cell::new(None), async move {
+            let output = connect.await;
+            (output, RESOLVED_BY.with(Cell::get))
+        })
+        .await
+}
+
+/// Record that `source` answered the current lookup, if it is in a `scope`.
+pub(crate) fn record(source: ResolvedBy) {
+    let _ = RESOLVED_BY.try_with(|cell| cell.set(Some(source)));
+}
+
+/// A resolver recording its answers as coming from `source`, and failing
+/// lookups that take longer than `timeout`.
+pub(crate) struct Lookup {
+    resolver: Arc<dyn Resolve>,
+    source: ResolvedBy,
+    timeout: Option<Duration>,
+}
+
+impl Lookup {
+    pub(crate) fn new(
+        resolver: Arc<dyn Resolve>,
+        source: ResolvedBy,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Lookup {
+            resolver,
+            source,
+            timeout,
+        }
+    }
+}
+
+impl Resolve for Lookup {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolving = self.resolver.resolve(name);
+        let source = self.source;
+        let timeout = self.timeout;
+        Box::pin(async move {
+            let addrs = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, resolving)
+                    .await
+                    .map_err(|_| Box::new(TimedOut) as BoxError)??,
+                None => resolving.await?,
+            };
+            record(source);
+            crate::timing::mark_resolved();
+            Ok(addrs)
+        })
+    }
+}
+
+/// A resolver trying `secondary` when `primary` fails.
+pub(crate) struct FallbackResolver {
+    primary: Arc<dyn Resolve>,
+    secondary: Arc<dyn Resolve>,
+}
+
+impl FallbackResolver {
+    pub(crate) fn new(primary: Arc<dyn Resolve>, secondary: Arc<dyn Resolve>) -> Self {
+        FallbackResolver { primary, secondary }
+    }
+}
+
+impl Resolve for FallbackResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let primary = self.primary.resolve(name.clone());
+        let secondary = self.secondary.clone();
+        Box::pin(async move {
+            match primary.await {
+                Ok(addrs) => Ok(addrs),
+                Err(err) => {
+                    log::debug!("DNS lookup of {} failed, falling back: {}", name, err);
+                    secondary.resolve(name).await
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::Addrs;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    struct Fixed(Option<SocketAddr>);
+
+    impl Resolve for Fixed {
+        fn resolve(&self, _: Name) -> Resolving {
+            let addr = self.0;
+            Box::pin(async move {
+                match addr {
+                    Some(addr) => Ok(Box::new(std::iter::once(addr)) as Addrs),
+                    None => Err("no such host".into()),
+                }
+            })
+        }
+    }
+
+    struct Stalled;
+
+    impl Resolve for Stalled {
+        fn resolve(&self, _: Name) -> Resolving {
+            Box::pin(futures_util::future::pending())
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback_and_provenance() {
+        let addr = "10.0.0.1:0".parse().unwrap();
+        let timeout = Some(Duration::from_millis(10));
+        let custom = Arc::new(Lookup::new(Arc::new(Stalled), ResolvedBy::Custom, timeout));
+        let system = Arc::new(Lookup::new(
+            Arc::new(Fixed(Some(addr))),
+            ResolvedBy::System,
+            timeout,
+        ));
+        let resolver = FallbackResolver::new(custom.clone(), system);
+
+        let name = Name::from_str("example.com").unwrap();
+        let (addrs, source) = scope(resolver.resolve(name.clone())).await;
+        assert_eq!(addrs.unwrap().collect::<Vec<_>>(), [addr]);
+        assert_eq!(source, Some(ResolvedBy::System));
+
+        let (res, source) = scope(custom.resolve(name)).await;
+        let err = res.err().unwrap();
+        assert!(err.is::<TimedOut>());
+        assert_eq!(source, None);
+    }
+}
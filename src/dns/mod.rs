@@ -1,9 +1,17 @@
 //! DNS resolution
 
-pub use resolve::{Addrs, Name, Resolve, Resolving};
+#[cfg(feature = "hickory-dns")]
+pub use hickory::HickoryConfig;
+pub use lookup::{Fallback, ResolvedBy};
+pub use resolve::{Addrs, IntoResolve, Name, Resolve, Resolving};
 pub(crate) use resolve::{DnsResolverWithOverrides, DynResolver};
 
+#[cfg(feature = "__boring")]
+pub(crate) mod doh;
+pub(crate) mod family;
 pub(crate) mod gai;
 #[cfg(feature = "hickory-dns")]
 pub(crate) mod hickory;
+pub(crate) mod lookup;
+pub(crate) mod private;
 pub(crate) mod resolve;
@@ -0,0 +1,203 @@
+//! Rejection of private, loopback and link-local destinations.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use url::{Host, Url};
+
+use super::{Addrs, Name, Resolve, Resolving};
+
+/// A resolver failing for names that resolve to a non-public address.
+///
+/// Every connection resolves its host again, so a name that is re-bound
+/// to a private address after a first check is still rejected.
+pub(crate) struct DenyPrivateIps {
+    resolver: Arc<dyn Resolve>,
+}
+
+impl DenyPrivateIps {
+    pub(crate) fn new(resolver: Arc<dyn Resolve>) -> Self {
+        DenyPrivateIps { resolver }
+    }
+}
+
+impl Resolve for DenyPrivateIps {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolving = self.resolver.resolve(name);
+        Box::pin(async move {
+            let addrs = resolving.await?.collect::<Vec<_>>();
+            if let Some(addr) = addrs.iter().find(|addr| is_private(addr.ip())) {
+                return Err(PrivateAddress(addr.ip()).into());
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Check the host of `url` when it is an IP address, which is connected to
+/// without being resolved.
+pub(crate) fn check_url(url: &Url) -> Result<(), PrivateAddress> {
+    let ip = match url.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        _ => return Ok(()),
+    };
+    check_addr(ip)
+}
+
+/// Check an address that is connected to without resolving a name, such as
+/// the one set with `RequestBuilder::resolve_to`.
+pub(crate) fn check_addr(ip: IpAddr) -> Result<(), PrivateAddress> {
+    if is_private(ip) {
+        return Err(PrivateAddress(ip));
+    }
+    Ok(())
+}
+
+/// The error of a connection to a non-public address.
+#[derive(Debug)]
+pub(crate) struct PrivateAddress(IpAddr);
+
+impl fmt::Display for PrivateAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "destination {} is not a public address", self.0)
+    }
+}
+
+impl StdError for PrivateAddress {}
+
+/// Whether `ip` is an address that untrusted URLs must not reach.
+pub(crate) fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => is_private_v6(ip),
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    match a {
+        // "This network", private, loopback.
+        0 | 10 | 127 => true,
+        // Shared address space (carrier-grade NAT).
+        100 => (64..128).contains(&b),
+        // Link-local, including cloud metadata at 169.254.169.254.
+        169 => b == 254,
+        172 => (16..32).contains(&b),
+        // IETF protocol assignments, TEST-NET-1 and private.
+        192 => (b == 0 && (c == 0 || c == 2)) || b == 168,
+        // Benchmarking, and TEST-NET-2.
+        198 => b == 18 || b == 19 || (b == 51 && c == 100),
+        // TEST-NET-3.
+        203 => b == 0 && c == 113,
+        // Multicast, reserved and broadcast.
+        224..=255 => true,
+        _ => false,
+    }
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    if let Some(v4) = embedded_v4(&segments) {
+        return is_private_v4(v4);
+    }
+    ip.is_unspecified()
+        || ip.is_loopback()
+        // Unique local.
+        || segments[0] & 0xfe00 == 0xfc00
+        // Link-local.
+        || segments[0] & 0xffc0 == 0xfe80
+        // Site-local, deprecated but still routed by some networks.
+        || segments[0] & 0xffc0 == 0xfec0
+        // Multicast.
+        || segments[0] & 0xff00 == 0xff00
+        // Documentation.
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+}
+
+/// The IPv4 address mapped into or translated by `segments`, if any.
+fn embedded_v4(segments: &[u16; 8]) -> Option<Ipv4Addr> {
+    let v4 = |hi: u16, lo: u16| Ipv4Addr::new((hi >> 8) as u8, hi as u8, (lo >> 8) as u8, lo as u8);
+    match *segments {
+        // IPv4-mapped, ::ffff:a.b.c.d
+        [0, 0, 0, 0, 0, 0xffff, hi, lo] => Some(v4(hi, lo)),
+        // IPv4-compatible, ::a.b.c.d, past the loopback and unspecified
+        // addresses.
+        [0, 0, 0, 0, 0, 0, hi, lo] if hi != 0 => Some(v4(hi, lo)),
+        // NAT64, 64:ff9b::a.b.c.d
+        [0x64, 0xff9b, 0, 0, 0, 0, hi, lo] => Some(v4(hi, lo)),
+        // 6to4, 2002:abcd:efgh::
+        [0x2002, hi, lo, ..] => Some(v4(hi, lo)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn private(ip: &str) -> bool {
+        is_private(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn private_ranges() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.31.255.255",
+            "192.168.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "100.127.255.255",
+            "198.18.0.1",
+            "198.19.255.255",
+            "192.0.2.1",
+            "198.51.100.7",
+            "203.0.113.9",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "::ffff:10.0.0.1",
+            "::ffff:100.64.0.1",
+            "::a9fe:a9fe",
+            "fec0::1",
+            "2001:db8::1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:c0a8:0101::1",
+        ] {
+            assert!(private(ip), "{} should be private", ip);
+        }
+
+        for ip in [
+            "93.184.216.34",
+            "172.32.0.1",
+            "100.128.0.1",
+            "100.63.255.255",
+            "198.17.255.255",
+            "198.20.0.1",
+            "2606:2800:220:1::1",
+            "::ffff:93.184.216.34",
+        ] {
+            assert!(!private(ip), "{} should be public", ip);
+        }
+    }
+
+    #[test]
+    fn literal_urls() {
+        let check = |url: &str| check_url(&Url::parse(url).unwrap()).is_ok();
+        assert!(!check("http://169.254.169.254/latest/meta-data/"));
+        assert!(!check("http://2130706433/"));
+        assert!(!check("http://[::ffff:7f00:1]/"));
+        assert!(check("http://93.184.216.34/"));
+        assert!(check("http://localhost/"));
+    }
+}
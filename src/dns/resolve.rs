@@ -29,6 +29,29 @@ pub trait Resolve: Send + Sync {
     fn resolve(&self, name: Name) -> Resolving;
 }
 
+/// A resolver that can be passed to
+/// [`ClientBuilder::dns_resolver`](crate::ClientBuilder::dns_resolver).
+///
+/// Implemented for an `Arc` of any [`Resolve`] implementation, and for
+/// `Arc<dyn Resolve>` so that resolvers chosen at runtime can be shared
+/// between clients.
+pub trait IntoResolve {
+    /// Convert into a shared resolver.
+    fn into_resolve(self) -> Arc<dyn Resolve>;
+}
+
+impl<R: Resolve + 'static> IntoResolve for Arc<R> {
+    fn into_resolve(self) -> Arc<dyn Resolve> {
+        self
+    }
+}
+
+impl IntoResolve for Arc<dyn Resolve> {
+    fn into_resolve(self) -> Arc<dyn Resolve> {
+        self
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct DynResolver {
     resolver: Arc<dyn Resolve>,
@@ -75,6 +98,7 @@ impl Resolve for DnsResolverWithOverrides {
     fn resolve(&self, name: Name) -> Resolving {
         match self.overrides.get(name.as_str()) {
             Some(dest) => {
+                super::lookup::record(super::ResolvedBy::Override);
                 let addrs: Addrs = Box::new(dest.clone().into_iter());
                 Box::pin(futures_util::future::ready(Ok(addrs)))
             }
@@ -0,0 +1,440 @@
+//! Egress Policy
+//!
+//! By default, a `Client` connects to any destination. An `egress::Policy`
+//! set with [`ClientBuilder::egress_policy`] restricts the destinations of
+//! all requests in one place, including redirects.
+//!
+//! A destination is checked twice: once with the scheme, host and port of
+//! its URL before the request is sent, and once with every address its host
+//! resolves to before connecting, so a host re-bound to another address is
+//! checked again. A [`Rule`] with an [`ip_range`](Rule::ip_range) is an
+//! address rule, checked against the resolved addresses, or against the
+//! URL when its host is an IP address. Other rules are URL rules.
+//!
+//! [`ClientBuilder::egress_policy`]: crate::ClientBuilder::egress_policy
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use ipnet::IpNet;
+use url::{Host, Url};
+
+use crate::dns::{Addrs, Name, Resolve, Resolving};
+use crate::error::BoxError;
+
+/// A type that controls which destinations a `Client` may connect to.
+///
+/// - `allow` only lets through destinations matching one of its rules.
+/// - `deny` blocks destinations matching any of its rules.
+/// - `custom` can be used to create a customized policy.
+pub struct Policy {
+    inner: PolicyKind,
+}
+
+/// A condition on the destinations of requests.
+///
+/// A new `Rule` matches every destination. Each method adds a condition
+/// that must hold as well.
+///
+/// # Example
+///
+/// ```rust
+/// use rquest::egress::Rule;
+///
+/// // HTTPS to any subdomain of example.com
+/// let api = Rule::new().scheme("https").host("*.example.com");
+/// // Anything in 10.0.0.0/8
+/// let internal = Rule::new().ip_range([10, 0, 0, 0], 8);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Rule {
+    scheme: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    ip: Option<IpNet>,
+}
+
+/// A destination checked against a `Policy`.
+#[derive(Debug)]
+pub struct Target<'a> {
+    scheme: Option<&'a str>,
+    host: &'a str,
+    port: Option<u16>,
+    ip: Option<IpAddr>,
+}
+
+/// The outcome of checking a `Target`.
+#[derive(Debug)]
+pub struct Decision {
+    inner: Result<(), BoxError>,
+}
+
+impl Policy {
+    /// Create a `Policy` that only allows destinations matching one of
+    /// `rules`.
+    ///
+    /// When `rules` has both URL and address rules, a destination must match
+    /// one of each.
+    pub fn allow<I: IntoIterator<Item = Rule>>(rules: I) -> Self {
+        Self {
+            inner: PolicyKind::Allow(rules.into_iter().collect()),
+        }
+    }
+
+    /// Create a `Policy` that denies destinations matching any of `rules`.
+    pub fn deny<I: IntoIterator<Item = Rule>>(rules: I) -> Self {
+        Self {
+            inner: PolicyKind::Deny(rules.into_iter().collect()),
+        }
+    }
+
+    /// Create a custom `Policy` using the passed function.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rquest::{Error, egress};
+    /// #
+    /// # fn run() -> Result<(), Error> {
+    /// let blocked = egress::Policy::deny([egress::Rule::new().host("*.internal")]);
+    /// let custom = egress::Policy::custom(move |target| {
+    ///     if target.port().map_or(false, |port| port < 1024 && port != 80 && port != 443) {
+    ///         target.deny("privileged port")
+    ///     } else {
+    ///         blocked.check(target)
+    ///     }
+    /// });
+    /// let client = rquest::Client::builder()
+    ///     .egress_policy(custom)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn custom<T>(policy: T) -> Self
+    where
+        T: Fn(Target) -> Decision + Send + Sync + 'static,
+    {
+        Self {
+            inner: PolicyKind::Custom(Box::new(policy)),
+        }
+    }
+
+    /// Apply this policy to a given [`Target`] to produce a [`Decision`].
+    pub fn check(&self, target: Target) -> Decision {
+        match self.inner {
+            PolicyKind::Custom(ref custom) => custom(target),
+            PolicyKind::Allow(ref rules) => {
+                let allowed = [false, true].iter().all(|&address| {
+                    let mut group = rules
+                        .iter()
+                        .filter(|rule| rule.ip.is_some() == address)
+                        .peekable();
+                    !target.checks(address)
+                        || group.peek().is_none()
+                        || group.any(|rule| rule.matches(&target))
+                });
+                if allowed {
+                    target.allow()
+                } else {
+                    target.deny(Denied(target.host.to_owned()))
+                }
+            }
+            PolicyKind::Deny(ref rules) => {
+                if rules
+                    .iter()
+                    .any(|rule| target.checks(rule.ip.is_some()) && rule.matches(&target))
+                {
+                    target.deny(Denied(target.host.to_owned()))
+                } else {
+                    target.allow()
+                }
+            }
+        }
+    }
+
+    /// Check the URL of a request before it is sent.
+    pub(crate) fn check_url(&self, url: &Url) -> Result<(), BoxError> {
+        let ip = match url.host() {
+            Some(Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+            _ => None,
+        };
+        self.check(Target {
+            scheme: Some(url.scheme()),
+            host: url.host_str().unwrap_or(""),
+            port: url.port_or_known_default(),
+            ip,
+        })
+        .inner
+    }
+
+    pub(crate) fn check_addr(&self, host: &str, ip: IpAddr) -> Result<(), BoxError> {
+        self.check(Target {
+            scheme: None,
+            host,
+            port: None,
+            ip: Some(ip),
+        })
+        .inner
+    }
+}
+
+impl Rule {
+    /// Create a `Rule` matching every destination.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match URLs with this scheme.
+    ///
+    /// This is not checked against resolved addresses.
+    pub fn scheme(mut self, scheme: &str) -> Self {
+        self.scheme = Some(scheme.to_ascii_lowercase());
+        self
+    }
+
+    /// Only match this host.
+    ///
+    /// A leading `*.` matches any subdomain, at any depth, but not the
+    /// domain itself.
+    pub fn host(mut self, pattern: &str) -> Self {
+        self.host = Some(pattern.trim_end_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    /// Only match URLs with this port, explicit or the scheme's default.
+    ///
+    /// This is not checked against resolved addresses.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Only match addresses within `addr/prefix_len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix_len` is longer than the address.
+    pub fn ip_range<A: Into<IpAddr>>(mut self, addr: A, prefix_len: u8) -> Self {
+        let net = IpNet::new(addr.into(), prefix_len).expect("prefix length is too long");
+        self.ip = Some(net.trunc());
+        self
+    }
+
+    /// Whether the conditions of this rule known for `target` hold.
+    fn matches(&self, target: &Target) -> bool {
+        let scheme = match (&self.scheme, target.scheme) {
+            (Some(expected), Some(scheme)) => expected.eq_ignore_ascii_case(scheme),
+            _ => true,
+        };
+        let port = match (self.port, target.port) {
+            (Some(expected), Some(port)) => expected == port,
+            _ => true,
+        };
+        let ip = match (&self.ip, target.ip) {
+            (Some(net), Some(ip)) => net.contains(&ip),
+            _ => true,
+        };
+        let host = match self.host {
+            Some(ref pattern) => host_matches(pattern, target.host),
+            None => true,
+        };
+        scheme && port && ip && host
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    match pattern.strip_prefix("*.") {
+        Some(domain) => {
+            host.len() > domain.len() + 1
+                && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+                && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+        }
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
+impl<'a> Target<'a> {
+    /// Get the scheme of the request, unknown when checking an address.
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme
+    }
+
+    /// Get the host of the request.
+    pub fn host(&self) -> &str {
+        self.host
+    }
+
+    /// Get the port of the request, unknown when checking an address.
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Get the address to connect to, known for IP hosts and when checking
+    /// the addresses a host resolves to.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
+
+    /// Whether address rules (or else URL rules) are checked against this.
+    fn checks(&self, address: bool) -> bool {
+        // Only URLs have a scheme, and only addresses and IP hosts an IP.
+        if address {
+            self.ip.is_some()
+        } else {
+            self.scheme.is_some()
+        }
+    }
+
+    /// Returns a decision letting the request through.
+    pub fn allow(self) -> Decision {
+        Decision { inner: Ok(()) }
+    }
+
+    /// Returns a decision failing the request with an error.
+    pub fn deny<E: Into<Box<dyn StdError + Send + Sync>>>(self, error: E) -> Decision {
+        Decision {
+            inner: Err(error.into()),
+        }
+    }
+}
+
+impl Decision {
+    /// Whether the destination is allowed.
+    pub fn is_allowed(&self) -> bool {
+        self.inner.is_ok()
+    }
+}
+
+enum PolicyKind {
+    Allow(Vec<Rule>),
+    Deny(Vec<Rule>),
+    Custom(Box<dyn Fn(Target) -> Decision + Send + Sync + 'static>),
+}
+
+impl fmt::Debug for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Policy").field(&self.inner).finish()
+    }
+}
+
+impl fmt::Debug for PolicyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PolicyKind::Allow(ref rules) => f.debug_tuple("Allow").field(rules).finish(),
+            PolicyKind::Deny(ref rules) => f.debug_tuple("Deny").field(rules).finish(),
+            PolicyKind::Custom(..) => f.pad("Custom"),
+        }
+    }
+}
+
+// pub(crate)
+
+/// A resolver checking every resolved address against a `Policy`.
+pub(crate) struct PolicyResolver {
+    resolver: Arc<dyn Resolve>,
+    policy: Arc<Policy>,
+}
+
+impl PolicyResolver {
+    pub(crate) fn new(resolver: Arc<dyn Resolve>, policy: Arc<Policy>) -> Self {
+        PolicyResolver { resolver, policy }
+    }
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        let host = name.as_str().to_owned();
+        let resolving = self.resolver.resolve(name);
+        Box::pin(async move {
+            let addrs = resolving.await?.collect::<Vec<_>>();
+            for addr in &addrs {
+                policy.check_addr(&host, addr.ip())?;
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Denied(String);
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "destination {:?} denied by egress policy", self.0)
+    }
+}
+
+impl StdError for Denied {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(policy: &Policy, url: &str) -> bool {
+        policy.check_url(&Url::parse(url).unwrap()).is_ok()
+    }
+
+    fn addr(policy: &Policy, host: &str, ip: &str) -> bool {
+        policy.check_addr(host, ip.parse().unwrap()).is_ok()
+    }
+
+    #[test]
+    fn allow_list() {
+        let policy = Policy::allow([
+            Rule::new().scheme("https").host("*.example.com"),
+            Rule::new().host("example.org").port(8443),
+            Rule::new().ip_range([93, 184, 0, 0], 16),
+        ]);
+
+        assert!(url(&policy, "https://api.example.com/"));
+        assert!(url(&policy, "https://a.b.example.com/"));
+        assert!(!url(&policy, "http://api.example.com/"));
+        assert!(!url(&policy, "https://example.com/"));
+        assert!(!url(&policy, "https://badexample.com/"));
+        assert!(url(&policy, "https://example.org:8443/"));
+        assert!(!url(&policy, "https://example.org/"));
+        // Matches the address rule, but none of the URL rules.
+        assert!(!url(&policy, "https://93.184.216.34/"));
+
+        assert!(addr(&policy, "api.example.com", "93.184.216.34"));
+        assert!(!addr(&policy, "api.example.com", "10.0.0.1"));
+    }
+
+    #[test]
+    fn deny_list() {
+        let policy = Policy::deny([
+            Rule::new().host("metadata.google.internal"),
+            Rule::new().ip_range([169, 254, 0, 0], 16),
+            Rule::new().port(22),
+        ]);
+
+        assert!(url(&policy, "https://example.com/"));
+        assert!(!url(&policy, "http://metadata.google.internal/"));
+        assert!(!url(&policy, "http://169.254.169.254/"));
+        assert!(!url(&policy, "http://example.com:22/"));
+
+        assert!(addr(&policy, "example.com", "93.184.216.34"));
+        assert!(!addr(&policy, "example.com", "169.254.169.254"));
+    }
+
+    #[test]
+    fn custom() {
+        let policy = Policy::custom(|target| {
+            if target.host().ends_with(".test") {
+                target.deny("no tests")
+            } else {
+                target.allow()
+            }
+        });
+
+        assert!(url(&policy, "https://example.com/"));
+        assert!(!url(&policy, "https://a.test/"));
+        assert!(!addr(&policy, "a.test", "127.0.0.1"));
+    }
+}
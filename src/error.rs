@@ -303,6 +303,17 @@ impl fmt::Display for TimedOut {
 
 impl StdError for TimedOut {}
 
+#[derive(Debug)]
+pub(crate) struct Aborted;
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("request aborted by its scope")
+    }
+}
+
+impl StdError for Aborted {}
+
 #[derive(Debug)]
 pub(crate) struct BadScheme;
 
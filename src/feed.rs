@@ -0,0 +1,532 @@
+//! RSS, Atom and JSON feeds.
+//!
+//! [`Response::feed`](crate::Response::feed) parses the entries of a feed
+//! response. A [`FeedWatcher`] polls a feed with conditional requests, so
+//! an unchanged feed costs a `304 Not Modified`, and yields only the entries
+//! it has not seen before.
+//!
+//! RSS 0.9x, 1.0 and 2.0, Atom and [JSON Feed](https://jsonfeed.org) are
+//! recognized.
+//!
+//! This requires the optional `feed` feature to be enabled.
+
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use encoding_rs::{Encoding, UTF_8};
+use futures_core::Stream;
+use http::header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use http::StatusCode;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use url::Url;
+
+use crate::{error, Client, IntoUrl};
+
+/// Entry ids remembered by a `FeedWatcher`, unless a feed has more.
+const MAX_SEEN: usize = 10_000;
+
+/// An entry of a feed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Entry {
+    /// The `guid` or `id` of the entry, or else its link or title.
+    pub id: String,
+    /// The title of the entry.
+    pub title: Option<String>,
+    /// The link to the entry, resolved against the URL of the feed.
+    pub link: Option<String>,
+    /// When the entry was published, or else last updated, as written in
+    /// the feed.
+    pub published: Option<String>,
+}
+
+/// Polls a feed, yielding its new entries.
+///
+/// # Example
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use rquest::feed::FeedWatcher;
+/// use std::time::Duration;
+///
+/// # async fn run() -> Result<(), rquest::Error> {
+/// let mut entries = FeedWatcher::new(rquest::Client::new(), "https://blog.rust-lang.org/feed.xml")?
+///     .interval(Duration::from_secs(600))
+///     .skip_existing(true)
+///     .watch();
+///
+/// while let Some(entry) = entries.next().await {
+///     let entry = entry?;
+///     println!("{}: {:?}", entry.id, entry.title);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FeedWatcher {
+    client: Client,
+    url: Url,
+    interval: Duration,
+    skip_existing: bool,
+    polled: bool,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl FeedWatcher {
+    /// Watch the feed at `url`, requested with `client`.
+    pub fn new<U: IntoUrl>(client: Client, url: U) -> crate::Result<FeedWatcher> {
+        Ok(FeedWatcher {
+            client,
+            url: url.into_url()?,
+            interval: Duration::from_secs(300),
+            skip_existing: false,
+            polled: false,
+            etag: None,
+            last_modified: None,
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+        })
+    }
+
+    /// Set the time between two polls of [`watch`](FeedWatcher::watch).
+    ///
+    /// Default is 5 minutes.
+    pub fn interval(mut self, interval: Duration) -> FeedWatcher {
+        self.interval = interval;
+        self
+    }
+
+    /// Set whether the entries in the feed when it is first polled are
+    /// skipped, yielding only those published afterwards.
+    ///
+    /// Default is `false`.
+    pub fn skip_existing(mut self, skip: bool) -> FeedWatcher {
+        self.skip_existing = skip;
+        self
+    }
+
+    /// Poll the feed once, returning its new entries, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the request fails, the server responds with an error
+    /// status, or the feed can't be parsed.
+    pub async fn poll(&mut self) -> crate::Result<Vec<Entry>> {
+        let mut req = self.client.get(self.url.clone());
+        if let Some(ref etag) = self.etag {
+            req = req.header(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(ref last_modified) = self.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+        let res = req.send().await?;
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Vec::new());
+        }
+        let res = res.error_for_status()?;
+        self.etag = res.headers().get(ETAG).cloned();
+        self.last_modified = res.headers().get(LAST_MODIFIED).cloned();
+
+        let url = res.url().clone();
+        let entries = res.feed().await.map_err(|e| e.with_url(url))?;
+
+        let skip = self.skip_existing && !self.polled;
+        self.polled = true;
+        let limit = MAX_SEEN.max(2 * entries.len());
+        let mut new = Vec::new();
+        for entry in entries {
+            if self.seen.insert(entry.id.clone()) {
+                self.order.push_back(entry.id.clone());
+                if !skip {
+                    new.push(entry);
+                }
+            }
+        }
+        while self.order.len() > limit {
+            if let Some(id) = self.order.pop_front() {
+                self.seen.remove(&id);
+            }
+        }
+        // Feeds list their latest entries first.
+        new.reverse();
+        Ok(new)
+    }
+
+    /// Poll the feed every [`interval`](FeedWatcher::interval), yielding
+    /// each new entry.
+    ///
+    /// A failed poll yields its error, and the feed is polled again after
+    /// the interval.
+    pub fn watch(self) -> impl Stream<Item = crate::Result<Entry>> {
+        let state = (self, VecDeque::new(), false);
+
+        futures_util::stream::unfold(state, |(mut watcher, mut queue, mut wait)| async move {
+            loop {
+                if let Some(entry) = queue.pop_front() {
+                    return Some((Ok(entry), (watcher, queue, wait)));
+                }
+                if wait {
+                    tokio::time::sleep(watcher.interval).await;
+                }
+                wait = true;
+                match watcher.poll().await {
+                    Ok(entries) => queue.extend(entries),
+                    Err(err) => return Some((Err(err), (watcher, queue, wait))),
+                }
+            }
+        })
+    }
+}
+
+/// Decode a feed, with the `charset` of its `Content-Type`, or else the
+/// encoding of its BOM or XML declaration, or else as UTF-8.
+pub(crate) fn decode<'a>(body: &'a [u8], charset: Option<&str>) -> Cow<'a, str> {
+    let encoding = charset
+        .or_else(|| declared_encoding(body))
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+        .unwrap_or(UTF_8);
+    // A BOM takes precedence, as in a browser.
+    let (text, _, _) = encoding.decode(body);
+    text
+}
+
+/// The `encoding` of the XML declaration of `body`.
+fn declared_encoding(body: &[u8]) -> Option<&str> {
+    let body = body.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(body);
+    if !body.starts_with(b"<?xml") {
+        return None;
+    }
+    let end = body.iter().take(256).position(|&b| b == b'>')?;
+    let decl = std::str::from_utf8(&body[..end]).ok()?;
+    let rest = &decl[decl.find("encoding")? + "encoding".len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let rest = &rest[1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+/// Parse the entries of a feed, in document order, resolving their links
+/// against `base`.
+pub(crate) fn parse(body: &str, base: &Url) -> crate::Result<Vec<Entry>> {
+    let mut entries = match body.trim_start().as_bytes().first() {
+        Some(b'{') => parse_json(body)?,
+        _ => parse_xml(body)?,
+    };
+    for entry in &mut entries {
+        if let Some(ref mut link) = entry.link {
+            if let Ok(url) = base.join(link.trim()) {
+                *link = url.into();
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_json(body: &str) -> crate::Result<Vec<Entry>> {
+    let feed: serde_json::Value = serde_json::from_str(body).map_err(error::decode)?;
+    let items = match feed.get("items").and_then(|items| items.as_array()) {
+        Some(items) => items,
+        None => return Err(error::decode("JSON feed has no `items`")),
+    };
+    let field = |item: &serde_json::Value, name: &str| {
+        item.get(name).and_then(|v| v.as_str()).map(str::to_owned)
+    };
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let id = item
+                .get("id")
+                .and_then(|id| match id {
+                    serde_json::Value::String(id) => Some(id.clone()),
+                    // Invalid, but seen in the wild.
+                    serde_json::Value::Number(id) => Some(id.to_string()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            entry(
+                id,
+                field(item, "title"),
+                field(item, "url"),
+                field(item, "date_published").or_else(|| field(item, "date_modified")),
+            )
+        })
+        .collect())
+}
+
+/// The fields of an entry being parsed.
+#[derive(Default)]
+struct Partial {
+    id: Option<String>,
+    title: Option<String>,
+    link: Option<String>,
+    published: Option<String>,
+    updated: Option<String>,
+}
+
+/// The child of an entry whose text is being read.
+#[derive(Clone, Copy)]
+enum Field {
+    Id,
+    Title,
+    Link,
+    Published,
+    Updated,
+}
+
+fn parse_xml(body: &str) -> crate::Result<Vec<Entry>> {
+    // A BOM left by a decoder is not whitespace to the reader.
+    let mut reader = Reader::from_str(body.trim_start_matches('\u{feff}'));
+    reader.trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut buf = Vec::new();
+    let mut depth = 0usize;
+    // The depth of the entry being parsed, and its fields.
+    let mut current: Option<(usize, Partial)> = None;
+    let mut field: Option<(Field, String)> = None;
+
+    loop {
+        let event = reader.read_event_into(&mut buf).map_err(error::decode)?;
+        match event {
+            Event::Start(ref e) => {
+                depth += 1;
+                match current {
+                    None if is_entry(e) => current = Some((depth, Partial::default())),
+                    Some((at, ref mut partial)) if depth == at + 1 => {
+                        field = child(e, partial).map(|f| (f, String::new()));
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(ref e) => {
+                if let Some((at, ref mut partial)) = current {
+                    if depth == at {
+                        child(e, partial);
+                    }
+                }
+            }
+            Event::Text(ref e) => {
+                if let Some((_, ref mut text)) = field {
+                    text.push_str(&e.unescape().map_err(error::decode)?);
+                }
+            }
+            Event::CData(ref e) => {
+                if let Some((_, ref mut text)) = field {
+                    text.push_str(&String::from_utf8_lossy(e));
+                }
+            }
+            Event::End(_) => {
+                if let Some((at, ref mut partial)) = current {
+                    if depth == at + 1 {
+                        if let Some((kind, text)) = field.take() {
+                            set(partial, kind, text);
+                        }
+                    } else if depth == at {
+                        let partial = std::mem::take(partial);
+                        current = None;
+                        let published = partial.published.or(partial.updated);
+                        entries.extend(entry(
+                            partial.id.unwrap_or_default(),
+                            partial.title,
+                            partial.link,
+                            published,
+                        ));
+                    }
+                }
+                depth = depth.saturating_sub(1);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(entries)
+}
+
+fn is_entry(e: &BytesStart) -> bool {
+    matches!(e.local_name().as_ref(), b"item" | b"entry")
+}
+
+/// Handle a child element of an entry, returning the field its text is.
+fn child(e: &BytesStart, partial: &mut Partial) -> Option<Field> {
+    match e.local_name().as_ref() {
+        b"guid" | b"id" => Some(Field::Id),
+        b"title" => Some(Field::Title),
+        b"link" => {
+            // Atom links are in attributes, RSS ones in text.
+            let mut href = None;
+            let mut alternate = true;
+            for attr in e.attributes().flatten() {
+                let value = attr.unescape_value().ok()?;
+                match attr.key.local_name().as_ref() {
+                    b"href" => href = Some(value.into_owned()),
+                    b"rel" => alternate = value == "alternate",
+                    _ => {}
+                }
+            }
+            match href {
+                Some(href) => {
+                    if alternate && partial.link.is_none() {
+                        partial.link = Some(href);
+                    }
+                    None
+                }
+                None => Some(Field::Link),
+            }
+        }
+        b"pubDate" | b"published" | b"issued" => Some(Field::Published),
+        b"updated" | b"modified" | b"date" => Some(Field::Updated),
+        _ => None,
+    }
+}
+
+fn set(partial: &mut Partial, field: Field, text: String) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+    let slot = match field {
+        Field::Id => &mut partial.id,
+        Field::Title => &mut partial.title,
+        Field::Link => &mut partial.link,
+        Field::Published => &mut partial.published,
+        Field::Updated => &mut partial.updated,
+    };
+    if slot.is_none() {
+        *slot = Some(text.to_owned());
+    }
+}
+
+/// An entry, identified by its link or title when it has no id.
+fn entry(
+    id: String,
+    title: Option<String>,
+    link: Option<String>,
+    published: Option<String>,
+) -> Option<Entry> {
+    let id = Some(id)
+        .filter(|id| !id.is_empty())
+        .or_else(|| link.clone())
+        .or_else(|| title.clone())?;
+    Some(Entry {
+        id,
+        title,
+        link,
+        published,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> crate::Result<Vec<Entry>> {
+        super::parse(body, &Url::parse("https://example.com/blog/feed").unwrap())
+    }
+
+    #[test]
+    fn rss() {
+        let entries = parse(
+            r#"<?xml version="1.0"?>
+            <rss version="2.0"><channel>
+              <title>Blog</title>
+              <link>https://example.com/</link>
+              <item>
+                <title>Second &amp; last</title>
+                <link>https://example.com/2</link>
+                <guid isPermaLink="false">post-2</guid>
+                <pubDate>Tue, 02 Jan 2024 00:00:00 GMT</pubDate>
+              </item>
+              <item>
+                <title><![CDATA[First <post>]]></title>
+                <link>https://example.com/1</link>
+              </item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            entries,
+            [
+                Entry {
+                    id: "post-2".into(),
+                    title: Some("Second & last".into()),
+                    link: Some("https://example.com/2".into()),
+                    published: Some("Tue, 02 Jan 2024 00:00:00 GMT".into()),
+                },
+                Entry {
+                    id: "https://example.com/1".into(),
+                    title: Some("First <post>".into()),
+                    link: Some("https://example.com/1".into()),
+                    published: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn atom() {
+        let entries = parse(
+            r#"<feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Blog</title>
+              <entry>
+                <id>urn:uuid:1</id>
+                <title type="html">Hello</title>
+                <link rel="self" href="https://example.com/1.atom"/>
+                <link href="posts/1"/>
+                <updated>2024-01-02T00:00:00Z</updated>
+                <author><name>Someone</name><id>ignored</id></author>
+              </entry>
+            </feed>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            entries,
+            [Entry {
+                id: "urn:uuid:1".into(),
+                title: Some("Hello".into()),
+                link: Some("https://example.com/blog/posts/1".into()),
+                published: Some("2024-01-02T00:00:00Z".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn json() {
+        let entries = parse(
+            r#"{
+              "version": "https://jsonfeed.org/version/1.1",
+              "items": [
+                {"id": "1", "url": "https://example.com/1", "date_published": "2024-01-02T00:00:00Z"},
+                {"id": 2, "title": "Two"},
+                {"content_text": "no id"}
+              ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].link.as_deref(), Some("https://example.com/1"));
+        assert_eq!(entries[1].id, "2");
+
+        assert!(parse(r#"{"title": "not a feed"}"#).is_err());
+    }
+
+    #[test]
+    fn decodes() {
+        let body = b"<?xml version='1.0' encoding='ISO-8859-1'?><rss><item><title>Caf\xe9</title></item></rss>";
+        assert_eq!(declared_encoding(body), Some("ISO-8859-1"));
+        let entries = parse(&decode(body, None)).unwrap();
+        assert_eq!(entries[0].title.as_deref(), Some("Café"));
+
+        // The charset of the response wins over the declaration.
+        let body = "<?xml version=\"1.0\" encoding=\"windows-1252\"?><rss/>";
+        assert_eq!(decode(body.as_bytes(), Some("utf-8")), body);
+        // And a BOM over both.
+        assert_eq!(decode(b"\xEF\xBB\xBF<rss/>", Some("latin1")), "<rss/>");
+    }
+}
@@ -0,0 +1,109 @@
+//! Per-host memory of HTTP/2 downgrades.
+//!
+//! When a host refuses to speak h2 (it does not select it via ALPN, or the
+//! h2 connection fails with a protocol-level error), the host is remembered
+//! for a configurable TTL so later connections only offer HTTP/1.1 instead of
+//! failing the same way again.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use http::Uri;
+use url::Url;
+
+/// A cache of hosts that should not be offered h2, keyed by `host:port`.
+pub(crate) struct H2Fallback {
+    ttl: Duration,
+    hosts: Mutex<HashMap<String, Instant>>,
+}
+
+impl H2Fallback {
+    pub(crate) fn new(ttl: Duration) -> H2Fallback {
+        H2Fallback {
+            ttl,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `true` if h2 negotiation should be skipped for `key`.
+    pub(crate) fn is_downgraded(&self, key: &str) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        match hosts.get(key) {
+            Some(expires) if *expires > Instant::now() => true,
+            Some(_) => {
+                hosts.remove(key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Remember that `key` should only be offered HTTP/1.1 until the TTL elapses.
+    pub(crate) fn downgrade(&self, key: String) {
+        log::debug!("remembering HTTP/1.1 fallback for {}", key);
+        let expires = Instant::now() + self.ttl;
+        let mut hosts = self.hosts.lock().unwrap();
+        hosts.retain(|_, e| *e > Instant::now());
+        hosts.insert(key, expires);
+    }
+}
+
+/// The cache key for a connection destination.
+pub(crate) fn uri_key(uri: &Uri) -> Option<String> {
+    let host = uri.host()?;
+    let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+    Some(format!("{}:{}", host.to_ascii_lowercase(), port))
+}
+
+/// The cache key for a request URL.
+pub(crate) fn url_key(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    let port = url.port_or_known_default()?;
+    Some(format!("{}:{}", host.to_ascii_lowercase(), port))
+}
+
+/// Returns `true` if `err` indicates the peer can't (or won't) speak h2.
+pub(crate) fn is_h2_rejection(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = err.source();
+    while let Some(cause) = source {
+        if let Some(err) = cause.downcast_ref::<h2::Error>() {
+            return matches!(
+                err.reason(),
+                Some(h2::Reason::PROTOCOL_ERROR)
+                    | Some(h2::Reason::HTTP_1_1_REQUIRED)
+                    | Some(h2::Reason::INADEQUATE_SECURITY)
+            );
+        }
+        source = cause.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrade_expires() {
+        let cache = H2Fallback::new(Duration::from_millis(0));
+        cache.downgrade("example.com:443".into());
+        assert!(!cache.is_downgraded("example.com:443"));
+
+        let cache = H2Fallback::new(Duration::from_secs(60));
+        cache.downgrade("example.com:443".into());
+        assert!(cache.is_downgraded("example.com:443"));
+        assert!(!cache.is_downgraded("example.com:8443"));
+    }
+
+    #[test]
+    fn uri_and_url_keys_match() {
+        let uri: Uri = "https://Example.com/path".parse().unwrap();
+        let url = Url::parse("https://example.com/other").unwrap();
+        assert_eq!(uri_key(&uri), url_key(&url));
+        assert_eq!(uri_key(&uri).as_deref(), Some("example.com:443"));
+    }
+}
@@ -0,0 +1,37 @@
+//! How HTTP/1 requests are written to connections.
+//!
+//! Some WAFs look at how the head of a request is split into TCP segments
+//! and TLS records. [`ClientBuilder::http1_head_writes`] sets whether the
+//! head is written along with the body, which impersonation profiles set
+//! to the behavior of their browser.
+//!
+//! [`ClientBuilder::http1_head_writes`]: crate::ClientBuilder::http1_head_writes
+
+/// Whether the head of an HTTP/1 request is written along with its body.
+///
+/// Each write becomes its own TLS record, and its own TCP segment when
+/// `TCP_NODELAY` is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadWrites {
+    /// Copy the head and the body into one buffer, written at once.
+    Coalesced,
+    /// Write the head on its own, then the body.
+    Separate,
+    /// Write the head and the body at once when they are at most this many
+    /// bytes together, separately otherwise.
+    ///
+    /// Chrome merges them up to 1400 bytes.
+    MergeUpTo(usize),
+}
+
+impl HeadWrites {
+    /// The most bytes of a head and body written at once, when connections
+    /// split writes themselves.
+    pub(crate) fn merge_limit(self) -> Option<usize> {
+        match self {
+            HeadWrites::Coalesced => None,
+            HeadWrites::Separate => Some(0),
+            HeadWrites::MergeUpTo(limit) => Some(limit),
+        }
+    }
+}
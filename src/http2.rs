@@ -0,0 +1,983 @@
+//! HTTP/2 frames of connections.
+//!
+//! [`ClientBuilder::http2_frame_callback`] passes the connection-level
+//! frames a client sends and receives to a callback, to check the HTTP/2
+//! fingerprint of an impersonation or watch how a server manages its
+//! connections.
+//!
+//! The first frames a client writes are also shaped here, to send the
+//! `SETTINGS` in the order and the `PRIORITY` frames of the browser it
+//! impersonates, then the `HEADERS` of its requests with the browser's
+//! priority and stream ids, and the first frames the server sends are kept
+//! in a [`ServerPreface`], for
+//! [`Response::handshake_report`](crate::Response::handshake_report).
+//!
+//! [`ClientBuilder::http2_frame_callback`]: crate::ClientBuilder::http2_frame_callback
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::ReadBuf;
+
+/// The connection preface of HTTP/2 clients.
+pub(crate) const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_HEADER_LEN: usize = 9;
+
+pub(crate) const DATA: u8 = 0;
+pub(crate) const HEADERS: u8 = 1;
+pub(crate) const PRIORITY: u8 = 2;
+pub(crate) const SETTINGS: u8 = 4;
+pub(crate) const PING: u8 = 6;
+pub(crate) const GOAWAY: u8 = 7;
+pub(crate) const WINDOW_UPDATE: u8 = 8;
+
+const ACK: u8 = 0x1;
+const PADDED: u8 = 0x8;
+const PRIORITY_FLAG: u8 = 0x20;
+
+/// The largest frame every peer accepts, whatever its settings.
+const MIN_MAX_FRAME_SIZE: usize = 1 << 14;
+
+/// Whether a frame was sent or received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Sent by the client.
+    Sent,
+    /// Received from the server.
+    Received,
+}
+
+/// An HTTP/2 frame, as passed to
+/// [`ClientBuilder::http2_frame_callback`](crate::ClientBuilder::http2_frame_callback).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Frame {
+    /// A `SETTINGS` frame.
+    Settings {
+        /// Whether this acknowledges the settings of the peer.
+        ack: bool,
+        /// The identifiers and values of the settings, in order.
+        settings: Vec<(u16, u32)>,
+    },
+    /// A `PRIORITY` frame.
+    Priority {
+        /// The stream whose priority is set.
+        stream: u32,
+        /// Whether the stream becomes the sole dependency of its parent.
+        exclusive: bool,
+        /// The stream it depends on, or 0 for none.
+        dependency: u32,
+        /// The weight of the stream, from 1 to 256.
+        weight: u16,
+    },
+    /// A `WINDOW_UPDATE` frame.
+    WindowUpdate {
+        /// The stream whose window grows, or 0 for the connection.
+        stream: u32,
+        /// How much the window grows.
+        increment: u32,
+    },
+    /// A `PING` frame.
+    Ping {
+        /// Whether this answers a ping of the peer.
+        ack: bool,
+        /// The opaque data of the ping.
+        payload: [u8; 8],
+    },
+    /// A `GOAWAY` frame.
+    GoAway {
+        /// The last stream the sender may have processed.
+        last_stream: u32,
+        /// The error code, 0 for a graceful shutdown.
+        error_code: u32,
+        /// Additional debug data.
+        debug_data: Vec<u8>,
+    },
+}
+
+impl Frame {
+    /// The frame of `raw`, if it is one that callbacks are passed and is
+    /// well formed.
+    fn parse(raw: &RawFrame<'_>) -> Option<Frame> {
+        let payload = raw.payload?;
+        let u32_at = |i: usize| {
+            u32::from_be_bytes([payload[i], payload[i + 1], payload[i + 2], payload[i + 3]])
+        };
+        let frame = match raw.kind {
+            SETTINGS if payload.len() % 6 == 0 => Frame::Settings {
+                ack: raw.flags & ACK != 0,
+                settings: payload
+                    .chunks(6)
+                    .map(|s| {
+                        let id = u16::from_be_bytes([s[0], s[1]]);
+                        let value = u32::from_be_bytes([s[2], s[3], s[4], s[5]]);
+                        (id, value)
+                    })
+                    .collect(),
+            },
+            PRIORITY if payload.len() == 5 => Frame::Priority {
+                stream: raw.stream,
+                exclusive: payload[0] & 0x80 != 0,
+                dependency: u32_at(0) & 0x7fff_ffff,
+                weight: u16::from(payload[4]) + 1,
+            },
+            WINDOW_UPDATE if payload.len() == 4 => Frame::WindowUpdate {
+                stream: raw.stream,
+                increment: u32_at(0) & 0x7fff_ffff,
+            },
+            PING if payload.len() == 8 => {
+                let mut data = [0; 8];
+                data.copy_from_slice(payload);
+                Frame::Ping {
+                    ack: raw.flags & ACK != 0,
+                    payload: data,
+                }
+            }
+            GOAWAY if payload.len() >= 8 => Frame::GoAway {
+                last_stream: u32_at(0) & 0x7fff_ffff,
+                error_code: u32_at(4),
+                debug_data: payload[8..].to_vec(),
+            },
+            _ => return None,
+        };
+        Some(frame)
+    }
+}
+
+/// The parameters an HTTP/2 server sent at the start of a connection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServerPreface {
+    settings: Vec<(u16, u32)>,
+    window_update: Option<u32>,
+}
+
+impl ServerPreface {
+    /// Get the identifiers and values of the first `SETTINGS` frame of the
+    /// server, in order.
+    pub fn settings(&self) -> &[(u16, u32)] {
+        &self.settings
+    }
+
+    /// Get the value of the setting `id`, if the server sent it.
+    pub fn setting(&self, id: u16) -> Option<u32> {
+        self.settings
+            .iter()
+            .find(|&&(setting, _)| setting == id)
+            .map(|&(_, value)| value)
+    }
+
+    /// Get the increment of the connection `WINDOW_UPDATE` the server sent
+    /// after its settings, if any.
+    pub fn window_update(&self) -> Option<u32> {
+        self.window_update
+    }
+}
+
+/// Keeps the `ServerPreface` of a connection, once it is read.
+#[derive(Clone, Default)]
+pub(crate) struct ReceivedPreface(Arc<Mutex<Option<ServerPreface>>>);
+
+impl ReceivedPreface {
+    pub(crate) fn get(&self) -> Option<ServerPreface> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Reads the first frames of a server into a `ReceivedPreface`, until the
+/// first one that isn't its initial `SETTINGS` or connection
+/// `WINDOW_UPDATE`.
+#[derive(Default)]
+pub(crate) struct PrefaceRecorder {
+    frames: Frames,
+    received: ReceivedPreface,
+    preface: ServerPreface,
+    settings: bool,
+    done: bool,
+}
+
+impl PrefaceRecorder {
+    pub(crate) fn received(&self) -> ReceivedPreface {
+        self.received.clone()
+    }
+
+    pub(crate) fn on_read(&mut self, data: &[u8]) {
+        if self.done {
+            return;
+        }
+        let (preface, settings, done) = (&mut self.preface, &mut self.settings, &mut self.done);
+        self.frames.feed(data, &mut |event| {
+            let raw = match event {
+                Event::Frame(raw) if !*done => raw,
+                _ => return,
+            };
+            match Frame::parse(&raw) {
+                Some(Frame::Settings {
+                    ack: false,
+                    settings: entries,
+                }) if !*settings => {
+                    preface.settings = entries;
+                    *settings = true;
+                }
+                Some(Frame::WindowUpdate {
+                    stream: 0,
+                    increment,
+                }) if *settings && preface.window_update.is_none() => {
+                    preface.window_update = Some(increment);
+                }
+                // A server may acknowledge the settings of the client
+                // before sending its own.
+                Some(Frame::Settings { ack: true, .. }) if !*settings => {}
+                _ => *done = true,
+            }
+        });
+        if self.settings {
+            *self.received.0.lock().unwrap() = Some(self.preface.clone());
+        }
+        if self.done || self.preface.window_update.is_some() {
+            self.done = true;
+            self.frames = Frames::default();
+        }
+    }
+}
+
+pub(crate) type FrameCallback = Arc<dyn Fn(Direction, &Frame) + Send + Sync>;
+
+/// Passes the frames of one connection to a callback.
+pub(crate) struct FrameInspector {
+    tap: Tap,
+    callback: FrameCallback,
+}
+
+impl FrameInspector {
+    pub(crate) fn new(callback: FrameCallback) -> FrameInspector {
+        FrameInspector {
+            tap: Tap::default(),
+            callback,
+        }
+    }
+
+    pub(crate) fn on_write(&mut self, data: &[u8]) {
+        let callback = &self.callback;
+        self.tap
+            .on_write(data, |event| notify(callback, Direction::Sent, event));
+    }
+
+    pub(crate) fn on_read(&mut self, data: &[u8]) {
+        let callback = &self.callback;
+        self.tap
+            .on_read(data, |event| notify(callback, Direction::Received, event));
+    }
+}
+
+fn notify(callback: &FrameCallback, direction: Direction, event: Event<'_>) {
+    if let Event::Frame(raw) = event {
+        if let Some(frame) = Frame::parse(&raw) {
+            callback(direction, &frame);
+        }
+    }
+}
+
+/// What a connection writes or reads.
+pub(crate) enum Event<'a> {
+    /// Bytes of an HTTP/1 connection.
+    Http1(&'a [u8]),
+    /// The preface of an HTTP/2 connection.
+    Preface,
+    /// A frame of an HTTP/2 connection.
+    Frame(RawFrame<'a>),
+}
+
+/// The header and payload of a frame.
+pub(crate) struct RawFrame<'a> {
+    pub(crate) kind: u8,
+    pub(crate) flags: u8,
+    pub(crate) stream: u32,
+    pub(crate) len: usize,
+    /// The payload, except for `DATA` frames, which aren't kept.
+    pub(crate) payload: Option<&'a [u8]>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Protocol {
+    #[default]
+    Unknown,
+    Http1,
+    Http2,
+}
+
+/// Follows the traffic of a connection, splitting HTTP/2 into frames.
+///
+/// A connection is HTTP/2 if it starts with the preface, whether it was
+/// negotiated with ALPN or used with prior knowledge.
+#[derive(Default)]
+pub(crate) struct Tap {
+    protocol: Protocol,
+    /// The first bytes written, until they tell the protocol.
+    pending: Vec<u8>,
+    written: Frames,
+    read: Frames,
+}
+
+impl Tap {
+    pub(crate) fn on_write(&mut self, data: &[u8], mut f: impl FnMut(Event<'_>)) {
+        if data.is_empty() {
+            return;
+        }
+        if self.protocol != Protocol::Unknown {
+            return self.on_data(true, data, f);
+        }
+
+        self.pending.extend_from_slice(data);
+        if self.pending.len() < PREFACE.len() && PREFACE.starts_with(&self.pending) {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        if pending.starts_with(PREFACE) {
+            self.protocol = Protocol::Http2;
+            f(Event::Preface);
+            self.written.feed(&pending[PREFACE.len()..], &mut f);
+        } else {
+            self.protocol = Protocol::Http1;
+            f(Event::Http1(&pending));
+        }
+    }
+
+    pub(crate) fn on_read(&mut self, data: &[u8], f: impl FnMut(Event<'_>)) {
+        if !data.is_empty() {
+            self.on_data(false, data, f);
+        }
+    }
+
+    fn on_data(&mut self, written: bool, data: &[u8], mut f: impl FnMut(Event<'_>)) {
+        if self.protocol != Protocol::Http2 {
+            return f(Event::Http1(data));
+        }
+        let frames = if written {
+            &mut self.written
+        } else {
+            &mut self.read
+        };
+        frames.feed(data, &mut f);
+    }
+}
+
+/// How the first frames of HTTP/2 connections are shaped.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct PrefaceShape {
+    /// The ids of the settings, in the order the first `SETTINGS` frame
+    /// lists them. Those left out follow, in the order they came in.
+    pub(crate) settings_order: Vec<u16>,
+    /// The `PRIORITY` frames sent after the settings, as a stream, whether
+    /// its dependency is exclusive, the stream it depends on and its weight
+    /// from 1 to 256.
+    pub(crate) priority: Vec<(u32, bool, u32, u16)>,
+    /// The priority given to the `HEADERS` frames of requests, as whether
+    /// the dependency is exclusive, the stream depended on and the weight.
+    pub(crate) headers_priority: Option<(bool, u32, u16)>,
+}
+
+impl PrefaceShape {
+    /// Whether connections are left as they are.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.settings_order.is_empty()
+            && self.priority.is_empty()
+            && self.headers_priority.is_none()
+    }
+
+    /// How much the ids of request streams are raised, so that requests
+    /// start after the idle streams of the `PRIORITY` frames, as in
+    /// Firefox, rather than on them.
+    pub(crate) fn stream_offset(&self) -> u32 {
+        let last = self
+            .priority
+            .iter()
+            .map(|&(stream, ..)| stream)
+            .max()
+            .unwrap_or(0);
+        (last + 1) & !1
+    }
+}
+
+/// Shapes the first frames a connection writes, until the first one that
+/// isn't its initial `SETTINGS` or connection `WINDOW_UPDATE`, before which
+/// the `PRIORITY` frames are sent.
+///
+/// The frames are held back until complete, and written in their shape
+/// rather than as they came. If requests have a priority, or their streams
+/// are moved past idle ones, the frames that follow are rewritten too, for
+/// as long as the connection lasts.
+pub(crate) struct PrefaceShaper {
+    shape: Arc<PrefaceShape>,
+    offset: u32,
+    /// Whether the preface was written.
+    preface: bool,
+    /// Whether the initial `SETTINGS` frame was written.
+    settings: bool,
+    /// Whether the `PRIORITY` frames were written.
+    primed: bool,
+    /// What is left of the payload of the frame being rewritten.
+    remaining: usize,
+    pending: Vec<u8>,
+}
+
+impl PrefaceShaper {
+    pub(crate) fn new(shape: Arc<PrefaceShape>) -> PrefaceShaper {
+        PrefaceShaper {
+            offset: shape.stream_offset(),
+            shape,
+            preface: false,
+            settings: false,
+            primed: false,
+            remaining: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Shape `data` into `out`, returning whether the shaping is done, and
+    /// what comes next should be written as it is.
+    pub(crate) fn shape(&mut self, data: &[u8], out: &mut Vec<u8>) -> bool {
+        self.pending.extend_from_slice(data);
+        if self.primed {
+            self.rewrite(out);
+            return false;
+        }
+        if !self.preface {
+            if self.pending.len() < PREFACE.len() && PREFACE.starts_with(&self.pending) {
+                return false;
+            }
+            if !self.pending.starts_with(PREFACE) {
+                // Not HTTP/2 after all.
+                out.append(&mut self.pending);
+                return true;
+            }
+            out.extend_from_slice(PREFACE);
+            self.pending.drain(..PREFACE.len());
+            self.preface = true;
+        }
+
+        while self.pending.len() >= FRAME_HEADER_LEN {
+            let header = &self.pending[..FRAME_HEADER_LEN];
+            let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let (kind, flags) = (header[3], header[4]);
+            let stream = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+            let initial = match kind {
+                SETTINGS => !self.settings && flags & ACK == 0,
+                WINDOW_UPDATE => stream == 0,
+                _ => false,
+            };
+            if !initial {
+                break;
+            }
+            let end = FRAME_HEADER_LEN + len;
+            if self.pending.len() < end {
+                return false;
+            }
+            out.extend_from_slice(&self.pending[..FRAME_HEADER_LEN]);
+            if kind == SETTINGS {
+                self.settings = true;
+                out.extend(self.order_settings(&self.pending[FRAME_HEADER_LEN..end]));
+            } else {
+                out.extend_from_slice(&self.pending[FRAME_HEADER_LEN..end]);
+            }
+            self.pending.drain(..end);
+        }
+        if self.pending.len() < FRAME_HEADER_LEN {
+            return false;
+        }
+
+        for &(stream, exclusive, dependency, weight) in &self.shape.priority {
+            out.extend_from_slice(&[0, 0, 5, PRIORITY, 0]);
+            out.extend_from_slice(&stream.to_be_bytes());
+            out.extend_from_slice(&priority_fields(exclusive, dependency, weight));
+        }
+        self.primed = true;
+        if self.offset == 0 && self.shape.headers_priority.is_none() {
+            out.append(&mut self.pending);
+            return true;
+        }
+        self.rewrite(out);
+        false
+    }
+
+    /// Write the complete frame headers of `pending` to `out`, with the
+    /// ids of request streams raised by the offset, and the priority of
+    /// the shape added to `HEADERS` frames without one. Payloads are
+    /// passed as they are.
+    fn rewrite(&mut self, out: &mut Vec<u8>) {
+        let mut i = 0;
+        loop {
+            let passed = self.remaining.min(self.pending.len() - i);
+            out.extend_from_slice(&self.pending[i..i + passed]);
+            self.remaining -= passed;
+            i += passed;
+
+            let rest = &self.pending[i..];
+            if self.remaining > 0 || rest.len() < FRAME_HEADER_LEN {
+                break;
+            }
+            let mut header = [0; FRAME_HEADER_LEN];
+            header.copy_from_slice(&rest[..FRAME_HEADER_LEN]);
+            let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let (kind, flags) = (header[3], header[4]);
+            // A frame that would outgrow the smallest size peers accept
+            // keeps its priority.
+            let priority = self.shape.headers_priority.filter(|_| {
+                kind == HEADERS && flags & PRIORITY_FLAG == 0 && len + 5 <= MIN_MAX_FRAME_SIZE
+            });
+            // The priority comes after the pad length.
+            let pad = usize::from(priority.is_some() && flags & PADDED != 0);
+            if rest.len() < FRAME_HEADER_LEN + pad {
+                break;
+            }
+
+            let stream = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+            if stream % 2 == 1 {
+                let stream = stream.wrapping_add(self.offset) & 0x7fff_ffff;
+                header[5..].copy_from_slice(&stream.to_be_bytes());
+            }
+            match priority {
+                Some((exclusive, dependency, weight)) => {
+                    header[..3].copy_from_slice(&(len as u32 + 5).to_be_bytes()[1..]);
+                    header[4] |= PRIORITY_FLAG;
+                    out.extend_from_slice(&header);
+                    out.extend_from_slice(&rest[FRAME_HEADER_LEN..FRAME_HEADER_LEN + pad]);
+                    out.extend_from_slice(&priority_fields(exclusive, dependency, weight));
+                }
+                None => out.extend_from_slice(&header),
+            }
+            i += FRAME_HEADER_LEN + pad;
+            self.remaining = len - pad;
+        }
+        self.pending.drain(..i);
+    }
+
+    /// The entries of a `SETTINGS` payload, in the order of the shape.
+    fn order_settings(&self, payload: &[u8]) -> Vec<u8> {
+        if payload.len() % 6 != 0 {
+            return payload.to_vec();
+        }
+        let mut entries = payload.chunks(6).collect::<Vec<_>>();
+        let order = &self.shape.settings_order;
+        entries.sort_by_key(|entry| {
+            let id = u16::from_be_bytes([entry[0], entry[1]]);
+            order.iter().position(|&o| o == id).unwrap_or(order.len())
+        });
+        entries.concat()
+    }
+}
+
+/// The stream dependency and weight of a `PRIORITY` frame, or of a
+/// `HEADERS` frame with the `PRIORITY` flag.
+fn priority_fields(exclusive: bool, dependency: u32, weight: u16) -> [u8; 5] {
+    let dependency = dependency | if exclusive { 1 << 31 } else { 0 };
+    let [a, b, c, d] = dependency.to_be_bytes();
+    [a, b, c, d, (weight - 1) as u8]
+}
+
+/// Lowers the ids of the request streams in the frames a server sends,
+/// which a `PrefaceShaper` raised by `offset`, so that the client finds
+/// its streams.
+///
+/// Frame headers split over reads are held back until complete, as is the
+/// last stream of a `GOAWAY` frame.
+pub(crate) struct ShiftedReads {
+    offset: u32,
+    /// What is left of the payload of the frame being read.
+    remaining: usize,
+    /// The start of a frame header, not rewritten yet.
+    held: Vec<u8>,
+    /// Rewritten bytes that didn't fit in the last read.
+    ready: Vec<u8>,
+}
+
+impl ShiftedReads {
+    pub(crate) fn new(offset: u32) -> ShiftedReads {
+        ShiftedReads {
+            offset,
+            remaining: 0,
+            held: Vec::new(),
+            ready: Vec::new(),
+        }
+    }
+
+    /// Put the rewritten bytes left from the last read in `buf`, returning
+    /// whether there were any, in which case nothing should be read.
+    pub(crate) fn put_ready(&mut self, buf: &mut ReadBuf<'_>) -> bool {
+        if self.ready.is_empty() {
+            return false;
+        }
+        let n = self.ready.len().min(buf.remaining());
+        buf.put_slice(&self.ready[..n]);
+        self.ready.drain(..n);
+        true
+    }
+
+    /// Rewrite the bytes read into `buf` from `start`, holding back those
+    /// that can't be rewritten yet.
+    pub(crate) fn on_read(&mut self, buf: &mut ReadBuf<'_>, start: usize) {
+        if self.held.is_empty() {
+            let kept = self.rewrite(&mut buf.filled_mut()[start..]);
+            buf.set_filled(start + kept);
+            return;
+        }
+        let mut data = std::mem::take(&mut self.held);
+        data.extend_from_slice(&buf.filled()[start..]);
+        let kept = self.rewrite(&mut data);
+        buf.set_filled(start);
+        let fits = kept.min(buf.remaining());
+        buf.put_slice(&data[..fits]);
+        self.ready.extend_from_slice(&data[fits..kept]);
+    }
+
+    /// Rewrite `data` in place, returning how many of its bytes are done.
+    /// The others are held back.
+    fn rewrite(&mut self, data: &mut [u8]) -> usize {
+        let mut i = 0;
+        loop {
+            let skipped = self.remaining.min(data.len() - i);
+            self.remaining -= skipped;
+            i += skipped;
+
+            let rest = &mut data[i..];
+            if rest.is_empty() {
+                return i;
+            }
+            let goaway = rest.len() > 3 && rest[3] == GOAWAY;
+            if rest.len() < FRAME_HEADER_LEN + if goaway { 4 } else { 0 } {
+                self.held.extend_from_slice(rest);
+                return i;
+            }
+            self.unshift(&mut rest[5..FRAME_HEADER_LEN]);
+            if goaway {
+                self.unshift(&mut rest[FRAME_HEADER_LEN..FRAME_HEADER_LEN + 4]);
+            }
+            self.remaining = u32::from_be_bytes([0, rest[0], rest[1], rest[2]]) as usize;
+            i += FRAME_HEADER_LEN;
+        }
+    }
+
+    /// Lower the stream id in `field` if it is a request stream past the
+    /// idle ones.
+    fn unshift(&self, field: &mut [u8]) {
+        let stream = u32::from_be_bytes([field[0], field[1], field[2], field[3]]) & 0x7fff_ffff;
+        if stream % 2 == 1 && stream > self.offset {
+            field.copy_from_slice(&(stream - self.offset).to_be_bytes());
+        }
+    }
+}
+
+/// Splits a direction of an HTTP/2 connection into frames.
+#[derive(Default)]
+struct Frames {
+    buf: Vec<u8>,
+    /// What remains of a `DATA` frame, which isn't kept.
+    skip: usize,
+}
+
+impl Frames {
+    /// Pass the frames completed by `data` to `f`.
+    fn feed(&mut self, data: &[u8], f: &mut impl FnMut(Event<'_>)) {
+        let skipped = self.skip.min(data.len());
+        self.skip -= skipped;
+        self.buf.extend_from_slice(&data[skipped..]);
+
+        while self.buf.len() >= FRAME_HEADER_LEN {
+            let len = u32::from_be_bytes([0, self.buf[0], self.buf[1], self.buf[2]]) as usize;
+            let mut frame = RawFrame {
+                kind: self.buf[3],
+                flags: self.buf[4],
+                stream: u32::from_be_bytes([self.buf[5], self.buf[6], self.buf[7], self.buf[8]])
+                    & 0x7fff_ffff,
+                len,
+                payload: None,
+            };
+            let end = FRAME_HEADER_LEN + len;
+
+            if frame.kind == DATA {
+                f(Event::Frame(frame));
+                let available = end.min(self.buf.len());
+                self.skip = end - available;
+                self.buf.drain(..available);
+            } else if self.buf.len() >= end {
+                frame.payload = Some(&self.buf[FRAME_HEADER_LEN..end]);
+                f(Event::Frame(frame));
+                self.buf.drain(..end);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    pub(crate) fn frame(kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.push(kind);
+        frame.push(flags);
+        frame.extend_from_slice(&stream.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn splits_frames() {
+        let mut frames = Frames::default();
+        let mut seen = Vec::new();
+        let mut feed = |frames: &mut Frames, data: &[u8]| {
+            frames.feed(data, &mut |event| {
+                if let Event::Frame(raw) = event {
+                    seen.push((raw.kind, raw.len, raw.payload.map(<[u8]>::to_vec)));
+                }
+            })
+        };
+
+        let data = frame(DATA, 1, 1, &[b'x'; 20]);
+        let ping = frame(PING, 0, 0, &[1; 8]);
+        feed(&mut frames, &ping[..5]);
+        feed(&mut frames, &ping[5..]);
+        // A DATA frame is split over reads, and its payload skipped.
+        feed(&mut frames, &data[..15]);
+        let mut rest = data[15..].to_vec();
+        rest.extend_from_slice(&ping);
+        feed(&mut frames, &rest);
+
+        assert_eq!(
+            seen,
+            [
+                (PING, 8, Some(vec![1; 8])),
+                (DATA, 20, None),
+                (PING, 8, Some(vec![1; 8])),
+            ]
+        );
+        assert!(frames.buf.is_empty());
+    }
+
+    #[test]
+    fn inspects_frames() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorded = seen.clone();
+        let mut inspector = FrameInspector::new(Arc::new(move |direction, frame: &Frame| {
+            recorded.lock().unwrap().push((direction, frame.clone()));
+        }));
+
+        inspector.on_write(&PREFACE[..10]);
+        let mut rest = PREFACE[10..].to_vec();
+        rest.extend_from_slice(&frame(SETTINGS, 0, 0, &[0, 4, 0, 0x60, 0, 0]));
+        rest.extend_from_slice(&frame(WINDOW_UPDATE, 0, 0, &15663105u32.to_be_bytes()));
+        rest.extend_from_slice(&frame(1, 0x5, 1, &[0x82]));
+        inspector.on_write(&rest);
+
+        let mut goaway = vec![0, 0, 0, 1, 0, 0, 0, 0];
+        goaway.extend_from_slice(b"bye");
+        inspector.on_read(&frame(PING, 0, 0, &[7; 8]));
+        inspector.on_read(&frame(GOAWAY, 0, 0, &goaway));
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (
+                    Direction::Sent,
+                    Frame::Settings {
+                        ack: false,
+                        settings: vec![(4, 6291456)],
+                    }
+                ),
+                (
+                    Direction::Sent,
+                    Frame::WindowUpdate {
+                        stream: 0,
+                        increment: 15663105,
+                    }
+                ),
+                (
+                    Direction::Received,
+                    Frame::Ping {
+                        ack: false,
+                        payload: [7; 8],
+                    }
+                ),
+                (
+                    Direction::Received,
+                    Frame::GoAway {
+                        last_stream: 1,
+                        error_code: 0,
+                        debug_data: b"bye".to_vec(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn shapes_preface() {
+        let mut shaper = PrefaceShaper::new(Arc::new(PrefaceShape {
+            settings_order: vec![2, 4, 3],
+            priority: vec![(3, false, 0, 201), (5, true, 3, 256)],
+            headers_priority: None,
+        }));
+        let settings = [
+            [0, 3, 0, 0, 0, 100],
+            [0, 4, 0, 0x40, 0, 0],
+            [0, 2, 0, 0, 0, 0],
+            [0, 6, 0, 4, 0, 0],
+        ];
+        let window_update = frame(WINDOW_UPDATE, 0, 0, &10420225u32.to_be_bytes());
+        let headers = frame(1, 0x5, 1, &[0x82]);
+
+        let mut out = Vec::new();
+        assert!(!shaper.shape(&PREFACE[..10], &mut out));
+        assert!(out.is_empty());
+        let mut rest = PREFACE[10..].to_vec();
+        rest.extend_from_slice(&frame(SETTINGS, 0, 0, &settings.concat()));
+        rest.extend_from_slice(&window_update[..5]);
+        assert!(!shaper.shape(&rest, &mut out));
+        let mut rest = window_update[5..].to_vec();
+        rest.extend_from_slice(&headers);
+        assert!(!shaper.shape(&rest, &mut out));
+
+        let mut expected = PREFACE.to_vec();
+        expected.extend_from_slice(&frame(
+            SETTINGS,
+            0,
+            0,
+            &[settings[2], settings[1], settings[0], settings[3]].concat(),
+        ));
+        expected.extend_from_slice(&window_update);
+        expected.extend_from_slice(&frame(PRIORITY, 0, 3, &[0, 0, 0, 0, 200]));
+        expected.extend_from_slice(&frame(PRIORITY, 0, 5, &[0x80, 0, 0, 3, 255]));
+        // The first request comes after the idle streams.
+        expected.extend_from_slice(&frame(1, 0x5, 7, &[0x82]));
+        assert_eq!(out, expected);
+
+        // And so do the frames of later requests.
+        let mut out = Vec::new();
+        let data = frame(DATA, 0x1, 3, b"body");
+        assert!(!shaper.shape(&data[..12], &mut out));
+        assert!(!shaper.shape(&data[12..], &mut out));
+        assert!(!shaper.shape(&frame(WINDOW_UPDATE, 0, 0, &[0, 0, 1, 0]), &mut out));
+        let mut expected = frame(DATA, 0x1, 9, b"body");
+        expected.extend(frame(WINDOW_UPDATE, 0, 0, &[0, 0, 1, 0]));
+        assert_eq!(out, expected);
+
+        let mut out = Vec::new();
+        let mut shaper = PrefaceShaper::new(Arc::new(PrefaceShape::default()));
+        assert!(shaper.shape(b"GET / HTTP/1.1\r\n\r\n", &mut out));
+        assert_eq!(out, b"GET / HTTP/1.1\r\n\r\n");
+    }
+
+    #[test]
+    fn shapes_headers_priority() {
+        let mut shaper = PrefaceShaper::new(Arc::new(PrefaceShape {
+            settings_order: Vec::new(),
+            priority: Vec::new(),
+            headers_priority: Some((true, 0, 256)),
+        }));
+        let mut data = PREFACE.to_vec();
+        data.extend(frame(SETTINGS, 0, 0, &[]));
+        data.extend(frame(HEADERS, 0x5, 1, &[0x82, 0x84]));
+        let mut out = Vec::new();
+        assert!(!shaper.shape(&data, &mut out));
+
+        let mut expected = PREFACE.to_vec();
+        expected.extend(frame(SETTINGS, 0, 0, &[]));
+        expected.extend(frame(HEADERS, 0x25, 1, &[0x80, 0, 0, 0, 255, 0x82, 0x84]));
+        assert_eq!(out, expected);
+
+        // A padded frame keeps its pad length first, and a frame with a
+        // priority keeps it.
+        let mut out = Vec::new();
+        let padded = frame(HEADERS, 0xd, 3, &[1, 0x82, 0]);
+        assert!(!shaper.shape(&padded[..9], &mut out));
+        assert!(!shaper.shape(&padded[9..], &mut out));
+        let prioritized = frame(HEADERS, 0x25, 5, &[0, 0, 0, 3, 15, 0x82]);
+        assert!(!shaper.shape(&prioritized, &mut out));
+        let mut expected = frame(HEADERS, 0x2d, 3, &[1, 0x80, 0, 0, 0, 255, 0x82, 0]);
+        expected.extend(prioritized);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn unshifts_read_streams() {
+        let mut reads = ShiftedReads::new(6);
+        let mut data = frame(HEADERS, 0x4, 7, &[0x88]);
+        data.extend(frame(SETTINGS, ACK, 0, &[]));
+        data.extend(frame(DATA, 0x1, 9, b"body"));
+        let mut goaway = 9u32.to_be_bytes().to_vec();
+        goaway.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend(frame(GOAWAY, 0, 0, &goaway));
+
+        // Split in the middle of the GOAWAY frame, before its last stream,
+        // and read the rest into a buffer with too little room.
+        let split = data.len() - 12;
+        let mut storage = [0; 64];
+        let mut buf = ReadBuf::new(&mut storage);
+        buf.put_slice(&data[..split]);
+        reads.on_read(&mut buf, 0);
+        let mut out = buf.filled().to_vec();
+        assert_eq!(out.len(), split - 5);
+
+        let mut storage = [0; 10];
+        let mut buf = ReadBuf::new(&mut storage);
+        buf.put_slice(&data[split..split + 10]);
+        reads.on_read(&mut buf, 0);
+        assert_eq!(buf.filled().len(), 10);
+        out.extend_from_slice(buf.filled());
+        let mut buf = ReadBuf::new(&mut storage);
+        assert!(reads.put_ready(&mut buf));
+        out.extend_from_slice(buf.filled());
+        let mut buf = ReadBuf::new(&mut storage);
+        assert!(!reads.put_ready(&mut buf));
+        buf.put_slice(&data[split + 10..]);
+        reads.on_read(&mut buf, 0);
+        out.extend_from_slice(buf.filled());
+
+        let mut expected = frame(HEADERS, 0x4, 1, &[0x88]);
+        expected.extend(frame(SETTINGS, ACK, 0, &[]));
+        expected.extend(frame(DATA, 0x1, 3, b"body"));
+        let mut goaway = 3u32.to_be_bytes().to_vec();
+        goaway.extend_from_slice(&[0, 0, 0, 0]);
+        expected.extend(frame(GOAWAY, 0, 0, &goaway));
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn ignores_http1() {
+        let mut tap = Tap::default();
+        let mut http1 = Vec::new();
+        tap.on_write(b"GET / HTTP/1.1\r\n\r\n", |event| {
+            if let Event::Http1(data) = event {
+                http1.extend_from_slice(data);
+            }
+        });
+        assert_eq!(http1, b"GET / HTTP/1.1\r\n\r\n");
+    }
+
+    #[test]
+    fn records_server_preface() {
+        let mut recorder = PrefaceRecorder::default();
+        let received = recorder.received();
+        let mut settings = Vec::new();
+        for (id, value) in [(3u16, 100u32), (4, 65_535)] {
+            settings.extend_from_slice(&id.to_be_bytes());
+            settings.extend_from_slice(&value.to_be_bytes());
+        }
+        let mut data = frame(SETTINGS, ACK, 0, &[]);
+        data.extend(frame(SETTINGS, 0, 0, &settings));
+        data.extend(frame(WINDOW_UPDATE, 0, 0, &1_000_000u32.to_be_bytes()));
+
+        recorder.on_read(&data[..20]);
+        assert_eq!(received.get(), None);
+        recorder.on_read(&data[20..]);
+        // Later frames don't change it.
+        recorder.on_read(&frame(SETTINGS, 0, 0, &[]));
+
+        let preface = received.get().unwrap();
+        assert_eq!(preface.settings(), [(3, 100), (4, 65_535)]);
+        assert_eq!(preface.setting(4), Some(65_535));
+        assert_eq!(preface.setting(1), None);
+        assert_eq!(preface.window_update(), Some(1_000_000));
+    }
+}
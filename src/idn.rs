@@ -0,0 +1,192 @@
+//! Detection of IDN homograph hosts.
+//!
+//! A host is considered confusable when one of its internationalized labels
+//! mixes scripts outside of the combinations commonly used together (Latin
+//! with Han, Kana or Hangul), or is written entirely in Cyrillic or Greek
+//! letters that look like Latin ones, as in `аррӏе.com`. Labels written in
+//! the script of the top-level domain, as in `пример.рф`, are accepted.
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use log::warn;
+use url::{Host, Url};
+
+/// How a `Client` treats hosts that may be IDN homograph spoofs.
+///
+/// See [`ClientBuilder::homograph_policy`](crate::ClientBuilder::homograph_policy).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HomographPolicy {
+    /// Connect to any host, the default.
+    #[default]
+    Allow,
+    /// Connect, but log a warning for confusable hosts.
+    Warn,
+    /// Fail requests to confusable hosts before connecting.
+    Reject,
+}
+
+impl HomographPolicy {
+    /// Check the host of `url` against this policy.
+    pub(crate) fn check(self, url: &Url) -> Result<(), Confusable> {
+        if self == HomographPolicy::Allow {
+            return Ok(());
+        }
+        let host = match url.host() {
+            Some(Host::Domain(host)) if host.contains("xn--") => host,
+            _ => return Ok(()),
+        };
+        if !is_confusable(host) {
+            return Ok(());
+        }
+        match self {
+            HomographPolicy::Reject => Err(Confusable(host.to_owned())),
+            _ => {
+                warn!("host {:?} may be an IDN homograph", host);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The error of a request to a host rejected by `HomographPolicy::Reject`.
+#[derive(Debug)]
+pub(crate) struct Confusable(String);
+
+impl fmt::Display for Confusable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "host {:?} may be an IDN homograph", self.0)
+    }
+}
+
+impl StdError for Confusable {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Thai,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Bopomofo,
+    Han,
+    Other,
+}
+
+/// Scripts that may be mixed within a label.
+const ALLOWED_MIXES: [&[Script]; 3] = [
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+/// Cyrillic and Greek letters that look like Latin ones.
+const LATIN_LOOKALIKES: &str = "асԁеһіјӏорԛѕԝхуъьыҽпгѵѡαικνορτυχ";
+
+/// The script of `c`, or `None` for characters shared by all scripts.
+fn script(c: char) -> Option<Script> {
+    let script = match c as u32 {
+        0x30..=0x39 | 0x2D | 0x5F => return None,
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+        0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x400..=0x52F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => Script::Cyrillic,
+        0x530..=0x58F => Script::Armenian,
+        0x590..=0x5FF => Script::Hebrew,
+        0x600..=0x6FF | 0x750..=0x77F => Script::Arabic,
+        0xE00..=0xE7F => Script::Thai,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3040..=0x309F => Script::Hiragana,
+        0x30A0..=0x30FF => Script::Katakana,
+        0x3100..=0x312F => Script::Bopomofo,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+        0x3000..=0x303F => return None,
+        _ => Script::Other,
+    };
+    Some(script)
+}
+
+fn scripts(label: &[char]) -> Vec<Script> {
+    let mut scripts = Vec::new();
+    for script in label.iter().copied().filter_map(script) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    scripts
+}
+
+/// Whether the ASCII (punycode) `host` may be an IDN homograph.
+pub(crate) fn is_confusable(host: &str) -> bool {
+    let (unicode, decoded) = idna::domain_to_unicode(host);
+    if decoded.is_err() {
+        // Invalid punycode.
+        return true;
+    }
+    let labels = unicode
+        .trim_end_matches('.')
+        .split('.')
+        .map(|label| label.chars().collect())
+        .collect::<Vec<Vec<char>>>();
+
+    let tld = labels
+        .last()
+        .map(|label| scripts(label))
+        .unwrap_or_default();
+    labels.iter().any(|label| {
+        let scripts = scripts(label);
+        match scripts[..] {
+            [] => false,
+            [Script::Cyrillic] | [Script::Greek] if tld != scripts => label
+                .iter()
+                .all(|&c| script(c).is_none() || LATIN_LOOKALIKES.contains(c)),
+            [_] => false,
+            _ => !ALLOWED_MIXES
+                .iter()
+                .any(|allowed| scripts.iter().all(|script| allowed.contains(script))),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confusable_hosts() {
+        // аррӏе.com, all Cyrillic
+        assert!(is_confusable("xn--80ak6aa92e.com"));
+        // aрple.com, Latin with a Cyrillic "р"
+        assert!(is_confusable("xn--aple-g6d.com"));
+
+        assert!(!is_confusable("example.com"));
+        // münchen.de
+        assert!(!is_confusable("xn--mnchen-3ya.de"));
+        // 日本語テスト.jp
+        assert!(!is_confusable("xn--zckzah9945czlbtz6h.jp"));
+        // пример.рф, Cyrillic under a Cyrillic TLD
+        assert!(!is_confusable("xn--e1afmkfd.xn--p1ai"));
+        // правительство.com, Cyrillic with letters unlike Latin ones
+        assert!(!is_confusable("xn--80aealotwbjpid2k.com"));
+
+        // Invalid punycode.
+        assert!(is_confusable("xn--!!.com"));
+    }
+
+    #[test]
+    fn policy() {
+        let url = Url::parse("https://xn--80ak6aa92e.com/").unwrap();
+        assert!(HomographPolicy::Reject.check(&url).is_err());
+        assert!(HomographPolicy::Warn.check(&url).is_ok());
+        assert!(HomographPolicy::Allow.check(&url).is_ok());
+    }
+}
@@ -0,0 +1,129 @@
+//! Features of the linked BoringSSL that profiles rely on.
+
+use boring::error::ErrorStack;
+use boring::ssl::{
+    CertCompressionAlgorithm, Ssl, SslConnector, SslContext, SslContextBuilder, SslCurve, SslMethod,
+};
+use foreign_types::ForeignType;
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+/// What the linked BoringSSL supports of the features profiles use.
+///
+/// Older builds of BoringSSL may lack some of them. Profiles then leave the
+/// feature out, which makes their ClientHello differ from the browser's,
+/// and a warning is logged once instead of failing every handshake.
+///
+/// Returned by [`capabilities`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The `application_settings` (ALPS) extension sent by Chromium.
+    pub alps: bool,
+    /// The `compress_certificate` extension.
+    pub cert_compression: bool,
+    /// The post-quantum `X25519Kyber768Draft00` group of Chrome 124 to
+    /// 130.
+    pub post_quantum: bool,
+    /// The post-quantum `X25519MLKEM768` group, which replaced
+    /// `X25519Kyber768Draft00` in Chrome 131. Without it, the profiles of
+    /// Chrome 131 offer `X25519Kyber768Draft00` instead.
+    pub ml_kem: bool,
+}
+
+/// Probe the linked BoringSSL, once.
+pub fn capabilities() -> Capabilities {
+    static CAPABILITIES: OnceLock<Capabilities> = OnceLock::new();
+    *CAPABILITIES.get_or_init(|| {
+        let capabilities = probe();
+        let missing = [
+            (capabilities.alps, "ALPS"),
+            (capabilities.cert_compression, "certificate compression"),
+            (capabilities.post_quantum, "post-quantum key exchange"),
+            (capabilities.ml_kem, "ML-KEM key exchange"),
+        ];
+        for (_, feature) in missing.iter().filter(|(supported, _)| !supported) {
+            log::warn!(
+                "the linked BoringSSL lacks {}, impersonation profiles will leave it out",
+                feature
+            );
+        }
+        capabilities
+    })
+}
+
+fn probe() -> Capabilities {
+    let builder = || SslConnector::builder(SslMethod::tls_client()).ok();
+
+    let post_quantum = builder().map_or(false, |mut builder| {
+        builder
+            .set_curves(&[SslCurve::X25519_KYBER768_DRAFT00])
+            .is_ok()
+    });
+    let ml_kem = builder().map_or(false, |mut builder| {
+        set_curves_list(&mut builder, "X25519MLKEM768").is_ok()
+    });
+    let cert_compression = builder().map_or(false, |mut builder| {
+        builder
+            .add_cert_compression_alg(CertCompressionAlgorithm::Brotli)
+            .is_ok()
+    });
+    let alps = SslContext::builder(SslMethod::tls_client())
+        .and_then(|builder| Ssl::new(&builder.build()))
+        .map_or(false, |ssl| {
+            const ALPN_H2: &[u8] = b"h2";
+            let added = unsafe {
+                boring_sys::SSL_add_application_settings(
+                    ssl.as_ptr(),
+                    ALPN_H2.as_ptr(),
+                    ALPN_H2.len(),
+                    std::ptr::null(),
+                    0,
+                )
+            };
+            added == 1
+        });
+
+    Capabilities {
+        alps,
+        cert_compression,
+        post_quantum,
+        ml_kem,
+    }
+}
+
+/// Set the groups offered by `builder`, by their names in BoringSSL.
+///
+/// Used for the groups `SslCurve` has no constant for.
+pub(crate) fn set_curves_list(
+    builder: &mut SslContextBuilder,
+    curves: &str,
+) -> Result<(), ErrorStack> {
+    let curves = CString::new(curves).map_err(|_| ErrorStack::get())?;
+    let set = unsafe { boring_sys::SSL_CTX_set1_curves_list(builder.as_ptr(), curves.as_ptr()) };
+    if set == 1 {
+        Ok(())
+    } else {
+        Err(ErrorStack::get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_boringssl_supports_everything() {
+        assert_eq!(
+            capabilities(),
+            Capabilities {
+                alps: true,
+                cert_compression: true,
+                post_quantum: true,
+                // Depends on the BoringSSL of the pinned rboring; the hello
+                // offers a post-quantum share either way.
+                ml_kem: capabilities().ml_kem,
+            }
+        );
+    }
+}
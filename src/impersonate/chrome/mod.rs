@@ -1,22 +1,6 @@
-pub mod v100;
-pub mod v101;
-pub mod v104;
-pub mod v105;
-pub mod v106;
-pub mod v107;
-pub mod v108;
-pub mod v109;
-pub mod v114;
-pub mod v116;
-pub mod v117;
-pub mod v118;
-pub mod v119;
-pub mod v120;
-pub mod v123;
-pub mod v124;
-pub mod v126;
-pub mod v127;
-pub mod cronet;
+use crate::impersonate::extension::{ChromeExtension, Extension, SslExtension};
+use crate::impersonate::{Impersonate, ProfileSpec};
+use boring::{error::ErrorStack, ssl::SslConnectorBuilder};
 
 const CIPHER_LIST: [&str; 15] = [
     "TLS_AES_128_GCM_SHA256",
@@ -35,3 +19,316 @@ const CIPHER_LIST: [&str; 15] = [
     "TLS_RSA_WITH_AES_128_CBC_SHA",
     "TLS_RSA_WITH_AES_256_CBC_SHA",
 ];
+
+fn tls() -> Result<SslConnectorBuilder, ErrorStack> {
+    ChromeExtension::builder()?.configure_cipher_list(&CIPHER_LIST)
+}
+
+fn tls_pq_curves() -> Result<SslConnectorBuilder, ErrorStack> {
+    ChromeExtension::builder()?
+        .configure_cipher_list(&CIPHER_LIST)?
+        .configure_chrome_pq_curves(true)
+}
+
+fn tls_mlkem_curves() -> Result<SslConnectorBuilder, ErrorStack> {
+    ChromeExtension::builder()?
+        .configure_cipher_list(&CIPHER_LIST)?
+        .configure_chrome_mlkem_curves(true)
+}
+
+pub(crate) static V100: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls),
+    http2: Some("1:65536;3:1000;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("sec-ch-ua", Some(r#"" Not A;Brand";v="99", "Chromium";v="100", "Google Chrome";v="100""#)),
+        ("sec-ch-ua-mobile", Some("?0")),
+        ("sec-ch-ua-platform", Some(r#""Windows""#)),
+        ("dnt", Some("1")),
+        ("upgrade-insecure-requests", Some("1")),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/100.0.4896.75 Safari/537.36")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9")),
+        ("sec-fetch-site", Some("none")),
+        ("sec-fetch-mode", Some("navigate")),
+        ("sec-fetch-user", Some("?1")),
+        ("sec-fetch-dest", Some("document")),
+        ("accept-encoding", Some("gzip, deflate, br")),
+        ("accept-language", Some("en-US,en;q=0.9")),
+    ],
+};
+
+pub(crate) static V101: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome100),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#"" Not A;Brand";v="99", "Chromium";v="101", "Google Chrome";v="101""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/101.0.4951.67 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V104: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome101),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="104", " Not A;Brand";v="99", "Google Chrome";v="104""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/104.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V105: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome104),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Google Chrome";v="105", "Not)A;Brand";v="8", "Chromium";v="105""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/105.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V106: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome105),
+    tls: None,
+    http2: Some("1:65536;2:0;3:1000;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="106", "Google Chrome";v="106", "Not;A=Brand";v="99""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/106.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V107: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome106),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="107", "Google Chrome";v="107", "Not;A=Brand";v="99""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/107.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V108: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome107),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Not?A_Brand";v="8", "Chromium";v="108", "Google Chrome";v="108""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/108.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V109: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome108),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="110", "Not A(Brand";v="24", "Google Chrome";v="110""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")),
+    ],
+};
+
+pub(crate) static V114: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome108),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="114", "Not A(Brand";v="30", "Google Chrome";v="114""#)),
+        ("sec-ch-ua-platform", Some(r#""Mac OS""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/114.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V116: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome109),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("dnt", None),
+        ("sec-ch-ua", Some(r#""Chromium";v="116", "Not)A;Brand";v="24", "Google Chrome";v="116""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/116.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V117: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome116),
+    tls: None,
+    http2: Some("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("sec-ch-ua", Some(r#""Google Chrome";v="117", "Not;A=Brand";v="8", "Chromium";v="117""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/117.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V118: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome109),
+    tls: None,
+    http2: Some("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="118", "Google Chrome";v="118", "Not=A?Brand";v="99""#)),
+        ("sec-ch-ua-platform", Some(r#""macOS""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/118.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V119: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls),
+    http2: Some("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("cache-control", Some("max-age=0")),
+        ("sec-ch-ua", Some(r#""Microsoft Edge";v="119", "Chromium";v="119", "Not?A_Brand";v="24""#)),
+        ("sec-ch-ua-mobile", Some("?0")),
+        ("sec-ch-ua-platform", Some(r#""macOS""#)),
+        ("dnt", Some("1")),
+        ("upgrade-insecure-requests", Some("1")),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36 Edg/119.0.0.0")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")),
+        ("sec-fetch-site", Some("none")),
+        ("sec-fetch-mode", Some("navigate")),
+        ("sec-fetch-user", Some("?1")),
+        ("sec-fetch-dest", Some("document")),
+        ("accept-encoding", Some("gzip, deflate, br")),
+        ("accept-language", Some("en;q=0.8,en-GB;q=0.7,en-US;q=0.6")),
+    ],
+};
+
+pub(crate) static V120: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome119),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Not_A Brand";v="8", "Chromium";v="120", "Google Chrome";v="120""#)),
+        ("sec-ch-ua-platform", Some(r#""Windows""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")),
+    ],
+};
+
+pub(crate) static V123: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome118),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("dnt", None),
+        ("sec-ch-ua", Some(r#""Google Chrome";v="123", "Not:A-Brand";v="8", "Chromium";v="123""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36")),
+        ("sec-fetch-site", Some("?1")),
+        ("sec-fetch-mode", Some("same-site")),
+        ("sec-fetch-user", Some("document")),
+        ("sec-fetch-dest", Some("navigate")),
+        ("accept-encoding", Some("gzip, deflate, br, zstd")),
+        ("accept-language", Some("en-US;q=1.0")),
+    ],
+};
+
+pub(crate) static V124: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome123),
+    tls: Some(tls_pq_curves),
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="124", "Google Chrome";v="124", "Not-A.Brand";v="99""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V126: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome118),
+    tls: Some(tls_pq_curves),
+    http2: None,
+    headers: &[
+        ("dnt", None),
+        ("sec-ch-ua", Some(r#""Not/A)Brand";v="8", "Chromium";v="126", "Google Chrome";v="126""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36")),
+        ("accept-encoding", Some("gzip, deflate, br, zstd")),
+    ],
+};
+
+pub(crate) static V127: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome126),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Not)A;Brand";v="99", "Google Chrome";v="127", "Chromium";v="127""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/127.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V128: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome127),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="128", "Not;A=Brand";v="24", "Google Chrome";v="128""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/128.0.0.0 Safari/537.36")),
+        ("accept-language", Some("en-US,en;q=0.9")),
+        ("priority", Some("u=0, i")),
+    ],
+};
+
+pub(crate) static V129: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome128),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Google Chrome";v="129", "Not=A?Brand";v="8", "Chromium";v="129""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V130: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome129),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="130", "Google Chrome";v="130", "Not?A_Brand";v="99""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static V131: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome130),
+    tls: Some(tls_mlkem_curves),
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Google Chrome";v="131", "Chromium";v="131", "Not_A Brand";v="24""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")),
+    ],
+};
+
+pub(crate) static ANDROID_WEBVIEW_13: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Chrome131),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Android WebView";v="131", "Chromium";v="131", "Not_A Brand";v="24""#)),
+        ("sec-ch-ua-mobile", Some("?1")),
+        ("sec-ch-ua-platform", Some(r#""Android""#)),
+        ("user-agent", Some("Mozilla/5.0 (Linux; Android 13; Pixel 7 Build/TQ3A.230901.001; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/131.0.6778.135 Mobile Safari/537.36")),
+    ],
+};
+
+pub(crate) static ANDROID_WEBVIEW_14: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::AndroidWebView13),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Linux; Android 14; Pixel 8 Build/AP2A.240905.003; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/131.0.6778.135 Mobile Safari/537.36")),
+    ],
+};
+
+pub(crate) static ANDROID_WEBVIEW_15: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::AndroidWebView14),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Linux; Android 15; Pixel 9 Build/AP3A.241105.007; wv) AppleWebKit/537.36 (KHTML, like Gecko) Version/4.0 Chrome/131.0.6778.135 Mobile Safari/537.36")),
+    ],
+};
+
+pub(crate) static CRONET: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls_pq_curves),
+    http2: Some("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[("accept-encoding", Some("gzip, deflate, br, zstd"))],
+};
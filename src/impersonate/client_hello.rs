@@ -0,0 +1,250 @@
+//! TLS fingerprints built at runtime.
+
+use super::BoringTlsConnector;
+use crate::error;
+use boring::error::ErrorStack;
+use boring::ssl::{
+    CertCompressionAlgorithm, SslConnector, SslConnectorBuilder, SslCurve, SslMethod, SslOptions,
+    SslVersion,
+};
+use std::sync::Arc;
+
+/// A builder of a [`BoringTlsConnector`] sending a ClientHello of your own.
+///
+/// Lists left empty keep the defaults of BoringSSL. BoringSSL orders the
+/// extensions of the ClientHello itself; they can only be shuffled on each
+/// connection, with
+/// [`ClientBuilder::permute_extensions`](crate::ClientBuilder::permute_extensions).
+///
+/// # Example
+///
+/// ```rust
+/// # fn run() -> Result<(), rquest::Error> {
+/// use rquest::boring::ssl::{SslCurve, SslVersion};
+/// use rquest::impersonate::{BoringTlsConnector, Http2Settings};
+///
+/// let tls = BoringTlsConnector::builder()
+///     .cipher_list(&[
+///         "TLS_AES_128_GCM_SHA256",
+///         "TLS_AES_256_GCM_SHA384",
+///         "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+///         "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+///     ])
+///     .curves(&[SslCurve::X25519, SslCurve::SECP256R1])
+///     .sigalgs_list(&["ecdsa_secp256r1_sha256", "rsa_pss_rsae_sha256"])
+///     .alpn_protos(&["h2", "http/1.1"])
+///     .grease(true)
+///     .min_version(SslVersion::TLS1_2)
+///     .build()?;
+///
+/// let http2 = Http2Settings::from_akamai("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p")?;
+///
+/// let client = rquest::Client::builder()
+///     .use_boring_tls(tls)
+///     .http2_settings(http2)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+#[must_use]
+pub struct BoringTlsConnectorBuilder {
+    cipher_list: Vec<String>,
+    curves: Vec<SslCurve>,
+    sigalgs_list: Vec<String>,
+    alpn_protos: Option<Vec<String>>,
+    grease: bool,
+    ocsp_stapling: bool,
+    signed_cert_timestamps: bool,
+    cert_compression: Vec<CertCompressionAlgorithm>,
+    session_tickets: bool,
+    min_version: Option<SslVersion>,
+    max_version: Option<SslVersion>,
+}
+
+impl BoringTlsConnector {
+    /// Start building a connector with a ClientHello of your own.
+    pub fn builder() -> BoringTlsConnectorBuilder {
+        BoringTlsConnectorBuilder {
+            session_tickets: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl BoringTlsConnectorBuilder {
+    /// Set the cipher suites, in the order they are offered.
+    ///
+    /// Suites use their IANA names, such as `TLS_AES_128_GCM_SHA256`.
+    pub fn cipher_list(mut self, ciphers: &[&str]) -> BoringTlsConnectorBuilder {
+        self.cipher_list = ciphers.iter().map(|&c| c.to_owned()).collect();
+        self
+    }
+
+    /// Set the supported groups, in the order they are offered.
+    pub fn curves(mut self, curves: &[SslCurve]) -> BoringTlsConnectorBuilder {
+        self.curves = curves.to_vec();
+        self
+    }
+
+    /// Set the signature algorithms, in the order they are offered.
+    ///
+    /// Algorithms use their TLS 1.3 names, such as `rsa_pss_rsae_sha256`.
+    pub fn sigalgs_list(mut self, sigalgs: &[&str]) -> BoringTlsConnectorBuilder {
+        self.sigalgs_list = sigalgs.iter().map(|&s| s.to_owned()).collect();
+        self
+    }
+
+    /// Set the ALPN protocols, in the order they are offered.
+    ///
+    /// `h2` is left out of connections that must use HTTP/1, such as with
+    /// [`ClientBuilder::http1_only`](crate::ClientBuilder::http1_only).
+    /// Without a list, `h2` and `http/1.1` are offered.
+    pub fn alpn_protos(mut self, protos: &[&str]) -> BoringTlsConnectorBuilder {
+        self.alpn_protos = Some(protos.iter().map(|&p| p.to_owned()).collect());
+        self
+    }
+
+    /// Send GREASE values, as Chromium does.
+    pub fn grease(mut self, enabled: bool) -> BoringTlsConnectorBuilder {
+        self.grease = enabled;
+        self
+    }
+
+    /// Send the `status_request` extension, asking for OCSP stapling.
+    pub fn ocsp_stapling(mut self, enabled: bool) -> BoringTlsConnectorBuilder {
+        self.ocsp_stapling = enabled;
+        self
+    }
+
+    /// Send the `signed_certificate_timestamp` extension.
+    pub fn signed_cert_timestamps(mut self, enabled: bool) -> BoringTlsConnectorBuilder {
+        self.signed_cert_timestamps = enabled;
+        self
+    }
+
+    /// Offer certificate compression with `algorithm`.
+    ///
+    /// Algorithms are offered in the order they are added.
+    pub fn cert_compression(
+        mut self,
+        algorithm: CertCompressionAlgorithm,
+    ) -> BoringTlsConnectorBuilder {
+        self.cert_compression.push(algorithm);
+        self
+    }
+
+    /// Send the `session_ticket` extension. This is the default.
+    pub fn session_tickets(mut self, enabled: bool) -> BoringTlsConnectorBuilder {
+        self.session_tickets = enabled;
+        self
+    }
+
+    /// Set the lowest TLS version offered.
+    pub fn min_version(mut self, version: SslVersion) -> BoringTlsConnectorBuilder {
+        self.min_version = Some(version);
+        self
+    }
+
+    /// Set the highest TLS version offered.
+    pub fn max_version(mut self, version: SslVersion) -> BoringTlsConnectorBuilder {
+        self.max_version = Some(version);
+        self
+    }
+
+    /// Check the settings and build the connector.
+    ///
+    /// # Errors
+    ///
+    /// This fails if BoringSSL rejects a cipher suite, curve, signature
+    /// algorithm or version, or if an ALPN protocol is empty or longer than
+    /// 255 bytes.
+    pub fn build(self) -> crate::Result<BoringTlsConnector> {
+        if let Some(ref protos) = self.alpn_protos {
+            if let Some(proto) = protos.iter().find(|p| p.is_empty() || p.len() > 255) {
+                return Err(error::builder(format!("invalid ALPN protocol {:?}", proto)));
+            }
+        }
+        self.ssl().map_err(error::builder)?;
+
+        let alpn_protos = self.alpn_protos.clone().map(Arc::from);
+        let mut connector = BoringTlsConnector::new(move || self.ssl());
+        connector.alpn_protos = alpn_protos;
+        Ok(connector)
+    }
+
+    fn ssl(&self) -> Result<SslConnectorBuilder, ErrorStack> {
+        let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+        builder.set_default_verify_paths()?;
+        if !self.cipher_list.is_empty() {
+            builder.set_cipher_list(&self.cipher_list.join(":"))?;
+        }
+        if !self.curves.is_empty() {
+            builder.set_curves(&self.curves)?;
+        }
+        if !self.sigalgs_list.is_empty() {
+            builder.set_sigalgs_list(&self.sigalgs_list.join(":"))?;
+        }
+        builder.set_grease_enabled(self.grease);
+        if self.ocsp_stapling {
+            builder.enable_ocsp_stapling();
+        }
+        if self.signed_cert_timestamps {
+            builder.enable_signed_cert_timestamps();
+        }
+        for &algorithm in &self.cert_compression {
+            builder.add_cert_compression_alg(algorithm)?;
+        }
+        if !self.session_tickets {
+            builder.set_options(SslOptions::NO_TICKET);
+        }
+        builder.set_min_proto_version(self.min_version)?;
+        builder.set_max_proto_version(self.max_version)?;
+        Ok(builder)
+    }
+}
+
+/// The wire format of `protos`, without `h2` unless `h2` is set.
+pub(super) fn encode_alpn(protos: &[String], h2: bool) -> Vec<u8> {
+    let mut wire = Vec::new();
+    for proto in protos.iter().filter(|&p| h2 || p != "h2") {
+        wire.push(proto.len() as u8);
+        wire.extend_from_slice(proto.as_bytes());
+    }
+    wire
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpn_wire_format() {
+        let protos = ["h2".to_owned(), "http/1.1".to_owned()];
+        assert_eq!(encode_alpn(&protos, true), b"\x02h2\x08http/1.1");
+        assert_eq!(encode_alpn(&protos, false), b"\x08http/1.1");
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(BoringTlsConnector::builder()
+            .cipher_list(&["TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"])
+            .curves(&[SslCurve::X25519])
+            .sigalgs_list(&["rsa_pss_rsae_sha256"])
+            .alpn_protos(&["http/1.1"])
+            .build()
+            .is_ok());
+        assert!(BoringTlsConnector::builder()
+            .cipher_list(&["NOT_A_CIPHER"])
+            .build()
+            .is_err());
+        assert!(BoringTlsConnector::builder()
+            .sigalgs_list(&["not_a_sigalg"])
+            .build()
+            .is_err());
+        assert!(BoringTlsConnector::builder()
+            .alpn_protos(&[""])
+            .build()
+            .is_err());
+    }
+}
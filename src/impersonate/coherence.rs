@@ -0,0 +1,265 @@
+//! Checks that the headers of an impersonation could come from one browser.
+
+use super::ImpersonateSettings;
+use http::header::{HeaderMap, ACCEPT_LANGUAGE, USER_AGENT};
+use std::fmt;
+
+/// A combination of headers that no real browser sends.
+///
+/// Returned by [`ImpersonateSettings::check_coherence`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Incoherence {
+    /// Client hints are sent with the user agent of a browser that isn't
+    /// based on Chromium, such as Safari or Firefox.
+    ClientHintsWithoutChromium,
+    /// `sec-ch-ua-platform` names another operating system than the user
+    /// agent.
+    Platform {
+        /// The operating system of the user agent.
+        user_agent: &'static str,
+        /// The operating system of `sec-ch-ua-platform`.
+        client_hints: String,
+    },
+    /// `sec-ch-ua-mobile` disagrees with the user agent on whether the
+    /// device is a phone.
+    Mobile,
+    /// `sec-ch-ua` has another Chromium version than the user agent.
+    Version {
+        /// The major version of the user agent.
+        user_agent: String,
+        /// The major version of `sec-ch-ua`.
+        client_hints: String,
+    },
+    /// `accept-language` isn't a list of language tags with valid weights.
+    AcceptLanguage,
+}
+
+impl fmt::Display for Incoherence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incoherence::ClientHintsWithoutChromium => {
+                f.write_str("client hints sent by a browser that isn't Chromium-based")
+            }
+            Incoherence::Platform {
+                user_agent,
+                client_hints,
+            } => write!(
+                f,
+                "user agent runs on {} but sec-ch-ua-platform is {}",
+                user_agent, client_hints
+            ),
+            Incoherence::Mobile => f.write_str("sec-ch-ua-mobile disagrees with the user agent"),
+            Incoherence::Version {
+                user_agent,
+                client_hints,
+            } => write!(
+                f,
+                "user agent is version {} but sec-ch-ua is version {}",
+                user_agent, client_hints
+            ),
+            Incoherence::AcceptLanguage => f.write_str("malformed accept-language"),
+        }
+    }
+}
+
+impl ImpersonateSettings {
+    /// Find the headers that contradict each other.
+    ///
+    /// Servers compare the user agent with the client hints, and a mismatch
+    /// such as a Safari user agent sending `sec-ch-ua`, or a Windows user
+    /// agent with a macOS `sec-ch-ua-platform`, flags a client at once. Run
+    /// this after changing the headers of a profile, before using it.
+    ///
+    /// An empty list means no problem was found.
+    pub fn check_coherence(&self) -> Vec<Incoherence> {
+        check(&self.headers)
+    }
+}
+
+fn check(headers: &HeaderMap) -> Vec<Incoherence> {
+    let mut found = Vec::new();
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(user_agent) = header(USER_AGENT.as_str()) {
+        let has_hints = headers
+            .keys()
+            .any(|name| name.as_str().starts_with("sec-ch-ua"));
+        if has_hints && !user_agent.contains("Chrome/") {
+            found.push(Incoherence::ClientHintsWithoutChromium);
+        }
+
+        if let (Some(os), Some(platform)) = (os(user_agent), header("sec-ch-ua-platform")) {
+            let platform = platform.trim_matches('"');
+            let hinted = match platform {
+                "Mac OS" | "Mac OS X" => "macOS",
+                "Chromium OS" => "Chrome OS",
+                platform => platform,
+            };
+            if hinted != os {
+                found.push(Incoherence::Platform {
+                    user_agent: os,
+                    client_hints: platform.to_owned(),
+                });
+            }
+        }
+
+        if let Some(mobile) = header("sec-ch-ua-mobile") {
+            if (mobile == "?1") != user_agent.contains("Mobile") {
+                found.push(Incoherence::Mobile);
+            }
+        }
+
+        let ua_version = user_agent
+            .split_once("Chrome/")
+            .and_then(|(_, rest)| rest.split('.').next());
+        let hint_version = header("sec-ch-ua").and_then(chromium_version);
+        if let (Some(ua_version), Some(hint_version)) = (ua_version, hint_version) {
+            if ua_version != hint_version {
+                found.push(Incoherence::Version {
+                    user_agent: ua_version.to_owned(),
+                    client_hints: hint_version.to_owned(),
+                });
+            }
+        }
+    }
+
+    if let Some(languages) = headers.get(ACCEPT_LANGUAGE) {
+        if !languages.to_str().map_or(false, valid_accept_language) {
+            found.push(Incoherence::AcceptLanguage);
+        }
+    }
+
+    found
+}
+
+/// The operating system of a user agent, named as in `sec-ch-ua-platform`.
+fn os(user_agent: &str) -> Option<&'static str> {
+    // iOS user agents also say "like Mac OS X", and Android ones "Linux".
+    let os = if user_agent.contains("Windows") {
+        "Windows"
+    } else if user_agent.contains("iPhone") || user_agent.contains("iPad") {
+        "iOS"
+    } else if user_agent.contains("Android") {
+        "Android"
+    } else if user_agent.contains("CrOS") {
+        "Chrome OS"
+    } else if user_agent.contains("Macintosh") {
+        "macOS"
+    } else if user_agent.contains("Linux") {
+        "Linux"
+    } else {
+        return None;
+    };
+    Some(os)
+}
+
+/// The Chromium major version in a `sec-ch-ua` brand list.
+fn chromium_version(brands: &str) -> Option<&str> {
+    brands.split(',').find_map(|brand| {
+        let (name, version) = brand.trim().split_once(";v=")?;
+        match name.trim_matches('"') {
+            "Chromium" | "Google Chrome" => Some(version.trim_matches('"')),
+            _ => None,
+        }
+    })
+}
+
+fn valid_accept_language(languages: &str) -> bool {
+    languages.split(',').all(|language| {
+        let mut parts = language.trim().split(';');
+        let tag = parts.next().unwrap_or_default();
+        let valid_tag = tag == "*"
+            || tag.split('-').enumerate().all(|(i, subtag)| {
+                (1..=8).contains(&subtag.len())
+                    && subtag
+                        .chars()
+                        .all(|c| c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()))
+            });
+        let valid_weight = parts.all(|param| {
+            param
+                .trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .map_or(false, |q| (0.0..=1.0).contains(&q))
+        });
+        valid_tag && valid_weight
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+
+    const WINDOWS_CHROME: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/127.0.0.0 Safari/537.36";
+    const MAC_SAFARI: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15";
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.parse().unwrap(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    #[test]
+    fn coherent_chrome() {
+        let headers = headers(&[
+            (
+                "sec-ch-ua",
+                r#""Not)A;Brand";v="99", "Google Chrome";v="127", "Chromium";v="127""#,
+            ),
+            ("sec-ch-ua-mobile", "?0"),
+            ("sec-ch-ua-platform", r#""Windows""#),
+            ("user-agent", WINDOWS_CHROME),
+            ("accept-language", "en-US,en;q=0.9"),
+        ]);
+        assert!(check(&headers).is_empty());
+    }
+
+    #[test]
+    fn flags_contradictions() {
+        let safari = headers(&[("sec-ch-ua-mobile", "?0"), ("user-agent", MAC_SAFARI)]);
+        assert_eq!(check(&safari), [Incoherence::ClientHintsWithoutChromium]);
+
+        let platform = headers(&[
+            ("sec-ch-ua-platform", r#""macOS""#),
+            ("user-agent", WINDOWS_CHROME),
+        ]);
+        assert_eq!(
+            check(&platform),
+            [Incoherence::Platform {
+                user_agent: "Windows",
+                client_hints: "macOS".to_owned(),
+            }]
+        );
+
+        let mobile = headers(&[("sec-ch-ua-mobile", "?1"), ("user-agent", WINDOWS_CHROME)]);
+        assert_eq!(check(&mobile), [Incoherence::Mobile]);
+
+        let version = headers(&[
+            (
+                "sec-ch-ua",
+                r#""Chromium";v="126", "Google Chrome";v="126""#,
+            ),
+            ("user-agent", WINDOWS_CHROME),
+        ]);
+        assert_eq!(
+            check(&version),
+            [Incoherence::Version {
+                user_agent: "127".to_owned(),
+                client_hints: "126".to_owned(),
+            }]
+        );
+
+        for languages in ["en-US;q=2", "en_US", "en;0.9", ""] {
+            let headers = headers(&[("accept-language", languages)]);
+            assert_eq!(
+                check(&headers),
+                [Incoherence::AcceptLanguage],
+                "{}",
+                languages
+            );
+        }
+    }
+}
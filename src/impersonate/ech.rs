@@ -0,0 +1,289 @@
+//! Encrypted Client Hello, see
+//! [draft-ietf-tls-esni](https://datatracker.ietf.org/doc/draft-ietf-tls-esni/).
+//!
+//! The configurations of servers are set by hand, or looked up in their
+//! HTTPS records. BoringSSL encrypts the inner ClientHello with them, and
+//! when a server rejects the configuration offered, the connection is
+//! made again with the retry configurations it sent, as Chrome does.
+
+use std::collections::HashMap;
+use std::os::raw::c_int;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use boring::error::ErrorStack;
+use boring::ex_data::Index;
+use boring::ssl::{ConnectConfiguration, Ssl, SslConnectorBuilder, SslRef};
+use foreign_types::ForeignTypeRef;
+use http::Uri;
+
+use crate::dns::doh::DohResolver;
+
+/// Hosts whose configuration is cached, beyond which expired ones are
+/// purged.
+const MAX_HOSTS: usize = 1024;
+
+/// How long a failed lookup is cached for, connecting without ECH.
+const FAILED_TTL: Duration = Duration::from_secs(60);
+
+/// The alert sent when the server rejected the configuration.
+const ALERT_ECH_REQUIRED: c_int = 121;
+
+/// The ECH configurations of the servers a client connects to.
+pub(crate) struct EchConfigs {
+    /// The lists set with `ClientBuilder::ech_config_list`, by host.
+    fixed: HashMap<String, Arc<[u8]>>,
+    /// The resolver HTTPS records are looked up with, if any.
+    dns: Option<DohResolver>,
+    /// The lists looked up, or sent back by servers, by host and port.
+    cache: Mutex<HashMap<(String, u16), Entry>>,
+}
+
+struct Entry {
+    /// The `ECHConfigList` to offer, or `None` to connect without ECH.
+    list: Option<Arc<[u8]>>,
+    /// When the entry expires, if it was looked up.
+    expires: Option<Instant>,
+    /// Whether the server rejected the list of the last handshake, which
+    /// is made again with the new one.
+    rejected: bool,
+}
+
+/// What a connection offering ECH is tagged with, to find its entry when
+/// the server rejects it.
+struct Tag {
+    configs: Arc<EchConfigs>,
+    host: String,
+    port: u16,
+}
+
+impl EchConfigs {
+    pub(crate) fn new(fixed: HashMap<String, Vec<u8>>, dns: Option<DohResolver>) -> EchConfigs {
+        EchConfigs {
+            fixed: fixed
+                .into_iter()
+                .map(|(host, list)| (host.to_ascii_lowercase(), list.into()))
+                .collect(),
+            dns,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Look up the configuration of the server of `uri` in its HTTPS
+    /// record, unless it is set or cached.
+    pub(crate) async fn prepare(&self, uri: &Uri) {
+        let (dns, (host, port)) = match (&self.dns, key(uri)) {
+            (Some(dns), Some(key)) => (dns, key),
+            _ => return,
+        };
+        // The server of the resolver is connected to without a lookup, as
+        // it would be its own.
+        if host == dns.host()
+            || self.fixed.contains_key(&host)
+            || self.cached(&host, port).is_some()
+        {
+            return;
+        }
+
+        let (list, ttl) = match dns.lookup_ech(&host, port).await {
+            Ok(answer) => (answer.list.map(Arc::from), answer.ttl),
+            Err(err) => {
+                log::debug!("ECH lookup for {} failed: {}", host, err);
+                (None, FAILED_TTL)
+            }
+        };
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_HOSTS {
+            cache.retain(|_, entry| entry.expires.map_or(true, |expires| expires > now));
+        }
+        cache.insert(
+            (host, port),
+            Entry {
+                list,
+                expires: Some(now + ttl),
+                rejected: false,
+            },
+        );
+    }
+
+    /// Whether the server of `uri` rejected the configuration of the last
+    /// handshake, which should then be made again.
+    pub(crate) fn take_rejection(&self, uri: &Uri) -> bool {
+        let key = match key(uri) {
+            Some(key) => key,
+            None => return false,
+        };
+        let mut cache = self.cache.lock().unwrap();
+        cache
+            .get_mut(&key)
+            .map_or(false, |entry| std::mem::take(&mut entry.rejected))
+    }
+
+    /// The list to offer to the server at `host` and `port`.
+    fn list(&self, host: &str, port: u16) -> Option<Arc<[u8]>> {
+        match self.cached(host, port) {
+            Some(list) => list,
+            None => self.fixed.get(host).cloned(),
+        }
+    }
+
+    /// The cached list of the server, `Some(None)` if it has none.
+    fn cached(&self, host: &str, port: u16) -> Option<Option<Arc<[u8]>>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(&(host.to_owned(), port))
+            .filter(|entry| {
+                entry
+                    .expires
+                    .map_or(true, |expires| expires > Instant::now())
+            })
+            .map(|entry| entry.list.clone())
+    }
+
+    /// Record that the server rejected the list offered, sending `retry`,
+    /// or nothing if it disabled ECH.
+    fn reject(&self, host: &str, port: u16, retry: Option<Vec<u8>>) {
+        let mut cache = self.cache.lock().unwrap();
+        let expires = cache
+            .get(&(host.to_owned(), port))
+            .and_then(|entry| entry.expires);
+        cache.insert(
+            (host.to_owned(), port),
+            Entry {
+                list: retry.map(Arc::from),
+                expires,
+                rejected: true,
+            },
+        );
+    }
+}
+
+/// The host, lowercased, and the port of `uri`.
+fn key(uri: &Uri) -> Option<(String, u16)> {
+    let host = uri.host()?.trim_end_matches('.').to_ascii_lowercase();
+    Some((host, uri.port_u16().unwrap_or(443)))
+}
+
+/// The ex data index of the tag of a connection.
+fn tag_index() -> Result<Index<Ssl, Tag>, ErrorStack> {
+    static INDEX: OnceLock<Index<Ssl, Tag>> = OnceLock::new();
+    if let Some(index) = INDEX.get() {
+        return Ok(*index);
+    }
+    let index = Ssl::new_ex_index()?;
+    Ok(*INDEX.get_or_init(|| index))
+}
+
+/// Offer the configuration of the server of `uri` on a connection, if it
+/// has one.
+pub(super) fn offer(
+    conf: &mut ConnectConfiguration,
+    uri: &Uri,
+    configs: &Arc<EchConfigs>,
+) -> Result<(), ErrorStack> {
+    let (host, port) = match key(uri) {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+    let list = match configs.list(&host, port) {
+        Some(list) => list,
+        None => return Ok(()),
+    };
+    // SAFETY: the list is copied by BoringSSL.
+    let set =
+        unsafe { boring_sys::SSL_set1_ech_config_list(conf.as_ptr(), list.as_ptr(), list.len()) };
+    if set != 1 {
+        // A malformed list is skipped rather than failing the connection,
+        // as Chrome does.
+        log::debug!(
+            "invalid ECH configuration for {}: {}",
+            host,
+            ErrorStack::get()
+        );
+        return Ok(());
+    }
+    conf.set_ex_data(
+        tag_index()?,
+        Tag {
+            configs: configs.clone(),
+            host,
+            port,
+        },
+    );
+    Ok(())
+}
+
+/// Watch for the rejections of connections made with `builder`, keeping
+/// the retry configurations sent by servers.
+pub(super) fn watch_rejections(builder: &mut SslConnectorBuilder) -> Result<(), ErrorStack> {
+    // Set up the index before any handshake needs it.
+    tag_index()?;
+    // SAFETY: the callback only reads the connection it is called with.
+    unsafe { boring_sys::SSL_CTX_set_info_callback(builder.as_ptr(), Some(info_callback)) };
+    Ok(())
+}
+
+unsafe extern "C" fn info_callback(ssl: *const boring_sys::SSL, kind: c_int, value: c_int) {
+    if kind & boring_sys::SSL_CB_WRITE_ALERT as c_int == 0 || value & 0xff != ALERT_ECH_REQUIRED {
+        return;
+    }
+    let ssl = SslRef::from_ptr(ssl as *mut boring_sys::SSL);
+    let tag = match tag_index().ok().and_then(|index| ssl.ex_data(index)) {
+        Some(tag) => tag,
+        None => return,
+    };
+
+    let mut configs = std::ptr::null();
+    let mut len = 0;
+    boring_sys::SSL_get0_ech_retry_configs(ssl.as_ptr(), &mut configs, &mut len);
+    let retry = if configs.is_null() || len == 0 {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(configs, len).to_vec())
+    };
+    log::debug!(
+        "ECH rejected by {}, {}",
+        tag.host,
+        if retry.is_some() {
+            "retrying with its configuration"
+        } else {
+            "retrying without ECH"
+        }
+    );
+    tag.configs.reject(&tag.host, tag.port, retry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejection_replaces_the_list() {
+        let configs = EchConfigs::new(
+            HashMap::from([("Example.com".to_owned(), vec![1, 2, 3])]),
+            None,
+        );
+        let uri = Uri::from_static("https://example.com/");
+        assert_eq!(
+            configs.list("example.com", 443).as_deref(),
+            Some(&[1, 2, 3][..])
+        );
+        assert!(!configs.take_rejection(&uri));
+
+        configs.reject("example.com", 443, Some(vec![4, 5]));
+        assert!(configs.take_rejection(&uri));
+        assert!(!configs.take_rejection(&uri));
+        assert_eq!(
+            configs.list("example.com", 443).as_deref(),
+            Some(&[4, 5][..])
+        );
+
+        configs.reject("example.com", 443, None);
+        assert_eq!(configs.list("example.com", 443), None);
+        assert_eq!(
+            configs.list("example.com", 8443).as_deref(),
+            Some(&[1, 2, 3][..])
+        );
+    }
+}
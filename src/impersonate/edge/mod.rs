@@ -1,7 +1,6 @@
-pub mod edge101;
-pub mod edge122;
-pub mod edge127;
-pub mod edge99;
+use crate::impersonate::extension::{EdgeExtension, Extension, SslExtension};
+use crate::impersonate::{Impersonate, ProfileSpec};
+use boring::{error::ErrorStack, ssl::SslConnectorBuilder};
 
 const CIPHER_LIST: [&str; 15] = [
     "TLS_AES_128_GCM_SHA256",
@@ -20,3 +19,115 @@ const CIPHER_LIST: [&str; 15] = [
     "TLS_RSA_WITH_AES_128_CBC_SHA",
     "TLS_RSA_WITH_AES_256_CBC_SHA",
 ];
+
+fn tls() -> Result<SslConnectorBuilder, ErrorStack> {
+    EdgeExtension::builder()?.configure_cipher_list(&CIPHER_LIST)
+}
+
+fn tls_pq_curves() -> Result<SslConnectorBuilder, ErrorStack> {
+    EdgeExtension::builder()?
+        .configure_cipher_list(&CIPHER_LIST)?
+        .configure_chrome_pq_curves(true)
+}
+
+fn tls_mlkem_curves() -> Result<SslConnectorBuilder, ErrorStack> {
+    EdgeExtension::builder()?
+        .configure_cipher_list(&CIPHER_LIST)?
+        .configure_chrome_mlkem_curves(true)
+}
+
+pub(crate) static V99: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls),
+    http2: Some("1:65536;3:1000;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("sec-ch-ua", Some(r#"" Not A;Brand";v="99", "Chromium";v="99", "Microsoft Edge";v="99""#)),
+        ("sec-ch-ua-mobile", Some("?0")),
+        ("sec-ch-ua-platform", Some(r#""Windows""#)),
+        ("upgrade-insecure-requests", Some("1")),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/99.0.4844.51 Safari/537.36 Edg/99.0.1150.30")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.9")),
+        ("sec-fetch-site", Some("none")),
+        ("sec-fetch-mode", Some("navigate")),
+        ("sec-fetch-user", Some("?1")),
+        ("sec-fetch-dest", Some("document")),
+        ("accept-encoding", Some("gzip, deflate, br")),
+        ("accept-language", Some("en-US,en;q=0.9")),
+    ],
+};
+
+pub(crate) static V101: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge99),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#"" Not A;Brand";v="99", "Chromium";v="101", "Microsoft Edge";v="101""#)),
+        ("user-agent", Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/101.0.4951.64 Safari/537.36 Edg/101.0.1210.47")),
+    ],
+};
+
+pub(crate) static V122: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge101),
+    tls: None,
+    http2: Some("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"),
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="122", "Not(A:Brand";v="24", "Microsoft Edge";v="122""#)),
+        ("sec-ch-ua-platform", Some(r#""macOS""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36 Edg/122.0.0.0")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7")),
+        ("accept-language", Some("en;q=0.8,en-GB;q=0.7,en-US;q=0.6")),
+    ],
+};
+
+pub(crate) static V127: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge122),
+    tls: Some(tls_pq_curves),
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Not)A;Brand";v="99", "Microsoft Edge";v="127", "Chromium";v="127""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/127.0.0.0 Safari/537.36 Edg/127.0.0.0")),
+        ("accept-encoding", Some("gzip, deflate, br, zstd")),
+        ("accept-language", Some("en-US,en;q=0.9")),
+        ("priority", Some("u=0, i")),
+    ],
+};
+
+pub(crate) static V128: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge127),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="128", "Not;A=Brand";v="24", "Microsoft Edge";v="128""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/128.0.0.0 Safari/537.36 Edg/128.0.0.0")),
+    ],
+};
+
+pub(crate) static V129: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge128),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Microsoft Edge";v="129", "Not=A?Brand";v="8", "Chromium";v="129""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36 Edg/129.0.0.0")),
+    ],
+};
+
+pub(crate) static V130: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge129),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Chromium";v="130", "Microsoft Edge";v="130", "Not?A_Brand";v="99""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/130.0.0.0 Safari/537.36 Edg/130.0.0.0")),
+    ],
+};
+
+pub(crate) static V131: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Edge130),
+    tls: Some(tls_mlkem_curves),
+    http2: None,
+    headers: &[
+        ("sec-ch-ua", Some(r#""Microsoft Edge";v="131", "Chromium";v="131", "Not_A Brand";v="24""#)),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36 Edg/131.0.0.0")),
+    ],
+};
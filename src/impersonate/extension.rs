@@ -7,6 +7,8 @@ use boring::ssl::{
 };
 use foreign_types::ForeignTypeRef;
 
+use super::capabilities;
+
 /// Extension trait for `SslConnector`.
 pub trait Extension {
     /// The signature algorithms list.
@@ -21,6 +23,23 @@ pub trait SslExtension {
     /// Configure chrome to use the curves. (Chrome 123+)
     fn configure_chrome_new_curves(self) -> Result<SslConnectorBuilder, ErrorStack>;
 
+    /// Configure chrome to use the curves, offering the hybrid post-quantum
+    /// `X25519Kyber768Draft00` key share first if `post_quantum` is set and
+    /// the linked BoringSSL supports it. (Chrome 124+)
+    fn configure_chrome_pq_curves(
+        self,
+        post_quantum: bool,
+    ) -> Result<SslConnectorBuilder, ErrorStack>;
+
+    /// Configure chrome to use the curves, offering the hybrid post-quantum
+    /// `X25519MLKEM768` key share first if `post_quantum` is set and the
+    /// linked BoringSSL supports it, or else the `X25519Kyber768Draft00`
+    /// share of the earlier versions. (Chrome 131+)
+    fn configure_chrome_mlkem_curves(
+        self,
+        post_quantum: bool,
+    ) -> Result<SslConnectorBuilder, ErrorStack>;
+
     /// Configure the certificate verification for the given `SslConnectorBuilder`.
     fn configure_cert_verification(
         self,
@@ -74,7 +93,9 @@ impl Extension for ChromeExtension {
         builder.set_curves(&[SslCurve::X25519, SslCurve::SECP256R1, SslCurve::SECP384R1])?;
         builder.set_sigalgs_list(&SIGALGS_LIST.join(":"))?;
         builder.enable_signed_cert_timestamps();
-        builder.add_cert_compression_alg(CertCompressionAlgorithm::Brotli)?;
+        if capabilities().cert_compression {
+            builder.add_cert_compression_alg(CertCompressionAlgorithm::Brotli)?;
+        }
         builder.set_min_proto_version(Some(SslVersion::TLS1_2))?;
         builder.set_max_proto_version(Some(SslVersion::TLS1_3))?;
 
@@ -106,7 +127,9 @@ impl Extension for EdgeExtension {
         builder.set_curves(&[SslCurve::X25519, SslCurve::SECP256R1, SslCurve::SECP384R1])?;
         builder.set_sigalgs_list(&SIGALGS_LIST.join(":"))?;
         builder.enable_signed_cert_timestamps();
-        builder.add_cert_compression_alg(CertCompressionAlgorithm::Brotli)?;
+        if capabilities().cert_compression {
+            builder.add_cert_compression_alg(CertCompressionAlgorithm::Brotli)?;
+        }
         builder.set_min_proto_version(Some(SslVersion::TLS1_2))?;
         builder.set_max_proto_version(Some(SslVersion::TLS1_3))?;
 
@@ -147,7 +170,9 @@ impl Extension for SafariExtension {
             SslCurve::SECP521R1,
         ])?;
         builder.enable_signed_cert_timestamps();
-        builder.add_cert_compression_alg(CertCompressionAlgorithm::Zlib)?;
+        if capabilities().cert_compression {
+            builder.add_cert_compression_alg(CertCompressionAlgorithm::Zlib)?;
+        }
         builder.set_min_proto_version(Some(SslVersion::TLS1))?;
 
         Ok(builder)
@@ -185,16 +210,42 @@ impl Extension for OkHttpExtension {
 }
 
 impl SslExtension for SslConnectorBuilder {
-    fn configure_chrome_new_curves(mut self) -> Result<SslConnectorBuilder, ErrorStack> {
-        self.set_curves(&[
+    fn configure_chrome_new_curves(self) -> Result<SslConnectorBuilder, ErrorStack> {
+        self.configure_chrome_pq_curves(true)
+    }
+
+    fn configure_chrome_pq_curves(
+        mut self,
+        post_quantum: bool,
+    ) -> Result<SslConnectorBuilder, ErrorStack> {
+        let curves = [
             SslCurve::X25519_KYBER768_DRAFT00,
             SslCurve::X25519,
             SslCurve::SECP256R1,
             SslCurve::SECP384R1,
-        ])?;
+        ];
+        if post_quantum && capabilities().post_quantum {
+            self.set_curves(&curves)?;
+        } else {
+            self.set_curves(&curves[1..])?;
+        }
         Ok(self)
     }
 
+    fn configure_chrome_mlkem_curves(
+        mut self,
+        post_quantum: bool,
+    ) -> Result<SslConnectorBuilder, ErrorStack> {
+        if post_quantum && capabilities().ml_kem {
+            super::capabilities::set_curves_list(&mut self, "X25519MLKEM768:X25519:P-256:P-384")?;
+            Ok(self)
+        } else {
+            // Chrome has always offered a post-quantum share since 124, so
+            // the closest hello offers the draft group rather than none.
+            self.configure_chrome_pq_curves(post_quantum)
+        }
+    }
+
     fn configure_cert_verification(
         mut self,
         certs_verification: bool,
@@ -244,7 +295,7 @@ impl SslConnectExtension for ConnectConfiguration {
     }
 
     fn configure_add_application_settings(&mut self, h2: bool) -> &mut ConnectConfiguration {
-        if h2 {
+        if h2 && capabilities().alps {
             const ALPN_H2: &str = "h2";
             const ALPN_H2_LENGTH: usize = 2;
             unsafe {
@@ -0,0 +1,345 @@
+//! TLS settings from JA3 and JA4 fingerprints.
+
+use super::{BoringTlsConnector, BoringTlsConnectorBuilder};
+use crate::error;
+use boring::ssl::{CertCompressionAlgorithm, SslCurve, SslVersion};
+
+const EXT_STATUS_REQUEST: u16 = 5;
+const EXT_SIGNED_CERT_TIMESTAMP: u16 = 18;
+const EXT_COMPRESS_CERTIFICATE: u16 = 27;
+const EXT_SESSION_TICKET: u16 = 35;
+const EXT_SUPPORTED_VERSIONS: u16 = 43;
+
+/// The cipher suites BoringSSL can offer, by IANA id.
+const CIPHERS: [(u16, &str); 28] = [
+    (0x000a, "TLS_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0x002f, "TLS_RSA_WITH_AES_128_CBC_SHA"),
+    (0x0035, "TLS_RSA_WITH_AES_256_CBC_SHA"),
+    (0x003c, "TLS_RSA_WITH_AES_128_CBC_SHA256"),
+    (0x003d, "TLS_RSA_WITH_AES_256_CBC_SHA256"),
+    (0x009c, "TLS_RSA_WITH_AES_128_GCM_SHA256"),
+    (0x009d, "TLS_RSA_WITH_AES_256_GCM_SHA384"),
+    (0x1301, "TLS_AES_128_GCM_SHA256"),
+    (0x1302, "TLS_AES_256_GCM_SHA384"),
+    (0x1303, "TLS_CHACHA20_POLY1305_SHA256"),
+    (0x1304, "TLS_AES_128_CCM_SHA256"),
+    (0xc008, "TLS_ECDHE_ECDSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xc009, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA"),
+    (0xc00a, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA"),
+    (0xc012, "TLS_ECDHE_RSA_WITH_3DES_EDE_CBC_SHA"),
+    (0xc013, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA"),
+    (0xc014, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA"),
+    (0xc023, "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256"),
+    (0xc024, "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384"),
+    (0xc027, "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256"),
+    (0xc028, "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384"),
+    (0xc02b, "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256"),
+    (0xc02c, "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384"),
+    (0xc02f, "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"),
+    (0xc030, "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384"),
+    (0xcca8, "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256"),
+    (0xcca9, "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256"),
+    // Signals secure renegotiation, which BoringSSL does with an extension.
+    (0x00ff, ""),
+];
+
+/// The signature algorithms BoringSSL can offer, by IANA id.
+const SIGALGS: [(u16, &str); 12] = [
+    (0x0201, "rsa_pkcs1_sha1"),
+    (0x0203, "ecdsa_sha1"),
+    (0x0401, "rsa_pkcs1_sha256"),
+    (0x0403, "ecdsa_secp256r1_sha256"),
+    (0x0501, "rsa_pkcs1_sha384"),
+    (0x0503, "ecdsa_secp384r1_sha384"),
+    (0x0601, "rsa_pkcs1_sha512"),
+    (0x0603, "ecdsa_secp521r1_sha512"),
+    (0x0804, "rsa_pss_rsae_sha256"),
+    (0x0805, "rsa_pss_rsae_sha384"),
+    (0x0806, "rsa_pss_rsae_sha512"),
+    (0x0807, "ed25519"),
+];
+
+fn curve(id: u16) -> Option<SslCurve> {
+    let curve = match id {
+        23 => SslCurve::SECP256R1,
+        24 => SslCurve::SECP384R1,
+        25 => SslCurve::SECP521R1,
+        29 => SslCurve::X25519,
+        0x6399 => SslCurve::X25519_KYBER768_DRAFT00,
+        _ => return None,
+    };
+    Some(curve)
+}
+
+/// GREASE values, reserved to keep servers tolerant of unknown ones.
+fn is_grease(id: u16) -> bool {
+    id & 0x0f0f == 0x0a0a && id >> 8 == id & 0xff
+}
+
+/// What a fingerprint tells of a ClientHello.
+#[derive(Default)]
+struct Fingerprint {
+    ciphers: Vec<&'static str>,
+    extensions: Vec<u16>,
+    curves: Vec<SslCurve>,
+    sigalgs: Vec<&'static str>,
+    alpn: Option<&'static [&'static str]>,
+    max_version: Option<SslVersion>,
+    grease: bool,
+}
+
+impl Fingerprint {
+    fn set_ciphers(&mut self, ids: &[u16]) -> crate::Result<()> {
+        for &id in ids {
+            if is_grease(id) {
+                self.grease = true;
+                continue;
+            }
+            match CIPHERS.iter().find(|&&(i, _)| i == id) {
+                Some((_, "")) => {}
+                Some((_, name)) => self.ciphers.push(name),
+                None => return Err(unsupported("cipher suite", id)),
+            }
+        }
+        Ok(())
+    }
+
+    fn set_extensions(&mut self, ids: &[u16]) {
+        for &id in ids {
+            if is_grease(id) {
+                self.grease = true;
+            } else {
+                self.extensions.push(id);
+            }
+        }
+    }
+
+    fn into_builder(self) -> BoringTlsConnectorBuilder {
+        let has = |id| self.extensions.contains(&id);
+        let mut builder = BoringTlsConnector::builder()
+            .cipher_list(&self.ciphers)
+            .curves(&self.curves)
+            .sigalgs_list(&self.sigalgs)
+            .grease(self.grease)
+            .ocsp_stapling(has(EXT_STATUS_REQUEST))
+            .signed_cert_timestamps(has(EXT_SIGNED_CERT_TIMESTAMP))
+            .session_tickets(has(EXT_SESSION_TICKET));
+        if has(EXT_COMPRESS_CERTIFICATE) {
+            builder = builder.cert_compression(CertCompressionAlgorithm::Brotli);
+        }
+        if let Some(alpn) = self.alpn {
+            builder = builder.alpn_protos(alpn);
+        }
+        if let Some(version) = self.max_version {
+            builder = builder.max_version(version);
+        }
+        builder
+    }
+}
+
+impl BoringTlsConnectorBuilder {
+    /// Start from the TLS settings of a JA3 fingerprint.
+    ///
+    /// A JA3 fingerprint lists the version, cipher suites, extensions,
+    /// supported groups and point formats of a ClientHello, such as
+    /// `771,4865-4866-4867-49195,0-23-65281-10-11-35-16-5-13-18-51-45-43-27,29-23-24,0`.
+    /// The suites and groups are offered in their order, and the extensions
+    /// that BoringSSL can leave out are sent if listed: `status_request`,
+    /// `signed_certificate_timestamp`, `compress_certificate` (with Brotli)
+    /// and `session_ticket`.
+    ///
+    /// BoringSSL orders the other extensions itself, and JA3 strings usually
+    /// leave out GREASE values, so set [`grease`](Self::grease) to match
+    /// Chromium.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the fingerprint is malformed, or has a cipher suite,
+    /// group or point format that BoringSSL doesn't support.
+    pub fn from_ja3(ja3: &str) -> crate::Result<BoringTlsConnectorBuilder> {
+        let invalid =
+            |reason: &str| error::builder(format!("invalid JA3 fingerprint {:?}: {}", ja3, reason));
+        let list = |part: &str| -> crate::Result<Vec<u16>> {
+            part.split('-')
+                .filter(|id| !id.is_empty())
+                .map(|id| id.parse::<u16>().map_err(|_| invalid("malformed id")))
+                .collect()
+        };
+
+        let parts = ja3.trim().split(',').collect::<Vec<_>>();
+        let (version, ciphers, extensions, curves, point_formats) = match parts[..] {
+            [a, b, c, d, e] => (a, b, c, d, e),
+            _ => return Err(invalid("expected 5 parts separated by `,`")),
+        };
+
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.set_ciphers(&list(ciphers)?)?;
+        fingerprint.set_extensions(&list(extensions)?);
+        for id in list(curves)? {
+            if is_grease(id) {
+                fingerprint.grease = true;
+                continue;
+            }
+            let group = curve(id).ok_or_else(|| unsupported("group", id))?;
+            fingerprint.curves.push(group);
+        }
+        if list(point_formats)?.iter().any(|&format| format != 0) {
+            return Err(invalid("only uncompressed points are supported"));
+        }
+
+        // TLS 1.3 is offered with an extension, as the version says 1.2.
+        fingerprint.max_version = if fingerprint.extensions.contains(&EXT_SUPPORTED_VERSIONS) {
+            Some(SslVersion::TLS1_3)
+        } else {
+            match version.parse::<u16>() {
+                Ok(0x0301) => Some(SslVersion::TLS1),
+                Ok(0x0302) => Some(SslVersion::TLS1_1),
+                Ok(0x0303) => Some(SslVersion::TLS1_2),
+                _ => return Err(invalid("unsupported TLS version")),
+            }
+        };
+
+        Ok(fingerprint.into_builder())
+    }
+
+    /// Start from the TLS settings of a raw JA4 fingerprint.
+    ///
+    /// Hashed JA4 fingerprints can't be reversed, so this takes the raw
+    /// form, such as
+    /// `t13d0410h2_1301,1302,c02b,c02f_000a,000b,000d,0017,002b_0403,0804`.
+    /// The original-order form (JA4_ro) keeps the order of the cipher
+    /// suites, which the sorted form (JA4_r) loses. The version, ALPN,
+    /// suites and signature algorithms are used, and extensions as with
+    /// [`from_ja3`](Self::from_ja3). JA4 doesn't list the supported groups,
+    /// which keep the defaults of BoringSSL.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the fingerprint is hashed or malformed, or has a cipher
+    /// suite or signature algorithm that BoringSSL doesn't support.
+    pub fn from_ja4(ja4: &str) -> crate::Result<BoringTlsConnectorBuilder> {
+        let invalid =
+            |reason: &str| error::builder(format!("invalid JA4 fingerprint {:?}: {}", ja4, reason));
+        let list = |part: &str| -> crate::Result<Vec<u16>> {
+            part.split(',')
+                .filter(|id| !id.is_empty())
+                .map(|id| u16::from_str_radix(id, 16).map_err(|_| invalid("malformed id")))
+                .collect()
+        };
+
+        let parts = ja4.trim().split('_').collect::<Vec<_>>();
+        let (prefix, ciphers, extensions, sigalgs) = match parts[..] {
+            [a, b, c] => (a, b, c, ""),
+            [a, b, c, d] => (a, b, c, d),
+            _ => return Err(invalid("expected 4 parts separated by `_`")),
+        };
+        if ciphers.len() == 12 && !ciphers.contains(',') {
+            return Err(invalid(
+                "hashed fingerprints can't be reversed, use the raw form",
+            ));
+        }
+        if prefix.len() != 10 || !prefix.is_ascii() {
+            return Err(invalid("malformed prefix"));
+        }
+
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.max_version = match &prefix[1..3] {
+            "10" => Some(SslVersion::TLS1),
+            "11" => Some(SslVersion::TLS1_1),
+            "12" => Some(SslVersion::TLS1_2),
+            "13" => Some(SslVersion::TLS1_3),
+            _ => return Err(invalid("unsupported TLS version")),
+        };
+        fingerprint.alpn = match &prefix[8..] {
+            "h2" => Some(&["h2", "http/1.1"][..]),
+            "h1" => Some(&["http/1.1"][..]),
+            "00" => None,
+            _ => return Err(invalid("unsupported ALPN protocol")),
+        };
+
+        let ciphers = list(ciphers)?;
+        if prefix[4..6].parse::<usize>().ok() != Some(ciphers.len()) {
+            return Err(invalid("the number of cipher suites doesn't match"));
+        }
+        fingerprint.set_ciphers(&ciphers)?;
+        fingerprint.set_extensions(&list(extensions)?);
+        for id in list(sigalgs)? {
+            match SIGALGS.iter().find(|&&(i, _)| i == id) {
+                Some((_, name)) => fingerprint.sigalgs.push(name),
+                None => return Err(unsupported("signature algorithm", id)),
+            }
+        }
+
+        Ok(fingerprint.into_builder())
+    }
+}
+
+fn unsupported(what: &str, id: u16) -> crate::Error {
+    error::builder(format!("unsupported {} 0x{:04x}", what, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME_JA3: &str = "771,4865-4866-4867-49195-49199-49196-49200-52393-52392-49171-49172-156-157-47-53,0-23-65281-10-11-35-16-5-13-18-51-45-43-27-17513,29-23-24,0";
+
+    #[test]
+    fn ja3() {
+        assert!(BoringTlsConnectorBuilder::from_ja3(CHROME_JA3)
+            .unwrap()
+            .grease(true)
+            .build()
+            .is_ok());
+
+        for invalid in [
+            "771,4865-4866,0-23",
+            "771,4865-x,0-23,29,0",
+            "771,65535,0-23,29,0",
+            "771,4865,0-23,256,0",
+            "771,4865,0-23,29,1",
+            "768,4865,0-23,29,0",
+        ] {
+            assert!(
+                BoringTlsConnectorBuilder::from_ja3(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn ja4() {
+        let raw = "t13d0510h2_1301,1302,c02b,c02f,00ff_0005,000a,000b,000d,0017,001b,0023,002b,002d,0033_0403,0804,0401";
+        assert!(BoringTlsConnectorBuilder::from_ja4(raw)
+            .unwrap()
+            .build()
+            .is_ok());
+
+        for invalid in [
+            "t13d1516h2_8daaf6152771_02713d6af862",
+            "t13d0410h2_1301,1302,c02b_000a_0403",
+            "t13d0110h3_1301_000a_0403",
+            "t13d0110h2_1301_000a_ffff",
+        ] {
+            assert!(
+                BoringTlsConnectorBuilder::from_ja4(invalid).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn grease() {
+        assert!(is_grease(0x0a0a));
+        assert!(is_grease(0xfafa));
+        assert!(!is_grease(0x0a1a));
+        assert!(!is_grease(0x1301));
+
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.set_ciphers(&[0x2a2a, 0x1301]).unwrap();
+        assert!(fingerprint.grease);
+        assert_eq!(fingerprint.ciphers, ["TLS_AES_128_GCM_SHA256"]);
+    }
+}
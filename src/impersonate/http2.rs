@@ -0,0 +1,651 @@
+//! HTTP/2 settings of an impersonation.
+
+use std::sync::Arc;
+
+use h2::profile::AgentProfile;
+
+use super::ClientProfile;
+use crate::error;
+use crate::http2::PrefaceShape;
+
+/// The largest flow control window, see RFC 9113 section 6.9.1.
+const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+/// The window of a connection before any `WINDOW_UPDATE`.
+const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+const MIN_FRAME_SIZE: u32 = 1 << 14;
+const MAX_STREAM_ID: u32 = (1 << 31) - 1;
+const MAX_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// The pseudo-header orders the HTTP/2 encoder can send, with the client
+/// profile whose requests it sends them in.
+///
+/// The encoder of h2 0.3 takes an `AgentProfile` rather than an order, so an
+/// order is sent by picking the profile that sends it. The shaper can't
+/// reorder the fields after encoding either, as that would move the entries
+/// they add to the HPACK table out from under later requests. Until the
+/// encoder takes an order, orders outside this table are refused.
+const PSEUDO_ORDERS: [([PseudoHeader; 4], ClientProfile); 3] = {
+    use PseudoHeader::*;
+    [
+        ([Method, Authority, Scheme, Path], ClientProfile::Chrome),
+        ([Method, Path, Authority, Scheme], ClientProfile::Firefox),
+        ([Method, Scheme, Path, Authority], ClientProfile::Safari),
+    ]
+};
+
+/// HTTP/2 settings.
+///
+/// Built with [`Http2Settings::builder`], or from the HTTP/2 fingerprint of a
+/// client with [`Http2Settings::from_akamai`], and applied with
+/// [`ClientBuilder::http2_settings`](crate::ClientBuilder::http2_settings).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Http2Settings {
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    max_concurrent_streams: Option<u32>,
+    max_header_list_size: Option<u32>,
+    header_table_size: Option<u32>,
+    enable_push: Option<bool>,
+    max_frame_size: Option<u32>,
+    settings_order: Vec<u16>,
+    priority: Vec<Http2Priority>,
+    headers_priority: Option<Http2Priority>,
+    headers_pseudo_order: Option<[PseudoHeader; 4]>,
+}
+
+/// A pseudo-header field of HTTP/2 requests, whose order tells browsers
+/// apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PseudoHeader {
+    /// `:method`, `m` in Akamai fingerprints.
+    Method,
+    /// `:authority`, `a` in Akamai fingerprints.
+    Authority,
+    /// `:scheme`, `s` in Akamai fingerprints.
+    Scheme,
+    /// `:path`, `p` in Akamai fingerprints.
+    Path,
+}
+
+/// A `PRIORITY` frame sent when a connection opens, before its first
+/// request, as Firefox sends to build its tree of idle streams, or the
+/// priority of the `HEADERS` frames of requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Http2Priority {
+    stream: u32,
+    exclusive: bool,
+    dependency: u32,
+    weight: u16,
+}
+
+/// A builder of [`Http2Settings`], checking them when built.
+#[derive(Clone, Debug, Default)]
+#[must_use]
+pub struct Http2SettingsBuilder {
+    settings: Http2Settings,
+}
+
+impl Http2Settings {
+    /// Start building settings, all unset.
+    pub fn builder() -> Http2SettingsBuilder {
+        Http2SettingsBuilder::default()
+    }
+
+    /// Parse the settings from an Akamai HTTP/2 fingerprint.
+    ///
+    /// A fingerprint is made of the `SETTINGS` of the client, its initial
+    /// `WINDOW_UPDATE`, its `PRIORITY` frames and its pseudo-header order,
+    /// separated by `|`, such as
+    /// `1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p`.
+    ///
+    /// The order of the `SETTINGS`, the `PRIORITY` frames, written as
+    /// `stream:exclusive:dependency:weight`, and the pseudo-header order
+    /// are kept as well.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the fingerprint is malformed, has a setting that isn't
+    /// supported or is repeated, or if the settings are invalid.
+    pub fn from_akamai(fingerprint: &str) -> crate::Result<Http2Settings> {
+        let invalid = |reason: &str| {
+            error::builder(format!(
+                "invalid Akamai fingerprint {:?}: {}",
+                fingerprint, reason
+            ))
+        };
+
+        let parts = fingerprint.trim().split('|').collect::<Vec<_>>();
+        let (settings, window_update, priority, pseudo_headers) = match parts[..] {
+            [a, b, c, d] => (a, b, c, d),
+            _ => return Err(invalid("expected 4 parts separated by `|`")),
+        };
+
+        let mut builder = Http2Settings::builder();
+        let mut seen = Vec::new();
+        for setting in settings.split([';', ',']).filter(|s| !s.is_empty()) {
+            let (id, value) = setting
+                .split_once(':')
+                .and_then(|(id, value)| Some((id.parse::<u16>().ok()?, value.parse::<u32>().ok()?)))
+                .ok_or_else(|| invalid(&format!("malformed setting {:?}", setting)))?;
+            if seen.contains(&id) {
+                return Err(invalid(&format!("setting {} is repeated", id)));
+            }
+            seen.push(id);
+            builder.settings.settings_order.push(id);
+            builder = match id {
+                1 => builder.header_table_size(value),
+                2 => match value {
+                    0 | 1 => builder.enable_push(value == 1),
+                    _ => return Err(invalid("ENABLE_PUSH must be 0 or 1")),
+                },
+                3 => builder.max_concurrent_streams(value),
+                4 => builder.initial_stream_window_size(value),
+                5 => builder.max_frame_size(value),
+                6 => builder.max_header_list_size(value),
+                _ => return Err(invalid(&format!("setting {} is not supported", id))),
+            };
+        }
+
+        let increment = window_update
+            .parse::<u32>()
+            .map_err(|_| invalid("malformed WINDOW_UPDATE"))?;
+        if increment != 0 {
+            let window = DEFAULT_WINDOW_SIZE
+                .checked_add(increment)
+                .ok_or_else(|| invalid("WINDOW_UPDATE is too large"))?;
+            builder = builder.initial_connection_window_size(window);
+        }
+
+        if priority != "0" {
+            for frame in priority.split(',') {
+                let fields = frame
+                    .split(':')
+                    .map(|field| field.parse::<u32>().ok())
+                    .collect::<Option<Vec<_>>>();
+                builder = match fields.as_deref() {
+                    Some(&[stream, exclusive @ (0 | 1), dependency, weight]) => {
+                        let weight = u16::try_from(weight).unwrap_or(u16::MAX);
+                        builder.priority(Http2Priority::new(
+                            stream,
+                            exclusive == 1,
+                            dependency,
+                            weight,
+                        ))
+                    }
+                    _ => return Err(invalid("malformed PRIORITY frames")),
+                };
+            }
+        }
+
+        let pseudo = pseudo_headers
+            .split(',')
+            .map(|name| match name {
+                "m" => Some(PseudoHeader::Method),
+                "a" => Some(PseudoHeader::Authority),
+                "s" => Some(PseudoHeader::Scheme),
+                "p" => Some(PseudoHeader::Path),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        let order = match pseudo.as_deref() {
+            Some(&[a, b, c, d]) if is_permutation([a, b, c, d]) => [a, b, c, d],
+            _ => return Err(invalid("pseudo-header order must list m, a, s and p once")),
+        };
+        builder = builder.headers_pseudo_order(order);
+
+        builder.build()
+    }
+
+    /// `SETTINGS_INITIAL_WINDOW_SIZE`, the flow control window of streams.
+    pub fn initial_stream_window_size(&self) -> Option<u32> {
+        self.initial_stream_window_size
+    }
+
+    /// The flow control window of connections.
+    pub fn initial_connection_window_size(&self) -> Option<u32> {
+        self.initial_connection_window_size
+    }
+
+    /// `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    pub fn max_concurrent_streams(&self) -> Option<u32> {
+        self.max_concurrent_streams
+    }
+
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub fn max_header_list_size(&self) -> Option<u32> {
+        self.max_header_list_size
+    }
+
+    /// `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn header_table_size(&self) -> Option<u32> {
+        self.header_table_size
+    }
+
+    /// `SETTINGS_ENABLE_PUSH`.
+    pub fn enable_push(&self) -> Option<bool> {
+        self.enable_push
+    }
+
+    /// `SETTINGS_MAX_FRAME_SIZE`.
+    pub fn max_frame_size(&self) -> Option<u32> {
+        self.max_frame_size
+    }
+
+    /// The increment of the `WINDOW_UPDATE` sent when a connection opens,
+    /// which grows its window from 65,535 to the initial connection window.
+    pub fn initial_window_update(&self) -> Option<u32> {
+        self.initial_connection_window_size
+            .map(|size| size - DEFAULT_WINDOW_SIZE)
+            .filter(|&increment| increment != 0)
+    }
+
+    /// The ids of the settings, in the order they are sent.
+    pub fn settings_order(&self) -> &[u16] {
+        &self.settings_order
+    }
+
+    /// The `PRIORITY` frames sent when a connection opens.
+    pub fn priority(&self) -> &[Http2Priority] {
+        &self.priority
+    }
+
+    /// The priority of the `HEADERS` frames of requests, whose stream is 0
+    /// for the stream of each request.
+    pub fn headers_priority(&self) -> Option<Http2Priority> {
+        self.headers_priority
+    }
+
+    /// Give the `HEADERS` frames of requests `priority`, unless they have
+    /// one.
+    pub(crate) fn or_headers_priority(&mut self, priority: Option<Http2Priority>) {
+        self.headers_priority = self.headers_priority.or(priority);
+    }
+
+    /// The order of the pseudo-header fields of requests.
+    pub fn headers_pseudo_order(&self) -> Option<[PseudoHeader; 4]> {
+        self.headers_pseudo_order
+    }
+
+    /// How the first frames of connections are shaped to match.
+    pub(crate) fn preface(&self) -> Arc<PrefaceShape> {
+        Arc::new(PrefaceShape {
+            settings_order: self.settings_order.clone(),
+            priority: self
+                .priority
+                .iter()
+                .map(|p| (p.stream, p.exclusive, p.dependency, p.weight))
+                .collect(),
+            headers_priority: self
+                .headers_priority
+                .map(|p| (p.exclusive, p.dependency, p.weight)),
+        })
+    }
+}
+
+impl Http2Priority {
+    /// A frame giving `stream` a `weight` from 1 to 256 and making it
+    /// depend on the stream `dependency`, or on none if it is 0.
+    ///
+    /// An `exclusive` dependency makes the stream the sole child of its
+    /// parent, the former children becoming its own.
+    pub fn new(stream: u32, exclusive: bool, dependency: u32, weight: u16) -> Http2Priority {
+        Http2Priority {
+            stream,
+            exclusive,
+            dependency,
+            weight,
+        }
+    }
+
+    /// Get the stream whose priority is set.
+    pub fn stream(&self) -> u32 {
+        self.stream
+    }
+
+    /// Whether the dependency is exclusive.
+    pub fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    /// Get the stream depended on, or 0 for none.
+    pub fn dependency(&self) -> u32 {
+        self.dependency
+    }
+
+    /// Get the weight, from 1 to 256.
+    pub fn weight(&self) -> u16 {
+        self.weight
+    }
+}
+
+impl Http2SettingsBuilder {
+    /// Set `SETTINGS_INITIAL_WINDOW_SIZE`, at most 2^31 - 1.
+    pub fn initial_stream_window_size(mut self, size: u32) -> Http2SettingsBuilder {
+        self.settings.initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// Set the flow control window of connections, from 65,535 to 2^31 - 1.
+    pub fn initial_connection_window_size(mut self, size: u32) -> Http2SettingsBuilder {
+        self.settings.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// Set `SETTINGS_MAX_CONCURRENT_STREAMS`.
+    pub fn max_concurrent_streams(mut self, max: u32) -> Http2SettingsBuilder {
+        self.settings.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// Set `SETTINGS_MAX_HEADER_LIST_SIZE`, which must not be zero.
+    pub fn max_header_list_size(mut self, size: u32) -> Http2SettingsBuilder {
+        self.settings.max_header_list_size = Some(size);
+        self
+    }
+
+    /// Set `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn header_table_size(mut self, size: u32) -> Http2SettingsBuilder {
+        self.settings.header_table_size = Some(size);
+        self
+    }
+
+    /// Set `SETTINGS_ENABLE_PUSH`.
+    pub fn enable_push(mut self, enabled: bool) -> Http2SettingsBuilder {
+        self.settings.enable_push = Some(enabled);
+        self
+    }
+
+    /// Set `SETTINGS_MAX_FRAME_SIZE`, from 2^14 to 2^24 - 1.
+    pub fn max_frame_size(mut self, size: u32) -> Http2SettingsBuilder {
+        self.settings.max_frame_size = Some(size);
+        self
+    }
+
+    /// Set the order the settings are sent in, by id, each at most once.
+    ///
+    /// Settings left out are sent after those listed.
+    pub fn settings_order<I>(mut self, order: I) -> Http2SettingsBuilder
+    where
+        I: IntoIterator<Item = u16>,
+    {
+        self.settings.settings_order = order.into_iter().collect();
+        self
+    }
+
+    /// Set the order of the pseudo-header fields of requests, such as
+    /// `:method`, `:authority`, `:scheme`, `:path` for Chrome.
+    ///
+    /// The HTTP/2 encoder sends the orders of Chrome, Firefox
+    /// (`:method`, `:path`, `:authority`, `:scheme`) and Safari
+    /// (`:method`, `:scheme`, `:path`, `:authority`), so others are
+    /// refused when built. By default, the order of the client profile of
+    /// the impersonation is sent.
+    pub fn headers_pseudo_order(mut self, order: [PseudoHeader; 4]) -> Http2SettingsBuilder {
+        self.settings.headers_pseudo_order = Some(order);
+        self
+    }
+
+    /// Add a `PRIORITY` frame to send when a connection opens, after the
+    /// frames added before.
+    pub fn priority(mut self, frame: Http2Priority) -> Http2SettingsBuilder {
+        self.settings.priority.push(frame);
+        self
+    }
+
+    /// Give the `HEADERS` frame of each request a `weight` from 1 to 256
+    /// and make its stream depend on the stream `dependency`, or on none if
+    /// it is 0, as Chrome does. By default, requests have no priority.
+    pub fn headers_priority(
+        mut self,
+        exclusive: bool,
+        dependency: u32,
+        weight: u16,
+    ) -> Http2SettingsBuilder {
+        self.settings.headers_priority = Some(Http2Priority::new(0, exclusive, dependency, weight));
+        self
+    }
+
+    /// Check and return the settings.
+    ///
+    /// # Errors
+    ///
+    /// This fails if a setting is out of its range, or if the orders or
+    /// `PRIORITY` frames are invalid.
+    pub fn build(self) -> crate::Result<Http2Settings> {
+        let settings = self.settings;
+        let invalid = |reason: String| {
+            Err(error::builder(format!(
+                "invalid HTTP/2 settings: {}",
+                reason
+            )))
+        };
+
+        if let Some(size) = settings.initial_stream_window_size {
+            if size > MAX_WINDOW_SIZE {
+                return invalid(format!("stream window size {} exceeds 2^31 - 1", size));
+            }
+        }
+        if let Some(size) = settings.initial_connection_window_size {
+            if !(DEFAULT_WINDOW_SIZE..=MAX_WINDOW_SIZE).contains(&size) {
+                return invalid(format!(
+                    "connection window size {} is not between 65535 and 2^31 - 1",
+                    size
+                ));
+            }
+        }
+        if let Some(size) = settings.max_frame_size {
+            if !(MIN_FRAME_SIZE..=MAX_FRAME_SIZE).contains(&size) {
+                return invalid(format!(
+                    "max frame size {} is not between 2^14 and 2^24 - 1",
+                    size
+                ));
+            }
+        }
+        if let Some(list) = settings.max_header_list_size {
+            if list == 0 {
+                return invalid("max header list size must not be zero".to_owned());
+            }
+        }
+        if let Some(order) = settings.headers_pseudo_order {
+            if !is_permutation(order) {
+                return invalid(format!(
+                    "pseudo-header order {:?} must list each field once",
+                    order
+                ));
+            }
+            if !PSEUDO_ORDERS.iter().any(|(sent, _)| *sent == order) {
+                return invalid(format!(
+                    "pseudo-header order {:?} can't be sent by the HTTP/2 encoder",
+                    order
+                ));
+            }
+        }
+        for (i, id) in settings.settings_order.iter().enumerate() {
+            if settings.settings_order[..i].contains(id) {
+                return invalid(format!("setting {} is ordered twice", id));
+            }
+        }
+        for frame in &settings.priority {
+            if frame.stream == 0 || frame.stream > MAX_STREAM_ID {
+                return invalid(format!("invalid PRIORITY stream {}", frame.stream));
+            }
+            if frame.dependency == frame.stream || frame.dependency > MAX_STREAM_ID {
+                return invalid(format!(
+                    "stream {} can't depend on stream {}",
+                    frame.stream, frame.dependency
+                ));
+            }
+            if !(1..=256).contains(&frame.weight) {
+                return invalid(format!(
+                    "PRIORITY weight {} is not between 1 and 256",
+                    frame.weight
+                ));
+            }
+        }
+        if let Some(priority) = settings.headers_priority {
+            if priority.dependency > MAX_STREAM_ID {
+                return invalid(format!(
+                    "requests can't depend on stream {}",
+                    priority.dependency
+                ));
+            }
+            if !(1..=256).contains(&priority.weight) {
+                return invalid(format!(
+                    "HEADERS weight {} is not between 1 and 256",
+                    priority.weight
+                ));
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// Whether `order` lists each pseudo-header field once.
+fn is_permutation(order: [PseudoHeader; 4]) -> bool {
+    (0..4).all(|i| !order[..i].contains(&order[i]))
+}
+
+/// The HTTP/2 profile of the encoder for `profile`, or for the client
+/// profile whose pseudo-header order is `order`, if another.
+pub(crate) fn agent_profile(
+    profile: ClientProfile,
+    order: Option<[PseudoHeader; 4]>,
+) -> AgentProfile {
+    let sends = |order: [PseudoHeader; 4]| {
+        PSEUDO_ORDERS.iter().any(|&(sent, sender)| {
+            sent == order
+                && matches!(
+                    (sender, profile),
+                    (
+                        ClientProfile::Chrome,
+                        ClientProfile::Chrome | ClientProfile::Edge
+                    ) | (
+                        ClientProfile::Firefox,
+                        ClientProfile::Firefox | ClientProfile::OkHttp
+                    ) | (ClientProfile::Safari, ClientProfile::Safari)
+                )
+        })
+    };
+    match order {
+        Some(order) if !sends(order) => PSEUDO_ORDERS
+            .iter()
+            .find(|&&(sent, _)| sent == order)
+            .map_or(profile, |&(_, sender)| sender)
+            .into(),
+        _ => profile.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_akamai() {
+        let settings =
+            Http2Settings::from_akamai("1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p")
+                .unwrap();
+        assert_eq!(
+            settings,
+            Http2Settings::builder()
+                .header_table_size(65536)
+                .enable_push(false)
+                .initial_stream_window_size(6291456)
+                .max_header_list_size(262144)
+                .initial_connection_window_size(15728640)
+                .settings_order([1, 2, 4, 6])
+                .headers_pseudo_order([
+                    PseudoHeader::Method,
+                    PseudoHeader::Authority,
+                    PseudoHeader::Scheme,
+                    PseudoHeader::Path,
+                ])
+                .build()
+                .unwrap()
+        );
+
+        let firefox = Http2Settings::from_akamai(
+            "1:65536;4:131072;5:16384|12517377|3:0:0:201,5:0:0:101|m,p,a,s",
+        )
+        .unwrap();
+        assert_eq!(firefox.max_frame_size(), Some(16384));
+        assert_eq!(firefox.initial_connection_window_size(), Some(12582912));
+        assert_eq!(firefox.initial_window_update(), Some(12517377));
+        assert_eq!(firefox.settings_order(), [1, 4, 5]);
+        assert!(matches!(
+            agent_profile(ClientProfile::Chrome, firefox.headers_pseudo_order()),
+            AgentProfile::Firefox
+        ));
+        assert!(matches!(
+            agent_profile(ClientProfile::OkHttp, firefox.headers_pseudo_order()),
+            AgentProfile::OkHttp
+        ));
+        assert_eq!(
+            firefox.priority(),
+            [
+                Http2Priority::new(3, false, 0, 201),
+                Http2Priority::new(5, false, 0, 101),
+            ]
+        );
+
+        for invalid in [
+            "1:65536;4:6291456|15663105|0",
+            "1:65536;1:4096|0|0|m,a,s,p",
+            "8:1|0|0|m,a,s,p",
+            "2:2|0|0|m,a,s,p",
+            "1:x|0|0|m,a,s,p",
+            "4:6291456|0|0|m,a,s",
+            "4:6291456|0|1:0|m,a,s,p",
+            "4:6291456|0|3:2:0:201|m,a,s,p",
+            "4:6291456|0|3:0:0:0|m,a,s,p",
+            "4:6291456|0|3:0:3:16|m,a,s,p",
+            "4:6291456|0|0|m,a,s,s",
+            "4:6291456|0|0|m,s,a,p",
+        ] {
+            assert!(Http2Settings::from_akamai(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn validates_ranges() {
+        let build = |builder: Http2SettingsBuilder| builder.build().is_ok();
+        assert!(build(Http2Settings::builder()));
+        assert!(!build(
+            Http2Settings::builder().initial_stream_window_size(1 << 31)
+        ));
+        assert!(!build(
+            Http2Settings::builder().initial_connection_window_size(1024)
+        ));
+        // Servers take stream windows larger than the connection window.
+        assert!(build(
+            Http2Settings::builder()
+                .initial_stream_window_size(1 << 20)
+                .initial_connection_window_size(1 << 16)
+        ));
+        assert!(!build(Http2Settings::builder().max_frame_size(1024)));
+        assert!(!build(Http2Settings::builder().max_header_list_size(0)));
+        assert!(build(
+            Http2Settings::builder()
+                .header_table_size(65536)
+                .max_header_list_size(4096)
+        ));
+        assert!(!build(Http2Settings::builder().settings_order([1, 4, 1])));
+        assert!(!build(
+            Http2Settings::builder().priority(Http2Priority::new(0, false, 0, 16))
+        ));
+        assert!(!build(
+            Http2Settings::builder().priority(Http2Priority::new(3, false, 0, 257))
+        ));
+        assert!(build(
+            Http2Settings::builder().headers_priority(true, 0, 256)
+        ));
+        assert!(!build(
+            Http2Settings::builder().headers_priority(true, 0, 0)
+        ));
+        assert!(!build(Http2Settings::builder().headers_priority(
+            false,
+            1 << 31,
+            16
+        )));
+    }
+}
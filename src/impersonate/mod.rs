@@ -1,77 +1,279 @@
 #![allow(missing_debug_implementations)]
 
+mod capabilities;
 mod chrome;
+mod client_hello;
+mod coherence;
+mod ech;
 mod edge;
 pub mod extension;
+mod fingerprint;
+mod http2;
 mod okhttp;
+pub(crate) mod pinning;
 mod profile;
+mod randomized;
+mod registry;
 mod safari;
+mod session;
+mod spec;
 
 use crate::connect::HttpConnector;
 use crate::impersonate::extension::{SslConnectExtension, SslExtension};
+use crate::tls::Identity;
 use antidote::Mutex;
-#[cfg(feature = "socks")]
-use boring::ssl::Ssl;
 use boring::{
     error::ErrorStack,
-    ssl::{ConnectConfiguration, SslConnectorBuilder},
+    hash::MessageDigest,
+    ssl::{ConnectConfiguration, SslConnectorBuilder, SslSessionCacheMode},
+    x509::X509,
 };
-use hyper_boring::{HttpsConnector, HttpsLayerSettings, SessionCache};
-pub(crate) use profile::configure_impersonate;
-use profile::ClientProfile;
-pub use profile::{Http2Settings, Impersonate, ImpersonateSettings};
+pub use capabilities::{capabilities, Capabilities};
+pub use client_hello::BoringTlsConnectorBuilder;
+pub use coherence::Incoherence;
+pub(crate) use ech::EchConfigs;
+pub(crate) use http2::agent_profile;
+pub use http2::{Http2Priority, Http2Settings, Http2SettingsBuilder, PseudoHeader};
+use hyper_boring::{HttpsConnector, HttpsLayer};
+pub(crate) use profile::{
+    configure_impersonate, configure_impersonate_headers, configure_impersonate_tls, get_settings,
+    head_writes, header_order, headers_priority,
+};
+pub use profile::{ClientProfile, Custom, Impersonate, ImpersonateSettings};
+pub use session::{MemorySessionStore, TlsSessionKey, TlsSessionStore};
+pub use spec::{ProfileSpec, TlsBuilder};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::OnceCell;
+use tower_layer::Layer;
 
 type Builder = dyn Fn() -> Result<SslConnectorBuilder, ErrorStack> + Send + Sync;
 
 /// Context for impersonating a client.
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub(crate) struct ImpersonateContext {
     pub impersonate: Impersonate,
     pub enable_ech_grease: bool,
     pub permute_extensions: bool,
     pub certs_verification: bool,
     pub pre_shared_key: bool,
+    pub post_quantum: Option<bool>,
     pub h2: bool,
+    pub session: SessionPartition,
 }
 
-const DEFAULT_SESSION_CACHE_CAPACITY: usize = 8;
+/// Who a TLS session may be resumed by.
+///
+/// Sessions are cached by server name and port within a partition, so a
+/// ticket is never offered through another proxy, or by a client with
+/// another partition id.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct SessionPartition {
+    /// The identity of the proxy connections go through.
+    pub proxy: Option<String>,
+    /// The id set with `ClientBuilder::tls_session_partition`.
+    pub id: Option<String>,
+}
 
-type Session = Arc<Mutex<SessionCache>>;
+/// Contexts whose layers are kept, beyond which they are all dropped, so
+/// that rotating through many proxies doesn't grow the cache without
+/// bound.
+const MAX_PARTITIONS: usize = 256;
 
 /// A wrapper around a `SslConnectorBuilder` that allows for additional settings.
 #[derive(Clone)]
 pub struct BoringTlsConnector {
     /// The inner `SslConnectorBuilder`.
     builder: Arc<Builder>,
-    /// The cached `HttpsConnector` sessions.
-    session: Arc<OnceCell<Session>>,
+    /// The TLS sessions resumed by the layers, keyed by partition.
+    sessions: Arc<dyn TlsSessionStore>,
+    /// The ECH configurations offered to servers, if ECH is enabled.
+    ech: Option<Arc<EchConfigs>>,
+    /// The client certificate presented to servers asking for one.
+    identity: Option<Identity>,
+    /// Root certificates trusted besides those of the builder.
+    root_certs: Arc<[X509]>,
+    /// The public keys servers must present, by host.
+    pins: Arc<[pinning::Pin]>,
+    /// The configured TLS layers, by context.
+    ///
+    /// Running `builder` parses cipher lists and sets up extensions, so it
+    /// is done once per context rather than once per connection. Guarded by
+    /// a blocking lock, so that a connector isn't tied to the runtime it is
+    /// first used in.
+    layers: Arc<Mutex<HashMap<ImpersonateContext, HttpsLayer>>>,
+    /// The ALPN protocols set with [`BoringTlsConnectorBuilder::alpn_protos`].
+    alpn_protos: Option<Arc<[String]>>,
 }
 
 impl BoringTlsConnector {
     /// Create a new `BoringTlsConnector` with the given function.
+    ///
+    /// The function is called the first time a connection is made with a
+    /// given set of settings, and the configured connector is reused for the
+    /// following ones.
     pub fn new<F>(builder: F) -> BoringTlsConnector
     where
         F: Fn() -> Result<SslConnectorBuilder, ErrorStack> + Send + Sync + 'static,
     {
         Self {
             builder: Arc::new(builder),
-            session: Arc::new(OnceCell::new()),
+            sessions: Arc::new(MemorySessionStore::new()),
+            ech: None,
+            identity: None,
+            root_certs: Arc::new([]),
+            pins: Arc::new([]),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            alpn_protos: None,
+        }
+    }
+
+    /// A connector with the same settings, and caches of its own.
+    pub(crate) fn fresh(&self) -> BoringTlsConnector {
+        Self {
+            builder: self.builder.clone(),
+            sessions: Arc::new(MemorySessionStore::new()),
+            ech: self.ech.clone(),
+            identity: self.identity.clone(),
+            root_certs: self.root_certs.clone(),
+            pins: self.pins.clone(),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            alpn_protos: self.alpn_protos.clone(),
+        }
+    }
+
+    /// A connector with the same settings, keeping its TLS sessions in
+    /// `store`.
+    pub(crate) fn with_session_store(&self, store: Arc<dyn TlsSessionStore>) -> BoringTlsConnector {
+        BoringTlsConnector {
+            sessions: store,
+            ..self.fresh()
+        }
+    }
+
+    /// A connector with the same settings, offering ECH with `configs`.
+    pub(crate) fn with_ech(&self, configs: Arc<EchConfigs>) -> BoringTlsConnector {
+        BoringTlsConnector {
+            ech: Some(configs),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            ..self.clone()
+        }
+    }
+
+    /// A connector with the same settings, presenting `identity` to servers
+    /// asking for a client certificate.
+    pub(crate) fn with_identity(&self, identity: Identity) -> BoringTlsConnector {
+        BoringTlsConnector {
+            identity: Some(identity),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            ..self.clone()
+        }
+    }
+
+    /// A connector with the same settings, also trusting the root
+    /// certificates `certs`.
+    pub(crate) fn with_root_certs(&self, certs: Vec<X509>) -> BoringTlsConnector {
+        BoringTlsConnector {
+            root_certs: self.root_certs.iter().cloned().chain(certs).collect(),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            ..self.clone()
+        }
+    }
+
+    /// A connector with the same settings, accepting only the public keys
+    /// of `pins` from their hosts.
+    pub(crate) fn with_pins(&self, pins: Vec<pinning::Pin>) -> BoringTlsConnector {
+        BoringTlsConnector {
+            pins: self.pins.iter().cloned().chain(pins).collect(),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            ..self.clone()
+        }
+    }
+
+    /// `profile`, with the client certificate, the root certificates and
+    /// the pins of this connector, which belong to the client rather than
+    /// to a profile.
+    pub(crate) fn carry_certs_to(&self, profile: BoringTlsConnector) -> BoringTlsConnector {
+        BoringTlsConnector {
+            identity: self.identity.clone(),
+            pins: self.pins.clone(),
+            root_certs: profile
+                .root_certs
+                .iter()
+                .chain(self.root_certs.iter())
+                .cloned()
+                .collect(),
+            layers: Arc::new(Mutex::new(HashMap::new())),
+            ..profile
         }
     }
 
+    /// The ECH configurations offered to servers, if ECH is enabled.
+    pub(crate) fn ech(&self) -> Option<&Arc<EchConfigs>> {
+        self.ech.as_ref()
+    }
+
     /// Create a new `HttpsConnector` with the settings from the `ImpersonateContext`.
     #[inline]
-    pub(crate) async fn create_connector(
+    pub(crate) fn create_connector(
         &self,
         context: &ImpersonateContext,
         http: HttpConnector,
     ) -> Result<HttpsConnector<HttpConnector>, ErrorStack> {
+        Ok(self.layer(context)?.layer(http))
+    }
+
+    /// The `HttpsLayer` with the settings from the `ImpersonateContext`.
+    pub(crate) fn layer(&self, context: &ImpersonateContext) -> Result<HttpsLayer, ErrorStack> {
+        if let Some(layer) = self.layers.lock().get(context) {
+            return Ok(layer.clone());
+        }
+
+        let layer = self.create_layer(context)?;
+        let mut layers = self.layers.lock();
+        if layers.len() >= MAX_PARTITIONS {
+            layers.clear();
+        }
+        layers.insert(context.clone(), layer.clone());
+        Ok(layer)
+    }
+
+    /// Create a new `HttpsLayer` with the settings from the `ImpersonateContext`.
+    fn create_layer(&self, context: &ImpersonateContext) -> Result<HttpsLayer, ErrorStack> {
         // Create the `SslConnectorBuilder` and configure it.
-        let builder = (self.builder)()?
-            .configure_alpn_protos(context.h2)?
-            .configure_cert_verification(context.certs_verification)?;
+        let mut builder = (self.builder)()?;
+        // Offer the protocols of the connector, unless none is left for
+        // HTTP/1 connections.
+        let alpn_protos = self
+            .alpn_protos
+            .as_deref()
+            .map(|protos| client_hello::encode_alpn(protos, context.h2))
+            .filter(|protos| !protos.is_empty());
+        match alpn_protos {
+            Some(protos) => builder.set_alpn_protos(&protos)?,
+            None => builder = builder.configure_alpn_protos(context.h2)?,
+        }
+        let mut builder = builder.configure_cert_verification(context.certs_verification)?;
+        if let Some(ref identity) = self.identity {
+            identity.add_to_boring(&mut builder)?;
+        }
+        for cert in self.root_certs.iter() {
+            // A certificate the store already has is skipped.
+            let _ = builder.cert_store_mut().add_cert(cert.clone());
+        }
+
+        // Override whether the post-quantum key share of Chromium is offered.
+        if let Some(post_quantum) = context.post_quantum {
+            if matches!(
+                context.impersonate.profile(),
+                ClientProfile::Chrome | ClientProfile::Edge
+            ) {
+                builder = if context.impersonate.offers_ml_kem() {
+                    builder.configure_chrome_mlkem_curves(post_quantum)?
+                } else {
+                    builder.configure_chrome_pq_curves(post_quantum)?
+                };
+            }
+        }
 
         // Check if the PSK extension should be enabled.
         let psk_extension = matches!(
@@ -83,57 +285,81 @@ impl BoringTlsConnector {
                 | Impersonate::Chrome124
                 | Impersonate::Chrome126
                 | Impersonate::Chrome127
+                | Impersonate::Chrome128
+                | Impersonate::Chrome129
+                | Impersonate::Chrome130
+                | Impersonate::Chrome131
                 | Impersonate::Cronet
+                | Impersonate::AndroidWebView13
+                | Impersonate::AndroidWebView14
+                | Impersonate::AndroidWebView15
                 | Impersonate::Edge122
                 | Impersonate::Edge127
+                | Impersonate::Edge128
+                | Impersonate::Edge129
+                | Impersonate::Edge130
+                | Impersonate::Edge131
+                | Impersonate::Randomized
         );
 
-        // Create the `HttpsConnector` with the given settings.
-        let mut http = if psk_extension || context.pre_shared_key {
-            // Initialize the session cache.
-            let session = self
-                .session
-                .get_or_init(|| async {
-                    Session::new(Mutex::new(SessionCache::with_capacity(
-                        DEFAULT_SESSION_CACHE_CAPACITY,
-                    )))
-                })
-                .await
-                .clone();
-
-            HttpsConnector::with_connector_and_settings(
-                http,
-                builder,
-                HttpsLayerSettings::builder()
-                    .session_cache_capacity(DEFAULT_SESSION_CACHE_CAPACITY)
-                    .session_cache(session)
-                    .build(),
-            )?
-        } else {
-            HttpsConnector::with_connector(http, builder)?
-        };
-
-        // Set the callback to add application settings.
+        // Keep the sessions issued to connections in the store, under the
+        // key the connection was tagged with.
+        let resumption = psk_extension || context.pre_shared_key;
+        if resumption {
+            let sessions = self.sessions.clone();
+            let index = session::key_index()?;
+            builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+            builder.set_new_session_callback(move |ssl, session| {
+                if let (Some(key), Ok(der)) = (ssl.ex_data(index), session.to_der()) {
+                    sessions.put(key, der);
+                }
+            });
+        }
+
+        if self.ech.is_some() {
+            ech::watch_rejections(&mut builder)?;
+        }
+
+        let mut layer = HttpsLayer::with_connector(builder)?;
+
+        // Set the callback to add application settings, to offer the ECH
+        // configuration of the server, to check the pins of the server, and
+        // to resume the session of the partition with the server.
+        let trust = self.trust(context.certs_verification)?;
         let context = context.clone();
-        http.set_callback(move |conf, _| {
+        let sessions = self.sessions.clone();
+        let ech = self.ech.clone();
+        let pins = self.pins.clone();
+        layer.set_callback(move |conf, uri| {
+            crate::timing::mark_tls_started();
             configure_ssl_context(conf, &context);
+            if context.certs_verification && !pins.is_empty() {
+                pinning::enforce(conf, uri, &pins);
+            }
+            if let Some(ref ech) = ech {
+                ech::offer(conf, uri, ech)?;
+            }
+            if resumption {
+                session::resume(conf, uri, &context.session, &trust, &*sessions)?;
+            }
             Ok(())
         });
-        Ok(http)
+        Ok(layer)
     }
 
-    /// Create a new `SslConnector` with the settings from the `ImpersonateContext`.
-    #[cfg(feature = "socks")]
-    #[inline]
-    pub(crate) async fn create_ssl(
-        &self,
-        context: &ImpersonateContext,
-        http: HttpConnector,
-        uri: &http::uri::Uri,
-        host: &str,
-    ) -> Result<Ssl, ErrorStack> {
-        let connector = self.create_connector(context, http).await?;
-        connector.setup_ssl(uri, host)
+    /// A digest of how the layers verify servers, so that a session issued
+    /// under one configuration isn't resumed under another.
+    fn trust(&self, certs_verification: bool) -> Result<String, ErrorStack> {
+        let mut trust = vec![u8::from(certs_verification)];
+        for cert in self.root_certs.iter() {
+            trust.extend_from_slice(&cert.digest(MessageDigest::sha256())?);
+        }
+        for (host, key) in self.pins.iter() {
+            trust.extend_from_slice(host.as_bytes());
+            trust.push(0);
+            trust.extend_from_slice(key);
+        }
+        Ok(crate::proxy::digest(&trust))
     }
 }
 
@@ -143,7 +369,8 @@ fn configure_ssl_context(conf: &mut ConnectConfiguration, ctx: &ImpersonateConte
         ctx.impersonate.profile(),
         ClientProfile::Chrome | ClientProfile::Edge
     ) {
-        conf.configure_permute_extensions(ctx.permute_extensions)
+        let permute_extensions = ctx.permute_extensions || ctx.impersonate.permutes_extensions();
+        conf.configure_permute_extensions(permute_extensions)
             .configure_enable_ech_grease(ctx.enable_ech_grease)
             .configure_add_application_settings(ctx.h2);
     }
@@ -1,7 +1,189 @@
-pub mod okhttp3_11;
-pub mod okhttp3_13;
-pub mod okhttp3_14;
-pub mod okhttp3_9;
-pub mod okhttp4_10;
-pub mod okhttp4_9;
-pub mod okhttp5;
+use crate::impersonate::extension::{Extension, OkHttpExtension, SslExtension};
+use crate::impersonate::{Impersonate, ProfileSpec};
+use boring::{error::ErrorStack, ssl::SslConnectorBuilder};
+
+const CIPHER_LIST_3_9: [&str; 15] = [
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+];
+
+const CIPHER_LIST_3_11: [&str; 13] = [
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+];
+
+const CIPHER_LIST_3_13: [&str; 18] = [
+    "TLS_AES_128_GCM_SHA256",
+    "TLS_AES_256_GCM_SHA384",
+    "TLS_CHACHA20_POLY1305_SHA256",
+    "TLS_AES_128_CCM_SHA256",
+    "TLS_AES_256_CCM_8_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+];
+
+const CIPHER_LIST_3_14: [&str; 16] = [
+    "TLS_AES_128_GCM_SHA256",
+    "TLS_AES_256_GCM_SHA384",
+    "TLS_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
+];
+
+const CIPHER_LIST_4_9: [&str; 15] = [
+    "TLS_AES_128_GCM_SHA256",
+    "TLS_AES_256_GCM_SHA384",
+    "TLS_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_RSA_WITH_AES_256_CBC_SHA",
+];
+
+fn tls_3_9() -> Result<SslConnectorBuilder, ErrorStack> {
+    OkHttpExtension::builder()?.configure_cipher_list(&CIPHER_LIST_3_9)
+}
+
+fn tls_3_11() -> Result<SslConnectorBuilder, ErrorStack> {
+    OkHttpExtension::builder()?.configure_cipher_list(&CIPHER_LIST_3_11)
+}
+
+fn tls_3_13() -> Result<SslConnectorBuilder, ErrorStack> {
+    OkHttpExtension::builder()?.configure_cipher_list(&CIPHER_LIST_3_13)
+}
+
+fn tls_3_14() -> Result<SslConnectorBuilder, ErrorStack> {
+    OkHttpExtension::builder()?.configure_cipher_list(&CIPHER_LIST_3_14)
+}
+
+fn tls_4_9() -> Result<SslConnectorBuilder, ErrorStack> {
+    OkHttpExtension::builder()?.configure_cipher_list(&CIPHER_LIST_4_9)
+}
+
+pub(crate) static V3_9: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls_3_9),
+    http2: Some("4:16777216|16711681|0|m,p,a,s"),
+    headers: &[
+        ("accept", Some("*/*")),
+        ("accept-language", Some("de-DE,de;q=0.9,en-US;q=0.8,en;q=0.7")),
+        ("user-agent", Some("MaiMemo/4.4.50_639 okhttp/3.9 Android/5.0 Channel/WanDouJia Device/alps+M8+Emulator (armeabi-v7a) Screen/4.44 Resolution/480x800 DId/aa6cde19def3806806d5374c4e5fd617 RAM/0.94 ROM/4.91 Theme/Day")),
+        ("accept-encoding", Some("gzip, deflate, br")),
+    ],
+};
+
+pub(crate) static V3_11: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp3_9),
+    tls: Some(tls_3_11),
+    http2: None,
+    headers: &[
+        ("user-agent", Some("NRC Audio/2.0.6 (nl.nrc.audio; build:36; Android 12; Sdk:31; Manufacturer:motorola; Model: moto g72) OkHttp/3.11.0")),
+    ],
+};
+
+pub(crate) static V3_13: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp3_11),
+    tls: Some(tls_3_13),
+    http2: None,
+    headers: &[
+        ("user-agent", Some("GM-Android/6.112.2 (240590300; M:Google Pixel 7a; O:34; D:2b045e03986fa6dc) ObsoleteUrlFactory/1.0 OkHttp/3.13.0")),
+    ],
+};
+
+pub(crate) static V3_14: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp3_13),
+    tls: Some(tls_3_14),
+    http2: None,
+    headers: &[
+        ("accept-language", Some("en-US,en;q=0.9")),
+        ("user-agent", Some("DS podcast/2.0.1 (be.standaard.audio; build:9; Android 11; Sdk:30; Manufacturer:samsung; Model: SM-A405FN) OkHttp/3.14.0")),
+    ],
+};
+
+pub(crate) static V4_9: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp3_13),
+    tls: Some(tls_4_9),
+    http2: None,
+    headers: &[
+        ("user-agent", Some("GM-Android/6.111.1 (240460200; M:motorola moto g power (2021); O:30; D:76ba9f6628d198c8) ObsoleteUrlFactory/1.0 OkHttp/4.9")),
+    ],
+};
+
+pub(crate) static V4_10: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp4_9),
+    tls: Some(tls_3_14),
+    http2: None,
+    headers: &[
+        ("user-agent", Some("GM-Android/6.112.2 (240590300; M:samsung SM-G781U1; O:33; D:edb34792871638d8) ObsoleteUrlFactory/1.0 OkHttp/4.10.0")),
+    ],
+};
+
+pub(crate) static V5: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp3_14),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("user-agent", Some("NRC Audio/2.0.6 (nl.nrc.audio; build:36; Android 14; Sdk:34; Manufacturer:OnePlus; Model: CPH2609) OkHttp/5.0.0-alpha2")),
+    ],
+};
+
+pub(crate) static V5_1: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::OkHttp5),
+    tls: Some(tls_4_9),
+    http2: None,
+    headers: &[("user-agent", Some("okhttp/5.1.0"))],
+};
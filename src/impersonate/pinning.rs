@@ -0,0 +1,49 @@
+//! Pinning the public keys of servers.
+
+use boring::error::ErrorStack;
+use boring::ssl::{ConnectConfiguration, SslVerifyMode};
+use boring::x509::{X509Ref, X509VerifyResult};
+use http::Uri;
+
+/// A host and the SHA-256 digest of a public key it must present.
+pub(crate) type Pin = (String, [u8; 32]);
+
+/// Accept the connection to the host of `uri` only if a certificate of its
+/// verified chain has a public key pinned for the host, if any is.
+pub(super) fn enforce(conf: &mut ConnectConfiguration, uri: &Uri, pins: &[Pin]) {
+    let host = match uri.host() {
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => return,
+    };
+    let digests = pins
+        .iter()
+        .filter(|(pinned, _)| pinned.eq_ignore_ascii_case(host))
+        .map(|(_, digest)| *digest)
+        .collect::<Vec<_>>();
+    if digests.is_empty() {
+        return;
+    }
+
+    conf.set_verify_callback(SslVerifyMode::PEER, move |verified, ctx| {
+        // The leaf comes last, once the chain is built and verified.
+        if !verified || ctx.error_depth() != 0 {
+            return verified;
+        }
+        let pinned = ctx.chain().map_or(false, |chain| {
+            chain
+                .iter()
+                .any(|cert| spki_sha256(cert).map_or(false, |digest| digests.contains(&digest)))
+        });
+        if !pinned {
+            log::debug!("no certificate of the chain has a pinned public key");
+            ctx.set_error(X509VerifyResult::APPLICATION_VERIFICATION);
+        }
+        pinned
+    });
+}
+
+/// The SHA-256 digest of the DER `SubjectPublicKeyInfo` of `cert`.
+pub(crate) fn spki_sha256(cert: &X509Ref) -> Result<[u8; 32], ErrorStack> {
+    let der = cert.public_key()?.public_key_to_der()?;
+    Ok(boring::sha::sha256(&der))
+}
@@ -1,95 +1,147 @@
 #![allow(missing_docs)]
 
-use super::BoringTlsConnector;
+use super::{BoringTlsConnector, Http2Priority, Http2Settings, ProfileSpec};
+use crate::http1::HeadWrites;
 use crate::{
-    impersonate::{chrome, edge, okhttp, safari},
+    impersonate::{chrome, edge, okhttp, randomized, registry, safari},
     ClientBuilder,
 };
 use h2::profile::AgentProfile;
-use http::HeaderMap;
+use http::{header::COOKIE, HeaderMap, HeaderName};
 use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 
 /// Configure the client to impersonate the given version
 pub(crate) fn configure_impersonate(ver: Impersonate, builder: ClientBuilder) -> ClientBuilder {
     let settings = get_settings(ver);
+    let builder = apply_tls(
+        builder,
+        settings.tls_connector,
+        settings.http2,
+        ver.profile(),
+    )
+    .http1_head_writes(head_writes(ver.profile()));
+    apply_headers(builder, settings.headers, settings.gzip, settings.brotli)
+}
+
+/// Configure the client with the headers of the given version only
+pub(crate) fn configure_impersonate_headers(
+    ver: Impersonate,
+    builder: ClientBuilder,
+) -> ClientBuilder {
+    let settings = get_settings(ver);
+    apply_headers(builder, settings.headers, settings.gzip, settings.brotli)
+}
+
+/// Configure the client with the TLS and HTTP/2 settings of the given version only
+pub(crate) fn configure_impersonate_tls(ver: Impersonate, builder: ClientBuilder) -> ClientBuilder {
+    let settings = get_settings(ver);
+    apply_tls(
+        builder,
+        settings.tls_connector,
+        settings.http2,
+        ver.profile(),
+    )
+    .http1_head_writes(head_writes(ver.profile()))
+}
+
+fn apply_tls(
+    builder: ClientBuilder,
+    tls_connector: BoringTlsConnector,
+    mut http2: Http2Settings,
+    profile: ClientProfile,
+) -> ClientBuilder {
+    http2.or_headers_priority(headers_priority(profile));
+    builder.use_boring_tls(tls_connector).http2_settings(http2)
+}
+
+fn apply_headers(
+    builder: ClientBuilder,
+    headers: Arc<HeaderMap>,
+    gzip: bool,
+    brotli: bool,
+) -> ClientBuilder {
+    let order = header_order(&headers);
     builder
-        .use_boring_tls(settings.tls_connector)
-        .http2_initial_stream_window_size(settings.http2.initial_stream_window_size)
-        .http2_initial_connection_window_size(settings.http2.initial_connection_window_size)
-        .http2_max_concurrent_streams(settings.http2.max_concurrent_streams)
-        .http2_max_header_list_size(settings.http2.max_header_list_size)
-        .http2_header_table_size(settings.http2.header_table_size)
-        .http2_enable_push(settings.http2.enable_push)
-        .replace_default_headers(settings.headers)
-        .brotli(settings.brotli)
-        .gzip(settings.gzip)
+        .replace_default_headers(headers)
+        .header_order(order)
+        .brotli(brotli)
+        .gzip(gzip)
 }
 
-macro_rules! impersonate_match {
-    ($ver:expr, $headers:expr, $($variant:pat => $path:path),+) => {
-        match $ver {
-            $(
-                $variant => $path($headers),
-            )+
+/// How a browser writes the head and body of HTTP/1 requests.
+pub(crate) fn head_writes(profile: ClientProfile) -> HeadWrites {
+    match profile {
+        // `HttpStreamParser` merges bodies in memory into the write of the
+        // head when both fit in 1400 bytes.
+        ClientProfile::Chrome | ClientProfile::Edge => HeadWrites::MergeUpTo(1400),
+        ClientProfile::OkHttp | ClientProfile::Safari | ClientProfile::Firefox => {
+            HeadWrites::Coalesced
         }
     }
 }
 
+/// The priority a browser gives the `HEADERS` frames of its requests.
+pub(crate) fn headers_priority(profile: ClientProfile) -> Option<Http2Priority> {
+    match profile {
+        // Documents are fetched at the highest priority, each request
+        // taking the place of the others at the root.
+        ClientProfile::Chrome | ClientProfile::Edge => Some(Http2Priority::new(0, true, 0, 256)),
+        ClientProfile::OkHttp | ClientProfile::Safari | ClientProfile::Firefox => None,
+    }
+}
+
+/// The order a browser sends `headers` in: its own headers in a fixed
+/// order, then the cookies.
+pub(crate) fn header_order(headers: &HeaderMap) -> Vec<HeaderName> {
+    let mut order = headers.keys().cloned().collect::<Vec<_>>();
+    order.push(COOKIE);
+    order
+}
+
 /// Get the settings for the given impersonate version
-fn get_settings(ver: Impersonate) -> ImpersonateSettings {
-    impersonate_match!(
-        ver,
-        HeaderMap::new(),
-        Impersonate::Chrome100 => chrome::v100::get_settings,
-        Impersonate::Chrome101 => chrome::v101::get_settings,
-        Impersonate::Chrome104 => chrome::v104::get_settings,
-        Impersonate::Chrome105 => chrome::v105::get_settings,
-        Impersonate::Chrome106 => chrome::v106::get_settings,
-        Impersonate::Chrome107 => chrome::v107::get_settings,
-        Impersonate::Chrome108 => chrome::v108::get_settings,
-        Impersonate::Chrome109 => chrome::v109::get_settings,
-        Impersonate::Chrome114 => chrome::v114::get_settings,
-        Impersonate::Chrome116 => chrome::v116::get_settings,
-        Impersonate::Chrome117 => chrome::v117::get_settings,
-        Impersonate::Chrome118 => chrome::v118::get_settings,
-        Impersonate::Chrome119 => chrome::v119::get_settings,
-        Impersonate::Chrome120 => chrome::v120::get_settings,
-        Impersonate::Chrome123 => chrome::v123::get_settings,
-        Impersonate::Chrome124 => chrome::v124::get_settings,
-        Impersonate::Chrome126 => chrome::v126::get_settings,
-        Impersonate::Chrome127 => chrome::v127::get_settings,
-        Impersonate::Cronet => chrome::cronet::get_settings,
-
-        Impersonate::SafariIos17_2 => safari::safari_ios_17_2::get_settings,
-        Impersonate::SafariIos17_4_1 => safari::safari_ios_17_4_1::get_settings,
-        Impersonate::SafariIos16_5 => safari::safari_ios_16_5::get_settings,
-        Impersonate::Safari15_3 => safari::safari15_3::get_settings,
-        Impersonate::Safari15_5 => safari::safari15_5::get_settings,
-        Impersonate::Safari15_6_1 => safari::safari15_6_1::get_settings,
-        Impersonate::Safari16 => safari::safari16::get_settings,
-        Impersonate::Safari16_5 => safari::safari16_5::get_settings,
-        Impersonate::Safari17_0 => safari::safari17_0::get_settings,
-        Impersonate::Safari17_2_1 => safari::safari17_2_1::get_settings,
-        Impersonate::Safari17_4_1 => safari::safari17_4_1::get_settings,
-        Impersonate::Safari17_5 => safari::safari17_5::get_settings,
-
-        Impersonate::OkHttp3_9 => okhttp::okhttp3_9::get_settings,
-        Impersonate::OkHttp3_11 => okhttp::okhttp3_11::get_settings,
-        Impersonate::OkHttp3_13 => okhttp::okhttp3_13::get_settings,
-        Impersonate::OkHttp3_14 => okhttp::okhttp3_14::get_settings,
-        Impersonate::OkHttp4_9 => okhttp::okhttp4_9::get_settings,
-        Impersonate::OkHttp4_10 => okhttp::okhttp4_10::get_settings,
-        Impersonate::OkHttp5 => okhttp::okhttp5::get_settings,
-
-        Impersonate::Edge99 => edge::edge99::get_settings,
-        Impersonate::Edge101 => edge::edge101::get_settings,
-        Impersonate::Edge122 => edge::edge122::get_settings,
-        Impersonate::Edge127 => edge::edge127::get_settings
-    )
+pub(crate) fn get_settings(ver: Impersonate) -> ImpersonateSettings {
+    if let Impersonate::Custom(custom) = ver {
+        return registry::settings(custom);
+    }
+    match ver.spec() {
+        Some(spec) => builtin_settings(ver, spec),
+        None => randomized::get_settings(HeaderMap::new()),
+    }
+}
+
+/// The settings of the built-in profiles, resolved once.
+///
+/// Resolving a profile parses the headers of all the profiles it is based
+/// on, which clients built per task would otherwise repeat.
+static BUILTIN: RwLock<Vec<(Impersonate, ImpersonateSettings)>> = RwLock::new(Vec::new());
+
+fn builtin_settings(ver: Impersonate, spec: &ProfileSpec) -> ImpersonateSettings {
+    let cached = BUILTIN
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(v, _)| *v == ver)
+        .map(|(_, settings)| settings.fresh());
+    if let Some(settings) = cached {
+        return settings;
+    }
+
+    let settings = spec
+        .settings()
+        .expect("built-in impersonate profiles are valid");
+    let mut builtin = BUILTIN.write().unwrap();
+    // Another thread may have resolved the profile meanwhile.
+    if let Some((_, cached)) = builtin.iter().find(|(v, _)| *v == ver) {
+        return cached.fresh();
+    }
+    let fresh = settings.fresh();
+    builtin.push((ver, settings));
+    fresh
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum Impersonate {
     Chrome100,
     Chrome101,
@@ -110,7 +162,14 @@ pub enum Impersonate {
     #[default]
     Chrome126,
     Chrome127,
+    Chrome128,
+    Chrome129,
+    Chrome130,
+    Chrome131,
     Cronet,
+    AndroidWebView13,
+    AndroidWebView14,
+    AndroidWebView15,
     SafariIos17_2,
     SafariIos17_4_1,
     SafariIos16_5,
@@ -130,69 +189,231 @@ pub enum Impersonate {
     OkHttp4_9,
     OkHttp4_10,
     OkHttp5,
+    OkHttp5_1,
     Edge99,
     Edge101,
     Edge122,
     Edge127,
+    Edge128,
+    Edge129,
+    Edge130,
+    Edge131,
+    /// A Chrome-like fingerprint that is unique to each client.
+    ///
+    /// The TLS 1.2 cipher order, supported curves, HTTP/2 settings and
+    /// Chrome version are drawn at random when the client is built, and
+    /// TLS extensions are shuffled on every connection. Use this to avoid
+    /// fingerprint blocklists rather than to match a real browser exactly.
+    Randomized,
+    /// A profile added with [`Impersonate::register`].
+    Custom(Custom),
 }
 
+/// The handle of a profile added with [`Impersonate::register`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Custom(pub(crate) usize);
+
 /// Impersonate version from string
 impl FromStr for Impersonate {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "chrome_100" => Ok(Impersonate::Chrome100),
-            "chrome_101" => Ok(Impersonate::Chrome101),
-            "chrome_104" => Ok(Impersonate::Chrome104),
-            "chrome_105" => Ok(Impersonate::Chrome105),
-            "chrome_106" => Ok(Impersonate::Chrome106),
-            "chrome_107" => Ok(Impersonate::Chrome107),
-            "chrome_108" => Ok(Impersonate::Chrome108),
-            "chrome_109" => Ok(Impersonate::Chrome109),
-            "chrome_114" => Ok(Impersonate::Chrome114),
-            "chrome_116" => Ok(Impersonate::Chrome116),
-            "chrome_117" => Ok(Impersonate::Chrome117),
-            "chrome_118" => Ok(Impersonate::Chrome118),
-            "chrome_119" => Ok(Impersonate::Chrome119),
-            "chrome_120" => Ok(Impersonate::Chrome120),
-            "chrome_123" => Ok(Impersonate::Chrome123),
-            "chrome_124" => Ok(Impersonate::Chrome124),
-            "chrome_126" => Ok(Impersonate::Chrome126),
-            "chrome_127" => Ok(Impersonate::Chrome127),
-            "cronet" => Ok(Impersonate::Cronet),
-
-            "safari_ios_17.2" => Ok(Impersonate::SafariIos17_2),
-            "safari_ios_17.4.1" => Ok(Impersonate::SafariIos17_4_1),
-            "safari_15.3" => Ok(Impersonate::Safari15_3),
-            "safari_15.5" => Ok(Impersonate::Safari15_5),
-            "safari_15.6.1" => Ok(Impersonate::Safari15_6_1),
-            "safari_16" => Ok(Impersonate::Safari16),
-            "safari_16.5" => Ok(Impersonate::Safari16_5),
-            "safari_ios_16.5" => Ok(Impersonate::SafariIos16_5),
-            "safari_17.0" => Ok(Impersonate::Safari17_0),
-            "safari_17.2.1" => Ok(Impersonate::Safari17_2_1),
-            "safari_17.4.1" => Ok(Impersonate::Safari17_4_1),
-            "safari_17.5" => Ok(Impersonate::Safari17_5),
-
-            "okhttp_3.9" => Ok(Impersonate::OkHttp3_9),
-            "okhttp_3.11" => Ok(Impersonate::OkHttp3_11),
-            "okhttp_3.13" => Ok(Impersonate::OkHttp3_13),
-            "okhttp_3.14" => Ok(Impersonate::OkHttp3_14),
-            "okhttp_4.9" => Ok(Impersonate::OkHttp4_9),
-            "okhttp_4.10" => Ok(Impersonate::OkHttp4_10),
-            "okhttp_5" => Ok(Impersonate::OkHttp5),
-
-            "edge_99" => Ok(Impersonate::Edge99),
-            "edge_101" => Ok(Impersonate::Edge101),
-            "edge_122" => Ok(Impersonate::Edge122),
-            "edge_127" => Ok(Impersonate::Edge127),
-            _ => Err("Invalid impersonate version"),
-        }
+        builtin(s)
+            .or_else(|| registry::lookup(s).map(Impersonate::Custom))
+            .ok_or("Invalid impersonate version")
     }
 }
 
+/// The built-in profile named `s`.
+fn builtin(s: &str) -> Option<Impersonate> {
+    let ver = match s {
+        "chrome_100" => Impersonate::Chrome100,
+        "chrome_101" => Impersonate::Chrome101,
+        "chrome_104" => Impersonate::Chrome104,
+        "chrome_105" => Impersonate::Chrome105,
+        "chrome_106" => Impersonate::Chrome106,
+        "chrome_107" => Impersonate::Chrome107,
+        "chrome_108" => Impersonate::Chrome108,
+        "chrome_109" => Impersonate::Chrome109,
+        "chrome_114" => Impersonate::Chrome114,
+        "chrome_116" => Impersonate::Chrome116,
+        "chrome_117" => Impersonate::Chrome117,
+        "chrome_118" => Impersonate::Chrome118,
+        "chrome_119" => Impersonate::Chrome119,
+        "chrome_120" => Impersonate::Chrome120,
+        "chrome_123" => Impersonate::Chrome123,
+        "chrome_124" => Impersonate::Chrome124,
+        "chrome_126" => Impersonate::Chrome126,
+        "chrome_127" => Impersonate::Chrome127,
+        "chrome_128" => Impersonate::Chrome128,
+        "chrome_129" => Impersonate::Chrome129,
+        "chrome_130" => Impersonate::Chrome130,
+        "chrome_131" => Impersonate::Chrome131,
+        "cronet" => Impersonate::Cronet,
+        "android_webview_13" => Impersonate::AndroidWebView13,
+        "android_webview_14" => Impersonate::AndroidWebView14,
+        "android_webview_15" => Impersonate::AndroidWebView15,
+
+        "safari_ios_17.2" => Impersonate::SafariIos17_2,
+        "safari_ios_17.4.1" => Impersonate::SafariIos17_4_1,
+        "safari_15.3" => Impersonate::Safari15_3,
+        "safari_15.5" => Impersonate::Safari15_5,
+        "safari_15.6.1" => Impersonate::Safari15_6_1,
+        "safari_16" => Impersonate::Safari16,
+        "safari_16.5" => Impersonate::Safari16_5,
+        "safari_ios_16.5" => Impersonate::SafariIos16_5,
+        "safari_17.0" => Impersonate::Safari17_0,
+        "safari_17.2.1" => Impersonate::Safari17_2_1,
+        "safari_17.4.1" => Impersonate::Safari17_4_1,
+        "safari_17.5" => Impersonate::Safari17_5,
+
+        "okhttp_3.9" => Impersonate::OkHttp3_9,
+        "okhttp_3.11" => Impersonate::OkHttp3_11,
+        "okhttp_3.13" => Impersonate::OkHttp3_13,
+        "okhttp_3.14" => Impersonate::OkHttp3_14,
+        "okhttp_4.9" => Impersonate::OkHttp4_9,
+        "okhttp_4.10" => Impersonate::OkHttp4_10,
+        "okhttp_5" => Impersonate::OkHttp5,
+        "okhttp_5.1" => Impersonate::OkHttp5_1,
+
+        "edge_99" => Impersonate::Edge99,
+        "edge_101" => Impersonate::Edge101,
+        "edge_122" => Impersonate::Edge122,
+        "edge_127" => Impersonate::Edge127,
+        "edge_128" => Impersonate::Edge128,
+        "edge_129" => Impersonate::Edge129,
+        "edge_130" => Impersonate::Edge130,
+        "edge_131" => Impersonate::Edge131,
+
+        "randomized" => Impersonate::Randomized,
+        _ => return None,
+    };
+    Some(ver)
+}
+
 impl Impersonate {
+    /// Add a profile named `name`, returning its handle.
+    ///
+    /// The handle can be used like any built-in version, and the profile
+    /// is also found by parsing its name. It is treated as a Chrome profile
+    /// for the HTTP/2 pseudo-header order and per-connection TLS settings;
+    /// use [`Impersonate::register_with_profile`] for other browsers.
+    ///
+    /// Registering a name again replaces its settings, keeping its handle.
+    /// Each client gets its own TLS session cache, even when the clients
+    /// impersonate the same registered profile.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `name` is the name of a built-in profile.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn run() -> Result<(), rquest::Error> {
+    /// use rquest::impersonate::Impersonate;
+    ///
+    /// let settings = Impersonate::Chrome127.spec().unwrap().settings()?;
+    /// // ... adjust the settings ...
+    /// let mine = Impersonate::register("my_profile", settings)?;
+    ///
+    /// let client = rquest::Client::builder().impersonate(mine).build()?;
+    /// assert_eq!("my_profile".parse::<Impersonate>(), Ok(mine));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register(name: &str, settings: ImpersonateSettings) -> crate::Result<Impersonate> {
+        Impersonate::register_with_profile(name, ClientProfile::Chrome, settings)
+    }
+
+    /// Add a profile named `name` of the `profile` browser family.
+    ///
+    /// See [`Impersonate::register`].
+    pub fn register_with_profile(
+        name: &str,
+        profile: ClientProfile,
+        settings: ImpersonateSettings,
+    ) -> crate::Result<Impersonate> {
+        if builtin(name).is_some() {
+            return Err(crate::error::builder(format!(
+                "{:?} is the name of a built-in impersonate profile",
+                name
+            )));
+        }
+        Ok(Impersonate::Custom(registry::register(
+            name, profile, settings,
+        )))
+    }
+
+    /// The data of the profile.
+    ///
+    /// This is `None` for [`Impersonate::Randomized`], which draws new
+    /// settings for each client, and for registered profiles.
+    pub fn spec(&self) -> Option<&'static ProfileSpec> {
+        let spec = match self {
+            Impersonate::Chrome100 => &chrome::V100,
+            Impersonate::Chrome101 => &chrome::V101,
+            Impersonate::Chrome104 => &chrome::V104,
+            Impersonate::Chrome105 => &chrome::V105,
+            Impersonate::Chrome106 => &chrome::V106,
+            Impersonate::Chrome107 => &chrome::V107,
+            Impersonate::Chrome108 => &chrome::V108,
+            Impersonate::Chrome109 => &chrome::V109,
+            Impersonate::Chrome114 => &chrome::V114,
+            Impersonate::Chrome116 => &chrome::V116,
+            Impersonate::Chrome117 => &chrome::V117,
+            Impersonate::Chrome118 => &chrome::V118,
+            Impersonate::Chrome119 => &chrome::V119,
+            Impersonate::Chrome120 => &chrome::V120,
+            Impersonate::Chrome123 => &chrome::V123,
+            Impersonate::Chrome124 => &chrome::V124,
+            Impersonate::Chrome126 => &chrome::V126,
+            Impersonate::Chrome127 => &chrome::V127,
+            Impersonate::Chrome128 => &chrome::V128,
+            Impersonate::Chrome129 => &chrome::V129,
+            Impersonate::Chrome130 => &chrome::V130,
+            Impersonate::Chrome131 => &chrome::V131,
+            Impersonate::Cronet => &chrome::CRONET,
+            Impersonate::AndroidWebView13 => &chrome::ANDROID_WEBVIEW_13,
+            Impersonate::AndroidWebView14 => &chrome::ANDROID_WEBVIEW_14,
+            Impersonate::AndroidWebView15 => &chrome::ANDROID_WEBVIEW_15,
+
+            Impersonate::SafariIos17_2 => &safari::IOS_17_2,
+            Impersonate::SafariIos17_4_1 => &safari::IOS_17_4_1,
+            Impersonate::SafariIos16_5 => &safari::IOS_16_5,
+            Impersonate::Safari15_3 => &safari::V15_3,
+            Impersonate::Safari15_5 => &safari::V15_5,
+            Impersonate::Safari15_6_1 => &safari::V15_6_1,
+            Impersonate::Safari16 => &safari::V16,
+            Impersonate::Safari16_5 => &safari::V16_5,
+            Impersonate::Safari17_0 => &safari::V17_0,
+            Impersonate::Safari17_2_1 => &safari::V17_2_1,
+            Impersonate::Safari17_4_1 => &safari::V17_4_1,
+            Impersonate::Safari17_5 => &safari::V17_5,
+
+            Impersonate::OkHttp3_9 => &okhttp::V3_9,
+            Impersonate::OkHttp3_11 => &okhttp::V3_11,
+            Impersonate::OkHttp3_13 => &okhttp::V3_13,
+            Impersonate::OkHttp3_14 => &okhttp::V3_14,
+            Impersonate::OkHttp4_9 => &okhttp::V4_9,
+            Impersonate::OkHttp4_10 => &okhttp::V4_10,
+            Impersonate::OkHttp5 => &okhttp::V5,
+            Impersonate::OkHttp5_1 => &okhttp::V5_1,
+
+            Impersonate::Edge99 => &edge::V99,
+            Impersonate::Edge101 => &edge::V101,
+            Impersonate::Edge122 => &edge::V122,
+            Impersonate::Edge127 => &edge::V127,
+            Impersonate::Edge128 => &edge::V128,
+            Impersonate::Edge129 => &edge::V129,
+            Impersonate::Edge130 => &edge::V130,
+            Impersonate::Edge131 => &edge::V131,
+
+            Impersonate::Randomized | Impersonate::Custom(_) => return None,
+        };
+        Some(spec)
+    }
+
     /// Get the client profile for the given impersonate version
     pub fn profile(&self) -> ClientProfile {
         match self {
@@ -214,7 +435,15 @@ impl Impersonate {
             | Impersonate::Chrome124
             | Impersonate::Chrome126
             | Impersonate::Chrome127
-            | Impersonate::Cronet => ClientProfile::Chrome,
+            | Impersonate::Chrome128
+            | Impersonate::Chrome129
+            | Impersonate::Chrome130
+            | Impersonate::Chrome131
+            | Impersonate::Cronet
+            | Impersonate::AndroidWebView13
+            | Impersonate::AndroidWebView14
+            | Impersonate::AndroidWebView15
+            | Impersonate::Randomized => ClientProfile::Chrome,
 
             Impersonate::SafariIos17_2
             | Impersonate::SafariIos16_5
@@ -235,18 +464,58 @@ impl Impersonate {
             | Impersonate::OkHttp3_14
             | Impersonate::OkHttp4_9
             | Impersonate::OkHttp4_10
-            | Impersonate::OkHttp5 => ClientProfile::OkHttp,
+            | Impersonate::OkHttp5
+            | Impersonate::OkHttp5_1 => ClientProfile::OkHttp,
 
             Impersonate::Edge99
             | Impersonate::Edge101
             | Impersonate::Edge122
-            | Impersonate::Edge127 => ClientProfile::Edge,
+            | Impersonate::Edge127
+            | Impersonate::Edge128
+            | Impersonate::Edge129
+            | Impersonate::Edge130
+            | Impersonate::Edge131 => ClientProfile::Edge,
+
+            Impersonate::Custom(custom) => registry::profile(*custom),
         }
     }
+
+    /// Whether the TLS extensions are shuffled on every connection, as
+    /// Chromium does by default.
+    pub(crate) fn permutes_extensions(&self) -> bool {
+        matches!(
+            self,
+            Impersonate::Chrome128
+                | Impersonate::Chrome129
+                | Impersonate::Chrome130
+                | Impersonate::Chrome131
+                | Impersonate::AndroidWebView13
+                | Impersonate::AndroidWebView14
+                | Impersonate::AndroidWebView15
+                | Impersonate::Edge128
+                | Impersonate::Edge129
+                | Impersonate::Edge130
+                | Impersonate::Edge131
+                | Impersonate::Randomized
+        )
+    }
+
+    /// Whether the post-quantum key share is `X25519MLKEM768` rather than
+    /// `X25519Kyber768Draft00`.
+    pub(crate) fn offers_ml_kem(&self) -> bool {
+        matches!(
+            self,
+            Impersonate::Chrome131
+                | Impersonate::AndroidWebView13
+                | Impersonate::AndroidWebView14
+                | Impersonate::AndroidWebView15
+                | Impersonate::Edge131
+        )
+    }
 }
 
 /// Client profile to impersonate
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum ClientProfile {
     /// Chrome impersonate client profile
     Chrome,
@@ -276,17 +545,68 @@ impl From<ClientProfile> for AgentProfile {
 pub struct ImpersonateSettings {
     pub tls_connector: BoringTlsConnector,
     pub http2: Http2Settings,
-    pub headers: HeaderMap,
+    /// The default headers, shared by the clients of the profile until one
+    /// changes them.
+    pub headers: Arc<HeaderMap>,
     pub gzip: bool,
     pub brotli: bool,
 }
 
-/// HTTP/2 settings.
-pub struct Http2Settings {
-    pub initial_stream_window_size: Option<u32>,
-    pub initial_connection_window_size: Option<u32>,
-    pub max_concurrent_streams: Option<u32>,
-    pub max_header_list_size: Option<u32>,
-    pub header_table_size: Option<u32>,
-    pub enable_push: Option<bool>,
+impl ImpersonateSettings {
+    /// A copy of the settings, with a TLS connector of its own so that
+    /// clients don't share TLS sessions. The headers are shared.
+    pub(crate) fn fresh(&self) -> ImpersonateSettings {
+        ImpersonateSettings {
+            tls_connector: self.tls_connector.fresh(),
+            http2: self.http2.clone(),
+            headers: self.headers.clone(),
+            gzip: self.gzip,
+            brotli: self.brotli,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_settings_are_resolved_once() {
+        let first = get_settings(Impersonate::Chrome127);
+        let second = get_settings(Impersonate::Chrome127);
+        assert!(Arc::ptr_eq(&first.headers, &second.headers));
+
+        let cached = BUILTIN
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(ver, _)| *ver == Impersonate::Chrome127)
+            .count();
+        assert_eq!(cached, 1);
+    }
+
+    #[test]
+    fn builtin_settings_are_cached_once_across_threads() {
+        let start = std::sync::Arc::new(std::sync::Barrier::new(8));
+        let threads = (0..8)
+            .map(|_| {
+                let start = start.clone();
+                std::thread::spawn(move || {
+                    start.wait();
+                    get_settings(Impersonate::Chrome128)
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let cached = BUILTIN
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(ver, _)| *ver == Impersonate::Chrome128)
+            .count();
+        assert_eq!(cached, 1);
+    }
 }
@@ -0,0 +1,197 @@
+//! A Chrome-like profile whose fingerprint is randomized per client.
+//!
+//! Every call to `get_settings` draws a new TLS 1.2 cipher order, curve
+//! list, set of HTTP/2 settings and Chrome version, so each client built
+//! with `Impersonate::Randomized` has its own JA3/Akamai fingerprint while
+//! remaining a valid browser-shaped handshake. Extensions are additionally
+//! permuted and GREASE values redrawn on every connection.
+
+use crate::impersonate::extension::{ChromeExtension, Extension, SslExtension};
+use crate::impersonate::{capabilities, BoringTlsConnector};
+use crate::impersonate::{profile::ImpersonateSettings, Http2Settings};
+use boring::ssl::SslCurve;
+use http::{
+    header::{ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, UPGRADE_INSECURE_REQUESTS, USER_AGENT},
+    HeaderMap, HeaderValue,
+};
+use std::sync::Arc;
+
+/// TLS 1.3 suites, which BoringSSL always offers in its own order.
+const TLS13_CIPHER_LIST: [&str; 3] = [
+    "TLS_AES_128_GCM_SHA256",
+    "TLS_AES_256_GCM_SHA384",
+    "TLS_CHACHA20_POLY1305_SHA256",
+];
+
+/// TLS 1.2 suites that are always offered, in a random order.
+const CIPHER_LIST: [&str; 6] = [
+    "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+    "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+];
+
+/// Legacy TLS 1.2 suites, each offered or not at random.
+const LEGACY_CIPHER_LIST: [&str; 6] = [
+    "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+    "TLS_RSA_WITH_AES_128_GCM_SHA256",
+    "TLS_RSA_WITH_AES_256_GCM_SHA384",
+    "TLS_RSA_WITH_AES_128_CBC_SHA",
+    "TLS_RSA_WITH_AES_256_CBC_SHA",
+];
+
+const CURVES: [&[SslCurve]; 3] = [
+    &[SslCurve::X25519, SslCurve::SECP256R1, SslCurve::SECP384R1],
+    &[
+        SslCurve::X25519_KYBER768_DRAFT00,
+        SslCurve::X25519,
+        SslCurve::SECP256R1,
+        SslCurve::SECP384R1,
+    ],
+    &[
+        SslCurve::X25519,
+        SslCurve::SECP256R1,
+        SslCurve::SECP384R1,
+        SslCurve::SECP521R1,
+    ],
+];
+
+pub(crate) fn get_settings(headers: HeaderMap) -> ImpersonateSettings {
+    let mut ciphers = CIPHER_LIST.to_vec();
+    shuffle(&mut ciphers);
+    ciphers.extend(LEGACY_CIPHER_LIST.iter().filter(|_| random(4) != 0));
+    let cipher_list = TLS13_CIPHER_LIST
+        .iter()
+        .chain(ciphers.iter())
+        .copied()
+        .collect::<Vec<_>>();
+    let mut curves = random(CURVES.len());
+    // The second list has the post-quantum group.
+    if curves == 1 && !capabilities().post_quantum {
+        curves = 0;
+    }
+    let curves = CURVES[curves];
+
+    let mut http2 = Http2Settings::builder()
+        .initial_stream_window_size(jitter(6291456))
+        .initial_connection_window_size(jitter(15728640))
+        .max_header_list_size(jitter(262144))
+        .header_table_size([4096, 65536][random(2)])
+        .enable_push(false);
+    if let Some(max) = [None, Some(100), Some(1000)][random(3)] {
+        http2 = http2.max_concurrent_streams(max);
+    }
+
+    ImpersonateSettings {
+        tls_connector: BoringTlsConnector::new(move || {
+            let mut builder = ChromeExtension::builder()?.configure_cipher_list(&cipher_list)?;
+            builder.set_curves(curves)?;
+            Ok(builder)
+        }),
+        http2: http2.build().expect("valid HTTP/2 settings"),
+        headers: Arc::new(create_headers(headers, 120 + random(8) as u32)),
+        gzip: true,
+        brotli: true,
+    }
+}
+
+/// A value within ±25% of `base`, rounded to a multiple of 1024.
+fn jitter(base: u32) -> u32 {
+    let spread = base / 2 / 1024;
+    (base - base / 4) / 1024 * 1024 + random(spread as usize + 1) as u32 * 1024
+}
+
+/// A uniformly random number below `n`.
+fn random(n: usize) -> usize {
+    let mut buf = [0; 8];
+    boring::rand::rand_bytes(&mut buf).expect("BoringSSL RNG failure");
+    (u64::from_ne_bytes(buf) % n as u64) as usize
+}
+
+fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, random(i + 1));
+    }
+}
+
+fn create_headers(mut headers: HeaderMap, version: u32) -> HeaderMap {
+    let sec_ch_ua = format!(
+        "\"Not)A;Brand\";v=\"99\", \"Google Chrome\";v=\"{0}\", \"Chromium\";v=\"{0}\"",
+        version
+    );
+    let user_agent = format!("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{}.0.0.0 Safari/537.36", version);
+
+    headers.insert(
+        "sec-ch-ua",
+        HeaderValue::from_str(&sec_ch_ua).expect("valid header value"),
+    );
+    headers.insert("sec-ch-ua-mobile", HeaderValue::from_static("?0"));
+    headers.insert("sec-ch-ua-platform", HeaderValue::from_static("\"macOS\""));
+    headers.insert(UPGRADE_INSECURE_REQUESTS, HeaderValue::from_static("1"));
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&user_agent).expect("valid header value"),
+    );
+    headers.insert(ACCEPT, HeaderValue::from_static("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7"));
+    headers.insert("sec-fetch-site", HeaderValue::from_static("none"));
+    headers.insert("sec-fetch-mode", HeaderValue::from_static("navigate"));
+    headers.insert("sec-fetch-user", HeaderValue::from_static("?1"));
+    headers.insert("sec-fetch-dest", HeaderValue::from_static("document"));
+    // zstd is only offered when responses in it can be decoded.
+    headers.insert(
+        ACCEPT_ENCODING,
+        HeaderValue::from_static(if cfg!(feature = "zstd") {
+            "gzip, deflate, br, zstd"
+        } else {
+            "gzip, deflate, br"
+        }),
+    );
+    headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("en-US,en;q=0.9"));
+
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_stays_in_range() {
+        for _ in 0..1000 {
+            let value = jitter(6291456);
+            assert!((4718592..=7864320).contains(&value), "{}", value);
+            assert_eq!(value % 1024, 0);
+        }
+    }
+
+    #[test]
+    fn shuffle_keeps_items() {
+        let mut items = CIPHER_LIST.to_vec();
+        shuffle(&mut items);
+        items.sort_unstable();
+        let mut expected = CIPHER_LIST.to_vec();
+        expected.sort_unstable();
+        assert_eq!(items, expected);
+    }
+
+    #[test]
+    fn settings_are_valid() {
+        // Every draw must build, whatever values it picks.
+        for _ in 0..100 {
+            let settings = get_settings(HeaderMap::new());
+            let version = settings.headers["user-agent"].to_str().unwrap();
+            assert!(version.contains("Chrome/12"), "{}", version);
+        }
+    }
+
+    #[test]
+    fn accept_encoding_matches_decoders() {
+        let headers = create_headers(HeaderMap::new(), 120);
+        let encodings = headers[ACCEPT_ENCODING].to_str().unwrap();
+        assert_eq!(encodings.contains("zstd"), cfg!(feature = "zstd"));
+        assert!(encodings.contains("br"));
+    }
+}
@@ -0,0 +1,88 @@
+//! Profiles registered at runtime.
+
+use super::profile::{ClientProfile, Custom, ImpersonateSettings};
+use std::sync::RwLock;
+
+struct Registered {
+    name: String,
+    profile: ClientProfile,
+    settings: ImpersonateSettings,
+}
+
+/// The registered profiles, indexed by their handle.
+///
+/// Profiles are never removed, so a handle stays valid.
+static REGISTRY: RwLock<Vec<Registered>> = RwLock::new(Vec::new());
+
+/// Add or replace the profile named `name`.
+pub(crate) fn register(
+    name: &str,
+    profile: ClientProfile,
+    settings: ImpersonateSettings,
+) -> Custom {
+    let mut registry = REGISTRY.write().unwrap();
+    let registered = Registered {
+        name: name.to_owned(),
+        profile,
+        settings,
+    };
+    match registry.iter().position(|r| r.name == name) {
+        Some(i) => {
+            registry[i] = registered;
+            Custom(i)
+        }
+        None => {
+            registry.push(registered);
+            Custom(registry.len() - 1)
+        }
+    }
+}
+
+/// The handle of the profile named `name`.
+pub(crate) fn lookup(name: &str) -> Option<Custom> {
+    let registry = REGISTRY.read().unwrap();
+    registry.iter().position(|r| r.name == name).map(Custom)
+}
+
+pub(crate) fn profile(custom: Custom) -> ClientProfile {
+    REGISTRY.read().unwrap()[custom.0].profile
+}
+
+/// The settings of a profile, with a TLS connector of their own so that
+/// clients don't share TLS sessions.
+pub(crate) fn settings(custom: Custom) -> ImpersonateSettings {
+    REGISTRY.read().unwrap()[custom.0].settings.fresh()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::impersonate::Impersonate;
+
+    fn settings() -> crate::impersonate::ImpersonateSettings {
+        Impersonate::Chrome127.spec().unwrap().settings().unwrap()
+    }
+
+    #[test]
+    fn register_and_parse() {
+        let custom = Impersonate::register("registry_test", settings()).unwrap();
+        assert_eq!("registry_test".parse::<Impersonate>(), Ok(custom));
+        assert!(matches!(custom.profile(), super::ClientProfile::Chrome));
+
+        // Registering again keeps the handle.
+        let mut changed = settings();
+        changed.gzip = false;
+        assert_eq!(
+            Impersonate::register("registry_test", changed).unwrap(),
+            custom
+        );
+        assert!(
+            !super::settings(match custom {
+                Impersonate::Custom(custom) => custom,
+                _ => unreachable!(),
+            })
+            .gzip
+        );
+
+        assert!(Impersonate::register("chrome_127", settings()).is_err());
+    }
+}
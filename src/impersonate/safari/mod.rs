@@ -1,15 +1,6 @@
-pub mod safari15_3;
-pub mod safari15_5;
-pub mod safari15_6_1;
-pub mod safari16;
-pub mod safari16_5;
-pub mod safari17_0;
-pub mod safari17_2_1;
-pub mod safari17_4_1;
-pub mod safari17_5;
-pub mod safari_ios_16_5;
-pub mod safari_ios_17_2;
-pub mod safari_ios_17_4_1;
+use crate::impersonate::extension::{Extension, SafariExtension, SslExtension};
+use crate::impersonate::{Impersonate, ProfileSpec};
+use boring::{error::ErrorStack, ssl::SslConnectorBuilder};
 
 const OLD_CIPHER_LIST: [&str; 26] = [
     "TLS_AES_128_GCM_SHA256",
@@ -62,3 +53,126 @@ const CIPHER_LIST: [&str; 20] = [
     "TLS_ECDHE_RSA_WITH_3DES_EDE_CBC_SHA",
     "TLS_RSA_WITH_3DES_EDE_CBC_SHA",
 ];
+
+fn tls_old() -> Result<SslConnectorBuilder, ErrorStack> {
+    SafariExtension::builder()?.configure_cipher_list(&OLD_CIPHER_LIST)
+}
+
+fn tls() -> Result<SslConnectorBuilder, ErrorStack> {
+    SafariExtension::builder()?.configure_cipher_list(&CIPHER_LIST)
+}
+
+pub(crate) static V15_3: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls_old),
+    http2: Some("3:100;4:4194304|10485760|0|m,s,p,a"),
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.3 Safari/605.1.15")),
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")),
+        ("accept-language", Some("en-US,en;q=0.9")),
+        ("accept-encoding", Some("gzip, deflate, br")),
+    ],
+};
+
+pub(crate) static V15_5: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari15_3),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.5 Safari/605.1.15")),
+    ],
+};
+
+pub(crate) static V15_6_1: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari15_5),
+    tls: Some(tls),
+    http2: None,
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.6.1 Safari/605.1.15")),
+    ],
+};
+
+pub(crate) static V16: ProfileSpec = ProfileSpec {
+    based_on: None,
+    tls: Some(tls),
+    http2: Some("3:100;4:4194304|10485760|0|m,s,p,a"),
+    headers: &[
+        ("accept", Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")),
+        ("sec-fetch-site", Some("none")),
+        ("accept-encoding", Some("gzip, deflate, br")),
+        ("sec-fetch-mode", Some("navigate")),
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.0 Safari/605.1.15")),
+        ("accept-language", Some("en-US,en;q=0.9")),
+        ("sec-fetch-dest", Some("document")),
+    ],
+};
+
+pub(crate) static V16_5: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari16),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.5 Safari/605.1.15")),
+    ],
+};
+
+pub(crate) static V17_0: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari16_5),
+    tls: None,
+    http2: Some("2:0;3:100;4:4194304|10485760|0|m,s,p,a"),
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Safari/605.1.15")),
+    ],
+};
+
+pub(crate) static V17_2_1: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari16),
+    tls: None,
+    http2: Some("2:0;3:100;4:4194304|10485760|0|m,s,p,a"),
+    headers: &[],
+};
+
+pub(crate) static V17_4_1: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari17_2_1),
+    tls: None,
+    http2: Some("3:100;4:4194304|10485760|0|m,s,p,a"),
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Safari/605.1.15")),
+    ],
+};
+
+pub(crate) static V17_5: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari17_4_1),
+    tls: None,
+    http2: Some("2:0;3:100;4:4194304|10485760|0|m,s,p,a"),
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Safari/605.1.15")),
+    ],
+};
+
+pub(crate) static IOS_16_5: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::Safari17_5),
+    tls: None,
+    http2: Some("3:100;4:2097152|10485760|0|m,s,p,a"),
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (iPhone; CPU iPhone OS 16_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.5 Mobile/15E148 Safari/604.1")),
+    ],
+};
+
+pub(crate) static IOS_17_2: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::SafariIos16_5),
+    tls: None,
+    http2: Some("2:0;3:100;4:2097152|10485760|0|m,s,p,a"),
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (iPhone; CPU iPhone OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Mobile/15E148 Safari/604.1")),
+    ],
+};
+
+pub(crate) static IOS_17_4_1: ProfileSpec = ProfileSpec {
+    based_on: Some(Impersonate::SafariIos17_2),
+    tls: None,
+    http2: None,
+    headers: &[
+        ("user-agent", Some("Mozilla/5.0 (iPad; CPU OS 17_4_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4.1 Mobile/15E148 Safari/604.1")),
+    ],
+};
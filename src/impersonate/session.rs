@@ -0,0 +1,498 @@
+//! TLS sessions kept for resumption, in memory or in a store of your own.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use boring::error::ErrorStack;
+use boring::ex_data::Index;
+use boring::ssl::{ConnectConfiguration, Ssl, SslSession, SslVersion};
+use http::Uri;
+
+use super::SessionPartition;
+use crate::error;
+
+/// Sessions kept by a `MemorySessionStore`, beyond which they are all
+/// dropped, so that crawling many hosts doesn't grow it without bound.
+const MAX_SESSIONS: usize = 4096;
+
+/// The first bytes of an exported `MemorySessionStore`, ending with the
+/// version of the format.
+const MAGIC: &[u8] = b"RQTLS\x02";
+
+/// The server, the partition and the trust configuration a TLS session
+/// was issued for.
+///
+/// A resumed session skips the verification of the server's certificate,
+/// so a session issued to a client that verified it otherwise, or not at
+/// all, is never offered.
+///
+/// Its `Display` form is stable, so that it may be used as the key of a
+/// session in an external store.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TlsSessionKey {
+    host: String,
+    port: u16,
+    partition: SessionPartition,
+    trust: String,
+}
+
+/// Where the TLS sessions of a client are kept between connections.
+///
+/// Profiles offering a `pre_shared_key` resume the sessions kept here, so
+/// a store that outlives the process, such as a file or a database shared
+/// by several workers, lets them resume sessions after a restart.
+///
+/// Sessions are passed in their DER encoding. A store only needs to keep
+/// the latest session of each key, and should hand out a TLS 1.3 session
+/// only once, as browsers do: its ticket is meant for a single use, and
+/// offering it twice lets servers link the connections.
+///
+/// # Example
+///
+/// ```rust
+/// use rquest::impersonate::{Impersonate, MemorySessionStore};
+///
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let sessions = MemorySessionStore::new();
+/// if let Ok(saved) = std::fs::read("sessions.bin") {
+///     sessions.import(&saved)?;
+/// }
+///
+/// let client = rquest::Client::builder()
+///     .impersonate(Impersonate::Chrome127)
+///     .tls_session_store(sessions.clone())
+///     .build()?;
+///
+/// // ... make requests ...
+///
+/// std::fs::write("sessions.bin", sessions.export())?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait TlsSessionStore: Send + Sync + 'static {
+    /// Get the session to resume with the server of `key`, if there is
+    /// one.
+    fn get(&self, key: &TlsSessionKey) -> Option<Vec<u8>>;
+
+    /// Keep `session`, newly issued by the server of `key`, replacing the
+    /// one kept before.
+    fn put(&self, key: &TlsSessionKey, session: Vec<u8>);
+}
+
+impl<S: TlsSessionStore + ?Sized> TlsSessionStore for Arc<S> {
+    fn get(&self, key: &TlsSessionKey) -> Option<Vec<u8>> {
+        (**self).get(key)
+    }
+
+    fn put(&self, key: &TlsSessionKey, session: Vec<u8>) {
+        (**self).put(key, session)
+    }
+}
+
+/// A `TlsSessionStore` in memory, which can be exported to bytes and
+/// imported back.
+///
+/// This is the store of a client by default. Clones share their sessions.
+#[derive(Clone, Default)]
+pub struct MemorySessionStore {
+    sessions: Arc<Mutex<HashMap<TlsSessionKey, Vec<u8>>>>,
+}
+
+// ===== impl TlsSessionKey =====
+
+impl TlsSessionKey {
+    pub(crate) fn new(
+        uri: &Uri,
+        partition: &SessionPartition,
+        trust: &str,
+    ) -> Option<TlsSessionKey> {
+        Some(TlsSessionKey {
+            host: uri.host()?.to_owned(),
+            port: uri.port_u16().unwrap_or(443),
+            partition: SessionPartition {
+                proxy: partition
+                    .proxy
+                    .as_deref()
+                    .map(|proxy| crate::proxy::digest(proxy.as_bytes())),
+                id: partition.id.clone(),
+            },
+            trust: trust.to_owned(),
+        })
+    }
+
+    /// Get the host name of the server.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Get the port of the server.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Get the id set with
+    /// [`ClientBuilder::tls_session_partition`](crate::ClientBuilder::tls_session_partition),
+    /// if any.
+    pub fn partition(&self) -> Option<&str> {
+        self.partition.id.as_deref()
+    }
+
+    /// Get a digest of the proxy the session was made through, and of the
+    /// credentials used with it, if any.
+    pub fn proxy(&self) -> Option<&str> {
+        self.partition.proxy.as_deref()
+    }
+
+    /// Get a digest of how the server's certificate was verified: whether
+    /// it was at all, the root certificates trusted besides the built-in
+    /// ones, and the pinned public keys.
+    pub fn trust(&self) -> &str {
+        &self.trust
+    }
+}
+
+impl fmt::Display for TlsSessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)?;
+        if let Some(ref id) = self.partition.id {
+            write!(f, ";partition={}", id)?;
+        }
+        if let Some(ref proxy) = self.partition.proxy {
+            write!(f, ";proxy={}", proxy)?;
+        }
+        write!(f, ";trust={}", self.trust)
+    }
+}
+
+impl fmt::Debug for TlsSessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("TlsSessionKey")
+            .field(&format_args!("{}", self))
+            .finish()
+    }
+}
+
+// ===== impl MemorySessionStore =====
+
+impl MemorySessionStore {
+    /// Create an empty store.
+    pub fn new() -> MemorySessionStore {
+        MemorySessionStore::default()
+    }
+
+    /// Get the number of sessions kept.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Returns true if no session is kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Encode the sessions kept, to be [imported](MemorySessionStore::import)
+    /// later, by this process or another.
+    ///
+    /// The bytes hold the secrets of the sessions, so keep them as safe as
+    /// the cookies of the client.
+    ///
+    /// Sessions whose partition id is longer than 65535 bytes are left out,
+    /// as the format can't hold them.
+    pub fn export(&self) -> Vec<u8> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut out = MAGIC.to_vec();
+        for (key, session) in sessions.iter() {
+            let mut entry = Vec::new();
+            let fits = put_str(&mut entry, &key.host).is_some()
+                && put_opt_str(&mut entry, key.partition.id.as_deref()).is_some()
+                && put_opt_str(&mut entry, key.partition.proxy.as_deref()).is_some()
+                && put_str(&mut entry, &key.trust).is_some();
+            let len = match u32::try_from(session.len()) {
+                Ok(len) if fits => len,
+                _ => {
+                    log::debug!("TLS session of {} is too large to export", key);
+                    continue;
+                }
+            };
+            out.extend_from_slice(&entry);
+            out.extend_from_slice(&key.port.to_be_bytes());
+            out.extend_from_slice(&len.to_be_bytes());
+            out.extend_from_slice(session);
+        }
+        out
+    }
+
+    /// Add the sessions of `bytes`, returned by
+    /// [`export`](MemorySessionStore::export), replacing those kept for the
+    /// same servers.
+    ///
+    /// # Errors
+    ///
+    /// This fails, adding no session, if `bytes` are not an export of a
+    /// `MemorySessionStore`.
+    pub fn import(&self, bytes: &[u8]) -> crate::Result<()> {
+        let mut reader = match bytes.strip_prefix(MAGIC) {
+            Some(rest) => Reader(rest),
+            None => return Err(error::decode("not an export of TLS sessions")),
+        };
+        let mut imported = Vec::new();
+        while !reader.0.is_empty() {
+            let entry = (|| {
+                let host = reader.str()?;
+                let id = reader.opt_str()?;
+                let proxy = reader.opt_str()?;
+                let trust = reader.str()?;
+                let port = u16::from_be_bytes(reader.take(2)?.try_into().ok()?);
+                let len = u32::from_be_bytes(reader.take(4)?.try_into().ok()?);
+                let session = reader.take(len as usize)?.to_vec();
+                let key = TlsSessionKey {
+                    host,
+                    port,
+                    partition: SessionPartition { proxy, id },
+                    trust,
+                };
+                Some((key, session))
+            })();
+            match entry {
+                Some(entry) => imported.push(entry),
+                None => return Err(error::decode("truncated export of TLS sessions")),
+            }
+        }
+
+        for (key, session) in imported {
+            self.put(&key, session);
+        }
+        Ok(())
+    }
+}
+
+impl TlsSessionStore for MemorySessionStore {
+    fn get(&self, key: &TlsSessionKey) -> Option<Vec<u8>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let single_use = SslSession::from_der(sessions.get(key)?).map_or(false, |session| {
+            session.protocol_version() == SslVersion::TLS1_3
+        });
+        if single_use {
+            sessions.remove(key)
+        } else {
+            sessions.get(key).cloned()
+        }
+    }
+
+    fn put(&self, key: &TlsSessionKey, session: Vec<u8>) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(key) {
+            sessions.clear();
+        }
+        sessions.insert(key.clone(), session);
+    }
+}
+
+impl fmt::Debug for MemorySessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemorySessionStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Write `s` after its length, or nothing if it is too long for it.
+fn put_str(out: &mut Vec<u8>, s: &str) -> Option<()> {
+    let len = u16::try_from(s.len()).ok()?;
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(s.as_bytes());
+    Some(())
+}
+
+fn put_opt_str(out: &mut Vec<u8>, s: Option<&str>) -> Option<()> {
+    match s {
+        Some(s) => {
+            out.push(1);
+            put_str(out, s)
+        }
+        None => {
+            out.push(0);
+            Some(())
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Option<&[u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = u16::from_be_bytes(self.take(2)?.try_into().ok()?);
+        String::from_utf8(self.take(len as usize)?.to_vec()).ok()
+    }
+
+    fn opt_str(&mut self) -> Option<Option<String>> {
+        match self.take(1)?[0] {
+            0 => Some(None),
+            1 => self.str().map(Some),
+            _ => None,
+        }
+    }
+}
+
+// ===== resumption =====
+
+/// The ex data index of the key of a connection, read when the server
+/// issues it a session.
+pub(super) fn key_index() -> Result<Index<Ssl, TlsSessionKey>, ErrorStack> {
+    static INDEX: OnceLock<Index<Ssl, TlsSessionKey>> = OnceLock::new();
+    if let Some(index) = INDEX.get() {
+        return Ok(*index);
+    }
+    let index = Ssl::new_ex_index()?;
+    Ok(*INDEX.get_or_init(|| index))
+}
+
+/// Offer the session kept for the server of `uri` on a connection, and
+/// tag the connection so that the session it gets is kept.
+///
+/// `trust` is the digest of how the connection verifies the server, see
+/// `TlsSessionKey::trust`.
+pub(super) fn resume(
+    conf: &mut ConnectConfiguration,
+    uri: &Uri,
+    partition: &SessionPartition,
+    trust: &str,
+    store: &dyn TlsSessionStore,
+) -> Result<(), ErrorStack> {
+    let key = match TlsSessionKey::new(uri, partition, trust) {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+    if let Some(session) = store.get(&key) {
+        // A session that can't be decoded, or was issued for other
+        // settings, is skipped rather than failing the connection.
+        if let Ok(session) = SslSession::from_der(&session) {
+            // Safety: boring requires the session to come from the same
+            // `SSL_CTX` as the connection, because BoringSSL ties a session
+            // to the X.509 method of the context that made it. A session
+            // decoded from DER is made with the default X.509 method, the
+            // one of every `SslConnector`, so it fits any of our contexts.
+            // BoringSSL doesn't offer a session whose version or cipher
+            // the context doesn't enable, and the key holds the trust
+            // digest, so the session was issued after the same checks of
+            // the server's certificate that resuming it skips.
+            let _ = unsafe { conf.set_session(&session) };
+        }
+    }
+    conf.set_ex_data(key_index()?, key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(host: &str, id: Option<&str>) -> TlsSessionKey {
+        TlsSessionKey {
+            host: host.to_owned(),
+            port: 443,
+            partition: SessionPartition {
+                proxy: None,
+                id: id.map(str::to_owned),
+            },
+            trust: "trust".to_owned(),
+        }
+    }
+
+    #[test]
+    fn export_then_import() {
+        let store = MemorySessionStore::new();
+        store.put(&key("example.com", None), b"first".to_vec());
+        store.put(&key("example.com", Some("alice")), b"second".to_vec());
+
+        let imported = MemorySessionStore::new();
+        imported.import(&store.export()).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(
+            imported.get(&key("example.com", Some("alice"))),
+            Some(b"second".to_vec())
+        );
+        assert_eq!(
+            key("example.com", Some("alice")).to_string(),
+            "example.com:443;partition=alice;trust=trust"
+        );
+
+        let export = store.export();
+        let err = imported.import(&export[..export.len() - 1]).unwrap_err();
+        assert!(err.is_decode());
+        assert!(imported.import(b"sessions").unwrap_err().is_decode());
+    }
+
+    #[test]
+    fn key_hides_the_proxy() {
+        let partition = SessionPartition {
+            proxy: Some("http://proxy.example:8080#secret".to_owned()),
+            id: None,
+        };
+        let uri = Uri::from_static("https://example.com/");
+        let key = TlsSessionKey::new(&uri, &partition, "trust").unwrap();
+
+        let proxy = key.proxy().unwrap();
+        assert_eq!(proxy.len(), 64);
+        assert!(!key.to_string().contains("proxy.example"));
+        assert!(!key.to_string().contains("secret"));
+
+        let store = MemorySessionStore::new();
+        store.put(&key, b"session".to_vec());
+        let export = store.export();
+        assert!(!export.windows(6).any(|w| w == b"secret"));
+        assert_eq!(TlsSessionKey::new(&uri, &partition, "trust").unwrap(), key);
+    }
+
+    #[test]
+    fn export_skips_long_partitions() {
+        let store = MemorySessionStore::new();
+        let long = "a".repeat(usize::from(u16::MAX) + 1);
+        store.put(&key("example.com", Some(&long)), b"first".to_vec());
+        store.put(&key("example.com", None), b"second".to_vec());
+
+        let imported = MemorySessionStore::new();
+        imported.import(&store.export()).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(
+            imported.get(&key("example.com", None)),
+            Some(b"second".to_vec())
+        );
+    }
+
+    #[test]
+    fn sessions_are_bounded() {
+        let store = MemorySessionStore::new();
+        for i in 0..MAX_SESSIONS {
+            store.put(&key(&format!("{}.example", i), None), b"session".to_vec());
+        }
+        assert_eq!(store.len(), MAX_SESSIONS);
+
+        // Replacing a session keeps the others.
+        store.put(&key("0.example", None), b"newer".to_vec());
+        assert_eq!(store.len(), MAX_SESSIONS);
+
+        store.put(&key("new.example", None), b"session".to_vec());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn sessions_that_cannot_be_decoded_are_kept() {
+        // Only TLS 1.3 sessions are taken out by `get`, see the
+        // `tls_session_store` test for those.
+        let store = MemorySessionStore::new();
+        store.put(&key("example.com", None), b"session".to_vec());
+        assert!(store.get(&key("example.com", None)).is_some());
+        assert!(store.get(&key("example.com", None)).is_some());
+    }
+}
@@ -0,0 +1,230 @@
+//! Browser profiles as data.
+//!
+//! Consecutive releases of a browser mostly differ by a version number in a
+//! couple of headers, so each profile is a [`ProfileSpec`] naming the
+//! profile it is based on and listing only what changed. Adding a release
+//! is adding a spec to the table of its browser.
+
+use super::{BoringTlsConnector, Http2Settings, Impersonate, ImpersonateSettings};
+use crate::error;
+use boring::{error::ErrorStack, ssl::SslConnectorBuilder};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::Arc;
+
+/// Creates the TLS settings of a profile.
+pub type TlsBuilder = fn() -> Result<SslConnectorBuilder, ErrorStack>;
+
+/// A browser profile, as the changes it makes to another profile.
+///
+/// The settings of a profile are those of the profile it is
+/// [`based_on`](ProfileSpec::based_on), with its own TLS and HTTP/2 settings
+/// when it has some, and its headers applied in order.
+///
+/// # Example
+///
+/// ```rust
+/// # fn run() -> Result<(), rquest::Error> {
+/// use rquest::impersonate::{Impersonate, ProfileSpec};
+///
+/// static CHROME_132: ProfileSpec = ProfileSpec {
+///     based_on: Some(Impersonate::Chrome131),
+///     tls: None,
+///     http2: None,
+///     headers: &[
+///         ("sec-ch-ua", Some(r#""Not A(Brand";v="8", "Chromium";v="132", "Google Chrome";v="132""#)),
+///         ("user-agent", Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/132.0.0.0 Safari/537.36")),
+///     ],
+/// };
+///
+/// let settings = CHROME_132.settings()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ProfileSpec {
+    /// The profile this one changes, or `None` for a profile listing all
+    /// its settings.
+    pub based_on: Option<Impersonate>,
+    /// The TLS settings, when they differ from the base profile.
+    pub tls: Option<TlsBuilder>,
+    /// The HTTP/2 settings as an Akamai fingerprint, when they differ from
+    /// the base profile. See [`Http2Settings::from_akamai`].
+    pub http2: Option<&'static str>,
+    /// The headers to set, or to remove with `None`.
+    ///
+    /// A header of the base profile that is set again keeps its position,
+    /// and new headers are added after the others.
+    pub headers: &'static [(&'static str, Option<&'static str>)],
+}
+
+impl ProfileSpec {
+    /// The settings of the profile.
+    ///
+    /// # Errors
+    ///
+    /// This fails if a header or the HTTP/2 fingerprint is invalid, or if
+    /// neither the profile nor those it is based on have TLS settings.
+    pub fn settings(&self) -> crate::Result<ImpersonateSettings> {
+        let (tls, http2, headers) = self.resolve()?;
+        let tls = tls.ok_or_else(|| error::builder("profile has no TLS settings"))?;
+        let http2 = match http2 {
+            Some(fingerprint) => Http2Settings::from_akamai(fingerprint)?,
+            None => Http2Settings::default(),
+        };
+
+        let mut map = HeaderMap::with_capacity(headers.len());
+        for (name, value) in headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(error::builder)?;
+            let value = HeaderValue::from_str(value).map_err(error::builder)?;
+            map.insert(name, value);
+        }
+
+        Ok(ImpersonateSettings {
+            tls_connector: BoringTlsConnector::new(tls),
+            http2,
+            headers: Arc::new(map),
+            gzip: true,
+            brotli: true,
+        })
+    }
+
+    /// The TLS builder, HTTP/2 fingerprint and headers of the profile,
+    /// after those of its bases.
+    #[allow(clippy::type_complexity)]
+    fn resolve(
+        &self,
+    ) -> crate::Result<(
+        Option<TlsBuilder>,
+        Option<&'static str>,
+        Vec<(&'static str, &'static str)>,
+    )> {
+        let (tls, http2, mut headers) = match self.based_on {
+            Some(base) => base
+                .spec()
+                .ok_or_else(|| error::builder(format!("{:?} can't be a base profile", base)))?
+                .resolve()?,
+            None => (None, None, Vec::new()),
+        };
+
+        for &(name, value) in self.headers {
+            let position = headers
+                .iter()
+                .position(|&(n, _)| n.eq_ignore_ascii_case(name));
+            match (value, position) {
+                (Some(value), Some(i)) => headers[i].1 = value,
+                (Some(value), None) => headers.push((name, value)),
+                (None, Some(i)) => {
+                    headers.remove(i);
+                }
+                (None, None) => {}
+            }
+        }
+
+        Ok((self.tls.or(tls), self.http2.or(http2), headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_specs_are_valid() {
+        for name in [
+            "chrome_100",
+            "chrome_101",
+            "chrome_104",
+            "chrome_105",
+            "chrome_106",
+            "chrome_107",
+            "chrome_108",
+            "chrome_109",
+            "chrome_114",
+            "chrome_116",
+            "chrome_117",
+            "chrome_118",
+            "chrome_119",
+            "chrome_120",
+            "chrome_123",
+            "chrome_124",
+            "chrome_126",
+            "chrome_127",
+            "chrome_128",
+            "chrome_129",
+            "chrome_130",
+            "chrome_131",
+            "cronet",
+            "android_webview_13",
+            "android_webview_14",
+            "android_webview_15",
+            "safari_ios_17.2",
+            "safari_ios_17.4.1",
+            "safari_15.3",
+            "safari_15.5",
+            "safari_15.6.1",
+            "safari_16",
+            "safari_16.5",
+            "safari_ios_16.5",
+            "safari_17.0",
+            "safari_17.2.1",
+            "safari_17.4.1",
+            "safari_17.5",
+            "okhttp_3.9",
+            "okhttp_3.11",
+            "okhttp_3.13",
+            "okhttp_3.14",
+            "okhttp_4.9",
+            "okhttp_4.10",
+            "okhttp_5",
+            "okhttp_5.1",
+            "edge_99",
+            "edge_101",
+            "edge_122",
+            "edge_127",
+            "edge_128",
+            "edge_129",
+            "edge_130",
+            "edge_131",
+        ] {
+            let ver = name.parse::<Impersonate>().unwrap();
+            let spec = ver.spec().unwrap();
+            let settings = spec.settings().unwrap();
+            let incoherences = settings.check_coherence();
+            assert!(incoherences.is_empty(), "{}: {:?}", name, incoherences);
+        }
+        assert!(Impersonate::Randomized.spec().is_none());
+    }
+
+    #[test]
+    fn applies_changes_over_base() {
+        static SPEC: ProfileSpec = ProfileSpec {
+            based_on: Some(Impersonate::Chrome127),
+            tls: None,
+            http2: None,
+            headers: &[
+                ("Sec-CH-UA-Platform", Some("\"Linux\"")),
+                ("sec-ch-ua-mobile", None),
+                ("x-new", Some("1")),
+            ],
+        };
+        let base = Impersonate::Chrome127.spec().unwrap().settings().unwrap();
+        let settings = SPEC.settings().unwrap();
+        assert_eq!(settings.headers["sec-ch-ua-platform"], "\"Linux\"");
+        assert!(!settings.headers.contains_key("sec-ch-ua-mobile"));
+        assert_eq!(settings.http2, base.http2);
+
+        let names = |headers: &HeaderMap| headers.keys().cloned().collect::<Vec<_>>();
+        let mut expected = names(&base.headers);
+        expected.retain(|name| name != "sec-ch-ua-mobile");
+        expected.push(HeaderName::from_static("x-new"));
+        assert_eq!(names(&settings.headers), expected);
+
+        static NO_TLS: ProfileSpec = ProfileSpec {
+            based_on: None,
+            tls: None,
+            http2: None,
+            headers: &[],
+        };
+        assert!(NO_TLS.settings().is_err());
+    }
+}
@@ -73,6 +73,35 @@ impl IntoUrlSealed for String {
     }
 }
 
+/// Convert `url` into a `Url`, joining it to `base` if it is relative.
+pub(crate) fn into_url_with_base<U: IntoUrl>(url: U, base: Option<&Url>) -> crate::Result<Url> {
+    if let Some(base) = base {
+        if let Err(url::ParseError::RelativeUrlWithoutBase) = Url::parse(url.as_str()) {
+            return join_base(base, url.as_str());
+        }
+    }
+    url.into_url()
+}
+
+/// Append the relative `path` to the path of `base`.
+///
+/// Unlike `Url::join`, the last segment of `base` is kept whether or not it
+/// ends with a `/`, and a leading `/` in `path` does not replace the path of
+/// `base`, so `https://host/v1` joined with `/items` is `https://host/v1/items`.
+/// The query of `path` replaces the one of `base`.
+pub(crate) fn join_base(base: &Url, path: &str) -> crate::Result<Url> {
+    let mut dir = base.clone();
+    if !dir.path().ends_with('/') {
+        let path = format!("{}/", dir.path());
+        dir.set_path(&path);
+    }
+    dir.set_query(None);
+    dir.set_fragment(None);
+    dir.join(path.trim_start_matches('/'))
+        .map_err(crate::error::builder)?
+        .into_url()
+}
+
 pub(crate) fn expect_uri(url: &Url) -> http::Uri {
     url.as_str()
         .parse()
@@ -96,6 +125,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn join_base_paths() {
+        let join = |base: &str, path: &str| {
+            join_base(&Url::parse(base).unwrap(), path)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(join("https://h/v1", "/items"), "https://h/v1/items");
+        assert_eq!(join("https://h/v1/", "items"), "https://h/v1/items");
+        assert_eq!(join("https://h", "/items?a=1"), "https://h/items?a=1");
+        assert_eq!(join("https://h/v1?key=x", "items"), "https://h/v1/items");
+        assert_eq!(
+            join("https://h/v1", "//evil.com/x"),
+            "https://h/v1/evil.com/x"
+        );
+    }
+
+    #[test]
+    fn into_url_relative() {
+        let base = Url::parse("https://h/v1").unwrap();
+        let url = into_url_with_base("/items", Some(&base)).unwrap();
+        assert_eq!(url.as_str(), "https://h/v1/items");
+        let url = into_url_with_base("http://other/x", Some(&base)).unwrap();
+        assert_eq!(url.as_str(), "http://other/x");
+
+        let err = into_url_with_base("/items", None).unwrap_err();
+        assert!(err.is_builder());
+    }
+
     #[test]
     fn into_url_blob_scheme() {
         let err = "blob:https://example.com".into_url().unwrap_err();
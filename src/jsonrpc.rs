@@ -0,0 +1,701 @@
+//! JSON-RPC 2.0 over HTTP or a websocket.
+//!
+//! A [`JsonRpcClient`] numbers its calls and matches each response to its
+//! call by id, so calls may be sent concurrently, and in a [`Batch`]. An
+//! error object returned by the server is an [`ErrorObject`].
+//!
+//! Over HTTP, every call is a `POST` sent with the [`Client`], and so with
+//! its impersonation, cookies and proxies. Over a websocket opened with
+//! [`Client::websocket`], the calls share the connection, and the
+//! notifications pushed by the server, such as those of subscriptions, are
+//! streamed by [`JsonRpcClient::notifications`].
+//!
+//! This requires the optional `jsonrpc` feature to be enabled.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::{error, Client, IntoUrl};
+
+/// A client calling the methods of a JSON-RPC 2.0 server.
+///
+/// Cloning it is cheap, and the clones share the ids of their calls, and
+/// the websocket connection if there is one.
+///
+/// # Example
+///
+/// ```no_run
+/// use rquest::jsonrpc::JsonRpcClient;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let rpc = JsonRpcClient::new(rquest::Client::new(), "https://rpc.example.com")?;
+///
+/// let block: String = rpc.call("eth_blockNumber", ()).await?;
+/// println!("block {}", block);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct JsonRpcClient {
+    transport: Transport,
+    next_id: Arc<AtomicU64>,
+}
+
+#[derive(Clone)]
+enum Transport {
+    Http {
+        client: Client,
+        url: Url,
+    },
+    #[cfg(feature = "websocket")]
+    WebSocket(ws::Connection),
+}
+
+/// The error object of a call that failed on the server.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct ErrorObject {
+    /// The error code.
+    pub code: i64,
+    /// A short description of the error.
+    pub message: String,
+    /// Additional information about the error, defined by the server.
+    pub data: Option<Value>,
+}
+
+/// The error of a JSON-RPC call.
+#[derive(Debug)]
+pub enum RpcError {
+    /// The server answered the call with an error object.
+    Rpc(ErrorObject),
+    /// The call could not be sent, or its response could not be read or
+    /// deserialized.
+    Request(crate::Error),
+}
+
+/// A notification sent by the server over a websocket.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Notification {
+    /// The method of the notification, such as `eth_subscription`.
+    pub method: String,
+    /// The parameters of the notification, `Null` if there are none.
+    pub params: Value,
+}
+
+/// Calls and notifications sent together in a single JSON-RPC batch.
+///
+/// Built with [`JsonRpcClient::batch`].
+#[must_use = "a batch does nothing until it is sent"]
+pub struct Batch<'a> {
+    rpc: &'a JsonRpcClient,
+    messages: Vec<Value>,
+    ids: Vec<u64>,
+    error: Option<crate::Error>,
+}
+
+impl JsonRpcClient {
+    /// Call the methods of the server at `url` with `client`, over HTTP.
+    pub fn new<U: IntoUrl>(client: Client, url: U) -> crate::Result<JsonRpcClient> {
+        Ok(JsonRpcClient::with_transport(Transport::Http {
+            client,
+            url: url.into_url()?,
+        }))
+    }
+
+    /// Call the methods of the server over the websocket `websocket`.
+    ///
+    /// The websocket is driven by tasks spawned on the current Tokio
+    /// runtime, and closed once every clone of the client is dropped. A
+    /// call is not subject to the timeouts of the `Client` the websocket
+    /// was opened with, so wrap it in one of your own if needed.
+    #[cfg(feature = "websocket")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+    pub fn websocket(websocket: crate::WebSocket) -> JsonRpcClient {
+        JsonRpcClient::with_transport(Transport::WebSocket(ws::Connection::spawn(websocket)))
+    }
+
+    fn with_transport(transport: Transport) -> JsonRpcClient {
+        JsonRpcClient {
+            transport,
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Call `method` with `params`, and deserialize its result into `R`.
+    ///
+    /// `params` must serialize to an array or an object, or to nothing,
+    /// such as `()`, for a method taking no parameters.
+    pub async fn call<P, R>(&self, method: &str, params: P) -> Result<R, RpcError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = self.next_id();
+        let request = message(method, params, Some(id))?;
+        let mut responses = self.transport.send(request, &[id]).await?;
+        let result = take_response(&mut responses, id)?;
+        serde_json::from_value(result).map_err(|e| RpcError::Request(error::decode(e)))
+    }
+
+    /// Send the notification `method` with `params`, which the server
+    /// doesn't answer.
+    pub async fn notify<P: Serialize>(&self, method: &str, params: P) -> crate::Result<()> {
+        let notification = message(method, params, None)?;
+        self.transport.send(notification, &[]).await.map(drop)
+    }
+
+    /// Start a batch of calls and notifications.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use rquest::jsonrpc::JsonRpcClient;
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let rpc = JsonRpcClient::new(rquest::Client::new(), "https://rpc.example.com")?;
+    ///
+    /// let results = rpc
+    ///     .batch()
+    ///     .call("eth_blockNumber", ())
+    ///     .call("eth_getBalance", ["0x407d73d8a49eeb85d32cf465507dd71d507100c1", "latest"])
+    ///     .send()
+    ///     .await?;
+    ///
+    /// for result in results {
+    ///     match result {
+    ///         Ok(value) => println!("{}", value),
+    ///         Err(error) => println!("error {}: {}", error.code, error.message),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn batch(&self) -> Batch<'_> {
+        Batch {
+            rpc: self,
+            messages: Vec::new(),
+            ids: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Stream the notifications the server sends over the websocket, such
+    /// as those of subscriptions.
+    ///
+    /// Notifications are only kept once this is called, and go to the
+    /// latest stream returned. The stream ends when the websocket closes.
+    /// Over HTTP, it ends at once.
+    pub fn notifications(&self) -> impl futures_core::Stream<Item = Notification> + Send {
+        let receiver: Option<tokio::sync::mpsc::UnboundedReceiver<Notification>> =
+            match self.transport {
+                Transport::Http { .. } => None,
+                #[cfg(feature = "websocket")]
+                Transport::WebSocket(ref connection) => Some(connection.subscribe()),
+            };
+
+        futures_util::stream::unfold(receiver, |receiver| async move {
+            let mut receiver = receiver?;
+            let notification = receiver.recv().await?;
+            Some((notification, Some(receiver)))
+        })
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for JsonRpcClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut f = f.debug_struct("JsonRpcClient");
+        match self.transport {
+            Transport::Http { ref url, .. } => f.field("url", url),
+            #[cfg(feature = "websocket")]
+            Transport::WebSocket(_) => f.field("transport", &"websocket"),
+        };
+        f.finish()
+    }
+}
+
+impl Transport {
+    /// Send `body` and return the responses to the calls `ids`, by id.
+    async fn send(&self, body: Value, ids: &[u64]) -> crate::Result<HashMap<u64, Value>> {
+        match *self {
+            Transport::Http {
+                ref client,
+                ref url,
+            } => {
+                let res = client.post(url.clone()).json(&body).send().await?;
+                let status = res.status();
+                let bytes = res.bytes().await?;
+                if ids.is_empty() {
+                    // Notifications have no response, though some servers
+                    // answer with an empty body rather than nothing.
+                    if status.is_client_error() || status.is_server_error() {
+                        return Err(error::status_code(url.clone(), status));
+                    }
+                    return Ok(HashMap::new());
+                }
+                match serde_json::from_slice(&bytes) {
+                    Ok(responses) => Ok(by_id(responses, ids)),
+                    Err(_) if status.is_client_error() || status.is_server_error() => {
+                        Err(error::status_code(url.clone(), status))
+                    }
+                    Err(err) => Err(error::decode(err).with_url(url.clone())),
+                }
+            }
+            #[cfg(feature = "websocket")]
+            Transport::WebSocket(ref connection) => connection.send(body, ids).await,
+        }
+    }
+}
+
+impl<'a> Batch<'a> {
+    /// Add a call of `method` with `params`.
+    ///
+    /// Its result is returned by [`send`](Batch::send) at the position of
+    /// the call among the calls of the batch.
+    pub fn call<P: Serialize>(mut self, method: &str, params: P) -> Batch<'a> {
+        let id = self.rpc.next_id();
+        self.push(message(method, params, Some(id)));
+        self.ids.push(id);
+        self
+    }
+
+    /// Add the notification `method` with `params`.
+    pub fn notify<P: Serialize>(mut self, method: &str, params: P) -> Batch<'a> {
+        self.push(message(method, params, None));
+        self
+    }
+
+    fn push(&mut self, message: crate::Result<Value>) {
+        match message {
+            Ok(message) => self.messages.push(message),
+            Err(err) => {
+                self.error.get_or_insert(err);
+            }
+        }
+    }
+
+    /// Send the batch, returning the result or error object of each call,
+    /// in the order the calls were added.
+    ///
+    /// # Errors
+    ///
+    /// This fails if a call has invalid parameters, the batch can't be
+    /// sent, or the response to a call is missing or malformed.
+    pub async fn send(self) -> crate::Result<Vec<Result<Value, ErrorObject>>> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        // An empty array is an invalid request.
+        if self.messages.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut responses = self
+            .rpc
+            .transport
+            .send(Value::Array(self.messages), &self.ids)
+            .await?;
+        self.ids
+            .iter()
+            .map(|&id| match take_response(&mut responses, id) {
+                Ok(result) => Ok(Ok(result)),
+                Err(RpcError::Rpc(error)) => Ok(Err(error)),
+                Err(RpcError::Request(err)) => Err(err),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Batch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Batch")
+            .field("messages", &self.messages.len())
+            .finish()
+    }
+}
+
+// ===== impl ErrorObject =====
+
+impl ErrorObject {
+    /// Invalid JSON was received by the server.
+    pub const PARSE_ERROR: i64 = -32700;
+    /// The JSON sent is not a valid request object.
+    pub const INVALID_REQUEST: i64 = -32600;
+    /// The method does not exist or is not available.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// Invalid method parameters.
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// Internal JSON-RPC error.
+    pub const INTERNAL_ERROR: i64 = -32603;
+
+    /// Returns true if the method does not exist on the server.
+    pub fn is_method_not_found(&self) -> bool {
+        self.code == ErrorObject::METHOD_NOT_FOUND
+    }
+
+    /// Returns true if the parameters of the call were rejected.
+    pub fn is_invalid_params(&self) -> bool {
+        self.code == ErrorObject::INVALID_PARAMS
+    }
+
+    /// Returns true if the code is one reserved for server errors defined
+    /// by the implementation, from -32099 to -32000.
+    pub fn is_server_error(&self) -> bool {
+        (-32099..=-32000).contains(&self.code)
+    }
+
+    /// Deserialize the `data` of the error into `T`, if there is some.
+    pub fn data<T: DeserializeOwned>(&self) -> Option<serde_json::Result<T>> {
+        self.data.clone().map(serde_json::from_value)
+    }
+
+    fn from_value(value: &Value) -> Option<ErrorObject> {
+        Some(ErrorObject {
+            code: value.get("code")?.as_i64()?,
+            message: value
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned(),
+            data: value.get("data").cloned(),
+        })
+    }
+}
+
+impl fmt::Display for ErrorObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JSON-RPC error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for ErrorObject {}
+
+// ===== impl RpcError =====
+
+impl RpcError {
+    /// Get the error object returned by the server, if there is one.
+    pub fn error_object(&self) -> Option<&ErrorObject> {
+        match *self {
+            RpcError::Rpc(ref error) => Some(error),
+            RpcError::Request(_) => None,
+        }
+    }
+}
+
+impl From<crate::Error> for RpcError {
+    fn from(err: crate::Error) -> RpcError {
+        RpcError::Request(err)
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RpcError::Rpc(ref error) => fmt::Display::fmt(error, f),
+            RpcError::Request(ref err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            RpcError::Rpc(ref error) => Some(error),
+            RpcError::Request(ref err) => Some(err),
+        }
+    }
+}
+
+/// A request object, or a notification object without `id`.
+fn message<P: Serialize>(method: &str, params: P, id: Option<u64>) -> crate::Result<Value> {
+    let params = serde_json::to_value(params).map_err(error::builder)?;
+
+    let mut message = Map::new();
+    message.insert("jsonrpc".into(), "2.0".into());
+    message.insert("method".into(), method.into());
+    match params {
+        Value::Null => {}
+        Value::Array(_) | Value::Object(_) => {
+            message.insert("params".into(), params);
+        }
+        _ => {
+            return Err(error::builder(format!(
+                "JSON-RPC params of {:?} must be an array or an object",
+                method
+            )))
+        }
+    }
+    if let Some(id) = id {
+        message.insert("id".into(), id.into());
+    }
+    Ok(Value::Object(message))
+}
+
+/// Index the response objects in `responses` by id.
+///
+/// An error without id, which the server returns when it can't tell the
+/// call, as for a batch it can't parse, is the response to all of `ids`.
+fn by_id(responses: Value, ids: &[u64]) -> HashMap<u64, Value> {
+    let responses = match responses {
+        Value::Array(responses) => responses,
+        response => vec![response],
+    };
+
+    let mut by_id = HashMap::new();
+    for response in responses {
+        match response.get("id") {
+            Some(id) if !id.is_null() => {
+                if let Some(id) = id.as_u64() {
+                    by_id.insert(id, response);
+                }
+            }
+            _ if response.get("error").is_some() => {
+                for &id in ids {
+                    by_id.entry(id).or_insert_with(|| response.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    by_id
+}
+
+/// The result of the call `id`, taken out of `responses`.
+fn take_response(responses: &mut HashMap<u64, Value>, id: u64) -> Result<Value, RpcError> {
+    let mut response = match responses.remove(&id) {
+        Some(Value::Object(response)) => response,
+        _ => {
+            return Err(RpcError::Request(error::decode(format!(
+                "no JSON-RPC response to call {}",
+                id
+            ))))
+        }
+    };
+
+    if let Some(error) = response.get("error") {
+        return match ErrorObject::from_value(error) {
+            Some(error) => Err(RpcError::Rpc(error)),
+            None => Err(RpcError::Request(error::decode(
+                "invalid JSON-RPC error object",
+            ))),
+        };
+    }
+    match response.remove("result") {
+        Some(result) => Ok(result),
+        None => Err(RpcError::Request(error::decode(
+            "JSON-RPC response has neither `result` nor `error`",
+        ))),
+    }
+}
+
+#[cfg(feature = "websocket")]
+mod ws {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::{SinkExt, StreamExt};
+    use log::{debug, warn};
+    use serde_json::Value;
+    use tokio::sync::{mpsc, oneshot};
+
+    use super::Notification;
+    use crate::{error, Message, WebSocket};
+
+    /// A websocket shared by the clones of a `JsonRpcClient`.
+    ///
+    /// A writer task sends the messages queued on `outgoing`, and ends,
+    /// closing the websocket, once every clone is dropped. A reader task
+    /// hands each response to the call waiting for it.
+    #[derive(Clone)]
+    pub(super) struct Connection {
+        outgoing: mpsc::UnboundedSender<Message>,
+        state: Arc<State>,
+    }
+
+    struct State {
+        /// The calls waiting for a response, `None` once the websocket is
+        /// closed.
+        pending: Mutex<Option<HashMap<u64, oneshot::Sender<Value>>>>,
+        notifications: Mutex<Option<mpsc::UnboundedSender<Notification>>>,
+    }
+
+    impl Connection {
+        pub(super) fn spawn(websocket: WebSocket) -> Connection {
+            let (mut sink, mut stream) = websocket.split();
+            let (outgoing, mut queue) = mpsc::unbounded_channel();
+            let state = Arc::new(State {
+                pending: Mutex::new(Some(HashMap::new())),
+                notifications: Mutex::new(None),
+            });
+
+            tokio::spawn(async move {
+                while let Some(message) = queue.recv().await {
+                    if let Err(err) = sink.send(message).await {
+                        debug!("JSON-RPC websocket write error: {}", err);
+                        return;
+                    }
+                }
+                let _ = sink.close().await;
+            });
+
+            let reader = state.clone();
+            tokio::spawn(async move {
+                while let Some(message) = stream.next().await {
+                    let text = match message {
+                        Ok(Message::Text(text)) => text,
+                        Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                            Ok(text) => text,
+                            Err(_) => continue,
+                        },
+                        Ok(Message::Close(_)) => break,
+                        Ok(_) => continue,
+                        Err(err) => {
+                            debug!("JSON-RPC websocket read error: {}", err);
+                            break;
+                        }
+                    };
+                    match serde_json::from_str(&text) {
+                        Ok(Value::Array(responses)) => {
+                            responses.into_iter().for_each(|r| reader.dispatch(r))
+                        }
+                        Ok(response) => reader.dispatch(response),
+                        Err(err) => warn!("invalid JSON-RPC message: {}", err),
+                    }
+                }
+                // Fail the calls still waiting, and any call made from now.
+                reader.pending.lock().unwrap().take();
+                reader.notifications.lock().unwrap().take();
+            });
+
+            Connection { outgoing, state }
+        }
+
+        /// Send `body` and wait for the responses to the calls `ids`.
+        pub(super) async fn send(
+            &self,
+            body: Value,
+            ids: &[u64],
+        ) -> crate::Result<HashMap<u64, Value>> {
+            let mut receivers = Vec::with_capacity(ids.len());
+            {
+                let mut pending = self.state.pending.lock().unwrap();
+                let pending = pending.as_mut().ok_or_else(closed)?;
+                for &id in ids {
+                    let (tx, rx) = oneshot::channel();
+                    pending.insert(id, tx);
+                    receivers.push((id, rx));
+                }
+            }
+            // Forget the calls once answered, failed or cancelled.
+            let _waiting = Waiting {
+                state: &self.state,
+                ids,
+            };
+
+            if self.outgoing.send(Message::Text(body.to_string())).is_err() {
+                return Err(closed());
+            }
+
+            let mut responses = HashMap::with_capacity(ids.len());
+            for (id, rx) in receivers {
+                responses.insert(id, rx.await.map_err(|_| closed())?);
+            }
+            Ok(responses)
+        }
+
+        pub(super) fn subscribe(&self) -> mpsc::UnboundedReceiver<Notification> {
+            let (tx, rx) = mpsc::unbounded_channel();
+            // The websocket is closed once `pending` is taken, and the
+            // sender dropped so that the stream ends.
+            let open = self.state.pending.lock().unwrap().is_some();
+            if open {
+                *self.state.notifications.lock().unwrap() = Some(tx);
+            }
+            rx
+        }
+    }
+
+    impl State {
+        fn dispatch(&self, message: Value) {
+            if let Some(method) = message.get("method").and_then(Value::as_str) {
+                if message.get("id").map_or(true, Value::is_null) {
+                    let notification = Notification {
+                        method: method.to_owned(),
+                        params: message.get("params").cloned().unwrap_or(Value::Null),
+                    };
+                    if let Some(ref tx) = *self.notifications.lock().unwrap() {
+                        let _ = tx.send(notification);
+                    }
+                }
+                return;
+            }
+
+            let id = match message.get("id") {
+                Some(id) if !id.is_null() => match id.as_u64() {
+                    Some(id) => id,
+                    None => {
+                        warn!("JSON-RPC response without a known id: {}", message);
+                        return;
+                    }
+                },
+                // An error the server could not tie to a call, such as a
+                // parse error, fails every call waiting.
+                _ if message.get("error").is_some() => {
+                    let waiting = self
+                        .pending
+                        .lock()
+                        .unwrap()
+                        .as_mut()
+                        .map(std::mem::take)
+                        .unwrap_or_default();
+                    for (_, tx) in waiting {
+                        let _ = tx.send(message.clone());
+                    }
+                    return;
+                }
+                _ => {
+                    warn!("JSON-RPC response without an id: {}", message);
+                    return;
+                }
+            };
+            let waiting = self
+                .pending
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|pending| pending.remove(&id));
+            if let Some(tx) = waiting {
+                let _ = tx.send(message);
+            }
+        }
+    }
+
+    /// Removes the calls `ids` from `pending` when dropped.
+    struct Waiting<'a> {
+        state: &'a State,
+        ids: &'a [u64],
+    }
+
+    impl Drop for Waiting<'_> {
+        fn drop(&mut self) {
+            if let Some(pending) = self.state.pending.lock().unwrap().as_mut() {
+                self.ids.iter().for_each(|id| drop(pending.remove(id)));
+            }
+        }
+    }
+
+    fn closed() -> crate::Error {
+        error::request("JSON-RPC websocket is closed")
+    }
+}
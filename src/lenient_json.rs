@@ -0,0 +1,122 @@
+//! Cleanup of almost-JSON documents.
+//!
+//! Many endpoints serve JavaScript object literals rather than JSON: a
+//! byte order mark, comments, trailing commas and non-finite numbers all
+//! make `serde_json` reject an otherwise fine document. `sanitize` rewrites
+//! them into strict JSON, leaving strings untouched.
+
+/// Literals outside of JSON, and what they are replaced with.
+const LITERALS: [(&[u8], &[u8]); 5] = [
+    (b"NaN", b"null"),
+    (b"Infinity", b"null"),
+    (b"-Infinity", b"null"),
+    (b"+Infinity", b"null"),
+    (b"undefined", b"null"),
+];
+
+/// Rewrite `input` into strict JSON.
+///
+/// This strips a leading BOM, `//` and `/* */` comments and commas before
+/// a closing `]` or `}`, and replaces `NaN`, `Infinity` and `undefined`
+/// with `null`.
+pub(crate) fn sanitize(input: &[u8]) -> Vec<u8> {
+    let input = input.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(input);
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        match input[i] {
+            b'"' => {
+                let end = string_end(input, i);
+                out.extend_from_slice(&input[i..end]);
+                i = end;
+            }
+            b'/' if input.get(i + 1) == Some(&b'/') => {
+                i = input[i..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(input.len(), |n| i + n);
+            }
+            b'/' if input.get(i + 1) == Some(&b'*') => {
+                i = input[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(input.len(), |n| i + 2 + n + 2);
+            }
+            b @ (b']' | b'}') => {
+                let last = out.iter().rposition(|b| !b.is_ascii_whitespace());
+                if let Some(last) = last.filter(|&last| out[last] == b',') {
+                    out.remove(last);
+                }
+                out.push(b);
+                i += 1;
+            }
+            _ => {
+                let literal = LITERALS.iter().find(|(literal, _)| {
+                    input[i..].starts_with(literal)
+                        && !input
+                            .get(i + literal.len())
+                            .map_or(false, |b| b.is_ascii_alphanumeric() || *b == b'_')
+                });
+                match literal {
+                    Some((literal, replacement)) => {
+                        out.extend_from_slice(replacement);
+                        i += literal.len();
+                    }
+                    None => {
+                        out.push(input[i]);
+                        i += 1;
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The index after the string starting with the quote at `start`.
+fn string_end(input: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < input.len() {
+        match input[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    input.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(input: &str) -> String {
+        String::from_utf8(sanitize(input.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn strips_bom_and_comments() {
+        assert_eq!(clean("\u{feff}{\"a\": 1}"), "{\"a\": 1}");
+        assert_eq!(
+            clean("{\n  // note\n  \"a\": /* one */ 1\n}"),
+            "{\n  \n  \"a\":  1\n}"
+        );
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert_eq!(clean("[1, 2, ]"), "[1, 2 ]");
+        assert_eq!(clean("{\"a\": [1,],\n}"), "{\"a\": [1]\n}");
+    }
+
+    #[test]
+    fn non_finite_numbers() {
+        assert_eq!(
+            clean("[NaN, -Infinity, Infinity, undefined]"),
+            "[null, null, null, null]"
+        );
+        assert_eq!(clean("{\"NaN\": \"NaN,]\"}"), "{\"NaN\": \"NaN,]\"}");
+        assert_eq!(clean("[\"a\\\"//b\"]"), "[\"a\\\"//b\"]");
+    }
+}
@@ -237,10 +237,32 @@
 //! - **brotli**: Provides response body brotli decompression.
 //! - **zstd**: Provides response body zstd decompression.
 //! - **deflate**: Provides response body deflate decompression.
+//! - **zlib-ng**: Decompresses gzip and deflate bodies with zlib-ng, which
+//!   uses SIMD instructions, instead of miniz_oxide.
+//! - **libdeflate**: Provides decompressing whole gzip and deflate bodies
+//!   with libdeflate. See [`DecompressionBackend`].
 //! - **json**: Provides serialization and deserialization for JSON bodies.
+//! - **json-simd**: Parses the JSON bodies decoded off the runtime with
+//!   `simd-json`.
 //! - **multipart**: Provides functionality for multipart forms.
 //! - **stream**: Adds support for `futures::Stream`.
 //! - **socks**: Provides SOCKS5 proxy support.
+//! - **shadowsocks**: Provides shadowsocks (`ss://`) proxy support.
+//! - **links**: Provides streaming extraction of links from HTML responses.
+//! - **archive**: Provides streaming extraction of tar, `.tar.gz` and zip
+//!   response bodies.
+//! - **feed**: Provides polling of RSS, Atom and JSON feeds for new entries.
+//! - **outbox**: Provides a disk-backed queue of requests, sent once the
+//!   network is back.
+//! - **jsonrpc**: Provides a JSON-RPC 2.0 client over HTTP or websockets.
+//! - **scripting**: Provides request hooks written in Rhai scripts, with
+//!   hashing functions from BoringSSL.
+//! - **s3**: Provides presigned URLs and multipart uploads for S3-compatible
+//!   object storage.
+//! - **native-roots**: Provides trusting the root certificates of the
+//!   platform's certificate store.
+//! - **test-server**: Provides a local HTTP/HTTPS server fixture for tests.
+//! - **bench-support**: Provides the fixtures of the benchmarks in `benches/`.
 //! - **hickory-dns**: Enables a hickory-dns async resolver instead of default
 //!   threadpool using `getaddrinfo`.
 //!
@@ -264,16 +286,32 @@ pub use http::{StatusCode, Version};
 pub use url::Url;
 
 // universal mods
+#[cfg(feature = "json")]
+mod api_error;
+#[cfg(feature = "archive")]
+pub mod archive;
 #[macro_use]
 mod error;
+mod idn;
 /// HTTP client implementate module
 pub mod impersonate;
 mod into_url;
+#[cfg(feature = "json")]
+mod lenient_json;
+#[cfg(feature = "links")]
+pub mod links;
+mod media_type;
 mod response;
+mod timing;
 
+#[cfg(feature = "json")]
+pub use self::api_error::ApiError;
 pub use self::error::{Error, Result};
+pub use self::idn::HomographPolicy;
 pub use self::into_url::IntoUrl;
-pub use self::response::ResponseBuilderExt;
+pub use self::media_type::MediaType;
+pub use self::response::{History, HistoryEntry, ResponseBuilderExt};
+pub use self::timing::{ConnectTimings, HandshakeReport, Ping};
 
 /// Shortcut method to quickly make a `GET` request.
 ///
@@ -313,8 +351,7 @@ pub async fn get<T: IntoUrl>(url: T) -> crate::Result<Response> {
 #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
 pub async fn websocket<T: IntoUrl>(url: T) -> crate::Result<async_impl::websocket::WebSocket> {
     Ok(Client::new()
-        .get(url)
-        .upgrade()
+        .websocket(url)
         .send()
         .await?
         .into_websocket()
@@ -352,18 +389,56 @@ pub use self::async_impl::websocket::{
     Message, WebSocket, WebSocketRequestBuilder, WebSocketResponse,
 };
 pub use self::async_impl::{
-    Body, Client, ClientBuilder, Request, RequestBuilder, Response, Upgraded,
+    Body, Client, ClientBuilder, ConnectionMode, ContentDecoder, ContentEncoding,
+    ContentLengthHint, DecoderStream, DecompressionBackend, Priority, Progress, Request,
+    RequestBuilder, RequestTemplate, Response, ResponseHead, Scope, Timeouts, Upgraded,
 };
 pub use self::proxy::{NoProxy, Proxy};
+pub use self::proxy_pool::{
+    HealthCheck, ProxyEvent, ProxyPool, ProxyPoolBuilder, ProxyStatus, Rotation,
+};
+#[cfg(feature = "__boring")]
+pub use self::proxy_protocol::ProxyProtocol;
 
+#[cfg(feature = "__boring")]
+mod alt_svc;
 mod async_impl;
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
 #[cfg(feature = "blocking")]
 pub mod blocking;
 mod connect;
 #[cfg(feature = "cookies")]
 pub mod cookie;
 pub mod dns;
+pub mod egress;
+#[cfg(feature = "feed")]
+pub mod feed;
+#[cfg(feature = "__boring")]
+mod h2_fallback;
+pub mod http1;
+pub mod http2;
+#[cfg(feature = "jsonrpc")]
+pub mod jsonrpc;
+pub mod middleware;
+#[cfg(feature = "outbox")]
+pub mod outbox;
 mod proxy;
+mod proxy_pool;
+#[cfg(feature = "__boring")]
+mod proxy_protocol;
+pub mod rate_limit;
+mod raw_head;
 pub mod redirect;
+pub mod retry;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "shadowsocks")]
+mod shadowsocks;
+#[cfg(feature = "test-server")]
+pub mod test_server;
 pub mod tls;
 mod util;
+mod wire_capture;
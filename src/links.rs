@@ -0,0 +1,310 @@
+//! Streaming HTML link extraction.
+//!
+//! [`LinkExtractor`] scans HTML incrementally, chunk by chunk, and yields the
+//! absolute URLs referenced by `a[href]`, `area[href]`, `link[href]`,
+//! `img[src]`, `iframe[src]` and `script[src]`. A `<base href>` element
+//! changes the base URL for the links that follow it, as in a browser.
+//!
+//! Use [`Response::links`](crate::Response::links) to extract links while
+//! the response body is downloading.
+//!
+//! This requires the optional `links` feature to be enabled.
+
+use url::Url;
+
+/// Tags larger than this are dropped instead of buffered.
+const MAX_TAG_LEN: usize = 64 * 1024;
+
+/// An incremental extractor of links from an HTML document.
+///
+/// # Example
+///
+/// ```
+/// use rquest::links::LinkExtractor;
+/// # use url::Url;
+///
+/// let mut links = LinkExtractor::new(Url::parse("https://example.com/a/").unwrap());
+/// let mut found = links.feed(b"<a href=\"b\">b</a><img sr");
+/// found.extend(links.feed(b"c=\"/c.png\">"));
+///
+/// assert_eq!(found[0].as_str(), "https://example.com/a/b");
+/// assert_eq!(found[1].as_str(), "https://example.com/c.png");
+/// ```
+#[derive(Debug)]
+pub struct LinkExtractor {
+    base: Url,
+    buf: Vec<u8>,
+    state: State,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Looking for the next `<`.
+    Text,
+    /// Inside `<!-- ... -->`.
+    Comment,
+    /// Inside the body of a `<script>` or `<style>` element.
+    RawText(&'static [u8]),
+}
+
+impl LinkExtractor {
+    /// Create an extractor that resolves relative links against `base`.
+    ///
+    /// This should be the final URL of the document, after redirects.
+    pub fn new(base: Url) -> LinkExtractor {
+        LinkExtractor {
+            base,
+            buf: Vec::new(),
+            state: State::Text,
+        }
+    }
+
+    /// Feed the next chunk of the document, returning the links it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Url> {
+        let mut links = Vec::new();
+        self.buf.extend_from_slice(chunk);
+
+        let mut pos = 0;
+        loop {
+            match self.state {
+                State::Text => match find(&self.buf[pos..], b"<") {
+                    Some(i) => {
+                        let start = pos + i;
+                        match self.buf.get(start + 1) {
+                            Some(b) if b.is_ascii_alphabetic() || *b == b'/' || *b == b'!' => {}
+                            // A bare `<` in text, not a tag.
+                            Some(_) => {
+                                pos = start + 1;
+                                continue;
+                            }
+                            None => {
+                                pos = start;
+                                break;
+                            }
+                        }
+                        if self.buf[start..].starts_with(b"<!--") {
+                            pos = start + 4;
+                            self.state = State::Comment;
+                            continue;
+                        }
+                        if self.buf.len() - start < 4 && b"<!--".starts_with(&self.buf[start..]) {
+                            // Might be the start of a comment, wait for more.
+                            pos = start;
+                            break;
+                        }
+                        match tag_end(&self.buf[start..]) {
+                            Some(len) => {
+                                let tag = &self.buf[start + 1..start + len - 1];
+                                self.state = handle_tag(&mut self.base, tag, &mut links);
+                                pos = start + len;
+                            }
+                            None => {
+                                pos = start;
+                                if self.buf.len() - pos > MAX_TAG_LEN {
+                                    pos = self.buf.len();
+                                }
+                                break;
+                            }
+                        }
+                    }
+                    None => {
+                        pos = self.buf.len();
+                        break;
+                    }
+                },
+                State::Comment => match find(&self.buf[pos..], b"-->") {
+                    Some(i) => {
+                        pos += i + 3;
+                        self.state = State::Text;
+                    }
+                    None => {
+                        pos = self.buf.len().saturating_sub(2).max(pos);
+                        break;
+                    }
+                },
+                State::RawText(end) => match find_ignore_case(&self.buf[pos..], end) {
+                    Some(i) => {
+                        pos += i;
+                        self.state = State::Text;
+                    }
+                    None => {
+                        pos = self.buf.len().saturating_sub(end.len() - 1).max(pos);
+                        break;
+                    }
+                },
+            }
+        }
+
+        self.buf.drain(..pos);
+        links
+    }
+}
+
+/// Handles a complete tag (without the `<>`), returning the next scanner state.
+fn handle_tag(base: &mut Url, tag: &[u8], links: &mut Vec<Url>) -> State {
+    let name_len = tag
+        .iter()
+        .position(|b| b.is_ascii_whitespace() || *b == b'/')
+        .unwrap_or(tag.len());
+    let name = tag[..name_len].to_ascii_lowercase();
+    let attrs = &tag[name_len..];
+
+    let attr = match &name[..] {
+        b"a" | b"area" | b"link" | b"base" => Some("href"),
+        b"img" | b"iframe" | b"script" => Some("src"),
+        _ => None,
+    };
+
+    if let Some(value) = attr.and_then(|attr| attribute(attrs, attr)) {
+        if let Ok(url) = base.join(value.trim()) {
+            if name == b"base" {
+                *base = url;
+            } else if url.scheme() == "http" || url.scheme() == "https" {
+                links.push(url);
+            }
+        }
+    }
+
+    match &name[..] {
+        b"script" if !self_closing(attrs) => State::RawText(b"</script"),
+        b"style" => State::RawText(b"</style"),
+        _ => State::Text,
+    }
+}
+
+fn self_closing(attrs: &[u8]) -> bool {
+    attrs.iter().rev().find(|b| !b.is_ascii_whitespace()) == Some(&b'/')
+}
+
+/// Returns the length of the tag starting at `buf[0] == b'<'`, including the `>`.
+fn tag_end(buf: &[u8]) -> Option<usize> {
+    let mut quote = None;
+    for (i, &b) in buf.iter().enumerate().skip(1) {
+        match (quote, b) {
+            (None, b'"') | (None, b'\'') => quote = Some(b),
+            (Some(q), b) if q == b => quote = None,
+            (None, b'>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Finds the value of the attribute `name` in the attribute section of a tag.
+fn attribute(mut attrs: &[u8], name: &str) -> Option<String> {
+    loop {
+        attrs = trim_start(attrs, |b| b.is_ascii_whitespace() || b == b'/');
+        if attrs.is_empty() {
+            return None;
+        }
+
+        let key_len = attrs
+            .iter()
+            .position(|b| b.is_ascii_whitespace() || *b == b'=' || *b == b'/')
+            .unwrap_or(attrs.len());
+        let key = &attrs[..key_len];
+        attrs = trim_start(&attrs[key_len..], |b| b.is_ascii_whitespace());
+
+        let value = if let Some(rest) = attrs.strip_prefix(b"=") {
+            let rest = trim_start(rest, |b| b.is_ascii_whitespace());
+            let (value, rest) = match rest.first() {
+                Some(&q) if q == b'"' || q == b'\'' => {
+                    let end = rest[1..].iter().position(|b| *b == q).map(|i| i + 1);
+                    let end = end.unwrap_or(rest.len());
+                    (&rest[1..end], rest.get(end + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let end = rest
+                        .iter()
+                        .position(|b| b.is_ascii_whitespace())
+                        .unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            attrs = rest;
+            Some(value)
+        } else {
+            None
+        };
+
+        if key.eq_ignore_ascii_case(name.as_bytes()) {
+            return value.map(|v| decode_entities(&String::from_utf8_lossy(v)));
+        }
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_owned();
+    }
+    value
+        .replace("&amp;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+}
+
+fn trim_start(buf: &[u8], f: impl Fn(u8) -> bool) -> &[u8] {
+    let start = buf.iter().position(|b| !f(*b)).unwrap_or(buf.len());
+    &buf[start..]
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(chunks: &[&str]) -> Vec<String> {
+        let mut extractor = LinkExtractor::new(Url::parse("https://example.com/dir/").unwrap());
+        chunks
+            .iter()
+            .flat_map(|c| extractor.feed(c.as_bytes()))
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn extracts_links_across_chunks() {
+        let links = extract(&[
+            "<html><head><link rel=stylesheet href='/s.css'><scr",
+            "ipt src=\"app.js\"></script></head><body>",
+            "<a class=x HREF=\"page?a=1&amp;b=2\">x</a><img src=https://cdn.example.org/i.png>",
+        ]);
+        assert_eq!(
+            links,
+            [
+                "https://example.com/s.css",
+                "https://example.com/dir/app.js",
+                "https://example.com/dir/page?a=1&b=2",
+                "https://cdn.example.org/i.png",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_comments_scripts_and_other_schemes() {
+        let links = extract(&[
+            "<!-- <a href=\"/hidden\"> -",
+            "-><script>if (a<b) { x = '<a href=\"/js\">'; }</scr",
+            "ipt><a href=\"mailto:a@example.com\"><a href=/shown>",
+        ]);
+        assert_eq!(links, ["https://example.com/shown"]);
+    }
+
+    #[test]
+    fn base_href_changes_resolution() {
+        let links = extract(&["<base href=\"https://other.example/x/\"><a href=y>"]);
+        assert_eq!(links, ["https://other.example/x/y"]);
+    }
+}
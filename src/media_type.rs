@@ -0,0 +1,160 @@
+use std::fmt::Write;
+
+use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use mime::Mime;
+
+/// A kind of content used for `Accept` negotiation.
+///
+/// See [`RequestBuilder::accepts`](crate::RequestBuilder::accepts) and
+/// [`Response::negotiated_type`](crate::Response::negotiated_type).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MediaType {
+    /// An HTML document, `text/html` or `application/xhtml+xml`.
+    Html,
+    /// A JSON document, `application/json` or any `+json` type.
+    Json,
+    /// An XML document, `application/xml`, `text/xml` or any `+xml` type.
+    Xml,
+    /// Plain text, `text/plain`.
+    Text,
+    /// A stylesheet, `text/css`.
+    Css,
+    /// A script, `application/javascript` or `text/javascript`.
+    Javascript,
+    /// An image, using the formats a browser advertises.
+    Image,
+    /// Anything, `*/*`.
+    Any,
+}
+
+impl MediaType {
+    /// The media ranges advertised for this type, sharing the same quality.
+    fn ranges(self) -> &'static [&'static str] {
+        match self {
+            MediaType::Html => &["text/html", "application/xhtml+xml"],
+            MediaType::Json => &["application/json"],
+            MediaType::Xml => &["application/xml"],
+            MediaType::Text => &["text/plain"],
+            MediaType::Css => &["text/css"],
+            MediaType::Javascript => &["application/javascript", "text/javascript"],
+            MediaType::Image => &[
+                "image/avif",
+                "image/webp",
+                "image/apng",
+                "image/svg+xml",
+                "image/*",
+            ],
+            MediaType::Any => &["*/*"],
+        }
+    }
+
+    fn matches(self, mime: &Mime) -> bool {
+        let (ty, subty, suffix) = (mime.type_(), mime.subtype(), mime.suffix());
+        match self {
+            MediaType::Html => {
+                (ty == mime::TEXT && subty == mime::HTML)
+                    || (ty == mime::APPLICATION && subty == "xhtml")
+            }
+            MediaType::Json => subty == mime::JSON || suffix == Some(mime::JSON),
+            MediaType::Xml => subty == mime::XML || suffix == Some(mime::XML),
+            MediaType::Text => ty == mime::TEXT && subty == mime::PLAIN,
+            MediaType::Css => ty == mime::TEXT && subty == mime::CSS,
+            MediaType::Javascript => subty == mime::JAVASCRIPT,
+            MediaType::Image => ty == mime::IMAGE,
+            MediaType::Any => true,
+        }
+    }
+
+    /// Build an `Accept` header value, in order of decreasing preference.
+    ///
+    /// The first type has an implicit quality of `1`, each following type
+    /// drops by `0.1`, and `*/*` always comes last. For example
+    /// `[Html, Xml, Any]` yields the Firefox navigation header
+    /// `text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8`.
+    pub(crate) fn accept_header(types: &[MediaType]) -> HeaderValue {
+        let mut ordered: Vec<MediaType> = Vec::with_capacity(types.len());
+        for ty in types.iter().filter(|ty| **ty != MediaType::Any) {
+            if !ordered.contains(ty) {
+                ordered.push(*ty);
+            }
+        }
+        if types.contains(&MediaType::Any) || ordered.is_empty() {
+            ordered.push(MediaType::Any);
+        }
+
+        let mut value = String::new();
+        for (i, ty) in ordered.iter().enumerate() {
+            let q = 10usize.saturating_sub(i).max(1);
+            for range in ty.ranges() {
+                if !value.is_empty() {
+                    value.push(',');
+                }
+                value.push_str(range);
+                if q < 10 {
+                    let _ = write!(value, ";q=0.{}", q);
+                }
+            }
+        }
+        HeaderValue::from_str(&value).expect("media ranges are valid header values")
+    }
+
+    /// The type of a response body according to its `Content-Type` header.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<MediaType> {
+        let mime: Mime = headers.get(CONTENT_TYPE)?.to_str().ok()?.parse().ok()?;
+        [
+            MediaType::Html,
+            MediaType::Json,
+            MediaType::Xml,
+            MediaType::Text,
+            MediaType::Css,
+            MediaType::Javascript,
+            MediaType::Image,
+        ]
+        .into_iter()
+        .find(|ty| ty.matches(&mime))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MediaType::*;
+    use super::*;
+
+    #[test]
+    fn accept_header_orders_by_preference() {
+        assert_eq!(
+            MediaType::accept_header(&[Html, Xml, Any]),
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+        );
+        assert_eq!(
+            MediaType::accept_header(&[Any, Json]),
+            "application/json,*/*;q=0.9"
+        );
+        assert_eq!(MediaType::accept_header(&[]), "*/*");
+    }
+
+    #[test]
+    fn negotiated_type_from_content_type() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(MediaType::from_headers(&headers), None);
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        assert_eq!(MediaType::from_headers(&headers), Some(Json));
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+        assert_eq!(MediaType::from_headers(&headers), Some(Html));
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/octet-stream"),
+        );
+        assert_eq!(MediaType::from_headers(&headers), None);
+    }
+}
@@ -0,0 +1,251 @@
+//! Middleware
+//!
+//! Layers set with [`ClientBuilder::with_layer`] wrap the sending of every
+//! request of a client, between [`RequestBuilder::send`] and its connection
+//! pool. They are `tower` layers over a [`Next`] service, so that retries,
+//! the refresh of auth tokens, the signing of requests or logging are
+//! written once, without wrapping the whole client. An [`Interceptor`], set
+//! with [`ClientBuilder::with_interceptor`], is the simpler form for edits
+//! that don't need to wait for anything.
+//!
+//! The first layer set is the outermost, seeing requests first and
+//! responses last. Redirects are followed by the client, so a layer sees
+//! the request as it was built and the response at the end of its
+//! redirects.
+//!
+//! The layers are applied once, when the client is built, and the service
+//! they make is cloned for each request, as `tower` services shared
+//! between tasks are. State the clones share, such as the permits of a
+//! `ConcurrencyLimit` or the worker of a `Buffer`, holds for all the
+//! requests of the client; a service whose clones don't share it, such as
+//! a `RateLimit`, is to be wrapped in a `Buffer`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::task::{Context, Poll};
+//!
+//! use rquest::middleware::Next;
+//! use rquest::{Request, Response};
+//! use tower_service::Service;
+//!
+//! #[derive(Clone)]
+//! struct Log(Next);
+//!
+//! impl Service<Request> for Log {
+//!     type Response = Response;
+//!     type Error = rquest::Error;
+//!     type Future = <Next as Service<Request>>::Future;
+//!
+//!     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), rquest::Error>> {
+//!         self.0.poll_ready(cx)
+//!     }
+//!
+//!     fn call(&mut self, req: Request) -> Self::Future {
+//!         println!("{} {}", req.method(), req.url());
+//!         self.0.call(req)
+//!     }
+//! }
+//!
+//! # fn run() -> Result<(), rquest::Error> {
+//! let client = rquest::Client::builder()
+//!     .with_layer(tower_layer::layer_fn(Log))
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ClientBuilder::with_layer`]: crate::ClientBuilder::with_layer
+//! [`ClientBuilder::with_interceptor`]: crate::ClientBuilder::with_interceptor
+//! [`RequestBuilder::send`]: crate::RequestBuilder::send
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::error::{self, BoxError};
+use crate::{Request, Response};
+
+pub(crate) type BoxFuture = Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>;
+
+/// A middleware layer of a client, wrapping the service of the next one.
+pub(crate) type Wrap = Arc<dyn Fn(Next) -> Next + Send + Sync>;
+
+/// The rest of the middleware of a client, then the client itself.
+///
+/// This is the service the layers set with
+/// [`ClientBuilder::with_layer`](crate::ClientBuilder::with_layer) wrap. It
+/// is always ready, and cheap to clone.
+#[derive(Clone)]
+pub struct Next {
+    inner: Arc<dyn Handle>,
+}
+
+/// Edits the requests of a client before they are sent, and the responses
+/// before they are returned.
+///
+/// Set with
+/// [`ClientBuilder::with_interceptor`](crate::ClientBuilder::with_interceptor).
+/// An error fails the request, as a request error.
+pub trait Interceptor: Send + Sync + 'static {
+    /// Edit `request`, such as to sign it, before it is sent.
+    fn request(
+        &self,
+        request: &mut Request,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// Edit `response` before it is returned.
+    fn response(
+        &self,
+        response: &mut Response,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _ = response;
+        Ok(())
+    }
+}
+
+trait Handle: Send + Sync {
+    fn handle(&self, req: Request) -> BoxFuture;
+}
+
+/// Sends requests with the client, past its middleware.
+struct Direct(Box<dyn Fn(Request) -> BoxFuture + Send + Sync>);
+
+/// Sends requests with the service of a layer, cloned for each request.
+struct Layered<S>(Mutex<S>);
+
+struct Intercepted {
+    interceptor: Arc<dyn Interceptor>,
+    next: Next,
+}
+
+/// Wrap `layer` to be set on a client.
+pub(crate) fn wrap<L>(layer: L) -> Wrap
+where
+    L: Layer<Next> + Send + Sync + 'static,
+    L::Service: Service<Request, Response = Response> + Clone + Send + 'static,
+    <L::Service as Service<Request>>::Error: Into<BoxError>,
+    <L::Service as Service<Request>>::Future: Send + 'static,
+{
+    Arc::new(move |next| Next::new(Layered(Mutex::new(layer.layer(next)))))
+}
+
+/// Wrap `interceptor` to be set on a client.
+pub(crate) fn intercept<I: Interceptor>(interceptor: I) -> Wrap {
+    let interceptor: Arc<dyn Interceptor> = Arc::new(interceptor);
+    Arc::new(move |next| {
+        Next::new(Intercepted {
+            interceptor: interceptor.clone(),
+            next,
+        })
+    })
+}
+
+/// The service of `layers`, sending requests through them, then with
+/// `direct`.
+pub(crate) fn stack<F>(layers: &[Wrap], direct: F) -> Next
+where
+    F: Fn(Request) -> BoxFuture + Send + Sync + 'static,
+{
+    layers
+        .iter()
+        .rev()
+        .fold(Next::new(Direct(Box::new(direct))), |next, wrap| wrap(next))
+}
+
+/// Turn the error of a layer into the error of a request, unless it is one.
+fn into_error(err: BoxError) -> crate::Error {
+    match err.downcast::<crate::Error>() {
+        Ok(err) => *err,
+        Err(err) => error::request(err),
+    }
+}
+
+// ===== impl Next =====
+
+impl Next {
+    fn new(handle: impl Handle + 'static) -> Next {
+        Next {
+            inner: Arc::new(handle),
+        }
+    }
+
+    /// Send `req` through the rest of the middleware.
+    pub(crate) fn send(&self, req: Request) -> BoxFuture {
+        self.inner.handle(req)
+    }
+}
+
+impl Service<Request> for Next {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        self.inner.handle(req)
+    }
+}
+
+impl fmt::Debug for Next {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Next").finish()
+    }
+}
+
+// ===== impl Handle =====
+
+impl Handle for Direct {
+    fn handle(&self, req: Request) -> BoxFuture {
+        (self.0)(req)
+    }
+}
+
+impl<S> Handle for Layered<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    fn handle(&self, req: Request) -> BoxFuture {
+        let mut service = self.0.lock().unwrap().clone();
+        Box::pin(async move {
+            futures_util::future::poll_fn(|cx| service.poll_ready(cx))
+                .await
+                .map_err(|err| into_error(err.into()))?;
+            service
+                .call(req)
+                .await
+                .map_err(|err| into_error(err.into()))
+        })
+    }
+}
+
+impl Handle for Intercepted {
+    fn handle(&self, mut req: Request) -> BoxFuture {
+        if let Err(err) = self.interceptor.request(&mut req) {
+            let err = error::request(err).with_url(req.url().clone());
+            return Box::pin(futures_util::future::ready(Err(err)));
+        }
+        let interceptor = self.interceptor.clone();
+        let pending = self.next.inner.handle(req);
+        Box::pin(async move {
+            let mut res = pending.await?;
+            match interceptor.response(&mut res) {
+                Ok(()) => Ok(res),
+                Err(err) => Err(error::request(err).with_url(res.url().clone())),
+            }
+        })
+    }
+}
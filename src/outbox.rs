@@ -0,0 +1,547 @@
+//! A disk-backed queue of requests.
+//!
+//! An [`Outbox`] persists requests to a directory, so that those made while
+//! offline survive restarts, and sends them in the order they were queued
+//! once the network is back. Each request is a file of its own, written
+//! before [`Outbox::enqueue`] returns.
+//!
+//! A request that can't be sent for now, because the server can't be
+//! reached or answers with `408`, `429` or a `5xx` status, stays queued for
+//! the next [`Outbox::flush`]. A request the server rejects is moved to the
+//! `failed` directory of the outbox, to be looked at. Responses with a
+//! `409 Conflict` or `412 Precondition Failed` status are given to the
+//! handler set with [`Outbox::on_conflict`], which decides what becomes of
+//! the request.
+//!
+//! A request may be sent again after the server has handled it, if the
+//! connection is lost before its response. Set an
+//! [`idempotency_key`](crate::RequestBuilder::idempotency_key) on the
+//! requests the server must not apply twice; it is queued with them.
+//!
+//! Only the method, URL, headers and body of requests are queued. Requests
+//! with a streaming body can't be. As the headers may hold credentials, the
+//! files are only readable by their owner on unix.
+//!
+//! A directory must be opened by a single `Outbox` at a time. Two of them,
+//! in one process or in several, would queue requests under the same
+//! names, and lose some of them.
+//!
+//! This requires the optional `outbox` feature to be enabled.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use http::header::{HeaderName, HeaderValue};
+use http::{Method, StatusCode};
+use url::Url;
+
+use crate::{error, Client, Request, Response};
+
+/// The first line of the file of a queued request starts with this.
+const MAGIC: &str = "rquest-outbox 1";
+
+const EXTENSION: &str = "req";
+
+const FAILED: &str = "failed";
+
+type ConflictHandler = dyn Fn(&Request, &Response) -> Resolution + Send + Sync;
+
+/// The order in which an [`Outbox`] sends its requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Order {
+    /// A request is sent only after all of those queued before it were.
+    ///
+    /// A request that can't be sent for now ends the flush.
+    #[default]
+    Strict,
+    /// Requests are sent in the order they were queued, but one that can't
+    /// be sent for now doesn't hold back those queued after it.
+    Independent,
+}
+
+/// What becomes of a queued request the server answered with a conflict.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Resolution {
+    /// Remove the request from the queue.
+    Discard,
+    /// Keep the request queued, to send it again on the next flush.
+    Retry,
+    /// Queue another request in place of this one, sent on the next flush.
+    Replace(Request),
+    /// Move the request to the `failed` directory of the outbox.
+    Fail,
+}
+
+/// The outcome of [`Outbox::flush`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Flushed {
+    /// The requests that were sent, and removed from the queue.
+    pub sent: usize,
+    /// The requests that a conflict handler discarded.
+    pub discarded: usize,
+    /// The requests that were moved to the `failed` directory.
+    pub failed: usize,
+    /// The requests still queued.
+    pub pending: usize,
+}
+
+/// A queue of requests persisted to a directory, sent when the network is
+/// back.
+///
+/// An outbox can be shared between tasks; only one flush runs at a time.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use rquest::outbox::Outbox;
+///
+/// # async fn run() -> Result<(), rquest::Error> {
+/// let client = rquest::Client::new();
+/// let outbox = Outbox::new(client.clone(), "/var/lib/agent/outbox")?;
+///
+/// let reading = client
+///     .post("https://example.com/readings")
+///     .idempotency_key("reading-1337")
+///     .body("{\"celsius\":21.5}")
+///     .build()?;
+/// outbox.enqueue(reading).await?;
+///
+/// loop {
+///     let flushed = outbox.flush().await?;
+///     if flushed.pending == 0 {
+///         break;
+///     }
+///     tokio::time::sleep(Duration::from_secs(30)).await;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Outbox {
+    client: Client,
+    dir: PathBuf,
+    order: Order,
+    max_attempts: Option<u32>,
+    on_conflict: Option<Arc<ConflictHandler>>,
+    next: AtomicU64,
+    flushing: tokio::sync::Mutex<()>,
+}
+
+/// What became of a request a flush sent.
+enum Outcome {
+    Sent,
+    Resolved(Resolution),
+}
+
+impl Outbox {
+    /// Open the outbox in `dir`, sending its requests with `client`.
+    ///
+    /// The directory is created if needed. The requests already queued in
+    /// it, such as before a restart, are kept.
+    ///
+    /// No other `Outbox` may use the directory while this one does.
+    pub fn new<P: AsRef<Path>>(client: Client, dir: P) -> crate::Result<Outbox> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(dir.join(FAILED)).map_err(error::builder)?;
+        // Failed requests keep their names, which new ones mustn't take.
+        let mut next = 0;
+        for dir in [dir.clone(), dir.join(FAILED)] {
+            if let Some((seq, _)) = queued(&dir).map_err(error::builder)?.last() {
+                next = next.max(seq + 1);
+            }
+        }
+
+        Ok(Outbox {
+            client,
+            dir,
+            order: Order::default(),
+            max_attempts: None,
+            on_conflict: None,
+            next: AtomicU64::new(next),
+            flushing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Set the order in which requests are sent.
+    ///
+    /// Default is [`Order::Strict`].
+    pub fn order(mut self, order: Order) -> Outbox {
+        self.order = order;
+        self
+    }
+
+    /// Set how many flushes may fail to send a request before it is moved
+    /// to the `failed` directory.
+    ///
+    /// Default is no limit.
+    pub fn max_attempts(mut self, max: u32) -> Outbox {
+        self.max_attempts = Some(max);
+        self
+    }
+
+    /// Set the handler of the requests answered with a `409 Conflict` or
+    /// `412 Precondition Failed` status.
+    ///
+    /// Without one, these requests are moved to the `failed` directory.
+    pub fn on_conflict<F>(mut self, handler: F) -> Outbox
+    where
+        F: Fn(&Request, &Response) -> Resolution + Send + Sync + 'static,
+    {
+        self.on_conflict = Some(Arc::new(handler));
+        self
+    }
+
+    /// Queue `request`, persisting it before returning.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the request has a streaming body, or can't be written.
+    pub async fn enqueue(&self, request: Request) -> crate::Result<()> {
+        let url = request.url().clone();
+        let data = serialize(0, &request)?;
+        let path = self.path(self.next.fetch_add(1, Ordering::SeqCst));
+        blocking(move || write(&path, &data))
+            .await
+            .map_err(|e| error::request(e).with_url(url))
+    }
+
+    /// Get the number of queued requests.
+    pub async fn pending(&self) -> crate::Result<usize> {
+        let dir = self.dir.clone();
+        let queued = blocking(move || queued(&dir))
+            .await
+            .map_err(error::request)?;
+        Ok(queued.len())
+    }
+
+    /// Send the queued requests, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the queue can't be read or written. Requests that
+    /// can't be sent are not errors, but left queued or failed.
+    pub async fn flush(&self) -> crate::Result<Flushed> {
+        let _flushing = self.flushing.lock().await;
+        let dir = self.dir.clone();
+        let queued = blocking(move || queued(&dir))
+            .await
+            .map_err(error::request)?;
+
+        let mut flushed = Flushed::default();
+        for (i, (_, path)) in queued.iter().enumerate() {
+            let path = path.as_path();
+            let file = path.to_owned();
+            let data = blocking(move || fs::read(file))
+                .await
+                .map_err(error::request)?;
+            let (attempts, req) = match parse(&data) {
+                Some(entry) => entry,
+                None => {
+                    log::warn!("outbox: unreadable request {}", path.display());
+                    self.fail(path).await?;
+                    flushed.failed += 1;
+                    continue;
+                }
+            };
+
+            let resolution = match self.send(&req).await {
+                Outcome::Sent => {
+                    self.remove(path).await?;
+                    flushed.sent += 1;
+                    continue;
+                }
+                Outcome::Resolved(resolution) => resolution,
+            };
+            let held = match resolution {
+                Resolution::Discard => {
+                    self.remove(path).await?;
+                    flushed.discarded += 1;
+                    false
+                }
+                Resolution::Fail => {
+                    self.fail(path).await?;
+                    flushed.failed += 1;
+                    false
+                }
+                Resolution::Replace(req) => {
+                    self.rewrite(path, 0, &req).await?;
+                    flushed.pending += 1;
+                    true
+                }
+                Resolution::Retry => {
+                    let attempts = attempts.saturating_add(1);
+                    if self.max_attempts.map_or(false, |max| attempts >= max) {
+                        self.fail(path).await?;
+                        flushed.failed += 1;
+                        false
+                    } else {
+                        self.rewrite(path, attempts, &req).await?;
+                        flushed.pending += 1;
+                        true
+                    }
+                }
+            };
+            if held && self.order == Order::Strict {
+                flushed.pending += queued.len() - i - 1;
+                break;
+            }
+        }
+        Ok(flushed)
+    }
+
+    async fn send(&self, req: &Request) -> Outcome {
+        let copy = req.try_clone().expect("queued requests have bytes bodies");
+        match self.client.execute(copy).await {
+            Ok(res) => match res.status() {
+                status if !status.is_client_error() && !status.is_server_error() => Outcome::Sent,
+                StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED => {
+                    Outcome::Resolved(match self.on_conflict {
+                        Some(ref handler) => handler(req, &res),
+                        None => Resolution::Fail,
+                    })
+                }
+                StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => {
+                    Outcome::Resolved(Resolution::Retry)
+                }
+                status if status.is_server_error() => Outcome::Resolved(Resolution::Retry),
+                status => {
+                    log::debug!(
+                        "outbox: {} {} rejected: {}",
+                        req.method(),
+                        req.url(),
+                        status
+                    );
+                    Outcome::Resolved(Resolution::Fail)
+                }
+            },
+            Err(err) if err.is_connect() || err.is_timeout() || crate::retry::is_reset(&err) => {
+                log::debug!("outbox: {} {} not sent: {}", req.method(), req.url(), err);
+                Outcome::Resolved(Resolution::Retry)
+            }
+            Err(err) => {
+                log::debug!("outbox: {} {} failed: {}", req.method(), req.url(), err);
+                Outcome::Resolved(Resolution::Fail)
+            }
+        }
+    }
+
+    fn path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("{:020}.{}", seq, EXTENSION))
+    }
+
+    async fn rewrite(&self, path: &Path, attempts: u32, req: &Request) -> crate::Result<()> {
+        let data = serialize(attempts, req)?;
+        let path = path.to_owned();
+        blocking(move || write(&path, &data))
+            .await
+            .map_err(error::request)
+    }
+
+    async fn remove(&self, path: &Path) -> crate::Result<()> {
+        let path = path.to_owned();
+        blocking(move || fs::remove_file(path))
+            .await
+            .map_err(error::request)
+    }
+
+    async fn fail(&self, path: &Path) -> crate::Result<()> {
+        let from = path.to_owned();
+        let to = self
+            .dir
+            .join(FAILED)
+            .join(path.file_name().unwrap_or_default());
+        blocking(move || fs::rename(from, to))
+            .await
+            .map_err(error::request)
+    }
+}
+
+impl fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Outbox")
+            .field("dir", &self.dir)
+            .field("order", &self.order)
+            .field("max_attempts", &self.max_attempts)
+            .finish()
+    }
+}
+
+/// Run the file system operation `f` off the runtime.
+async fn blocking<T, F>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(io::Error::new(io::ErrorKind::Other, e)))
+}
+
+/// List the queued requests of `dir`, oldest first.
+fn queued(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut queued = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != EXTENSION) {
+            continue;
+        }
+        let seq = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u64>().ok());
+        if let Some(seq) = seq {
+            queued.push((seq, path));
+        }
+    }
+    queued.sort();
+    Ok(queued)
+}
+
+/// Write `data` to `path` at once, so that a crash can't leave half of it.
+fn write(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)
+}
+
+/// Serialize `req` as the file of a queued request:
+///
+/// ```text
+/// rquest-outbox 1 <attempts> <body length, or "-" without a body>
+/// <method> <url>
+/// <name>: <value>
+/// ...
+///
+/// <body>
+/// ```
+fn serialize(attempts: u32, req: &Request) -> crate::Result<Vec<u8>> {
+    let body = match req.body() {
+        Some(body) => match body.as_bytes() {
+            Some(bytes) => Some(bytes),
+            None => {
+                return Err(
+                    error::builder("streaming bodies can't be queued").with_url(req.url().clone())
+                )
+            }
+        },
+        None => None,
+    };
+
+    let mut data = match body {
+        Some(body) => format!("{} {} {}\n", MAGIC, attempts, body.len()),
+        None => format!("{} {} -\n", MAGIC, attempts),
+    }
+    .into_bytes();
+    data.extend_from_slice(format!("{} {}\n", req.method(), req.url()).as_bytes());
+    for (name, value) in req.headers() {
+        data.extend_from_slice(name.as_str().as_bytes());
+        data.extend_from_slice(b": ");
+        data.extend_from_slice(value.as_bytes());
+        data.push(b'\n');
+    }
+    data.push(b'\n');
+    data.extend_from_slice(body.unwrap_or_default());
+    Ok(data)
+}
+
+/// Parse the file of a queued request, into its attempts and the request.
+fn parse(data: &[u8]) -> Option<(u32, Request)> {
+    let mut lines = data.split(|b| *b == b'\n');
+    let mut consumed = 0;
+    let mut line = || {
+        let line = lines.next()?;
+        consumed += line.len() + 1;
+        Some(line)
+    };
+
+    let first = std::str::from_utf8(line()?).ok()?;
+    let mut fields = first.strip_prefix(MAGIC)?.split_whitespace();
+    let attempts = fields.next()?.parse().ok()?;
+    let body_len = match fields.next()? {
+        "-" => None,
+        len => Some(len.parse::<usize>().ok()?),
+    };
+
+    let request_line = std::str::from_utf8(line()?).ok()?;
+    let (method, url) = request_line.split_once(' ')?;
+    let mut req = Request::new(
+        Method::from_bytes(method.as_bytes()).ok()?,
+        Url::parse(url).ok()?,
+    );
+
+    loop {
+        let header = line()?;
+        if header.is_empty() {
+            break;
+        }
+        let at = header.windows(2).position(|w| w == b": ")?;
+        req.headers_mut().append(
+            HeaderName::from_bytes(&header[..at]).ok()?,
+            HeaderValue::from_bytes(&header[at + 2..]).ok()?,
+        );
+    }
+
+    if let Some(len) = body_len {
+        let body = data.get(consumed..)?;
+        if body.len() != len {
+            return None;
+        }
+        *req.body_mut() = Some(body.to_vec().into());
+    }
+    Some((attempts, req))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_roundtrip() {
+        let mut req = Request::new(Method::POST, "https://example.com/a?b=c".parse().unwrap());
+        req.headers_mut()
+            .insert("content-type", HeaderValue::from_static("application/json"));
+        req.headers_mut()
+            .append("x-tag", HeaderValue::from_static("one"));
+        req.headers_mut()
+            .append("x-tag", HeaderValue::from_static("two"));
+        *req.body_mut() = Some("{\"a\":\n1}".into());
+
+        let (attempts, parsed) = parse(&serialize(3, &req).unwrap()).unwrap();
+        assert_eq!(attempts, 3);
+        assert_eq!(parsed.method(), Method::POST);
+        assert_eq!(parsed.url().as_str(), "https://example.com/a?b=c");
+        assert_eq!(parsed.headers(), req.headers());
+        assert_eq!(
+            parsed.body().and_then(|b| b.as_bytes()),
+            Some(&b"{\"a\":\n1}"[..])
+        );
+
+        let get = Request::new(Method::GET, "https://example.com/".parse().unwrap());
+        let (_, parsed) = parse(&serialize(0, &get).unwrap()).unwrap();
+        assert!(parsed.body().is_none());
+    }
+
+    #[test]
+    fn parse_truncated() {
+        let mut req = Request::new(Method::PUT, "https://example.com/".parse().unwrap());
+        *req.body_mut() = Some("0123456789".into());
+        let mut data = serialize(0, &req).unwrap();
+        data.truncate(data.len() - 1);
+
+        assert!(parse(&data).is_none());
+        assert!(parse(b"something else\n").is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! HAProxy PROXY protocol headers.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>.
+
+use std::net::{IpAddr, SocketAddr};
+
+/// The version of the PROXY protocol header to send.
+///
+/// See [`ClientBuilder::proxy_protocol`](crate::ClientBuilder::proxy_protocol).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProxyProtocol {
+    /// The human-readable text header.
+    V1,
+    /// The binary header.
+    V2,
+}
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// A PROXY protocol header to send at the start of each direct connection.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ProxyHeader {
+    pub(crate) version: ProxyProtocol,
+    /// The announced client address, defaulting to the socket's local address.
+    pub(crate) source: Option<SocketAddr>,
+}
+
+impl ProxyHeader {
+    /// Encode the header for a connection from `local` to `peer`.
+    pub(crate) fn encode(&self, local: SocketAddr, peer: SocketAddr) -> Vec<u8> {
+        let (src, dst) = same_family(self.source.unwrap_or(local), peer);
+        match self.version {
+            ProxyProtocol::V1 => {
+                let proto = if src.is_ipv4() { "TCP4" } else { "TCP6" };
+                format!(
+                    "PROXY {} {} {} {} {}\r\n",
+                    proto,
+                    src.ip(),
+                    dst.ip(),
+                    src.port(),
+                    dst.port()
+                )
+                .into_bytes()
+            }
+            ProxyProtocol::V2 => {
+                let mut buf = Vec::with_capacity(16 + 36);
+                buf.extend_from_slice(&V2_SIGNATURE);
+                // Version 2, PROXY command.
+                buf.push(0x21);
+                match (src.ip(), dst.ip()) {
+                    (IpAddr::V4(src_ip), IpAddr::V4(dst_ip)) => {
+                        // AF_INET, STREAM.
+                        buf.push(0x11);
+                        buf.extend_from_slice(&12u16.to_be_bytes());
+                        buf.extend_from_slice(&src_ip.octets());
+                        buf.extend_from_slice(&dst_ip.octets());
+                    }
+                    (src_ip, dst_ip) => {
+                        // AF_INET6, STREAM.
+                        buf.push(0x21);
+                        buf.extend_from_slice(&36u16.to_be_bytes());
+                        buf.extend_from_slice(&to_ipv6(src_ip).octets());
+                        buf.extend_from_slice(&to_ipv6(dst_ip).octets());
+                    }
+                }
+                buf.extend_from_slice(&src.port().to_be_bytes());
+                buf.extend_from_slice(&dst.port().to_be_bytes());
+                buf
+            }
+        }
+    }
+}
+
+/// Both addresses must be of the same family, so map IPv4 into IPv6 if needed.
+fn same_family(src: SocketAddr, dst: SocketAddr) -> (SocketAddr, SocketAddr) {
+    if src.is_ipv4() == dst.is_ipv4() {
+        return (src, dst);
+    }
+    (
+        SocketAddr::new(IpAddr::V6(to_ipv6(src.ip())), src.port()),
+        SocketAddr::new(IpAddr::V6(to_ipv6(dst.ip())), dst.port()),
+    )
+}
+
+fn to_ipv6(ip: IpAddr) -> std::net::Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: ProxyProtocol, source: Option<&str>) -> ProxyHeader {
+        ProxyHeader {
+            version,
+            source: source.map(|s| s.parse().unwrap()),
+        }
+    }
+
+    #[test]
+    fn v1_header() {
+        let local = "10.0.0.1:40000".parse().unwrap();
+        let peer = "93.184.216.34:443".parse().unwrap();
+        assert_eq!(
+            header(ProxyProtocol::V1, None).encode(local, peer),
+            b"PROXY TCP4 10.0.0.1 93.184.216.34 40000 443\r\n"
+        );
+        assert_eq!(
+            header(ProxyProtocol::V1, Some("[2001:db8::1]:1234")).encode(local, peer),
+            b"PROXY TCP6 2001:db8::1 ::ffff:93.184.216.34 1234 443\r\n"
+        );
+    }
+
+    #[test]
+    fn v2_header() {
+        let local = "10.0.0.1:40000".parse().unwrap();
+        let peer = "93.184.216.34:443".parse().unwrap();
+        let buf = header(ProxyProtocol::V2, Some("192.0.2.7:1234")).encode(local, peer);
+        assert_eq!(&buf[..12], &V2_SIGNATURE);
+        assert_eq!(
+            &buf[12..],
+            [0x21, 0x11, 0, 12, 192, 0, 2, 7, 93, 184, 216, 34, 0x04, 0xd2, 0x01, 0xbb]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+//! Per-host rate limiting
+//!
+//! A [`RateLimit`] set with [`ClientBuilder::rate_limit`] spaces out the
+//! requests a `Client` sends to each host. Every host has a token bucket
+//! refilled at a steady rate, and may additionally have a crawl delay, the
+//! least time between two of its requests, as found in a `robots.txt`.
+//! Requests over the limit wait before they are sent.
+//!
+//! The state of each bucket can be kept in a [`Store`], so that a restarted
+//! crawler picks up where it left off instead of bursting against hosts it
+//! was already throttling.
+//!
+//! [`ClientBuilder::rate_limit`]: crate::ClientBuilder::rate_limit
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Hosts remembered in memory, beyond which idle ones are forgotten.
+const MAX_HOSTS: usize = 4096;
+
+/// The longest a request waits for its slot, to which the waits of extreme
+/// rates and crawl delays are cut.
+const MAX_WAIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A limit on the rate of requests to each host.
+///
+/// Clones share their state, so a crawl delay set on one applies to the
+/// clients built with the others.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn run() -> Result<(), rquest::Error> {
+/// use rquest::rate_limit::RateLimit;
+///
+/// // 2 requests a second, in bursts of up to 5.
+/// let limit = RateLimit::new(2, Duration::from_secs(1)).burst(5);
+/// let client = rquest::Client::builder()
+///     .rate_limit(limit.clone())
+///     .build()?;
+///
+/// // Honor the `Crawl-delay` of a robots.txt.
+/// limit.set_crawl_delay("example.com", Some(Duration::from_secs(10)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RateLimit {
+    /// Tokens added per second.
+    rate: f64,
+    burst: f64,
+    store: Option<Arc<dyn Store>>,
+    hosts: Arc<Mutex<HashMap<String, HostState>>>,
+}
+
+/// The state of the bucket of a host, as kept in a [`Store`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostState {
+    /// The tokens left in the bucket at `updated`.
+    pub tokens: f64,
+    /// When `tokens` was measured.
+    pub updated: SystemTime,
+    /// When the latest request was allowed to start.
+    pub last_request: Option<SystemTime>,
+    /// The least time between two requests to the host.
+    pub crawl_delay: Option<Duration>,
+}
+
+/// Storage of the state of rate limits, surviving a restart.
+///
+/// The state of a host is loaded the first time a request to it is made,
+/// and saved each time it changes. Both calls are made while a request is
+/// being started, so they should be quick, for instance writing to a local
+/// database or buffering writes to a remote one.
+pub trait Store: Send + Sync {
+    /// Load the state of `host`, if it was saved.
+    ///
+    /// A state that can't be valid, such as one with more tokens than the
+    /// burst size, or times more than a day ahead, is corrected.
+    fn load(&self, host: &str) -> Option<HostState>;
+    /// Save the state of `host`.
+    fn save(&self, host: &str, state: &HostState);
+}
+
+impl RateLimit {
+    /// Allow `requests` requests to each host every `per`.
+    ///
+    /// The burst size defaults to `requests`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `requests` or `per` is zero.
+    pub fn new(requests: u32, per: Duration) -> RateLimit {
+        assert!(requests > 0, "rate limit must allow some requests");
+        assert!(!per.is_zero(), "rate limit period must not be zero");
+        RateLimit {
+            rate: f64::from(requests) / per.as_secs_f64(),
+            burst: f64::from(requests),
+            store: None,
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set the most requests to a host that can be sent at once, after it
+    /// has not been requested for a while.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `burst` is zero.
+    pub fn burst(mut self, burst: u32) -> RateLimit {
+        assert!(burst > 0, "rate limit burst must not be zero");
+        self.burst = f64::from(burst);
+        self
+    }
+
+    /// Keep the state of the limit in `store`.
+    pub fn store<S: Store + 'static>(mut self, store: S) -> RateLimit {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Set the crawl delay of `host`, or remove it with `None`.
+    pub fn set_crawl_delay(&self, host: &str, delay: Option<Duration>) {
+        let state = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let state = self.entry(&mut hosts, host, SystemTime::now());
+            state.crawl_delay = delay;
+            state.clone()
+        };
+        self.save(host, &state);
+    }
+
+    /// The crawl delay of `host`.
+    pub fn crawl_delay(&self, host: &str) -> Option<Duration> {
+        let mut hosts = self.hosts.lock().unwrap();
+        self.entry(&mut hosts, host, SystemTime::now()).crawl_delay
+    }
+
+    /// Reserve the next slot for a request to `host`, returning how long
+    /// to wait before sending it.
+    pub(crate) fn reserve(&self, host: &str) -> Duration {
+        self.reserve_at(host, SystemTime::now())
+    }
+
+    fn reserve_at(&self, host: &str, now: SystemTime) -> Duration {
+        let (wait, state) = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let (rate, burst) = (self.rate, self.burst);
+            let state = self.entry(&mut hosts, host, now);
+
+            // Slots are reserved ahead, so the bucket may be measured after
+            // `now`.
+            let base = state.updated.max(now);
+            let tokens = refill(state, base, rate, burst);
+            let mut start = base;
+            if tokens < 1.0 {
+                let wait = Duration::try_from_secs_f64((1.0 - tokens) / rate).unwrap_or(MAX_WAIT);
+                start = after(start, wait);
+            }
+            if let (Some(delay), Some(last)) = (state.crawl_delay, state.last_request) {
+                start = start.max(after(last, delay));
+            }
+
+            state.tokens = (tokens + elapsed(start, base) * rate).min(burst) - 1.0;
+            state.updated = start;
+            state.last_request = Some(start);
+            (since(start, now), state.clone())
+        };
+        self.save(host, &state);
+        wait
+    }
+
+    /// The state of `host`, loading it if it isn't in memory.
+    fn entry<'a>(
+        &self,
+        hosts: &'a mut HashMap<String, HostState>,
+        host: &str,
+        now: SystemTime,
+    ) -> &'a mut HostState {
+        if !hosts.contains_key(host) {
+            if hosts.len() >= MAX_HOSTS {
+                // Forget hosts back to a full bucket, which a store can
+                // reload.
+                let (rate, burst) = (self.rate, self.burst);
+                hosts.retain(|_, state| {
+                    state.crawl_delay.is_some() || refill(state, now, rate, burst) < burst
+                });
+            }
+            let state = self
+                .store
+                .as_ref()
+                .and_then(|store| store.load(host))
+                .map(|state| checked(state, now, self.burst))
+                .unwrap_or(HostState {
+                    tokens: self.burst,
+                    updated: now,
+                    last_request: None,
+                    crawl_delay: None,
+                });
+            hosts.insert(host.to_owned(), state);
+        }
+        hosts.get_mut(host).expect("host was just inserted")
+    }
+
+    fn save(&self, host: &str, state: &HostState) {
+        if let Some(ref store) = self.store {
+            store.save(host, state);
+        }
+    }
+}
+
+impl fmt::Debug for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("rate", &self.rate)
+            .field("burst", &self.burst)
+            .field("store", &self.store.is_some())
+            .finish()
+    }
+}
+
+/// The tokens in the bucket of `state` at `at`.
+fn refill(state: &HostState, at: SystemTime, rate: f64, burst: f64) -> f64 {
+    (state.tokens + elapsed(at, state.updated) * rate).min(burst)
+}
+
+/// `time` plus `delay`, cut to `MAX_WAIT`.
+fn after(time: SystemTime, delay: Duration) -> SystemTime {
+    time.checked_add(delay.min(MAX_WAIT)).unwrap_or(time)
+}
+
+/// Correct a state loaded from a `Store`, which may have been written by
+/// another limit, or be corrupt.
+fn checked(mut state: HostState, now: SystemTime, burst: f64) -> HostState {
+    state.tokens = if state.tokens.is_finite() {
+        state.tokens.clamp(0.0, burst)
+    } else {
+        burst
+    };
+    let latest = after(now, MAX_WAIT);
+    state.updated = state.updated.min(latest);
+    state.last_request = state.last_request.map(|last| last.min(latest));
+    state.crawl_delay = state.crawl_delay.map(|delay| delay.min(MAX_WAIT));
+    state
+}
+
+fn since(later: SystemTime, earlier: SystemTime) -> Duration {
+    later.duration_since(earlier).unwrap_or_default()
+}
+
+fn elapsed(later: SystemTime, earlier: SystemTime) -> f64 {
+    since(later, earlier).as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<Mutex<HashMap<String, HostState>>>);
+
+    impl Store for MemoryStore {
+        fn load(&self, host: &str) -> Option<HostState> {
+            self.0.lock().unwrap().get(host).cloned()
+        }
+
+        fn save(&self, host: &str, state: &HostState) {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(host.to_owned(), state.clone());
+        }
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn bursts_then_spaces_requests() {
+        let limit = RateLimit::new(1, secs(1)).burst(2);
+        let now = SystemTime::now();
+        assert_eq!(limit.reserve_at("a.com", now), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", now), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", now), secs(1));
+        assert_eq!(limit.reserve_at("a.com", now), secs(2));
+        assert_eq!(limit.reserve_at("b.com", now), Duration::ZERO);
+
+        // The bucket refills while the host isn't requested.
+        let later = now + secs(10);
+        assert_eq!(limit.reserve_at("a.com", later), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", later), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", later), secs(1));
+    }
+
+    #[test]
+    fn crawl_delay() {
+        let limit = RateLimit::new(10, secs(1));
+        limit.set_crawl_delay("a.com", Some(secs(5)));
+        let now = SystemTime::now() + secs(1);
+        assert_eq!(limit.reserve_at("a.com", now), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", now), secs(5));
+        assert_eq!(limit.reserve_at("a.com", now + secs(7)), secs(3));
+    }
+
+    #[test]
+    fn persists_across_restarts() {
+        let store = MemoryStore::default();
+        let now = SystemTime::now();
+
+        let limit = RateLimit::new(1, secs(1)).store(store.clone());
+        limit.set_crawl_delay("a.com", Some(secs(3)));
+        assert_eq!(limit.reserve_at("a.com", now + secs(1)), Duration::ZERO);
+        drop(limit);
+
+        let limit = RateLimit::new(1, secs(1)).store(store);
+        assert_eq!(limit.crawl_delay("a.com"), Some(secs(3)));
+        assert_eq!(limit.reserve_at("a.com", now + secs(2)), secs(2));
+    }
+
+    #[test]
+    fn extreme_values_wait_at_most_a_day() {
+        let limit = RateLimit::new(1, Duration::MAX).burst(1);
+        let now = SystemTime::now();
+        assert_eq!(limit.reserve_at("a.com", now), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", now), MAX_WAIT);
+
+        let limit = RateLimit::new(1, secs(1));
+        limit.set_crawl_delay("a.com", Some(Duration::MAX));
+        let now = now + secs(1);
+        assert_eq!(limit.reserve_at("a.com", now), Duration::ZERO);
+        assert_eq!(limit.reserve_at("a.com", now), MAX_WAIT);
+    }
+
+    #[test]
+    fn corrects_loaded_state() {
+        let store = MemoryStore::default();
+        let now = SystemTime::now();
+        store.save(
+            "a.com",
+            &HostState {
+                tokens: f64::NAN,
+                updated: now,
+                last_request: Some(now + secs(365 * 24 * 60 * 60)),
+                crawl_delay: None,
+            },
+        );
+
+        let limit = RateLimit::new(1, secs(1)).store(store);
+        assert_eq!(limit.reserve_at("a.com", now), Duration::ZERO);
+        let state = limit.hosts.lock().unwrap()["a.com"].clone();
+        assert!(state.tokens.is_finite());
+        assert!(state.last_request.unwrap() <= now + MAX_WAIT);
+    }
+}
@@ -0,0 +1,346 @@
+//! The raw heads of HTTP/1 responses.
+//!
+//! A `HeaderMap` groups the values of each header and lowercases names, so
+//! the head of a response is recorded as read from the connection when
+//! [`ClientBuilder::http1_preserve_response_headers`] is set.
+//!
+//! [`ClientBuilder::http1_preserve_response_headers`]: crate::ClientBuilder::http1_preserve_response_headers
+
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http::header::HeaderValue;
+
+/// Heads larger than this aren't recorded.
+const MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// Records the head of the latest response read on an HTTP/1 connection.
+///
+/// A response starts with the first bytes read after a request is written,
+/// as HTTP/1 clients don't pipeline requests.
+#[derive(Clone, Default)]
+pub(crate) struct HeadRecorder(Arc<Mutex<State>>);
+
+#[derive(Default)]
+struct State {
+    reading: bool,
+    buf: Vec<u8>,
+    head: Option<Bytes>,
+}
+
+/// The head of a response, in its extensions.
+#[derive(Clone)]
+pub(crate) struct RawHead(pub(crate) Bytes);
+
+impl HeadRecorder {
+    /// A request was written, so the next bytes read start its response.
+    pub(crate) fn on_write(&self) {
+        let mut state = self.0.lock().unwrap();
+        if !state.reading {
+            state.reading = true;
+            state.buf.clear();
+        }
+    }
+
+    pub(crate) fn on_read(&self, data: &[u8]) {
+        let mut state = self.0.lock().unwrap();
+        if !state.reading || data.is_empty() {
+            return;
+        }
+        state.buf.extend_from_slice(data);
+        while let Some(end) = head_end(&state.buf) {
+            let head = Bytes::copy_from_slice(&state.buf[..end]);
+            state.buf.drain(..end);
+            // Informational responses precede the final one.
+            let informational = head.get(9) == Some(&b'1') && !head[9..].starts_with(b"101");
+            state.head = Some(head);
+            if !informational {
+                state.reading = false;
+                state.buf.clear();
+                return;
+            }
+        }
+        if state.buf.len() > MAX_HEAD_LEN {
+            state.reading = false;
+            state.buf.clear();
+            state.head = None;
+        }
+    }
+
+    /// The head of the latest response.
+    pub(crate) fn latest(&self) -> Option<Bytes> {
+        self.0.lock().unwrap().head.clone()
+    }
+}
+
+/// Collapses the whitespace between the parts of the status lines of HTTP/1
+/// responses, as in `HTTP/1.1  200\tOK`, which browsers accept but the
+/// parser rejects.
+///
+/// Like [`HeadRecorder`], it takes the first bytes read after a request is
+/// written as the start of a response.
+#[derive(Default)]
+pub(crate) struct StatusLine {
+    at: At,
+    code: u16,
+}
+
+/// Where in a response head the next byte read is.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum At {
+    /// Past the status line, until a request is written.
+    #[default]
+    Done,
+    /// In the version, after this many bytes of it.
+    Version(usize),
+    /// In the whitespace after the version.
+    AfterVersion,
+    Code,
+    /// In the whitespace after the status code.
+    AfterCode,
+    Reason,
+    /// In the header fields of an informational response, at the start of
+    /// a line or not.
+    Fields(bool),
+}
+
+impl StatusLine {
+    /// A request was written, so the next bytes read start its response.
+    pub(crate) fn on_write(&mut self) {
+        if self.at == At::Done {
+            self.at = At::Version(0);
+            self.code = 0;
+        }
+    }
+
+    /// Rewrites `data` as read from the connection in place, returning how
+    /// many of its bytes are left.
+    pub(crate) fn on_read(&mut self, data: &mut [u8]) -> usize {
+        let mut len = 0;
+        for i in 0..data.len() {
+            if let Some(b) = self.step(data[i]) {
+                data[len] = b;
+                len += 1;
+            }
+        }
+        len
+    }
+
+    fn step(&mut self, b: u8) -> Option<u8> {
+        let blank = b == b' ' || b == b'\t';
+        match self.at {
+            At::Done => {}
+            At::Version(n) if n < 5 => {
+                // Not a response head, so it is left alone.
+                self.at = if b == b"HTTP/"[n] {
+                    At::Version(n + 1)
+                } else {
+                    At::Done
+                };
+            }
+            At::Version(_) if blank => {
+                self.at = At::AfterVersion;
+                return Some(b' ');
+            }
+            At::Version(n) => {
+                self.at = if b == b'\n' || n > 16 {
+                    At::Done
+                } else {
+                    At::Version(n + 1)
+                };
+            }
+            At::AfterVersion if blank => return None,
+            At::AfterVersion => {
+                self.at = At::Code;
+                return self.step(b);
+            }
+            At::Code if blank => {
+                self.at = At::AfterCode;
+                return Some(b' ');
+            }
+            At::Code if b.is_ascii_digit() => {
+                self.code = self
+                    .code
+                    .saturating_mul(10)
+                    .saturating_add(u16::from(b - b'0'));
+            }
+            At::Code => {
+                self.at = At::Reason;
+                return self.step(b);
+            }
+            At::AfterCode if blank => return None,
+            At::AfterCode => {
+                self.at = At::Reason;
+                return self.step(b);
+            }
+            At::Reason if b == b'\n' => {
+                // Informational responses precede the final one.
+                self.at = if (100..200).contains(&self.code) && self.code != 101 {
+                    At::Fields(true)
+                } else {
+                    At::Done
+                };
+            }
+            At::Reason => {}
+            At::Fields(start) => {
+                self.at = match b {
+                    b'\n' if start => {
+                        self.code = 0;
+                        At::Version(0)
+                    }
+                    b'\n' => At::Fields(true),
+                    b'\r' => At::Fields(start),
+                    _ => At::Fields(false),
+                };
+            }
+        }
+        Some(b)
+    }
+}
+
+/// The length of the head at the start of `buf`, up to its blank line.
+fn head_end(buf: &[u8]) -> Option<usize> {
+    let mut start = 0;
+    while let Some(i) = buf[start..].iter().position(|&b| b == b'\n') {
+        let end = start + i + 1;
+        let line = &buf[start..end];
+        if line == b"\n" || line == b"\r\n" {
+            return Some(end);
+        }
+        start = end;
+    }
+    None
+}
+
+/// The header fields of a head, in order, with their original names.
+///
+/// Folded lines are joined, and invalid ones skipped.
+pub(crate) fn fields(head: &[u8]) -> Vec<(String, HeaderValue)> {
+    let mut fields: Vec<(String, Vec<u8>)> = Vec::new();
+    let lines = head
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+    // Skip the status line.
+    for line in lines.skip(1) {
+        if line.is_empty() {
+            break;
+        }
+        if line[0] == b' ' || line[0] == b'\t' {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(b' ');
+                value.extend_from_slice(trim(line));
+            }
+            continue;
+        }
+        let colon = match line.iter().position(|&b| b == b':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let name = trim(&line[..colon]);
+        match std::str::from_utf8(name) {
+            Ok(name) if !name.is_empty() => {
+                fields.push((name.to_owned(), trim(&line[colon + 1..]).to_vec()))
+            }
+            _ => {}
+        }
+    }
+    fields
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, HeaderValue::from_bytes(&value).ok()?)))
+        .collect()
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_final_head() {
+        let recorder = HeadRecorder::default();
+        recorder.on_read(b"HTTP/1.1 200 OK\r\n\r\n");
+        assert!(recorder.latest().is_none());
+
+        recorder.on_write();
+        recorder.on_read(b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nSet-");
+        recorder.on_read(b"Cookie: a=1\r\n\r\nbody\r\n\r\n");
+        assert_eq!(
+            recorder.latest().unwrap(),
+            "HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\n\r\n"
+        );
+
+        // The body isn't mistaken for the head of another response.
+        recorder.on_read(b"\r\n\r\n");
+        assert_eq!(
+            recorder.latest().unwrap(),
+            "HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn parses_fields() {
+        let fields = fields(
+            b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nX-Folded: a\r\n  b\r\n\
+              bad line\r\nset-cookie: b=2\r\nSET-COOKIE :c=3\r\n\r\n",
+        );
+        let fields = fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("Set-Cookie", "a=1"),
+                ("X-Folded", "a b"),
+                ("set-cookie", "b=2"),
+                ("SET-COOKIE", "c=3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn collapses_status_line_whitespace() {
+        fn read(status_line: &mut StatusLine, data: &[u8]) -> Vec<u8> {
+            let mut data = data.to_vec();
+            let len = status_line.on_read(&mut data);
+            data.truncate(len);
+            data
+        }
+
+        let mut status_line = StatusLine::default();
+        // Nothing is rewritten before a request is written.
+        assert_eq!(read(&mut status_line, b"HTTP/1.1  200"), b"HTTP/1.1  200");
+
+        status_line.on_write();
+        assert_eq!(read(&mut status_line, b"HTTP/1.1 \t"), b"HTTP/1.1 ");
+        assert_eq!(
+            read(
+                &mut status_line,
+                b" 100\t Continue\r\nA: 1\r\n\r\nHTTP/1.1\t204  No  Content\r\n"
+            ),
+            b"100 Continue\r\nA: 1\r\n\r\nHTTP/1.1 204 No  Content\r\n"
+        );
+        // The body is left alone.
+        assert_eq!(read(&mut status_line, b"HTTP/1.1  200"), b"HTTP/1.1  200");
+
+        status_line.on_write();
+        assert_eq!(
+            read(&mut status_line, b"HTTP/1.1 200\r\n\r\n"),
+            b"HTTP/1.1 200\r\n\r\n"
+        );
+
+        status_line.on_write();
+        assert_eq!(read(&mut status_line, b"not  http"), b"not  http");
+    }
+}
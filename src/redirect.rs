@@ -21,8 +21,16 @@ use crate::Url;
 ///   the allowed maximum redirect hops in a chain.
 /// - `none` can be used to disable all redirect behavior.
 /// - `custom` can be used to create a customized policy.
+///
+/// Any of them can additionally reject redirect loops with
+/// [`detect_loops`](Policy::detect_loops) and `https` to `http` downgrades
+/// with [`forbid_downgrades`](Policy::forbid_downgrades), failing with a
+/// [`Rejected`] error.
 pub struct Policy {
     inner: PolicyKind,
+    detect_loops: bool,
+    forbid_downgrades: bool,
+    strip_sensitive_headers: bool,
 }
 
 /// A type that holds information on the next request and previous requests
@@ -40,21 +48,58 @@ pub struct Action {
     inner: ActionKind,
 }
 
+/// The error of a redirect rejected by [`Policy::detect_loops`] or
+/// [`Policy::forbid_downgrades`].
+///
+/// It can be found as the source of the returned `Error`.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn run() -> Result<(), rquest::Error> {
+/// use std::error::Error as _;
+/// use rquest::redirect::{self, Rejected};
+///
+/// let client = rquest::Client::builder()
+///     .redirect(redirect::Policy::default().detect_loops(true))
+///     .build()?;
+///
+/// if let Err(err) = client.get("https://example.com/loop").send().await {
+///     if let Some(rejected) = err.source().and_then(|e| e.downcast_ref::<Rejected>()) {
+///         eprintln!("hop {} to {} is a {:?}", rejected.hop(), rejected.url(), rejected.reason());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Rejected {
+    reason: Reason,
+    hop: usize,
+    url: Url,
+}
+
+/// Why a redirect was [`Rejected`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Reason {
+    /// The redirect leads to a URL already visited in the chain.
+    Loop,
+    /// The redirect leads from an `https` URL to an `http` one.
+    Downgrade,
+}
+
 impl Policy {
     /// Create a `Policy` with a maximum number of redirects.
     ///
     /// An `Error` will be returned if the max is reached.
     pub fn limited(max: usize) -> Self {
-        Self {
-            inner: PolicyKind::Limit(max),
-        }
+        Self::new(PolicyKind::Limit(max))
     }
 
     /// Create a `Policy` that does not follow any redirect.
     pub fn none() -> Self {
-        Self {
-            inner: PolicyKind::None,
-        }
+        Self::new(PolicyKind::None)
     }
 
     /// Create a custom `Policy` using the passed function.
@@ -99,11 +144,46 @@ impl Policy {
     where
         T: Fn(Attempt) -> Action + Send + Sync + 'static,
     {
+        Self::new(PolicyKind::Custom(Box::new(policy)))
+    }
+
+    fn new(inner: PolicyKind) -> Self {
         Self {
-            inner: PolicyKind::Custom(Box::new(policy)),
+            inner,
+            detect_loops: false,
+            forbid_downgrades: false,
+            strip_sensitive_headers: true,
         }
     }
 
+    /// Reject redirects to a URL already visited in the chain.
+    ///
+    /// This is checked before the rest of the policy. Note that a server may
+    /// legitimately redirect back to a URL, for instance after setting a
+    /// cookie, which is why it is disabled by default.
+    pub fn detect_loops(mut self, enabled: bool) -> Self {
+        self.detect_loops = enabled;
+        self
+    }
+
+    /// Reject redirects from an `https` URL to an `http` one.
+    ///
+    /// This is checked before the rest of the policy. Disabled by default.
+    pub fn forbid_downgrades(mut self, enabled: bool) -> Self {
+        self.forbid_downgrades = enabled;
+        self
+    }
+
+    /// Remove the `Authorization`, `Cookie` and other credential headers of
+    /// the request when a redirect leads to another host or port.
+    ///
+    /// Cookies of a cookie store are still added for the new URL. Enabled by
+    /// default.
+    pub fn strip_sensitive_headers(mut self, enabled: bool) -> Self {
+        self.strip_sensitive_headers = enabled;
+        self
+    }
+
     /// Apply this policy to a given [`Attempt`] to produce a [`Action`].
     ///
     /// # Note
@@ -125,6 +205,14 @@ impl Policy {
     /// # }
     /// ```
     pub fn redirect(&self, attempt: Attempt) -> Action {
+        if let Some(reason) = self.violation(&attempt) {
+            let rejected = Rejected {
+                reason,
+                hop: attempt.previous.len(),
+                url: attempt.next.clone(),
+            };
+            return attempt.error(rejected);
+        }
         match self.inner {
             PolicyKind::Custom(ref custom) => custom(attempt),
             PolicyKind::Limit(max) => {
@@ -147,8 +235,28 @@ impl Policy {
         .inner
     }
 
+    fn violation(&self, attempt: &Attempt) -> Option<Reason> {
+        if self.detect_loops && attempt.previous.contains(attempt.next) {
+            return Some(Reason::Loop);
+        }
+        let downgrade = attempt.previous.last().map_or(false, |prev| {
+            prev.scheme() == "https" && attempt.next.scheme() == "http"
+        });
+        if self.forbid_downgrades && downgrade {
+            return Some(Reason::Downgrade);
+        }
+        None
+    }
+
+    pub(crate) fn strips_sensitive_headers(&self) -> bool {
+        self.strip_sensitive_headers
+    }
+
     pub(crate) fn is_default(&self) -> bool {
         matches!(self.inner, PolicyKind::Limit(10))
+            && !self.detect_loops
+            && !self.forbid_downgrades
+            && self.strip_sensitive_headers
     }
 }
 
@@ -208,10 +316,44 @@ enum PolicyKind {
 
 impl fmt::Debug for Policy {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.debug_tuple("Policy").field(&self.inner).finish()
+        f.debug_struct("Policy")
+            .field("inner", &self.inner)
+            .field("detect_loops", &self.detect_loops)
+            .field("forbid_downgrades", &self.forbid_downgrades)
+            .field("strip_sensitive_headers", &self.strip_sensitive_headers)
+            .finish()
     }
 }
 
+impl Rejected {
+    /// Get why the redirect was rejected.
+    pub fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    /// Get the position of the rejected redirect in the chain, starting at 1.
+    pub fn hop(&self) -> usize {
+        self.hop
+    }
+
+    /// Get the URL the rejected redirect leads to.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            Reason::Loop => "redirect loop",
+            Reason::Downgrade => "https to http downgrade",
+        };
+        write!(f, "{} at redirect {} to {}", reason, self.hop, self.url)
+    }
+}
+
+impl StdError for Rejected {}
+
 impl fmt::Debug for PolicyKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -312,6 +454,48 @@ fn test_redirect_policy_custom() {
     }
 }
 
+#[test]
+fn test_redirect_policy_loops_and_downgrades() {
+    let policy = Policy::default().detect_loops(true).forbid_downgrades(true);
+    let previous = vec![
+        Url::parse("https://a.b/start").unwrap(),
+        Url::parse("https://a.b/login").unwrap(),
+    ];
+
+    let next = Url::parse("https://a.b/start").unwrap();
+    match policy.check(StatusCode::FOUND, &next, &previous) {
+        ActionKind::Error(err) => {
+            let rejected = err.downcast_ref::<Rejected>().unwrap();
+            assert_eq!(rejected.reason(), Reason::Loop);
+            assert_eq!(rejected.hop(), 2);
+            assert_eq!(rejected.url(), &next);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let next = Url::parse("http://a.b/end").unwrap();
+    match policy.check(StatusCode::FOUND, &next, &previous) {
+        ActionKind::Error(err) => {
+            let rejected = err.downcast_ref::<Rejected>().unwrap();
+            assert_eq!(rejected.reason(), Reason::Downgrade);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let next = Url::parse("https://a.b/end").unwrap();
+    match policy.check(StatusCode::FOUND, &next, &previous) {
+        ActionKind::Follow => (),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // Off by default.
+    let next = Url::parse("http://a.b/start").unwrap();
+    match Policy::default().check(StatusCode::FOUND, &next, &previous) {
+        ActionKind::Follow => (),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
 #[test]
 fn test_remove_sensitive_headers() {
     use hyper::header::{HeaderValue, ACCEPT, AUTHORIZATION, COOKIE};
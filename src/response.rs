@@ -1,8 +1,79 @@
+use http::{HeaderMap, StatusCode};
 use url::Url;
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct ResponseUrl(pub Url);
 
+/// The steps taken before a final `Response` was received.
+///
+/// Redirect responses that were followed are recorded in the order they were
+/// received, along with the number of times a request was transparently
+/// retried.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    retries: usize,
+}
+
+/// An intermediate response that was followed to reach the final `Response`.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    status: StatusCode,
+    url: Url,
+    headers: HeaderMap,
+}
+
+pub(crate) static EMPTY_HISTORY: History = History {
+    entries: Vec::new(),
+    retries: 0,
+};
+
+impl History {
+    pub(crate) fn push(&mut self, status: StatusCode, url: Url, headers: HeaderMap) {
+        self.entries.push(HistoryEntry {
+            status,
+            url,
+            headers,
+        });
+    }
+
+    pub(crate) fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    /// Returns `true` if the request was neither redirected nor retried.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.retries == 0
+    }
+
+    /// The intermediate responses, oldest first.
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// The number of times a request was retried, across all redirects.
+    pub fn retries(&self) -> usize {
+        self.retries
+    }
+}
+
+impl HistoryEntry {
+    /// The status of the intermediate response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The `Url` that was requested.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The headers of the intermediate response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+}
+
 /// Extension trait for http::response::Builder objects
 ///
 /// Allows the user to add a `Url` to the http::Response
@@ -24,6 +95,21 @@ mod tests {
     use http::response::Builder;
     use url::Url;
 
+    #[test]
+    fn test_history() {
+        let mut history = super::History::default();
+        assert!(history.is_empty());
+
+        let url = Url::parse("http://example.com").unwrap();
+        history.push(http::StatusCode::FOUND, url.clone(), http::HeaderMap::new());
+        history.set_retries(1);
+
+        assert!(!history.is_empty());
+        assert_eq!(history.retries(), 1);
+        assert_eq!(history.entries()[0].status(), http::StatusCode::FOUND);
+        assert_eq!(history.entries()[0].url(), &url);
+    }
+
     #[test]
     fn test_response_builder_ext() {
         let url = Url::parse("http://example.com").unwrap();
@@ -0,0 +1,731 @@
+//! Retries
+//!
+//! A `Client` transparently retries some failed requests, such as those
+//! refused by an HTTP/2 server shutting down gracefully. A [`Policy`] set
+//! with [`ClientBuilder::retry_policy`] also sends requests again when the
+//! server answers with a status such as 429 or 503, or the connection
+//! fails, waiting longer after each attempt.
+//!
+//! When a site starts failing hard, every request may turn into several,
+//! adding load to a struggling target and to your own egress. A [`Budget`]
+//! set with [`ClientBuilder::retry_budget`] caps retries at a share of the
+//! requests recently sent, across the whole client.
+//!
+//! An [`IdempotencyKey`] set with [`RequestBuilder::idempotency_key`] lets
+//! the server tell the attempts of a request apart from new requests, so
+//! that a `POST` can be sent again safely.
+//!
+//! [`ClientBuilder::retry_policy`]: crate::ClientBuilder::retry_policy
+//! [`ClientBuilder::retry_budget`]: crate::ClientBuilder::retry_budget
+//! [`RequestBuilder::idempotency_key`]: crate::RequestBuilder::idempotency_key
+
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use http::{Method, StatusCode};
+use tower_service::Service;
+
+use crate::middleware::Next;
+use crate::{Request, Response};
+
+/// The number of slots the window of a budget is divided into.
+const SLOTS: usize = 10;
+
+/// The header carrying the idempotency key of a request.
+pub(crate) const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// The key of a request, sent in its `Idempotency-Key` header, with which
+/// the server recognizes the attempts of the request as one.
+///
+/// Set with [`RequestBuilder::idempotency_key`]. The key is drawn or set
+/// once, when the request is built, so every attempt a [`Policy`] makes
+/// sends the same key. Strings convert into [`IdempotencyKey::Value`].
+///
+/// # Example
+///
+/// ```rust
+/// # async fn run() -> Result<(), rquest::Error> {
+/// use rquest::retry::{IdempotencyKey, Policy};
+///
+/// let client = rquest::Client::builder()
+///     .retry_policy(Policy::new(3))
+///     .build()?;
+/// let res = client
+///     .post("https://api.example.com/v1/charges")
+///     .idempotency_key(IdempotencyKey::Auto)
+///     .body("amount=2000")
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`RequestBuilder::idempotency_key`]: crate::RequestBuilder::idempotency_key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdempotencyKey {
+    /// A random key, in the form of a version 4 UUID, drawn from the random
+    /// number generator of the operating system.
+    Auto,
+    /// The given key, such as the id of the operation in the application.
+    Value(String),
+}
+
+/// A limit on the retries of a client, relative to its requests.
+///
+/// Over the last `ttl`, a retry is allowed while the retries do not exceed
+/// `min_per_sec` a second plus `ratio` of the requests. Clones share their
+/// state, so a budget can be shared by several clients, and its
+/// [`stats`](Budget::stats) read while they run.
+///
+/// # Example
+///
+/// ```rust
+/// # fn run() -> Result<(), rquest::Error> {
+/// use rquest::retry::Budget;
+///
+/// // At most 20% extra load from retries.
+/// let budget = Budget::new(0.2);
+/// let client = rquest::Client::builder()
+///     .retry_budget(budget.clone())
+///     .build()?;
+///
+/// // ...
+/// println!("rejected {} retries", budget.stats().rejected);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Budget {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    ratio: f64,
+    min_per_sec: u32,
+    ttl: Duration,
+    window: Mutex<Window>,
+    requests: AtomicU64,
+    retries: AtomicU64,
+    rejected: AtomicU64,
+}
+
+/// Counts of the requests and retries in each slot of the window, the
+/// oldest after `current`.
+struct Window {
+    slots: [(u64, u64); SLOTS],
+    current: usize,
+    started: Instant,
+}
+
+/// When a client sends a request again, and how long it waits first.
+///
+/// A request is sent again when the response has one of the
+/// [`statuses`](Policy::statuses), 429, 500, 502, 503 and 504 by default,
+/// or when the connection fails or is reset. The wait doubles after each
+/// attempt, from 100 milliseconds up to 10 seconds by default, unless the
+/// server asks for another one with a `Retry-After` header.
+///
+/// By default only idempotent methods, such as `GET` or `PUT`, and requests
+/// with an [`IdempotencyKey`], are sent again once the server may have seen
+/// them. A request that failed to
+/// connect is sent again whatever its method, since the server never saw
+/// it. Requests whose body is a stream are never sent again.
+///
+/// # Example
+///
+/// ```rust
+/// # fn run() -> Result<(), rquest::Error> {
+/// use std::time::Duration;
+///
+/// use rquest::retry::Policy;
+///
+/// let client = rquest::Client::builder()
+///     .retry_policy(
+///         Policy::new(5).backoff(Duration::from_millis(250), Duration::from_secs(30)),
+///     )
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Policy {
+    max_attempts: u32,
+    base: Duration,
+    max: Duration,
+    jitter: bool,
+    statuses: Vec<StatusCode>,
+    idempotent_only: bool,
+    retry_after: bool,
+}
+
+/// The layer of a client sending requests again as its [`Policy`] says.
+#[derive(Clone)]
+pub(crate) struct PolicyLayer {
+    policy: Arc<Policy>,
+    budget: Option<Budget>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Retrying {
+    policy: Arc<Policy>,
+    budget: Option<Budget>,
+    next: Next,
+}
+
+/// The totals of a [`Budget`] since it was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Requests sent, not counting retries.
+    pub requests: u64,
+    /// Retries allowed.
+    pub retries: u64,
+    /// Retries rejected because the budget was exhausted.
+    pub rejected: u64,
+}
+
+impl Budget {
+    /// Create a budget allowing retries of up to `ratio` of the requests,
+    /// for instance `0.2` for 20% extra load.
+    ///
+    /// By default, the requests of the last 10 seconds are counted, and 10
+    /// retries a second are always allowed so that a client with few
+    /// requests can still retry.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `ratio` is negative or not a number.
+    pub fn new(ratio: f64) -> Budget {
+        assert!(ratio >= 0.0, "retry ratio must not be negative");
+        Budget {
+            inner: Arc::new(Inner {
+                ratio,
+                min_per_sec: 10,
+                ttl: Duration::from_secs(10),
+                window: Mutex::new(Window {
+                    slots: [(0, 0); SLOTS],
+                    current: 0,
+                    started: Instant::now(),
+                }),
+                requests: AtomicU64::new(0),
+                retries: AtomicU64::new(0),
+                rejected: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Set how many retries a second are allowed regardless of the
+    /// requests.
+    pub fn min_per_sec(self, min_per_sec: u32) -> Budget {
+        self.configure(|inner| inner.min_per_sec = min_per_sec)
+    }
+
+    /// Set how long requests and retries are counted for.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `ttl` is shorter than a second, or longer than a
+    /// minute.
+    pub fn ttl(self, ttl: Duration) -> Budget {
+        assert!(
+            ttl >= Duration::from_secs(1) && ttl <= Duration::from_secs(60),
+            "retry budget ttl must be between 1 and 60 seconds"
+        );
+        self.configure(|inner| inner.ttl = ttl)
+    }
+
+    /// The totals of this budget.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            requests: self.inner.requests.load(Ordering::Relaxed),
+            retries: self.inner.retries.load(Ordering::Relaxed),
+            rejected: self.inner.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    fn configure(mut self, f: impl FnOnce(&mut Inner)) -> Budget {
+        let inner = Arc::get_mut(&mut self.inner)
+            .expect("retry budget must be configured before it is cloned");
+        f(inner);
+        self
+    }
+
+    /// Count a request.
+    pub(crate) fn deposit(&self) {
+        self.deposit_at(Instant::now());
+    }
+
+    /// Count a retry, returning whether it is allowed.
+    pub(crate) fn withdraw(&self) -> bool {
+        self.withdraw_at(Instant::now())
+    }
+
+    fn deposit_at(&self, now: Instant) {
+        let mut window = self.inner.window.lock().unwrap();
+        self.advance(&mut window, now);
+        let current = window.current;
+        window.slots[current].0 += 1;
+        self.inner.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn withdraw_at(&self, now: Instant) -> bool {
+        let mut window = self.inner.window.lock().unwrap();
+        self.advance(&mut window, now);
+        let (requests, retries) = window
+            .slots
+            .iter()
+            .fold((0, 0), |(a, b), &(c, d)| (a + c, b + d));
+        let allowed = f64::from(self.inner.min_per_sec) * self.inner.ttl.as_secs_f64()
+            + self.inner.ratio * requests as f64;
+        if (retries as f64) < allowed {
+            let current = window.current;
+            window.slots[current].1 += 1;
+            self.inner.retries.fetch_add(1, Ordering::Relaxed);
+            true
+        } else {
+            self.inner.rejected.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    /// Move the window to `now`, clearing the slots that expired.
+    fn advance(&self, window: &mut Window, now: Instant) {
+        let slot = self.inner.ttl / SLOTS as u32;
+        let elapsed = now.saturating_duration_since(window.started);
+        let expired = (elapsed.as_nanos() / slot.as_nanos()) as usize;
+        if expired == 0 {
+            return;
+        }
+        for _ in 0..expired.min(SLOTS) {
+            window.current = (window.current + 1) % SLOTS;
+            window.slots[window.current] = (0, 0);
+        }
+        window.started += slot * expired as u32;
+    }
+}
+
+impl fmt::Debug for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Budget")
+            .field("ratio", &self.inner.ratio)
+            .field("min_per_sec", &self.inner.min_per_sec)
+            .field("ttl", &self.inner.ttl)
+            .finish()
+    }
+}
+
+// ===== impl Policy =====
+
+impl Policy {
+    /// Create a policy making up to `max_attempts` attempts of a request,
+    /// counting the first one.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `max_attempts` is 0.
+    pub fn new(max_attempts: u32) -> Policy {
+        assert!(max_attempts > 0, "a request needs at least one attempt");
+        Policy {
+            max_attempts,
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            jitter: true,
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            idempotent_only: true,
+            retry_after: true,
+        }
+    }
+
+    /// Set the wait before the first retry, doubled after each one up to
+    /// `max`.
+    ///
+    /// A `Retry-After` longer than `max` isn't waited for, the response
+    /// being returned instead.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Policy {
+        self.base = base;
+        self.max = max.max(base);
+        self
+    }
+
+    /// Set whether waits are randomized, between half and all of the
+    /// backoff, so that clients failing together don't retry together.
+    ///
+    /// Default is `true`.
+    pub fn jitter(mut self, enabled: bool) -> Policy {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Set the statuses of the responses to send the request again after.
+    pub fn statuses<I>(mut self, statuses: I) -> Policy
+    where
+        I: IntoIterator<Item = StatusCode>,
+    {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Set whether only requests with an idempotent method are sent again
+    /// once the server may have seen them.
+    ///
+    /// Default is `true`.
+    pub fn idempotent_only(mut self, enabled: bool) -> Policy {
+        self.idempotent_only = enabled;
+        self
+    }
+
+    /// Set whether the wait asked for by the `Retry-After` header of a
+    /// response is used instead of the backoff.
+    ///
+    /// Default is `true`.
+    pub fn retry_after(mut self, enabled: bool) -> Policy {
+        self.retry_after = enabled;
+        self
+    }
+
+    /// The wait before sending a request again after `res`, or `None` if it
+    /// isn't, `retries` being the retries made so far.
+    fn after_response(&self, idempotent: bool, res: &Response, retries: u32) -> Option<Duration> {
+        if !self.statuses.contains(&res.status()) || !self.resends(idempotent) {
+            return None;
+        }
+        let asked = res
+            .headers()
+            .get(RETRY_AFTER)
+            .filter(|_| self.retry_after)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, SystemTime::now()));
+        match asked {
+            Some(wait) if wait > self.max => None,
+            Some(wait) => Some(wait),
+            None => Some(self.backoff_for(retries)),
+        }
+    }
+
+    /// The wait before sending a request again after `err`, or `None` if it
+    /// isn't.
+    fn after_error(&self, idempotent: bool, err: &crate::Error, retries: u32) -> Option<Duration> {
+        if err.is_connect() || (is_reset(err) && self.resends(idempotent)) {
+            Some(self.backoff_for(retries))
+        } else {
+            None
+        }
+    }
+
+    fn resends(&self, idempotent: bool) -> bool {
+        !self.idempotent_only || idempotent
+    }
+
+    fn backoff_for(&self, retries: u32) -> Duration {
+        let wait = self
+            .base
+            .checked_mul(1 << retries.min(31))
+            .map_or(self.max, |wait| wait.min(self.max));
+        if !self.jitter {
+            return wait;
+        }
+        let half = wait / 2;
+        let spread = half.as_nanos() as u64;
+        half + Duration::from_nanos(match spread {
+            0 => 0,
+            spread => crate::util::fast_random() % (spread + 1),
+        })
+    }
+}
+
+impl PolicyLayer {
+    pub(crate) fn new(policy: Policy, budget: Option<Budget>) -> PolicyLayer {
+        PolicyLayer {
+            policy: Arc::new(policy),
+            budget,
+        }
+    }
+}
+
+impl tower_layer::Layer<Next> for PolicyLayer {
+    type Service = Retrying;
+
+    fn layer(&self, next: Next) -> Retrying {
+        Retrying {
+            policy: self.policy.clone(),
+            budget: self.budget.clone(),
+            next,
+        }
+    }
+}
+
+impl Service<Request> for Retrying {
+    type Response = Response;
+    type Error = crate::Error;
+    type Future = Pin<Box<dyn Future<Output = crate::Result<Response>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<crate::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        let this = self.clone();
+        Box::pin(async move {
+            let mut next = this.next;
+            let mut retries = 0;
+            loop {
+                let idempotent =
+                    is_idempotent(req.method()) || req.headers().contains_key(IDEMPOTENCY_KEY);
+                let again = if retries + 1 < this.policy.max_attempts {
+                    req.try_clone()
+                } else {
+                    None
+                };
+                let result = next.call(req).await;
+                let again = match again {
+                    Some(again) => again,
+                    None => return result,
+                };
+                let wait = match result {
+                    Ok(ref res) => this.policy.after_response(idempotent, res, retries),
+                    Err(ref err) => this.policy.after_error(idempotent, err, retries),
+                };
+                let wait = match wait {
+                    Some(wait) => wait,
+                    None => return result,
+                };
+                if let Some(ref budget) = this.budget {
+                    if !budget.withdraw() {
+                        log::debug!("retry budget exhausted");
+                        return result;
+                    }
+                }
+                drop(result);
+                tokio::time::sleep(wait).await;
+                retries += 1;
+                req = again;
+            }
+        })
+    }
+}
+
+// ===== impl IdempotencyKey =====
+
+impl IdempotencyKey {
+    /// The value of the header, drawing the key if it is `Auto`.
+    pub(crate) fn header_value(self) -> crate::Result<HeaderValue> {
+        let key = match self {
+            IdempotencyKey::Auto => {
+                // Drawn from the OS, so that keys can't be guessed and don't
+                // collide across processes.
+                let mut uuid = [0; 16];
+                getrandom::getrandom(&mut uuid).map_err(crate::error::builder)?;
+                uuid[6] = (uuid[6] & 0x0f) | 0x40;
+                uuid[8] = (uuid[8] & 0x3f) | 0x80;
+                let hex = uuid
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>();
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+            }
+            IdempotencyKey::Value(key) => key,
+        };
+        HeaderValue::try_from(key).map_err(crate::error::builder)
+    }
+}
+
+impl From<&str> for IdempotencyKey {
+    fn from(key: &str) -> IdempotencyKey {
+        IdempotencyKey::Value(key.to_owned())
+    }
+}
+
+impl From<String> for IdempotencyKey {
+    fn from(key: String) -> IdempotencyKey {
+        IdempotencyKey::Value(key)
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+/// Whether the connection of the request was reset or closed before the
+/// response.
+pub(crate) fn is_reset(err: &crate::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            if matches!(
+                err.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+            ) {
+                return true;
+            }
+        }
+        if let Some(err) = err.downcast_ref::<hyper::Error>() {
+            if err.is_incomplete_message() {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+/// Parse a `Retry-After` value, either seconds or an HTTP date, into the
+/// wait from `now`.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = UNIX_EPOCH + Duration::from_secs(parse_http_date(value)?);
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// Parse an IMF-fixdate, such as `Sun, 06 Nov 1994 08:49:37 GMT`, into the
+/// seconds since the epoch.
+fn parse_http_date(value: &str) -> Option<u64> {
+    let mut parts = value.split(' ');
+    let _weekday = parts.next()?.strip_suffix(',')?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|part| part.parse::<u64>());
+    let (hours, minutes, seconds) = match (time.next(), time.next(), time.next()) {
+        (Some(Ok(h)), Some(Ok(m)), Some(Ok(s))) if h < 24 && m < 60 && s < 61 => (h, m, s),
+        _ => return None,
+    };
+    if parts.next()? != "GMT" || parts.next().is_some() || !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+
+    // Days since the epoch of the civil date, as in `days_from_civil`.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_retries_to_ratio() {
+        let budget = Budget::new(0.5).min_per_sec(0);
+        let now = Instant::now();
+        assert!(!budget.withdraw_at(now));
+        for _ in 0..4 {
+            budget.deposit_at(now);
+        }
+        assert!(budget.withdraw_at(now));
+        assert!(budget.withdraw_at(now));
+        assert!(!budget.withdraw_at(now));
+        assert_eq!(
+            budget.stats(),
+            Stats {
+                requests: 4,
+                retries: 2,
+                rejected: 2,
+            }
+        );
+
+        // Requests and retries expire with the window.
+        let later = now + Duration::from_secs(11);
+        assert!(!budget.withdraw_at(later));
+        budget.deposit_at(later);
+        budget.deposit_at(later);
+        assert!(budget.withdraw_at(later));
+    }
+
+    #[test]
+    fn min_per_sec() {
+        let budget = Budget::new(0.0).ttl(Duration::from_secs(1)).min_per_sec(2);
+        let now = Instant::now();
+        assert!(budget.withdraw_at(now));
+        assert!(budget.withdraw_at(now));
+        assert!(!budget.withdraw_at(now));
+    }
+
+    #[test]
+    fn retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_700);
+        assert_eq!(
+            parse_retry_after(" 120 ", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::from_secs(77))
+        );
+        assert_eq!(
+            parse_retry_after("Sat, 05 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 UTC", now),
+            None
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = Policy::new(10)
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(false);
+        assert_eq!(policy.backoff_for(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff_for(4), Duration::from_secs(1));
+        assert_eq!(policy.backoff_for(40), Duration::from_secs(1));
+
+        let policy = policy.jitter(true);
+        for _ in 0..100 {
+            let wait = policy.backoff_for(1);
+            assert!(wait >= Duration::from_millis(100) && wait <= Duration::from_millis(200));
+        }
+    }
+}
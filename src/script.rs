@@ -0,0 +1,253 @@
+//! Request hooks written in [Rhai](https://rhai.rs).
+//!
+//! A hook lets the headers of a request be computed by a small script, such
+//! as a signature reproducing one a site generates in JavaScript, so that it
+//! can be updated without recompiling.
+//!
+//! The script defines an `on_request(req)` function. `req` is a map with
+//! the `method`, `url`, `host`, `path`, `query`, `headers` (a map of
+//! lowercase names to values) and `body` (as a string, empty when it is a
+//! stream) of the request. The function returns a map of headers to set,
+//! where a `()` value removes the header, or `()` to leave them as is.
+//!
+//! Besides the Rhai standard library, scripts can call:
+//!
+//! - `now_ms()`, the Unix time in milliseconds
+//! - `base64(s)` and `hex(s)`, encoding the bytes of a string
+//! - `sha256(s)`, `md5(s)` and `hmac_sha256(key, s)`, as lowercase hex
+//!
+//! # Example
+//!
+//! ```
+//! # fn run() -> Result<(), rquest::Error> {
+//! use rquest::script::ScriptHook;
+//!
+//! let hook = ScriptHook::new(r#"
+//!     fn on_request(req) {
+//!         let ts = now_ms().to_string();
+//!         #{
+//!             "x-ts": ts,
+//!             "x-sign": hmac_sha256("secret", req.path + ts + req.body),
+//!         }
+//!     }
+//! "#)?;
+//!
+//! let client = rquest::Client::builder().script_hook(hook).build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine as _;
+use boring::hash::{hash, MessageDigest};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use url::Url;
+
+use crate::error;
+
+/// The function a script defines.
+const ENTRY: &str = "on_request";
+
+/// Operations a hook may run per request, so that a looping script fails
+/// instead of stalling the client.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A compiled script computing the headers of each request of a `Client`.
+///
+/// Set with [`ClientBuilder::script_hook`](crate::ClientBuilder::script_hook).
+/// See the [module documentation](self) for what scripts can do.
+pub struct ScriptHook {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptHook {
+    /// Compile a hook from the source of a script.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the script doesn't parse, or doesn't define an
+    /// `on_request(req)` function.
+    pub fn new(source: &str) -> crate::Result<ScriptHook> {
+        let engine = engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|e| error::builder(format!("invalid script: {}", e)))?;
+        let defined = ast
+            .iter_functions()
+            .any(|f| f.name == ENTRY && f.params.len() == 1);
+        if !defined {
+            return Err(error::builder(format!(
+                "script must define an `{}(req)` function",
+                ENTRY
+            )));
+        }
+        Ok(ScriptHook { engine, ast })
+    }
+
+    /// Compile a hook from a script file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<ScriptHook> {
+        let source = std::fs::read_to_string(path).map_err(error::builder)?;
+        ScriptHook::new(&source)
+    }
+
+    /// Run the script for a request, applying the headers it returns.
+    pub(crate) fn run(
+        &self,
+        method: &http::Method,
+        url: &Url,
+        headers: &mut HeaderMap,
+        body: Option<&[u8]>,
+    ) -> crate::Result<()> {
+        let mut names = Map::new();
+        for (name, value) in headers.iter() {
+            if let Ok(value) = value.to_str() {
+                names.insert(name.as_str().into(), value.to_owned().into());
+            }
+        }
+
+        let mut req = Map::new();
+        req.insert("method".into(), method.as_str().to_owned().into());
+        req.insert("url".into(), url.as_str().to_owned().into());
+        req.insert(
+            "host".into(),
+            url.host_str().unwrap_or("").to_owned().into(),
+        );
+        req.insert("path".into(), url.path().to_owned().into());
+        req.insert("query".into(), url.query().unwrap_or("").to_owned().into());
+        req.insert("headers".into(), Dynamic::from_map(names));
+        let body = body.map(String::from_utf8_lossy).unwrap_or_default();
+        req.insert("body".into(), body.into_owned().into());
+
+        let out: Dynamic = self
+            .engine
+            .call_fn(
+                &mut Scope::new(),
+                &self.ast,
+                ENTRY,
+                (Dynamic::from_map(req),),
+            )
+            .map_err(|e| error::request(format!("script hook failed: {}", e)))?;
+        if out.is_unit() {
+            return Ok(());
+        }
+        let out = out.try_cast::<Map>().ok_or_else(|| {
+            error::request(format!(
+                "script hook `{}` must return a map of headers",
+                ENTRY
+            ))
+        })?;
+
+        for (name, value) in out {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(error::request)?;
+            if value.is_unit() {
+                headers.remove(&name);
+                continue;
+            }
+            // Numbers and booleans are sent as their text.
+            let value = HeaderValue::from_str(&value.to_string()).map_err(error::request)?;
+            headers.insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ScriptHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScriptHook").finish()
+    }
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.register_fn("now_ms", || -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as i64)
+    });
+    engine.register_fn("base64", |s: &str| -> String {
+        base64::engine::general_purpose::STANDARD.encode(s)
+    });
+    engine.register_fn("hex", |s: &str| -> String { hex(s.as_bytes()) });
+    engine.register_fn("sha256", |s: &str| -> String {
+        hex(&boring::sha::sha256(s.as_bytes()))
+    });
+    engine.register_fn("md5", |s: &str| -> String {
+        hash(MessageDigest::md5(), s.as_bytes()).map_or_else(|_| String::new(), |d| hex(&d))
+    });
+    engine.register_fn("hmac_sha256", |key: &str, s: &str| -> String {
+        hex(&crate::util::hmac_sha256(key.as_bytes(), s.as_bytes()))
+    });
+    engine
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str, headers: &mut HeaderMap) -> crate::Result<()> {
+        let url = Url::parse("https://example.com/api?q=1").unwrap();
+        ScriptHook::new(source)?.run(&http::Method::POST, &url, headers, Some(b"{}"))
+    }
+
+    #[test]
+    fn sets_and_removes_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-old", HeaderValue::from_static("1"));
+        headers.insert("user-agent", HeaderValue::from_static("rquest"));
+        run(
+            r#"
+            fn on_request(req) {
+                #{
+                    "x-sign": req.method + " " + req.path + "?" + req.query + " " + req.body,
+                    "x-agent": hex(req.headers["user-agent"]),
+                    "x-len": req.body.len(),
+                    "x-old": (),
+                }
+            }
+            "#,
+            &mut headers,
+        )
+        .unwrap();
+        assert_eq!(headers["x-sign"], "POST /api?q=1 {}");
+        assert_eq!(headers["x-agent"], "727175657374");
+        assert_eq!(headers["x-len"], "2");
+        assert!(!headers.contains_key("x-old"));
+    }
+
+    #[test]
+    fn digests() {
+        let mut headers = HeaderMap::new();
+        run(
+            r#"fn on_request(req) { #{ "x-sha": sha256("abc"), "x-mac": hmac_sha256("key", "abc") } }"#,
+            &mut headers,
+        )
+        .unwrap();
+        assert_eq!(
+            headers["x-sha"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            headers["x-mac"],
+            "9c196e32dc0175f86f4b1cb89289d6619de6bee699e4c378e68309ed97a1a6ab"
+        );
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        let mut headers = HeaderMap::new();
+        assert!(ScriptHook::new("fn other(req) { () }").is_err());
+        assert!(ScriptHook::new("fn on_request(req) {").is_err());
+        assert!(run("fn on_request(req) { 42 }", &mut headers).is_err());
+        assert!(run("fn on_request(req) { loop {} }", &mut headers).is_err());
+    }
+}
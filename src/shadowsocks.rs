@@ -0,0 +1,595 @@
+//! Shadowsocks outbound support.
+//!
+//! Implements the client side of the AEAD protocol (SIP004) for proxies given
+//! as SIP002 `ss://` URLs. The TLS handshake with the origin runs inside the
+//! shadowsocks stream, so impersonation works as it does over a direct
+//! connection.
+//!
+//! Only the AES-GCM methods are supported, since those are the AEAD ciphers
+//! BoringSSL exposes through its EVP interface.
+
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use boring::hash::{hash, hmac_sha1, MessageDigest};
+use boring::symm::{decrypt_aead, encrypt_aead, Cipher};
+use http::uri::Scheme;
+use http::Uri;
+use hyper::client::connect::{Connected, Connection};
+use percent_encoding::percent_decode_str;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use url::Url;
+
+use crate::error::BoxError;
+
+const TAG_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const MAX_PAYLOAD: usize = 0x3FFF;
+
+/// An AEAD cipher method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Method {
+    Aes128Gcm,
+    Aes256Gcm,
+}
+
+impl Method {
+    fn from_name(name: &str) -> Option<Method> {
+        match name {
+            "aes-128-gcm" => Some(Method::Aes128Gcm),
+            "aes-256-gcm" => Some(Method::Aes256Gcm),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Method::Aes128Gcm => "aes-128-gcm",
+            Method::Aes256Gcm => "aes-256-gcm",
+        }
+    }
+
+    fn cipher(self) -> Cipher {
+        match self {
+            Method::Aes128Gcm => Cipher::aes_128_gcm(),
+            Method::Aes256Gcm => Cipher::aes_256_gcm(),
+        }
+    }
+
+    /// The key length, which is also the salt length.
+    fn key_len(self) -> usize {
+        match self {
+            Method::Aes128Gcm => 16,
+            Method::Aes256Gcm => 32,
+        }
+    }
+}
+
+/// A shadowsocks server, parsed from a SIP002 URL.
+#[derive(Clone)]
+pub(crate) struct ServerConfig {
+    host: String,
+    port: u16,
+    method: Method,
+    key: Arc<[u8]>,
+}
+
+impl ServerConfig {
+    /// Parse `ss://base64(method:password)@host:port`, or the plain
+    /// `ss://method:password@host:port` form.
+    pub(crate) fn from_url(url: &Url) -> crate::Result<ServerConfig> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| crate::error::builder("shadowsocks URL has no host"))?
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_owned();
+        let port = url
+            .port()
+            .ok_or_else(|| crate::error::builder("shadowsocks URL has no port"))?;
+        if url.query_pairs().any(|(k, _)| k == "plugin") {
+            return Err(crate::error::builder(
+                "shadowsocks plugins are not supported",
+            ));
+        }
+
+        let (method, password) = match url.password() {
+            Some(password) => (
+                percent_decode_str(url.username())
+                    .decode_utf8_lossy()
+                    .into_owned(),
+                percent_decode_str(password)
+                    .decode_utf8_lossy()
+                    .into_owned(),
+            ),
+            None => {
+                let userinfo = percent_decode_str(url.username()).decode_utf8_lossy();
+                let decoded = URL_SAFE_NO_PAD
+                    .decode(userinfo.trim_end_matches('='))
+                    .or_else(|_| STANDARD.decode(userinfo.as_bytes()))
+                    .map_err(crate::error::builder)?;
+                let decoded = String::from_utf8(decoded).map_err(crate::error::builder)?;
+                let (method, password) = decoded
+                    .split_once(':')
+                    .ok_or_else(|| crate::error::builder("invalid shadowsocks user info"))?;
+                (method.to_owned(), password.to_owned())
+            }
+        };
+
+        let method = Method::from_name(&method).ok_or_else(|| {
+            crate::error::builder(format!("unsupported shadowsocks method: {}", method))
+        })?;
+        let key = bytes_to_key(password.as_bytes(), method.key_len())?;
+
+        Ok(ServerConfig {
+            host,
+            port,
+            method,
+            key: key.into(),
+        })
+    }
+
+    /// The address of the server, to dial with the connector of the client.
+    pub(crate) fn uri(&self) -> Result<Uri, BoxError> {
+        let uri = if self.host.contains(':') {
+            format!("http://[{}]:{}/", self.host, self.port)
+        } else {
+            format!("http://{}:{}/", self.host, self.port)
+        };
+        Ok(uri.parse()?)
+    }
+
+    /// The server and a digest of the key, telling apart the users of a
+    /// server.
+    pub(crate) fn identity(&self) -> String {
+        format!("{:?}#{}", self, crate::proxy::digest(&self.key))
+    }
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(
+                f,
+                "ss://{}@[{}]:{}",
+                self.method.name(),
+                self.host,
+                self.port
+            )
+        } else {
+            write!(f, "ss://{}@{}:{}", self.method.name(), self.host, self.port)
+        }
+    }
+}
+
+/// Connect to `dst` through the shadowsocks `server`, which `tcp` is
+/// connected to.
+///
+/// The server is dialed by the caller, so that its name goes through the
+/// resolver of the client.
+pub(crate) fn connect(
+    tcp: TcpStream,
+    server: &ServerConfig,
+    dst: &Uri,
+) -> Result<ShadowsocksStream<TcpStream>, BoxError> {
+    let host = dst.host().ok_or("no host in url")?;
+    let port = match dst.port_u16() {
+        Some(port) => port,
+        None if dst.scheme() == Some(&Scheme::HTTPS) => 443,
+        None => 80,
+    };
+    let target = target_address(host, port)?;
+    ShadowsocksStream::new(tcp, server, target)
+}
+
+/// The SOCKS5-style address header sent as the first payload.
+fn target_address(host: &str, port: u16) -> Result<Vec<u8>, BoxError> {
+    let mut addr = Vec::with_capacity(host.len() + 4);
+    match host
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse::<IpAddr>()
+    {
+        Ok(IpAddr::V4(ip)) => {
+            addr.push(0x01);
+            addr.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            addr.push(0x04);
+            addr.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let len = u8::try_from(host.len()).map_err(|_| "host name too long")?;
+            addr.push(0x03);
+            addr.push(len);
+            addr.extend_from_slice(host.as_bytes());
+        }
+    }
+    addr.extend_from_slice(&port.to_be_bytes());
+    Ok(addr)
+}
+
+/// `EVP_BytesToKey` with MD5 and no salt, as used by shadowsocks.
+fn bytes_to_key(password: &[u8], len: usize) -> crate::Result<Vec<u8>> {
+    let mut key = Vec::with_capacity(len + 16);
+    let mut prev: Vec<u8> = Vec::new();
+    while key.len() < len {
+        prev.extend_from_slice(password);
+        prev = hash(MessageDigest::md5(), &prev)
+            .map_err(crate::error::builder)?
+            .to_vec();
+        key.extend_from_slice(&prev);
+    }
+    key.truncate(len);
+    Ok(key)
+}
+
+/// HKDF-SHA1 with the `ss-subkey` info string.
+fn subkey(key: &[u8], salt: &[u8]) -> io::Result<Vec<u8>> {
+    let prk = hmac_sha1(salt, key).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    let mut okm = Vec::with_capacity(key.len() + 20);
+    let mut block: Vec<u8> = Vec::new();
+    let mut counter = 1u8;
+    while okm.len() < key.len() {
+        block.extend_from_slice(b"ss-subkey");
+        block.push(counter);
+        block = hmac_sha1(&prk, &block)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+            .to_vec();
+        okm.extend_from_slice(&block);
+        counter += 1;
+    }
+    okm.truncate(key.len());
+    Ok(okm)
+}
+
+/// One direction of an AEAD session, with its own subkey and nonce.
+struct Aead {
+    cipher: Cipher,
+    key: Vec<u8>,
+    nonce: [u8; NONCE_LEN],
+}
+
+impl Aead {
+    fn new(method: Method, key: &[u8], salt: &[u8]) -> io::Result<Aead> {
+        Ok(Aead {
+            cipher: method.cipher(),
+            key: subkey(key, salt)?,
+            nonce: [0; NONCE_LEN],
+        })
+    }
+
+    fn increment_nonce(&mut self) {
+        for byte in self.nonce.iter_mut() {
+            *byte = byte.wrapping_add(1);
+            if *byte != 0 {
+                break;
+            }
+        }
+    }
+
+    fn seal(&mut self, plain: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+        let mut tag = [0; TAG_LEN];
+        let sealed = encrypt_aead(
+            self.cipher,
+            &self.key,
+            Some(&self.nonce),
+            &[],
+            plain,
+            &mut tag,
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        out.extend_from_slice(&sealed);
+        out.extend_from_slice(&tag);
+        self.increment_nonce();
+        Ok(())
+    }
+
+    fn open(&mut self, sealed: &[u8]) -> io::Result<Vec<u8>> {
+        let (data, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let plain = decrypt_aead(self.cipher, &self.key, Some(&self.nonce), &[], data, tag)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "shadowsocks decrypt error"))?;
+        self.increment_nonce();
+        Ok(plain)
+    }
+}
+
+/// A stream encrypted with the shadowsocks AEAD protocol.
+pub(crate) struct ShadowsocksStream<T> {
+    inner: T,
+    method: Method,
+    key: Arc<[u8]>,
+
+    encrypt: Aead,
+    /// The target address, sent together with the first write.
+    target: Option<Vec<u8>>,
+    /// Encrypted bytes not yet written to `inner`.
+    write_buf: Vec<u8>,
+    write_pos: usize,
+
+    decrypt: Option<Aead>,
+    /// Encrypted bytes read from `inner` but not yet decrypted.
+    read_buf: Vec<u8>,
+    payload_len: Option<usize>,
+    plain: Vec<u8>,
+    plain_pos: usize,
+}
+
+impl<T> ShadowsocksStream<T> {
+    fn new(inner: T, server: &ServerConfig, target: Vec<u8>) -> Result<Self, BoxError> {
+        let mut salt = vec![0; server.method.key_len()];
+        boring::rand::rand_bytes(&mut salt)?;
+        let encrypt = Aead::new(server.method, &server.key, &salt)?;
+
+        Ok(ShadowsocksStream {
+            inner,
+            method: server.method,
+            key: server.key.clone(),
+            encrypt,
+            target: Some(target),
+            write_buf: salt,
+            write_pos: 0,
+            decrypt: None,
+            read_buf: Vec::new(),
+            payload_len: None,
+            plain: Vec::new(),
+            plain_pos: 0,
+        })
+    }
+
+    /// Encrypt `data` as one chunk, prefixed by the target address if it
+    /// has not been sent yet. Returns how many bytes of `data` were taken.
+    fn seal_chunk(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut payload = self.target.take().unwrap_or_default();
+        let n = data.len().min(MAX_PAYLOAD - payload.len());
+        payload.extend_from_slice(&data[..n]);
+
+        self.encrypt
+            .seal(&(payload.len() as u16).to_be_bytes(), &mut self.write_buf)?;
+        self.encrypt.seal(&payload, &mut self.write_buf)?;
+        Ok(n)
+    }
+
+    /// Decrypt as much of `read_buf` as possible into `plain`.
+    fn open_chunks(&mut self) -> io::Result<()> {
+        let mut pos = 0;
+        loop {
+            let decrypt = match self.decrypt {
+                Some(ref mut decrypt) => decrypt,
+                None => {
+                    let salt_len = self.method.key_len();
+                    if self.read_buf.len() < salt_len {
+                        break;
+                    }
+                    pos = salt_len;
+                    let salt = &self.read_buf[..salt_len];
+                    self.decrypt
+                        .insert(Aead::new(self.method, &self.key, salt)?)
+                }
+            };
+
+            let rest = &self.read_buf[pos..];
+            match self.payload_len {
+                None if rest.len() >= 2 + TAG_LEN => {
+                    let len = decrypt.open(&rest[..2 + TAG_LEN])?;
+                    let len = u16::from_be_bytes([len[0], len[1]]) as usize & MAX_PAYLOAD;
+                    self.payload_len = Some(len);
+                    pos += 2 + TAG_LEN;
+                }
+                Some(len) if rest.len() >= len + TAG_LEN => {
+                    let payload = decrypt.open(&rest[..len + TAG_LEN])?;
+                    self.plain.extend_from_slice(&payload);
+                    self.payload_len = None;
+                    pos += len + TAG_LEN;
+                }
+                _ => break,
+            }
+        }
+        self.read_buf.drain(..pos);
+        Ok(())
+    }
+}
+
+impl<T: AsyncWrite + Unpin> ShadowsocksStream<T> {
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_buf.len() {
+            let n = ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.write_buf[self.write_pos..])
+            )?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_pos += n;
+        }
+        self.write_buf.clear();
+        self.write_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ShadowsocksStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.plain_pos < this.plain.len() {
+                let n = buf.remaining().min(this.plain.len() - this.plain_pos);
+                buf.put_slice(&this.plain[this.plain_pos..this.plain_pos + n]);
+                this.plain_pos += n;
+                if this.plain_pos == this.plain.len() {
+                    this.plain.clear();
+                    this.plain_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0; 8192];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            if chunk.filled().is_empty() {
+                if this.read_buf.is_empty() && this.payload_len.is_none() {
+                    return Poll::Ready(Ok(()));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            this.read_buf.extend_from_slice(chunk.filled());
+            this.open_chunks()?;
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ShadowsocksStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let n = this.seal_chunk(buf)?;
+        // The chunk is buffered now; push out what the socket takes.
+        if let Poll::Ready(Err(err)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(err));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.target.is_some() {
+            this.seal_chunk(&[])?;
+        }
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+impl<T: Connection> Connection for ShadowsocksStream<T> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn server(url: &str) -> ServerConfig {
+        ServerConfig::from_url(&Url::parse(url).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse_sip002_urls() {
+        // base64url("aes-128-gcm:test")
+        let config = server("ss://YWVzLTEyOC1nY206dGVzdA@192.168.100.1:8888#Example1");
+        assert_eq!(config.method, Method::Aes128Gcm);
+        assert_eq!(config.port, 8888);
+        assert_eq!(&*config.key, &bytes_to_key(b"test", 16).unwrap()[..]);
+
+        let config = server("ss://aes-256-gcm:p%40ss@[::1]:8388");
+        assert_eq!(config.method, Method::Aes256Gcm);
+        assert_eq!(config.host, "::1");
+        assert_eq!(format!("{:?}", config), "ss://aes-256-gcm@[::1]:8388");
+
+        let url = Url::parse("ss://chacha20-ietf-poly1305:x@example.com:8388").unwrap();
+        assert!(ServerConfig::from_url(&url).is_err());
+        let url = Url::parse("ss://aes-128-gcm:x@example.com:8388/?plugin=obfs-local").unwrap();
+        assert!(ServerConfig::from_url(&url).is_err());
+    }
+
+    #[test]
+    fn identity_tells_users_apart() {
+        let a = server("ss://aes-128-gcm:a@example.com:8388");
+        let b = server("ss://aes-128-gcm:b@example.com:8388");
+        assert_eq!(
+            a.identity(),
+            server("ss://aes-128-gcm:a@example.com:8388").identity()
+        );
+        assert_ne!(a.identity(), b.identity());
+    }
+
+    #[test]
+    fn evp_bytes_to_key() {
+        // Known answer from the shadowsocks reference implementation.
+        assert_eq!(
+            bytes_to_key(b"foobar", 32).unwrap(),
+            [
+                0x38, 0x58, 0xf6, 0x22, 0x30, 0xac, 0x3c, 0x91, 0x5f, 0x30, 0x0c, 0x66, 0x43, 0x12,
+                0xc6, 0x3f, 0x56, 0x83, 0x78, 0x52, 0x96, 0x14, 0xd2, 0x2d, 0xdb, 0x49, 0x23, 0x7d,
+                0x2f, 0x60, 0xbf, 0xdf,
+            ]
+        );
+    }
+
+    #[test]
+    fn hkdf_sha1_subkey() {
+        let key = (0..16).collect::<Vec<u8>>();
+        let salt = (16..32).collect::<Vec<u8>>();
+        assert_eq!(
+            subkey(&key, &salt).unwrap(),
+            [
+                0x6e, 0x1f, 0xe8, 0xa4, 0x52, 0x2b, 0x2b, 0x31, 0xdf, 0x56, 0xf7, 0x20, 0xd9, 0x24,
+                0x15, 0x21,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let config = server("ss://aes-128-gcm:secret@127.0.0.1:8388");
+        let (client, mut relay) = tokio::io::duplex(64 * 1024);
+        let target = target_address("example.com", 443).unwrap();
+        let mut stream = ShadowsocksStream::new(client, &config, target.clone()).unwrap();
+
+        stream.write_all(b"hello").await.unwrap();
+        stream.flush().await.unwrap();
+
+        // Decrypt what the client sent, acting as the server.
+        let mut sent = vec![0; 16 + 2 + TAG_LEN + target.len() + 5 + TAG_LEN];
+        relay.read_exact(&mut sent).await.unwrap();
+        let mut decrypt = Aead::new(config.method, &config.key, &sent[..16]).unwrap();
+        let len = decrypt.open(&sent[16..16 + 2 + TAG_LEN]).unwrap();
+        assert_eq!(
+            u16::from_be_bytes([len[0], len[1]]) as usize,
+            target.len() + 5
+        );
+        let payload = decrypt.open(&sent[16 + 2 + TAG_LEN..]).unwrap();
+        assert_eq!(&payload[..target.len()], &target[..]);
+        assert_eq!(&payload[target.len()..], b"hello");
+
+        // And answer with a reply split across two chunks.
+        let salt = [7; 16];
+        let mut encrypt = Aead::new(config.method, &config.key, &salt).unwrap();
+        let mut reply = salt.to_vec();
+        for part in [&b"wor"[..], &b"ld"[..]] {
+            encrypt
+                .seal(&(part.len() as u16).to_be_bytes(), &mut reply)
+                .unwrap();
+            encrypt.seal(part, &mut reply).unwrap();
+        }
+        relay.write_all(&reply).await.unwrap();
+        drop(relay);
+
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, b"world");
+    }
+}
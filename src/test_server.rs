@@ -0,0 +1,601 @@
+//! A local HTTP and HTTPS server for tests.
+//!
+//! [`TestServer`] runs on its own thread and serves a fixed set of endpoints
+//! useful to check what a client actually sends, without depending on
+//! external services such as httpbin.org:
+//!
+//! - `/headers` echoes the request headers, one `name: value` per line, in
+//!   the order they were received. Repeated header names are grouped.
+//! - `/client-hello` returns the raw TLS ClientHello of the connection
+//!   (HTTPS only), and `/ja3` its JA3 fingerprint string.
+//! - `/ech` responds `accepted` if the connection was made with Encrypted
+//!   Client Hello, offered with [`TestServer::ech_config_list`], and
+//!   `not accepted` otherwise.
+//! - `/client-cert` responds with the common name of the client
+//!   certificate, on the listener of [`TestServer::mtls_url`], which
+//!   requires one.
+//! - `/gzip`, `/brotli`, `/zstd` and `/deflate` return [`BODY`] compressed
+//!   with the matching `Content-Encoding`.
+//! - `/redirect/{n}` redirects `n` times before responding with `200 OK`.
+//! - `/slow?chunks={n}&delay={ms}` streams `n` chunks, `ms` milliseconds
+//!   apart.
+//! - `/status/{code}` responds with the given status code.
+//!
+//! The HTTPS endpoints use a freshly generated self-signed certificate for
+//! `localhost`, so clients must be built with
+//! [`danger_accept_invalid_certs`](crate::ClientBuilder::danger_accept_invalid_certs),
+//! or trust [`TestServer::certificate`].
+//!
+//! This requires the optional `test-server` feature to be enabled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), rquest::Error> {
+//! use rquest::test_server::TestServer;
+//!
+//! let server = TestServer::start();
+//! let client = rquest::Client::builder()
+//!     .danger_accept_invalid_certs(true)
+//!     .build()?;
+//!
+//! let ja3 = client.get(server.tls_url("/ja3")).send().await?.text().await?;
+//! println!("{}", ja3);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use boring::asn1::Asn1Time;
+use boring::bn::BigNum;
+use boring::ec::{EcGroup, EcKey};
+use boring::error::ErrorStack;
+use boring::hash::MessageDigest;
+use boring::nid::Nid;
+use boring::pkey::{PKey, Private};
+use boring::ssl::{
+    select_next_proto, AlpnError, Ssl, SslAcceptor, SslAcceptorBuilder, SslContextBuilder,
+    SslMethod, SslRef, SslVerifyMode,
+};
+use boring::x509::extension::SubjectAlternativeName;
+use boring::x509::{X509Name, X509};
+use bytes::Bytes;
+use foreign_types::ForeignTypeRef;
+use futures_util::future;
+use http::header::{CONTENT_ENCODING, CONTENT_TYPE, LOCATION};
+use http::{Request, Response, StatusCode};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::Body;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// The uncompressed body of the compression endpoints.
+pub const BODY: &str = "test server compressed body";
+
+/// A running test server, shut down when dropped.
+pub struct TestServer {
+    addr: SocketAddr,
+    tls_addr: SocketAddr,
+    mtls_addr: SocketAddr,
+    certificate: X509,
+    ech_config_list: Vec<u8>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// Start a server listening on random local ports.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the listeners can't be bound or the TLS setup fails.
+    pub fn start() -> TestServer {
+        let (acceptor, mtls_acceptor, index, certificate, ech_config_list) =
+            acceptors().expect("test server TLS setup");
+        let acceptor = Arc::new((acceptor, index));
+        let mtls_acceptor = Arc::new((mtls_acceptor, index));
+        let (addrs_tx, addrs_rx) = std::sync::mpsc::channel();
+        let (shutdown, shutdown_rx) = oneshot::channel();
+
+        let thread = thread::Builder::new()
+            .name("rquest-test-server".into())
+            .spawn(move || {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("test server runtime");
+                rt.block_on(async move {
+                    let http = TcpListener::bind(("127.0.0.1", 0)).await.expect("bind");
+                    let https = TcpListener::bind(("127.0.0.1", 0)).await.expect("bind");
+                    let mtls = TcpListener::bind(("127.0.0.1", 0)).await.expect("bind");
+                    let addrs = (
+                        http.local_addr().unwrap(),
+                        https.local_addr().unwrap(),
+                        mtls.local_addr().unwrap(),
+                    );
+                    addrs_tx.send(addrs).unwrap();
+
+                    let serve = future::join3(
+                        serve_http(http),
+                        serve_https(https, acceptor),
+                        serve_https(mtls, mtls_acceptor),
+                    );
+                    future::select(Box::pin(serve), shutdown_rx).await;
+                });
+            })
+            .expect("test server thread");
+
+        let (addr, tls_addr, mtls_addr) = addrs_rx.recv().expect("test server start");
+        TestServer {
+            addr,
+            tls_addr,
+            mtls_addr,
+            certificate,
+            ech_config_list,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        }
+    }
+
+    /// The address of the plain HTTP listener.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The address of the HTTPS listener.
+    pub fn tls_addr(&self) -> SocketAddr {
+        self.tls_addr
+    }
+
+    /// An `http://` URL for `path` on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// An `https://localhost` URL for `path` on this server.
+    ///
+    /// The host name makes clients send SNI, as they would to a real site.
+    pub fn tls_url(&self, path: &str) -> String {
+        format!("https://localhost:{}{}", self.tls_addr.port(), path)
+    }
+
+    /// An `https://localhost` URL for `path` on the listener that requires
+    /// a client certificate, any one.
+    pub fn mtls_url(&self, path: &str) -> String {
+        format!("https://localhost:{}{}", self.mtls_addr.port(), path)
+    }
+
+    /// The self-signed certificate of the HTTPS listeners.
+    pub fn certificate(&self) -> &X509 {
+        &self.certificate
+    }
+
+    /// The `ECHConfigList` of the HTTPS listener, for the public name
+    /// `localhost`, to set with
+    /// [`ClientBuilder::ech_config_list`](crate::ClientBuilder::ech_config_list).
+    pub fn ech_config_list(&self) -> &[u8] {
+        &self.ech_config_list
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown.take() {
+            let _ = tx.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Debug for TestServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TestServer")
+            .field("addr", &self.addr)
+            .field("tls_addr", &self.tls_addr)
+            .field("mtls_addr", &self.mtls_addr)
+            .finish()
+    }
+}
+
+async fn serve_http(listener: TcpListener) {
+    while let Ok((tcp, _)) = listener.accept().await {
+        tokio::spawn(async move {
+            let service = service_fn(|req| handle(req, TlsPeer::default()));
+            let _ = Http::new().serve_connection(tcp, service).await;
+        });
+    }
+}
+
+async fn serve_https(listener: TcpListener, acceptor: Arc<(SslAcceptor, HelloIndex)>) {
+    while let Ok((tcp, _)) = listener.accept().await {
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let (ref tls_acceptor, index) = *acceptor;
+            let tls = match tokio_boring::accept(tls_acceptor, tcp).await {
+                Ok(tls) => tls,
+                Err(err) => {
+                    log::debug!("test server TLS handshake failed: {:?}", err);
+                    return;
+                }
+            };
+            let ssl = tls.ssl();
+            let peer = TlsPeer {
+                hello: ssl.ex_data(index).cloned().map(Bytes::from),
+                // SAFETY: the connection is alive for the duration of the call.
+                ech: unsafe { boring_sys::SSL_ech_accepted(ssl.as_ptr()) } == 1,
+                client_cert: ssl.peer_certificate().and_then(|cert| {
+                    let name = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next()?;
+                    name.data().as_utf8().ok().map(|name| name.to_string())
+                }),
+            };
+            let service = service_fn(move |req| handle(req, peer.clone()));
+            let _ = Http::new().serve_connection(tls, service).await;
+        });
+    }
+}
+
+type HelloIndex = boring::ex_data::Index<Ssl, Vec<u8>>;
+
+/// What a request tells of the TLS connection it came on.
+#[derive(Clone, Default)]
+struct TlsPeer {
+    hello: Option<Bytes>,
+    ech: bool,
+    /// The common name of the client certificate.
+    client_cert: Option<String>,
+}
+
+/// The TLS acceptors of the HTTPS listeners, the second requiring a
+/// client certificate, with the index of the ClientHello in the
+/// connection's ex data, their self-signed `localhost` certificate, and the
+/// ECH configurations the first accepts.
+#[allow(clippy::type_complexity)]
+fn acceptors() -> Result<(SslAcceptor, SslAcceptor, HelloIndex, X509, Vec<u8>), ErrorStack> {
+    let key = EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?)?;
+    let key = PKey::from_ec_key(key)?;
+    let cert = certificate(&key)?;
+    let index = Ssl::new_ex_index::<Vec<u8>>()?;
+
+    let mut builder = acceptor(&key, &cert, index)?;
+    let ech_config_list = ech_keys(&mut builder)?;
+    let mut mtls = acceptor(&key, &cert, index)?;
+    // Any certificate will do, the handler tells which one it was.
+    mtls.set_verify_callback(
+        SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+        |_, _| true,
+    );
+    Ok((builder.build(), mtls.build(), index, cert, ech_config_list))
+}
+
+/// A TLS acceptor with `cert`, recording each ClientHello in the
+/// connection's ex data at `index`.
+fn acceptor(
+    key: &PKey<Private>,
+    cert: &X509,
+    index: HelloIndex,
+) -> Result<SslAcceptorBuilder, ErrorStack> {
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
+    builder.set_private_key(key)?;
+    builder.set_certificate(cert)?;
+    builder.set_alpn_select_callback(|_, client| {
+        select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(AlpnError::NOACK)
+    });
+    builder.set_select_certificate_callback(move |mut hello| {
+        let raw = hello.as_bytes().to_vec();
+        let ssl: &mut SslRef = hello.ssl_mut();
+        // Keep the first ClientHello if the server asks for a retry.
+        if ssl.ex_data(index).is_none() {
+            ssl.set_ex_data(index, raw);
+        }
+        Ok(())
+    });
+    Ok(builder)
+}
+
+/// Generate an ECH key for the public name `localhost` and set it on
+/// `builder`, returning the `ECHConfigList` clients offer.
+fn ech_keys(builder: &mut SslContextBuilder) -> Result<Vec<u8>, ErrorStack> {
+    fn check(ret: std::os::raw::c_int) -> Result<(), ErrorStack> {
+        if ret == 1 {
+            Ok(())
+        } else {
+            Err(ErrorStack::get())
+        }
+    }
+
+    // SAFETY: every object is freed once, after its last use, and the
+    // buffers BoringSSL allocates are copied before being freed.
+    unsafe {
+        let key = boring_sys::EVP_HPKE_KEY_new();
+        let keys = boring_sys::SSL_ECH_KEYS_new();
+        let mut config = std::ptr::null_mut();
+        let mut config_len = 0;
+        let mut list = std::ptr::null_mut();
+        let mut list_len = 0;
+        let result = (|| {
+            check(boring_sys::EVP_HPKE_KEY_generate(
+                key,
+                boring_sys::EVP_hpke_x25519_hkdf_sha256(),
+            ))?;
+            check(boring_sys::SSL_marshal_ech_config(
+                &mut config,
+                &mut config_len,
+                1,
+                key,
+                b"localhost\0".as_ptr().cast(),
+                16,
+            ))?;
+            check(boring_sys::SSL_ECH_KEYS_add(
+                keys, 1, config, config_len, key,
+            ))?;
+            check(boring_sys::SSL_ECH_KEYS_marshal_retry_configs(
+                keys,
+                &mut list,
+                &mut list_len,
+            ))?;
+            check(boring_sys::SSL_CTX_set1_ech_keys(builder.as_ptr(), keys))?;
+            Ok(std::slice::from_raw_parts(list, list_len).to_vec())
+        })();
+        boring_sys::OPENSSL_free(config.cast());
+        boring_sys::OPENSSL_free(list.cast());
+        boring_sys::SSL_ECH_KEYS_free(keys);
+        boring_sys::EVP_HPKE_KEY_free(key);
+        result
+    }
+}
+
+fn certificate(key: &PKey<Private>) -> Result<X509, ErrorStack> {
+    let mut name = X509Name::builder()?;
+    name.append_entry_by_text("CN", "localhost")?;
+    let name = name.build();
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&*BigNum::from_u32(1)?.to_asn1_integer()?)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&*Asn1Time::days_from_now(365)?)?;
+    builder.set_pubkey(key)?;
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(san)?;
+    builder.sign(key, MessageDigest::sha256())?;
+    Ok(builder.build())
+}
+
+async fn handle(req: Request<Body>, peer: TlsPeer) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path().to_owned();
+    let mut segments = path.trim_start_matches('/').splitn(2, '/');
+    let res = match (segments.next().unwrap_or(""), segments.next()) {
+        ("headers", None) => {
+            let mut body = String::new();
+            for (name, value) in req.headers() {
+                body.push_str(name.as_str());
+                body.push_str(": ");
+                body.push_str(&String::from_utf8_lossy(value.as_bytes()));
+                body.push('\n');
+            }
+            text(StatusCode::OK, body)
+        }
+        ("client-hello", None) => match peer.hello {
+            Some(hello) => Response::builder()
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(Body::from(hello))
+                .unwrap(),
+            None => text(StatusCode::NOT_FOUND, "no TLS ClientHello"),
+        },
+        ("ja3", None) => match peer.hello.as_deref().and_then(ja3) {
+            Some(ja3) => text(StatusCode::OK, ja3),
+            None => text(StatusCode::NOT_FOUND, "no TLS ClientHello"),
+        },
+        ("ech", None) if peer.ech => text(StatusCode::OK, "accepted"),
+        ("ech", None) => text(StatusCode::OK, "not accepted"),
+        ("client-cert", None) => match peer.client_cert {
+            Some(name) => text(StatusCode::OK, name),
+            None => text(StatusCode::NOT_FOUND, "no client certificate"),
+        },
+        (encoding @ ("gzip" | "brotli" | "zstd" | "deflate"), None) => {
+            let body = compress(encoding, BODY.as_bytes()).await;
+            let encoding = if encoding == "brotli" { "br" } else { encoding };
+            Response::builder()
+                .header(CONTENT_ENCODING, encoding)
+                .header(CONTENT_TYPE, "text/plain")
+                .body(Body::from(body))
+                .unwrap()
+        }
+        ("redirect", Some(n)) => match n.parse::<u32>() {
+            Ok(0) => text(StatusCode::OK, "redirect chain complete"),
+            Ok(n) => Response::builder()
+                .status(StatusCode::FOUND)
+                .header(LOCATION, format!("/redirect/{}", n - 1))
+                .body(Body::empty())
+                .unwrap(),
+            Err(_) => text(StatusCode::BAD_REQUEST, "invalid redirect count"),
+        },
+        ("slow", None) => {
+            let chunks = query_param(&req, "chunks").unwrap_or(5);
+            let delay = Duration::from_millis(query_param(&req, "delay").unwrap_or(100));
+            let (mut sender, body) = Body::channel();
+            tokio::spawn(async move {
+                for i in 0..chunks {
+                    tokio::time::sleep(delay).await;
+                    if sender.send_data(format!("{}\n", i).into()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            Response::new(body)
+        }
+        ("status", Some(code)) => {
+            match code.parse().ok().and_then(|c| StatusCode::from_u16(c).ok()) {
+                Some(status) => text(status, status.to_string()),
+                None => text(StatusCode::BAD_REQUEST, "invalid status code"),
+            }
+        }
+        _ => text(StatusCode::NOT_FOUND, "not found"),
+    };
+    Ok(res)
+}
+
+fn text<B: Into<Body>>(status: StatusCode, body: B) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(body.into())
+        .unwrap()
+}
+
+fn query_param<T: std::str::FromStr>(req: &Request<Body>, name: &str) -> Option<T> {
+    let query = req.uri().query()?;
+    url::form_urlencoded::parse(query.as_bytes())
+        .find(|(k, _)| k == name)
+        .and_then(|(_, v)| v.parse().ok())
+}
+
+async fn compress(encoding: &str, data: &[u8]) -> Vec<u8> {
+    use async_compression::tokio::write::{
+        BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder,
+    };
+
+    async fn encode<W: tokio::io::AsyncWrite + Unpin>(mut encoder: W, data: &[u8]) -> W {
+        encoder.write_all(data).await.expect("in-memory write");
+        encoder.shutdown().await.expect("in-memory write");
+        encoder
+    }
+
+    match encoding {
+        "gzip" => encode(GzipEncoder::new(Vec::new()), data)
+            .await
+            .into_inner(),
+        "brotli" => encode(BrotliEncoder::new(Vec::new()), data)
+            .await
+            .into_inner(),
+        "zstd" => encode(ZstdEncoder::new(Vec::new()), data)
+            .await
+            .into_inner(),
+        _ => encode(DeflateEncoder::new(Vec::new()), data)
+            .await
+            .into_inner(),
+    }
+}
+
+/// Compute the JA3 string of a ClientHello message body.
+fn ja3(hello: &[u8]) -> Option<String> {
+    fn is_grease(v: u16) -> bool {
+        v & 0x0f0f == 0x0a0a && v >> 8 == v & 0xff
+    }
+    fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+        values.map(|v| v.to_string()).collect::<Vec<_>>().join("-")
+    }
+
+    let mut r = Reader(hello);
+    let version = r.u16()?;
+    r.take(32)?;
+    let session_id_len = r.u8()? as usize;
+    r.take(session_id_len)?;
+    let cipher_len = r.u16()? as usize;
+    let ciphers = r.take(cipher_len)?;
+    let compression_len = r.u8()? as usize;
+    r.take(compression_len)?;
+
+    let mut extensions = Vec::new();
+    let mut groups = Vec::new();
+    let mut formats = Vec::new();
+    if !r.0.is_empty() {
+        let len = r.u16()? as usize;
+        let mut exts = Reader(r.take(len)?);
+        while !exts.0.is_empty() {
+            let ty = exts.u16()?;
+            let len = exts.u16()? as usize;
+            let mut data = Reader(exts.take(len)?);
+            extensions.push(ty);
+            match ty {
+                10 => {
+                    let len = data.u16()? as usize;
+                    let mut list = Reader(data.take(len)?);
+                    while !list.0.is_empty() {
+                        groups.push(list.u16()?);
+                    }
+                }
+                11 => {
+                    let len = data.u8()? as usize;
+                    formats.extend(data.take(len)?.iter().copied());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let ciphers = ciphers
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]));
+    Some(format!(
+        "{},{},{},{},{}",
+        version,
+        join(ciphers.filter(|v| !is_grease(*v))),
+        join(extensions.into_iter().filter(|v| !is_grease(*v))),
+        join(groups.into_iter().filter(|v| !is_grease(*v))),
+        join(formats.into_iter()),
+    ))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ja3_of_client_hello() {
+        let mut hello = vec![0x03, 0x03];
+        hello.extend_from_slice(&[0; 32]);
+        // Empty session id, two cipher suites (one GREASE), null compression.
+        hello.extend_from_slice(&[0, 0, 4, 0x0a, 0x0a, 0x13, 0x01, 1, 0]);
+        let extensions = [
+            // supported_groups: GREASE, x25519, secp256r1
+            &[0, 10, 0, 8, 0, 6, 0x2a, 0x2a, 0, 29, 0, 23][..],
+            // ec_point_formats: uncompressed
+            &[0, 11, 0, 2, 1, 0][..],
+            // GREASE extension
+            &[0x3a, 0x3a, 0, 0][..],
+        ]
+        .concat();
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&extensions);
+
+        assert_eq!(ja3(&hello).unwrap(), "771,4865,10-11,29-23,0");
+        assert_eq!(ja3(&hello[..40]), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! The phases of connections, pings and handshakes.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use http::{StatusCode, Version};
+
+use crate::http2::ServerPreface;
+
+/// How long establishing a connection took, phase by phase.
+///
+/// Available from [`Response::connect_timings`](crate::Response::connect_timings),
+/// for every response of the connection, and from a [`Ping`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnectTimings {
+    started: Instant,
+    dns: Option<Duration>,
+    connect: Duration,
+    tls: Option<Duration>,
+}
+
+/// The outcome of [`Client::ping`](crate::Client::ping).
+#[derive(Clone, Debug)]
+pub struct Ping {
+    pub(crate) status: StatusCode,
+    pub(crate) version: Version,
+    pub(crate) connection: Option<ConnectTimings>,
+    pub(crate) first_byte: Duration,
+    pub(crate) total: Duration,
+}
+
+/// What was negotiated with the server of a response, and how long each
+/// phase of getting it took, as curl's `--write-out` reports.
+///
+/// Returned by
+/// [`Response::handshake_report`](crate::Response::handshake_report).
+#[derive(Clone, Debug)]
+pub struct HandshakeReport {
+    pub(crate) version: Version,
+    pub(crate) remote_addr: Option<SocketAddr>,
+    pub(crate) connection: Option<ConnectTimings>,
+    pub(crate) reused: bool,
+    pub(crate) first_byte: Option<Duration>,
+    #[cfg(feature = "__tls")]
+    pub(crate) tls: Option<crate::tls::TlsInfo>,
+    pub(crate) http2: Option<ServerPreface>,
+}
+
+/// When the request of a response was sent, on a pooled or new
+/// connection, and when its head was received.
+#[derive(Clone, Copy)]
+pub(crate) struct Exchange {
+    pub(crate) sent: Instant,
+    pub(crate) head: Instant,
+}
+
+/// The instants of the phases of the connection being established.
+#[derive(Default)]
+struct Marks {
+    resolved: Cell<Option<Instant>>,
+    tls_started: Cell<Option<Instant>>,
+}
+
+tokio::task_local! {
+    static MARKS: Marks;
+}
+
+/// Run `connect`, timing the phases it goes through.
+pub(crate) async fn scope<F: Future>(connect: F) -> (F::Output, ConnectTimings) {
+    let started = Instant::now();
+    MARKS
+        .scope(Marks::default(), async move {
+            let output = connect.await;
+            let ended = Instant::now();
+            let (resolved, tls_started) =
+                MARKS.with(|marks| (marks.resolved.get(), marks.tls_started.get()));
+            let timings = ConnectTimings {
+                started,
+                dns: resolved.map(|resolved| resolved - started),
+                connect: tls_started
+                    .unwrap_or(ended)
+                    .saturating_duration_since(resolved.unwrap_or(started)),
+                tls: tls_started.map(|tls_started| ended - tls_started),
+            };
+            (output, timings)
+        })
+        .await
+}
+
+/// Record that the name of the connection is resolved, if it is in a
+/// `scope`.
+pub(crate) fn mark_resolved() {
+    let _ = MARKS.try_with(|marks| marks.resolved.set(Some(Instant::now())));
+}
+
+/// Record that the TLS handshake of the connection starts, if it is in a
+/// `scope`.
+pub(crate) fn mark_tls_started() {
+    let _ = MARKS.try_with(|marks| marks.tls_started.set(Some(Instant::now())));
+}
+
+// ===== impl ConnectTimings =====
+
+impl ConnectTimings {
+    /// Get how long resolving the name took, if it was looked up.
+    pub fn dns(&self) -> Option<Duration> {
+        self.dns
+    }
+
+    /// Get how long connecting to the server took, once its name was
+    /// resolved, including the tunnel of a proxy.
+    pub fn connect(&self) -> Duration {
+        self.connect
+    }
+
+    /// Get how long the TLS handshake took, for an HTTPS connection.
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    /// Get how long establishing the connection took in all.
+    pub fn total(&self) -> Duration {
+        self.dns.unwrap_or_default() + self.connect + self.tls.unwrap_or_default()
+    }
+
+    /// Whether the connection was established after `instant`.
+    pub(crate) fn started_after(&self, instant: Instant) -> bool {
+        self.started >= instant
+    }
+}
+
+// ===== impl HandshakeReport =====
+
+impl HandshakeReport {
+    pub(crate) fn new(
+        version: Version,
+        remote_addr: Option<SocketAddr>,
+        connection: Option<ConnectTimings>,
+        exchange: Option<Exchange>,
+    ) -> HandshakeReport {
+        // A connection established before the request was sent is reused.
+        let reused = match (connection, exchange) {
+            (Some(connection), Some(exchange)) => !connection.started_after(exchange.sent),
+            _ => false,
+        };
+        let first_byte = exchange.map(|exchange| {
+            let waited = exchange.head.saturating_duration_since(exchange.sent);
+            match connection {
+                Some(connection) if !reused => waited.saturating_sub(connection.total()),
+                _ => waited,
+            }
+        });
+        HandshakeReport {
+            version,
+            remote_addr,
+            connection,
+            reused,
+            first_byte,
+            #[cfg(feature = "__tls")]
+            tls: None,
+            http2: None,
+        }
+    }
+
+    /// Get the HTTP version negotiated.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Get the address of the server, or of the proxy, connected to.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Whether the request was sent on a pooled connection, established
+    /// for an earlier request.
+    pub fn reused(&self) -> bool {
+        self.reused
+    }
+
+    /// Get the phases of establishing the connection: DNS, TCP connect and
+    /// TLS handshake.
+    ///
+    /// For a reused connection, these are the phases it went through for
+    /// the request it was established for.
+    pub fn connection(&self) -> Option<&ConnectTimings> {
+        self.connection.as_ref()
+    }
+
+    /// Get how long the head of the response took to come once the request
+    /// was sent, on the connection.
+    ///
+    /// For a redirected request, this is the time of the last redirect.
+    pub fn first_byte(&self) -> Option<Duration> {
+        self.first_byte
+    }
+
+    /// Get the TLS parameters negotiated: version, cipher suite, ALPN
+    /// protocol and certificates of the server.
+    ///
+    /// This is `None` for plain HTTP, and unless the client was built with
+    /// [`ClientBuilder::tls_info`](crate::ClientBuilder::tls_info).
+    #[cfg(feature = "__tls")]
+    pub fn tls(&self) -> Option<&crate::tls::TlsInfo> {
+        self.tls.as_ref()
+    }
+
+    /// Get the settings and the connection window the server sent at the
+    /// start of an HTTP/2 connection.
+    pub fn http2(&self) -> Option<&ServerPreface> {
+        self.http2.as_ref()
+    }
+}
+
+// ===== impl Ping =====
+
+impl Ping {
+    /// Get the status of the response.
+    ///
+    /// Any status means the server answered, even one refusing the method.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get the HTTP version of the connection.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Whether the ping reused a pooled connection.
+    pub fn reused(&self) -> bool {
+        self.connection.is_none()
+    }
+
+    /// Get the phases of the connection established for the ping, or `None`
+    /// if it reused one.
+    pub fn connection(&self) -> Option<&ConnectTimings> {
+        self.connection.as_ref()
+    }
+
+    /// Get how long the head of the response took to come once the request
+    /// was sent, on the connection.
+    pub fn first_byte(&self) -> Duration {
+        self.first_byte
+    }
+
+    /// Get how long the ping took in all, until the end of the response.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+}
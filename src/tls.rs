@@ -9,7 +9,20 @@
 
 #[cfg(feature = "__boring")]
 use crate::impersonate::BoringTlsConnector;
+#[cfg(feature = "__boring")]
+use boring::{
+    error::ErrorStack,
+    pkcs12::Pkcs12,
+    pkey::{PKey, Private},
+    ssl::{
+        AsyncPrivateKeyMethod, AsyncPrivateKeyMethodError, BoxPrivateKeyMethodFinish,
+        BoxPrivateKeyMethodFuture, SslConnectorBuilder, SslRef, SslSignatureAlgorithm,
+    },
+    x509::X509,
+};
 use std::fmt;
+#[cfg(feature = "__boring")]
+use std::sync::Arc;
 
 /// A TLS protocol version.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -37,6 +50,374 @@ impl Version {
     pub const TLS_1_3: Version = Version(InnerVersion::Tls1_3);
 }
 
+/// A certificate, such as the root of a private CA.
+///
+/// Trusted with
+/// [`ClientBuilder::add_root_certificate`](crate::ClientBuilder::add_root_certificate).
+#[cfg(feature = "__boring")]
+#[derive(Clone)]
+pub struct Certificate {
+    inner: X509,
+}
+
+/// A client certificate, and the key that proves the client holds it.
+///
+/// Set with [`ClientBuilder::identity`](crate::ClientBuilder::identity).
+#[cfg(feature = "__boring")]
+#[derive(Clone)]
+pub struct Identity {
+    inner: ClientCert,
+}
+
+#[cfg(feature = "__boring")]
+#[derive(Clone)]
+enum ClientCert {
+    /// A key held in memory.
+    Key {
+        chain: Vec<X509>,
+        key: PKey<Private>,
+    },
+    /// A key that never leaves its device, which signs for the client.
+    Signer {
+        chain: Vec<X509>,
+        signer: Arc<dyn PrivateKeySigner>,
+    },
+}
+
+/// A private key that signs TLS handshakes without being exported, such as
+/// a key on a PKCS#11 token, a smart card, a TPM, or in the keystore of the
+/// OS.
+///
+/// BoringSSL calls the signer when the server asks for a client
+/// certificate, passing it the whole message to sign, which the signer
+/// hashes as the algorithm says. An RSA key is asked for
+/// `RSA_PSS_RSAE_SHA256` or its kin in TLS 1.3, so a token must support
+/// the PSS mechanisms, such as `CKM_SHA256_RSA_PKCS_PSS` with PKCS#11.
+///
+/// The signer is called on the blocking threads of the runtime, with
+/// [`tokio::task::spawn_blocking`], while the handshake waits for the
+/// signature, so it may block on the token. Outside of a Tokio runtime,
+/// such as with [`ClientBuilder::executor`](crate::ClientBuilder::executor),
+/// it is called on the thread of the handshake instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use rquest::boring::{ssl::SslSignatureAlgorithm, x509::X509};
+/// use rquest::tls::{Identity, PrivateKeySigner};
+///
+/// struct Token {
+///     // The PKCS#11 session and the handle of the key.
+/// }
+///
+/// impl PrivateKeySigner for Token {
+///     fn sign(
+///         &self,
+///         algorithm: SslSignatureAlgorithm,
+///         input: &[u8],
+///     ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+///         // Sign `input` with the mechanism of `algorithm` on the token.
+///         # let _ = (algorithm, input);
+///         unimplemented!()
+///     }
+/// }
+///
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let cert = X509::from_pem(&std::fs::read("client.pem")?)?;
+/// let identity = Identity::from_signer(vec![cert], Token {})?;
+/// let client = rquest::Client::builder().identity(identity).build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "__boring")]
+pub trait PrivateKeySigner: Send + Sync + 'static {
+    /// Sign `input` with `algorithm`, returning the signature.
+    fn sign(
+        &self,
+        algorithm: SslSignatureAlgorithm,
+        input: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+#[cfg(feature = "__boring")]
+impl Certificate {
+    /// A certificate from its DER encoding.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `der` isn't a certificate.
+    pub fn from_der(der: &[u8]) -> crate::Result<Certificate> {
+        X509::from_der(der)
+            .map(|inner| Certificate { inner })
+            .map_err(crate::error::builder)
+    }
+
+    /// A certificate from PEM, the first one if there are several.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `pem` holds no certificate.
+    pub fn from_pem(pem: &[u8]) -> crate::Result<Certificate> {
+        X509::from_pem(pem)
+            .map(|inner| Certificate { inner })
+            .map_err(crate::error::builder)
+    }
+
+    /// The certificates of a PEM bundle, such as a `ca-certificates.crt`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if a certificate of `pem` can't be parsed.
+    pub fn from_pem_bundle(pem: &[u8]) -> crate::Result<Vec<Certificate>> {
+        X509::stack_from_pem(pem)
+            .map(|certs| {
+                certs
+                    .into_iter()
+                    .map(|inner| Certificate { inner })
+                    .collect()
+            })
+            .map_err(crate::error::builder)
+    }
+
+    /// The SHA-256 digest of the public key of the certificate, its DER
+    /// `SubjectPublicKeyInfo`, as pinned with
+    /// [`ClientBuilder::pin_server_certificate`](crate::ClientBuilder::pin_server_certificate).
+    ///
+    /// This is the digest HPKP and `openssl x509 -pubkey | openssl pkey
+    /// -pubin -outform der | openssl dgst -sha256` compute.
+    pub fn public_key_sha256(&self) -> crate::Result<[u8; 32]> {
+        crate::impersonate::pinning::spki_sha256(&self.inner).map_err(crate::error::builder)
+    }
+
+    pub(crate) fn into_x509(self) -> X509 {
+        self.inner
+    }
+}
+
+#[cfg(feature = "__boring")]
+impl From<X509> for Certificate {
+    fn from(inner: X509) -> Certificate {
+        Certificate { inner }
+    }
+}
+
+#[cfg(feature = "__boring")]
+impl fmt::Debug for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Certificate").finish()
+    }
+}
+
+#[cfg(feature = "__boring")]
+impl Identity {
+    /// An identity from a DER-encoded PKCS#12 archive, such as a `.p12` or
+    /// `.pfx` file, encrypted with `password`.
+    ///
+    /// The archive holds the certificate, its key, and optionally the
+    /// intermediate certificates sent along with it.
+    ///
+    /// # Errors
+    ///
+    /// This fails if the archive can't be decrypted and parsed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let der = std::fs::read("client.p12")?;
+    /// let identity = rquest::tls::Identity::from_pkcs12_der(&der, "password")?;
+    /// let client = rquest::Client::builder()
+    ///     .impersonate(rquest::impersonate::Impersonate::Chrome131)
+    ///     .identity(identity)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_pkcs12_der(der: &[u8], password: &str) -> crate::Result<Identity> {
+        let parsed = Pkcs12::from_der(der)
+            .and_then(|pkcs12| pkcs12.parse(password))
+            .map_err(crate::error::builder)?;
+        let mut chain = vec![parsed.cert];
+        chain.extend(parsed.chain.into_iter().flatten());
+        Identity::from_key(chain, parsed.pkey)
+    }
+
+    /// An identity from PEM, holding the certificate, then the
+    /// intermediate certificates sent along with it if any, and the private
+    /// key, in PKCS#8 or the traditional RSA or EC format.
+    ///
+    /// The certificates and the key can be read from separate files and
+    /// concatenated.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `pem` holds no certificate, no key, or a key that
+    /// isn't the one of the certificate.
+    pub fn from_pem(pem: &[u8]) -> crate::Result<Identity> {
+        let chain = X509::stack_from_pem(pem).map_err(crate::error::builder)?;
+        let key = PKey::private_key_from_pem(pem).map_err(crate::error::builder)?;
+        Identity::from_key(chain, key)
+    }
+
+    fn from_key(chain: Vec<X509>, key: PKey<Private>) -> crate::Result<Identity> {
+        let matches = match chain.first() {
+            Some(cert) => cert
+                .public_key()
+                .map_or(false, |public| public.public_eq(&key)),
+            None => return Err(crate::error::builder("identity has no certificate")),
+        };
+        if !matches {
+            return Err(crate::error::builder(
+                "identity key isn't the key of its certificate",
+            ));
+        }
+        Ok(Identity {
+            inner: ClientCert::Key { chain, key },
+        })
+    }
+
+    /// An identity made of a certificate `chain`, leaf first, whose private
+    /// key is held by `signer`.
+    ///
+    /// # Errors
+    ///
+    /// This fails if `chain` is empty.
+    pub fn from_signer<S: PrivateKeySigner>(
+        chain: Vec<X509>,
+        signer: S,
+    ) -> crate::Result<Identity> {
+        if chain.is_empty() {
+            return Err(crate::error::builder("identity has no certificate"));
+        }
+        Ok(Identity {
+            inner: ClientCert::Signer {
+                chain,
+                signer: Arc::new(signer),
+            },
+        })
+    }
+
+    /// Present this identity on the connections made with `builder`.
+    pub(crate) fn add_to_boring(
+        &self,
+        builder: &mut SslConnectorBuilder,
+    ) -> Result<(), ErrorStack> {
+        match self.inner {
+            ClientCert::Key { ref chain, ref key } => {
+                builder.set_certificate(&chain[0])?;
+                for cert in &chain[1..] {
+                    builder.add_extra_chain_cert(cert.clone())?;
+                }
+                builder.set_private_key(key)?;
+            }
+            ClientCert::Signer {
+                ref chain,
+                ref signer,
+            } => {
+                builder.set_certificate(&chain[0])?;
+                for cert in &chain[1..] {
+                    builder.add_extra_chain_cert(cert.clone())?;
+                }
+                builder.set_async_private_key_method(SignerMethod(signer.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "__boring")]
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Identity").finish()
+    }
+}
+
+/// Signs the handshakes of BoringSSL with a `PrivateKeySigner`, off the
+/// threads of the runtime.
+#[cfg(feature = "__boring")]
+struct SignerMethod(Arc<dyn PrivateKeySigner>);
+
+#[cfg(feature = "__boring")]
+impl AsyncPrivateKeyMethod for SignerMethod {
+    fn sign(
+        &self,
+        _ssl: &mut SslRef,
+        input: &[u8],
+        signature_algorithm: SslSignatureAlgorithm,
+        _output: &mut [u8],
+    ) -> Result<BoxPrivateKeyMethodFuture, AsyncPrivateKeyMethodError> {
+        use futures_util::future::{self, Either};
+
+        let signer = self.0.clone();
+        let input = input.to_vec();
+        let sign = move || signer.sign(signature_algorithm, &input);
+        // The handshake is retried once the signature is there. Without a
+        // runtime to block on, the signer can only be called right away.
+        let signing = match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => Either::Left(runtime.spawn_blocking(sign)),
+            Err(_) => Either::Right(future::ready(Ok(sign()))),
+        };
+        Ok(Box::pin(async move {
+            let signature = match signing.await {
+                Ok(Ok(signature)) => signature,
+                Ok(Err(err)) => {
+                    log::debug!("client certificate signer failed: {}", err);
+                    return Err(AsyncPrivateKeyMethodError);
+                }
+                Err(err) => {
+                    log::debug!("client certificate signer panicked: {}", err);
+                    return Err(AsyncPrivateKeyMethodError);
+                }
+            };
+            let finish: BoxPrivateKeyMethodFinish = Box::new(move |_ssl, output| {
+                if signature.len() > output.len() {
+                    log::debug!(
+                        "client certificate signature is {} bytes, more than the {} expected",
+                        signature.len(),
+                        output.len()
+                    );
+                    return Err(AsyncPrivateKeyMethodError);
+                }
+                output[..signature.len()].copy_from_slice(&signature);
+                Ok(signature.len())
+            });
+            Ok(finish)
+        }))
+    }
+
+    fn decrypt(
+        &self,
+        _ssl: &mut SslRef,
+        _input: &[u8],
+        _output: &mut [u8],
+    ) -> Result<BoxPrivateKeyMethodFuture, AsyncPrivateKeyMethodError> {
+        // Only RSA key exchange decrypts with the key, and only servers.
+        Err(AsyncPrivateKeyMethodError)
+    }
+}
+
+/// Load the root certificates of the platform's store: the system keychain
+/// on macOS, the `ROOT` store on Windows, and the CA bundle of the
+/// distribution elsewhere, or those of `SSL_CERT_FILE` if set.
+///
+/// Certificates that BoringSSL can't parse are skipped.
+#[cfg(feature = "native-roots")]
+pub(crate) fn native_roots() -> crate::Result<Vec<X509>> {
+    let certs = rustls_native_certs::load_native_certs().map_err(crate::error::builder)?;
+    let roots = certs
+        .iter()
+        .filter_map(|cert| match X509::from_der(cert.as_ref()) {
+            Ok(cert) => Some(cert),
+            Err(err) => {
+                log::debug!("skipping native root certificate: {}", err);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    log::debug!("loaded {} native root certificates", roots.len());
+    Ok(roots)
+}
+
 pub(crate) enum TlsBackend {
     #[cfg(feature = "__boring")]
     BoringTls(BoringTlsConnector),
@@ -72,20 +453,159 @@ impl Default for TlsBackend {
 }
 /// Hyper extension carrying extra TLS layer information.
 /// Made available to clients on responses when `tls_info` is set.
+///
+/// See [`Response::tls_info`](crate::Response::tls_info).
 #[derive(Clone)]
 pub struct TlsInfo {
     pub(crate) peer_certificate: Option<Vec<u8>>,
+    pub(crate) peer_certificate_chain: Vec<Vec<u8>>,
+    pub(crate) version: Option<Version>,
+    pub(crate) cipher: Option<&'static str>,
+    pub(crate) alpn_protocol: Option<Vec<u8>>,
+    pub(crate) session_reused: bool,
+    pub(crate) ech_accepted: bool,
 }
 
 impl TlsInfo {
+    #[cfg(feature = "__boring")]
+    pub(crate) fn from_ssl(ssl: &boring::ssl::SslRef) -> TlsInfo {
+        use boring::ssl::SslVersion;
+        use foreign_types::ForeignTypeRef;
+
+        let version = ssl.version2().and_then(|version| match version {
+            SslVersion::TLS1 => Some(Version::TLS_1_0),
+            SslVersion::TLS1_1 => Some(Version::TLS_1_1),
+            SslVersion::TLS1_2 => Some(Version::TLS_1_2),
+            SslVersion::TLS1_3 => Some(Version::TLS_1_3),
+            _ => None,
+        });
+        TlsInfo {
+            peer_certificate: ssl.peer_certificate().and_then(|c| c.to_der().ok()),
+            peer_certificate_chain: ssl
+                .peer_cert_chain()
+                .map(|chain| chain.iter().filter_map(|c| c.to_der().ok()).collect())
+                .unwrap_or_default(),
+            version,
+            cipher: ssl.current_cipher().and_then(|c| c.standard_name()),
+            alpn_protocol: ssl.selected_alpn_protocol().map(<[u8]>::to_vec),
+            session_reused: ssl.session_reused(),
+            // SAFETY: the call only reads the state of the connection.
+            ech_accepted: unsafe { boring_sys::SSL_ech_accepted(ssl.as_ptr()) == 1 },
+        }
+    }
+
     /// Get the DER encoded leaf certificate of the peer.
     pub fn peer_certificate(&self) -> Option<&[u8]> {
         self.peer_certificate.as_ref().map(|der| &der[..])
     }
+
+    /// Get the DER encoded certificates sent by the peer, leaf first.
+    pub fn peer_certificate_chain(&self) -> impl Iterator<Item = &[u8]> {
+        self.peer_certificate_chain.iter().map(|der| &der[..])
+    }
+
+    /// Get the negotiated TLS version.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Get the IANA name of the negotiated cipher suite, such as
+    /// `TLS_AES_128_GCM_SHA256`.
+    pub fn cipher(&self) -> Option<&'static str> {
+        self.cipher
+    }
+
+    /// Get the protocol negotiated with ALPN, such as `h2`.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Whether the handshake resumed an earlier session.
+    pub fn session_reused(&self) -> bool {
+        self.session_reused
+    }
+
+    /// Whether the server accepted the Encrypted Client Hello offered, see
+    /// [`ClientBuilder::ech_config_list`](crate::ClientBuilder::ech_config_list).
+    pub fn ech_accepted(&self) -> bool {
+        self.ech_accepted
+    }
 }
 
 impl std::fmt::Debug for TlsInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        f.debug_struct("TlsInfo").finish()
+        f.debug_struct("TlsInfo")
+            .field("version", &self.version)
+            .field("cipher", &self.cipher)
+            .field(
+                "alpn_protocol",
+                &self.alpn_protocol.as_deref().map(String::from_utf8_lossy),
+            )
+            .field("session_reused", &self.session_reused)
+            .field("ech_accepted", &self.ech_accepted)
+            .finish()
+    }
+}
+
+#[cfg(all(test, feature = "__boring"))]
+mod tests {
+    use super::*;
+    use boring::{
+        asn1::Asn1Time, ec::EcGroup, ec::EcKey, hash::MessageDigest, nid::Nid, x509::X509Name,
+    };
+
+    fn key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn certificate(key: &PKey<Private>) -> X509 {
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_text("CN", "client").unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder
+            .set_not_before(&*Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&*Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder.sign(key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn identity_from_pem() {
+        let key = key();
+        let mut pem = certificate(&key).to_pem().unwrap();
+        pem.extend(key.private_key_to_pem_pkcs8().unwrap());
+        assert!(Identity::from_pem(&pem).is_ok());
+
+        let mut pem = certificate(&key).to_pem().unwrap();
+        pem.extend(self::key().private_key_to_pem_pkcs8().unwrap());
+        assert!(Identity::from_pem(&pem).unwrap_err().is_builder());
+
+        let pem = key.private_key_to_pem_pkcs8().unwrap();
+        assert!(Identity::from_pem(&pem).unwrap_err().is_builder());
+    }
+
+    #[test]
+    fn identity_from_pkcs12_der() {
+        let key = key();
+        let cert = certificate(&key);
+        let der = Pkcs12::builder()
+            .build("secret", "client", &key, &cert)
+            .unwrap()
+            .to_der()
+            .unwrap();
+        assert!(Identity::from_pkcs12_der(&der, "secret").is_ok());
+        assert!(Identity::from_pkcs12_der(&der, "wrong")
+            .unwrap_err()
+            .is_builder());
     }
 }
@@ -22,6 +22,42 @@ where
     header
 }
 
+/// HMAC-SHA256, see RFC 2104.
+#[cfg(feature = "__boring")]
+pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    use boring::sha::{sha256, Sha256};
+
+    const BLOCK: usize = 64;
+    let mut block = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(&block.map(|b| b ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(&block.map(|b| b ^ 0x5c));
+    outer.update(&inner.finish());
+    outer.finish()
+}
+
+/// Parse the name of a request method.
+///
+/// Names are case-sensitive, but for those of the standard methods that
+/// Fetch uppercases, such as `get` or `Post`.
+pub(crate) fn parse_method(name: &str) -> crate::Result<http::Method> {
+    const NORMALIZED: [&str; 6] = ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"];
+
+    let normalized = NORMALIZED
+        .iter()
+        .find(|standard| standard.eq_ignore_ascii_case(name));
+    http::Method::from_bytes(normalized.unwrap_or(&name).as_bytes())
+        .map_err(|_| crate::error::builder(format!("invalid HTTP method {:?}", name)))
+}
+
 // xor-shift
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) fn fast_random() -> u64 {
@@ -0,0 +1,221 @@
+//! Capture of the decrypted traffic of connections.
+//!
+//! See [`ClientBuilder::wire_capture`] for the format of the file.
+//!
+//! [`ClientBuilder::wire_capture`]: crate::ClientBuilder::wire_capture
+
+use crate::http2::{Event, RawFrame, Tap, SETTINGS, WINDOW_UPDATE};
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The file the connections of a client are captured to.
+///
+/// Records are written by a thread of their own, so that connections don't
+/// block on the file while they are polled.
+pub(crate) struct WireCapture {
+    lines: Mutex<Option<Sender<String>>>,
+    writer: Option<JoinHandle<()>>,
+    next_id: AtomicU32,
+}
+
+impl WireCapture {
+    /// Append to the file at `path`, creating it if needed.
+    pub(crate) fn open(path: &Path) -> io::Result<WireCapture> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (lines, rx) = mpsc::channel();
+        let writer = thread::Builder::new()
+            .name("rquest-wire-capture".into())
+            .spawn(move || write_lines(file, rx))?;
+        Ok(WireCapture {
+            lines: Mutex::new(Some(lines)),
+            writer: Some(writer),
+            next_id: AtomicU32::new(0),
+        })
+    }
+
+    /// Start capturing a new connection.
+    pub(crate) fn connection(self: &Arc<Self>) -> ConnCapture {
+        ConnCapture {
+            sink: self.clone(),
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            tap: Tap::default(),
+        }
+    }
+
+    fn record(&self, id: u32, direction: char, event: Event<'_>) {
+        let (protocol, record) = match event {
+            Event::Http1(data) => ("h1", escape(data)),
+            Event::Preface => ("h2", "PREFACE".to_owned()),
+            Event::Frame(frame) => ("h2", describe(&frame)),
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let line = format!(
+            "{} {:08x} {} {} {}\n",
+            time, id, direction, protocol, record
+        );
+        if let Some(lines) = self.lines.lock().unwrap().as_ref() {
+            let _ = lines.send(line);
+        }
+    }
+}
+
+impl Drop for WireCapture {
+    fn drop(&mut self) {
+        // Close the channel, and wait for the records left to be written.
+        if let Ok(lines) = self.lines.get_mut() {
+            lines.take();
+        }
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_lines(mut file: File, lines: mpsc::Receiver<String>) {
+    for line in lines {
+        // Each record is written at once, so connections don't interleave.
+        if let Err(e) = file.write_all(line.as_bytes()) {
+            log::debug!("wire capture failed: {}", e);
+        }
+    }
+}
+
+/// Records the traffic of one connection.
+pub(crate) struct ConnCapture {
+    sink: Arc<WireCapture>,
+    id: u32,
+    tap: Tap,
+}
+
+impl ConnCapture {
+    pub(crate) fn on_write(&mut self, data: &[u8]) {
+        let (sink, id) = (&self.sink, self.id);
+        self.tap.on_write(data, |event| sink.record(id, 'w', event));
+    }
+
+    pub(crate) fn on_read(&mut self, data: &[u8]) {
+        let (sink, id) = (&self.sink, self.id);
+        self.tap.on_read(data, |event| sink.record(id, 'r', event));
+    }
+}
+
+fn describe(frame: &RawFrame<'_>) -> String {
+    let name = match frame.kind {
+        0 => "DATA",
+        1 => "HEADERS",
+        2 => "PRIORITY",
+        3 => "RST_STREAM",
+        4 => "SETTINGS",
+        5 => "PUSH_PROMISE",
+        6 => "PING",
+        7 => "GOAWAY",
+        8 => "WINDOW_UPDATE",
+        9 => "CONTINUATION",
+        _ => "UNKNOWN",
+    };
+    let mut record = format!(
+        "{} flags=0x{:02x} stream={} len={}",
+        name, frame.flags, frame.stream, frame.len
+    );
+    if name == "UNKNOWN" {
+        let _ = write!(record, " type=0x{:02x}", frame.kind);
+    }
+
+    let payload = match frame.payload {
+        Some(payload) if !payload.is_empty() => payload,
+        _ => return record,
+    };
+    record.push(' ');
+    match frame.kind {
+        SETTINGS if payload.len() % 6 == 0 => {
+            let settings = payload
+                .chunks(6)
+                .map(|s| {
+                    let id = u16::from_be_bytes([s[0], s[1]]);
+                    let value = u32::from_be_bytes([s[2], s[3], s[4], s[5]]);
+                    format!("{}:{}", id, value)
+                })
+                .collect::<Vec<_>>();
+            record.push_str(&settings.join(";"));
+        }
+        WINDOW_UPDATE if payload.len() == 4 => {
+            let increment =
+                u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]) & 0x7fff_ffff;
+            let _ = write!(record, "{}", increment);
+        }
+        _ => {
+            for byte in payload {
+                let _ = write!(record, "{:02x}", byte);
+            }
+        }
+    }
+    record
+}
+
+fn escape(data: &[u8]) -> String {
+    data.iter()
+        .flat_map(|&byte| std::ascii::escape_default(byte))
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http2::{tests::frame, PREFACE};
+
+    #[test]
+    fn records_connections() {
+        let path = std::env::temp_dir().join(format!(
+            "rquest-wire-capture-{}",
+            crate::util::fast_random()
+        ));
+        let sink = Arc::new(WireCapture::open(&path).unwrap());
+
+        let mut h2 = sink.connection();
+        h2.on_write(&PREFACE[..10]);
+        let mut rest = PREFACE[10..].to_vec();
+        rest.extend_from_slice(&frame(4, 0, 0, &[0, 1, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0]));
+        rest.extend_from_slice(&frame(8, 0, 0, &15663105u32.to_be_bytes()));
+        h2.on_write(&rest);
+        h2.on_read(&frame(4, 1, 0, &[]));
+        h2.on_read(&frame(0, 1, 1, &[b'x'; 20]));
+        h2.on_read(&frame(6, 0, 0, &[1, 2]));
+
+        let mut h1 = sink.connection();
+        h1.on_write(b"GET / HTTP/1.1\r\n\r\n");
+        h1.on_read(b"HTTP/1.1 200 OK\r\n\r\n");
+
+        // The records are written once the capture is dropped.
+        drop((h2, h1, sink));
+        let capture = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records = capture
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            [
+                "00000000 w h2 PREFACE",
+                "00000000 w h2 SETTINGS flags=0x00 stream=0 len=12 1:65536;2:0",
+                "00000000 w h2 WINDOW_UPDATE flags=0x00 stream=0 len=4 15663105",
+                "00000000 r h2 SETTINGS flags=0x01 stream=0 len=0",
+                "00000000 r h2 DATA flags=0x01 stream=1 len=20",
+                "00000000 r h2 PING flags=0x00 stream=0 len=2 0102",
+                r"00000001 w h1 GET / HTTP/1.1\r\n\r\n",
+                r"00000001 r h1 HTTP/1.1 200 OK\r\n\r\n",
+            ]
+        );
+    }
+}
@@ -74,6 +74,831 @@ async fn user_agent() {
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
 
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn impersonate_headers_only() {
+    let server = server::http(move |req| async move {
+        assert!(req.headers()["user-agent"]
+            .to_str()
+            .unwrap()
+            .contains("Chrome/127"));
+        assert!(req.headers().contains_key("sec-ch-ua"));
+        http::Response::default()
+    });
+
+    let url = format!("http://{}/headers", server.addr());
+    let res = rquest::Client::builder()
+        .impersonate_headers(rquest::impersonate::Impersonate::Chrome127)
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn request_header_order() {
+    let server = server::http(move |req| async move {
+        let names = req
+            .headers()
+            .keys()
+            .map(|name| name.as_str())
+            .filter(|name| name.starts_with("x-"))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["x-c", "x-a", "x-b"]);
+        http::Response::default()
+    });
+
+    let url = format!("http://{}/order", server.addr());
+    let res = rquest::Client::builder()
+        .header_order(vec![
+            http::HeaderName::from_static("x-a"),
+            http::HeaderName::from_static("x-b"),
+        ])
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .header("x-a", "1")
+        .header("x-b", "2")
+        .header("x-c", "3")
+        .header_order(vec![
+            http::HeaderName::from_static("x-c"),
+            http::HeaderName::from_static("x-a"),
+        ])
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn impersonate_tls_only() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.headers()["accept"], "*/*");
+        assert_eq!(req.headers().get("user-agent"), None);
+        assert!(!req.headers().contains_key("sec-ch-ua"));
+        http::Response::default()
+    });
+
+    let url = format!("http://{}/tls", server.addr());
+    let res = rquest::Client::builder()
+        .impersonate_tls(rquest::impersonate::Impersonate::Chrome127)
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn request_impersonate() {
+    let server = server::http(move |req| async move {
+        let user_agent = req.headers()["user-agent"].to_str().unwrap().to_owned();
+        if req.uri().path() == "/chrome" {
+            assert!(user_agent.contains("Chrome/127"));
+            assert!(req.headers().contains_key("sec-ch-ua"));
+        } else {
+            assert_eq!(user_agent, "client");
+            assert!(!req.headers().contains_key("sec-ch-ua"));
+        }
+        http::Response::default()
+    });
+
+    let client = rquest::Client::builder()
+        .user_agent("client")
+        .build()
+        .expect("client builder");
+
+    let url = format!("http://{}/chrome", server.addr());
+    let res = client
+        .get(&url)
+        .impersonate(rquest::impersonate::Impersonate::Chrome127)
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let url = format!("http://{}/client", server.addr());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn request_impersonate_keeps_client_decoders() {
+    let server = server::http(move |req| async move {
+        let accept_encoding = req
+            .headers()
+            .get("accept-encoding")
+            .map_or("", |value| value.to_str().unwrap());
+        assert!(!accept_encoding.contains("gzip"), "{}", accept_encoding);
+        assert!(!accept_encoding.contains("br"), "{}", accept_encoding);
+        http::Response::default()
+    });
+
+    let client = rquest::Client::builder()
+        .no_gzip()
+        .no_brotli()
+        .build()
+        .expect("client builder");
+    let res = client
+        .get(&format!("http://{}/", server.addr()))
+        .impersonate(rquest::impersonate::Impersonate::Chrome127)
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn wire_capture() {
+    let server = server::http(move |_req| async move { http::Response::new("captured".into()) });
+
+    let path = std::env::temp_dir().join(format!("rquest-wire-capture-{}", server.addr().port()));
+    let url = format!("http://{}/capture", server.addr());
+    let res = rquest::Client::builder()
+        .wire_capture(&path)
+        .no_proxy()
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.text().await.expect("text"), "captured");
+
+    let capture = std::fs::read_to_string(&path).expect("capture");
+    std::fs::remove_file(&path).expect("remove capture");
+    assert!(capture.contains(" w h1 GET /capture HTTP/1.1\\r\\n"));
+    assert!(capture.contains(" r h1 HTTP/1.1 200 OK\\r\\n"));
+}
+
+#[tokio::test]
+async fn http1_head_writes() {
+    use rquest::http1::HeadWrites;
+
+    let server = server::http(move |req| async move {
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        http::Response::new(body.len().to_string().into())
+    });
+
+    let url = format!("http://{}/writes", server.addr());
+    for head_writes in [
+        HeadWrites::Coalesced,
+        HeadWrites::Separate,
+        HeadWrites::MergeUpTo(1400),
+    ] {
+        let client = rquest::Client::builder()
+            .http1_head_writes(head_writes)
+            .build()
+            .expect("client builder");
+        for len in [10, 4000] {
+            let res = client
+                .post(&url)
+                .body(vec![b'x'; len])
+                .send()
+                .await
+                .expect("request");
+            assert_eq!(res.text().await.expect("text"), len.to_string());
+        }
+    }
+}
+
+#[tokio::test]
+async fn h2c_prior_knowledge() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.version(), http::Version::HTTP_2);
+        http::Response::default()
+    });
+
+    let client = rquest::Client::builder()
+        .h2c_prior_knowledge()
+        .build()
+        .expect("client builder");
+
+    let url = format!("http://{}/h2c", server.addr());
+    for _ in 0..2 {
+        let res = client.get(&url).send().await.expect("request");
+        assert_eq!(res.version(), http::Version::HTTP_2);
+    }
+}
+
+#[tokio::test]
+async fn handshake_report() {
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let client = rquest::Client::builder()
+        .h2c_prior_knowledge()
+        .build()
+        .expect("client builder");
+    let url = format!("http://{}/report", server.addr());
+
+    let first = client.get(&url).send().await.unwrap().handshake_report();
+    assert_eq!(first.version(), http::Version::HTTP_2);
+    assert_eq!(first.remote_addr(), Some(server.addr()));
+    assert!(!first.reused());
+    assert!(first.connection().is_some());
+    assert!(first.first_byte().is_some());
+    let http2 = first.http2().expect("server preface");
+    assert!(!http2.settings().is_empty());
+
+    let second = client.get(&url).send().await.unwrap().handshake_report();
+    assert!(second.reused());
+    assert_eq!(second.connection(), first.connection());
+    assert_eq!(second.http2(), Some(http2));
+}
+
+#[tokio::test]
+async fn extension_methods_over_http1_and_http2() {
+    let server = server::http(move |req| async move {
+        let method = req.method().to_string();
+        let depth = req.headers().get("depth").cloned();
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        let mut res = http::Response::new(format!("{} {}", method, body.len()).into());
+        if let Some(depth) = depth {
+            res.headers_mut().insert("depth", depth);
+        }
+        res
+    });
+    let url = format!("http://{}/dav/", server.addr());
+
+    for client in [
+        Client::new(),
+        Client::builder().h2c_prior_knowledge().build().unwrap(),
+    ] {
+        let res = client
+            .propfind(&url)
+            .header("depth", "1")
+            .body("<propfind xmlns=\"DAV:\"><allprop/></propfind>")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.headers()["depth"], "1");
+        assert_eq!(res.text().await.unwrap(), "PROPFIND 44");
+
+        for (req, method) in [
+            (client.mkcol(&url), "MKCOL"),
+            (client.report(&url), "REPORT"),
+            (client.purge(&url), "PURGE"),
+            (client.method("SUBSCRIBE", &url), "SUBSCRIBE"),
+            (client.method("get", &url), "GET"),
+        ] {
+            let res = req.send().await.unwrap();
+            assert_eq!(res.text().await.unwrap(), format!("{} 0", method));
+        }
+    }
+
+    let err = Client::new()
+        .method("NOT A METHOD", &url)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn ping_reuses_pooled_connection() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.method(), "OPTIONS");
+        http::Response::builder()
+            .status(204)
+            .header("allow", "GET, OPTIONS")
+            .body(Default::default())
+            .unwrap()
+    });
+    let url = format!("http://{}/", server.addr());
+    let client = Client::new();
+
+    let first = client.ping(&url).await.unwrap();
+    assert_eq!(first.status(), rquest::StatusCode::NO_CONTENT);
+    assert!(!first.reused());
+    let connection = first.connection().unwrap();
+    // The host is an IP address, so nothing is resolved.
+    assert_eq!(connection.dns(), None);
+    assert_eq!(connection.tls(), None);
+    assert!(first.total() >= connection.total());
+
+    let second = client.ping(&url).await.unwrap();
+    assert!(second.reused());
+    assert!(second.connection().is_none());
+
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.connect_timings(), Some(*connection));
+}
+
+#[tokio::test]
+async fn http2_frame_callback() {
+    use rquest::http2::{Direction, Frame};
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let seen = frames.clone();
+    let url = format!("http://{}/frames", server.addr());
+    let res = rquest::Client::builder()
+        .http2_prior_knowledge()
+        .http2_frame_callback(move |direction, frame| {
+            seen.lock().unwrap().push((direction, frame.clone()));
+        })
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.version(), http::Version::HTTP_2);
+
+    let frames = frames.lock().unwrap();
+    let settings = |direction| {
+        frames.iter().any(|(d, frame)| {
+            *d == direction && matches!(frame, Frame::Settings { ack: false, .. })
+        })
+    };
+    assert!(settings(Direction::Sent));
+    assert!(settings(Direction::Received));
+}
+
+/// The pseudo-header fields of the first request `client` sends over
+/// HTTP/2, in order, as Akamai fingerprints write them.
+async fn sent_pseudo_order(client: rquest::Client) -> String {
+    use tokio::io::AsyncReadExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let server = async move {
+        let (mut tcp, _) = listener.accept().await.unwrap();
+        let mut preface = [0; 24];
+        tcp.read_exact(&mut preface).await.unwrap();
+        loop {
+            let mut header = [0; 9];
+            tcp.read_exact(&mut header).await.unwrap();
+            let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let mut payload = vec![0; len];
+            tcp.read_exact(&mut payload).await.unwrap();
+            // The connection closes with the first HEADERS frame.
+            if header[3] == 0x1 {
+                return (header[4], payload);
+            }
+        }
+    };
+    let ((flags, block), _) = tokio::join!(server, client.get(&url).send());
+
+    let mut i = 0;
+    if flags & 0x8 != 0 {
+        i += 1;
+    }
+    if flags & 0x20 != 0 {
+        i += 5;
+    }
+    let mut order = Vec::new();
+    while order.len() < 4 {
+        // The fields of a first request are indexed in the static table,
+        // but for the authority, whose value is a literal.
+        match block[i] {
+            0x82 => order.push("m"),
+            0x84 => order.push("p"),
+            0x86 => order.push("s"),
+            0x01 | 0x11 | 0x41 => {
+                order.push("a");
+                i += 1 + (block[i + 1] & 0x7f) as usize;
+            }
+            other => panic!("unexpected field representation {:#x}", other),
+        }
+        i += 1;
+    }
+    order.join(",")
+}
+
+#[tokio::test]
+async fn http2_settings_send_each_pseudo_order() {
+    use rquest::impersonate::Http2Settings;
+
+    for fingerprint in [
+        "2:0|15663105|0|m,a,s,p",
+        "2:0|15663105|0|m,p,a,s",
+        "2:0|15663105|0|m,s,p,a",
+    ] {
+        let client = rquest::Client::builder()
+            .http2_prior_knowledge()
+            .http2_settings(Http2Settings::from_akamai(fingerprint).unwrap())
+            .build()
+            .expect("client builder");
+        let order = sent_pseudo_order(client).await;
+        assert!(
+            fingerprint.ends_with(&order),
+            "{} sent {}",
+            fingerprint,
+            order
+        );
+    }
+}
+
+#[tokio::test]
+async fn http2_settings_shape_the_preface() {
+    use rquest::http2::{Direction, Frame};
+    use rquest::impersonate::Http2Settings;
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let settings =
+        Http2Settings::from_akamai("2:0;4:4194304;3:100|10485760|3:0:0:201,5:0:0:101|m,s,p,a")
+            .unwrap();
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let seen = frames.clone();
+    let client = rquest::Client::builder()
+        .http2_prior_knowledge()
+        .http2_settings(settings)
+        .http2_frame_callback(move |direction, frame| {
+            if direction == Direction::Sent {
+                seen.lock().unwrap().push(frame.clone());
+            }
+        })
+        .build()
+        .expect("client builder");
+    for _ in 0..2 {
+        let res = client
+            .get(&format!("http://{}/preface", server.addr()))
+            .send()
+            .await
+            .expect("request");
+        assert_eq!(res.status(), rquest::StatusCode::OK);
+    }
+
+    let frames = frames.lock().unwrap();
+    let order = frames
+        .iter()
+        .find_map(|frame| match frame {
+            Frame::Settings {
+                ack: false,
+                settings,
+            } => Some(settings.iter().map(|&(id, _)| id).collect::<Vec<_>>()),
+            _ => None,
+        })
+        .expect("SETTINGS sent");
+    assert_eq!(order[..3], [2, 4, 3]);
+    assert!(frames.contains(&Frame::WindowUpdate {
+        stream: 0,
+        increment: 10485760,
+    }));
+    let priority = frames
+        .iter()
+        .filter(|frame| matches!(frame, Frame::Priority { .. }))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        priority,
+        [
+            Frame::Priority {
+                stream: 3,
+                exclusive: false,
+                dependency: 0,
+                weight: 201,
+            },
+            Frame::Priority {
+                stream: 5,
+                exclusive: false,
+                dependency: 0,
+                weight: 101,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn http2_settings_give_requests_a_priority() {
+    use rquest::impersonate::Http2Settings;
+
+    let server = server::http(move |req| async move {
+        assert_eq!(req.version(), http::Version::HTTP_2);
+        http::Response::new("priority".into())
+    });
+
+    let settings = Http2Settings::builder()
+        .headers_priority(true, 0, 256)
+        .build()
+        .unwrap();
+    assert_eq!(
+        settings
+            .headers_priority()
+            .map(|p| (p.exclusive(), p.weight())),
+        Some((true, 256))
+    );
+    let client = rquest::Client::builder()
+        .http2_prior_knowledge()
+        .http2_settings(settings)
+        .build()
+        .expect("client builder");
+    for _ in 0..2 {
+        let res = client
+            .get(&format!("http://{}/priority", server.addr()))
+            .send()
+            .await
+            .expect("request");
+        assert_eq!(res.text().await.expect("text"), "priority");
+    }
+}
+
+#[tokio::test]
+async fn http2_settings_keep_unset_settings() {
+    use rquest::http2::{Direction, Frame};
+    use rquest::impersonate::Http2Settings;
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let settings = Http2Settings::from_akamai("2:0;4:4194304|10485760|0|m,a,s,p").unwrap();
+    let sent = Arc::new(Mutex::new(None));
+    let seen = sent.clone();
+    let client = rquest::Client::builder()
+        .http2_prior_knowledge()
+        .http2_max_frame_size(32768)
+        .http2_settings(settings)
+        .http2_frame_callback(move |direction, frame| {
+            if let (
+                Direction::Sent,
+                Frame::Settings {
+                    ack: false,
+                    settings,
+                },
+            ) = (direction, frame)
+            {
+                seen.lock().unwrap().get_or_insert_with(|| settings.clone());
+            }
+        })
+        .build()
+        .expect("client builder");
+    let res = client
+        .get(&format!("http://{}/settings", server.addr()))
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let sent = sent.lock().unwrap().clone().expect("SETTINGS sent");
+    assert!(sent.contains(&(4, 4194304)), "{:?}", sent);
+    assert!(sent.contains(&(5, 32768)), "{:?}", sent);
+}
+
+#[tokio::test]
+async fn custom_executor() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let server = server::http(move |_req| async move { http::Response::default() });
+
+    let spawned = Arc::new(AtomicUsize::new(0));
+    let counter = spawned.clone();
+    let url = format!("http://{}/executor", server.addr());
+    let res = rquest::Client::builder()
+        .executor(move |task| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(task);
+        })
+        .build()
+        .expect("client builder")
+        .get(&url)
+        .send()
+        .await
+        .expect("request");
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert!(spawned.load(Ordering::SeqCst) > 0);
+}
+
+#[tokio::test]
+async fn middleware_layers_and_interceptors() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use rquest::middleware::{Interceptor, Next};
+    use tower_service::Service;
+
+    // Sends a request once more if the server was unavailable.
+    #[derive(Clone)]
+    struct RetryOnce(Next);
+
+    impl Service<rquest::Request> for RetryOnce {
+        type Response = rquest::Response;
+        type Error = rquest::Error;
+        type Future = Pin<Box<dyn Future<Output = rquest::Result<rquest::Response>> + Send>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<rquest::Result<()>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, req: rquest::Request) -> Self::Future {
+            let mut next = self.0.clone();
+            Box::pin(async move {
+                let retry = req.try_clone();
+                let res = next.call(req).await?;
+                match retry {
+                    Some(retry) if res.status() == rquest::StatusCode::SERVICE_UNAVAILABLE => {
+                        next.call(retry).await
+                    }
+                    _ => Ok(res),
+                }
+            })
+        }
+    }
+
+    struct Sign;
+
+    impl Interceptor for Sign {
+        fn request(
+            &self,
+            req: &mut rquest::Request,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            req.headers_mut()
+                .insert("x-signed", http::HeaderValue::from_static("1"));
+            Ok(())
+        }
+
+        fn response(
+            &self,
+            res: &mut rquest::Response,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            if res.status().is_server_error() {
+                return Err("server error".into());
+            }
+            Ok(())
+        }
+    }
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            assert_eq!(req.headers()["x-signed"], "1");
+            let status = if hit == 0 { 503 } else { 200 };
+            http::Response::builder()
+                .status(status)
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+    let url = format!("http://{}/middleware", server.addr());
+
+    // The interceptor is outermost, so it only sees the retried response.
+    let client = rquest::Client::builder()
+        .with_interceptor(Sign)
+        .with_layer(tower_layer::layer_fn(RetryOnce))
+        .build()
+        .unwrap();
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+    // The other way around, the first response fails the request.
+    hits.store(0, Ordering::SeqCst);
+    let client = rquest::Client::builder()
+        .with_layer(tower_layer::layer_fn(RetryOnce))
+        .with_interceptor(Sign)
+        .build()
+        .unwrap();
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_request());
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn middleware_is_built_once() {
+    use rquest::middleware::Next;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let server = server::http(move |_req| async move { http::Response::default() });
+    let built = Arc::new(AtomicUsize::new(0));
+    let counter = built.clone();
+    let client = rquest::Client::builder()
+        .with_layer(tower_layer::layer_fn(move |next: Next| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            next
+        }))
+        .build()
+        .unwrap();
+    assert_eq!(built.load(Ordering::SeqCst), 1);
+
+    for _ in 0..3 {
+        let res = client
+            .get(&format!("http://{}/once", server.addr()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), rquest::StatusCode::OK);
+    }
+    assert_eq!(built.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn retry_policy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let server = server::http(move |_req| {
+        let hit = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            if hit % 3 == 2 {
+                return http::Response::default();
+            }
+            http::Response::builder()
+                .status(503)
+                .header("retry-after", "0")
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+    let url = format!("http://{}/retry", server.addr());
+
+    let client = rquest::Client::builder()
+        .retry_policy(
+            rquest::retry::Policy::new(3).backoff(Duration::from_secs(30), Duration::from_secs(30)),
+        )
+        .build()
+        .unwrap();
+
+    // The `Retry-After` of the responses is waited for, not the backoff.
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+    // A POST isn't idempotent, so it is sent once.
+    let res = client.post(&url).body("once").send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(hits.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn retry_idempotency_key() {
+    use std::sync::{Arc, Mutex};
+
+    let keys = Arc::new(Mutex::new(Vec::new()));
+    let seen = keys.clone();
+    let server = server::http(move |req| {
+        let mut seen = seen.lock().unwrap();
+        seen.push(req.headers()["idempotency-key"].clone());
+        let status = if seen.len() % 2 == 1 { 503 } else { 201 };
+        async move {
+            http::Response::builder()
+                .status(status)
+                .header("retry-after", "0")
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+    let url = format!("http://{}/charges", server.addr());
+
+    let client = rquest::Client::builder()
+        .retry_policy(rquest::retry::Policy::new(3))
+        .build()
+        .unwrap();
+
+    // A POST with a key is sent again, with the same key.
+    let res = client
+        .post(&url)
+        .idempotency_key(rquest::retry::IdempotencyKey::Auto)
+        .body("amount=2000")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::CREATED);
+
+    let res = client
+        .post(&url)
+        .idempotency_key("order-42")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::CREATED);
+
+    let keys = keys.lock().unwrap();
+    assert_eq!(keys.len(), 4);
+    assert_eq!(keys[0], keys[1]);
+    let uuid = keys[0].to_str().unwrap();
+    assert_eq!(uuid.len(), 36);
+    assert_eq!(&uuid[14..15], "4");
+    assert!(["8", "9", "a", "b"].contains(&&uuid[19..20]));
+    assert_eq!(keys[2], "order-42");
+    assert_eq!(keys[3], "order-42");
+}
+
 #[tokio::test]
 async fn response_text() {
     let _ = env_logger::try_init();
@@ -128,6 +953,107 @@ async fn response_json() {
     assert_eq!("Hello", text);
 }
 
+#[tokio::test]
+#[cfg(feature = "json")]
+async fn response_json_blocking_offloaded() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |req| async move {
+        if req.uri().path() == "/bad" {
+            http::Response::new("[1, 2,".into())
+        } else {
+            http::Response::new("[1, 2, 3]".into())
+        }
+    });
+
+    let client = Client::new();
+
+    let numbers = client
+        .get(&format!("http://{}/json", server.addr()))
+        .send()
+        .await
+        .expect("Failed to get")
+        .json_blocking_offloaded::<Vec<u32>>()
+        .await
+        .expect("Failed to get json");
+    assert_eq!(numbers, [1, 2, 3]);
+
+    let err = client
+        .get(&format!("http://{}/bad", server.addr()))
+        .send()
+        .await
+        .expect("Failed to get")
+        .json_blocking_offloaded::<Vec<u32>>()
+        .await
+        .unwrap_err();
+    assert!(err.is_decode());
+}
+
+#[tokio::test]
+#[cfg(feature = "json")]
+async fn response_json_lenient() {
+    let server = server::http(move |_req| async {
+        http::Response::new("\u{feff}{\"a\": [1, NaN,], // note\n}".into())
+    });
+
+    let res = Client::new()
+        .get(&format!("http://{}/json", server.addr()))
+        .send()
+        .await
+        .expect("Failed to get");
+    let json = res
+        .json_lenient::<HashMap<String, Vec<Option<f64>>>>()
+        .await
+        .expect("Failed to get json");
+    assert_eq!(json["a"], [Some(1.0), None]);
+}
+
+#[tokio::test]
+#[cfg(feature = "json")]
+async fn response_json_or_error() {
+    let server = server::http(move |req| async move {
+        match req.uri().path() {
+            "/ok" => http::Response::new("\"Hello\"".into()),
+            "/problem" => http::Response::builder()
+                .status(404)
+                .body("{\"message\":\"missing\"}".into())
+                .unwrap(),
+            _ => http::Response::builder()
+                .status(502)
+                .body("<html>Bad Gateway</html>".into())
+                .unwrap(),
+        }
+    });
+
+    let client = Client::new();
+    let get = |path: &str| {
+        client
+            .get(format!("http://{}{}", server.addr(), path))
+            .send()
+    };
+
+    let res = get("/ok").await.unwrap();
+    let text = res.json_or_error::<String, HashMap<String, String>>().await;
+    assert_eq!(text.unwrap(), "Hello");
+
+    let res = get("/problem").await.unwrap();
+    match res.json_or_error::<String, HashMap<String, String>>().await {
+        Err(rquest::ApiError::Status { status, error }) => {
+            assert_eq!(status, rquest::StatusCode::NOT_FOUND);
+            assert_eq!(error["message"], "missing");
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let res = get("/gateway").await.unwrap();
+    let err = res
+        .json_or_error::<String, HashMap<String, String>>()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(rquest::StatusCode::BAD_GATEWAY));
+    assert_eq!(err.body().unwrap(), "<html>Bad Gateway</html>");
+}
+
 #[tokio::test]
 async fn body_pipe_response() {
     let _ = env_logger::try_init();
@@ -187,12 +1113,179 @@ async fn overridden_dns_resolution_with_gai() {
         .resolve(overridden_domain, server.addr())
         .build()
         .expect("client builder");
-    let req = client.get(&url);
-    let res = req.send().await.expect("request");
+    let req = client.get(&url);
+    let res = req.send().await.expect("request");
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    let text = res.text().await.expect("Failed to get text");
+    assert_eq!("Hello", text);
+}
+
+#[tokio::test]
+async fn request_resolve_to() {
+    use rquest::egress::{Policy, Rule};
+
+    let _ = env_logger::builder().is_test(true).try_init();
+    let server = server::http(move |req| async move {
+        let host = req.headers()["host"].to_str().unwrap();
+        assert!(host.starts_with("rquest.test:"));
+        http::Response::new("Hello".into())
+    });
+
+    let url = format!("http://rquest.test:{}/resolve_to", server.addr().port());
+    let client = rquest::Client::new();
+    let res = client
+        .get(&url)
+        .resolve_to(server.addr().ip())
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.remote_addr(), Some(server.addr()));
+
+    let err = client
+        .get(&url)
+        .resolve_to(server.addr().ip())
+        .proxy(rquest::Proxy::http("http://127.0.0.1:1").unwrap())
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+
+    // The proxies of the client would resolve the host as well.
+    let proxied = rquest::Client::builder()
+        .proxy(rquest::Proxy::http("http://127.0.0.1:1").unwrap())
+        .build()
+        .unwrap();
+    let err = proxied
+        .get(&url)
+        .resolve_to(server.addr().ip())
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_builder());
+
+    // The address is checked as a resolved one would be.
+    let denying = rquest::Client::builder()
+        .deny_private_ips(true)
+        .build()
+        .unwrap();
+    let err = denying
+        .get(&url)
+        .resolve_to(server.addr().ip())
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request());
+
+    let policed = rquest::Client::builder()
+        .egress_policy(Policy::deny([Rule::new().ip_range([127, 0, 0, 0], 8)]))
+        .build()
+        .unwrap();
+    let err = policed
+        .get(&url)
+        .resolve_to(server.addr().ip())
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request());
+}
+
+#[tokio::test]
+async fn dns_fallback_and_resolved_by() {
+    use rquest::dns::{Fallback, Name, Resolve, ResolvedBy, Resolving};
+
+    struct Failing;
+
+    impl Resolve for Failing {
+        fn resolve(&self, _: Name) -> Resolving {
+            Box::pin(async { Err("no such host".into()) })
+        }
+    }
+
+    let _ = env_logger::builder().is_test(true).try_init();
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let client = rquest::Client::builder()
+        .dns_resolver(std::sync::Arc::new(Failing))
+        .dns_fallback(Fallback::CustomThenSystem)
+        .dns_timeout(std::time::Duration::from_secs(5))
+        .resolve("rquest.test", server.addr())
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    let url = format!("http://localhost:{}/", server.addr().port());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.resolved_by(), Some(ResolvedBy::System));
+
+    let url = format!("http://rquest.test:{}/", server.addr().port());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.resolved_by(), Some(ResolvedBy::Override));
+
+    let url = format!("http://{}/", server.addr());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.resolved_by(), None);
+}
+
+#[tokio::test]
+async fn dns_resolver_dyn() {
+    use rquest::dns::{Addrs, Name, Resolve, ResolvedBy, Resolving};
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    struct Pinned(SocketAddr);
+
+    impl Resolve for Pinned {
+        fn resolve(&self, _: Name) -> Resolving {
+            let addrs: Addrs = Box::new(std::iter::once(self.0));
+            Box::pin(async move { Ok(addrs) })
+        }
+    }
+
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    let resolver: Arc<dyn Resolve> = Arc::new(Pinned(server.addr()));
+    let client = rquest::Client::builder()
+        .dns_resolver(resolver)
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    let url = format!("http://rquest.test:{}/", server.addr().port());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.resolved_by(), Some(ResolvedBy::Custom));
+}
+
+#[cfg(feature = "__boring")]
+#[tokio::test]
+async fn dns_over_https() {
+    use rquest::dns::ResolvedBy;
+
+    assert!(rquest::Client::builder()
+        .dns_over_https("http://1.1.1.1/dns-query")
+        .build()
+        .is_err());
+
+    let server = server::http(move |_req| async { http::Response::new("Hello".into()) });
+
+    // localhost is answered without querying the server.
+    let client = rquest::Client::builder()
+        .dns_over_https("https://doh.invalid/dns-query")
+        .dns_over_https_bootstrap(&["192.0.2.1".parse().unwrap()])
+        .connect_timeout(std::time::Duration::from_millis(100))
+        .no_proxy()
+        .build()
+        .expect("client builder");
 
-    assert_eq!(res.status(), rquest::StatusCode::OK);
-    let text = res.text().await.expect("Failed to get text");
-    assert_eq!("Hello", text);
+    let url = format!("http://localhost:{}/", server.addr().port());
+    let res = client.get(&url).send().await.expect("request");
+    assert_eq!(res.resolved_by(), Some(ResolvedBy::DnsOverHttps));
+
+    // Other names are, through the unreachable bootstrap address.
+    let url = format!("http://rquest.invalid:{}/", server.addr().port());
+    let err = client.get(&url).send().await.unwrap_err();
+    assert!(err.is_connect());
 }
 
 #[tokio::test]
@@ -338,6 +1431,13 @@ async fn test_tls_info() {
     assert!(peer_certificate.is_some());
     let der = peer_certificate.unwrap();
     assert_eq!(der[0], 0x30); // ASN.1 SEQUENCE
+    assert_eq!(
+        tls_info.peer_certificate_chain().next(),
+        tls_info.peer_certificate()
+    );
+    assert!(tls_info.version().is_some());
+    assert!(tls_info.cipher().is_some());
+    assert!(resp.tls_info().is_some());
 
     let resp = rquest::Client::builder()
         .build()
@@ -349,3 +1449,655 @@ async fn test_tls_info() {
     let tls_info = resp.extensions().get::<rquest::tls::TlsInfo>();
     assert!(tls_info.is_none());
 }
+
+#[tokio::test]
+async fn scope_aborts_spawned_requests_on_drop() {
+    let server = server::http(move |_req| async {
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        http::Response::default()
+    });
+
+    let client = Client::new();
+    let scope = client.scope();
+    let url = format!("http://{}/slow", server.addr());
+    let pending = tokio::spawn(scope.get(&url).send());
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    drop(scope);
+
+    let err = pending.await.unwrap().unwrap_err();
+    assert!(err.is_request());
+    assert_eq!(err.url().map(|u| u.as_str()), Some(url.as_str()));
+}
+
+#[tokio::test]
+async fn scope_client_fails_after_drop() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let scope = Client::new().scope();
+    let client = scope.client().clone();
+    let url = format!("http://{}/", server.addr());
+    assert!(client.get(&url).send().await.is_ok());
+
+    drop(scope);
+    assert!(client.get(&url).send().await.unwrap_err().is_request());
+}
+
+#[tokio::test]
+async fn max_in_flight_queues_requests() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let server = {
+        let (running, peak) = (running.clone(), peak.clone());
+        server::http(move |_req| {
+            let (running, peak) = (running.clone(), peak.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                http::Response::new("done".into())
+            }
+        })
+    };
+
+    let client = Client::builder()
+        .max_in_flight(1)
+        .build()
+        .expect("client builder");
+    let url = format!("http://{}/", server.addr());
+
+    let fetch = |priority| {
+        let request = client.get(&url).priority(priority).send();
+        tokio::spawn(async move { request.await?.text().await })
+    };
+    let a = fetch(rquest::Priority::Foreground);
+    let b = fetch(rquest::Priority::Background);
+
+    assert_eq!(a.await.unwrap().unwrap(), "done");
+    assert_eq!(b.await.unwrap().unwrap(), "done");
+    assert_eq!(peak.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn max_in_flight_rejects_zero() {
+    let err = Client::builder().max_in_flight(0).build().unwrap_err();
+    assert!(err.is_builder());
+
+    let err = Client::builder()
+        .max_in_flight_for(rquest::Priority::Background, 0)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn rate_limit_spaces_requests() {
+    use rquest::rate_limit::RateLimit;
+    use std::time::{Duration, Instant};
+
+    let server = server::http(move |_req| async { http::Response::new("done".into()) });
+
+    let limit = RateLimit::new(1, Duration::from_millis(200));
+    let client = Client::builder()
+        .rate_limit(limit)
+        .build()
+        .expect("client builder");
+    let url = format!("http://{}/", server.addr());
+
+    let start = Instant::now();
+    client.get(&url).send().await.unwrap();
+    assert!(start.elapsed() < Duration::from_millis(200));
+    client.get(&url).send().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(190));
+}
+
+#[tokio::test]
+async fn http1_lenient() {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = stream.write_all(
+                b"HTTP/1.1  200\tOK\nContent-Length: 2\nX-Folded: a\n b\n\
+                  Bad Header\nX-Spaced : 1\nConnection: close\n\nok",
+            );
+        }
+    });
+    let url = format!("http://{}/", addr);
+
+    let strict = Client::builder().no_proxy().build().unwrap();
+    assert!(strict.get(&url).send().await.is_err());
+
+    let lenient = Client::builder()
+        .no_proxy()
+        .http1_lenient(true)
+        .build()
+        .unwrap();
+    let res = lenient.get(&url).send().await.unwrap();
+    assert_eq!(res.headers()["x-folded"], "a  b");
+    assert_eq!(res.headers()["x-spaced"], "1");
+    assert_eq!(res.text().await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn headers_ordered() {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nX-Trace: 1\r\n\
+                  set-cookie: b=2\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            );
+        }
+    });
+    let url = format!("http://{}/", addr);
+
+    let client = Client::builder()
+        .no_proxy()
+        .http1_preserve_response_headers(true)
+        .build()
+        .unwrap();
+    let res = client.get(&url).send().await.unwrap();
+    let headers = res.headers_ordered();
+    let names = headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value.to_str().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "Set-Cookie: a=1",
+            "X-Trace: 1",
+            "set-cookie: b=2",
+            "Content-Length: 2",
+            "Connection: close",
+        ]
+    );
+
+    // Without it, repeated headers are still all kept.
+    let res = rquest::Client::builder()
+        .no_proxy()
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap();
+    let cookies = res
+        .headers_ordered()
+        .into_iter()
+        .filter(|(name, _)| name == "set-cookie")
+        .count();
+    assert_eq!(cookies, 2);
+}
+
+#[test]
+fn client_across_runtimes() {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+        }
+    });
+    let url = format!("http://{}/", addr);
+
+    let client = Client::builder().no_proxy();
+
+    // The TLS session cached on one runtime is resumed on the next.
+    #[cfg(feature = "test-server")]
+    let server = rquest::test_server::TestServer::start();
+    #[cfg(feature = "test-server")]
+    let client = client.danger_accept_invalid_certs(true);
+
+    let client = client.build().unwrap();
+    for _ in 0..2 {
+        // Each runtime is shut down before the next one, taking the pooled
+        // connection with it.
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let text = rt.block_on(async { client.get(&url).send().await?.text().await });
+        assert_eq!(text.unwrap(), "ok");
+
+        #[cfg(feature = "test-server")]
+        {
+            let res = rt.block_on(client.get(server.tls_url("/headers")).send());
+            assert_eq!(res.unwrap().status(), rquest::StatusCode::OK);
+        }
+    }
+}
+
+#[tokio::test]
+async fn homograph_hosts_rejected() {
+    let client = Client::builder()
+        .homograph_policy(rquest::HomographPolicy::Reject)
+        .build()
+        .expect("client builder");
+
+    // аррӏе.com, written in Cyrillic
+    let err = client.get("https://аррӏе.com/").send().await.unwrap_err();
+    assert!(err.is_request());
+    assert_eq!(
+        err.url().map(|u| u.as_str()),
+        Some("https://xn--80ak6aa92e.com/")
+    );
+}
+
+#[tokio::test]
+async fn deny_private_ips() {
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = Client::builder()
+        .deny_private_ips(true)
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    let err = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request());
+
+    let err = client
+        .get(format!("http://localhost:{}/", server.addr().port()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_connect());
+}
+
+#[tokio::test]
+async fn egress_policy() {
+    use rquest::egress::{Policy, Rule};
+
+    let server = server::http(move |_req| async { http::Response::default() });
+
+    let client = Client::builder()
+        .egress_policy(Policy::deny([Rule::new().ip_range([127, 0, 0, 0], 8)]))
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    let err = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request());
+
+    let err = client
+        .get(format!("http://localhost:{}/", server.addr().port()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_connect());
+
+    let client = Client::builder()
+        .egress_policy(Policy::allow([Rule::new().host("localhost")]))
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    let res = client
+        .get(format!("http://localhost:{}/", server.addr().port()))
+        .send()
+        .await
+        .expect("allowed");
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let err = client
+        .get(format!("http://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_request());
+}
+
+#[tokio::test]
+async fn base_url() {
+    let server = server::http(move |req| async move {
+        assert_eq!(req.uri(), "/api/v1/items?page=2");
+        http::Response::default()
+    });
+
+    let client = Client::builder()
+        .base_url(format!("http://{}/api", server.addr()))
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    let res = client
+        .get("/v1/items?page=2")
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(
+        res.url().as_str(),
+        format!("http://{}/api/v1/items?page=2", server.addr())
+    );
+
+    let err = Client::new().get("/v1/items").send().await.unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn default_query() {
+    let server = server::http(move |req| async move {
+        let query = match req.uri().path() {
+            "/default" => Some("page=2&api_key=secret&locale=en"),
+            "/override" => Some("locale=fr&api_key=secret"),
+            "/other" => None,
+            _ => Some("locale=en"),
+        };
+        assert_eq!(req.uri().query(), query);
+        http::Response::default()
+    });
+
+    let client = Client::builder()
+        .base_url(format!("http://{}", server.addr()))
+        .default_query(&[("api_key", "secret"), ("locale", "en")])
+        .no_proxy()
+        .build()
+        .expect("client builder");
+
+    client.get("/default?page=2").send().await.expect("default");
+    client
+        .get("/override")
+        .query(&[("locale", "fr")])
+        .send()
+        .await
+        .expect("override");
+    client
+        .get("/removed")
+        .without_default_query("api_key")
+        .send()
+        .await
+        .expect("removed");
+
+    // Another origin, though the same server, gets none of the pairs.
+    client
+        .get(format!("http://localhost:{}/other", server.addr().port()))
+        .send()
+        .await
+        .expect("other origin");
+
+    let err = Client::builder()
+        .default_query(&[("api_key", "secret")])
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn send_head_first() {
+    let server = server::http(move |req| async move {
+        let body = match req.uri().path() {
+            "/small" => vec![b'a'; 16],
+            _ => vec![b'a'; 64 * 1024],
+        };
+        http::Response::builder()
+            .header("content-type", "application/octet-stream")
+            .body(body.into())
+            .unwrap()
+    });
+
+    let client = Client::new();
+    let url = |path| format!("http://{}{}", server.addr(), path);
+
+    let head = client.get(url("/small")).send_head_first().await.unwrap();
+    assert_eq!(head.headers()["content-type"], "application/octet-stream");
+    assert!(head.discard(1024).await.unwrap());
+
+    let head = client.get(url("/large")).send_head_first().await.unwrap();
+    assert_eq!(head.content_length(), Some(64 * 1024));
+    assert!(!head.discard(1024).await.unwrap());
+
+    let head = client.get(url("/large")).send_head_first().await.unwrap();
+    let body = head.into_response().bytes().await.unwrap();
+    assert_eq!(body.len(), 64 * 1024);
+}
+
+#[tokio::test]
+async fn connection_modes() {
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(
+        hyper::service::make_service_fn(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                Ok::<_, Infallible>(hyper::service::service_fn(|req| async move {
+                    let close = req.headers().get("connection").cloned();
+                    let mut res = http::Response::new(hyper::Body::empty());
+                    if let Some(close) = close {
+                        res.headers_mut().insert("x-connection", close);
+                    }
+                    Ok::<_, Infallible>(res)
+                }))
+            }
+        }),
+    );
+    let url = format!("http://{}/", server.local_addr());
+    tokio::spawn(server);
+
+    let client = rquest::Client::builder().no_proxy().build().unwrap();
+    let send = |mode| {
+        let req = client.get(&url).connection(mode);
+        async move {
+            let res = req.send().await.unwrap();
+            let header = res.headers().get("x-connection").cloned();
+            res.bytes().await.unwrap();
+            header
+        }
+    };
+
+    send(rquest::ConnectionMode::Reuse).await;
+    send(rquest::ConnectionMode::Reuse).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    send(rquest::ConnectionMode::Fresh).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    // The pooled connection is still there.
+    send(rquest::ConnectionMode::Reuse).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+    let header = send(rquest::ConnectionMode::Close).await;
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+    assert_eq!(header.unwrap(), "close");
+}
+
+#[tokio::test]
+async fn http1_max_buf_size() {
+    assert!(rquest::Client::builder()
+        .http1_max_buf_size(1024)
+        .build()
+        .is_err());
+
+    let server = server::http(move |req| async move {
+        let mut res = http::Response::new("ok".into());
+        if req.uri().path() == "/large" {
+            let large = "a".repeat(16 * 1024);
+            res.headers_mut().insert("x-large", large.parse().unwrap());
+        }
+        res
+    });
+    let client = rquest::Client::builder()
+        .http1_max_buf_size(8192)
+        .build()
+        .expect("client builder");
+    let res = client
+        .get(&format!("http://{}/buf", server.addr()))
+        .send()
+        .await
+        .expect("request");
+    assert_eq!(res.text().await.unwrap(), "ok");
+
+    // A head larger than the buffer doesn't fit.
+    let res = client
+        .get(&format!("http://{}/large", server.addr()))
+        .send()
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn buffer_memory_limit() {
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    assert!(rquest::Client::builder()
+        .http1_max_buf_size(16 * 1024)
+        .buffer_memory_limit(8 * 1024)
+        .build()
+        .is_err());
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    let server = hyper::Server::bind(&([127, 0, 0, 1], 0).into()).serve(
+        hyper::service::make_service_fn(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                Ok::<_, Infallible>(hyper::service::service_fn(|_req| async {
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    Ok::<_, Infallible>(http::Response::new(hyper::Body::from("ok")))
+                }))
+            }
+        }),
+    );
+    let url = format!("http://{}/", server.local_addr());
+    tokio::spawn(server);
+
+    // The budget fits the buffer of one connection, so the second request
+    // waits for the first one's connection instead of opening another.
+    let client = rquest::Client::builder()
+        .no_proxy()
+        .http1_max_buf_size(8 * 1024)
+        .buffer_memory_limit(12 * 1024)
+        .build()
+        .unwrap();
+    let send = || async { client.get(&url).send().await?.text().await };
+    let (a, b) = tokio::join!(send(), send());
+    assert_eq!(a.unwrap(), "ok");
+    assert_eq!(b.unwrap(), "ok");
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    // Without a budget, both requests open a connection.
+    let client = rquest::Client::builder()
+        .no_proxy()
+        .http1_max_buf_size(8 * 1024)
+        .build()
+        .unwrap();
+    let send = || async { client.get(&url).send().await?.text().await };
+    let (a, b) = tokio::join!(send(), send());
+    assert_eq!(a.unwrap(), "ok");
+    assert_eq!(b.unwrap(), "ok");
+    assert_eq!(connections.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn buffer_memory_limit_shares_idle_connections() {
+    let slow = server::http(move |_req| async move {
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        http::Response::new("slow".into())
+    });
+    let other = server::http(move |_req| async move { http::Response::new("other".into()) });
+
+    // The budget fits the buffer of one connection.
+    let client = rquest::Client::builder()
+        .no_proxy()
+        .http1_max_buf_size(8 * 1024)
+        .buffer_memory_limit(12 * 1024)
+        .connect_timeout(std::time::Duration::from_millis(300))
+        .build()
+        .unwrap();
+    let slow_url = format!("http://{}/", slow.addr());
+    let other_url = format!("http://{}/", other.addr());
+
+    // While the first connection is busy, waiting for a share times out
+    // like connecting does.
+    let (slow_res, other_res) = tokio::join!(
+        async { client.get(&slow_url).send().await?.text().await },
+        client.get(&other_url).send()
+    );
+    assert_eq!(slow_res.unwrap(), "slow");
+    let err = other_res.unwrap_err();
+    assert!(err.is_connect() || err.is_timeout(), "{:?}", err);
+
+    // Once idle in the pool, the first connection gives its share back.
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let res = client.get(&other_url).send().await.unwrap();
+    assert_eq!(res.text().await.unwrap(), "other");
+}
+
+#[tokio::test]
+async fn custom_content_decoder() {
+    let server = server::http(move |req| async move {
+        let accept_encoding = req.headers()["accept-encoding"].to_str().unwrap();
+        assert!(accept_encoding.ends_with("x-rot13"), "{}", accept_encoding);
+
+        http::Response::builder()
+            .header("content-encoding", "x-rot13")
+            .header("content-length", "5")
+            .body("uryyb".into())
+            .unwrap()
+    });
+
+    fn rot13(encoded: rquest::DecoderStream) -> rquest::DecoderStream {
+        Box::pin(encoded.map(|chunk| {
+            chunk.map(|bytes| {
+                bytes
+                    .iter()
+                    .map(|&b| match b {
+                        b'a'..=b'z' => (b - b'a' + 13) % 26 + b'a',
+                        _ => b,
+                    })
+                    .collect()
+            })
+        }))
+    }
+
+    let client = Client::builder()
+        .content_decoder("X-Rot13", rot13)
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(format!("http://{}/rot13", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers().get("content-encoding"), None);
+    assert_eq!(res.text().await.unwrap(), "hello");
+
+    let err = Client::builder()
+        .content_decoder("x rot13", rot13)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
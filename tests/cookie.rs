@@ -202,3 +202,300 @@ async fn cookie_store_path() {
     let url = format!("http://{}/subpath", server.addr());
     client.get(&url).send().await.unwrap();
 }
+
+#[tokio::test]
+async fn cookie_store_persist() {
+    use std::sync::{Arc, Mutex};
+
+    use rquest::cookie::{CookieStore, Jar, Persisting};
+    use rquest::header::HeaderValue;
+
+    #[derive(Default)]
+    struct Shared {
+        jar: Jar,
+        persisted: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl CookieStore for Shared {
+        fn set_cookies(&self, headers: &mut dyn Iterator<Item = &HeaderValue>, url: &rquest::Url) {
+            self.jar.set_cookies(headers, url)
+        }
+
+        fn cookies(&self, url: &rquest::Url) -> Option<HeaderValue> {
+            self.jar.cookies(url)
+        }
+
+        fn persist(&self, headers: &[HeaderValue], _url: &rquest::Url) -> Option<Persisting> {
+            let persisted = self.persisted.clone();
+            let headers = headers
+                .iter()
+                .map(|value| value.to_str().unwrap().to_owned())
+                .collect::<Vec<_>>();
+            Some(Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                persisted.lock().unwrap().extend(headers);
+                Ok(())
+            }))
+        }
+    }
+
+    let server = server::http(move |req| async move {
+        if req.uri() == "/redirect" {
+            http::Response::builder()
+                .status(302)
+                .header("Location", "/")
+                .header("Set-Cookie", "first=1")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            assert_eq!(req.headers()["cookie"], "first=1");
+            http::Response::builder()
+                .header("Set-Cookie", "second=2")
+                .body(Default::default())
+                .unwrap()
+        }
+    });
+
+    let store = Arc::new(Shared::default());
+    let client = rquest::Client::builder()
+        .cookie_provider(store.clone())
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/redirect", server.addr());
+    client.get(&url).send().await.unwrap();
+
+    assert_eq!(*store.persisted.lock().unwrap(), ["first=1", "second=2"]);
+}
+
+#[tokio::test]
+async fn cookie_jar_saved_and_loaded() {
+    let server = server::http(move |req| async move {
+        if req.uri() == "/set" {
+            http::Response::builder()
+                .header("Set-Cookie", "key=val; Max-Age=100")
+                .header("Set-Cookie", "session=1; HttpOnly")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            let mut cookies = req.headers()["cookie"]
+                .to_str()
+                .unwrap()
+                .split("; ")
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            cookies.sort();
+            assert_eq!(cookies, ["key=val", "session=1"]);
+            http::Response::default()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/set", server.addr());
+    client.get(&url).send().await.unwrap();
+
+    let mut saved = Vec::new();
+    client
+        .cookie_jar()
+        .expect("cookie_store(true) uses a Jar")
+        .save_netscape(&mut saved)
+        .unwrap();
+    let saved = String::from_utf8(saved).unwrap();
+    assert!(saved.contains("\tkey\tval\n"), "{}", saved);
+    assert!(saved.contains("#HttpOnly_127.0.0.1\tFALSE\t/\tFALSE\t0\tsession\t1\n"));
+
+    let jar = std::sync::Arc::new(rquest::cookie::Jar::default());
+    jar.load_netscape(saved.as_bytes()).unwrap();
+    let client = rquest::Client::builder()
+        .cookie_provider(jar)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/get", server.addr());
+    client.get(&url).send().await.unwrap();
+}
+
+#[tokio::test]
+async fn request_without_cookie_store() {
+    let server = server::http(move |req| async move {
+        let cookie = req
+            .headers()
+            .get("cookie")
+            .map(|value| value.to_str().unwrap().to_owned());
+        match req.uri().path() {
+            "/set" => http::Response::builder()
+                .header("Set-Cookie", "key=val")
+                .body(Default::default())
+                .unwrap(),
+            "/probe" => {
+                assert_eq!(cookie, None);
+                http::Response::builder()
+                    .header("Set-Cookie", "key=probe")
+                    .body(Default::default())
+                    .unwrap()
+            }
+            "/own" => {
+                assert_eq!(cookie, None);
+                http::Response::builder()
+                    .header("Set-Cookie", "own=1")
+                    .body(Default::default())
+                    .unwrap()
+            }
+            _ => {
+                assert_eq!(cookie.as_deref(), Some("key=val"));
+                http::Response::default()
+            }
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let url = |path: &str| format!("http://{}{}", server.addr(), path);
+    client.get(url("/set")).send().await.unwrap();
+
+    client.get(url("/probe")).no_cookies().send().await.unwrap();
+
+    let jar = std::sync::Arc::new(rquest::cookie::Jar::default());
+    client
+        .get(url("/own"))
+        .cookies(jar.clone())
+        .send()
+        .await
+        .unwrap();
+    let own = url("/").parse().unwrap();
+    assert_eq!(
+        rquest::cookie::CookieStore::cookies(&*jar, &own).unwrap(),
+        "own=1"
+    );
+
+    client.get(url("/check")).send().await.unwrap();
+}
+
+#[tokio::test]
+async fn cookie_jar_events() {
+    use rquest::cookie::CookieEvent;
+    use std::sync::{Arc, Mutex};
+
+    let server = server::http(move |req| async move {
+        let set_cookie = match req.uri().path() {
+            "/login" => "session=1; Path=/",
+            "/refresh" => "session=2; Path=/",
+            _ => "session=; Path=/; Max-Age=0",
+        };
+        http::Response::builder()
+            .header("Set-Cookie", set_cookie)
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let jar = Arc::new(rquest::cookie::Jar::default());
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    jar.subscribe(move |event| seen.lock().unwrap().push(event.clone()));
+
+    let client = rquest::Client::builder()
+        .cookie_provider(jar.clone())
+        .build()
+        .unwrap();
+    for path in ["/login", "/refresh", "/logout"] {
+        let url = format!("http://{}{}", server.addr(), path);
+        client.get(&url).send().await.unwrap();
+    }
+    jar.clear_host("127.0.0.1");
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            CookieEvent::Added {
+                name: "session".to_owned(),
+                value: "1".to_owned(),
+                domain: "127.0.0.1".to_owned(),
+                path: "/".to_owned(),
+            },
+            CookieEvent::Updated {
+                name: "session".to_owned(),
+                value: "2".to_owned(),
+                domain: "127.0.0.1".to_owned(),
+                path: "/".to_owned(),
+            },
+            CookieEvent::Expired {
+                name: "session".to_owned(),
+                domain: "127.0.0.1".to_owned(),
+                path: "/".to_owned(),
+            },
+            CookieEvent::HostCleared {
+                host: "127.0.0.1".to_owned(),
+            },
+        ]
+    );
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn cookie_jar_saved_and_loaded_as_json() {
+    let server = server::http(move |req| async move {
+        if req.uri() == "/set" {
+            http::Response::builder()
+                .header("Set-Cookie", "key=val; Max-Age=100; SameSite=Lax")
+                .header("Set-Cookie", "session=1; HttpOnly")
+                .header("Set-Cookie", "gone=1; Max-Age=0")
+                .body(Default::default())
+                .unwrap()
+        } else {
+            let mut cookies = req.headers()["cookie"]
+                .to_str()
+                .unwrap()
+                .split("; ")
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            cookies.sort();
+            assert_eq!(cookies, ["key=val", "session=1"]);
+            http::Response::default()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/set", server.addr());
+    client.get(&url).send().await.unwrap();
+
+    let mut saved = Vec::new();
+    client
+        .cookie_jar()
+        .expect("cookie_store(true) uses a Jar")
+        .save_json(&mut saved)
+        .unwrap();
+    let mut cookies: Vec<serde_json::Value> = serde_json::from_slice(&saved).unwrap();
+    cookies.sort_by_key(|cookie| cookie["name"].as_str().unwrap().to_owned());
+    assert_eq!(cookies.len(), 2);
+    assert_eq!(cookies[0]["name"], "key");
+    assert_eq!(cookies[0]["same_site"], "Lax");
+    assert!(cookies[0]["expires"].is_i64());
+    assert_eq!(cookies[1]["name"], "session");
+    assert_eq!(cookies[1]["domain"], "127.0.0.1");
+    assert_eq!(cookies[1]["host_only"], true);
+    assert_eq!(cookies[1]["http_only"], true);
+    assert!(cookies[1]["expires"].is_null());
+
+    let jar = std::sync::Arc::new(rquest::cookie::Jar::default());
+    jar.load_json(&saved[..]).unwrap();
+    // Incomplete and expired cookies are skipped.
+    jar.load_json(&br#"[{"name": "no-domain", "value": "1"}]"#[..])
+        .unwrap();
+    jar.load_json(&br#"[{"name": "old", "value": "1", "domain": "127.0.0.1", "expires": 1}]"#[..])
+        .unwrap();
+    assert!(jar.load_json(&b"{}"[..]).is_err());
+
+    let client = rquest::Client::builder()
+        .cookie_provider(jar)
+        .build()
+        .unwrap();
+    let url = format!("http://{}/get", server.addr());
+    client.get(&url).send().await.unwrap();
+}
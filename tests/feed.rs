@@ -0,0 +1,87 @@
+mod support;
+use support::server;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rquest::feed::FeedWatcher;
+
+fn rss(items: &[&str]) -> String {
+    let items: String = items
+        .iter()
+        .map(|id| format!("<item><guid>{}</guid><title>Post {}</title></item>", id, id))
+        .collect();
+    format!("<rss><channel><title>Blog</title>{}</channel></rss>", items)
+}
+
+#[tokio::test]
+async fn feed_watcher_yields_new_entries() {
+    let polls = Arc::new(AtomicUsize::new(0));
+    let server = {
+        let polls = polls.clone();
+        server::http(move |req| {
+            let poll = polls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let etag = format!("\"v{}\"", poll / 2);
+                if req
+                    .headers()
+                    .get("if-none-match")
+                    .map_or(false, |v| v == &etag[..])
+                {
+                    return http::Response::builder()
+                        .status(304)
+                        .body(Default::default())
+                        .unwrap();
+                }
+                let body = match poll {
+                    0 => rss(&["2", "1"]),
+                    _ => rss(&["4", "3", "2"]),
+                };
+                http::Response::builder()
+                    .header("etag", etag)
+                    .body(body.into())
+                    .unwrap()
+            }
+        })
+    };
+
+    let url = format!("http://{}/feed.xml", server.addr());
+    let mut watcher = FeedWatcher::new(rquest::Client::new(), &url)
+        .unwrap()
+        .skip_existing(true);
+
+    assert!(watcher.poll().await.unwrap().is_empty());
+    // Unchanged, so the server answers 304.
+    assert!(watcher.poll().await.unwrap().is_empty());
+
+    let ids = watcher
+        .poll()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["3", "4"]);
+    assert!(watcher.poll().await.unwrap().is_empty());
+    assert_eq!(polls.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn response_feed() {
+    let server = server::http(move |_req| async {
+        let body = b"<feed><entry><id>1</id><title>Caf\xe9</title><link href=\"/posts/1\"/></entry></feed>";
+        http::Response::builder()
+            .header("content-type", "application/atom+xml; charset=iso-8859-1")
+            .body(body[..].into())
+            .unwrap()
+    });
+
+    let url = format!("http://{}/blog/atom.xml", server.addr());
+    let entries = rquest::get(&url).await.unwrap().feed().await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title.as_deref(), Some("Café"));
+    assert_eq!(
+        entries[0].link.as_deref(),
+        Some(&*format!("http://{}/posts/1", server.addr()))
+    );
+}
@@ -86,6 +86,216 @@ async fn test_accept_encoding_header_is_not_changed_if_set() {
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
 
+#[tokio::test]
+async fn test_request_accept_encoding() {
+    let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(b"hello").unwrap();
+    let gzipped = encoder.finish().into_result().unwrap();
+
+    let server = server::http(move |req| {
+        let gzipped = gzipped.clone();
+        async move {
+            let accept = req.headers()["accept-encoding"]
+                .to_str()
+                .unwrap()
+                .to_owned();
+            let expected = match req.uri().path() {
+                "/gzip" => "gzip",
+                _ => "identity",
+            };
+            assert_eq!(accept, expected);
+            http::Response::builder()
+                .header("content-encoding", "gzip")
+                .body(gzipped.into())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::new();
+
+    let res = client
+        .get(&format!("http://{}/gzip", server.addr()))
+        .accept_encoding([rquest::ContentEncoding::Gzip])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "hello");
+
+    let res = client
+        .get(&format!("http://{}/identity", server.addr()))
+        .accept_encoding([])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    assert_ne!(&res.bytes().await.unwrap()[..], b"hello");
+}
+
+#[tokio::test]
+async fn test_stacked_encodings() {
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().into_result().unwrap()
+    }
+
+    let server = server::http(move |req| async move {
+        let (encoding, layers) = match req.uri().path() {
+            "/twice" => ("gzip, gzip", 2),
+            "/transfer" => ("gzip", 1),
+            _ => ("gzip, gzip, gzip, gzip", 4),
+        };
+        let body = (0..layers).fold(b"stacked".to_vec(), |body, _| gzip(&body));
+        let mut res = http::Response::builder();
+        if req.uri().path() == "/transfer" {
+            res = res
+                .header("content-encoding", "gzip")
+                .header("transfer-encoding", "gzip, chunked");
+        } else {
+            res = res.header("content-encoding", encoding);
+        }
+        let body = match req.uri().path() {
+            "/transfer" => gzip(&body),
+            _ => body,
+        };
+        res.body(body.into()).unwrap()
+    });
+
+    let client = rquest::Client::new();
+
+    for path in ["twice", "transfer"] {
+        let res = client
+            .get(&format!("http://{}/{}", server.addr(), path))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.headers().get("content-encoding").is_none());
+        assert_eq!(res.text().await.unwrap(), "stacked");
+    }
+
+    let res = client
+        .get(&format!("http://{}/deep", server.addr()))
+        .send()
+        .await
+        .unwrap();
+    let err = res.bytes().await.unwrap_err();
+    assert!(err.is_decode());
+}
+
+#[cfg(feature = "libdeflate")]
+#[tokio::test]
+async fn libdeflate_backend() {
+    let content = "test ".repeat(100_000);
+    let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(content.as_bytes()).unwrap();
+    let gzipped = encoder.finish().into_result().unwrap();
+
+    let server = server::http(move |_req| {
+        let gzipped = gzipped.clone();
+        async move {
+            http::Response::builder()
+                .header("content-encoding", "gzip")
+                .body(gzipped.into())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .decompression_backend(rquest::DecompressionBackend::Libdeflate)
+        .build()
+        .unwrap();
+    let res = client
+        .get(&format!("http://{}/gzip", server.addr()))
+        .send()
+        .await
+        .expect("response");
+
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.text().await.expect("text"), content);
+}
+
+#[cfg(feature = "libdeflate")]
+#[tokio::test]
+async fn libdeflate_backend_distrusts_gzip_size() {
+    let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(b"test").unwrap();
+    let mut gzipped = encoder.finish().into_result().unwrap();
+    // The trailer claims a 4 GiB body, which is neither allocated nor
+    // trusted.
+    let len = gzipped.len();
+    gzipped[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let server = server::http(move |_req| {
+        let gzipped = gzipped.clone();
+        async move {
+            http::Response::builder()
+                .header("content-encoding", "gzip")
+                .body(gzipped.into())
+                .unwrap()
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .decompression_backend(rquest::DecompressionBackend::Libdeflate)
+        .build()
+        .unwrap();
+    let res = client
+        .get(&format!("http://{}/gzip", server.addr()))
+        .send()
+        .await
+        .expect("response");
+
+    let err = res.bytes().await.unwrap_err();
+    assert!(err.is_decode());
+}
+
+#[cfg(not(feature = "libdeflate"))]
+#[test]
+fn libdeflate_backend_requires_feature() {
+    let err = rquest::Client::builder()
+        .decompression_backend(rquest::DecompressionBackend::Libdeflate)
+        .build()
+        .unwrap_err();
+    assert!(err.is_builder());
+}
+
+#[tokio::test]
+async fn gzip_length_hint_and_progress() {
+    let content = "test ".repeat(1000);
+    let mut encoder = libflate::gzip::Encoder::new(Vec::new()).unwrap();
+    encoder.write_all(content.as_bytes()).unwrap();
+    let gzipped = encoder.finish().into_result().unwrap();
+    let gzipped_len = gzipped.len() as u64;
+
+    let server = server::http(move |_req| {
+        let gzipped = gzipped.clone();
+        async move {
+            http::Response::builder()
+                .header("content-encoding", "gzip")
+                .header("content-length", gzipped.len())
+                .body(gzipped.into())
+                .unwrap()
+        }
+    });
+
+    let res = rquest::Client::new()
+        .get(&format!("http://{}/gzip", server.addr()))
+        .send()
+        .await
+        .expect("response");
+
+    // The length of the encoded body is known, not the decoded one.
+    let hint = res.content_length_hint();
+    assert_eq!(hint.transfer(), Some(gzipped_len));
+    assert_eq!(hint.decoded(), None);
+    let progress = res.progress();
+
+    let body = res.text().await.expect("text");
+    assert_eq!(body, content);
+    assert_eq!(progress.received(), gzipped_len);
+    assert_eq!(progress.fraction(), Some(1.0));
+}
+
 async fn gzip_case(response_size: usize, chunk_size: usize) {
     use futures_util::stream::StreamExt;
 
@@ -0,0 +1,169 @@
+mod support;
+use support::server;
+
+use rquest::jsonrpc::{ErrorObject, JsonRpcClient, RpcError};
+use serde_json::{json, Value};
+
+/// Answer a call of `add` with the sum of its parameters, and any other
+/// call with a "method not found" error.
+fn answer(call: &Value) -> Option<Value> {
+    let id = call.get("id")?.clone();
+    Some(match call["method"].as_str() {
+        Some("add") => {
+            let sum: i64 = call["params"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|n| n.as_i64().unwrap())
+                .sum();
+            json!({ "jsonrpc": "2.0", "result": sum, "id": id })
+        }
+        _ => json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32601, "message": "Method not found" },
+            "id": id,
+        }),
+    })
+}
+
+fn rpc_server() -> server::Server {
+    server::http(move |req| async move {
+        assert_eq!(req.method(), "POST");
+        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        let response = match body {
+            // Answer in reverse order, as ids are what match responses.
+            Value::Array(calls) => {
+                Value::Array(calls.iter().rev().filter_map(answer).collect()).to_string()
+            }
+            call => answer(&call).map(|r| r.to_string()).unwrap_or_default(),
+        };
+        http::Response::builder()
+            .header("content-type", "application/json")
+            .body(response.into())
+            .unwrap()
+    })
+}
+
+#[tokio::test]
+async fn call_and_error_object() {
+    let server = rpc_server();
+    let rpc =
+        JsonRpcClient::new(rquest::Client::new(), format!("http://{}/", server.addr())).unwrap();
+
+    let sum: i64 = rpc.call("add", [1, 2, 3]).await.unwrap();
+    assert_eq!(sum, 6);
+
+    match rpc.call::<_, i64>("sub", [1, 2]).await {
+        Err(RpcError::Rpc(error)) => {
+            assert_eq!(error.code, ErrorObject::METHOD_NOT_FOUND);
+            assert!(error.is_method_not_found());
+            assert_eq!(error.message, "Method not found");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    rpc.notify("add", [1]).await.unwrap();
+
+    let err = rpc.call::<_, i64>("add", 1).await.unwrap_err();
+    assert!(err.error_object().is_none());
+}
+
+#[tokio::test]
+async fn batch_matches_responses_by_id() {
+    let server = rpc_server();
+    let rpc =
+        JsonRpcClient::new(rquest::Client::new(), format!("http://{}/", server.addr())).unwrap();
+
+    let results = rpc
+        .batch()
+        .call("add", [1, 1])
+        .notify("add", [5])
+        .call("sub", [2, 1])
+        .call("add", [2, 3])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0], Ok(json!(2)));
+    assert!(results[1].as_ref().unwrap_err().is_method_not_found());
+    assert_eq!(results[2], Ok(json!(5)));
+}
+
+/// A websocket server answering `add`, failing `garbled` with an error
+/// without id, as a parse error is, and never answering `hang`.
+#[cfg(feature = "websocket")]
+fn ws_rpc_server() -> server::Server {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+    use tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
+
+    server::http(move |req| {
+        let accept = derive_accept_key(req.headers()["sec-websocket-key"].as_bytes());
+        tokio::spawn(async move {
+            let upgraded = hyper::upgrade::on(req).await.unwrap();
+            let mut ws = async_tungstenite::WebSocketStream::from_raw_socket(
+                upgraded.compat(),
+                Role::Server,
+                None,
+            )
+            .await;
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let call: Value = serde_json::from_str(&text).unwrap();
+                let response = match call["method"].as_str() {
+                    Some("hang") => continue,
+                    Some("garbled") => json!({
+                        "jsonrpc": "2.0",
+                        "error": { "code": -32700, "message": "Parse error" },
+                        "id": null,
+                    }),
+                    _ => answer(&call).unwrap(),
+                };
+                ws.send(Message::Text(response.to_string())).await.unwrap();
+            }
+        });
+
+        async move {
+            http::Response::builder()
+                .status(http::StatusCode::SWITCHING_PROTOCOLS)
+                .header(http::header::CONNECTION, "upgrade")
+                .header(http::header::UPGRADE, "websocket")
+                .header(http::header::SEC_WEBSOCKET_ACCEPT, accept)
+                .body(hyper::Body::empty())
+                .unwrap()
+        }
+    })
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn websocket_error_without_id_fails_the_calls() {
+    use std::time::Duration;
+
+    let server = ws_rpc_server();
+    let websocket = rquest::Client::new()
+        .websocket(format!("ws://{}/", server.addr()))
+        .send()
+        .await
+        .unwrap()
+        .into_websocket()
+        .await
+        .unwrap();
+    let rpc = JsonRpcClient::websocket(websocket);
+
+    // A call given up on no longer waits for its response.
+    let hang = rpc.call::<_, i64>("hang", [0]);
+    assert!(tokio::time::timeout(Duration::from_millis(100), hang)
+        .await
+        .is_err());
+
+    match rpc.call::<_, i64>("garbled", [0]).await {
+        Err(RpcError::Rpc(error)) => assert_eq!(error.code, ErrorObject::PARSE_ERROR),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let sum: i64 = rpc.call("add", [1, 2, 3]).await.unwrap();
+    assert_eq!(sum, 6);
+}
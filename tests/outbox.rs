@@ -0,0 +1,142 @@
+mod support;
+use support::server;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use rquest::header::HeaderValue;
+use rquest::outbox::{Outbox, Resolution};
+use rquest::StatusCode;
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rquest-outbox-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn outbox_flushes_in_order() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let server = {
+        let seen = seen.clone();
+        server::http(move |req| {
+            let mut seen = seen.lock().unwrap();
+            seen.push(req.uri().path().to_owned());
+            // The first request finds the server unavailable.
+            let status = if seen.len() == 1 { 503 } else { 200 };
+            async move {
+                http::Response::builder()
+                    .status(status)
+                    .body(Default::default())
+                    .unwrap()
+            }
+        })
+    };
+
+    let client = rquest::Client::new();
+    let dir = dir("order");
+    let outbox = Outbox::new(client.clone(), &dir).unwrap();
+    for path in ["a", "b"] {
+        let req = client
+            .post(format!("http://{}/{}", server.addr(), path))
+            .body(path)
+            .build()
+            .unwrap();
+        outbox.enqueue(req).await.unwrap();
+    }
+
+    // The headers of queued requests may hold credentials.
+    #[cfg(unix)]
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_file() {
+            assert_eq!(
+                entry.metadata().unwrap().permissions().mode() & 0o777,
+                0o600
+            );
+        }
+    }
+
+    let flushed = outbox.flush().await.unwrap();
+    assert_eq!((flushed.sent, flushed.pending), (0, 2));
+
+    let flushed = outbox.flush().await.unwrap();
+    assert_eq!((flushed.sent, flushed.pending), (2, 0));
+    assert_eq!(outbox.pending().await.unwrap(), 0);
+    assert_eq!(*seen.lock().unwrap(), ["/a", "/a", "/b"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn outbox_conflicts_after_restart() {
+    let server = server::http(move |req| async move {
+        let status = match req.uri().path() {
+            "/conflict" => 409,
+            _ => 400,
+        };
+        http::Response::builder()
+            .status(status)
+            .body(Default::default())
+            .unwrap()
+    });
+
+    let client = rquest::Client::new();
+    let dir = dir("conflict");
+    {
+        let outbox = Outbox::new(client.clone(), &dir).unwrap();
+        for path in ["conflict", "rejected"] {
+            let req = client
+                .put(format!("http://{}/{}", server.addr(), path))
+                .header("if-match", "\"v1\"")
+                .body("{}")
+                .build()
+                .unwrap();
+            outbox.enqueue(req).await.unwrap();
+        }
+    }
+
+    let conflicts = Arc::new(Mutex::new(Vec::new()));
+    let outbox = {
+        let conflicts = conflicts.clone();
+        Outbox::new(client, &dir)
+            .unwrap()
+            .on_conflict(move |req, res| {
+                conflicts
+                    .lock()
+                    .unwrap()
+                    .push((req.headers()["if-match"].clone(), res.status()));
+                Resolution::Discard
+            })
+    };
+    assert_eq!(outbox.pending().await.unwrap(), 2);
+
+    let flushed = outbox.flush().await.unwrap();
+    assert_eq!(
+        (flushed.discarded, flushed.failed, flushed.pending),
+        (1, 1, 0)
+    );
+    assert_eq!(
+        *conflicts.lock().unwrap(),
+        [(HeaderValue::from_static("\"v1\""), StatusCode::CONFLICT)]
+    );
+    assert_eq!(std::fs::read_dir(dir.join("failed")).unwrap().count(), 1);
+
+    // Once the queue is empty, a request queued after another restart
+    // doesn't take the name of the failed one.
+    drop(outbox);
+    let outbox = Outbox::new(rquest::Client::new(), &dir).unwrap();
+    let req = rquest::Client::new()
+        .put(format!("http://{}/rejected", server.addr()))
+        .body("{}")
+        .build()
+        .unwrap();
+    outbox.enqueue(req).await.unwrap();
+    let flushed = outbox.flush().await.unwrap();
+    assert_eq!(flushed.failed, 1);
+    assert_eq!(std::fs::read_dir(dir.join("failed")).unwrap().count(), 2);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -221,3 +221,85 @@ async fn http_over_http() {
     assert_eq!(res.url().as_str(), url);
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
+
+#[tokio::test]
+async fn request_proxy_overrides_client_proxy() {
+    let url = "http://hyper.rs/prox";
+    let server = server::http(move |req| {
+        assert_eq!(req.uri(), url);
+        assert_eq!(
+            req.headers()["proxy-authorization"],
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+
+        async { http::Response::default() }
+    });
+
+    let proxy = format!("http://{}", server.addr());
+
+    let client = rquest::Client::builder()
+        .proxy(rquest::Proxy::http("http://127.0.0.1:1").unwrap())
+        .build()
+        .unwrap();
+
+    for _ in 0..2 {
+        let res = client
+            .get(url)
+            .proxy(
+                rquest::Proxy::http(&proxy)
+                    .unwrap()
+                    .basic_auth("Aladdin", "open sesame"),
+            )
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), rquest::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn proxy_pool_authenticates_with_the_proxy_dialed() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let url = "http://hyper.rs/prox";
+    let proxy = |auth: &'static str| {
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        let server = server::http(move |req| {
+            let status = if req.headers()["proxy-authorization"] == auth {
+                counter.fetch_add(1, Ordering::SeqCst);
+                http::StatusCode::OK
+            } else {
+                http::StatusCode::PROXY_AUTHENTICATION_REQUIRED
+            };
+            async move {
+                http::Response::builder()
+                    .status(status)
+                    .body(Default::default())
+                    .unwrap()
+            }
+        });
+        (server, served)
+    };
+    let (alice, alice_served) = proxy("Basic YWxpY2U6YQ==");
+    let (bob, bob_served) = proxy("Basic Ym9iOmI=");
+
+    let pool = rquest::ProxyPool::builder()
+        .proxy(format!("http://alice:a@{}", alice.addr()))
+        .proxy(format!("http://bob:b@{}", bob.addr()))
+        .build()
+        .unwrap();
+    let client = rquest::Client::builder()
+        .proxy(pool.proxy())
+        .build()
+        .unwrap();
+
+    for _ in 0..4 {
+        let res = client.get(url).send().await.unwrap();
+        assert_eq!(res.status(), rquest::StatusCode::OK);
+    }
+    assert_eq!(alice_served.load(Ordering::SeqCst), 2);
+    assert_eq!(bob_served.load(Ordering::SeqCst), 2);
+}
@@ -332,3 +332,81 @@ async fn test_redirect_302_with_set_cookies() {
     assert_eq!(res.url().as_str(), dst);
     assert_eq!(res.status(), rquest::StatusCode::OK);
 }
+
+#[tokio::test]
+async fn test_redirect_history() {
+    let server = server::http(move |req| async move {
+        match req.uri().path() {
+            "/first" => http::Response::builder()
+                .status(302)
+                .header("location", "/second")
+                .body(Body::default())
+                .unwrap(),
+            "/second" => http::Response::builder()
+                .status(301)
+                .header("location", "/dst")
+                .header("server", "test-second")
+                .body(Body::default())
+                .unwrap(),
+            _ => http::Response::default(),
+        }
+    });
+
+    let url = format!("http://{}/first", server.addr());
+    let res = rquest::get(&url).await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let history = res.history();
+    assert_eq!(history.retries(), 0);
+    assert_eq!(history.entries().len(), 2);
+    assert_eq!(history.entries()[0].status(), rquest::StatusCode::FOUND);
+    assert_eq!(history.entries()[0].url().as_str(), url);
+    assert_eq!(
+        history.entries()[1].url().as_str(),
+        format!("http://{}/second", server.addr())
+    );
+    assert_eq!(
+        history.entries()[1]
+            .headers()
+            .get(rquest::header::SERVER)
+            .unwrap(),
+        &"test-second"
+    );
+}
+
+#[tokio::test]
+async fn test_redirect_policy_detects_loops() {
+    let server = server::http(move |req| async move {
+        let next = match req.uri().path() {
+            "/a" => "/b",
+            _ => "/a",
+        };
+        http::Response::builder()
+            .status(302)
+            .header("location", next)
+            .body(Body::default())
+            .unwrap()
+    });
+
+    let url = format!("http://{}/a", server.addr());
+    let err = rquest::Client::builder()
+        .redirect(rquest::redirect::Policy::default().detect_loops(true))
+        .build()
+        .unwrap()
+        .get(&url)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(err.is_redirect());
+    assert_eq!(
+        err.url().map(|url| url.as_str()),
+        Some(format!("http://{}/b", server.addr()).as_str())
+    );
+
+    let rejected = std::error::Error::source(&err)
+        .and_then(|err| err.downcast_ref::<rquest::redirect::Rejected>())
+        .expect("rejected redirect");
+    assert_eq!(rejected.reason(), rquest::redirect::Reason::Loop);
+    assert_eq!(rejected.hop(), 2);
+    assert_eq!(rejected.url().as_str(), url);
+}
@@ -0,0 +1,574 @@
+#![cfg(feature = "test-server")]
+use rquest::test_server::{TestServer, BODY};
+
+#[tokio::test]
+async fn headers_are_echoed_in_order() {
+    let server = TestServer::start();
+    let client = rquest::Client::new();
+
+    let body = client
+        .get(server.url("/headers"))
+        .header("x-first", "1")
+        .header("x-second", "2")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    let first = body.find("x-first: 1").expect("x-first echoed");
+    let second = body.find("x-second: 2").expect("x-second echoed");
+    assert!(first < second);
+}
+
+#[tokio::test]
+async fn redirect_chain() {
+    let server = TestServer::start();
+    let res = rquest::get(server.url("/redirect/3")).await.unwrap();
+
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    assert_eq!(res.url().path(), "/redirect/0");
+    assert_eq!(res.history().entries().len(), 3);
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn gzip_body() {
+    let server = TestServer::start();
+    let body = rquest::get(server.url("/gzip"))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(body, BODY);
+}
+
+#[tokio::test]
+async fn client_hello_over_tls() {
+    let server = TestServer::start();
+    let client = rquest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    let ja3 = client
+        .get(server.tls_url("/ja3"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(ja3.starts_with("771,"), "unexpected JA3: {}", ja3);
+
+    let res = client
+        .get(server.url("/client-hello"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn https_proxy_with_server_name() {
+    let server = TestServer::start();
+    let proxy = format!("https://{}", server.tls_addr());
+    let client = rquest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .proxy(
+            rquest::Proxy::http(&proxy)
+                .unwrap()
+                .tls_server_name("localhost"),
+        )
+        .build()
+        .unwrap();
+
+    // The proxy hop is TLS, so the endpoint sees the ClientHello sent to
+    // the proxy rather than to the target.
+    let hello = client
+        .get("http://proxied.test/client-hello")
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+
+    assert!(hello.windows(9).any(|w| w == b"localhost"));
+    // Only HTTP/1.1 is offered in ALPN.
+    assert!(hello.windows(9).any(|w| w == b"\x08http/1.1"));
+    assert!(!hello.windows(12).any(|w| w == b"\x02h2\x08http/1.1"));
+}
+
+#[tokio::test]
+async fn root_certificate_and_pins() {
+    use rquest::tls::Certificate;
+
+    let server = TestServer::start();
+    let url = server.tls_url("/status/200");
+    let cert = Certificate::from(server.certificate().clone());
+
+    let err = rquest::get(&url).await.unwrap_err();
+    assert!(err.is_connect());
+
+    let trusting = rquest::Client::builder()
+        .add_root_certificate(cert.clone())
+        .build()
+        .unwrap();
+    let res = trusting.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let pinned = rquest::Client::builder()
+        .add_root_certificate(cert.clone())
+        .pin_server_certificate("localhost", [0; 32])
+        .pin_server_certificate("localhost", cert.public_key_sha256().unwrap())
+        .build()
+        .unwrap();
+    let res = pinned.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    let mispinned = rquest::Client::builder()
+        .add_root_certificate(cert)
+        .pin_server_certificate("localhost", [0; 32])
+        .build()
+        .unwrap();
+    let err = mispinned.get(&url).send().await.unwrap_err();
+    assert!(err.is_connect());
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn tls_session_store() {
+    use rquest::impersonate::{Impersonate, MemorySessionStore, TlsSessionKey, TlsSessionStore};
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps the keys of the sessions issued.
+    #[derive(Clone, Default)]
+    struct Recording {
+        sessions: MemorySessionStore,
+        keys: Arc<Mutex<Vec<TlsSessionKey>>>,
+    }
+
+    impl TlsSessionStore for Recording {
+        fn get(&self, key: &TlsSessionKey) -> Option<Vec<u8>> {
+            self.sessions.get(key)
+        }
+
+        fn put(&self, key: &TlsSessionKey, session: Vec<u8>) {
+            self.keys.lock().unwrap().push(key.clone());
+            self.sessions.put(key, session)
+        }
+    }
+
+    let server = TestServer::start();
+    let store = Recording::default();
+    let client = rquest::Client::builder()
+        .impersonate(Impersonate::Chrome131)
+        .danger_accept_invalid_certs(true)
+        .tls_session_store(store.clone())
+        .build()
+        .unwrap();
+
+    let res = client
+        .get(server.tls_url("/status/200"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+    res.bytes().await.unwrap();
+
+    let key = store.keys.lock().unwrap()[0].clone();
+    assert_eq!(key.host(), "localhost");
+    assert_eq!(key.proxy(), None);
+
+    // The server speaks TLS 1.3, whose tickets are handed out once.
+    assert!(store.sessions.get(&key).is_some());
+    assert!(store.sessions.get(&key).is_none());
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn tls_sessions_are_kept_per_trust() {
+    use rquest::impersonate::{Impersonate, MemorySessionStore};
+    use rquest::tls::Certificate;
+
+    let server = TestServer::start();
+    let url = server.tls_url("/status/200");
+    let sessions = MemorySessionStore::new();
+
+    let insecure = rquest::Client::builder()
+        .impersonate(Impersonate::Chrome131)
+        .danger_accept_invalid_certs(true)
+        .tls_session_store(sessions.clone())
+        .build()
+        .unwrap();
+    insecure
+        .get(&url)
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 1);
+
+    // The session issued without verification doesn't let the pins be
+    // skipped by resuming it.
+    let mispinned = rquest::Client::builder()
+        .impersonate(Impersonate::Chrome131)
+        .add_root_certificate(Certificate::from(server.certificate().clone()))
+        .pin_server_certificate("localhost", [0; 32])
+        .tls_session_store(sessions.clone())
+        .build()
+        .unwrap();
+    let err = mispinned.get(&url).send().await.unwrap_err();
+    assert!(err.is_connect());
+    assert_eq!(sessions.len(), 1);
+}
+
+#[tokio::test]
+async fn encrypted_client_hello() {
+    async fn ech(server: &TestServer, list: &[u8]) -> String {
+        rquest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .ech_config_list("localhost", list)
+            .build()
+            .unwrap()
+            .get(server.tls_url("/ech"))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+    }
+
+    let server = TestServer::start();
+    let list = server.ech_config_list();
+    assert_eq!(ech(&server, list).await, "accepted");
+
+    // A configuration the server has no key for is rejected, and the
+    // handshake is made again with the one the server sends back.
+    let mut stale = list.to_vec();
+    // The config_id, after the length of the list, and the version and
+    // length of the config.
+    stale[6] ^= 0xff;
+    assert_eq!(ech(&server, &stale).await, "accepted");
+
+    let res = rquest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .get(server.tls_url("/ech"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "not accepted");
+}
+
+/// The groups offered in `supported_groups` by `client`, without GREASE.
+#[cfg(feature = "impersonate")]
+async fn supported_groups(server: &TestServer, client: rquest::ClientBuilder) -> Vec<u16> {
+    let ja3 = client
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .get(server.tls_url("/ja3"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    ja3.split(',')
+        .nth(3)
+        .unwrap()
+        .split('-')
+        .map(|group| group.parse().unwrap())
+        .collect()
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn post_quantum_key_share() {
+    use rquest::impersonate::{capabilities, Impersonate};
+
+    const X25519_KYBER768: u16 = 0x6399;
+    const X25519: u16 = 29;
+
+    let server = TestServer::start();
+    let groups = |impersonate, post_quantum: Option<bool>| {
+        let mut builder = rquest::Client::builder().impersonate(impersonate);
+        if let Some(post_quantum) = post_quantum {
+            builder = builder.post_quantum_key_share(post_quantum);
+        }
+        supported_groups(&server, builder)
+    };
+
+    let pq = capabilities().post_quantum;
+    assert_eq!(
+        groups(Impersonate::Chrome127, None).await[0],
+        if pq { X25519_KYBER768 } else { X25519 }
+    );
+    let without = groups(Impersonate::Chrome127, Some(false)).await;
+    assert_eq!(without[0], X25519);
+    assert!(!without.contains(&X25519_KYBER768));
+
+    assert!(!groups(Impersonate::Chrome100, None)
+        .await
+        .contains(&X25519_KYBER768));
+    assert_eq!(
+        groups(Impersonate::Chrome100, Some(true)).await[0],
+        if pq { X25519_KYBER768 } else { X25519 }
+    );
+
+    // Profiles of other browsers are left as they are.
+    assert_eq!(
+        groups(Impersonate::Safari16, Some(true)).await,
+        groups(Impersonate::Safari16, None).await
+    );
+}
+
+#[cfg(feature = "impersonate")]
+#[tokio::test]
+async fn ml_kem_key_share() {
+    use rquest::impersonate::{capabilities, Impersonate};
+
+    const X25519_MLKEM768: u16 = 0x11ec;
+    const X25519_KYBER768: u16 = 0x6399;
+    const X25519: u16 = 29;
+    const SECP256R1: u16 = 23;
+    const SECP384R1: u16 = 24;
+
+    let server = TestServer::start();
+    let groups = |impersonate, post_quantum: Option<bool>| {
+        let mut builder = rquest::Client::builder().impersonate(impersonate);
+        if let Some(post_quantum) = post_quantum {
+            builder = builder.post_quantum_key_share(post_quantum);
+        }
+        supported_groups(&server, builder)
+    };
+
+    // Without ML-KEM, the profiles fall back to the draft group of Chrome
+    // 124 to 130 rather than to no post-quantum share at all.
+    let capabilities = capabilities();
+    let post_quantum = if capabilities.ml_kem {
+        Some(X25519_MLKEM768)
+    } else if capabilities.post_quantum {
+        Some(X25519_KYBER768)
+    } else {
+        None
+    };
+    let expected = post_quantum
+        .into_iter()
+        .chain([X25519, SECP256R1, SECP384R1])
+        .collect::<Vec<_>>();
+    for impersonate in [
+        Impersonate::Chrome131,
+        Impersonate::Edge131,
+        Impersonate::AndroidWebView15,
+    ] {
+        assert_eq!(
+            groups(impersonate, None).await,
+            expected,
+            "{:?}",
+            impersonate
+        );
+    }
+
+    let without = groups(Impersonate::Chrome131, Some(false)).await;
+    assert_eq!(without, [X25519, SECP256R1, SECP384R1]);
+}
+
+/// A self-signed client certificate with the common name `name`, and its
+/// key.
+fn client_certificate(
+    name: &str,
+) -> (
+    rquest::boring::x509::X509,
+    rquest::boring::pkey::PKey<rquest::boring::pkey::Private>,
+) {
+    use rquest::boring::asn1::Asn1Time;
+    use rquest::boring::ec::{EcGroup, EcKey};
+    use rquest::boring::hash::MessageDigest;
+    use rquest::boring::nid::Nid;
+    use rquest::boring::pkey::PKey;
+    use rquest::boring::x509::{X509Name, X509};
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+    let mut subject = X509Name::builder().unwrap();
+    subject.append_entry_by_text("CN", name).unwrap();
+    let subject = subject.build();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_subject_name(&subject).unwrap();
+    cert.set_issuer_name(&subject).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+    (cert.build(), key)
+}
+
+#[tokio::test]
+async fn client_certificate_signer() {
+    use rquest::boring::hash::MessageDigest;
+    use rquest::boring::pkey::{PKey, Private};
+    use rquest::boring::sign::Signer;
+    use rquest::boring::ssl::SslSignatureAlgorithm;
+    use rquest::tls::{Identity, PrivateKeySigner};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A key in memory, standing for one on a token.
+    struct Token {
+        key: PKey<Private>,
+        signed: Arc<AtomicUsize>,
+    }
+
+    impl PrivateKeySigner for Token {
+        fn sign(
+            &self,
+            algorithm: SslSignatureAlgorithm,
+            input: &[u8],
+        ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+            if algorithm != SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256 {
+                return Err(format!("unsupported algorithm {:?}", algorithm).into());
+            }
+            // A token takes a while, which the runtime doesn't wait for.
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            self.signed.fetch_add(1, Ordering::SeqCst);
+            let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+            signer.update(input)?;
+            Ok(signer.sign_to_vec()?)
+        }
+    }
+
+    let server = TestServer::start();
+    let (cert, key) = client_certificate("rquest signer");
+    let signed = Arc::new(AtomicUsize::new(0));
+    let token = Token {
+        key,
+        signed: signed.clone(),
+    };
+    let client = rquest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .identity(Identity::from_signer(vec![cert], token).unwrap())
+        .build()
+        .unwrap();
+    let name = client
+        .get(server.mtls_url("/client-cert"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(name, "rquest signer");
+    assert_eq!(signed.load(Ordering::SeqCst), 1);
+
+    // The server requires a certificate.
+    let anonymous = rquest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    assert!(anonymous
+        .get(server.mtls_url("/client-cert"))
+        .send()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn client_certificate_identity() {
+    use rquest::boring::pkcs12::Pkcs12;
+    use rquest::tls::Identity;
+
+    let server = TestServer::start();
+    let (cert, key) = client_certificate("rquest identity");
+
+    let mut pem = cert.to_pem().unwrap();
+    pem.extend(key.private_key_to_pem_pkcs8().unwrap());
+    let der = Pkcs12::builder()
+        .build("secret", "rquest identity", &key, &cert)
+        .unwrap()
+        .to_der()
+        .unwrap();
+
+    for identity in [
+        Identity::from_pem(&pem).unwrap(),
+        Identity::from_pkcs12_der(&der, "secret").unwrap(),
+    ] {
+        let client = rquest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .identity(identity)
+            .build()
+            .unwrap();
+        let name = client
+            .get(server.mtls_url("/client-cert"))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(name, "rquest identity");
+
+        // The profile set on a request handshakes with the identity of the
+        // client as well.
+        #[cfg(feature = "impersonate")]
+        {
+            let name = client
+                .get(server.mtls_url("/client-cert"))
+                .impersonate(rquest::impersonate::Impersonate::Chrome131)
+                .send()
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+            assert_eq!(name, "rquest identity");
+        }
+    }
+}
+
+#[cfg(feature = "native-roots")]
+#[tokio::test]
+async fn native_roots_from_ssl_cert_file() {
+    let server = TestServer::start();
+    let url = server.tls_url("/status/200");
+
+    // The store also holds a certificate BoringSSL can't parse, which is
+    // skipped.
+    let mut pem = server.certificate().to_pem().unwrap();
+    pem.extend_from_slice(b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n");
+    let path = std::env::temp_dir().join(format!("rquest-native-roots-{}.pem", std::process::id()));
+    std::fs::write(&path, pem).unwrap();
+    std::env::set_var("SSL_CERT_FILE", &path);
+
+    let client = rquest::Client::builder()
+        .tls_native_roots()
+        .build()
+        .unwrap();
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), rquest::StatusCode::OK);
+
+    // Without it, the certificate isn't trusted.
+    let err = rquest::get(&url).await.unwrap_err();
+    assert!(err.is_connect());
+
+    // A store that can't be read fails the build.
+    std::fs::remove_file(&path).unwrap();
+    assert!(rquest::Client::builder()
+        .tls_native_roots()
+        .build()
+        .is_err());
+    std::env::remove_var("SSL_CERT_FILE");
+}
@@ -174,6 +174,77 @@ async fn response_timeout() {
     assert!(err.is_timeout());
 }
 
+#[tokio::test]
+async fn request_read_timeout_overrides_client() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            // immediate response, but delayed body
+            let body = hyper::Body::wrap_stream(futures_util::stream::once(async {
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                Ok::<_, std::convert::Infallible>("Hello")
+            }));
+
+            http::Response::new(body)
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .read_timeout(Duration::from_secs(3))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+    let req = client
+        .get(&url)
+        .read_timeout(Duration::from_millis(500))
+        .build()
+        .unwrap();
+
+    let timeouts = req.effective_timeouts();
+    assert_eq!(timeouts.total(), Some(Duration::from_secs(5)));
+    assert_eq!(timeouts.read(), Some(Duration::from_millis(500)));
+
+    let res = client.execute(req).await.expect("Failed to get");
+    let err = res.text().await.unwrap_err();
+
+    assert!(err.is_timeout());
+}
+
+#[tokio::test]
+async fn read_timeout_restarts_for_each_chunk() {
+    let _ = env_logger::try_init();
+
+    let server = server::http(move |_req| {
+        async {
+            // four chunks, each within the read timeout, but not all of them
+            let chunks = futures_util::stream::unfold(0, |n| async move {
+                if n == 4 {
+                    return None;
+                }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Some((Ok::<_, std::convert::Infallible>("chunk"), n + 1))
+            });
+
+            http::Response::new(hyper::Body::wrap_stream(chunks))
+        }
+    });
+
+    let client = rquest::Client::builder()
+        .read_timeout(Duration::from_millis(500))
+        .no_proxy()
+        .build()
+        .unwrap();
+
+    let url = format!("http://{}/slow", server.addr());
+    let body = client.get(&url).send().await.unwrap().text().await.unwrap();
+
+    assert_eq!(body, "chunk".repeat(4));
+}
+
 /// Tests that internal client future cancels when the oneshot channel
 /// is canceled.
 #[cfg(feature = "blocking")]